@@ -0,0 +1,23 @@
+//! Exposes the resolved notify version so recorded results say which release they measured
+use cargo_metadata::MetadataCommand;
+use std::path::Path;
+
+fn main() {
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    let manifest = Path::new(&std::env::var("CARGO_MANIFEST_DIR").unwrap()).join("Cargo.toml");
+    let version = MetadataCommand::new()
+        .manifest_path(manifest)
+        .exec()
+        .ok()
+        .and_then(|metadata| {
+            // The notify this crate links against, not whichever one the graph lists first
+            let resolve = metadata.resolve?;
+            let root = resolve.root?;
+            let node = resolve.nodes.iter().find(|node| node.id == root)?;
+            let dep = node.deps.iter().find(|dep| dep.name == "notify")?;
+            let package = metadata.packages.iter().find(|package| package.id == dep.pkg)?;
+            Some(package.version.to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=NOTIFY_VERSION={}", version);
+}
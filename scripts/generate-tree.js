@@ -3,14 +3,47 @@
 const fs = require('fs');
 const path = require('path');
 
+/**
+ * Stems used instead of f1..f9 with --unusual-names: unicode, emoji, spaces,
+ * a decomposed accent and a trailing dot, each a known source of path mismatches
+ */
+const UNUSUAL_STEMS = [
+  'ünïcödé',
+  '日本語',
+  '🚀 launch',
+  'with spaces',
+  ' leading space',
+  'many..dots',
+  'cafe\u0301',
+  'hash#and&amp',
+  'trailing dot.',
+];
+
+/**
+ * File and directory names for slot i (1-9)
+ * @param {number} i - Slot number
+ * @param {boolean} unusual - Whether to use UNUSUAL_STEMS
+ * @returns {{file: string, dir: string}} Names for the file and its subdirectory
+ */
+function slotNames(i, unusual) {
+  if (!unusual) {
+    return { file: `f${i}.js`, dir: `f${i}` };
+  }
+  const stem = UNUSUAL_STEMS[i - 1];
+  // Keep the trailing dot at the very end of the file name
+  const file = stem.endsWith('.') ? `${stem.slice(0, -1)}.js.` : `${stem}.js`;
+  return { file, dir: stem };
+}
+
 /**
  * Generate JavaScript file tree with the specified depth
  * Creates index.js and f1.js through f9.js at each level
  * Each fN.js imports its corresponding fN/ subdirectory
  * @param {number} maxDepth - Maximum depth of the tree
  * @param {string} rootDir - Root directory to generate the tree in
+ * @param {boolean} unusual - Name files and directories from UNUSUAL_STEMS
  */
-function generateFileTree(maxDepth, rootDir = './generated-tree') {
+function generateFileTree(maxDepth, rootDir = './generated-tree', unusual = false) {
   // Ensure the root directory exists
   if (fs.existsSync(rootDir)) {
     console.log(`Warning: Directory ${rootDir} already exists. Removing it...`);
@@ -20,7 +53,7 @@ function generateFileTree(maxDepth, rootDir = './generated-tree') {
   fs.mkdirSync(rootDir, { recursive: true });
 
   // Start recursive generation
-  generateLevel(rootDir, 1, maxDepth);
+  generateLevel(rootDir, 1, maxDepth, unusual);
 
   console.log(`✅ File tree generated successfully with depth ${maxDepth} in ${rootDir}`);
 
@@ -37,31 +70,32 @@ function generateFileTree(maxDepth, rootDir = './generated-tree') {
  * @param {string} currentPath - Current directory path
  * @param {number} currentDepth - Current depth level
  * @param {number} maxDepth - Maximum depth to generate
+ * @param {boolean} unusual - Name files and directories from UNUSUAL_STEMS
  */
-function generateLevel(currentPath, currentDepth, maxDepth) {
+function generateLevel(currentPath, currentDepth, maxDepth, unusual) {
   // Generate index.js that imports all f1.js through f9.js
   const indexContent = Array.from({ length: 9 }, (_, i) =>
-    `import "./f${i + 1}.js"`
+    `import "./${slotNames(i + 1, unusual).file}"`
   ).join('\n') + '\n';
 
   fs.writeFileSync(path.join(currentPath, 'index.js'), indexContent);
 
   // Generate f1.js through f9.js
   for (let i = 1; i <= 9; i++) {
-    const fileName = `f${i}.js`;
-    const filePath = path.join(currentPath, fileName);
+    const names = slotNames(i, unusual);
+    const filePath = path.join(currentPath, names.file);
 
     // If we haven't reached max depth, import the corresponding subdirectory
     if (currentDepth < maxDepth) {
-      const content = `import "./f${i}/index.js"\n`;
+      const content = `import "./${names.dir}/index.js"\n`;
       fs.writeFileSync(filePath, content);
 
       // Create the subdirectory and recurse
-      const subDirPath = path.join(currentPath, `f${i}`);
+      const subDirPath = path.join(currentPath, names.dir);
       fs.mkdirSync(subDirPath, { recursive: true });
 
       // Recursively generate the next level
-      generateLevel(subDirPath, currentDepth + 1, maxDepth);
+      generateLevel(subDirPath, currentDepth + 1, maxDepth, unusual);
     } else {
       // At max depth, just create empty files or minimal content
       fs.writeFileSync(filePath, `// Leaf file at depth ${currentDepth}\n`);
@@ -118,20 +152,27 @@ function formatBytes(bytes) {
 
 // Parse command line arguments
 function main() {
-  const args = process.argv.slice(2);
+  const argv = process.argv.slice(2);
+  const unusual = argv.includes('--unusual-names');
+  const args = argv.filter((arg) => arg !== '--unusual-names');
 
-  if (args.length === 0 || args.includes('--help') || args.includes('-h')) {
+  if (args.length === 0 || argv.includes('--help') || argv.includes('-h')) {
     console.log(`
-Usage: node generate-tree.js <depth> [output-dir]
+Usage: node generate-tree.js <depth> [output-dir] [--unusual-names]
 
 Arguments:
   depth       - Maximum depth of the tree (required, positive integer)
   output-dir  - Output directory path (optional, default: ./generated-tree)
 
+Options:
+  --unusual-names - Use unicode, emoji, spaces and trailing dots in names instead of f1..f9
+                    (Windows strips the trailing dot)
+
 Examples:
   node generate-tree.js 3
   node generate-tree.js 5 ./my-tree
   node generate-tree.js 2 /tmp/test-tree
+  node generate-tree.js 3 ./odd-tree --unusual-names
 
 Note: Be careful with large depth values as the number of files grows exponentially!
   Depth 1: 10 files (index.js + f1.js through f9.js)
@@ -167,7 +208,7 @@ Note: Be careful with large depth values as the number of files grows exponentia
       rl.close();
       if (answer.toLowerCase() === 'y' || answer.toLowerCase() === 'yes') {
         console.log('\n🚀 Starting generation...\n');
-        generateFileTree(depth, outputDir);
+        generateFileTree(depth, outputDir, unusual);
       } else {
         console.log('❌ Generation cancelled');
         process.exit(0);
@@ -175,7 +216,7 @@ Note: Be careful with large depth values as the number of files grows exponentia
     });
   } else {
     console.log(`🚀 Generating file tree with depth ${depth}...\n`);
-    generateFileTree(depth, outputDir);
+    generateFileTree(depth, outputDir, unusual);
   }
 }
 
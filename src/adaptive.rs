@@ -0,0 +1,243 @@
+use crate::harness::{enumerate_dir, prepare_scratch_dir};
+use crate::options::Options;
+use crate::order::shuffle;
+use crate::recursive_file_watcher::{event_channel, extended_length, filter_key};
+use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Delivered events per second the controller aims to stay under by default
+pub const DEFAULT_TARGET_RATE: f64 = 200.0;
+
+/// Name the adaptive watcher's results are recorded under
+pub const ADAPTIVE_NAME: &str = "Adaptive Filtered";
+
+/// Writes per second the workload makes, spread over every file
+const ADAPTIVE_WRITE_RATE: u64 = 1000;
+
+/// How long the workload runs
+const ADAPTIVE_DURATION: Duration = Duration::from_secs(5);
+
+/// How often the controller looks at the delivered rate
+const CONTROL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Sparsest filter the controller narrows to: one file in this many
+const MAX_RATIO: usize = 1024;
+
+/// Filter ratio for the next interval: narrowed while over `target`, widened once the
+/// rate would stay under it with twice as many files
+pub fn next_ratio(ratio: usize, rate: f64, target: f64) -> usize {
+    if rate > target {
+        (ratio * 2).min(MAX_RATIO)
+    } else if rate < target / 2.0 {
+        (ratio / 2).max(1)
+    } else {
+        ratio
+    }
+}
+
+/// Every file's position in a fixed order; one in `ratio` of them passes
+struct AdaptiveFilter {
+    index: HashMap<PathBuf, usize>,
+    ratio: AtomicUsize,
+}
+
+impl AdaptiveFilter {
+    fn passes(&self, event: &Event) -> bool {
+        let ratio = self.ratio.load(Ordering::Relaxed);
+        event
+            .paths
+            .iter()
+            .any(|path| self.index.get(filter_key(path).as_ref()).is_some_and(|i| i % ratio == 0))
+    }
+}
+
+/// One control interval: the ratio in force and what got through
+#[derive(Debug, Clone, PartialEq)]
+pub struct ControlStep {
+    /// End of the interval, from the workload's start
+    pub at: Duration,
+    pub ratio: usize,
+    pub delivered: usize,
+    pub rate: f64,
+}
+
+/// How the controller held the delivered rate and what coverage it kept
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdaptiveResult {
+    pub target: f64,
+    pub files: usize,
+    pub writes: usize,
+    pub steps: Vec<ControlStep>,
+}
+
+impl AdaptiveResult {
+    /// Share of files passing the filter, averaged over the intervals
+    pub fn coverage(&self) -> f64 {
+        let total: f64 = self.steps.iter().map(|step| 1.0 / step.ratio as f64).sum();
+        total / self.steps.len().max(1) as f64
+    }
+
+    /// Delivered events per second over the whole run
+    pub fn mean_rate(&self) -> f64 {
+        let delivered: usize = self.steps.iter().map(|step| step.delivered).sum();
+        let elapsed = self.steps.last().map_or(0.0, |step| step.at.as_secs_f64());
+        delivered as f64 / elapsed.max(f64::EPSILON)
+    }
+
+    /// Intervals in which the delivered rate went over the target
+    pub fn over_target(&self) -> usize {
+        self.steps.iter().filter(|step| step.rate > self.target).count()
+    }
+}
+
+/// Run the workload on `scratch` under the controller, returning each interval's step
+fn control(scratch: &Path, options: &Options) -> Result<AdaptiveResult, Box<dyn std::error::Error>> {
+    let mut files = enumerate_dir(scratch, options)?;
+    // Spread each ratio's files over the tree rather than over one directory's worth
+    shuffle(&mut files, options.seed);
+    let filter = Arc::new(AdaptiveFilter {
+        index: files.iter().enumerate().map(|(i, path)| (path.clone(), i)).collect(),
+        ratio: AtomicUsize::new(1),
+    });
+
+    let (tx, rx) = event_channel(&options.watch_config());
+    let callback_filter = filter.clone();
+    let mut watcher = RecommendedWatcher::new(
+        move |res: notify::Result<Event>| {
+            if res.as_ref().map_or(true, |event| callback_filter.passes(event)) {
+                tx.send(res);
+            }
+        },
+        Config::default(),
+    )?;
+    watcher.watch(&extended_length(scratch), RecursiveMode::Recursive)?;
+    std::thread::sleep(options.stabilize);
+
+    let writer_files = files.clone();
+    let seed = options.seed;
+    let writer = std::thread::spawn(move || -> std::io::Result<usize> {
+        let mut order: Vec<usize> = (0..writer_files.len()).collect();
+        shuffle(&mut order, seed.wrapping_add(1));
+        let interval = Duration::from_secs(1) / ADAPTIVE_WRITE_RATE as u32;
+        let start = Instant::now();
+        let mut writes = 0;
+        while start.elapsed() < ADAPTIVE_DURATION && !order.is_empty() {
+            let file = &writer_files[order[writes % order.len()]];
+            fs::OpenOptions::new().append(true).open(file)?.write_all(b"adaptive\n")?;
+            writes += 1;
+            // Paced against the start so a slow write doesn't lower the rate
+            if let Some(wait) = (interval * writes as u32).checked_sub(start.elapsed()) {
+                std::thread::sleep(wait);
+            }
+        }
+        Ok(writes)
+    });
+
+    let start = Instant::now();
+    let mut steps = Vec::new();
+    let mut next_step = start + CONTROL_INTERVAL;
+    let mut delivered = 0;
+    while start.elapsed() < ADAPTIVE_DURATION {
+        if rx.recv_timeout(next_step.saturating_duration_since(Instant::now())).is_ok() {
+            delivered += 1;
+        }
+        if Instant::now() >= next_step {
+            let ratio = filter.ratio.load(Ordering::Relaxed);
+            let rate = delivered as f64 / CONTROL_INTERVAL.as_secs_f64();
+            steps.push(ControlStep { at: start.elapsed(), ratio, delivered, rate });
+            filter.ratio.store(next_ratio(ratio, rate, options.target_rate), Ordering::Relaxed);
+            delivered = 0;
+            next_step += CONTROL_INTERVAL;
+        }
+    }
+    let writes = writer.join().map_err(|_| "writer thread panicked")??;
+    Ok(AdaptiveResult {
+        target: options.target_rate,
+        files: files.len(),
+        writes,
+        steps,
+    })
+}
+
+/// Write to a copy of `dir` faster than `--target-rate` allows and let a controller
+/// adjust a native watcher's filter ratio to bring the delivered rate back under it
+///
+/// A real tool facing an event storm can only shed load by caring about fewer
+/// files; this shows how fast a simple multiplicative controller settles and how
+/// much of the tree it still covers once it has.
+pub fn run_adaptive_test(dir: &Path, options: &Options) -> Result<AdaptiveResult, Box<dyn std::error::Error>> {
+    println!("\n=== Adaptive Filter Ratio ===");
+    println!("Source directory: {}", dir.display());
+    println!(
+        "Target: {:.0} events/s, workload: {} writes/s for {:?}",
+        options.target_rate, ADAPTIVE_WRITE_RATE, ADAPTIVE_DURATION
+    );
+
+    let scratch = std::path::absolute(prepare_scratch_dir(dir, "adaptive", options)?)?;
+    let result = control(&scratch, options);
+    fs::remove_dir_all(&scratch)?;
+    let result = result?;
+
+    println!("\n📊 Controller steps ({}):", std::env::consts::OS);
+    println!("  {:>8} {:>8} {:>10} {:>12} {:>10}", "At", "Ratio", "Coverage", "Events/s", "");
+    for step in &result.steps {
+        println!(
+            "  {:>8} {:>8} {:>9.1}% {:>12.0} {:>10}",
+            format!("{:.2?}", step.at),
+            format!("1/{}", step.ratio),
+            100.0 / step.ratio as f64,
+            step.rate,
+            if step.rate > result.target { "over" } else { "" }
+        );
+    }
+    println!(
+        "\n  {} writes to {} files; delivered {:.0} events/s on average, {} of {} intervals over target",
+        result.writes,
+        result.files,
+        result.mean_rate(),
+        result.over_target(),
+        result.steps.len()
+    );
+    println!("  Achieved coverage: {:.1}% of files on average", result.coverage() * 100.0);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_ratio() {
+        assert_eq!(next_ratio(1, 500.0, 200.0), 2);
+        assert_eq!(next_ratio(4, 150.0, 200.0), 4);
+        assert_eq!(next_ratio(4, 50.0, 200.0), 2);
+        assert_eq!(next_ratio(1, 0.0, 200.0), 1);
+        assert_eq!(next_ratio(MAX_RATIO, 1e6, 200.0), MAX_RATIO);
+    }
+
+    #[test]
+    fn test_adaptive_result_summary() {
+        let step = |ratio: usize, rate: f64| ControlStep {
+            at: Duration::ZERO,
+            ratio,
+            delivered: (rate / 4.0) as usize,
+            rate,
+        };
+        let mut result = AdaptiveResult {
+            target: 200.0,
+            files: 100,
+            writes: 1000,
+            steps: vec![step(1, 800.0), step(2, 400.0), step(4, 200.0), step(4, 200.0)],
+        };
+        result.steps[3].at = Duration::from_secs(1);
+        assert_eq!(result.over_target(), 2);
+        assert!((result.coverage() - 0.5).abs() < 1e-9);
+        assert!((result.mean_rate() - 400.0).abs() < 1e-9);
+    }
+}
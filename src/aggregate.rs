@@ -0,0 +1,144 @@
+use crate::normalize::normalize;
+use notify::Event;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// One "something changed here" notification standing in for every change below it in a window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectoryChange {
+    pub dir: PathBuf,
+    /// Canonical per-file events folded into this notification
+    pub changes: usize,
+    pub opened: Instant,
+}
+
+/// Directory a change is reported against: the parent of the changed entry
+pub fn changed_dir(path: &Path) -> &Path {
+    path.parent().unwrap_or(path)
+}
+
+/// Per-file vs per-directory notification counts for one run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AggregateStats {
+    pub window: Duration,
+    /// Events as delivered by notify
+    pub raw: usize,
+    /// Canonical per-file events before aggregating
+    pub normalized: usize,
+    /// Directory notifications left after aggregating
+    pub notifications: usize,
+    /// Distinct directories notified about
+    pub directories: usize,
+    /// Most per-file events folded into a single notification
+    pub max_changes: usize,
+}
+
+impl AggregateStats {
+    /// Per-file events per directory notification
+    pub fn compression_ratio(&self) -> f64 {
+        if self.notifications == 0 {
+            1.0
+        } else {
+            self.normalized as f64 / self.notifications as f64
+        }
+    }
+
+    /// Print the per-file → per-directory counts
+    pub fn report(&self, indent: &str) {
+        println!(
+            "{}Directory aggregation ({:?} window): {} raw → {} per-file → {} notifications for {} directories ({:.1}x, at most {} changes in one)",
+            indent,
+            self.window,
+            self.raw,
+            self.normalized,
+            self.notifications,
+            self.directories,
+            self.compression_ratio(),
+            self.max_changes
+        );
+    }
+}
+
+/// Collapse timestamped raw events into per-directory notifications
+///
+/// The first change in a directory opens a notification for it; further changes
+/// there within `window` only bump its count, the way a full-rebuild tool that
+/// just needs to know which directories to rescan would consume them.
+pub fn aggregate(events: &[Event], received_at: &[Instant], window: Duration) -> (Vec<DirectoryChange>, AggregateStats) {
+    let mut notifications: Vec<DirectoryChange> = Vec::new();
+    let mut open: HashMap<PathBuf, usize> = HashMap::new();
+    let mut normalized = 0;
+
+    for (event, &at) in events.iter().zip(received_at) {
+        for change in normalize(event) {
+            normalized += 1;
+            let dir = changed_dir(&change.path);
+            match open.get(dir) {
+                Some(&i) if at.saturating_duration_since(notifications[i].opened) < window => {
+                    notifications[i].changes += 1;
+                }
+                _ => {
+                    open.insert(dir.to_path_buf(), notifications.len());
+                    notifications.push(DirectoryChange {
+                        dir: dir.to_path_buf(),
+                        changes: 1,
+                        opened: at,
+                    });
+                }
+            }
+        }
+    }
+
+    let stats = AggregateStats {
+        window,
+        raw: events.len(),
+        normalized,
+        notifications: notifications.len(),
+        directories: notifications.iter().map(|n| &n.dir).collect::<HashSet<_>>().len(),
+        max_changes: notifications.iter().map(|n| n.changes).max().unwrap_or(0),
+    };
+    (notifications, stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, DataChange, ModifyKind};
+    use notify::EventKind;
+
+    fn modify(path: &str) -> Event {
+        Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Any))).add_path(PathBuf::from(path))
+    }
+
+    #[test]
+    fn test_aggregate_by_directory() {
+        let start = Instant::now();
+        let events = [
+            modify("/src/a.rs"),
+            modify("/src/b.rs"),
+            Event::new(EventKind::Create(CreateKind::Folder)).add_path(PathBuf::from("/src/gen")),
+            modify("/docs/x.md"),
+            modify("/src/a.rs"),
+        ];
+        let times = [
+            start,
+            start + Duration::from_millis(5),
+            start + Duration::from_millis(10),
+            start + Duration::from_millis(10),
+            start + Duration::from_millis(60),
+        ];
+
+        let (notifications, stats) = aggregate(&events, &times, Duration::from_millis(50));
+        let summary: Vec<_> = notifications.iter().map(|n| (n.dir.to_str().unwrap(), n.changes)).collect();
+        // The new folder counts against its parent; the late write opens a fresh window
+        assert_eq!(summary, [("/src", 3), ("/docs", 1), ("/src", 1)]);
+        assert_eq!((stats.raw, stats.normalized, stats.notifications), (5, 5, 3));
+        assert_eq!((stats.directories, stats.max_changes), (2, 3));
+        assert!((stats.compression_ratio() - 5.0 / 3.0).abs() < 1e-9);
+
+        let (_, unaggregated) = aggregate(&events, &times, Duration::ZERO);
+        assert_eq!(unaggregated.notifications, 5);
+        assert_eq!(aggregate(&[], &[], Duration::ZERO).1.compression_ratio(), 1.0);
+    }
+}
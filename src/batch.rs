@@ -0,0 +1,379 @@
+use crate::harness::{enumerate_dir, prepare_scratch_dir, write_rounds};
+use crate::options::Options;
+use crate::recursive_file_watcher::{event_channel, extended_length, ChannelKind, WatchConfig};
+use notify::event::{DataChange, ModifyKind};
+use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+use std::time::{Duration, Instant};
+
+/// Events a batch holds before it's flushed early by default
+pub const DEFAULT_BATCH_EVENTS: usize = 256;
+
+/// How often a partly filled batch is flushed by default
+pub const DEFAULT_BATCH_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Synthetic events pushed straight through each delivery, without a watcher
+const REPLAY_EVENTS: usize = 200_000;
+
+/// Rounds of back-to-back appends in the live burst
+const LIVE_BURST_ROUNDS: usize = 20;
+
+/// Quiet time after which the live consumer assumes the burst is over
+const LIVE_IDLE: Duration = Duration::from_millis(500);
+
+/// Groups events into `Vec<Event>` and hands each group to the channel in one send
+///
+/// A batch goes out once it holds `max_events`, or when the flusher thread finds
+/// it non-empty on its `interval` tick, so no event waits longer than about one
+/// interval. The flusher stops once the batcher is dropped.
+pub struct EventBatcher {
+    pending: Mutex<Vec<Event>>,
+    tx: crossbeam_channel::Sender<Vec<Event>>,
+    max_events: usize,
+    full_flushes: AtomicU64,
+    timed_flushes: AtomicU64,
+}
+
+impl EventBatcher {
+    pub fn new(max_events: usize, interval: Duration) -> (Arc<Self>, crossbeam_channel::Receiver<Vec<Event>>) {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let batcher = Arc::new(Self {
+            pending: Mutex::new(Vec::with_capacity(max_events)),
+            tx,
+            max_events: max_events.max(1),
+            full_flushes: AtomicU64::new(0),
+            timed_flushes: AtomicU64::new(0),
+        });
+        let weak: Weak<Self> = Arc::downgrade(&batcher);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(interval);
+            let Some(batcher) = weak.upgrade() else {
+                break;
+            };
+            if batcher.flush() {
+                batcher.timed_flushes.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+        (batcher, rx)
+    }
+
+    /// Add `event` to the current batch, sending the batch if that fills it
+    pub fn push(&self, event: Event) {
+        let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        pending.push(event);
+        if pending.len() >= self.max_events {
+            let batch = std::mem::replace(&mut *pending, Vec::with_capacity(self.max_events));
+            drop(pending);
+            self.full_flushes.fetch_add(1, Ordering::Relaxed);
+            let _ = self.tx.send(batch);
+        }
+    }
+
+    /// Send whatever the current batch holds; false if it was empty
+    pub fn flush(&self) -> bool {
+        let batch = std::mem::take(&mut *self.pending.lock().unwrap_or_else(PoisonError::into_inner));
+        if batch.is_empty() {
+            return false;
+        }
+        // Send errors just mean the receiver was dropped
+        let _ = self.tx.send(batch);
+        true
+    }
+
+    /// Batches sent because they filled up, and because the interval came round
+    pub fn flushes(&self) -> (u64, u64) {
+        (
+            self.full_flushes.load(Ordering::Relaxed),
+            self.timed_flushes.load(Ordering::Relaxed),
+        )
+    }
+}
+
+/// How events get from the callback to the consumer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    /// One channel send per event, as every watcher mode does
+    Single,
+    /// One channel send per `EventBatcher` batch
+    Batched,
+}
+
+impl Delivery {
+    pub const ALL: [Delivery; 2] = [Delivery::Single, Delivery::Batched];
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Single => "Single events",
+            Self::Batched => "Batched",
+        }
+    }
+
+    /// Name results are recorded under, kept apart from the watcher modes'
+    pub fn history_name(&self) -> &'static str {
+        match self {
+            Self::Single => "Single-Event Delivery",
+            Self::Batched => "Batched Delivery",
+        }
+    }
+}
+
+/// Consumer throughput of one delivery under one workload
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchResult {
+    pub delivery: Delivery,
+    /// `"replay"` for synthetic events, `"live"` for a native watcher's
+    pub workload: &'static str,
+    pub events: usize,
+    /// Channel sends it took to deliver them
+    pub sends: usize,
+    /// First send to last receive
+    pub elapsed: Duration,
+    /// Batches sent because they filled up, and on the interval; `None` for single events
+    pub flushes: Option<(u64, u64)>,
+    /// Errors the watcher reported instead of events, left out of both deliveries
+    pub errors: u64,
+}
+
+impl BatchResult {
+    pub fn events_per_sec(&self) -> f64 {
+        self.events as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    pub fn mean_batch(&self) -> f64 {
+        self.events as f64 / self.sends.max(1) as f64
+    }
+}
+
+/// The channel the single-event delivery uses: the watchers' own pipeline on crossbeam,
+/// so both deliveries ride the same channel implementation
+fn single_config() -> WatchConfig {
+    WatchConfig {
+        channel: ChannelKind::Crossbeam,
+        ..WatchConfig::default()
+    }
+}
+
+/// Synthetic content modifications cycling over `files`
+fn replay_events(files: &[PathBuf], count: usize) -> Vec<Event> {
+    (0..count)
+        .map(|i| {
+            let event = Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Content)));
+            match files.get(i % files.len().max(1)) {
+                Some(path) => event.add_path(path.clone()),
+                None => event,
+            }
+        })
+        .collect()
+}
+
+/// Push `events` through `delivery` from a producer thread and time the consumer draining them
+fn replay(delivery: Delivery, events: Vec<Event>, options: &Options) -> Result<BatchResult, Box<dyn std::error::Error>> {
+    let count = events.len();
+    let start = Instant::now();
+    let (sends, flushes) = match delivery {
+        Delivery::Single => {
+            let (tx, rx) = event_channel(&single_config());
+            let producer = std::thread::spawn(move || {
+                for event in events {
+                    tx.send(Ok(event));
+                }
+            });
+            for _ in 0..count {
+                rx.recv()?;
+            }
+            producer.join().map_err(|_| "producer thread panicked")?;
+            (count, None)
+        }
+        Delivery::Batched => {
+            let (batcher, rx) = EventBatcher::new(options.batch_events, options.batch_interval);
+            let producer_batcher = batcher.clone();
+            let producer = std::thread::spawn(move || {
+                let batcher = producer_batcher;
+                for event in events {
+                    batcher.push(event);
+                }
+                batcher.flush();
+            });
+            let (mut received, mut sends) = (0, 0);
+            while received < count {
+                received += rx.recv()?.len();
+                sends += 1;
+            }
+            producer.join().map_err(|_| "producer thread panicked")?;
+            (sends, Some(batcher.flushes()))
+        }
+    };
+    Ok(BatchResult {
+        delivery,
+        workload: "replay",
+        events: count,
+        sends,
+        elapsed: start.elapsed(),
+        flushes,
+        errors: 0,
+    })
+}
+
+/// Consume until `LIVE_IDLE` passes without an event, counting events and sends
+fn drain_live(mut recv: impl FnMut(Duration) -> Option<usize>) -> (usize, usize, Duration) {
+    let (mut events, mut sends) = (0, 0);
+    let mut first = None;
+    let mut last = Instant::now();
+    let mut wait = Duration::from_secs(5);
+    while let Some(count) = recv(wait) {
+        last = Instant::now();
+        first.get_or_insert(last);
+        events += count;
+        sends += 1;
+        wait = LIVE_IDLE;
+    }
+    (events, sends, first.map_or(Duration::ZERO, |first| last - first))
+}
+
+/// Watch a copy of `dir` natively and burst appends at it, delivering through `delivery`
+fn live(delivery: Delivery, dir: &Path, options: &Options) -> Result<BatchResult, Box<dyn std::error::Error>> {
+    let scratch = std::path::absolute(prepare_scratch_dir(dir, "batch", options)?)?;
+    let result = (|| -> Result<BatchResult, Box<dyn std::error::Error>> {
+        let files = enumerate_dir(&scratch, options)?;
+        // Counted in the callback either way, since a batch only carries events
+        let errors = Arc::new(AtomicU64::new(0));
+        let callback_errors = errors.clone();
+        let count_error = move |res: notify::Result<Event>| match res {
+            Ok(event) => Some(event),
+            Err(_) => {
+                callback_errors.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        };
+
+        let (watcher, consumer, batcher) = match delivery {
+            Delivery::Single => {
+                let (tx, rx) = event_channel(&single_config());
+                let mut watcher = RecommendedWatcher::new(
+                    move |res| {
+                        if let Some(event) = count_error(res) {
+                            tx.send(Ok(event));
+                        }
+                    },
+                    Config::default(),
+                )?;
+                watcher.watch(&extended_length(&scratch), RecursiveMode::Recursive)?;
+                let consumer = std::thread::spawn(move || drain_live(|wait| rx.recv_timeout(wait).ok().map(|_| 1)));
+                (watcher, consumer, None)
+            }
+            Delivery::Batched => {
+                let (batcher, rx) = EventBatcher::new(options.batch_events, options.batch_interval);
+                let callback_batcher = batcher.clone();
+                let mut watcher = RecommendedWatcher::new(
+                    move |res| {
+                        if let Some(event) = count_error(res) {
+                            callback_batcher.push(event);
+                        }
+                    },
+                    Config::default(),
+                )?;
+                watcher.watch(&extended_length(&scratch), RecursiveMode::Recursive)?;
+                let consumer =
+                    std::thread::spawn(move || drain_live(|wait| rx.recv_timeout(wait).ok().map(|batch| batch.len())));
+                (watcher, consumer, Some(batcher))
+            }
+        };
+        // Give watcher time to stabilize
+        std::thread::sleep(options.stabilize);
+        write_rounds(&files, LIVE_BURST_ROUNDS, Duration::ZERO);
+        let (events, sends, elapsed) = consumer.join().map_err(|_| "consumer thread panicked")?;
+        drop(watcher);
+
+        Ok(BatchResult {
+            delivery,
+            workload: "live",
+            events,
+            sends,
+            elapsed,
+            flushes: batcher.map(|batcher| batcher.flushes()),
+            errors: errors.load(Ordering::Relaxed),
+        })
+    })();
+    fs::remove_dir_all(&scratch)?;
+    result
+}
+
+/// Compare consumer throughput with one channel send per event against batched sends
+///
+/// The replay pushes synthetic events as fast as a producer thread can, which is
+/// where per-event sends dominate; the live run shows what's left of the
+/// difference behind a native watcher and a real burst of appends.
+pub fn run_batch_test(dir: &Path, options: &Options) -> Result<Vec<BatchResult>, Box<dyn std::error::Error>> {
+    println!("\n=== Event Batching ===");
+    println!("Source directory: {}", dir.display());
+    println!(
+        "Batches of up to {} events, flushed every {:?}",
+        options.batch_events, options.batch_interval
+    );
+
+    let files = enumerate_dir(dir, options)?;
+    let mut results = Vec::new();
+    for delivery in Delivery::ALL {
+        println!("\n--- {} ---", delivery.display_name());
+        let replayed = replay(delivery, replay_events(&files, REPLAY_EVENTS), options)?;
+        println!("   Replayed {} events in {:.1?}", replayed.events, replayed.elapsed);
+        results.push(replayed);
+        let burst = live(delivery, dir, options)?;
+        println!("   Live burst: {} events in {:.1?}", burst.events, burst.elapsed);
+        if burst.errors > 0 {
+            println!("   ⚠️  {} watcher errors left out of the burst", burst.errors);
+        }
+        results.push(burst);
+    }
+
+    println!("\n📊 Consumer throughput by delivery ({}):", std::env::consts::OS);
+    println!(
+        "  {:<16} {:<8} {:>10} {:>10} {:>12} {:>14} {:>14} {:>8}",
+        "Delivery", "Workload", "Events", "Sends", "Mean batch", "Events/s", "Full/Timed", "Errors"
+    );
+    for result in &results {
+        println!(
+            "  {:<16} {:<8} {:>10} {:>10} {:>12.1} {:>14.0} {:>14} {:>8}",
+            result.delivery.display_name(),
+            result.workload,
+            result.events,
+            result.sends,
+            result.mean_batch(),
+            result.events_per_sec(),
+            result.flushes.map_or("-".to_string(), |(full, timed)| format!("{}/{}", full, timed)),
+            result.errors
+        );
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batcher_flushes_full_and_timed() {
+        let (batcher, rx) = EventBatcher::new(3, Duration::from_millis(100));
+        for _ in 0..7 {
+            batcher.push(Event::new(EventKind::Any));
+        }
+        assert_eq!(rx.recv_timeout(Duration::from_secs(1)).unwrap().len(), 3);
+        assert_eq!(rx.recv_timeout(Duration::from_secs(1)).unwrap().len(), 3);
+        // The last event goes out on the next tick
+        assert_eq!(rx.recv_timeout(Duration::from_secs(1)).unwrap().len(), 1);
+        assert_eq!(batcher.flushes(), (2, 1));
+        assert!(!batcher.flush());
+    }
+
+    #[test]
+    fn test_replay_events_cycle_files() {
+        let files = [PathBuf::from("a"), PathBuf::from("b")];
+        let events = replay_events(&files, 5);
+        assert_eq!(events.len(), 5);
+        assert_eq!(events[4].paths, [PathBuf::from("a")]);
+        assert!(replay_events(&[], 2).iter().all(|event| event.paths.is_empty()));
+    }
+}
@@ -0,0 +1,307 @@
+use crate::harness::{enumerate_dir, ordered_watches, prepare_scratch_dir};
+use crate::options::Options;
+use crate::order::{splitmix64, SPLITMIX_GAMMA};
+use crate::recursive_file_watcher::{event_channel, extended_length, EventReceiver};
+use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Watches manual mode may hold at once by default, a common default inotify limit
+pub const DEFAULT_WATCH_BUDGET: usize = 8192;
+
+/// Modifications made per policy
+const BUDGET_WRITES: usize = 300;
+
+/// Share of the files that get most of the modifications
+const HOT_FRACTION: f64 = 0.1;
+
+/// Share of the modifications landing on those files, roughly how edits cluster in a project
+const HOT_SHARE: f64 = 0.8;
+
+/// A write not reported within this long counts as missed
+const MISS_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// What a budgeted watcher does about a file it turns out to have missed a change to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Keep the first files that fit and never change them
+    Static,
+    /// Watch the missed file, evicting the watch with the oldest activity
+    Lru,
+}
+
+impl EvictionPolicy {
+    pub const ALL: [EvictionPolicy; 2] = [EvictionPolicy::Static, EvictionPolicy::Lru];
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Static => "Static Budget",
+            Self::Lru => "LRU Budget",
+        }
+    }
+}
+
+/// Watched paths up to a fixed capacity, ordered by when each last saw activity
+#[derive(Debug)]
+pub struct LruWatchSet {
+    capacity: usize,
+    ticks: HashMap<PathBuf, u64>,
+    /// The same paths keyed by tick, oldest first
+    order: BTreeMap<u64, PathBuf>,
+    clock: u64,
+}
+
+impl LruWatchSet {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ticks: HashMap::new(),
+            order: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.ticks.len()
+    }
+
+    /// Mark `path` as just active; false if it isn't in the set
+    pub fn touch(&mut self, path: &Path) -> bool {
+        let Some(tick) = self.ticks.get_mut(path) else {
+            return false;
+        };
+        self.clock += 1;
+        let path = self.order.remove(tick).expect("every tick has an entry in the order");
+        *tick = self.clock;
+        self.order.insert(self.clock, path);
+        true
+    }
+
+    /// Add `path` as the most recently active, returning the path evicted to make room
+    pub fn insert(&mut self, path: PathBuf) -> Option<PathBuf> {
+        if self.touch(&path) || self.capacity == 0 {
+            return None;
+        }
+        let evicted = if self.ticks.len() >= self.capacity {
+            let (_, oldest) = self.order.pop_first()?;
+            self.ticks.remove(&oldest);
+            Some(oldest)
+        } else {
+            None
+        };
+        self.clock += 1;
+        self.ticks.insert(path.clone(), self.clock);
+        self.order.insert(self.clock, path);
+        evicted
+    }
+}
+
+/// How well one policy kept up with the modifications under the budget
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BudgetResult {
+    pub policy: EvictionPolicy,
+    pub budget: usize,
+    pub files: usize,
+    pub writes: usize,
+    /// Writes reported within `MISS_TIMEOUT`
+    pub caught: usize,
+    pub evictions: usize,
+    /// Time spent swapping watches, each eviction's unwatch and watch together
+    pub eviction_time: Duration,
+}
+
+impl BudgetResult {
+    /// Fraction of modifications that still got caught
+    pub fn hit_rate(&self) -> f64 {
+        self.caught as f64 / self.writes.max(1) as f64
+    }
+}
+
+/// `count` files to modify, most of them from a small hot set, reproducible with `seed`
+pub fn write_targets(files: &[PathBuf], count: usize, seed: u64) -> Vec<PathBuf> {
+    if files.is_empty() {
+        return Vec::new();
+    }
+    let mut state = seed;
+    let mut next = move || {
+        state = state.wrapping_add(SPLITMIX_GAMMA);
+        splitmix64(state)
+    };
+    // Spread the hot set over the tree instead of taking one directory's worth
+    let hot: Vec<&PathBuf> = (0..((files.len() as f64 * HOT_FRACTION).ceil() as usize))
+        .map(|_| &files[(next() % files.len() as u64) as usize])
+        .collect();
+    (0..count)
+        .map(|_| {
+            let pick = (next() >> 11) as f64 / (1u64 << 53) as f64;
+            if pick < HOT_SHARE {
+                hot[(next() % hot.len() as u64) as usize].clone()
+            } else {
+                files[(next() % files.len() as u64) as usize].clone()
+            }
+        })
+        .collect()
+}
+
+/// Wait up to `MISS_TIMEOUT` for an event naming `target`, counting every event as activity
+fn await_report(rx: &EventReceiver, target: &Path, watched: &mut LruWatchSet) -> bool {
+    let deadline = Instant::now() + MISS_TIMEOUT;
+    let mut caught = false;
+    while !caught {
+        let Ok(item) = rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) else {
+            break;
+        };
+        let Ok(event) = item.result else {
+            continue;
+        };
+        for path in &event.paths {
+            watched.touch(path);
+            caught |= path == target;
+        }
+    }
+    caught
+}
+
+/// Modify a copy of `dir` under one policy, with at most `--watch-budget` watches held
+fn run_policy(
+    dir: &Path,
+    policy: EvictionPolicy,
+    options: &Options,
+) -> Result<BudgetResult, Box<dyn std::error::Error>> {
+    let scratch = std::path::absolute(prepare_scratch_dir(dir, "budget", options)?)?;
+    let result = (|| -> Result<BudgetResult, Box<dyn std::error::Error>> {
+        let files = ordered_watches(enumerate_dir(&scratch, options)?, options);
+        let (tx, rx) = event_channel(&options.watch_config());
+        let mut watcher = RecommendedWatcher::new(move |res| tx.send(res), Config::default())?;
+        let mut watched = LruWatchSet::new(options.watch_budget);
+        for file in files.iter().take(options.watch_budget) {
+            watcher.watch(&extended_length(file), RecursiveMode::NonRecursive)?;
+            watched.insert(file.clone());
+        }
+        println!("   Watching {} of {} files", watched.len(), files.len());
+        std::thread::sleep(options.stabilize);
+
+        let targets = write_targets(&files, BUDGET_WRITES, options.seed);
+        let mut result = BudgetResult {
+            policy,
+            budget: options.watch_budget,
+            files: files.len(),
+            writes: targets.len(),
+            caught: 0,
+            evictions: 0,
+            eviction_time: Duration::ZERO,
+        };
+        for target in &targets {
+            fs::OpenOptions::new().append(true).open(target)?.write_all(b"budget\n")?;
+            if await_report(&rx, target, &mut watched) {
+                result.caught += 1;
+            } else if policy == EvictionPolicy::Lru && options.watch_budget > 0 {
+                // The tool learns about the file some other way, e.g. the user opening it
+                let start = Instant::now();
+                if let Some(evicted) = watched.insert(target.clone()) {
+                    watcher.unwatch(&extended_length(&evicted))?;
+                    result.evictions += 1;
+                }
+                watcher.watch(&extended_length(target), RecursiveMode::NonRecursive)?;
+                result.eviction_time += start.elapsed();
+            }
+        }
+        Ok(result)
+    })();
+    fs::remove_dir_all(&scratch)?;
+    let result = result?;
+    println!(
+        "   {} of {} writes caught ({:.1}%), {} evictions",
+        result.caught,
+        result.writes,
+        result.hit_rate() * 100.0,
+        result.evictions
+    );
+    Ok(result)
+}
+
+/// Watch at most `--watch-budget` files and measure how many modifications still get caught
+///
+/// Modifications cluster on a hot set of files, as edits in a real project do.
+/// The static policy keeps the first files that fit; the LRU policy swaps in a
+/// file it missed a change to, evicting the watch that saw activity least recently.
+pub fn run_budget_test(dir: &Path, options: &Options) -> Result<Vec<BudgetResult>, Box<dyn std::error::Error>> {
+    println!("\n=== Capped Watch Budget ===");
+    println!("Source directory: {}", dir.display());
+    println!("Budget: {} watches", options.watch_budget);
+
+    let mut results = Vec::new();
+    for policy in EvictionPolicy::ALL {
+        println!("\n--- {} ---", policy.display_name());
+        results.push(run_policy(dir, policy, options)?);
+    }
+    if results.iter().any(|result| result.files <= result.budget) {
+        println!("\nNote: the budget covers every file; lower --watch-budget to see eviction at work");
+    }
+
+    println!("\n📊 Modifications caught under the budget ({}):", std::env::consts::OS);
+    println!(
+        "  {:<16} {:>8} {:>8} {:>8} {:>8} {:>9} {:>10} {:>12}",
+        "Policy", "Budget", "Files", "Writes", "Caught", "Hit rate", "Evictions", "Per evict"
+    );
+    for result in &results {
+        println!(
+            "  {:<16} {:>8} {:>8} {:>8} {:>8} {:>8.1}% {:>10} {:>12}",
+            result.policy.display_name(),
+            result.budget,
+            result.files,
+            result.writes,
+            result.caught,
+            result.hit_rate() * 100.0,
+            result.evictions,
+            if result.evictions == 0 {
+                "-".to_string()
+            } else {
+                format!("{:.1?}", result.eviction_time / result.evictions as u32)
+            }
+        );
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_watch_set() {
+        let path = |name: &str| PathBuf::from(name);
+        let mut watched = LruWatchSet::new(2);
+        assert_eq!(watched.insert(path("a")), None);
+        assert_eq!(watched.insert(path("b")), None);
+        assert!(watched.touch(Path::new("a")));
+        assert!(!watched.touch(Path::new("c")));
+        // b saw activity least recently
+        assert_eq!(watched.insert(path("c")), Some(path("b")));
+        assert_eq!(watched.insert(path("a")), None);
+        assert_eq!(watched.insert(path("d")), Some(path("c")));
+        assert_eq!(watched.len(), 2);
+        assert!(watched.touch(Path::new("a")) && watched.touch(Path::new("d")));
+        assert_eq!(LruWatchSet::new(0).insert(path("a")), None);
+    }
+
+    #[test]
+    fn test_write_targets_skewed() {
+        let files: Vec<PathBuf> = (0..100).map(|i| PathBuf::from(format!("f{}", i))).collect();
+        let targets = write_targets(&files, 1000, 3);
+        assert_eq!(targets, write_targets(&files, 1000, 3));
+        assert_ne!(targets, write_targets(&files, 1000, 4));
+        let mut counts: HashMap<&PathBuf, usize> = HashMap::new();
+        for target in &targets {
+            *counts.entry(target).or_default() += 1;
+        }
+        let mut counts: Vec<usize> = counts.into_values().collect();
+        counts.sort_unstable_by(|a, b| b.cmp(a));
+        // Ten hot files take most of the writes
+        assert!(counts.iter().take(10).sum::<usize>() > 700);
+        assert!(write_targets(&[], 10, 0).is_empty());
+    }
+}
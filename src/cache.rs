@@ -0,0 +1,187 @@
+use crate::harness::{enumerate_files, start_watcher_on_roots};
+use crate::options::Options;
+use crate::recursive_file_watcher::WatcherMode;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// How the cold run's caches are emptied
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Eviction {
+    /// `echo 3 > /proc/sys/vm/drop_caches`: page cache, dentries and inodes (root only)
+    DropCaches,
+    /// `posix_fadvise(DONTNEED)` on every file: page cache only, but unprivileged
+    Fadvise,
+}
+
+impl Eviction {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Self::DropCaches => "drop_caches (page cache, dentries and inodes)",
+            Self::Fadvise => "fadvise pre-pass (file pages only; dentries and inodes stay cached)",
+        }
+    }
+
+    /// What the evicted run is called: with dentries and inodes still cached, the
+    /// tree walk after an fadvise pass is warm, so only its file pages are cold
+    pub fn heading(&self) -> &'static str {
+        match self {
+            Self::DropCaches => "Cold",
+            Self::Fadvise => "Page-cold",
+        }
+    }
+
+    /// History metric for the evicted run's setup time, kept apart per eviction
+    pub fn metric(&self) -> &'static str {
+        match self {
+            Self::DropCaches => "cold_setup_us",
+            Self::Fadvise => "page_cold_setup_us",
+        }
+    }
+
+    /// Evict cached state for `files`
+    pub fn evict(&self, files: &[PathBuf]) -> io::Result<()> {
+        match self {
+            Self::DropCaches => drop_caches(),
+            Self::Fadvise => {
+                for file in files {
+                    fadvise_dontneed(file)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Flush dirty pages, then ask the kernel to drop clean caches
+fn drop_caches() -> io::Result<()> {
+    #[cfg(unix)]
+    unsafe {
+        libc::sync();
+    }
+    fs::write("/proc/sys/vm/drop_caches", "3").map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("could not write /proc/sys/vm/drop_caches ({}); --drop-caches needs root on Linux", e),
+        )
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn fadvise_dontneed(path: &std::path::Path) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+    let file = fs::File::open(path)?;
+    // SAFETY: the descriptor stays open for the duration of the call
+    let result = unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED) };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::from_raw_os_error(result))
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn fadvise_dontneed(_path: &std::path::Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// One mode's setup time straight after eviction and again with everything cached
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColdWarm {
+    pub mode: WatcherMode,
+    /// How `cold` was evicted, which decides how cold it really is
+    pub eviction: Eviction,
+    pub cold: Duration,
+    pub warm: Duration,
+}
+
+impl ColdWarm {
+    /// How many times slower the cold setup was
+    pub fn ratio(&self) -> f64 {
+        self.cold.as_secs_f64() / self.warm.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// Set up `mode` and tear it down again, returning the wall time of setup
+///
+/// This includes manual mode's tree walk, which is where a cold cache hurts most
+/// and which the watcher's own setup time leaves out.
+fn setup_time(mode: WatcherMode, roots: &[PathBuf], options: &Options) -> notify::Result<Duration> {
+    let start = Instant::now();
+    let (watcher, _rx, _) = start_watcher_on_roots(mode, roots, options)?;
+    let elapsed = start.elapsed();
+    drop(watcher);
+    Ok(elapsed)
+}
+
+/// Time every mode's setup cold, right after evicting caches, and then warm
+///
+/// The cold number is what a dev tool's first start after boot, or after a large
+/// checkout, feels like; the warm one is what repeated benchmark runs usually show.
+pub fn run_cold_warm(roots: &[PathBuf], options: &Options) -> Result<Vec<ColdWarm>, Box<dyn std::error::Error>> {
+    let eviction = if options.drop_caches { Eviction::DropCaches } else { Eviction::Fadvise };
+    println!("\n=== Cold vs Warm Cache Setup ===");
+    println!("Eviction: {}", eviction.describe());
+    if eviction == Eviction::Fadvise {
+        println!("Only file pages are evicted, so the evicted runs are page-cold; pass --drop-caches for a fully cold run");
+    }
+    let files: Vec<PathBuf> = enumerate_files(roots, options)?;
+
+    let mut results = Vec::new();
+    for mode in WatcherMode::EVERY {
+        println!("\n--- {} ({}) ---", mode.display_name(), eviction.heading().to_lowercase());
+        eviction.evict(&files)?;
+        let cold = setup_time(mode, roots, options)?;
+        println!("\n--- {} (warm) ---", mode.display_name());
+        let warm = setup_time(mode, roots, options)?;
+        results.push(ColdWarm {
+            mode,
+            eviction,
+            cold,
+            warm,
+        });
+    }
+
+    println!("\n📊 {} vs warm setup:", eviction.heading());
+    println!(
+        "  {:<20} {:>12} {:>12} {:>15}",
+        "Mode",
+        eviction.heading(),
+        "Warm",
+        format!("{}/Warm", eviction.heading())
+    );
+    for result in &results {
+        println!(
+            "  {:<20} {:>12} {:>12} {:>14.2}x",
+            result.mode.display_name(),
+            format!("{:.1?}", result.cold),
+            format!("{:.1?}", result.warm),
+            result.ratio()
+        );
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fadvise_eviction_and_ratio() {
+        let path = PathBuf::from("test_cache_evict_file");
+        fs::write(&path, "cached").unwrap();
+        let evicted = Eviction::Fadvise.evict(std::slice::from_ref(&path));
+        fs::remove_file(&path).unwrap();
+        evicted.unwrap();
+
+        let result = ColdWarm {
+            mode: WatcherMode::Native,
+            eviction: Eviction::Fadvise,
+            cold: Duration::from_millis(30),
+            warm: Duration::from_millis(10),
+        };
+        assert!((result.ratio() - 3.0).abs() < 1e-9);
+        assert_ne!(Eviction::Fadvise.metric(), Eviction::DropCaches.metric());
+    }
+}
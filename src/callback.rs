@@ -0,0 +1,191 @@
+use crate::harness::{enumerate_dir, get_filtered_files, FILTER_RATIO};
+use crate::heap::{AllocWindow, COUNTING};
+use crate::latency::DurationSummary;
+use crate::options::Options;
+use crate::rate_limit::{RateLimit, DEFAULT_RATE_BURST};
+use crate::recursive_file_watcher::{
+    event_channel, filter_entry, filter_handler, filter_key, EventSink, FilterSet, WatchConfig,
+};
+use crate::stats::is_rescan;
+use notify::event::{DataChange, ModifyKind};
+use notify::{Event, EventKind};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Events per second the callback is driven at, the rate a busy build can reach
+pub const CALLBACK_EVENT_RATE: u32 = 50_000;
+
+/// How the filtered callback is exercised
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallbackVariant {
+    /// The callback as `NativeFiltered` installs it
+    Filtered,
+    /// The same with a token bucket well above the event rate, so only its bookkeeping costs
+    RateLimited,
+    /// The callback `NativeFiltered` installed before `filter_handler`, to compare against
+    Previous,
+}
+
+impl CallbackVariant {
+    pub const ALL: [Self; 3] = [Self::Filtered, Self::RateLimited, Self::Previous];
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Filtered => "filtered",
+            Self::RateLimited => "rate-limited",
+            Self::Previous => "previous",
+        }
+    }
+
+    fn config(&self, options: &Options) -> WatchConfig {
+        let mut config = options.watch_config();
+        if *self == Self::RateLimited {
+            config.rate_limit = Some(RateLimit {
+                rate: 2.0 * CALLBACK_EVENT_RATE as f64,
+                burst: DEFAULT_RATE_BURST,
+            });
+        }
+        config
+    }
+}
+
+/// What one variant cost notify's thread per event
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallbackCost {
+    pub variant: CallbackVariant,
+    pub events: usize,
+    pub forwarded: usize,
+    pub time: DurationSummary,
+    pub allocations_per_event: f64,
+    pub bytes_per_event: f64,
+}
+
+impl CallbackCost {
+    /// Share of notify's thread the callback takes at `CALLBACK_EVENT_RATE`, in percent
+    pub fn load_percent(&self) -> f64 {
+        self.time.mean.as_secs_f64() * CALLBACK_EVENT_RATE as f64 * 100.0
+    }
+}
+
+/// A content change to each of `paths` in turn, `count` in all
+pub fn synthetic_events(paths: &[PathBuf], count: usize) -> Vec<Event> {
+    (0..count)
+        .map(|i| {
+            Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Content))).add_path(paths[i % paths.len()].clone())
+        })
+        .collect()
+}
+
+/// The filtered callback as it was: matched on the raw paths, each keyed on its own,
+/// then sent through `EventSink::send`, which strips extended-length prefixes again
+fn previous_filter_handler(filter: Arc<FilterSet>, tx: EventSink) -> impl Fn(notify::Result<Event>) + Send + Sync {
+    move |res: notify::Result<Event>| {
+        let should_send = match &res {
+            Ok(event) if !is_rescan(event) => event.paths.iter().any(|path| filter.contains(filter_key(path).as_ref())),
+            _ => true,
+        };
+        if should_send {
+            tx.send(res);
+        }
+    }
+}
+
+/// Hand `events` to a fresh filtered callback one every `1 / CALLBACK_EVENT_RATE` seconds,
+/// timing each call and counting what it allocates
+pub fn drive(variant: CallbackVariant, filter: &Arc<FilterSet>, events: Vec<Event>, options: &Options) -> CallbackCost {
+    let (tx, rx) = event_channel(&variant.config(options));
+    let handler: Box<dyn Fn(notify::Result<Event>) + Send + Sync> = match variant {
+        CallbackVariant::Previous => Box::new(previous_filter_handler(Arc::clone(filter), tx)),
+        _ => Box::new(filter_handler(Arc::clone(filter), tx)),
+    };
+    let interval = Duration::from_secs(1) / CALLBACK_EVENT_RATE;
+    let count = events.len();
+    let mut busy = Vec::with_capacity(count);
+
+    let window = AllocWindow::start();
+    let start = Instant::now();
+    for (i, event) in events.into_iter().enumerate() {
+        let due = start + interval * i as u32;
+        while Instant::now() < due {
+            std::hint::spin_loop();
+        }
+        let called = Instant::now();
+        handler(Ok(event));
+        busy.push(called.elapsed());
+    }
+    let allocs = window.finish();
+
+    CallbackCost {
+        variant,
+        events: count,
+        forwarded: rx.depth().get(),
+        time: DurationSummary::from_durations(busy).unwrap_or_default(),
+        allocations_per_event: allocs.allocations as f64 / count.max(1) as f64,
+        bytes_per_event: allocs.bytes as f64 / count.max(1) as f64,
+    }
+}
+
+/// Drive the filtered callback at `CALLBACK_EVENT_RATE` with events for every file in
+/// `dir`, one in `FILTER_RATIO` of them watched, and report its per-event overhead
+pub fn run_callback_bench(dir: &Path, options: &Options) -> Result<Vec<CallbackCost>, Box<dyn std::error::Error>> {
+    println!("\n=== Filtered Callback Overhead ===");
+    let files: Vec<PathBuf> = enumerate_dir(dir, options)?.iter().map(|path| filter_entry(path)).collect();
+    if files.is_empty() {
+        return Err("no files to generate events for".into());
+    }
+    let mut filter = FilterSet::new(&[dir.to_path_buf()]);
+    filter.extend(get_filtered_files(&files, FILTER_RATIO));
+    let filter = Arc::new(filter);
+    let count = CALLBACK_EVENT_RATE as usize;
+    println!(
+        "{} events at {}/s over {} files, {} of them in the filter",
+        count,
+        CALLBACK_EVENT_RATE,
+        files.len(),
+        filter.len()
+    );
+
+    let mut results = Vec::new();
+    println!(
+        "\n  {:<16} {:>9} {:>10} {:>10} {:>12} {:>12} {:>7}",
+        "Variant", "Forwarded", "Mean", "p99", "Allocs/event", "Bytes/event", "Load"
+    );
+    for variant in CallbackVariant::ALL {
+        let cost = drive(variant, &filter, synthetic_events(&files, count), options);
+        println!(
+            "  {:<16} {:>9} {:>10} {:>10} {:>12.3} {:>12.1} {:>6.1}%",
+            variant.display_name(),
+            cost.forwarded,
+            format!("{:.0?}", cost.time.mean),
+            format!("{:.0?}", cost.time.p99),
+            cost.allocations_per_event,
+            cost.bytes_per_event,
+            cost.load_percent()
+        );
+        results.push(cost);
+    }
+    println!("\n  Load is the share of notify's thread spent in the callback; the channel's own block allocations are included");
+    if !COUNTING {
+        println!("  Allocation columns read zero without --features heap-counters");
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_callback_forwards_only_filtered_events() {
+        let files: Vec<PathBuf> = (0..20).map(|i| PathBuf::from(format!("/t/{}.js", i))).collect();
+        let mut filter = FilterSet::new(&[PathBuf::from("/t")]);
+        filter.extend(get_filtered_files(&files, FILTER_RATIO));
+        let filter = Arc::new(filter);
+
+        let cost = drive(CallbackVariant::Filtered, &filter, synthetic_events(&files, 100), &Options::default());
+        assert_eq!((cost.events, cost.forwarded), (100, 10));
+        let previous = drive(CallbackVariant::Previous, &filter, synthetic_events(&files, 100), &Options::default());
+        assert_eq!(previous.forwarded, 10);
+    }
+}
@@ -0,0 +1,257 @@
+use crate::merge::{load_reports, MergedReport};
+use crate::options::Options;
+use crate::history::Measurement;
+use crate::report::{better_direction, mean, samples_by_metric};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+
+/// How one scenario fared on one OS across every run of it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScenarioOutcome {
+    pub ok: usize,
+    pub runs: usize,
+}
+
+impl ScenarioOutcome {
+    pub fn cell(&self) -> String {
+        if self.ok == self.runs {
+            "✓".to_string()
+        } else if self.ok == 0 {
+            "✗".to_string()
+        } else {
+            format!("{}/{}", self.ok, self.runs)
+        }
+    }
+}
+
+/// Best watcher mode on one OS for one metric, with its mean value
+#[derive(Debug, Clone, PartialEq)]
+pub struct Winner {
+    pub watcher_mode: String,
+    pub value: f64,
+}
+
+/// Which scenarios work and which mode wins, per OS, from real merged results
+///
+/// Environments are pooled by OS, so several Linux hosts make one column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapabilityMatrix {
+    pub oses: Vec<String>,
+    /// Per command, its outcome on each OS in `oses` order; `None` where it never ran
+    pub scenarios: BTreeMap<String, Vec<Option<ScenarioOutcome>>>,
+    /// Per (command, metric) with a better direction, the winning mode on each OS; `None` where fewer than two modes recorded it
+    pub winners: BTreeMap<(String, String), Vec<Option<Winner>>>,
+    /// (OS, command, status) of every failed run, deduplicated
+    pub failures: BTreeSet<(String, String, String)>,
+}
+
+impl CapabilityMatrix {
+    pub fn from_merged(merged: &MergedReport) -> Self {
+        let mut oses: Vec<String> = Vec::new();
+        for group in &merged.environments {
+            if !oses.contains(&group.environment.os) {
+                oses.push(group.environment.os.clone());
+            }
+        }
+
+        let mut scenarios: BTreeMap<String, Vec<Option<ScenarioOutcome>>> = BTreeMap::new();
+        let mut failures = BTreeSet::new();
+        // Every measurement per command, per OS; modes only compete within one scenario
+        let mut measurements: Vec<BTreeMap<String, Vec<Measurement>>> = vec![BTreeMap::new(); oses.len()];
+        for group in &merged.environments {
+            let os = oses.iter().position(|os| *os == group.environment.os).unwrap_or(0);
+            for run in &group.runs {
+                let outcomes = scenarios.entry(run.command.clone()).or_insert_with(|| vec![None; oses.len()]);
+                let outcome = outcomes[os].get_or_insert(ScenarioOutcome { ok: 0, runs: 0 });
+                outcome.runs += 1;
+                if run.status == "ok" {
+                    outcome.ok += 1;
+                } else {
+                    failures.insert((group.environment.os.clone(), run.command.clone(), run.status.clone()));
+                }
+                measurements[os].entry(run.command.clone()).or_default().extend(run.measurements.iter().cloned());
+            }
+        }
+
+        let mut winners: BTreeMap<(String, String), Vec<Option<Winner>>> = BTreeMap::new();
+        for (os, commands) in measurements.iter().enumerate() {
+            for (command, measurements) in commands {
+                let mut by_metric: BTreeMap<String, Vec<(String, f64)>> = BTreeMap::new();
+                for ((mode, metric), values) in samples_by_metric(measurements) {
+                    by_metric.entry(metric).or_default().push((mode, mean(&values)));
+                }
+                for (metric, modes) in by_metric {
+                    let Some(better) = better_direction(&metric) else {
+                        continue;
+                    };
+                    if modes.len() < 2 {
+                        continue;
+                    }
+                    let best = modes
+                        .into_iter()
+                        .reduce(|best, next| if better.prefers(next.1, best.1) { next } else { best })
+                        .map(|(watcher_mode, value)| Winner { watcher_mode, value });
+                    winners.entry((command.clone(), metric)).or_insert_with(|| vec![None; oses.len()])[os] = best;
+                }
+            }
+        }
+
+        Self {
+            oses,
+            scenarios,
+            winners,
+            failures,
+        }
+    }
+
+    pub fn print(&self) {
+        let header = |first: &str| {
+            print!("  {:<28}", first);
+            for os in &self.oses {
+                print!(" {:>20}", os);
+            }
+            println!();
+        };
+        println!("Scenarios (✓ every run ok, ✗ every run failed, k/n partly, - not run):");
+        header("Scenario");
+        for (command, outcomes) in &self.scenarios {
+            print!("  {:<28}", command);
+            for outcome in outcomes {
+                print!(" {:>20}", outcome.map_or("-".to_string(), |o| o.cell()));
+            }
+            println!();
+        }
+
+        if !self.winners.is_empty() {
+            println!("\nBest mode per scenario and metric:");
+            header("Scenario / metric");
+            for ((command, metric), winners) in &self.winners {
+                print!("  {:<28}", format!("{} {}", command, metric));
+                for winner in winners {
+                    print!(" {:>20}", winner.as_ref().map_or("-", |w| w.watcher_mode.as_str()));
+                }
+                println!();
+            }
+        }
+
+        if !self.failures.is_empty() {
+            println!("\nFailures:");
+            for (os, command, status) in &self.failures {
+                println!("  {:<10} {:<24} {}", os, command, status);
+            }
+        }
+    }
+
+    /// The same tables as Markdown, for a generated capability page
+    pub fn to_markdown(&self) -> String {
+        let mut md = String::new();
+        let header = |md: &mut String, first: &str| {
+            let _ = writeln!(md, "| {} | {} |", first, self.oses.join(" | "));
+            let columns = first.matches('|').count() + 1 + self.oses.len();
+            let _ = writeln!(md, "|{}", "---|".repeat(columns));
+        };
+        let _ = writeln!(md, "## Scenarios\n");
+        header(&mut md, "Scenario");
+        for (command, outcomes) in &self.scenarios {
+            let cells: Vec<String> = outcomes.iter().map(|o| o.map_or("-".to_string(), |o| o.cell())).collect();
+            let _ = writeln!(md, "| `{}` | {} |", command, cells.join(" | "));
+        }
+        if !self.winners.is_empty() {
+            let _ = writeln!(md, "\n## Best mode per scenario and metric\n");
+            header(&mut md, "Scenario | Metric");
+            for ((command, metric), winners) in &self.winners {
+                let cells: Vec<String> = winners
+                    .iter()
+                    .map(|w| w.as_ref().map_or("-".to_string(), |w| format!("{} ({:.1})", w.watcher_mode, w.value)))
+                    .collect();
+                let _ = writeln!(md, "| `{}` | `{}` | {} |", command, metric, cells.join(" | "));
+            }
+        }
+        md
+    }
+}
+
+/// Summarise merged results per OS: which scenarios fail and which mode wins each metric
+pub fn run_capabilities(paths: &[PathBuf], options: &Options) -> Result<CapabilityMatrix, Box<dyn std::error::Error>> {
+    if paths.is_empty() {
+        return Err("capabilities needs a merged report or several report files".into());
+    }
+    let mut reports = Vec::new();
+    for path in paths {
+        reports.extend(load_reports(path)?);
+    }
+    let merged = MergedReport::merge(reports);
+    let runs: usize = merged.environments.iter().map(|group| group.runs.len()).sum();
+    let matrix = CapabilityMatrix::from_merged(&merged);
+
+    println!("\n=== Capability matrix: {} runs on {} ===", runs, matrix.oses.join(", "));
+    matrix.print();
+    if let Some(path) = &options.capability_md {
+        fs::write(path, matrix.to_markdown())?;
+        println!("\nWrote {}", path.display());
+    }
+    Ok(matrix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::{Environment, RunRecord, TreeFingerprint};
+    use crate::report::RunReport;
+
+    fn report(os: &str, host: &str, command: &str, status: &str, values: &[(&str, f64)]) -> RunReport {
+        let mut run = RunRecord::new(command, &[]);
+        run.id = format!("{}-{}-{}-{}", os, host, command, values.len());
+        for (mode, value) in values {
+            run.add(mode, "setup_us", *value);
+        }
+        let environment = Environment {
+            os: os.to_string(),
+            hostname: host.to_string(),
+            ..Environment::capture()
+        };
+        let tree = TreeFingerprint {
+            hash: "abc".to_string(),
+            files: 3,
+        };
+        RunReport::new(&run, &tree, &environment, status)
+    }
+
+    #[test]
+    fn test_capability_matrix() {
+        let merged = MergedReport::merge([
+            report("linux", "a", "compare", "ok", &[("Manual", 900.0), ("Native", 100.0)]),
+            report("linux", "b", "test-overlay", "error: not root", &[]),
+            report("linux", "b", "compare", "ok", &[("Manual", 1100.0), ("Native", 300.0)]),
+            report("macos", "c", "compare", "ok", &[("Manual", 50.0), ("Native", 80.0)]),
+            report("linux", "a", "test-deep", "ok", &[("Manual", 10.0), ("Native", 5000.0)]),
+        ]);
+        let matrix = CapabilityMatrix::from_merged(&merged);
+        assert_eq!(matrix.oses, ["linux", "macos"]);
+
+        let compare = &matrix.scenarios["compare"];
+        assert_eq!(compare[0], Some(ScenarioOutcome { ok: 2, runs: 2 }));
+        assert_eq!(matrix.scenarios["test-overlay"][0].unwrap().cell(), "✗");
+        assert_eq!(matrix.scenarios["test-overlay"][1], None);
+
+        let key = |command: &str| (command.to_string(), "setup_us".to_string());
+        let winners: Vec<&str> = matrix.winners[&key("compare")]
+            .iter()
+            .map(|w| w.as_ref().unwrap().watcher_mode.as_str())
+            .collect();
+        assert_eq!(winners, ["Native", "Manual"]);
+        assert_eq!(matrix.winners[&key("compare")][0].as_ref().unwrap().value, 200.0);
+        // Another scenario's runs neither join the compare means nor share its winner
+        assert_eq!(matrix.winners[&key("test-deep")][0].as_ref().unwrap().watcher_mode, "Manual");
+        assert_eq!(matrix.winners[&key("test-deep")][1], None);
+        assert_eq!(matrix.failures.len(), 1);
+
+        let md = matrix.to_markdown();
+        assert!(md.contains("| Scenario | linux | macos |"));
+        assert!(md.contains("| `test-overlay` | ✗ | - |"));
+        assert!(md.contains("| Scenario | Metric | linux | macos |\n|---|---|---|---|"));
+        assert!(md.contains("| `compare` | `setup_us` | Native (200.0) | Manual (50.0) |"));
+    }
+}
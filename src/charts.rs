@@ -0,0 +1,167 @@
+use std::fmt::Write;
+
+const SPARK_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Series colours, cycled when there are more modes than colours
+const SVG_COLORS: [&str; 6] = ["#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b"];
+
+const SVG_WIDTH: f64 = 800.0;
+const SVG_CHART_HEIGHT: f64 = 260.0;
+const SVG_MARGIN: f64 = 50.0;
+
+/// One-line chart of `values`, scaled between their min and max
+pub fn sparkline(values: &[f64]) -> String {
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    values
+        .iter()
+        .map(|v| {
+            if max > min {
+                let level = ((v - min) / (max - min) * (SPARK_LEVELS.len() - 1) as f64).round();
+                SPARK_LEVELS[level as usize]
+            } else {
+                SPARK_LEVELS[SPARK_LEVELS.len() / 2]
+            }
+        })
+        .collect()
+}
+
+/// A named series of `(x, y)` points; `x` is shared across a chart's series
+pub struct Series {
+    pub name: String,
+    pub points: Vec<(f64, f64)>,
+}
+
+/// One panel of an SVG document
+pub struct Chart {
+    pub title: String,
+    pub series: Vec<Series>,
+    /// Vertical markers, e.g. where the notify version changed
+    pub markers: Vec<(f64, String)>,
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Render `charts` stacked vertically as a standalone SVG document
+pub fn render_svg(charts: &[Chart]) -> String {
+    let height = SVG_CHART_HEIGHT * charts.len().max(1) as f64;
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif" font-size="11">"#,
+        w = SVG_WIDTH,
+        h = height
+    );
+    for (i, chart) in charts.iter().enumerate() {
+        let top = i as f64 * SVG_CHART_HEIGHT;
+        render_chart(&mut svg, chart, top);
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn render_chart(svg: &mut String, chart: &Chart, top: f64) {
+    let points = chart.series.iter().flat_map(|s| &s.points);
+    let (mut x_min, mut x_max, mut y_max) = (f64::INFINITY, f64::NEG_INFINITY, 0.0f64);
+    for &(x, y) in points {
+        x_min = x_min.min(x);
+        x_max = x_max.max(x);
+        y_max = y_max.max(y);
+    }
+    if !x_min.is_finite() {
+        return;
+    }
+    let plot_w = SVG_WIDTH - 2.0 * SVG_MARGIN;
+    let plot_h = SVG_CHART_HEIGHT - 2.0 * SVG_MARGIN;
+    let sx = |x: f64| SVG_MARGIN + if x_max > x_min { (x - x_min) / (x_max - x_min) * plot_w } else { plot_w / 2.0 };
+    let sy = |y: f64| top + SVG_MARGIN + plot_h - if y_max > 0.0 { y / y_max * plot_h } else { 0.0 };
+
+    let _ = writeln!(
+        svg,
+        r#"<text x="{}" y="{}" font-size="13" font-weight="bold">{}</text>"#,
+        SVG_MARGIN,
+        top + SVG_MARGIN - 20.0,
+        escape(&chart.title)
+    );
+    let _ = writeln!(
+        svg,
+        r##"<path d="M{x0},{yt} V{yb} H{x1}" fill="none" stroke="#888"/>"##,
+        x0 = SVG_MARGIN,
+        yt = top + SVG_MARGIN,
+        yb = top + SVG_MARGIN + plot_h,
+        x1 = SVG_MARGIN + plot_w
+    );
+    let _ = writeln!(
+        svg,
+        r#"<text x="{}" y="{}" text-anchor="end">{:.0}</text>"#,
+        SVG_MARGIN - 4.0,
+        top + SVG_MARGIN + 4.0,
+        y_max
+    );
+    for (x, label) in &chart.markers {
+        let _ = writeln!(
+            svg,
+            r##"<line x1="{x}" y1="{yt}" x2="{x}" y2="{yb}" stroke="#aaa" stroke-dasharray="4 3"/><text x="{tx}" y="{ty}">{label}</text>"##,
+            x = sx(*x),
+            yt = top + SVG_MARGIN,
+            yb = top + SVG_MARGIN + plot_h,
+            tx = sx(*x) + 3.0,
+            ty = top + SVG_MARGIN + 10.0,
+            label = escape(label)
+        );
+    }
+    for (i, series) in chart.series.iter().enumerate() {
+        let color = SVG_COLORS[i % SVG_COLORS.len()];
+        let path: Vec<String> = series
+            .points
+            .iter()
+            .map(|&(x, y)| format!("{:.1},{:.1}", sx(x), sy(y)))
+            .collect();
+        let _ = writeln!(
+            svg,
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="2"/>"#,
+            path.join(" "),
+            color
+        );
+        let _ = writeln!(
+            svg,
+            r#"<text x="{}" y="{}" fill="{}">{}</text>"#,
+            SVG_MARGIN + plot_w - 150.0,
+            top + SVG_MARGIN + 14.0 * (i as f64 + 1.0),
+            color,
+            escape(&series.name)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparkline_scales_to_range() {
+        assert_eq!(sparkline(&[1.0, 2.0, 3.0, 8.0]), "▁▂▃█");
+        assert_eq!(sparkline(&[5.0, 5.0]), "▅▅");
+        assert_eq!(sparkline(&[]), "");
+    }
+
+    #[test]
+    fn test_render_svg() {
+        let chart = Chart {
+            title: "setup <µs>".to_string(),
+            series: vec![Series {
+                name: "Native Recursive".to_string(),
+                points: vec![(0.0, 10.0), (1.0, 20.0)],
+            }],
+            markers: vec![(1.0, "notify 6.1.1".to_string())],
+        };
+        let svg = render_svg(&[chart]);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("<polyline"));
+        assert!(svg.contains("setup &lt;µs&gt;"));
+        assert!(svg.contains("notify 6.1.1"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+}
@@ -0,0 +1,778 @@
+use crate::harness::{enumerate_dir, prepare_scratch_dir, settle_for, start_watcher, watched_files, SettlingCollector};
+use crate::latency::DurationSummary;
+use crate::normalize::NormalizedKind;
+use crate::options::Options;
+use crate::oracle::{Oracle, OracleScore};
+use crate::recursive_file_watcher::WatcherMode;
+use crate::resources::{or_dash, ResourceSample};
+use crate::stats::CollectedEvents;
+use notify::event::{ModifyKind, RenameMode};
+use notify::EventKind;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// `test-delete-heavy` keeps every Nth file and deletes the rest
+const DELETE_KEEP_EVERY: usize = 4;
+
+/// Top-level directories `test-create-heavy` adds, each with one nested subdirectory
+const CREATE_DIRS: usize = 50;
+
+/// Files `test-create-heavy` writes into each new directory
+const CREATE_FILES_PER_DIR: usize = 20;
+
+/// Files `test-rename-storm` renames back to back
+const RENAME_STORM_FILES: usize = 300;
+
+/// Packages `test-npm-install` unpacks
+const INSTALL_PACKAGES: usize = 2000;
+
+/// Files under each package's `lib/`, besides its manifest, entry point and readme
+const INSTALL_LIB_FILES: usize = 8;
+
+/// Artifacts under each of `target/debug/{deps,build,incremental}` in `test-build-artifacts`
+const ARTIFACT_FILES: usize = 200;
+
+/// Times `test-build-artifacts` rewrites every artifact, editing one source file per round
+const ARTIFACT_ROUNDS: usize = 10;
+
+/// New subtrees `test-attach-latency` creates, one probe file each
+const ATTACH_TRIALS: usize = 50;
+
+/// Directory levels in each `test-attach-latency` subtree, the probe file at the bottom
+const ATTACH_DEPTH: usize = 3;
+
+/// Time notify's backend thread gets to close its watches once the watcher is dropped
+const DROP_GRACE: Duration = Duration::from_millis(200);
+
+/// One mode's run of a churn workload
+#[derive(Debug, Clone)]
+pub struct ChurnResult {
+    pub mode: WatcherMode,
+    /// Operations the workload made
+    pub operations: usize,
+    /// Watched files the workload deleted
+    pub removed: usize,
+    /// How long the workload took to make them
+    pub elapsed: Duration,
+    pub score: OracleScore,
+    /// Watch errors reported while the workload ran
+    pub errors: usize,
+    /// Registered inotify watches before the watcher, before the workload,
+    /// after it settled, and once the watcher was dropped
+    pub watches: [Option<usize>; 4],
+    /// How soon new directories' contents were reported, when the workload made any
+    pub pickup: Option<Pickup>,
+    /// How soon files written straight into new subtrees were reported, when the workload wrote any
+    pub attach: Option<Pickup>,
+    /// How the workload's renames were reported, when it made any
+    pub renames: Option<RenameStats>,
+    /// Events received in all
+    pub events: usize,
+    /// Events naming a path under the workload's noise directory, when it has one
+    pub noise_events: Option<usize>,
+    /// Events the `--exclude` list kept from the consumer
+    pub excluded: u64,
+}
+
+impl ChurnResult {
+    /// Per-file watches manual modes kept on files that are gone, once events settled
+    pub fn dangling_watches(&self) -> Option<usize> {
+        if self.removed == 0 || !matches!(self.mode, WatcherMode::Manual | WatcherMode::ManualFiltered) {
+            return None;
+        }
+        Some(self.watches[2]?.saturating_sub(self.watches[1]?.saturating_sub(self.removed)))
+    }
+
+    /// Watches still registered once the watcher is gone
+    pub fn leaked_watches(&self) -> Option<usize> {
+        Some(self.watches[3]?.saturating_sub(self.watches[0]?))
+    }
+}
+
+/// What a churn workload did
+#[derive(Debug, Default)]
+pub struct Applied {
+    pub operations: usize,
+    /// Watched files it deleted, whose per-file watches should go with them
+    pub removed: usize,
+    /// Directories it created, with when each `mkdir` was issued
+    pub new_dirs: Vec<(PathBuf, Instant)>,
+    /// Files written into a subtree made just before, with when its `mkdir` was issued
+    pub attached: Vec<(PathBuf, Instant)>,
+    /// Renames it made, from and to
+    pub renames: Vec<(PathBuf, PathBuf)>,
+    /// Directory whose every change is noise a consumer would rather not see, like `node_modules`
+    pub noise_dir: Option<PathBuf>,
+}
+
+/// A churn workload: given the mode, the scratch root and the files the mode
+/// watches, it changes the tree and registers what it expects on the oracle
+type Workload<'a> = &'a dyn Fn(WatcherMode, &Path, &HashSet<PathBuf>, &mut Oracle) -> io::Result<Applied>;
+
+/// How soon each of a set of new paths was first reported
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pickup {
+    pub targets: usize,
+    /// Targets never reported
+    pub missed: usize,
+    /// From the target's creation starting to its first event
+    pub latency: Option<DurationSummary>,
+}
+
+impl Pickup {
+    /// First event naming a path inside each directory; the directory itself doesn't count
+    pub fn of_dirs(new_dirs: &[(PathBuf, Instant)], collected: &CollectedEvents) -> Self {
+        Self::measure(new_dirs, collected, |dir, path| path != dir && path.starts_with(dir))
+    }
+
+    /// First event naming each file
+    pub fn of_files(files: &[(PathBuf, Instant)], collected: &CollectedEvents) -> Self {
+        Self::measure(files, collected, |file, path| path == file)
+    }
+
+    fn measure(
+        targets: &[(PathBuf, Instant)],
+        collected: &CollectedEvents,
+        reports: impl Fn(&Path, &Path) -> bool,
+    ) -> Self {
+        let mut latencies = Vec::new();
+        for (target, created_at) in targets {
+            let first = collected
+                .events
+                .iter()
+                .zip(&collected.received_at)
+                .filter(|(event, _)| event.paths.iter().any(|path| reports(target, path)))
+                .map(|(_, &at)| at)
+                .min();
+            if let Some(at) = first {
+                latencies.push(at.saturating_duration_since(*created_at));
+            }
+        }
+        Self {
+            targets: targets.len(),
+            missed: targets.len() - latencies.len(),
+            latency: DurationSummary::from_durations(latencies),
+        }
+    }
+}
+
+/// How a batch of renames was reported
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RenameStats {
+    pub renames: usize,
+    /// Renames reported as one event naming both sides, or as halves sharing a tracker
+    pub paired: usize,
+    /// Renames whose two sides were both named by some event, paired or not
+    pub both_sides: usize,
+    /// Renames only one side of which was ever named
+    pub one_side: usize,
+    /// Renames no event named either side of
+    pub dropped: usize,
+}
+
+impl RenameStats {
+    pub fn measure(renames: &[(PathBuf, PathBuf)], collected: &CollectedEvents) -> Self {
+        let mut named: HashSet<&Path> = HashSet::new();
+        let mut pairs: HashSet<(&Path, &Path)> = HashSet::new();
+        // Halves of a rename per tracker, inotify's cookie
+        let mut halves: HashMap<usize, (Option<&Path>, Option<&Path>)> = HashMap::new();
+        for event in &collected.events {
+            named.extend(event.paths.iter().map(PathBuf::as_path));
+            match (event.kind, event.paths.as_slice()) {
+                (EventKind::Modify(ModifyKind::Name(RenameMode::Both)), [from, to]) => {
+                    pairs.insert((from, to));
+                },
+                (EventKind::Modify(ModifyKind::Name(RenameMode::From)), [from]) => {
+                    if let Some(tracker) = event.tracker() {
+                        halves.entry(tracker).or_default().0 = Some(from);
+                    }
+                },
+                (EventKind::Modify(ModifyKind::Name(RenameMode::To)), [to]) => {
+                    if let Some(tracker) = event.tracker() {
+                        halves.entry(tracker).or_default().1 = Some(to);
+                    }
+                },
+                _ => {},
+            }
+        }
+        pairs.extend(halves.into_values().filter_map(|half| Some((half.0?, half.1?))));
+
+        let mut stats = Self {
+            renames: renames.len(),
+            ..Self::default()
+        };
+        for (from, to) in renames {
+            if pairs.contains(&(from.as_path(), to.as_path())) {
+                stats.paired += 1;
+            }
+            match (named.contains(from.as_path()), named.contains(to.as_path())) {
+                (true, true) => stats.both_sides += 1,
+                (false, false) => stats.dropped += 1,
+                _ => stats.one_side += 1,
+            }
+        }
+        stats
+    }
+}
+
+/// Run `workload` against a fresh copy of `dir` under `mode`
+fn run_mode(
+    dir: &Path,
+    slug: &str,
+    mode: WatcherMode,
+    options: &Options,
+    workload: Workload,
+) -> Result<ChurnResult, Box<dyn std::error::Error>> {
+    let tmp_dir = std::path::absolute(prepare_scratch_dir(dir, slug, options)?)?;
+    let result = (|| -> Result<_, Box<dyn std::error::Error>> {
+        let baseline = ResourceSample::take().inotify_watches;
+        let (watcher, rx) = start_watcher(mode, &tmp_dir, options)?;
+        let watched: HashSet<PathBuf> = watched_files(mode, &tmp_dir, options)?.into_iter().collect();
+        let collector =
+            SettlingCollector::spawn(rx, settle_for(mode, options), options.max_collect, options.consumer_delay);
+        // Give watcher time to stabilize
+        std::thread::sleep(options.stabilize);
+
+        let before = ResourceSample::take().inotify_watches;
+        let mut oracle = Oracle::default();
+        let start = Instant::now();
+        let applied = workload(mode, &tmp_dir, &watched, &mut oracle)?;
+        let elapsed = start.elapsed();
+        collector.workload_done();
+        let settled = collector.finish().ok_or("event collector stopped")?;
+        let after = ResourceSample::take().inotify_watches;
+        drop(watcher);
+        std::thread::sleep(DROP_GRACE);
+        let dropped = ResourceSample::take().inotify_watches;
+        Ok((oracle, applied, elapsed, settled, [baseline, before, after, dropped]))
+    })();
+    fs::remove_dir_all(&tmp_dir)?;
+    let (oracle, applied, elapsed, settled, watches) = result?;
+
+    let score = oracle.score(&settled.collected, options.expect_timeout);
+    println!(
+        "   {} operations in {:.1?}, {} events",
+        applied.operations,
+        elapsed,
+        settled.collected.events.len()
+    );
+    score.report("   ");
+    settled.collected.errors.report("   ");
+    let pickup = (!applied.new_dirs.is_empty()).then(|| Pickup::of_dirs(&applied.new_dirs, &settled.collected));
+    let attach = (!applied.attached.is_empty()).then(|| Pickup::of_files(&applied.attached, &settled.collected));
+    let renames = (!applied.renames.is_empty()).then(|| RenameStats::measure(&applied.renames, &settled.collected));
+    let noise_events = applied.noise_dir.as_ref().map(|noise_dir| {
+        let events = &settled.collected.events;
+        events.iter().filter(|event| event.paths.iter().any(|path| path.starts_with(noise_dir))).count()
+    });
+    Ok(ChurnResult {
+        mode,
+        operations: applied.operations,
+        removed: applied.removed,
+        elapsed,
+        score,
+        errors: settled.collected.errors.total(),
+        watches,
+        pickup,
+        attach,
+        renames,
+        events: settled.collected.events.len(),
+        noise_events,
+        excluded: settled.collected.excluded,
+    })
+}
+
+/// Run `workload` under every mode and print detection, errors and watch counts
+fn run_churn(
+    title: &str,
+    dir: &Path,
+    slug: &str,
+    options: &Options,
+    workload: Workload,
+) -> Result<Vec<ChurnResult>, Box<dyn std::error::Error>> {
+    println!("\n=== {} ===", title);
+    println!("Source directory: {}", dir.display());
+
+    let mut results = Vec::new();
+    for mode in WatcherMode::EVERY {
+        println!("\n--- {} ---", mode.display_name());
+        match run_mode(dir, slug, mode, options, workload) {
+            Ok(result) => results.push(result),
+            Err(e) => eprintln!("   {} failed: {}", mode.display_name(), e),
+        }
+    }
+
+    println!("\n📊 {} ({}):", title, std::env::consts::OS);
+    println!(
+        "  {:<20} {:>6} {:>10} {:>9} {:>9} {:>7} {:>22} {:>9} {:>7}",
+        "Mode", "Ops", "Time", "Expected", "Prec/Rec", "Errors", "Watches (pre/post/drop)", "Dangling", "Leaked"
+    );
+    for result in &results {
+        let [_, before, after, dropped] = result.watches;
+        println!(
+            "  {:<20} {:>6} {:>10} {:>9} {:>9} {:>7} {:>22} {:>9} {:>7}",
+            result.mode.display_name(),
+            result.operations,
+            format!("{:.1?}", result.elapsed),
+            result.score.expected,
+            result.score.describe(),
+            result.errors,
+            format!("{}/{}/{}", or_dash(before), or_dash(after), or_dash(dropped)),
+            or_dash(result.dangling_watches()),
+            or_dash(result.leaked_watches())
+        );
+    }
+
+    if results.iter().any(|result| result.renames.is_some()) {
+        println!("\n📊 Rename delivery:");
+        println!(
+            "  {:<20} {:>8} {:>8} {:>10} {:>9} {:>8}",
+            "Mode", "Renames", "Paired", "Both sides", "One side", "Dropped"
+        );
+        for result in &results {
+            if let Some(renames) = &result.renames {
+                println!(
+                    "  {:<20} {:>8} {:>8} {:>10} {:>9} {:>8}",
+                    result.mode.display_name(),
+                    renames.renames,
+                    renames.paired,
+                    renames.both_sides,
+                    renames.one_side,
+                    renames.dropped
+                );
+            }
+        }
+    }
+    if results.iter().any(|result| result.noise_events.is_some()) {
+        println!("\n📊 Event flood:");
+        println!(
+            "  {:<20} {:>8} {:>8} {:>8} {:>8} {:>8}  Suppressed",
+            "Mode", "Ops", "Events", "Noise", "Excluded", "Noise/op"
+        );
+        for result in &results {
+            if let Some(noise) = result.noise_events {
+                println!(
+                    "  {:<20} {:>8} {:>8} {:>8} {:>8} {:>8.2}  {}",
+                    result.mode.display_name(),
+                    result.operations,
+                    result.events,
+                    noise,
+                    result.excluded,
+                    noise as f64 / result.operations.max(1) as f64,
+                    if noise == 0 { "yes" } else { "no" }
+                );
+            }
+        }
+    }
+    print_pickup("New directory pickup (mkdir → first event inside)", &results, |result| result.pickup.as_ref());
+    print_pickup("Recursive attach latency (mkdir → first event for the file written inside)", &results, |result| {
+        result.attach.as_ref()
+    });
+
+    println!("\n=== {} Complete ===\n", title);
+    Ok(results)
+}
+
+/// Print one pickup table row per mode that measured it; nothing when none did
+fn print_pickup(title: &str, results: &[ChurnResult], pickup: impl Fn(&ChurnResult) -> Option<&Pickup>) {
+    if !results.iter().any(|result| pickup(result).is_some()) {
+        return;
+    }
+    println!("\n📊 {}:", title);
+    println!("  {:<20} {:>7} {:>8} {:>10} {:>10} {:>10}", "Mode", "Targets", "Missed", "p50", "p99", "Max");
+    for result in results {
+        let Some(pickup) = pickup(result) else {
+            continue;
+        };
+        let latency = |pick: fn(&DurationSummary) -> Duration| {
+            pickup.latency.as_ref().map_or("-".to_string(), |s| format!("{:.1?}", pick(s)))
+        };
+        println!(
+            "  {:<20} {:>7} {:>8} {:>10} {:>10} {:>10}",
+            result.mode.display_name(),
+            pickup.targets,
+            pickup.missed,
+            latency(|s| s.p50),
+            latency(|s| s.p99),
+            latency(|s| s.max)
+        );
+    }
+}
+
+/// Delete three quarters of the tree while watching and report which deletions each mode saw
+///
+/// inotify drops a per-file watch by itself once its file is gone, so manual
+/// mode's watch count should fall by the number of watched files deleted; any
+/// surplus after the workload settles, or after the watcher is dropped, is a
+/// dangling watch.
+pub fn run_delete_heavy_test(dir: &Path, options: &Options) -> Result<Vec<ChurnResult>, Box<dyn std::error::Error>> {
+    run_churn("Delete-Heavy Workload", dir, "delete-heavy", options, &|_, root, watched, oracle| {
+        let mut applied = Applied::default();
+        for (i, file) in enumerate_dir(root, options).map_err(io::Error::other)?.iter().enumerate() {
+            if i % DELETE_KEEP_EVERY == 0 {
+                continue;
+            }
+            let at = Instant::now();
+            match fs::remove_file(file) {
+                Ok(()) => {
+                    applied.operations += 1;
+                    if watched.contains(file) {
+                        applied.removed += 1;
+                        oracle.expect(file, NormalizedKind::Removed, at);
+                    } else {
+                        oracle.touch(file);
+                    }
+                },
+                Err(e) => eprintln!("   Failed to delete {}: {}", file.display(), e),
+            }
+        }
+        Ok(applied)
+    })
+}
+
+/// Create thousands of files in new directories while watching and time their pickup
+///
+/// Native recursion has to notice each new directory and watch it before its
+/// files can be reported, so pickup latency is where inotify (a watch added per
+/// directory after the fact) and FSEvents (the whole subtree already covered)
+/// differ. The manual and filtered modes only watch what existed at setup and
+/// should report none of it.
+pub fn run_create_heavy_test(dir: &Path, options: &Options) -> Result<Vec<ChurnResult>, Box<dyn std::error::Error>> {
+    run_churn("Create-Heavy Workload", dir, "create-heavy", options, &|mode, root, _, oracle| {
+        let mut applied = Applied::default();
+        for i in 0..CREATE_DIRS {
+            let top = root.join(format!("created-{}", i));
+            for new_dir in [top.clone(), top.join("nested")] {
+                applied.new_dirs.push((new_dir.clone(), Instant::now()));
+                fs::create_dir(&new_dir)?;
+                applied.operations += 1;
+                for j in 0..CREATE_FILES_PER_DIR {
+                    let file = new_dir.join(format!("file-{}.js", j));
+                    let at = Instant::now();
+                    fs::write(&file, format!("// Created {}\n", j))?;
+                    applied.operations += 1;
+                    if mode.watches_new_paths() {
+                        oracle.expect(&file, NormalizedKind::Created, at);
+                    } else {
+                        oracle.touch(&file);
+                    }
+                }
+                oracle.touch(&new_dir);
+            }
+        }
+        Ok(applied)
+    })
+}
+
+/// Rename hundreds of files back to back, as formatters and codegen tools do, and score pair delivery
+///
+/// inotify reports a rename inside the watched tree as two halves sharing a
+/// cookie, which notify pairs into one event; per-file watches only see the
+/// source move away. A rename is dropped when neither side was ever named.
+pub fn run_rename_storm_test(dir: &Path, options: &Options) -> Result<Vec<ChurnResult>, Box<dyn std::error::Error>> {
+    run_churn("Rename Storm", dir, "rename-storm", options, &|_, root, watched, oracle| {
+        let mut applied = Applied::default();
+        for from in enumerate_dir(root, options).map_err(io::Error::other)?.into_iter().take(RENAME_STORM_FILES) {
+            let mut to = from.clone().into_os_string();
+            to.push(".renamed");
+            let to = PathBuf::from(to);
+            let at = Instant::now();
+            match fs::rename(&from, &to) {
+                Ok(()) => {
+                    applied.operations += 1;
+                    if watched.contains(&from) {
+                        oracle.expect(&from, NormalizedKind::Renamed, at);
+                    } else {
+                        oracle.touch(&from);
+                    }
+                    oracle.touch(&to);
+                    applied.renames.push((from, to));
+                },
+                Err(e) => eprintln!("   Failed to rename {}: {}", from.display(), e),
+            }
+        }
+        Ok(applied)
+    })
+}
+
+/// Where `test-npm-install` unpacks packages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InstallLayout {
+    /// Every package directly under `node_modules/`
+    #[default]
+    Npm,
+    /// Packages in `node_modules/.pnpm/<name>@<version>/node_modules/<name>`, symlinked from `node_modules/<name>`
+    Pnpm,
+}
+
+impl InstallLayout {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "npm" => Some(Self::Npm),
+            "pnpm" => Some(Self::Pnpm),
+            _ => None,
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Npm => "npm",
+            Self::Pnpm => "pnpm",
+        }
+    }
+}
+
+/// Write one package's manifest, entry point, readme and `lib/` files, returning how many files
+fn unpack_package(package_dir: &Path, name: &str) -> io::Result<usize> {
+    fs::create_dir_all(package_dir.join("lib"))?;
+    fs::write(package_dir.join("package.json"), format!("{{\"name\":\"{}\",\"version\":\"1.0.0\"}}\n", name))?;
+    fs::write(package_dir.join("index.js"), "module.exports = require('./lib/0');\n")?;
+    fs::write(package_dir.join("README.md"), format!("# {}\n", name))?;
+    for i in 0..INSTALL_LIB_FILES {
+        fs::write(package_dir.join("lib").join(format!("{}.js", i)), format!("exports.n = {};\n", i))?;
+    }
+    Ok(INSTALL_LIB_FILES + 3)
+}
+
+#[cfg(unix)]
+fn link_package(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn link_package(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_dir(target, link)
+}
+
+/// Unpack tens of thousands of small files into `node_modules` while watching and measure the flood
+///
+/// A dev server watching a project gets every one of these events unless it
+/// filters them. The filtered modes only ever watch the files enumerated at
+/// setup, so they should suppress all of it; the recursive modes report the
+/// flood in full, and the native one has to add a watch per new directory too.
+pub fn run_npm_install_test(dir: &Path, options: &Options) -> Result<Vec<ChurnResult>, Box<dyn std::error::Error>> {
+    let title = format!("{} Install Simulation", options.install_layout.display_name());
+    run_churn(&title, dir, "npm-install", options, &|_, root, _, oracle| {
+        let node_modules = root.join("node_modules");
+        let mut applied = Applied {
+            noise_dir: Some(node_modules.clone()),
+            ..Applied::default()
+        };
+        for i in 0..INSTALL_PACKAGES {
+            let name = format!("package-{}", i);
+            let package_dir = match options.install_layout {
+                InstallLayout::Npm => node_modules.join(&name),
+                InstallLayout::Pnpm => {
+                    let store = node_modules.join(".pnpm").join(format!("{}@1.0.0", name));
+                    store.join("node_modules").join(&name)
+                }
+            };
+            applied.operations += unpack_package(&package_dir, &name)?;
+            if options.install_layout == InstallLayout::Pnpm {
+                link_package(&package_dir, &node_modules.join(&name))?;
+                applied.operations += 1;
+            }
+        }
+        // Everything is noise: touched, so nothing counts as spurious, but nothing is expected
+        for path in enumerate_dir(&node_modules, options).map_err(io::Error::other)? {
+            oracle.touch(path);
+        }
+        Ok(applied)
+    })
+}
+
+/// Rewrite thousands of build artifacts under `target/` while watching the whole root
+///
+/// Checks that the `--exclude` list (`target` unless given) keeps every artifact
+/// event from the consumer in each mode, while the source edits made between
+/// rounds still come through. Native recursion still registers inotify watches
+/// for the excluded directories; only delivery is filtered.
+pub fn run_build_artifacts_test(dir: &Path, options: &Options) -> Result<Vec<ChurnResult>, Box<dyn std::error::Error>> {
+    let mut options = options.clone();
+    if options.exclude.is_empty() {
+        options.exclude = vec!["target".to_string()];
+    }
+    println!("Excluding: {}", options.exclude.join(", "));
+    run_churn("Build-Artifact Churn", dir, "build-artifacts", &options, &|_, root, watched, oracle| {
+        let target = root.join("target");
+        let mut applied = Applied {
+            noise_dir: Some(target.clone()),
+            ..Applied::default()
+        };
+        let mut sources: Vec<&PathBuf> = watched.iter().collect();
+        sources.sort();
+        let artifact_dirs = ["deps", "build", "incremental"].map(|name| target.join("debug").join(name));
+        for artifact_dir in &artifact_dirs {
+            fs::create_dir_all(artifact_dir)?;
+        }
+        for round in 0..ARTIFACT_ROUNDS {
+            for artifact_dir in &artifact_dirs {
+                for i in 0..ARTIFACT_FILES {
+                    fs::write(artifact_dir.join(format!("artifact-{}.o", i)), format!("round {}\n", round))?;
+                    applied.operations += 1;
+                }
+            }
+            if let Some(source) = sources.get(round % sources.len().max(1)) {
+                let at = Instant::now();
+                fs::OpenOptions::new().append(true).open(source)?.write_all(b"// edited\n")?;
+                applied.operations += 1;
+                oracle.expect(source, NormalizedKind::Modified, at);
+            }
+        }
+        for path in enumerate_dir(&target, &options).map_err(io::Error::other)? {
+            oracle.touch(path);
+        }
+        Ok(applied)
+    })
+}
+
+/// Create a fresh subtree and immediately write a file at its bottom, timing that file's first event
+///
+/// This is the recursive attach latency: inotify only learns of each new
+/// directory from an event on its parent and has to add a watch before anything
+/// inside can be reported, so a file written in the gap is seen late or never.
+/// FSEvents watches the whole tree by path and has no such gap.
+pub fn run_attach_latency_test(dir: &Path, options: &Options) -> Result<Vec<ChurnResult>, Box<dyn std::error::Error>> {
+    run_churn("Recursive Attach Latency", dir, "attach-latency", options, &|mode, root, _, oracle| {
+        let mut applied = Applied::default();
+        for i in 0..ATTACH_TRIALS {
+            let top = root.join(format!("attach-{}", i));
+            let bottom = (0..ATTACH_DEPTH).fold(top.clone(), |path, level| path.join(format!("level-{}", level)));
+            let file = bottom.join("probe.js");
+            let at = Instant::now();
+            fs::create_dir_all(&bottom)?;
+            fs::write(&file, "// Probe\n")?;
+            applied.operations += 1;
+            applied.attached.push((file.clone(), at));
+            if mode.watches_new_paths() {
+                oracle.expect(&file, NormalizedKind::Created, at);
+            } else {
+                oracle.touch(&file);
+            }
+            for new_dir in bottom.ancestors().take_while(|path| path.starts_with(&top)) {
+                oracle.touch(new_dir);
+            }
+            if !options.pace.is_zero() {
+                std::thread::sleep(options.pace);
+            }
+        }
+        Ok(applied)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recursive_file_watcher::walk_files;
+
+    #[test]
+    fn test_dangling_and_leaked_watches() {
+        let result = ChurnResult {
+            mode: WatcherMode::Manual,
+            operations: 30,
+            removed: 30,
+            elapsed: Duration::ZERO,
+            score: OracleScore::default(),
+            errors: 0,
+            // 40 watches, 30 of them on deleted files, but only 28 went away
+            watches: [Some(2), Some(42), Some(14), Some(3)],
+            pickup: None,
+            attach: None,
+            renames: None,
+            events: 0,
+            noise_events: None,
+            excluded: 0,
+        };
+        assert_eq!(result.dangling_watches(), Some(2));
+        assert_eq!(result.leaked_watches(), Some(1));
+
+        let native = ChurnResult { mode: WatcherMode::Native, ..result.clone() };
+        assert_eq!(native.dangling_watches(), None);
+        let nothing_deleted = ChurnResult { removed: 0, ..result.clone() };
+        assert_eq!(nothing_deleted.dangling_watches(), None);
+        let elsewhere = ChurnResult { watches: [None; 4], ..result };
+        assert_eq!(elsewhere.leaked_watches(), None);
+    }
+
+    #[test]
+    fn test_rename_stats() {
+        use notify::event::{EventAttributes, RemoveKind};
+        use notify::Event;
+
+        let rename = |from: &str, to: &str| (PathBuf::from(from), PathBuf::from(to));
+        let renames = [rename("/a", "/a2"), rename("/b", "/b2"), rename("/c", "/c2"), rename("/d", "/d2")];
+        let name = |mode| EventKind::Modify(ModifyKind::Name(mode));
+        let half = |mode, path: &str, tracker| {
+            let mut attrs = EventAttributes::new();
+            attrs.set_tracker(tracker);
+            Event { kind: name(mode), paths: vec![PathBuf::from(path)], attrs }
+        };
+        let collected = CollectedEvents {
+            events: vec![
+                // Paired by notify
+                Event::new(name(RenameMode::Both)).add_path(PathBuf::from("/a")).add_path(PathBuf::from("/a2")),
+                // Paired only through the tracker
+                half(RenameMode::From, "/b", 7),
+                half(RenameMode::To, "/b2", 7),
+                // Only the source seen going away
+                Event::new(EventKind::Remove(RemoveKind::File)).add_path(PathBuf::from("/c")),
+            ],
+            ..CollectedEvents::default()
+        };
+
+        let stats = RenameStats::measure(&renames, &collected);
+        assert_eq!(
+            stats,
+            RenameStats { renames: 4, paired: 2, both_sides: 2, one_side: 1, dropped: 1 }
+        );
+    }
+
+    #[test]
+    fn test_pickup() {
+        use notify::event::CreateKind;
+        use notify::{Event, EventKind};
+
+        let start = Instant::now();
+        let ms = |n| start + Duration::from_millis(n);
+        let new_dirs = [
+            (PathBuf::from("/w/a"), ms(0)),
+            (PathBuf::from("/w/a/nested"), ms(10)),
+            (PathBuf::from("/w/b"), ms(20)),
+        ];
+        let mut collected = CollectedEvents::default();
+        for (path, at) in [
+            // The directory itself doesn't count as its contents being seen
+            ("/w/b", 21),
+            ("/w/a/f.js", 4),
+            ("/w/a/nested/f.js", 13),
+            ("/w/ab/f.js", 22),
+        ] {
+            collected.events.push(Event::new(EventKind::Create(CreateKind::File)).add_path(PathBuf::from(path)));
+            collected.received_at.push(ms(at));
+        }
+
+        let pickup = Pickup::of_dirs(&new_dirs, &collected);
+        assert_eq!((pickup.targets, pickup.missed), (3, 1));
+        let latency = pickup.latency.unwrap();
+        assert_eq!(latency.min, Duration::from_millis(3));
+        assert_eq!(latency.max, Duration::from_millis(4));
+
+        let files = [(PathBuf::from("/w/a/nested/f.js"), ms(10)), (PathBuf::from("/w/b/f.js"), ms(20))];
+        let attach = Pickup::of_files(&files, &collected);
+        assert_eq!((attach.targets, attach.missed), (2, 1));
+        assert_eq!(attach.latency.unwrap().p50, Duration::from_millis(3));
+    }
+
+    #[test]
+    fn test_unpack_package() {
+        let dir = std::env::temp_dir().join(format!("churn-unpack-{}", std::process::id()));
+        let package_dir = dir.join("node_modules").join("left-pad");
+        let written = unpack_package(&package_dir, "left-pad").unwrap();
+        assert_eq!(written, walk_files(&dir).count());
+        assert!(fs::read_to_string(package_dir.join("package.json")).unwrap().contains("\"left-pad\""));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
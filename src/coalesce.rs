@@ -0,0 +1,178 @@
+use crate::normalize::{normalize, NormalizedEvent, NormalizedKind};
+use notify::Event;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Drops repeats of the same change to the same path within a time window
+///
+/// The first event for a `(path, kind)` pair opens a window; identical events
+/// arriving before it closes are merged into it. The merged event is emitted
+/// when the window closes, so every event in a group waits until then.
+pub struct Coalescer {
+    window: Duration,
+    last_delivered: HashMap<(PathBuf, NormalizedKind), Instant>,
+}
+
+impl Coalescer {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            last_delivered: HashMap::new(),
+        }
+    }
+
+    /// Place `event` arriving at `at` in a group
+    ///
+    /// Returns whether it opened a new group (and so is delivered rather than
+    /// merged) and when that group is flushed.
+    pub fn admit(&mut self, event: &NormalizedEvent, at: Instant) -> (bool, Instant) {
+        let key = (event.path.clone(), event.kind);
+        match self.last_delivered.get(&key) {
+            Some(&opened) if at.saturating_duration_since(opened) < self.window => {
+                (false, opened + self.window)
+            }
+            _ => {
+                self.last_delivered.insert(key, at);
+                (true, at + self.window)
+            }
+        }
+    }
+}
+
+/// Raw vs coalesced event counts for one run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoalesceStats {
+    pub window: Duration,
+    /// Events as delivered by notify
+    pub raw: usize,
+    /// Canonical events before coalescing
+    pub normalized: usize,
+    /// Canonical events left after coalescing
+    pub coalesced: usize,
+    /// Mean wait between an event arriving and its group being flushed
+    pub mean_added_latency: Duration,
+    /// Longest such wait
+    pub max_added_latency: Duration,
+}
+
+impl CoalesceStats {
+    /// Percentage of normalized events merged away
+    pub fn reduction_percent(&self) -> f64 {
+        if self.normalized == 0 {
+            0.0
+        } else {
+            (self.normalized - self.coalesced) as f64 / self.normalized as f64 * 100.0
+        }
+    }
+
+    /// Normalized events per delivered event, e.g. `2.0` when half are merged
+    pub fn compression_ratio(&self) -> f64 {
+        if self.coalesced == 0 {
+            1.0
+        } else {
+            self.normalized as f64 / self.coalesced as f64
+        }
+    }
+
+    /// Print the raw → normalized → coalesced counts
+    pub fn report(&self, indent: &str) {
+        println!(
+            "{}Coalesced ({:?} window): {} raw → {} normalized → {} delivered ({:.1}% merged, +{:?} mean latency)",
+            indent,
+            self.window,
+            self.raw,
+            self.normalized,
+            self.coalesced,
+            self.reduction_percent(),
+            self.mean_added_latency
+        );
+    }
+}
+
+/// Coalesce timestamped raw events, returning the surviving canonical events and counts
+pub fn coalesce(
+    events: &[Event],
+    received_at: &[Instant],
+    window: Duration,
+) -> (Vec<NormalizedEvent>, CoalesceStats) {
+    let mut coalescer = Coalescer::new(window);
+    let mut normalized = 0;
+    let mut delivered = Vec::new();
+    let mut total_latency = Duration::ZERO;
+    let mut max_latency = Duration::ZERO;
+
+    for (event, &at) in events.iter().zip(received_at) {
+        for change in normalize(event) {
+            normalized += 1;
+            let (opened, flush_at) = coalescer.admit(&change, at);
+            let latency = flush_at.saturating_duration_since(at);
+            total_latency += latency;
+            max_latency = max_latency.max(latency);
+            if opened {
+                delivered.push(change);
+            }
+        }
+    }
+
+    let stats = CoalesceStats {
+        window,
+        raw: events.len(),
+        normalized,
+        coalesced: delivered.len(),
+        mean_added_latency: total_latency / normalized.max(1) as u32,
+        max_added_latency: max_latency,
+    };
+    (delivered, stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{AccessKind, DataChange, ModifyKind};
+    use notify::EventKind;
+
+    fn modify(path: &str) -> Event {
+        Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Any)))
+            .add_path(PathBuf::from(path))
+    }
+
+    #[test]
+    fn test_coalesce_within_window() {
+        let start = Instant::now();
+        let events = [
+            modify("/a"),
+            modify("/a"),
+            modify("/b"),
+            Event::new(EventKind::Access(AccessKind::Any)).add_path(PathBuf::from("/a")),
+            modify("/a"),
+        ];
+        let times = [
+            start,
+            start + Duration::from_millis(5),
+            start + Duration::from_millis(6),
+            start + Duration::from_millis(7),
+            start + Duration::from_millis(60),
+        ];
+
+        let (delivered, stats) = coalesce(&events, &times, Duration::from_millis(50));
+        let paths: Vec<_> = delivered.iter().map(|e| e.path.to_str().unwrap()).collect();
+        assert_eq!(paths, ["/a", "/b", "/a"]);
+        assert_eq!(stats.raw, 5);
+        assert_eq!(stats.normalized, 4);
+        assert_eq!(stats.coalesced, 3);
+        assert_eq!(stats.reduction_percent(), 25.0);
+        assert!((stats.compression_ratio() - 4.0 / 3.0).abs() < 1e-9);
+        // Waits: 50, 45, 50, 50 ms → merged events wait less than the window
+        assert_eq!(stats.max_added_latency, Duration::from_millis(50));
+        assert_eq!(stats.mean_added_latency, Duration::from_micros(48_750));
+    }
+
+    #[test]
+    fn test_zero_window_keeps_everything() {
+        let start = Instant::now();
+        let events = [modify("/a"), modify("/a")];
+        let (delivered, _) = coalesce(&events, &[start, start], Duration::ZERO);
+        assert_eq!(delivered.len(), 2);
+    }
+}
@@ -0,0 +1,436 @@
+use crate::harness::{prepare_scratch_dir, spawn_event_collector, start_watcher, watched_files};
+use crate::normalize::{is_metadata, normalize, NormalizedKind};
+use crate::options::Options;
+use crate::recursive_file_watcher::WatcherMode;
+use crate::stats::CollectedEvents;
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Files each operation is applied to, per mode
+const COVERAGE_FILES: usize = 3;
+
+/// How long each mode collects events once its operations have run
+const COVERAGE_COLLECT_DURATION: Duration = Duration::from_secs(2);
+
+/// Extended attribute `Operation::Xattr` sets
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+const XATTR_NAME: &str = "user.watcher-benchmark";
+
+/// File operation one column of the coverage grid applies
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// `ftruncate` to zero length on an already open file
+    Truncate,
+    /// A line added through `O_APPEND`
+    Append,
+    /// The first bytes overwritten in place, keeping the size
+    Overwrite,
+    /// Opened with `O_TRUNC` and written again in full
+    Rewrite,
+    /// Permission bits changed (the read-only flag on Windows)
+    Chmod,
+    /// Owner and group set to their current values, which still updates ctime
+    Chown,
+    /// A user extended attribute set
+    Xattr,
+    /// Modification time set to now, as `touch` does, with the content left alone
+    Touch,
+    /// Modification time set an hour back, as a checkout or archive extraction might
+    Backdate,
+}
+
+impl Operation {
+    pub const WRITES: [Operation; 4] = [Self::Truncate, Self::Append, Self::Overwrite, Self::Rewrite];
+    pub const METADATA: [Operation; 3] = [Self::Chmod, Self::Chown, Self::Xattr];
+    pub const TIMESTAMPS: [Operation; 2] = [Self::Touch, Self::Backdate];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Truncate => "truncate",
+            Self::Append => "append",
+            Self::Overwrite => "overwrite",
+            Self::Rewrite => "rewrite",
+            Self::Chmod => "chmod",
+            Self::Chown => "chown",
+            Self::Xattr => "xattr",
+            Self::Touch => "touch",
+            Self::Backdate => "backdate",
+        }
+    }
+
+    fn apply(&self, path: &Path) -> io::Result<()> {
+        match self {
+            Self::Truncate => fs::OpenOptions::new().write(true).open(path)?.set_len(0),
+            Self::Append => fs::OpenOptions::new()
+                .append(true)
+                .open(path)?
+                .write_all(b"\n// Appended by coverage"),
+            Self::Overwrite => {
+                let mut file = fs::OpenOptions::new().write(true).open(path)?;
+                let len = file.metadata()?.len().min(8) as usize;
+                file.seek(SeekFrom::Start(0))?;
+                file.write_all(&b"////////"[..len])
+            },
+            Self::Rewrite => {
+                let content = fs::read(path)?;
+                fs::write(path, [content.as_slice(), b"\n// Rewritten by coverage"].concat())
+            },
+            Self::Chmod => {
+                let mut permissions = fs::metadata(path)?.permissions();
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    permissions.set_mode(permissions.mode() ^ 0o040);
+                }
+                #[cfg(not(unix))]
+                permissions.set_readonly(!permissions.readonly());
+                fs::set_permissions(path, permissions)
+            },
+            Self::Chown => chown_to_self(path),
+            Self::Xattr => set_xattr(path),
+            Self::Touch => set_mtime(path, SystemTime::now()),
+            Self::Backdate => set_mtime(path, SystemTime::now() - Duration::from_secs(3600)),
+        }
+    }
+}
+
+fn set_mtime(path: &Path, mtime: SystemTime) -> io::Result<()> {
+    fs::OpenOptions::new().write(true).open(path)?.set_modified(mtime)
+}
+
+#[cfg(unix)]
+fn chown_to_self(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = fs::metadata(path)?;
+    std::os::unix::fs::chown(path, Some(metadata.uid()), Some(metadata.gid()))
+}
+
+#[cfg(not(unix))]
+fn chown_to_self(_path: &Path) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "chown needs a unix platform"))
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn set_xattr(path: &Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    let c_name = CString::new(XATTR_NAME)?;
+    let value = b"1";
+    // SAFETY: both strings are NUL-terminated and the value pointer covers its length
+    #[cfg(target_os = "linux")]
+    let result = unsafe { libc::setxattr(c_path.as_ptr(), c_name.as_ptr(), value.as_ptr().cast(), value.len(), 0) };
+    // SAFETY: as above; macOS adds a resource-fork position and an options word
+    #[cfg(target_os = "macos")]
+    let result = unsafe { libc::setxattr(c_path.as_ptr(), c_name.as_ptr(), value.as_ptr().cast(), value.len(), 0, 0) };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn set_xattr(_path: &Path) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "xattr needs Linux or macOS"))
+}
+
+/// Which normalized kinds one mode reported for one operation's files
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoverageCell {
+    pub targets: usize,
+    /// Targets at least one event named
+    pub reported: usize,
+    pub kinds: BTreeSet<NormalizedKind>,
+    /// Some of the events were metadata-only changes, which `kinds` leaves out
+    pub metadata: bool,
+    /// The operation itself failed on every target, e.g. xattrs on a filesystem without them
+    pub unsupported: bool,
+    /// The watcher could not be set up at all
+    pub failed: bool,
+}
+
+impl CoverageCell {
+    fn from_events(targets: &[PathBuf], collected: &CollectedEvents) -> Self {
+        let targets: HashSet<&PathBuf> = targets.iter().collect();
+        let mut reported = HashSet::new();
+        let mut kinds = BTreeSet::new();
+        let mut metadata = false;
+        for event in &collected.events {
+            for change in normalize(event) {
+                if let Some(target) = targets.get(&change.path) {
+                    reported.insert(*target);
+                    if is_metadata(event) {
+                        metadata = true;
+                    } else {
+                        kinds.insert(change.kind);
+                    }
+                }
+            }
+        }
+        Self {
+            targets: targets.len(),
+            reported: reported.len(),
+            kinds,
+            metadata,
+            ..Self::default()
+        }
+    }
+
+    /// Whether every target was reported; `None` when the cell can't say, as with unsupported operations
+    pub fn complete(&self) -> Option<bool> {
+        if self.unsupported || (self.targets == 0 && !self.failed) {
+            return None;
+        }
+        Some(!self.failed && self.reported == self.targets)
+    }
+
+    /// Grid text, e.g. `✓ modified`, `~ 1/3 metadata` or `✗ none`
+    fn describe(&self) -> String {
+        let mut kinds: Vec<String> = self.kinds.iter().map(ToString::to_string).collect();
+        if self.metadata {
+            kinds.push("metadata".to_string());
+        }
+        let kinds = kinds.join("+");
+        if self.failed {
+            "✗ error".to_string()
+        } else if self.unsupported {
+            "- unsupported".to_string()
+        } else if self.targets == 0 {
+            "- no files".to_string()
+        } else if self.reported == 0 {
+            "✗ none".to_string()
+        } else if self.reported < self.targets {
+            format!("~ {}/{} {}", self.reported, self.targets, kinds)
+        } else {
+            format!("✓ {}", kinds)
+        }
+    }
+}
+
+/// Apply each operation to its own few files under one watcher, attributing events by path
+fn run_mode(
+    tmp_dir: &Path,
+    mode: WatcherMode,
+    operations: &[Operation],
+    options: &Options,
+) -> Result<Vec<CoverageCell>, Box<dyn std::error::Error>> {
+    let (watcher, rx) = start_watcher(mode, tmp_dir, options)?;
+    let files = watched_files(mode, tmp_dir, options)?;
+    let targets: Vec<Vec<PathBuf>> = operations
+        .iter()
+        .enumerate()
+        .map(|(i, _)| files.iter().skip(i * COVERAGE_FILES).take(COVERAGE_FILES).cloned().collect())
+        .collect();
+
+    let collector = spawn_event_collector(rx, COVERAGE_COLLECT_DURATION + options.poll_interval, Duration::ZERO);
+    // Give watcher time to stabilize
+    std::thread::sleep(options.stabilize);
+    let mut applied = vec![0; operations.len()];
+    for ((operation, files), applied) in operations.iter().zip(&targets).zip(&mut applied) {
+        for file in files {
+            match operation.apply(file) {
+                Ok(()) => *applied += 1,
+                Err(e) => eprintln!("   {} failed on {}: {}", operation.name(), file.display(), e),
+            }
+            std::thread::sleep(options.pace);
+        }
+    }
+    let mut collected = collector.recv()?;
+    drop(watcher);
+    if options.ignore_metadata {
+        collected.drop_metadata();
+    }
+
+    Ok(targets
+        .iter()
+        .zip(applied)
+        .map(|(files, applied)| CoverageCell {
+            unsupported: !files.is_empty() && applied == 0,
+            ..CoverageCell::from_events(files, &collected)
+        })
+        .collect())
+}
+
+fn print_grid(operations: &[Operation], rows: &[(WatcherMode, Vec<CoverageCell>)]) {
+    print!("  {:<20}", "Mode");
+    for operation in operations {
+        print!(" {:>18}", operation.name());
+    }
+    println!();
+    for (mode, cells) in rows {
+        print!("  {:<20}", mode.display_name());
+        for cell in cells {
+            print!(" {:>18}", cell.describe());
+        }
+        println!();
+    }
+}
+
+/// One grid row: `operations` applied to a scratch copy of `dir` under `mode`
+///
+/// A watcher that can't be set up yields a row of failed cells rather than an error.
+pub fn coverage_row(
+    dir: &Path,
+    mode: WatcherMode,
+    operations: &[Operation],
+    options: &Options,
+) -> Result<Vec<CoverageCell>, Box<dyn std::error::Error>> {
+    let tmp_dir = std::path::absolute(prepare_scratch_dir(dir, "coverage", options)?)?;
+    let cells = run_mode(&tmp_dir, mode, operations, options).unwrap_or_else(|e| {
+        eprintln!("   {} failed: {}", mode.display_name(), e);
+        let failed = CoverageCell {
+            failed: true,
+            ..CoverageCell::default()
+        };
+        vec![failed; operations.len()]
+    });
+    fs::remove_dir_all(&tmp_dir)?;
+    Ok(cells)
+}
+
+/// Apply `operations` under every mode and print which kinds each one was reported as
+fn run_coverage(
+    title: &str,
+    dir: &Path,
+    operations: &[Operation],
+    options: &Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("\n=== {} ===", title);
+    println!("Source directory: {}", dir.display());
+    if options.ignore_metadata {
+        println!("Metadata-only events are left out (--ignore-metadata)");
+    }
+    println!(
+        "{} operations × {} files per mode, collecting {:?}",
+        operations.len(),
+        COVERAGE_FILES,
+        COVERAGE_COLLECT_DURATION
+    );
+
+    let mut rows = Vec::new();
+    for mode in WatcherMode::EVERY {
+        println!("\n--- {} ---", mode.display_name());
+        rows.push((mode, coverage_row(dir, mode, operations, options)?));
+    }
+
+    println!("\n📊 Normalized kinds reported per operation ({}):", std::env::consts::OS);
+    print_grid(operations, &rows);
+
+    println!("\n=== {} Complete ===\n", title);
+
+    Ok(())
+}
+
+/// Truncate, append to, overwrite and rewrite files under every mode and show the kinds reported
+///
+/// A `✗ none` cell means the operation is invisible to that mode: a consumer
+/// keyed on change events will never learn about it. Polling with content
+/// comparison sees a truncation; backends that only report writes may not.
+pub fn run_write_coverage(dir: &Path, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    run_coverage("Write Event Coverage", dir, &Operation::WRITES, options)
+}
+
+/// Change permissions, ownership and extended attributes and show which modes report them
+///
+/// inotify reports all three as attribute changes, while the poll watcher only
+/// notices what alters the metadata it compares. With `--ignore-metadata` the
+/// grid shows what a tool that drops metadata events would still see.
+pub fn run_metadata_coverage(dir: &Path, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    run_coverage("Metadata Event Coverage", dir, &Operation::METADATA, options)
+}
+
+/// Move files' modification times forward and back without touching content
+///
+/// make-style builders rebuild on a newer mtime alone, so a mode that stays
+/// silent here misses `touch`. A backdated file is what a checkout can leave
+/// behind, and notify's poll watcher only reports an mtime that moved forward.
+pub fn run_touch_coverage(dir: &Path, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    run_coverage("Timestamp Event Coverage", dir, &Operation::TIMESTAMPS, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{AccessKind, DataChange, MetadataKind, ModifyKind};
+    use notify::{Event, EventKind};
+
+    #[test]
+    fn test_write_operations() {
+        let dir = Path::new("test_coverage_dir");
+        fs::create_dir_all(dir).unwrap();
+        let file = dir.join("a.js");
+        let read = || fs::read_to_string(&file).unwrap();
+
+        fs::write(&file, "console.log(1);").unwrap();
+        Operation::Overwrite.apply(&file).unwrap();
+        assert_eq!(read(), "////////log(1);");
+        Operation::Append.apply(&file).unwrap();
+        assert!(read().ends_with("Appended by coverage"));
+        Operation::Rewrite.apply(&file).unwrap();
+        assert!(read().starts_with("////////") && read().ends_with("Rewritten by coverage"));
+        Operation::Truncate.apply(&file).unwrap();
+        assert_eq!(read(), "");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_metadata_operations_keep_content() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = Path::new("test_coverage_metadata_dir");
+        fs::create_dir_all(dir).unwrap();
+        let file = dir.join("a.js");
+        fs::write(&file, "a").unwrap();
+        let mode = || fs::metadata(&file).unwrap().permissions().mode();
+
+        let before = mode();
+        Operation::Chmod.apply(&file).unwrap();
+        assert_eq!(mode() ^ before, 0o040);
+        Operation::Chown.apply(&file).unwrap();
+        // Not every filesystem (tmpfs before 6.6, some overlays) takes user xattrs
+        let _ = Operation::Xattr.apply(&file);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "a");
+
+        let mtime = || fs::metadata(&file).unwrap().modified().unwrap();
+        Operation::Backdate.apply(&file).unwrap();
+        let backdated = mtime();
+        assert!(backdated < SystemTime::now() - Duration::from_secs(3000));
+        Operation::Touch.apply(&file).unwrap();
+        assert!(mtime() > backdated);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "a");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_cell_kinds_and_verdicts() {
+        let targets = [PathBuf::from("/t/a"), PathBuf::from("/t/b")];
+        let mut collected = CollectedEvents::default();
+        for (kind, path) in [
+            (EventKind::Modify(ModifyKind::Data(DataChange::Any)), "/t/a"),
+            (EventKind::Access(AccessKind::Any), "/t/b"),
+            (EventKind::Create(notify::event::CreateKind::File), "/t/other"),
+        ] {
+            collected.events.push(Event::new(kind).add_path(PathBuf::from(path)));
+        }
+
+        let cell = CoverageCell::from_events(&targets, &collected);
+        assert_eq!((cell.targets, cell.reported), (2, 1));
+        assert_eq!(cell.kinds, BTreeSet::from([NormalizedKind::Modified]));
+        assert_eq!(cell.describe(), "~ 1/2 modified");
+        assert_eq!(CoverageCell { reported: 2, ..cell.clone() }.describe(), "✓ modified");
+        assert_eq!(CoverageCell::from_events(&targets, &CollectedEvents::default()).describe(), "✗ none");
+
+        collected.events.push(Event::new(EventKind::Modify(ModifyKind::Metadata(MetadataKind::Any))).add_path(PathBuf::from("/t/b")));
+        let cell = CoverageCell::from_events(&targets, &collected);
+        assert_eq!(cell.describe(), "✓ modified+metadata");
+        collected.drop_metadata();
+        assert_eq!(CoverageCell::from_events(&targets, &collected).describe(), "~ 1/2 modified");
+        assert_eq!(CoverageCell { unsupported: true, ..cell }.describe(), "- unsupported");
+    }
+}
@@ -0,0 +1,176 @@
+use crate::charts::{render_svg, sparkline, Chart, Series};
+use crate::harness::enumerate_files;
+use crate::limits::preflight_manual_watches;
+use crate::options::Options;
+use crate::recursive_file_watcher::extended_length;
+use crate::trend::LinearTrend;
+use notify::{ErrorKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Exponents within this much of 1 are reported as linear
+const LINEAR_TOLERANCE: f64 = 0.1;
+
+/// Cumulative setup time after one batch of watches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CurvePoint {
+    pub watches: usize,
+    pub elapsed: Duration,
+}
+
+/// How setup time grows with the number of watches, as `elapsed ∝ watches^exponent`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scaling {
+    pub exponent: f64,
+    pub std_error: f64,
+}
+
+impl Scaling {
+    /// Fit the exponent on log-log axes, or `None` with fewer than three points
+    pub fn fit(points: &[CurvePoint]) -> Option<Self> {
+        let logs: Vec<(f64, f64)> = points
+            .iter()
+            .filter(|p| p.watches > 0 && !p.elapsed.is_zero())
+            .map(|p| ((p.watches as f64).ln(), p.elapsed.as_secs_f64().ln()))
+            .collect();
+        let trend = LinearTrend::fit(&logs)?;
+        Some(Self {
+            exponent: trend.slope,
+            std_error: trend.slope_std_error,
+        })
+    }
+
+    pub fn describe(&self) -> &'static str {
+        if self.exponent > 1.0 + LINEAR_TOLERANCE {
+            "super-linear"
+        } else if self.exponent < 1.0 - LINEAR_TOLERANCE {
+            "sub-linear"
+        } else {
+            "linear"
+        }
+    }
+}
+
+/// Register a watch for each of `files` in batches, timing the running total after each
+///
+/// Hitting the OS watch limit ends the curve early with a final partial batch.
+fn register_in_batches(files: &[PathBuf], batch_size: usize) -> notify::Result<Vec<CurvePoint>> {
+    let mut watcher = RecommendedWatcher::new(|_| {}, notify::Config::default())?;
+    let start = Instant::now();
+    let mut watches = 0;
+    let mut points = Vec::new();
+    for batch in files.chunks(batch_size) {
+        let mut limit_reached = false;
+        for file in batch {
+            match watcher.watch(&extended_length(file), RecursiveMode::NonRecursive) {
+                Ok(()) => watches += 1,
+                Err(e) if matches!(e.kind, ErrorKind::MaxFilesWatch) => {
+                    println!("   OS watch limit reached after {} watches; stopping the curve there", watches);
+                    limit_reached = true;
+                    break;
+                },
+                Err(e) => return Err(e),
+            }
+        }
+        points.push(CurvePoint {
+            watches,
+            elapsed: start.elapsed(),
+        });
+        if limit_reached {
+            break;
+        }
+    }
+    Ok(points)
+}
+
+/// Time manual registration batch by batch and show whether it scales linearly
+///
+/// Each batch's per-watch cost is drawn as a sparkline; a flat line means the
+/// platform's watch registration is linear in the number of watches.
+pub fn run_setup_curve(roots: &[PathBuf], options: &Options) -> Result<Option<Scaling>, Box<dyn std::error::Error>> {
+    println!("\n=== Setup Time vs Watch Count ===");
+    let files: Vec<PathBuf> = enumerate_files(roots, options)?;
+    let files = preflight_manual_watches(files, options.allow_partial)?;
+    println!("Registering {} watches in batches of {}", files.len(), options.batch_size);
+
+    let points = register_in_batches(&files, options.batch_size)?;
+
+    println!("\n📈 Setup curve:");
+    println!("  {:>10} {:>14} {:>12} {:>12}", "Watches", "Cumulative", "Batch", "Per watch");
+    let mut previous = CurvePoint {
+        watches: 0,
+        elapsed: Duration::ZERO,
+    };
+    let mut per_watch = Vec::new();
+    for point in &points {
+        let batch = point.elapsed - previous.elapsed;
+        let per = batch / (point.watches - previous.watches).max(1) as u32;
+        per_watch.push(per.as_secs_f64());
+        println!(
+            "  {:>10} {:>14} {:>12} {:>12}",
+            point.watches,
+            format!("{:.1?}", point.elapsed),
+            format!("{:.1?}", batch),
+            format!("{:.1?}", per)
+        );
+        previous = *point;
+    }
+    println!("  Per-watch cost by batch: {}", sparkline(&per_watch));
+
+    let scaling = Scaling::fit(&points);
+    match scaling {
+        Some(s) => println!(
+            "  Scaling: time ∝ watches^{:.2} (± {:.2}), {}",
+            s.exponent,
+            s.std_error,
+            s.describe()
+        ),
+        None => println!("  Scaling: need at least three batches to fit; try a smaller --batch-size"),
+    }
+
+    if let Some(svg_path) = &options.curve_svg {
+        let chart = Chart {
+            title: "Cumulative setup time (ms) vs watches".to_string(),
+            series: vec![Series {
+                name: "Manual Recursive".to_string(),
+                points: points
+                    .iter()
+                    .map(|p| (p.watches as f64, p.elapsed.as_secs_f64() * 1e3))
+                    .collect(),
+            }],
+            markers: Vec::new(),
+        };
+        fs::write(svg_path, render_svg(&[chart]))?;
+        println!("\nWrote {}", svg_path.display());
+    }
+
+    Ok(scaling)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve(f: impl Fn(f64) -> f64) -> Vec<CurvePoint> {
+        (1..=8)
+            .map(|i| CurvePoint {
+                watches: i * 1000,
+                elapsed: Duration::from_secs_f64(f(i as f64 * 1000.0)),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_scaling_exponent() {
+        let linear = Scaling::fit(&curve(|n| n * 1e-5)).unwrap();
+        assert!((linear.exponent - 1.0).abs() < 1e-9);
+        assert_eq!(linear.describe(), "linear");
+
+        let quadratic = Scaling::fit(&curve(|n| n * n * 1e-9)).unwrap();
+        assert!((quadratic.exponent - 2.0).abs() < 1e-9);
+        assert_eq!(quadratic.describe(), "super-linear");
+
+        assert!(Scaling::fit(&curve(|n| n)[..2]).is_none());
+    }
+}
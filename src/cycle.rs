@@ -0,0 +1,181 @@
+use crate::harness::watched_files_in;
+use crate::latency::DurationSummary;
+use crate::options::Options;
+use crate::recursive_file_watcher::{extended_length, WatcherMode};
+use crate::resources::{verify_released, ResourceSample, RELEASE_GRACE};
+use crate::trend::LinearTrend;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Modes cycled; the filtered native mode registers exactly what native mode does
+const CYCLE_MODES: [WatcherMode; 3] = [WatcherMode::Manual, WatcherMode::ManualFiltered, WatcherMode::Native];
+
+/// First-cycle setup this many times the median counts as a warm-up effect
+const WARMUP_RATIO: f64 = 1.5;
+
+/// One setup and teardown of a watcher
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cycle {
+    pub setup: Duration,
+    /// Time spent in the watcher's drop
+    pub teardown: Duration,
+    /// What the process still held once the watcher had time to close everything
+    pub unreleased: Option<String>,
+}
+
+/// Every cycle of one mode, in order
+#[derive(Debug, Clone, PartialEq)]
+pub struct CycleResult {
+    pub mode: WatcherMode,
+    pub cycles: Vec<Cycle>,
+}
+
+impl CycleResult {
+    /// First cycle's setup over the median of the rest
+    pub fn warmup_ratio(&self) -> Option<f64> {
+        let (first, rest) = self.cycles.split_first()?;
+        let median = DurationSummary::from_durations(rest.iter().map(|cycle| cycle.setup).collect())?.p50;
+        Some(first.setup.as_secs_f64() / median.as_secs_f64().max(f64::EPSILON))
+    }
+
+    /// Setup time in µs against cycle number, leaving out the first cycle's warm-up
+    pub fn trend(&self) -> Option<LinearTrend> {
+        let points: Vec<(f64, f64)> = self
+            .cycles
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(i, cycle)| (i as f64, cycle.setup.as_secs_f64() * 1e6))
+            .collect();
+        LinearTrend::fit(&points)
+    }
+
+    /// Cycles after which the process held more than before the first one
+    pub fn leaking_cycles(&self) -> usize {
+        self.cycles.iter().filter(|cycle| cycle.unreleased.is_some()).count()
+    }
+}
+
+/// Register `mode`'s watches on a watcher that discards events, timing the whole setup
+fn set_up(mode: WatcherMode, roots: &[PathBuf], files: &[PathBuf]) -> notify::Result<(RecommendedWatcher, Duration)> {
+    let start = Instant::now();
+    let mut watcher = RecommendedWatcher::new(|_| {}, notify::Config::default())?;
+    match mode {
+        WatcherMode::Manual | WatcherMode::ManualFiltered => {
+            for file in files {
+                watcher.watch(&extended_length(file), RecursiveMode::NonRecursive)?;
+            }
+        },
+        _ => {
+            for root in roots {
+                watcher.watch(&extended_length(root), RecursiveMode::Recursive)?;
+            }
+        },
+    }
+    Ok((watcher, start.elapsed()))
+}
+
+/// Set up and tear down each mode's watcher `--cycles` times and check the cost stays flat
+///
+/// A first cycle much slower than the rest is a warm-up effect (dentry and inode
+/// caches, the backend's own allocations); setup that keeps growing, or a process
+/// that ends a cycle holding more than it started with, points to kernel or library
+/// state piling up across watchers.
+pub fn run_cycle_test(roots: &[PathBuf], options: &Options) -> Result<Vec<CycleResult>, Box<dyn std::error::Error>> {
+    println!("\n=== Setup/Teardown Cycling ===");
+    println!("{} cycles per mode", options.cycles);
+
+    let mut results = Vec::new();
+    for mode in CYCLE_MODES {
+        let files = watched_files_in(mode, roots, options)?;
+        println!("\n--- {} ({} files) ---", mode.display_name(), files.len());
+        let baseline = ResourceSample::take();
+        let mut cycles = Vec::with_capacity(options.cycles);
+        for _ in 0..options.cycles {
+            let (watcher, setup) = set_up(mode, roots, &files)?;
+            let start_drop = Instant::now();
+            drop(watcher);
+            let teardown = start_drop.elapsed();
+            let unreleased = verify_released(&baseline, RELEASE_GRACE).err();
+            cycles.push(Cycle { setup, teardown, unreleased });
+        }
+        let result = CycleResult { mode, cycles };
+        if let Some(unreleased) = result.cycles.last().and_then(|cycle| cycle.unreleased.as_ref()) {
+            println!("   ⚠️  After the last cycle the {}", unreleased);
+        }
+        results.push(result);
+    }
+
+    let short = |duration: Duration| format!("{:.1?}", duration);
+    println!("\n📊 Cost per cycle ({}):", std::env::consts::OS);
+    println!(
+        "  {:<20} {:>10} {:>10} {:>10} {:>8} {:>12} {:>8} {:>10} {:>8}",
+        "Mode", "First", "Median", "Last", "Warm-up", "Slope/cycle", "Trend", "Teardown", "Leaking"
+    );
+    for result in &results {
+        let setups: Vec<Duration> = result.cycles.iter().map(|cycle| cycle.setup).collect();
+        let teardowns: Vec<Duration> = result.cycles.iter().map(|cycle| cycle.teardown).collect();
+        let (Some(setup), Some(teardown)) =
+            (DurationSummary::from_durations(setups.clone()), DurationSummary::from_durations(teardowns))
+        else {
+            continue;
+        };
+        let trend = result.trend();
+        let verdict = match trend {
+            Some(trend) if trend.is_significant_growth() => "growing",
+            Some(_) => "flat",
+            None => "-",
+        };
+        println!(
+            "  {:<20} {:>10} {:>10} {:>10} {:>8} {:>12} {:>8} {:>10} {:>8}",
+            result.mode.display_name(),
+            short(setups[0]),
+            short(setup.p50),
+            short(setups[setups.len() - 1]),
+            result.warmup_ratio().map_or("-".to_string(), |ratio| format!(
+                "{:.2}x{}",
+                ratio,
+                if ratio > WARMUP_RATIO { "*" } else { "" }
+            )),
+            trend.map_or("-".to_string(), |trend| format!("{:+.1}µs", trend.slope)),
+            verdict,
+            short(teardown.p50),
+            result.leaking_cycles()
+        );
+    }
+    println!("  * first cycle over {:.1}x the median: a warm-up effect", WARMUP_RATIO);
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cycle(setup_ms: u64) -> Cycle {
+        Cycle {
+            setup: Duration::from_millis(setup_ms),
+            teardown: Duration::ZERO,
+            unreleased: None,
+        }
+    }
+
+    #[test]
+    fn test_warmup_and_trend() {
+        let flat = CycleResult {
+            mode: WatcherMode::Manual,
+            cycles: [30, 10, 11, 10, 11, 10].map(cycle).to_vec(),
+        };
+        assert!((flat.warmup_ratio().unwrap() - 3.0).abs() < 1e-9);
+        assert!(!flat.trend().unwrap().is_significant_growth());
+
+        let mut growing = CycleResult {
+            mode: WatcherMode::Native,
+            cycles: (10..16).map(cycle).collect(),
+        };
+        assert!(growing.trend().unwrap().is_significant_growth());
+        assert_eq!(growing.leaking_cycles(), 0);
+        growing.cycles[5].unreleased = Some("1 fds".to_string());
+        assert_eq!(growing.leaking_cycles(), 1);
+    }
+}
@@ -0,0 +1,187 @@
+use crate::recursive_file_watcher::{is_excluded, WatcherMode};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+use std::path::{Component, PathBuf};
+
+/// Files beyond which the graph gets too big for Graphviz to lay out usefully
+pub const DOT_MAX_FILES: usize = 2000;
+
+/// How a watcher covers one file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Marker {
+    /// Has a watch of its own, or sits below a recursive watch that reports it
+    Watched,
+    /// Below a recursive watch whose callback lets its events through the filter
+    Filtered,
+    /// Changes to it never reach the consumer
+    Unwatched,
+}
+
+impl Marker {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Watched => "watched",
+            Self::Filtered => "filtered",
+            Self::Unwatched => "unwatched",
+        }
+    }
+
+    fn style(&self) -> &'static str {
+        match self {
+            Self::Watched => r##"style=filled, fillcolor="#b7e4b0""##,
+            Self::Filtered => r##"style=filled, fillcolor="#a9cbe8""##,
+            Self::Unwatched => r##"style=dashed, color="#999999", fontcolor="#999999""##,
+        }
+    }
+}
+
+/// Mark each of `files` by how `mode` covers it
+///
+/// `covered` is what the watcher ended up with: the files holding a manual watch,
+/// or the files a filtered native watcher lets through. Excluded files are never covered.
+pub fn mark_files(mode: WatcherMode, files: &[PathBuf], covered: &[PathBuf], exclude: &[String]) -> Vec<(PathBuf, Marker)> {
+    let covered: HashSet<&PathBuf> = covered.iter().collect();
+    files
+        .iter()
+        .map(|file| {
+            let marker = if is_excluded(file, exclude) {
+                Marker::Unwatched
+            } else {
+                match mode {
+                    WatcherMode::Native | WatcherMode::Poll | WatcherMode::Debounced => Marker::Watched,
+                    WatcherMode::Manual | WatcherMode::ManualFiltered if covered.contains(file) => Marker::Watched,
+                    WatcherMode::NativeFiltered if covered.contains(file) => Marker::Filtered,
+                    _ => Marker::Unwatched,
+                }
+            };
+            (file.clone(), marker)
+        })
+        .collect()
+}
+
+/// One directory of the graph: the files right in it and its subdirectories
+#[derive(Debug, Default)]
+struct DirNode {
+    files: Vec<(String, Marker)>,
+    dirs: BTreeMap<String, DirNode>,
+}
+
+impl DirNode {
+    /// Covered and total files in this subtree
+    fn coverage(&self) -> (usize, usize) {
+        let own = self.files.iter().filter(|(_, marker)| *marker != Marker::Unwatched).count();
+        self.dirs.values().map(DirNode::coverage).fold((own, self.files.len()), |(c, t), (dc, dt)| (c + dc, t + dt))
+    }
+}
+
+/// `text` escaped for use inside a quoted Graphviz string
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Graphviz document of the tree below `roots` with every file coloured by its marker
+///
+/// Directories are labelled with how many files below them are covered, so a
+/// filtered mode's gaps show up without expanding every file.
+pub fn render_dot(roots: &[PathBuf], files: &[(PathBuf, Marker)], title: &str) -> String {
+    let mut trees: Vec<(String, DirNode)> = roots.iter().map(|root| (root.display().to_string(), DirNode::default())).collect();
+    for (file, marker) in files {
+        let Some((relative, tree)) = roots
+            .iter()
+            .zip(trees.iter_mut())
+            .find_map(|(root, (_, tree))| Some((file.strip_prefix(root).ok()?, tree)))
+        else {
+            continue;
+        };
+        let names: Vec<String> = relative
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect();
+        let Some((name, dirs)) = names.split_last() else {
+            continue;
+        };
+        let node = dirs.iter().fold(tree, |node, dir| node.dirs.entry(dir.clone()).or_default());
+        node.files.push((name.clone(), *marker));
+    }
+
+    let mut dot = String::new();
+    let _ = writeln!(dot, "digraph watched_tree {{");
+    let _ = writeln!(dot, "  label=\"{}\";", escape(title));
+    let _ = writeln!(dot, "  labelloc=t;");
+    let _ = writeln!(dot, "  rankdir=LR;");
+    let _ = writeln!(dot, "  node [fontname=\"sans-serif\", fontsize=10];");
+    let mut next_id = 0;
+    for (root, tree) in &trees {
+        write_dir(&mut dot, root, tree, None, &mut next_id);
+    }
+    let _ = writeln!(dot, "  subgraph cluster_legend {{");
+    let _ = writeln!(dot, "    label=\"Legend\";");
+    for marker in [Marker::Watched, Marker::Filtered, Marker::Unwatched] {
+        let _ = writeln!(dot, "    legend_{0} [label={0}, shape=note, {1}];", marker.display_name(), marker.style());
+    }
+    let _ = writeln!(dot, "  }}");
+    let _ = writeln!(dot, "}}");
+    dot
+}
+
+fn write_dir(dot: &mut String, name: &str, node: &DirNode, parent: Option<usize>, next_id: &mut usize) {
+    let id = *next_id;
+    *next_id += 1;
+    let (covered, total) = node.coverage();
+    let _ = writeln!(
+        dot,
+        "  n{} [label=\"{}\\n{}/{} covered\", shape=folder];",
+        id,
+        escape(name),
+        covered,
+        total
+    );
+    if let Some(parent) = parent {
+        let _ = writeln!(dot, "  n{} -> n{};", parent, id);
+    }
+    for (dir_name, child) in &node.dirs {
+        write_dir(dot, &format!("{}/", dir_name), child, Some(id), next_id);
+    }
+    for (file_name, marker) in &node.files {
+        let file_id = *next_id;
+        *next_id += 1;
+        let _ = writeln!(dot, "  n{} [label=\"{}\", shape=note, {}];", file_id, escape(file_name), marker.style());
+        let _ = writeln!(dot, "  n{} -> n{};", id, file_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mark_files() {
+        let files: Vec<PathBuf> = ["r/a.ts", "r/b.ts", "r/node_modules/c.js"].iter().map(PathBuf::from).collect();
+        let covered = [files[0].clone(), files[2].clone()];
+        let exclude = ["node_modules".to_string()];
+        let markers = |mode| -> Vec<Marker> {
+            mark_files(mode, &files, &covered, &exclude).into_iter().map(|(_, marker)| marker).collect()
+        };
+        assert_eq!(markers(WatcherMode::Native), [Marker::Watched, Marker::Watched, Marker::Unwatched]);
+        assert_eq!(markers(WatcherMode::ManualFiltered), [Marker::Watched, Marker::Unwatched, Marker::Unwatched]);
+        assert_eq!(markers(WatcherMode::NativeFiltered), [Marker::Filtered, Marker::Unwatched, Marker::Unwatched]);
+    }
+
+    #[test]
+    fn test_render_dot() {
+        let files = [
+            (PathBuf::from("/r/src/a.ts"), Marker::Filtered),
+            (PathBuf::from("/r/src/b.ts"), Marker::Unwatched),
+            (PathBuf::from("/r/say \"hi\".txt"), Marker::Unwatched),
+        ];
+        let dot = render_dot(&[PathBuf::from("/r")], &files, "Native Filtered");
+        assert!(dot.starts_with("digraph watched_tree {\n") && dot.ends_with("}\n"));
+        assert!(dot.contains(r#"n0 [label="/r\n1/3 covered", shape=folder];"#));
+        assert!(dot.contains(r#"n1 [label="src/\n1/2 covered", shape=folder];"#));
+        assert!(dot.contains(r#"[label="say \"hi\".txt", shape=note"#));
+        assert_eq!(dot.matches("-> ").count(), 4);
+    }
+}
@@ -0,0 +1,223 @@
+use crate::limits::{FdLimit, InotifyLimits};
+use crate::options::Options;
+use crate::recursive_file_watcher::WatcherMode;
+use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Watches registered to measure what one costs on this machine
+const CALIBRATION_WATCHES: usize = 64;
+
+/// Estimated setup time beyond which a run asks before going ahead
+pub const DEFAULT_MAX_SETUP: Duration = Duration::from_secs(60);
+
+/// Expected watch count and setup time for one watcher on one tree
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SetupEstimate {
+    /// OS-level watches the mode will register
+    pub watches: usize,
+    /// Measured cost of registering one of them
+    pub per_watch: Duration,
+    /// The limit those watches count against, by name
+    pub limit: Option<(&'static str, usize)>,
+}
+
+impl SetupEstimate {
+    pub fn setup_time(&self) -> Duration {
+        Duration::from_secs_f64(self.per_watch.as_secs_f64() * self.watches as f64)
+    }
+
+    /// Everything worth stopping for before setup starts
+    pub fn warnings(&self, max_setup: Duration) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if let Some((name, limit)) = self.limit.filter(|&(_, limit)| self.watches > limit) {
+            warnings.push(format!("{} watches needed but {} is {}", self.watches, name, limit));
+        }
+        if self.setup_time() > max_setup {
+            warnings.push(format!(
+                "setup is estimated at {:.1?}, over --max-setup {:?}",
+                self.setup_time(),
+                max_setup
+            ));
+        }
+        warnings
+    }
+}
+
+/// Watches `mode` registers for `individual` files over `dirs` directories below `roots` roots
+///
+/// Manual modes watch each file; inotify's recursive mode needs a watch per
+/// directory, while FSEvents and ReadDirectoryChangesW get by with one per root.
+/// Polling registers nothing but stats every file on its first scan.
+pub fn watches_needed(mode: WatcherMode, individual: usize, dirs: usize, roots: usize) -> usize {
+    match mode {
+        WatcherMode::Manual | WatcherMode::ManualFiltered | WatcherMode::Poll => individual,
+        WatcherMode::Native | WatcherMode::NativeFiltered | WatcherMode::Debounced => {
+            if cfg!(target_os = "linux") {
+                dirs
+            } else {
+                roots
+            }
+        }
+    }
+}
+
+/// The OS limit `mode`'s watches count against here, if any
+fn watch_limit(mode: WatcherMode) -> Option<(&'static str, usize)> {
+    if mode == WatcherMode::Poll {
+        return None;
+    }
+    if let Some(limits) = InotifyLimits::read() {
+        return Some(("fs.inotify.max_user_watches", limits.max_user_watches));
+    }
+    // kqueue holds a descriptor per watched file; FSEvents doesn't
+    let per_file = matches!(mode, WatcherMode::Manual | WatcherMode::ManualFiltered);
+    if per_file && cfg!(all(unix, not(target_os = "macos"))) {
+        return FdLimit::read().map(|limit| ("RLIMIT_NOFILE", limit.soft as usize));
+    }
+    None
+}
+
+/// Every directory holding one of `files`, up to and including the root it's under
+///
+/// Taken from an enumeration already made, so directories without files aren't
+/// counted; a recursive watch costs them too, but they are rarely many.
+fn dirs_of(roots: &[PathBuf], files: &[PathBuf]) -> Vec<PathBuf> {
+    let mut seen: HashSet<&Path> = roots.iter().map(PathBuf::as_path).collect();
+    let mut dirs = roots.to_vec();
+    for file in files {
+        for dir in file.ancestors().skip(1) {
+            // Ancestors of a directory already seen have been seen too
+            if !seen.insert(dir) {
+                break;
+            }
+            dirs.push(dir.to_path_buf());
+        }
+    }
+    dirs
+}
+
+/// Mean time to register a non-recursive watch on a spread sample of `paths`
+///
+/// Polling is calibrated with a `stat` per path instead, since that is all its setup does.
+fn calibrate(mode: WatcherMode, paths: &[PathBuf]) -> Duration {
+    let step = (paths.len() / CALIBRATION_WATCHES).max(1);
+    let sample: Vec<&PathBuf> = paths.iter().step_by(step).take(CALIBRATION_WATCHES).collect();
+    if sample.is_empty() {
+        return Duration::ZERO;
+    }
+    let start = Instant::now();
+    if mode == WatcherMode::Poll {
+        for path in &sample {
+            let _ = fs::metadata(path);
+        }
+    } else {
+        let Ok(mut watcher) = RecommendedWatcher::new(|_| {}, Config::default()) else {
+            return Duration::ZERO;
+        };
+        for path in &sample {
+            let _ = watcher.watch(path, RecursiveMode::NonRecursive);
+        }
+    }
+    start.elapsed() / sample.len() as u32
+}
+
+/// Estimate setup for `mode` on `roots` and warn before it hits an OS limit or `--max-setup`
+///
+/// `files` is what the mode would watch one by one, and `enumerated` every file
+/// found below `roots`, which the directory count for recursive inotify watches
+/// is taken from. With a terminal attached and no `--yes` the user is asked
+/// whether to go ahead, and declining fails the run; otherwise the warnings are
+/// printed and setup proceeds.
+pub fn preflight_setup(
+    roots: &[PathBuf],
+    mode: WatcherMode,
+    files: &[PathBuf],
+    enumerated: &[PathBuf],
+    options: &Options,
+    indent: &str,
+) -> Result<SetupEstimate, String> {
+    let individual = matches!(mode, WatcherMode::Manual | WatcherMode::ManualFiltered | WatcherMode::Poll);
+    let per_dir = cfg!(target_os = "linux") && !individual;
+    let dirs = if per_dir { dirs_of(roots, enumerated) } else { Vec::new() };
+    let sample = if individual {
+        files
+    } else if per_dir {
+        &dirs
+    } else {
+        roots
+    };
+    let estimate = SetupEstimate {
+        watches: watches_needed(mode, files.len(), dirs.len(), roots.len()),
+        per_watch: calibrate(mode, sample),
+        limit: watch_limit(mode),
+    };
+    println!(
+        "{}Setup estimate: {} watches × {:?} ≈ {:.1?}",
+        indent,
+        estimate.watches,
+        estimate.per_watch,
+        estimate.setup_time()
+    );
+
+    let warnings = estimate.warnings(options.max_setup);
+    if warnings.is_empty() {
+        return Ok(estimate);
+    }
+    for warning in &warnings {
+        eprintln!("{}⚠️  {} {}", indent, mode.display_name(), warning);
+    }
+    if options.assume_yes || !io::stdin().is_terminal() {
+        return Ok(estimate);
+    }
+    eprint!("{}   Continue anyway? [y/N] ", indent);
+    let _ = io::stderr().flush();
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_ok() && matches!(answer.trim(), "y" | "Y" | "yes") {
+        Ok(estimate)
+    } else {
+        Err(format!("{} setup cancelled: {}", mode.display_name(), warnings.join("; ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watches_needed() {
+        assert_eq!(watches_needed(WatcherMode::Manual, 5000, 300, 2), 5000);
+        assert_eq!(watches_needed(WatcherMode::Poll, 5000, 300, 2), 5000);
+        let native = watches_needed(WatcherMode::Native, 5000, 300, 2);
+        assert_eq!(native, if cfg!(target_os = "linux") { 300 } else { 2 });
+    }
+
+    #[test]
+    fn test_dirs_of() {
+        let roots = [PathBuf::from("/a"), PathBuf::from("/b")];
+        let files: Vec<PathBuf> = ["/a/x/1", "/a/x/y/2", "/a/x/3", "/a/4", "/b/z/5"].iter().map(PathBuf::from).collect();
+        let dirs: HashSet<PathBuf> = dirs_of(&roots, &files).into_iter().collect();
+        let expected: HashSet<PathBuf> = ["/a", "/b", "/a/x", "/a/x/y", "/b/z"].iter().map(PathBuf::from).collect();
+        assert_eq!(dirs, expected);
+    }
+
+    #[test]
+    fn test_estimate_warnings() {
+        let estimate = SetupEstimate {
+            watches: 200_000,
+            per_watch: Duration::from_micros(500),
+            limit: Some(("fs.inotify.max_user_watches", 65_536)),
+        };
+        assert_eq!(estimate.setup_time(), Duration::from_secs(100));
+        let warnings = estimate.warnings(Duration::from_secs(60));
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("max_user_watches is 65536"));
+        assert_eq!(estimate.warnings(Duration::from_secs(120)).len(), 1);
+
+        let small = SetupEstimate { watches: 100, ..estimate };
+        assert!(small.warnings(DEFAULT_MAX_SETUP).is_empty());
+    }
+}
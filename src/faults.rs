@@ -0,0 +1,178 @@
+use crate::order::{splitmix64, SPLITMIX_GAMMA};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Longest delay an injected delay fault holds an event back by default
+pub const DEFAULT_FAULT_DELAY: Duration = Duration::from_millis(20);
+
+/// Message of the errors injected in place of events
+pub const INJECTED_ERROR: &str = "injected fault";
+
+/// Faults injected between notify's callback and the consumer, each a probability per event
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FaultPlan {
+    /// Events discarded before they reach the channel
+    pub drop: f64,
+    /// Events replaced by a generic watch error, which a supervisor treats as fatal
+    pub error: f64,
+    /// Events held back on notify's thread for up to `max_delay`
+    pub delay: f64,
+    pub max_delay: Duration,
+    /// Seeds the per-event draws, so the same event sequence gets the same faults
+    pub seed: u64,
+}
+
+impl Default for FaultPlan {
+    fn default() -> Self {
+        Self {
+            drop: 0.0,
+            error: 0.0,
+            delay: 0.0,
+            max_delay: DEFAULT_FAULT_DELAY,
+            seed: 0,
+        }
+    }
+}
+
+impl FaultPlan {
+    /// Parse a spec like `drop=0.01,error=0.001,delay=0.05`; faults not named stay off
+    pub fn from_str(spec: &str) -> Option<Self> {
+        let mut plan = Self::default();
+        for part in spec.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+            let (name, rate) = part.split_once('=')?;
+            let rate: f64 = rate.trim().parse().ok().filter(|rate| (0.0..=1.0).contains(rate))?;
+            match name.trim() {
+                "drop" => plan.drop = rate,
+                "error" => plan.error = rate,
+                "delay" => plan.delay = rate,
+                _ => return None,
+            }
+        }
+        // One draw decides each event's fault, so the rates have to fit in it together
+        (plan.drop + plan.error + plan.delay <= 1.0).then_some(plan)
+    }
+}
+
+/// What happens to one event on its way to the consumer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    Drop,
+    Error,
+    Delay(Duration),
+}
+
+/// Seeded source of faults, shared by every call of a watcher's callback
+pub struct FaultInjector {
+    plan: FaultPlan,
+    state: AtomicU64,
+}
+
+impl FaultInjector {
+    pub fn new(plan: FaultPlan) -> Self {
+        Self {
+            plan,
+            state: AtomicU64::new(plan.seed),
+        }
+    }
+
+    /// Uniform draw in `[0, 1)`; SplitMix64 only adds to its state, so this needs no lock
+    fn next_unit(&self) -> f64 {
+        let state = self.state.fetch_add(SPLITMIX_GAMMA, Ordering::Relaxed).wrapping_add(SPLITMIX_GAMMA);
+        (splitmix64(state) >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Fault for the next event, if it gets one
+    pub fn draw(&self) -> Option<Fault> {
+        let plan = &self.plan;
+        let draw = self.next_unit();
+        if draw < plan.drop {
+            Some(Fault::Drop)
+        } else if draw < plan.drop + plan.error {
+            Some(Fault::Error)
+        } else if draw < plan.drop + plan.error + plan.delay {
+            Some(Fault::Delay(plan.max_delay.mul_f64(self.next_unit())))
+        } else {
+            None
+        }
+    }
+}
+
+/// Faults injected into a watcher's events
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct InjectedFaults {
+    pub dropped: u64,
+    pub errors: u64,
+    pub delayed: u64,
+    /// Time delayed events spent held back, together
+    pub delay_time: Duration,
+}
+
+impl InjectedFaults {
+    /// Faults injected after `earlier` was taken
+    pub fn since(&self, earlier: &InjectedFaults) -> Self {
+        Self {
+            dropped: self.dropped.saturating_sub(earlier.dropped),
+            errors: self.errors.saturating_sub(earlier.errors),
+            delayed: self.delayed.saturating_sub(earlier.delayed),
+            delay_time: self.delay_time.saturating_sub(earlier.delay_time),
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.dropped + self.errors + self.delayed
+    }
+
+    /// Print the injected faults, if there were any
+    pub fn report(&self, indent: &str) {
+        if self.total() == 0 {
+            return;
+        }
+        println!(
+            "{}Injected faults: {} dropped, {} errors, {} delayed ({:.1?} held back)",
+            indent, self.dropped, self.errors, self.delayed, self.delay_time
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fault_plan_parse() {
+        let plan = FaultPlan::from_str("drop=0.1, delay=0.25").unwrap();
+        assert_eq!((plan.drop, plan.error, plan.delay), (0.1, 0.0, 0.25));
+        assert_eq!(FaultPlan::from_str(""), Some(FaultPlan::default()));
+        assert!(FaultPlan::from_str("drop=1.5").is_none());
+        assert!(FaultPlan::from_str("drop=0.6,error=0.6").is_none());
+        assert!(FaultPlan::from_str("corrupt=0.1").is_none());
+        assert!(FaultPlan::from_str("drop").is_none());
+    }
+
+    #[test]
+    fn test_injector_is_seeded() {
+        let plan = FaultPlan {
+            drop: 0.2,
+            error: 0.1,
+            delay: 0.3,
+            seed: 7,
+            ..FaultPlan::default()
+        };
+        let draws = |plan: FaultPlan| {
+            let injector = FaultInjector::new(plan);
+            (0..1000).map(|_| injector.draw()).collect::<Vec<_>>()
+        };
+        let first = draws(plan);
+        assert_eq!(first, draws(plan));
+        assert_ne!(first, draws(FaultPlan { seed: 8, ..plan }));
+
+        let count = |wanted: fn(&Fault) -> bool| first.iter().flatten().filter(|fault| wanted(fault)).count();
+        assert!((150..250).contains(&count(|fault| *fault == Fault::Drop)));
+        assert!((60..140).contains(&count(|fault| *fault == Fault::Error)));
+        assert!((250..350).contains(&count(|fault| matches!(fault, Fault::Delay(_)))));
+        assert!(first.iter().flatten().all(|fault| match fault {
+            Fault::Delay(delay) => *delay < DEFAULT_FAULT_DELAY,
+            _ => true,
+        }));
+    }
+}
@@ -0,0 +1,477 @@
+use crate::harness::enumerate_files;
+use crate::heap::{require_counters, AllocWindow};
+use crate::options::Options;
+use crate::recursive_file_watcher::{filter_entry, FilterSet};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Filter-set sizes `filter-memory` measures when `--filter-sizes` isn't given
+pub const DEFAULT_FILTER_SIZES: [usize; 5] = [1_000, 10_000, 100_000, 500_000, 1_000_000];
+
+/// Paths per front-coded block; each block starts with one path stored whole
+const FRONT_CODED_BLOCK: usize = 16;
+
+/// Membership test the filtered watcher runs against every event path
+pub trait PathFilter {
+    fn contains(&self, path: &Path) -> bool;
+}
+
+impl PathFilter for HashSet<PathBuf> {
+    fn contains(&self, path: &Path) -> bool {
+        HashSet::contains(self, path)
+    }
+}
+
+impl PathFilter for HashSet<Box<Path>> {
+    fn contains(&self, path: &Path) -> bool {
+        HashSet::contains(self, path)
+    }
+}
+
+/// Paths sorted for binary search, without a hash table's empty slots
+pub struct SortedPaths(Vec<Box<Path>>);
+
+impl PathFilter for SortedPaths {
+    fn contains(&self, path: &Path) -> bool {
+        self.0.binary_search_by(|probe| probe.as_ref().cmp(path)).is_ok()
+    }
+}
+
+impl PathFilter for FilterSet {
+    fn contains(&self, path: &Path) -> bool {
+        FilterSet::contains(self, path)
+    }
+}
+
+/// Sorted paths in one buffer, each storing only what differs from the one before
+///
+/// Every `FRONT_CODED_BLOCK`th path is stored whole so lookups can binary-search
+/// the block heads and decode a single block.
+pub struct FrontCoded {
+    bytes: Vec<u8>,
+    /// Offset of each block's head in `bytes`
+    blocks: Vec<usize>,
+}
+
+impl FrontCoded {
+    pub fn new<'a>(keys: impl IntoIterator<Item = &'a [u8]>) -> Self {
+        let mut keys: Vec<&[u8]> = keys.into_iter().collect();
+        keys.sort_unstable();
+        keys.dedup();
+        let mut bytes = Vec::new();
+        let mut blocks = Vec::with_capacity(keys.len().div_ceil(FRONT_CODED_BLOCK));
+        let mut previous: &[u8] = &[];
+        for (i, key) in keys.into_iter().enumerate() {
+            if i % FRONT_CODED_BLOCK == 0 {
+                blocks.push(bytes.len());
+                push_varint(&mut bytes, key.len());
+                bytes.extend_from_slice(key);
+            } else {
+                let shared = previous.iter().zip(key).take_while(|(a, b)| a == b).count();
+                push_varint(&mut bytes, shared);
+                push_varint(&mut bytes, key.len() - shared);
+                bytes.extend_from_slice(&key[shared..]);
+            }
+            previous = key;
+        }
+        bytes.shrink_to_fit();
+        Self { bytes, blocks }
+    }
+
+    fn head(&self, block: usize) -> (&[u8], usize) {
+        let mut pos = self.blocks[block];
+        let len = read_varint(&self.bytes, &mut pos);
+        (&self.bytes[pos..pos + len], pos + len)
+    }
+
+    pub fn contains_bytes(&self, key: &[u8]) -> bool {
+        // Last block whose head sorts at or before `key`
+        let (mut low, mut high) = (0, self.blocks.len());
+        while low < high {
+            let mid = (low + high) / 2;
+            if self.head(mid).0 <= key {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        let Some(block) = low.checked_sub(1) else {
+            return false;
+        };
+        let (head, mut pos) = self.head(block);
+        let end = self.blocks.get(block + 1).copied().unwrap_or(self.bytes.len());
+        let mut current = head.to_vec();
+        while current.as_slice() < key && pos < end {
+            let shared = read_varint(&self.bytes, &mut pos);
+            let suffix = read_varint(&self.bytes, &mut pos);
+            current.truncate(shared);
+            current.extend_from_slice(&self.bytes[pos..pos + suffix]);
+            pos += suffix;
+        }
+        current == key
+    }
+}
+
+impl PathFilter for FrontCoded {
+    fn contains(&self, path: &Path) -> bool {
+        self.contains_bytes(path.as_os_str().as_encoded_bytes())
+    }
+}
+
+fn push_varint(bytes: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn read_varint(bytes: &[u8], pos: &mut usize) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = bytes[*pos];
+        *pos += 1;
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte < 0x80 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+/// 64-bit hashes of the paths only; a foreign path matches with probability about n/2^64
+pub struct HashedPaths(HashSet<u64>);
+
+fn path_hash(path: &Path) -> u64 {
+    // DefaultHasher::new uses fixed keys, so the same path always hashes the same
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl PathFilter for HashedPaths {
+    fn contains(&self, path: &Path) -> bool {
+        self.0.contains(&path_hash(path))
+    }
+}
+
+/// Ways to hold the filtered watcher's set of watched files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterContainer {
+    /// `HashSet<PathBuf>`, one full path per file
+    PathBufSet,
+    /// `HashSet<Box<Path>>`, dropping each buffer's spare capacity
+    BoxedSet,
+    /// Sorted `Vec<Box<Path>>` searched by bisection
+    Sorted,
+    /// Absolute directory path to its file names
+    PerDirectory,
+    /// Root-relative directory path to its file names, the `FilterSet` `NativeFiltered` uses
+    RootRelative,
+    /// Front-coded sorted paths in one buffer
+    FrontCoded,
+    /// Path hashes only, trading exactness for size
+    Hashes,
+}
+
+impl FilterContainer {
+    pub const ALL: [Self; 7] = [
+        Self::PathBufSet,
+        Self::BoxedSet,
+        Self::Sorted,
+        Self::PerDirectory,
+        Self::RootRelative,
+        Self::FrontCoded,
+        Self::Hashes,
+    ];
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::PathBufSet => "hashset-pathbuf",
+            Self::BoxedSet => "hashset-boxed",
+            Self::Sorted => "sorted-boxed",
+            Self::PerDirectory => "per-directory",
+            Self::RootRelative => "root-relative",
+            Self::FrontCoded => "front-coded",
+            Self::Hashes => "hashes-u64",
+        }
+    }
+
+    /// Build this container over `paths` under `roots`, copying them as the filtered watcher does
+    pub fn build(&self, roots: &[PathBuf], paths: &[PathBuf]) -> Box<dyn PathFilter> {
+        match self {
+            Self::PathBufSet => Box::new(paths.iter().cloned().collect::<HashSet<PathBuf>>()),
+            Self::BoxedSet => Box::new(paths.iter().map(|p| Box::from(p.as_path())).collect::<HashSet<Box<Path>>>()),
+            Self::Sorted => {
+                let mut sorted: Vec<Box<Path>> = paths.iter().map(|p| Box::from(p.as_path())).collect();
+                sorted.sort_unstable();
+                sorted.dedup();
+                sorted.shrink_to_fit();
+                Box::new(SortedPaths(sorted))
+            },
+            Self::PerDirectory | Self::RootRelative => {
+                let roots = if *self == Self::RootRelative { roots } else { &[] };
+                let mut set = FilterSet::new(roots);
+                set.extend(paths);
+                Box::new(set)
+            },
+            Self::FrontCoded => Box::new(FrontCoded::new(paths.iter().map(|p| p.as_os_str().as_encoded_bytes()))),
+            Self::Hashes => Box::new(HashedPaths(paths.iter().map(|p| path_hash(p)).collect())),
+        }
+    }
+}
+
+/// One container's cost at one filter size
+#[derive(Debug, Clone, PartialEq)]
+pub struct Footprint {
+    pub container: FilterContainer,
+    pub paths: usize,
+    /// Heap bytes the built container holds
+    pub heap_bytes: usize,
+    /// Most heap bytes live at once while building, temporaries included
+    pub peak_bytes: usize,
+    pub allocations: u64,
+    pub build: Duration,
+    /// Mean time to look up one watched path
+    pub lookup: Duration,
+}
+
+impl Footprint {
+    pub fn bytes_per_path(&self) -> f64 {
+        self.heap_bytes as f64 / self.paths.max(1) as f64
+    }
+}
+
+/// Build `container` over `paths`, reading its heap use from the counting allocator,
+/// then look every path up again
+pub fn measure(container: FilterContainer, roots: &[PathBuf], paths: &[PathBuf]) -> Result<Footprint, String> {
+    let window = AllocWindow::start();
+    let start = Instant::now();
+    let filter = container.build(roots, paths);
+    let build = start.elapsed();
+    let allocs = window.finish();
+
+    let start = Instant::now();
+    let found = paths.iter().filter(|path| filter.contains(path)).count();
+    let lookup = start.elapsed() / paths.len().max(1) as u32;
+    if found != paths.len() {
+        return Err(format!(
+            "{} found {} of its {} paths",
+            container.display_name(),
+            found,
+            paths.len()
+        ));
+    }
+    drop(filter);
+    Ok(Footprint {
+        container,
+        paths: paths.len(),
+        heap_bytes: allocs.retained_bytes,
+        peak_bytes: allocs.peak_bytes,
+        allocations: allocs.allocations,
+        build,
+        lookup,
+    })
+}
+
+/// What a filtered watcher holds for its filter, counting the copy its event callback needs
+#[derive(Debug, Clone, PartialEq)]
+pub struct SharedFilter {
+    pub layout: &'static str,
+    pub paths: usize,
+    /// Heap bytes of the watcher's filter and the callback's together
+    pub heap_bytes: usize,
+    /// Time to give the callback its copy
+    pub clone: Duration,
+}
+
+fn held<T>(layout: &'static str, paths: usize, build: impl FnOnce() -> T, share: impl FnOnce(&T) -> T) -> SharedFilter {
+    let window = AllocWindow::start();
+    let filter = build();
+    let start = Instant::now();
+    let copy = share(&filter);
+    let clone = start.elapsed();
+    let heap_bytes = window.finish().retained_bytes;
+    drop((filter, copy));
+    SharedFilter {
+        layout,
+        paths,
+        heap_bytes,
+        clone,
+    }
+}
+
+/// The filter kept as a `HashSet<PathBuf>` cloned into the callback, against one
+/// `FilterSet` shared through an `Arc`
+pub fn measure_sharing(roots: &[PathBuf], paths: &[PathBuf]) -> [SharedFilter; 2] {
+    [
+        held(
+            "pathbuf-clone",
+            paths.len(),
+            || paths.iter().cloned().collect::<HashSet<PathBuf>>(),
+            HashSet::clone,
+        ),
+        held(
+            "shared-filterset",
+            paths.len(),
+            || {
+                let mut set = FilterSet::new(roots);
+                set.extend(paths);
+                Arc::new(set)
+            },
+            Arc::clone,
+        ),
+    ]
+}
+
+/// Everything `filter-memory` measured
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FilterMemory {
+    pub footprints: Vec<Footprint>,
+    pub sharing: Vec<SharedFilter>,
+}
+
+/// `count` filter entries: the tree's own files, then copies with a `.N` suffix once those run out
+///
+/// Copies keep the tree's directory shape and path lengths, so a small tree still
+/// shows what a million-file filter would cost.
+pub fn filter_paths(files: &[PathBuf], count: usize) -> Vec<PathBuf> {
+    (0..count)
+        .map(|i| {
+            let file = &files[i % files.len()];
+            match i / files.len() {
+                0 => file.clone(),
+                copy => {
+                    let mut name = file.file_name().unwrap_or_default().to_os_string();
+                    name.push(format!(".{}", copy));
+                    file.with_file_name(name)
+                },
+            }
+        })
+        .collect()
+}
+
+/// `12.3 MiB`-style size for display
+pub fn format_bytes(bytes: usize) -> String {
+    let bytes = bytes as f64;
+    if bytes >= 1024.0 * 1024.0 {
+        format!("{:.1} MiB", bytes / (1024.0 * 1024.0))
+    } else if bytes >= 1024.0 {
+        format!("{:.1} KiB", bytes / 1024.0)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+/// Measure each filter container's heap footprint at growing filter sizes
+///
+/// Entries are built the way `NativeFiltered` builds them, absolute and keyed for
+/// lookup, so the `hashset-pathbuf` row is what that mode holds for a tree this size.
+pub fn run_filter_memory(roots: &[PathBuf], options: &Options) -> Result<FilterMemory, Box<dyn std::error::Error>> {
+    println!("\n=== Filter Set Memory ===");
+    require_counters("filter-memory")?;
+    let files: Vec<PathBuf> = enumerate_files(roots, options)?
+        .iter()
+        .map(|path| filter_entry(path))
+        .collect();
+    if files.is_empty() {
+        return Err("no files to build a filter from".into());
+    }
+    let sizes = if options.filter_sizes.is_empty() {
+        DEFAULT_FILTER_SIZES.to_vec()
+    } else {
+        options.filter_sizes.clone()
+    };
+    let raw: usize = files.iter().map(|path| path.as_os_str().len()).sum();
+    println!(
+        "{} files, {:.0} bytes of path per file; sizes past the tree reuse its paths with a suffix",
+        files.len(),
+        raw as f64 / files.len() as f64
+    );
+
+    let mut results = FilterMemory::default();
+    for &size in &sizes {
+        let paths = filter_paths(&files, size);
+        println!("\n📊 {} paths:", size);
+        println!(
+            "  {:<16} {:>11} {:>10} {:>10} {:>11} {:>10} {:>10} {:>9}",
+            "Container", "Heap", "Per path", "vs PathBuf", "Peak", "Allocs", "Build", "Lookup"
+        );
+        let mut baseline = None;
+        for container in FilterContainer::ALL {
+            let footprint = measure(container, roots, &paths)?;
+            let baseline = *baseline.get_or_insert(footprint.heap_bytes);
+            println!(
+                "  {:<16} {:>11} {:>9.1}B {:>9.2}x {:>11} {:>10} {:>10} {:>9}",
+                container.display_name(),
+                format_bytes(footprint.heap_bytes),
+                footprint.bytes_per_path(),
+                footprint.heap_bytes as f64 / baseline.max(1) as f64,
+                format_bytes(footprint.peak_bytes),
+                footprint.allocations,
+                format!("{:.1?}", footprint.build),
+                format!("{:.0?}", footprint.lookup)
+            );
+            results.footprints.push(footprint);
+        }
+
+        let [before, after] = measure_sharing(roots, &paths);
+        println!(
+            "  Watcher plus callback: {} {} (clone {:.1?}) → {} {} (clone {:.1?}), {:.2}x",
+            before.layout,
+            format_bytes(before.heap_bytes),
+            before.clone,
+            after.layout,
+            format_bytes(after.heap_bytes),
+            after.clone,
+            after.heap_bytes as f64 / before.heap_bytes.max(1) as f64
+        );
+        results.sharing.extend([before, after]);
+    }
+    println!("\n  hashes-u64 can report a foreign path as watched; the rest are exact");
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_containers_agree() {
+        let files: Vec<PathBuf> = ["/t/a/1.txt", "/t/a/2.txt", "/t/b/c/3.txt", "/t/4.txt"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        let paths = filter_paths(&files, 50);
+        assert_eq!(paths[4], PathBuf::from("/t/a/1.txt.1"));
+        assert_eq!(paths.iter().collect::<HashSet<_>>().len(), 50);
+
+        for container in FilterContainer::ALL {
+            let filter = container.build(&[PathBuf::from("/t")], &paths);
+            for path in &paths {
+                assert!(filter.contains(path), "{} lost {}", container.display_name(), path.display());
+            }
+            for foreign in ["/t/a/5.txt", "/t/a", "/u/a/1.txt", "/", "/t/a/1.txt.99"] {
+                assert!(!filter.contains(Path::new(foreign)), "{} has {}", container.display_name(), foreign);
+            }
+        }
+    }
+
+    #[test]
+    fn test_front_coding() {
+        let keys: Vec<String> = (0..100).map(|i| format!("/root/dir{}/file{}", i % 7, i)).collect();
+        let coded = FrontCoded::new(keys.iter().map(|k| k.as_bytes()));
+        let raw: usize = keys.iter().map(String::len).sum();
+        assert!(coded.bytes.len() < raw / 2);
+        assert_eq!(coded.blocks.len(), 7);
+        assert!(keys.iter().all(|k| coded.contains_bytes(k.as_bytes())));
+        assert!(!coded.contains_bytes(b""));
+        assert!(!coded.contains_bytes(b"/root/dir9/file1"));
+        assert!(!coded.contains_bytes(b"~"));
+    }
+}
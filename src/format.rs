@@ -0,0 +1,145 @@
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+/// Upgrade of a result from one version to the next
+pub type Migration = fn(&mut Map<String, Value>);
+
+/// A JSON result format, versioned on its own and stamped into each result as `format_version`
+///
+/// Version 1 is everything written before results were stamped, so a result
+/// without the field is read as version 1. Until each format got a version of
+/// its own they shared one counter, so every format starts out at 3.
+pub trait Versioned {
+    /// Version this build writes
+    const FORMAT_VERSION: u32;
+
+    /// Upgrades from version `n + 1` to `n + 2`, applied in turn; one per version after the first
+    const MIGRATIONS: &'static [Migration];
+}
+
+/// Version 1 results have the same fields as version 2, only no stamp
+pub fn from_v1(_: &mut Map<String, Value>) {}
+
+/// For a format that didn't change when the shared counter was bumped for another
+pub fn unchanged(_: &mut Map<String, Value>) {}
+
+/// Version 3 environments name their allocator; every earlier build used the system one
+pub fn environment_allocator(result: &mut Map<String, Value>) {
+    let stamp = |environment: Option<&mut Value>| {
+        if let Some(environment) = environment.and_then(Value::as_object_mut) {
+            environment.entry("allocator").or_insert_with(|| Value::from("system"));
+        }
+    };
+    // A report has one environment, a merged report one per group
+    stamp(result.get_mut("environment"));
+    for group in result.get_mut("environments").and_then(Value::as_array_mut).into_iter().flatten() {
+        stamp(group.get_mut("environment"));
+    }
+}
+
+/// Format version `value` was written with
+pub fn version_of(value: &Value) -> Result<u32, String> {
+    match value.get("format_version") {
+        None => Ok(1),
+        Some(version) => version
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .filter(|&v| v >= 1)
+            .ok_or_else(|| format!("invalid format_version: {}", version)),
+    }
+}
+
+/// Whether a result of `version` was written by a newer build than this one reads for `T`
+pub fn is_newer<T: Versioned>(version: u32) -> bool {
+    version > T::FORMAT_VERSION
+}
+
+/// Bring a result of any earlier version of `T`'s format up to its current version
+///
+/// Results from a newer build are refused rather than guessed at.
+pub fn migrate<T: Versioned>(mut value: Value) -> Result<Value, String> {
+    let version = version_of(&value)?;
+    if is_newer::<T>(version) {
+        return Err(format!(
+            "format version {} is newer than this build reads (up to {})",
+            version,
+            T::FORMAT_VERSION
+        ));
+    }
+    let object = value.as_object_mut().ok_or("result is not a JSON object")?;
+    for migration in &T::MIGRATIONS[version as usize - 1..] {
+        migration(object);
+    }
+    object.insert("format_version".to_string(), Value::from(T::FORMAT_VERSION));
+    Ok(value)
+}
+
+/// Parse one serialized result of any supported version into the current model,
+/// along with the version it was written in
+pub fn load<T: DeserializeOwned + Versioned>(json: &str) -> Result<(T, u32), String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    let version = version_of(&value)?;
+    let model = serde_json::from_value(migrate::<T>(value)?).map_err(|e| e.to_string())?;
+    Ok((model, version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Stamped {
+        format_version: u32,
+        events: usize,
+    }
+
+    impl Versioned for Stamped {
+        const FORMAT_VERSION: u32 = 2;
+        const MIGRATIONS: &'static [Migration] = &[from_v1];
+    }
+
+    #[test]
+    fn test_load_migrates_older_versions() {
+        assert_eq!(
+            load::<Stamped>(r#"{"events":3}"#),
+            Ok((Stamped { format_version: 2, events: 3 }, 1))
+        );
+        let current = r#"{"format_version":2,"events":4}"#;
+        assert_eq!(load::<Stamped>(current).unwrap().1, 2);
+
+        let newer = r#"{"format_version":3,"events":4}"#;
+        assert!(is_newer::<Stamped>(3));
+        assert!(load::<Stamped>(newer).unwrap_err().contains("newer"));
+        assert!(load::<Stamped>(r#"{"format_version":0,"events":4}"#).is_err());
+        assert!(load::<Stamped>("[1]").is_err());
+    }
+
+    /// Each format needs one migration per version after the first
+    fn check_migrations<T: Versioned>() {
+        assert_eq!(T::MIGRATIONS.len() as u32, T::FORMAT_VERSION - 1);
+    }
+
+    #[test]
+    fn test_every_format_has_its_migrations() {
+        check_migrations::<crate::watch::WatchSnapshot>();
+        check_migrations::<crate::latency::HistogramRecord>();
+        check_migrations::<crate::report::RunReport>();
+        check_migrations::<crate::merge::MergedReport>();
+    }
+
+    #[test]
+    fn test_v2_environments_gain_an_allocator() {
+        use crate::merge::MergedReport;
+        use crate::report::RunReport;
+        let report = serde_json::json!({ "format_version": 2, "environment": { "os": "linux" } });
+        assert_eq!(migrate::<RunReport>(report).unwrap()["environment"]["allocator"], "system");
+        let merged = serde_json::json!({
+            "format_version": 2,
+            "environments": [{ "environment": { "os": "linux" } }, { "environment": { "allocator": "mimalloc" } }],
+        });
+        let merged = migrate::<MergedReport>(merged).unwrap();
+        assert_eq!(merged["environments"][0]["environment"]["allocator"], "system");
+        assert_eq!(merged["environments"][1]["environment"]["allocator"], "mimalloc");
+    }
+}
@@ -0,0 +1,391 @@
+use crate::harness::{prepare_scratch_dir, spawn_event_collector, watched_files, write_rounds};
+use crate::latency::match_writes;
+use crate::options::Options;
+use crate::recursive_file_watcher::{EventReceiver, WatchConfig, WatcherMode};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Latencies swept by `fsevents-sweep` unless `--fsevents-latencies` is given
+const DEFAULT_LATENCIES_MS: [u64; 5] = [0, 10, 50, 100, 500];
+
+/// Files appended to at each sweep point
+const SWEEP_FILES: usize = 5;
+
+/// Appends per file at each sweep point, and the pause between rounds
+const SWEEP_ROUNDS: usize = 20;
+const SWEEP_INTERVAL: Duration = Duration::from_millis(5);
+
+/// `FSEventStreamCreate` flags this tool can set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsEventsFlag {
+    /// Per-file rather than per-directory events
+    FileEvents,
+    /// Deliver the first event of a burst immediately instead of after `latency`
+    NoDefer,
+    /// Skip events caused by this process
+    IgnoreSelf,
+    /// Report changes to the path of the watched root itself
+    WatchRoot,
+}
+
+impl FsEventsFlag {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "file-events" => Some(Self::FileEvents),
+            "no-defer" => Some(Self::NoDefer),
+            "ignore-self" => Some(Self::IgnoreSelf),
+            "watch-root" => Some(Self::WatchRoot),
+            _ => None,
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::FileEvents => "file-events",
+            Self::NoDefer => "no-defer",
+            Self::IgnoreSelf => "ignore-self",
+            Self::WatchRoot => "watch-root",
+        }
+    }
+
+    /// Parse a comma-separated list; `none` clears every flag
+    pub fn parse_list(s: &str) -> Option<Vec<Self>> {
+        if s == "none" {
+            return Some(Vec::new());
+        }
+        s.split(',').map(|flag| Self::from_str(flag.trim())).collect()
+    }
+
+    #[cfg(target_os = "macos")]
+    fn bits(&self) -> fsevent_sys::FSEventStreamCreateFlags {
+        match self {
+            Self::FileEvents => fsevent_sys::kFSEventStreamCreateFlagFileEvents,
+            Self::NoDefer => fsevent_sys::kFSEventStreamCreateFlagNoDefer,
+            Self::IgnoreSelf => fsevent_sys::kFSEventStreamCreateFlagIgnoreSelf,
+            Self::WatchRoot => fsevent_sys::kFSEventStreamCreateFlagWatchRoot,
+        }
+    }
+}
+
+/// FSEvents stream settings, which notify 6 fixes at zero latency with file events and no-defer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FsEventsConfig {
+    /// How long FSEvents waits to batch changes before calling back
+    pub latency: Duration,
+    pub flags: Vec<FsEventsFlag>,
+}
+
+impl Default for FsEventsConfig {
+    fn default() -> Self {
+        Self {
+            latency: Duration::ZERO,
+            flags: vec![FsEventsFlag::FileEvents, FsEventsFlag::NoDefer],
+        }
+    }
+}
+
+impl FsEventsConfig {
+    pub fn describe_flags(&self) -> String {
+        if self.flags.is_empty() {
+            return "none".to_string();
+        }
+        self.flags.iter().map(FsEventsFlag::display_name).collect::<Vec<_>>().join(",")
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod stream {
+    use super::FsEventsConfig;
+    use crate::recursive_file_watcher::{event_channel, EventReceiver, EventSink, WatchConfig};
+    use fsevent_sys as fs;
+    use fsevent_sys::core_foundation as cf;
+    use notify::event::{CreateKind, DataChange, Flag, MetadataKind, ModifyKind, RemoveKind, RenameMode};
+    use notify::{Event, EventKind};
+    use std::ffi::CStr;
+    use std::path::{Path, PathBuf};
+    use std::thread::JoinHandle;
+
+    /// A CoreFoundation reference handed between the caller and the run loop thread
+    struct CfRef(*mut libc::c_void);
+
+    // SAFETY: the path array is only touched by one thread at a time, and Apple
+    // documents CFRunLoopStop as callable from any thread
+    unsafe impl Send for CfRef {}
+
+    /// An FSEvents stream over some roots, with caller-chosen latency and flags
+    pub struct FsEventsWatcher {
+        run_loop: CfRef,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl FsEventsWatcher {
+        pub fn new(
+            roots: &[PathBuf],
+            config: &FsEventsConfig,
+            watch: &WatchConfig,
+        ) -> notify::Result<(Self, EventReceiver)> {
+            let (sink, rx) = event_channel(watch);
+            let flags = config.flags.iter().fold(fs::kFSEventStreamCreateFlagNone, |bits, f| bits | f.bits());
+            let latency = config.latency.as_secs_f64();
+            let paths = path_array(roots)?;
+            let info = Box::into_raw(Box::new(sink));
+
+            let (tx, run_loop_rx) = std::sync::mpsc::channel();
+            let paths = CfRef(paths);
+            let info = CfRef(info as *mut libc::c_void);
+            let thread = std::thread::Builder::new()
+                .name("fsevents-sweep loop".to_string())
+                .spawn(move || unsafe {
+                    let (paths, info) = (paths, info);
+                    let context = fs::FSEventStreamContext {
+                        version: 0,
+                        info: info.0,
+                        retain: None,
+                        release: Some(release_sink),
+                        copy_description: None,
+                    };
+                    let stream = fs::FSEventStreamCreate(
+                        cf::kCFAllocatorDefault,
+                        callback,
+                        &context,
+                        paths.0,
+                        fs::kFSEventStreamEventIdSinceNow,
+                        latency,
+                        flags,
+                    );
+                    cf::CFRelease(paths.0);
+                    let run_loop = cf::CFRunLoopGetCurrent();
+                    fs::FSEventStreamScheduleWithRunLoop(stream, run_loop, cf::kCFRunLoopDefaultMode);
+                    fs::FSEventStreamStart(stream);
+                    let _ = tx.send(CfRef(run_loop));
+                    cf::CFRunLoopRun();
+                    fs::FSEventStreamStop(stream);
+                    fs::FSEventStreamInvalidate(stream);
+                    fs::FSEventStreamRelease(stream);
+                })?;
+            let run_loop = run_loop_rx
+                .recv()
+                .map_err(|_| notify::Error::generic("FSEvents run loop did not start"))?;
+            Ok((
+                Self {
+                    run_loop,
+                    thread: Some(thread),
+                },
+                rx,
+            ))
+        }
+    }
+
+    impl Drop for FsEventsWatcher {
+        fn drop(&mut self) {
+            unsafe { cf::CFRunLoopStop(self.run_loop.0) };
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+
+    /// A CFArray of the roots' paths, owned by the caller
+    fn path_array(roots: &[PathBuf]) -> notify::Result<cf::CFMutableArrayRef> {
+        unsafe {
+            let paths = cf::CFArrayCreateMutable(cf::kCFAllocatorDefault, 0, &cf::kCFTypeArrayCallBacks);
+            for root in roots {
+                let root = std::path::absolute(root)?;
+                let mut err: cf::CFErrorRef = std::ptr::null_mut();
+                let path = cf::str_path_to_cfstring_ref(&root.to_string_lossy(), &mut err);
+                if path.is_null() {
+                    cf::CFRelease(paths);
+                    return Err(notify::Error::path_not_found().add_path(root));
+                }
+                cf::CFArrayAppendValue(paths, path);
+                cf::CFRelease(path);
+            }
+            Ok(paths)
+        }
+    }
+
+    extern "C" fn release_sink(info: *const libc::c_void) {
+        // SAFETY: `info` is the boxed sink handed to the stream context, released once
+        drop(unsafe { Box::from_raw(info as *mut EventSink) });
+    }
+
+    extern "C" fn callback(
+        _stream: fs::FSEventStreamRef,
+        info: *mut libc::c_void,
+        num_events: usize,
+        event_paths: *mut libc::c_void,
+        event_flags: *const fs::FSEventStreamEventFlags,
+        _event_ids: *const fs::FSEventStreamEventId,
+    ) {
+        // SAFETY: FSEvents passes `num_events` C strings and flags, and our sink as `info`
+        unsafe {
+            let sink = &*(info as *const EventSink);
+            let paths = event_paths as *const *const libc::c_char;
+            for i in 0..num_events {
+                let path = Path::new(CStr::from_ptr(*paths.add(i)).to_str().unwrap_or_default());
+                for kind in translate(*event_flags.add(i)) {
+                    let mut event = Event::new(kind).add_path(path.to_path_buf());
+                    if *event_flags.add(i) & fs::kFSEventStreamEventFlagMustScanSubDirs != 0 {
+                        event = event.set_flag(Flag::Rescan);
+                    }
+                    sink.send(Ok(event));
+                }
+            }
+        }
+    }
+
+    /// notify event kinds for one FSEvents flag word
+    fn translate(flags: fs::FSEventStreamEventFlags) -> Vec<EventKind> {
+        let table = [
+            (fs::kFSEventStreamEventFlagItemCreated, EventKind::Create(CreateKind::Any)),
+            (fs::kFSEventStreamEventFlagItemRemoved, EventKind::Remove(RemoveKind::Any)),
+            (fs::kFSEventStreamEventFlagItemRenamed, EventKind::Modify(ModifyKind::Name(RenameMode::Any))),
+            (fs::kFSEventStreamEventFlagItemModified, EventKind::Modify(ModifyKind::Data(DataChange::Content))),
+            (
+                fs::kFSEventStreamEventFlagItemInodeMetaMod,
+                EventKind::Modify(ModifyKind::Metadata(MetadataKind::Any)),
+            ),
+            (
+                fs::kFSEventStreamEventFlagItemXattrMod,
+                EventKind::Modify(ModifyKind::Metadata(MetadataKind::Extended)),
+            ),
+        ];
+        let kinds: Vec<EventKind> = table.iter().filter(|(bit, _)| flags & bit != 0).map(|(_, kind)| *kind).collect();
+        if kinds.is_empty() {
+            vec![EventKind::Any]
+        } else {
+            kinds
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub use stream::FsEventsWatcher;
+
+/// Start an FSEvents stream on `roots` with `config`; only macOS has one
+#[cfg(target_os = "macos")]
+fn start_stream(
+    roots: &[PathBuf],
+    config: &FsEventsConfig,
+    watch: &WatchConfig,
+) -> notify::Result<(Box<dyn Send>, EventReceiver)> {
+    let (watcher, rx) = FsEventsWatcher::new(roots, config, watch)?;
+    Ok((Box::new(watcher), rx))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn start_stream(
+    _roots: &[PathBuf],
+    _config: &FsEventsConfig,
+    _watch: &WatchConfig,
+) -> notify::Result<(Box<dyn Send>, EventReceiver)> {
+    Err(notify::Error::generic("fsevents-sweep needs macOS's FSEvents"))
+}
+
+/// One latency setting's burst results
+struct SweepPoint {
+    latency: Duration,
+    writes: usize,
+    events: usize,
+    p50: Option<Duration>,
+    p99: Option<Duration>,
+    unmatched: usize,
+}
+
+/// Sweep the FSEvents latency over a burst of appends and report the coalescing/latency trade-off
+///
+/// Higher latencies batch more changes into fewer callbacks but hold every event
+/// back for up to that long; `no-defer` delivers the first event of a burst at once.
+pub fn run_fsevents_sweep(dir: &Path, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    if !cfg!(target_os = "macos") {
+        return Err("fsevents-sweep needs macOS's FSEvents".into());
+    }
+    println!("\n=== FSEvents Latency Sweep ===");
+    println!("Source directory: {}", dir.display());
+    let latencies = if options.fsevents_latencies.is_empty() {
+        DEFAULT_LATENCIES_MS.map(Duration::from_millis).to_vec()
+    } else {
+        options.fsevents_latencies.clone()
+    };
+    let base = FsEventsConfig {
+        flags: options.fsevents_flags.clone(),
+        ..FsEventsConfig::default()
+    };
+    println!("Flags: {}", base.describe_flags());
+
+    println!("\n1. Copying files to temporary directory...");
+    let tmp_dir = std::path::absolute(prepare_scratch_dir(dir, "fsevents-sweep", options)?)?;
+    let targets: Vec<PathBuf> = watched_files(WatcherMode::Native, &tmp_dir, options)?.into_iter().take(SWEEP_FILES).collect();
+
+    println!("\n2. Running a burst at each latency...");
+    let mut points = Vec::new();
+    for latency in latencies {
+        println!("\n   --- latency {:?} ---", latency);
+        let config = FsEventsConfig {
+            latency,
+            ..base.clone()
+        };
+        let (watcher, rx) = start_stream(std::slice::from_ref(&tmp_dir), &config, &options.watch_config())?;
+        let collect_duration = latency + Duration::from_secs(2);
+        let event_rx = spawn_event_collector(rx, collect_duration, Duration::ZERO);
+        std::thread::sleep(options.stabilize);
+
+        let writes = write_rounds(&targets, SWEEP_ROUNDS, SWEEP_INTERVAL);
+        let collected = event_rx
+            .recv_timeout(collect_duration + Duration::from_secs(1))
+            .unwrap_or_default();
+        drop(watcher);
+
+        let latency_report = match_writes(&writes, &collected);
+        let total = latency_report.total();
+        println!("   {} writes, {} events", writes.len(), collected.events.len());
+        points.push(SweepPoint {
+            latency,
+            writes: writes.len(),
+            events: collected.events.len(),
+            p50: total.map(|s| s.p50),
+            p99: total.map(|s| s.p99),
+            unmatched: latency_report.unmatched,
+        });
+    }
+
+    println!("\n📊 FSEvents latency sweep:");
+    println!(
+        "  {:>10} {:>8} {:>8} {:>12} {:>12} {:>12} {:>8}",
+        "Latency", "Writes", "Events", "Events/write", "Total p50", "Total p99", "Missed"
+    );
+    let show = |d: Option<Duration>| d.map_or("-".to_string(), |d| format!("{:.1?}", d));
+    for point in &points {
+        println!(
+            "  {:>10} {:>8} {:>8} {:>12.2} {:>12} {:>12} {:>8}",
+            format!("{:?}", point.latency),
+            point.writes,
+            point.events,
+            point.events as f64 / point.writes.max(1) as f64,
+            show(point.p50),
+            show(point.p99),
+            point.unmatched
+        );
+    }
+
+    println!("\n3. Cleaning up...");
+    fs::remove_dir_all(&tmp_dir)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_flag_list() {
+        assert_eq!(
+            FsEventsFlag::parse_list("file-events, ignore-self"),
+            Some(vec![FsEventsFlag::FileEvents, FsEventsFlag::IgnoreSelf])
+        );
+        assert_eq!(FsEventsFlag::parse_list("none"), Some(Vec::new()));
+        assert_eq!(FsEventsFlag::parse_list("file-events,bogus"), None);
+        assert_eq!(FsEventsConfig::default().describe_flags(), "file-events,no-defer");
+    }
+}
@@ -0,0 +1,865 @@
+use crate::format::Versioned;
+use crate::latency::{DurationSummary, HistogramFormat, HistogramRecord, LatencyHistogram, WriteRecord};
+use crate::limits::preflight_manual_watches;
+use crate::options::Options;
+use crate::profile::SetupProfiler;
+use crate::recursive_file_watcher::{
+    event_stream, is_excluded, walk_files, walk_files_sorted, AsyncEventReceiver, ChannelKind, DebouncedRecursiveWatcher,
+    EventReceiver, FileListing, FileWalk, ManualRecursiveWatcher, NativeRecursiveWatcher, PollRecursiveWatcher, QueueDepth,
+    WatcherGuard, WatcherMode,
+};
+use crate::roots::{mark_fallback_roots, print_root_table, sum_watch_times, RootMetrics};
+use crate::shutdown;
+use crate::stats::{CollectedEvents, QueueDepthStats};
+use crate::tiers::is_priority;
+use futures::Stream;
+use notify::Event;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Every Nth enumerated file is kept by the filtered modes
+pub const FILTER_RATIO: usize = 10;
+
+/// Default `--max-files`: well past any real project, well short of a whole disk
+pub const DEFAULT_MAX_FILES: usize = 2_000_000;
+
+/// How often the event channel backlog is sampled
+const QUEUE_SAMPLE_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Get a subset of files for filtered watching (e.g., every 10th file)
+pub fn get_filtered_files(all_files: &[PathBuf], filter_ratio: usize) -> Vec<PathBuf> {
+    all_files
+        .iter()
+        .enumerate()
+        .filter_map(|(i, path)| {
+            if i % filter_ratio == 0 {
+                Some(path.clone())
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Files a filtered mode watches: every `--priority` file, plus every `FILTER_RATIO`th of the rest
+pub fn tiered_files(all_files: &[PathBuf], priority: &[String]) -> Vec<PathBuf> {
+    let (mut files, rest): (Vec<PathBuf>, Vec<PathBuf>) =
+        all_files.iter().cloned().partition(|path| is_priority(path, priority));
+    files.extend(get_filtered_files(&rest, FILTER_RATIO));
+    files
+}
+
+/// Copy directory recursively to a temporary location
+///
+/// Symlinks into the tree are recreated as links to the same place in the copy rather
+/// than followed, so a link back to an ancestor can't recurse forever. Links leading
+/// outside the tree are copied through as before, unless they lead to one of its
+/// ancestors; those and dangling links are kept as they are.
+///
+/// The tree is held to `--max-files` first, so an oversized source fails before
+/// anything is written; with `--truncate-files` only that many files are copied.
+pub fn copy_dir_recursive(src: &Path, dst: &Path, options: &Options) -> io::Result<()> {
+    let mut budget = enumerate_dir(src, options).map_err(io::Error::other)?.len();
+    // Create destination directory
+    fs::create_dir_all(dst)?;
+    let roots = (fs::canonicalize(src)?, fs::canonicalize(dst)?);
+    copy_entries(src, dst, &roots, &mut budget)
+}
+
+/// Copy `src`'s entries into the existing `dst`, at most `budget` files of them;
+/// `roots` are the canonical tree and copy roots
+fn copy_entries(src: &Path, dst: &Path, roots: &(PathBuf, PathBuf), budget: &mut usize) -> io::Result<()> {
+    // Read the source directory
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_name = entry.file_name();
+        let dest_path = dst.join(file_name);
+
+        if entry.file_type()?.is_symlink() {
+            match fs::canonicalize(&path) {
+                Ok(target) => {
+                    if let Ok(rest) = target.strip_prefix(&roots.0) {
+                        symlink(&roots.1.join(rest), &dest_path, target.is_dir())?;
+                        continue;
+                    }
+                    if roots.0.starts_with(&target) {
+                        // An ancestor of the whole tree would contain the tree again
+                        symlink(&target, &dest_path, true)?;
+                        continue;
+                    }
+                },
+                Err(_) => {
+                    symlink(&fs::read_link(&path)?, &dest_path, false)?;
+                    continue;
+                },
+            }
+        }
+
+        if path.is_dir() {
+            // Recursively copy subdirectory
+            fs::create_dir_all(&dest_path)?;
+            copy_entries(&path, &dest_path, roots, budget)?;
+        } else if *budget > 0 {
+            // Copy file
+            fs::copy(&path, &dest_path)?;
+            *budget -= 1;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path, _is_dir: bool) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink(target: &Path, link: &Path, is_dir: bool) -> io::Result<()> {
+    if is_dir {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
+
+/// Name used for scratch copies of `dir` below `./tmp`
+pub fn scratch_name(dir: &Path) -> &str {
+    dir.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("test")
+}
+
+/// Copy `dir` into a fresh `./tmp/<name>-<slug>` scratch directory
+pub fn prepare_scratch_dir(dir: &Path, slug: &str, options: &Options) -> io::Result<PathBuf> {
+    let scratch = PathBuf::from("./tmp").join(format!("{}-{}", scratch_name(dir), slug));
+    if scratch.exists() {
+        fs::remove_dir_all(&scratch)?;
+    }
+    copy_dir_recursive(dir, &scratch, options)?;
+
+    Ok(scratch)
+}
+
+/// Create a manual watcher for `files` after checking them against the OS watch limits
+pub fn manual_watcher(
+    files: Vec<PathBuf>,
+    options: &Options,
+) -> notify::Result<ManualRecursiveWatcher> {
+    let files = preflight_manual_watches(files, options.allow_partial)
+        .map_err(|e| notify::Error::generic(&e))?;
+    ManualRecursiveWatcher::new_with_config(files, &options.watch_config())
+}
+
+/// `files` rearranged into the `--watch-order` manual mode registers them in
+///
+/// Files under an `--exclude` directory are left out, so they're never watched at all.
+pub fn ordered_watches(mut files: Vec<PathBuf>, options: &Options) -> Vec<PathBuf> {
+    files.retain(|file| !is_excluded(file, &options.exclude));
+    options.watch_order.arrange(files, options.seed)
+}
+
+/// Files a watcher of the given mode is expected to report on below `root`, enumerated as `enumerate_roots` does
+pub fn watched_files(mode: WatcherMode, root: &Path, options: &Options) -> Result<Vec<PathBuf>, String> {
+    Ok(select_watched(mode, enumerate_dir(root, options)?, &[]))
+}
+
+/// Like `watched_files`, with the filtered modes also watching every `--priority` file
+pub fn watched_files_with_priority(mode: WatcherMode, root: &Path, options: &Options) -> Result<Vec<PathBuf>, String> {
+    Ok(select_watched(mode, enumerate_dir(root, options)?, &options.priority))
+}
+
+/// The part of `all_files` a watcher of the given mode covers
+pub fn select_watched(mode: WatcherMode, all_files: Vec<PathBuf>, priority: &[String]) -> Vec<PathBuf> {
+    match mode {
+        WatcherMode::Manual | WatcherMode::Native | WatcherMode::Poll | WatcherMode::Debounced => {
+            all_files
+        },
+        WatcherMode::ManualFiltered | WatcherMode::NativeFiltered => tiered_files(&all_files, priority),
+    }
+}
+
+/// Print how much of the requested watch list a manual watcher actually covers
+pub fn report_coverage(watcher: &ManualRecursiveWatcher, indent: &str) {
+    if watcher.is_partial() {
+        println!(
+            "{}Coverage: {} of {} files ({:.1}%){}",
+            indent,
+            watcher.files_watched(),
+            watcher.files_requested(),
+            watcher.coverage_percent(),
+            if watcher.limit_reached() { " - watch limit reached" } else { "" }
+        );
+    }
+    if watcher.retries() > 0 || !watcher.skipped().is_empty() {
+        println!(
+            "{}Failed watch calls: {} retries ({:?} backing off, not in setup time), {} files skipped",
+            indent,
+            watcher.retries(),
+            watcher.backoff_time(),
+            watcher.skipped().len()
+        );
+    }
+}
+
+/// Walk `root` in `--sort-enumeration` order if asked, else as `read_dir` lists it
+pub fn walk_root(root: &Path, options: &Options) -> FileWalk {
+    if options.sort_enumeration {
+        walk_files_sorted(root)
+    } else {
+        walk_files(root)
+    }
+}
+
+/// Walk every root, stopping at `--max-files` across all of them
+///
+/// Exceeding the cap is an error, since it usually means the tool was pointed at `/`
+/// or a home directory; with `--truncate-files` the listing is cut short with a warning.
+pub fn enumerate_roots(roots: &[PathBuf], options: &Options) -> Result<Vec<FileListing>, String> {
+    let max = options.max_files.unwrap_or(usize::MAX);
+    let mut remaining = max;
+    let mut listings = Vec::with_capacity(roots.len());
+    for root in roots {
+        // One past what's left shows whether the cap is exceeded without walking the rest
+        let mut listing = walk_root(root, options).list_up_to(remaining.saturating_add(1));
+        if listing.files.len() > remaining {
+            if !options.truncate_files {
+                return Err(format!(
+                    "more than {} files under the given roots; pass --max-files to raise the cap or --truncate-files to use the first {}",
+                    max, max
+                ));
+            }
+            listing.files.truncate(remaining);
+            eprintln!(
+                "Warning: --max-files {} reached in {}; later files are left out",
+                max,
+                root.display()
+            );
+        }
+        remaining -= listing.files.len();
+        listings.push(listing);
+    }
+    Ok(listings)
+}
+
+/// Every file below `roots`, enumerated as `enumerate_roots` does, with anything the walk skipped reported
+pub fn enumerate_files(roots: &[PathBuf], options: &Options) -> Result<Vec<PathBuf>, String> {
+    let listings = enumerate_roots(roots, options)?;
+    report_walk_errors(roots, &listings, "");
+    Ok(listings.into_iter().flat_map(|listing| listing.files).collect())
+}
+
+/// Every file below `dir`, enumerated as `enumerate_roots` does
+pub fn enumerate_dir(dir: &Path, options: &Options) -> Result<Vec<PathBuf>, String> {
+    enumerate_files(&[dir.to_path_buf()], options)
+}
+
+/// Walk errors listed individually before the rest are only counted
+const WALK_ERRORS_SHOWN: usize = 3;
+
+/// Print what each root's walk couldn't read or had to skip, so files the benchmark never saw are visible
+pub fn report_walk_errors(roots: &[PathBuf], listings: &[FileListing], indent: &str) {
+    for (root, listing) in roots.iter().zip(listings) {
+        if !listing.loops.is_empty() {
+            println!(
+                "{}Symlink loops in {}: {} links back to an ancestor not followed",
+                indent,
+                root.display(),
+                listing.loops.len()
+            );
+            for link in listing.loops.iter().take(WALK_ERRORS_SHOWN) {
+                println!("{}  {}", indent, link.display());
+            }
+        }
+        if !listing.duplicates.is_empty() {
+            println!(
+                "{}Directories reached twice in {}: {} links to a directory already walked not followed",
+                indent,
+                root.display(),
+                listing.duplicates.len()
+            );
+            for link in listing.duplicates.iter().take(WALK_ERRORS_SHOWN) {
+                println!("{}  {}", indent, link.display());
+            }
+        }
+        if listing.errors.is_empty() {
+            continue;
+        }
+        println!(
+            "{}Enumeration of {} incomplete: {} unreadable directories, {} unreadable entries, {} permission errors",
+            indent,
+            root.display(),
+            listing.unreadable_dirs(),
+            listing.errors.len() - listing.unreadable_dirs(),
+            listing.permission_errors()
+        );
+        for error in listing.errors.iter().take(WALK_ERRORS_SHOWN) {
+            println!("{}  {}: {}", indent, error.path.display(), error.message);
+        }
+        if listing.errors.len() > WALK_ERRORS_SHOWN {
+            println!("{}  ... and {} more", indent, listing.errors.len() - WALK_ERRORS_SHOWN);
+        }
+    }
+}
+
+/// Watches whose times are compared against the rest, to show how slow setup starts
+const EARLY_WATCHES: usize = 100;
+
+/// Per-watch setup times as a distribution, since the average hides slow first watches
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchTimeDistribution {
+    pub all: DurationSummary,
+    /// The first `EARLY_WATCHES` watch calls
+    pub early: DurationSummary,
+    /// Every later watch call, when there are any
+    pub rest: Option<DurationSummary>,
+}
+
+impl WatchTimeDistribution {
+    /// Summarize `times` in registration order, or `None` when no watch succeeded
+    pub fn new(times: &[Duration]) -> Option<Self> {
+        let split = EARLY_WATCHES.min(times.len());
+        Some(Self {
+            all: DurationSummary::from_durations(times.to_vec())?,
+            early: DurationSummary::from_durations(times[..split].to_vec())?,
+            rest: DurationSummary::from_durations(times[split..].to_vec()),
+        })
+    }
+
+    /// How many times slower the early watches' median is than the later ones'
+    pub fn early_slowdown(&self) -> Option<f64> {
+        let rest = self.rest?.p50.as_secs_f64();
+        (rest > 0.0).then(|| self.early.p50.as_secs_f64() / rest)
+    }
+}
+
+/// Print the distribution of manual-mode watch call times, plus a histogram with `--histogram`
+pub fn report_watch_times(watcher: &ManualRecursiveWatcher, options: &Options, indent: &str) {
+    let times = watcher.watch_times();
+    let Some(distribution) = WatchTimeDistribution::new(times) else {
+        return;
+    };
+    let line = |label: &str, s: &DurationSummary| {
+        println!(
+            "{}{:<18} min {:.1?}, p50 {:.1?}, p99 {:.1?}, max {:.1?}",
+            indent, label, s.min, s.p50, s.p99, s.max
+        )
+    };
+    line("Per-watch time:", &distribution.all);
+    line(&format!("  first {}:", EARLY_WATCHES.min(times.len())), &distribution.early);
+    if let Some(rest) = &distribution.rest {
+        line("  rest:", rest);
+    }
+    if let Some(slowdown) = distribution.early_slowdown() {
+        println!("{}  early watches are {:.1}x the later median", indent, slowdown);
+    }
+    match options.histogram {
+        Some(HistogramFormat::Text) => LatencyHistogram::new(&options.histogram_buckets, times.iter().copied())
+            .print_text("Per-watch time histogram", indent),
+        Some(HistogramFormat::Json) => println!(
+            "{}",
+            HistogramRecord {
+                format_version: HistogramRecord::FORMAT_VERSION,
+                mode: None,
+                metric: "watch_time",
+                histogram: LatencyHistogram::new(&options.histogram_buckets, times.iter().copied()),
+            }
+            .to_json()
+        ),
+        None => {},
+    }
+}
+
+/// Files a watcher of the given mode is expected to report on below any of `roots`, `--priority` included
+pub fn watched_files_in(mode: WatcherMode, roots: &[PathBuf], options: &Options) -> Result<Vec<PathBuf>, String> {
+    let listings = enumerate_roots(roots, options)?;
+    report_walk_errors(roots, &listings, "");
+    Ok(listings
+        .into_iter()
+        .flat_map(|listing| select_watched(mode, listing.files, &options.priority))
+        .collect())
+}
+
+/// Set up a watcher of the given mode on `root`, printing its setup statistics
+pub fn start_watcher(
+    mode: WatcherMode,
+    root: &Path,
+    options: &Options,
+) -> notify::Result<(WatcherGuard, EventReceiver)> {
+    let (watcher, rx, _) = start_watcher_on_roots(mode, &[root.to_path_buf()], options)?;
+    Ok((watcher, rx))
+}
+
+/// Set up a watcher of the given mode on `root` delivering through a tokio channel
+pub fn start_async_watcher(
+    mode: WatcherMode,
+    root: &Path,
+    options: &Options,
+) -> notify::Result<(WatcherGuard, AsyncEventReceiver)> {
+    let options = Options {
+        channel: ChannelKind::Tokio,
+        ..options.clone()
+    };
+    let (watcher, rx) = start_watcher(mode, root, &options)?;
+    let rx = rx
+        .into_async()
+        .ok_or_else(|| notify::Error::generic("tokio channel did not produce an async receiver"))?;
+    Ok((watcher, rx))
+}
+
+/// Set up a watcher of the given mode on `root` and turn it into a stream of events
+pub fn start_event_stream(
+    mode: WatcherMode,
+    root: &Path,
+    options: &Options,
+) -> notify::Result<impl Stream<Item = notify::Result<Event>>> {
+    let (watcher, rx) = start_async_watcher(mode, root, options)?;
+    Ok(event_stream(watcher, rx))
+}
+
+/// Record a recursive watcher's per-root setup times and the files each root covers
+fn fill_root_setup_times(
+    metrics: &mut [RootMetrics],
+    mode: WatcherMode,
+    times: &[Duration],
+    options: &Options,
+) -> notify::Result<()> {
+    for (m, time) in metrics.iter_mut().zip(times) {
+        let files = watched_files_with_priority(mode, &m.root, options).map_err(|e| notify::Error::generic(&e))?;
+        m.files = files.len();
+        m.setup_time = *time;
+    }
+    Ok(())
+}
+
+/// Set up one watcher of the given mode covering every root
+///
+/// Returns per-root setup metrics alongside the watcher, and prints them when
+/// there is more than one root.
+pub fn start_watcher_on_roots(
+    mode: WatcherMode,
+    roots: &[PathBuf],
+    options: &Options,
+) -> notify::Result<(WatcherGuard, EventReceiver, Vec<RootMetrics>)> {
+    let mut metrics: Vec<RootMetrics> = roots
+        .iter()
+        .map(|root| RootMetrics {
+            root: root.clone(),
+            ..RootMetrics::default()
+        })
+        .collect();
+
+    let profiler = SetupProfiler::start(options.profile.as_deref(), mode.display_name());
+    let (watcher, rx): (WatcherGuard, EventReceiver) = match mode {
+        WatcherMode::Manual | WatcherMode::ManualFiltered => {
+            let files = watched_files_in(mode, roots, options).map_err(|e| notify::Error::generic(&e))?;
+            let watcher = manual_watcher(ordered_watches(files, options), options)?;
+            println!("   Setup time: {:?}", watcher.setup_time());
+            println!("   Files watched: {}", watcher.files_watched());
+            report_coverage(&watcher, "   ");
+            report_watch_times(&watcher, options, "   ");
+            sum_watch_times(&mut metrics, watcher.watched_files(), watcher.watch_times());
+            let (watcher, rx) = watcher.into_parts();
+            (Box::new(watcher), rx)
+        },
+        WatcherMode::Native => {
+            let watcher =
+                NativeRecursiveWatcher::new_with_roots_and_config(roots, &options.watch_config())?;
+            println!("   Setup time: {:?}", watcher.setup_time());
+            fill_root_setup_times(&mut metrics, mode, watcher.root_setup_times(), options)?;
+            mark_fallback_roots(&mut metrics, watcher.fallback_roots(), "   ");
+            let (watcher, rx) = watcher.into_parts();
+            (Box::new(watcher), rx)
+        },
+        WatcherMode::NativeFiltered => {
+            let files = watched_files_in(mode, roots, options).map_err(|e| notify::Error::generic(&e))?;
+            let watcher = NativeRecursiveWatcher::new_with_roots_filter_and_config(
+                roots,
+                &files,
+                &options.watch_config(),
+            )?;
+            println!("   Setup time: {:?}", watcher.setup_time());
+            println!("   Files filtered: {}", watcher.files_filtered());
+            fill_root_setup_times(&mut metrics, mode, watcher.root_setup_times(), options)?;
+            mark_fallback_roots(&mut metrics, watcher.fallback_roots(), "   ");
+            let (watcher, rx) = watcher.into_parts();
+            (Box::new(watcher), rx)
+        },
+        WatcherMode::Poll => {
+            let watcher =
+                PollRecursiveWatcher::new_with_roots_and_config(roots, &options.watch_config())?;
+            println!("   Setup time: {:?}", watcher.setup_time());
+            fill_root_setup_times(&mut metrics, mode, watcher.root_setup_times(), options)?;
+            let (watcher, rx) = watcher.into_parts();
+            (Box::new(watcher), rx)
+        },
+        WatcherMode::Debounced => {
+            let watcher =
+                DebouncedRecursiveWatcher::new_with_roots_and_config(roots, &options.watch_config())?;
+            println!("   Setup time: {:?}", watcher.setup_time());
+            fill_root_setup_times(&mut metrics, mode, watcher.root_setup_times(), options)?;
+            let (debouncer, rx) = watcher.into_parts();
+            (Box::new(debouncer), rx)
+        },
+    };
+    if let Some(profiler) = profiler {
+        profiler.finish();
+    }
+
+    if roots.len() > 1 {
+        print_root_table(&metrics, "   ");
+    }
+    Ok((watcher, rx, metrics))
+}
+
+/// Receive events from `rx` until `duration` has elapsed
+pub fn collect_events(
+    rx: &EventReceiver,
+    duration: Duration,
+) -> CollectedEvents {
+    collect_events_paced(rx, duration, Duration::ZERO)
+}
+
+/// Like `collect_events`, but spend `consumer_delay` "processing" each item
+///
+/// Simulates a consumer slower than the event rate, so the backlog builds up in
+/// the channel and, with a bounded channel, in the kernel queue.
+pub fn collect_events_paced(
+    rx: &EventReceiver,
+    duration: Duration,
+    consumer_delay: Duration,
+) -> CollectedEvents {
+    let start = Instant::now();
+    collect_while(rx, consumer_delay, |_| start.elapsed() < duration)
+}
+
+/// Receive events until `keep_going`, given when the last event arrived, returns false
+fn collect_while(
+    rx: &EventReceiver,
+    consumer_delay: Duration,
+    mut keep_going: impl FnMut(Option<Instant>) -> bool,
+) -> CollectedEvents {
+    let mut collected = CollectedEvents::default();
+    let sampler = QueueDepthSampler::start(rx.depth());
+    let overhead_before = rx.overhead();
+    let backpressure_before = rx.backpressure();
+    let excluded_before = rx.excluded();
+    let faults_before = rx.injected();
+    let limited_before = rx.rate_limited();
+    let mut last_event = None;
+
+    while keep_going(last_event) && !shutdown::requested() {
+        match rx.recv_timeout(Duration::from_millis(10)) {
+            Ok(item) => {
+                last_event = Some(Instant::now());
+                collected.record(item);
+                if !consumer_delay.is_zero() {
+                    std::thread::sleep(consumer_delay);
+                }
+            }
+            Err(_) => {
+                // Timeout or disconnected
+            }
+        }
+    }
+
+    collected.queue_depth = sampler.finish();
+    collected.channel = rx.overhead().since(&overhead_before);
+    collected.backpressure = rx.backpressure().since(&backpressure_before);
+    collected.excluded = rx.excluded() - excluded_before;
+    collected.faults = rx.injected().since(&faults_before);
+    collected.rate_limited = rx.rate_limited().since(&limited_before);
+    collected
+}
+
+/// Settle period for `mode`: polling and debouncing hold events back by design
+pub fn settle_for(mode: WatcherMode, options: &Options) -> Duration {
+    match mode {
+        WatcherMode::Poll => options.settle + options.poll_interval,
+        WatcherMode::Debounced => options.settle + options.debounce,
+        _ => options.settle,
+    }
+}
+
+/// Events collected by a `SettlingCollector`, and how the collection ended
+#[derive(Debug)]
+pub struct Settled {
+    pub collected: CollectedEvents,
+    /// From the workload finishing to the collection stopping
+    pub after: Duration,
+    /// The collection hit its cap while events were still arriving
+    pub capped: bool,
+}
+
+impl Settled {
+    pub fn report(&self, settle: Duration, indent: &str) {
+        if self.capped {
+            println!("{}Events had not settled when collection was capped, {:?} after the last change", indent, self.after);
+        } else {
+            println!("{}Events settled {:?} after the last change ({:?} quiet)", indent, self.after, settle);
+        }
+    }
+}
+
+/// Event collection that stops once nothing has arrived for a settle period
+///
+/// The settle period only starts counting at `workload_done`, so a slow backend
+/// isn't cut off while the workload is still running, and a fast one isn't kept
+/// waiting out a fixed window. `max` caps the whole collection.
+pub struct SettlingCollector {
+    done: mpsc::Sender<Instant>,
+    events: mpsc::Receiver<Settled>,
+}
+
+impl SettlingCollector {
+    pub fn spawn(rx: EventReceiver, settle: Duration, max: Duration, consumer_delay: Duration) -> Self {
+        let (done_tx, done_rx) = mpsc::channel::<Instant>();
+        let (event_tx, event_rx) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            let start = Instant::now();
+            let mut done_at = None;
+            let mut capped = true;
+            let collected = collect_while(&rx, consumer_delay, |last_event| {
+                if done_at.is_none() {
+                    done_at = done_rx.try_recv().ok();
+                }
+                if let Some(done) = done_at {
+                    let quiet_since = last_event.map_or(done, |last| last.max(done));
+                    if quiet_since.elapsed() >= settle {
+                        capped = false;
+                        return false;
+                    }
+                }
+                start.elapsed() < max
+            });
+            let after = done_at.map_or(Duration::ZERO, |done: Instant| done.elapsed());
+            let _ = event_tx.send(Settled { collected, after, capped });
+        });
+
+        Self { done: done_tx, events: event_rx }
+    }
+
+    /// Start the settle period: the workload has made its last change
+    pub fn workload_done(&self) {
+        let _ = self.done.send(Instant::now());
+    }
+
+    /// Wait for the collection to end, or `None` if the collector thread died
+    pub fn finish(self) -> Option<Settled> {
+        self.events.recv().ok()
+    }
+}
+
+/// Async counterpart of `collect_events` for a tokio-channel watcher
+pub async fn collect_events_async(rx: &mut AsyncEventReceiver, duration: Duration) -> CollectedEvents {
+    let deadline = tokio::time::Instant::now() + duration;
+    let mut collected = CollectedEvents::default();
+    let sampler = QueueDepthSampler::start(rx.depth());
+    let overhead_before = rx.overhead();
+
+    // Stop at the deadline, or early once the watcher is gone
+    while let Ok(Some(item)) = tokio::time::timeout_at(deadline, rx.recv()).await {
+        collected.record(item);
+    }
+
+    collected.queue_depth = sampler.finish();
+    collected.channel = rx.overhead().since(&overhead_before);
+    collected
+}
+
+/// Append to `files` once per round, returning every write for latency matching
+pub fn write_rounds<P: AsRef<Path>>(files: &[P], rounds: usize, delay: Duration) -> Vec<WriteRecord> {
+    (0..rounds).flat_map(|_| append_to_files(files, delay)).collect()
+}
+
+/// Samples a channel's queue depth on a background thread until finished
+pub struct QueueDepthSampler {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<Vec<usize>>,
+}
+
+impl QueueDepthSampler {
+    pub fn start(depth: QueueDepth) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = stop.clone();
+        let handle = std::thread::spawn(move || {
+            let mut samples = Vec::new();
+            while !stop_flag.load(Ordering::Relaxed) {
+                samples.push(depth.get());
+                std::thread::sleep(QUEUE_SAMPLE_INTERVAL);
+            }
+            samples
+        });
+        Self { stop, handle }
+    }
+
+    /// Stop sampling and summarize what was seen
+    pub fn finish(self) -> QueueDepthStats {
+        self.stop.store(true, Ordering::Relaxed);
+        let samples = self.handle.join().unwrap_or_default();
+        QueueDepthStats::from_samples(&samples)
+    }
+}
+
+/// Re-enumerate `roots` after an overflow when `--rescan-on-overflow` is set
+pub fn recover_from_overflow(collected: &mut CollectedEvents, roots: &[PathBuf], options: &Options) {
+    if options.rescan_on_overflow {
+        collected.recover_with(|| match enumerate_files(roots, options) {
+            Ok(files) => files.len(),
+            Err(e) => {
+                eprintln!("Recovery re-enumeration failed: {}", e);
+                0
+            },
+        });
+    }
+}
+
+/// Drain `rx` on a background thread for `duration` and hand back everything received
+///
+/// Each item is followed by `consumer_delay` of simulated processing.
+pub fn spawn_event_collector(
+    rx: EventReceiver,
+    duration: Duration,
+    consumer_delay: Duration,
+) -> mpsc::Receiver<CollectedEvents> {
+    let (event_tx, event_rx) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        let _ = event_tx.send(collect_events_paced(&rx, duration, consumer_delay));
+    });
+
+    event_rx
+}
+
+/// Append a marker line to each file, pausing `delay` between writes
+///
+/// Returns when each successful write finished, for latency measurements.
+pub fn append_to_files<P: AsRef<Path>>(files: &[P], delay: Duration) -> Vec<WriteRecord> {
+    let mut writes = Vec::with_capacity(files.len());
+    for (i, file_path) in files.iter().enumerate() {
+        let file_path = file_path.as_ref();
+        // Append to file
+        if let Ok(mut content) = fs::read_to_string(file_path) {
+            content.push_str(&format!("\n// Modified by test {}", i));
+            let started_at = Instant::now();
+            match fs::write(file_path, content) {
+                Ok(()) => writes.push(WriteRecord {
+                    path: file_path.to_path_buf(),
+                    started_at,
+                    written_at: Instant::now(),
+                }),
+                Err(e) => eprintln!("   Failed to modify {}: {}", file_path.display(), e),
+            }
+        }
+        // Small delay between modifications, none when writing back to back
+        if !delay.is_zero() {
+            std::thread::sleep(delay);
+        }
+    }
+    writes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watch_time_distribution() {
+        let mut times = vec![Duration::from_micros(500); EARLY_WATCHES];
+        times.extend(vec![Duration::from_micros(10); 400]);
+        let distribution = WatchTimeDistribution::new(&times).unwrap();
+        assert_eq!(distribution.early.p50, Duration::from_micros(500));
+        assert_eq!(distribution.rest.unwrap().p50, Duration::from_micros(10));
+        assert_eq!(distribution.early_slowdown(), Some(50.0));
+
+        let few = WatchTimeDistribution::new(&times[..3]).unwrap();
+        assert!(few.rest.is_none());
+        assert!(WatchTimeDistribution::new(&[]).is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_keeps_symlink_loops_as_links() {
+        let dir = std::env::temp_dir().join(format!("watcher-copy-loop-{}", std::process::id()));
+        let copy = dir.with_extension("copy");
+        fs::create_dir_all(dir.join("a")).unwrap();
+        fs::write(dir.join("a/1.js"), "").unwrap();
+        std::os::unix::fs::symlink("..", dir.join("a/up")).unwrap();
+        std::os::unix::fs::symlink("missing", dir.join("dangling")).unwrap();
+
+        copy_dir_recursive(&dir, &copy, &Options::default()).unwrap();
+        let link = copy.join("a/up");
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::canonicalize(&link).unwrap(), fs::canonicalize(&copy).unwrap());
+        assert!(fs::symlink_metadata(copy.join("dangling")).is_ok());
+        assert_eq!(walk_files(&copy).collect::<Vec<_>>(), [copy.join("a/1.js")]);
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&copy).unwrap();
+    }
+
+    #[test]
+    fn test_enumerate_roots_caps_files() {
+        let dir = std::env::temp_dir().join(format!("watcher-max-files-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for i in 0..3 {
+            fs::write(dir.join(format!("{}.js", i)), "").unwrap();
+        }
+        let roots = [dir.clone()];
+        let mut options = Options {
+            max_files: Some(2),
+            ..Options::default()
+        };
+        assert!(enumerate_roots(&roots, &options).is_err());
+        let copy = dir.with_extension("copy");
+        assert!(copy_dir_recursive(&dir, &copy, &options).is_err());
+        assert!(!copy.exists());
+        // The watch-limit flag alone doesn't lift the file cap
+        options.allow_partial = true;
+        assert!(enumerate_roots(&roots, &options).is_err());
+        options.truncate_files = true;
+        assert_eq!(enumerate_roots(&roots, &options).unwrap()[0].files.len(), 2);
+        copy_dir_recursive(&dir, &copy, &options).unwrap();
+        assert_eq!(walk_files(&copy).count(), 2);
+        fs::remove_dir_all(&copy).unwrap();
+        options.max_files = Some(3);
+        options.truncate_files = false;
+        assert_eq!(enumerate_roots(&roots, &options).unwrap()[0].files.len(), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_watched_files_follow_sort_enumeration() {
+        let dir = std::env::temp_dir().join(format!("watcher-sorted-subset-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for i in (0..25).rev() {
+            fs::write(dir.join(format!("{:02}.js", i)), "").unwrap();
+        }
+        let options = Options {
+            sort_enumeration: true,
+            ..Options::default()
+        };
+        let expected: Vec<PathBuf> = ["00.js", "10.js", "20.js"].iter().map(|f| dir.join(f)).collect();
+        assert_eq!(watched_files(WatcherMode::ManualFiltered, &dir, &options).unwrap(), expected);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_get_filtered_files() {
+        let files: Vec<PathBuf> = (0..100)
+            .map(|i| PathBuf::from(format!("file{}.txt", i)))
+            .collect();
+
+        let filtered = get_filtered_files(&files, 10);
+        assert_eq!(filtered.len(), 10); // Should get every 10th file
+
+        let filtered = get_filtered_files(&files, 5);
+        assert_eq!(filtered.len(), 20); // Should get every 5th file
+    }
+}
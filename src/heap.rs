@@ -0,0 +1,132 @@
+#[cfg(feature = "heap-counters")]
+use std::alloc::{GlobalAlloc, Layout};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+#[cfg(all(feature = "jemalloc", feature = "mimalloc"))]
+compile_error!("enable at most one of the jemalloc and mimalloc features");
+
+#[cfg(feature = "mimalloc")]
+pub use mimalloc::MiMalloc as Inner;
+#[cfg(not(any(feature = "jemalloc", feature = "mimalloc")))]
+pub use std::alloc::System as Inner;
+#[cfg(feature = "jemalloc")]
+pub use tikv_jemallocator::Jemalloc as Inner;
+
+/// Allocator under the counters, chosen with the `jemalloc` and `mimalloc` features
+pub const ALLOCATOR_NAME: &str = if cfg!(feature = "jemalloc") {
+    "jemalloc"
+} else if cfg!(feature = "mimalloc") {
+    "mimalloc"
+} else {
+    "system"
+};
+
+/// Whether the `heap-counters` feature installed `CountingAllocator`; without it
+/// every `AllocWindow` reads zero
+pub const COUNTING: bool = cfg!(feature = "heap-counters");
+
+/// Fail a mode whose results are nothing but heap counts when they aren't being kept
+pub fn require_counters(mode: &str) -> Result<(), String> {
+    if COUNTING {
+        Ok(())
+    } else {
+        Err(format!("{} reads the heap counters; rebuild with --features heap-counters", mode))
+    }
+}
+
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+static PEAK_BYTES: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static BYTES_REQUESTED: AtomicU64 = AtomicU64::new(0);
+
+/// Global allocator that keeps running counts of live heap bytes and allocations
+///
+/// Installed as the global allocator by the `heap-counters` feature so memory
+/// benchmarks can read exactly what a structure holds; the relaxed counters cost a
+/// few nanoseconds per allocation, which every other mode would pay too.
+#[cfg(feature = "heap-counters")]
+pub struct CountingAllocator;
+
+#[cfg(feature = "heap-counters")]
+fn grow(size: usize) {
+    let live = LIVE_BYTES.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK_BYTES.fetch_max(live, Ordering::Relaxed);
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    BYTES_REQUESTED.fetch_add(size as u64, Ordering::Relaxed);
+}
+
+#[cfg(feature = "heap-counters")]
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = Inner.alloc(layout);
+        if !ptr.is_null() {
+            grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = Inner.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        Inner.dealloc(ptr, layout);
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = Inner.realloc(ptr, layout, new_size);
+        if !new.is_null() {
+            LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+            grow(new_size);
+        }
+        new
+    }
+}
+
+/// Heap activity between `AllocWindow::start` and `finish`, across the whole process
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AllocStats {
+    /// Allocations and reallocations made
+    pub allocations: u64,
+    /// Bytes those asked for, whether or not they were freed again
+    pub bytes: u64,
+    /// Growth in live heap bytes, i.e. what is still held at the end
+    pub retained_bytes: usize,
+    /// Most live heap bytes above the starting point at any moment, temporaries included
+    pub peak_bytes: usize,
+}
+
+/// Counter readings to measure a stretch of code against
+#[derive(Debug, Clone, Copy)]
+pub struct AllocWindow {
+    allocations: u64,
+    bytes: u64,
+    live: usize,
+}
+
+impl AllocWindow {
+    /// Start counting; this also restarts the peak, so windows shouldn't overlap
+    pub fn start() -> Self {
+        let live = LIVE_BYTES.load(Ordering::Relaxed);
+        PEAK_BYTES.store(live, Ordering::Relaxed);
+        Self {
+            allocations: ALLOCATIONS.load(Ordering::Relaxed),
+            bytes: BYTES_REQUESTED.load(Ordering::Relaxed),
+            live,
+        }
+    }
+
+    pub fn finish(&self) -> AllocStats {
+        AllocStats {
+            allocations: ALLOCATIONS.load(Ordering::Relaxed) - self.allocations,
+            bytes: BYTES_REQUESTED.load(Ordering::Relaxed) - self.bytes,
+            retained_bytes: LIVE_BYTES.load(Ordering::Relaxed).saturating_sub(self.live),
+            peak_bytes: PEAK_BYTES.load(Ordering::Relaxed).saturating_sub(self.live),
+        }
+    }
+}
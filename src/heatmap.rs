@@ -0,0 +1,298 @@
+use crate::aggregate::changed_dir;
+use crate::charts::escape;
+use notify::Event;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+use std::path::{Component, Path, PathBuf};
+
+/// Directory levels the terminal heatmap shows below each root
+const HEATMAP_DEPTH: usize = 4;
+
+/// Busiest subdirectories listed per directory; the rest are summed on one line
+const HEATMAP_CHILDREN: usize = 8;
+
+/// Width of a bar standing for every event in the run
+const BAR_WIDTH: usize = 24;
+
+const TREEMAP_WIDTH: f64 = 960.0;
+const TREEMAP_HEIGHT: f64 = 600.0;
+
+/// Directory levels the treemap subdivides below each root
+const TREEMAP_DEPTH: usize = 8;
+
+/// Events in one directory's subtree, and its subdirectories that saw any
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct DensityNode {
+    events: usize,
+    children: BTreeMap<String, DensityNode>,
+}
+
+impl DensityNode {
+    /// Subdirectories with events, busiest first, ties by name
+    fn busiest(&self) -> Vec<(&String, &DensityNode)> {
+        let mut children: Vec<_> = self.children.iter().collect();
+        children.sort_by(|a, b| b.1.events.cmp(&a.1.events).then(a.0.cmp(b.0)));
+        children
+    }
+}
+
+/// Shade for a subtree holding `share` of the run's events
+fn shade(share: f64) -> char {
+    match share {
+        s if s >= 0.5 => '█',
+        s if s >= 0.2 => '▓',
+        s if s >= 0.05 => '▒',
+        _ => '░',
+    }
+}
+
+/// Fill colour for a subtree, from pale yellow when quiet to dark red for the hottest,
+/// on a log scale so a few noisy directories don't wash out the rest
+fn heat_color(events: usize, total: usize) -> String {
+    let heat = if total > 1 {
+        (1.0 + events as f64).ln() / (1.0 + total as f64).ln()
+    } else {
+        1.0
+    };
+    let channel = |cold: f64, hot: f64| (cold + (hot - cold) * heat).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", channel(255.0, 189.0), channel(255.0, 0.0), channel(204.0, 38.0))
+}
+
+/// Event density over the directory tree below each root
+///
+/// Each event counts once against every directory above the one it happened
+/// in, so a directory's number is the traffic of its whole subtree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heatmap {
+    roots: Vec<(PathBuf, DensityNode)>,
+}
+
+impl Heatmap {
+    pub fn new(roots: &[PathBuf]) -> Self {
+        Self {
+            // Event paths are absolute whatever form the roots were given in
+            roots: roots
+                .iter()
+                .map(|root| (std::path::absolute(root).unwrap_or_else(|_| root.clone()), DensityNode::default()))
+                .collect(),
+        }
+    }
+
+    /// Heatmap of `events` below `roots`
+    pub fn from_events<'a>(roots: &[PathBuf], events: impl IntoIterator<Item = &'a Event>) -> Self {
+        let mut heatmap = Self::new(roots);
+        for event in events {
+            heatmap.record(event);
+        }
+        heatmap
+    }
+
+    /// Count `event` once against each directory it touched; paths outside every root are ignored
+    pub fn record(&mut self, event: &Event) {
+        let dirs: HashSet<&Path> = event.paths.iter().map(|path| changed_dir(path)).collect();
+        for dir in dirs {
+            let Some((relative, node)) = self
+                .roots
+                .iter_mut()
+                .find_map(|(root, node)| Some((dir.strip_prefix(root).ok()?, node)))
+            else {
+                continue;
+            };
+            let mut node = node;
+            node.events += 1;
+            for component in relative.components() {
+                if let Component::Normal(name) = component {
+                    node = node.children.entry(name.to_string_lossy().into_owned()).or_default();
+                    node.events += 1;
+                }
+            }
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.roots.iter().map(|(_, node)| node.events).sum()
+    }
+
+    /// Print each root's tree with a shaded bar per directory, busiest subtrees first
+    pub fn print(&self, indent: &str) {
+        let total = self.total();
+        if total == 0 {
+            return;
+        }
+        println!("{}Event heatmap:", indent);
+        for (root, node) in self.roots.iter().filter(|(_, node)| node.events > 0) {
+            print_node(&root.display().to_string(), node, total, 0, indent);
+        }
+    }
+
+    /// Standalone SVG treemap: each directory's area is its share of the events, its colour how hot it is
+    pub fn render_svg(&self, title: &str) -> String {
+        let total = self.total();
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif" font-size="11">"#,
+            w = TREEMAP_WIDTH,
+            h = TREEMAP_HEIGHT + 30.0
+        );
+        let _ = writeln!(
+            svg,
+            r#"<text x="4" y="18" font-size="13" font-weight="bold">{} ({} events)</text>"#,
+            escape(title),
+            total
+        );
+        let roots: Vec<(String, &DensityNode)> = self
+            .roots
+            .iter()
+            .filter(|(_, node)| node.events > 0)
+            .map(|(root, node)| (root.display().to_string(), node))
+            .collect();
+        let rect = Rect {
+            x: 0.0,
+            y: 30.0,
+            w: TREEMAP_WIDTH,
+            h: TREEMAP_HEIGHT,
+        };
+        layout_slices(&mut svg, &roots, total, rect, total, 0);
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+fn print_node(name: &str, node: &DensityNode, total: usize, depth: usize, indent: &str) {
+    let share = node.events as f64 / total as f64;
+    let bar = shade(share).to_string().repeat(((share * BAR_WIDTH as f64).round() as usize).max(1));
+    let label = format!("{}{}", "  ".repeat(depth), name);
+    println!(
+        "{}  {:<44} {:<width$} {:>8} {:>6.1}%",
+        indent,
+        label,
+        bar,
+        node.events,
+        share * 100.0,
+        width = BAR_WIDTH
+    );
+    if depth + 1 >= HEATMAP_DEPTH {
+        return;
+    }
+    let children = node.busiest();
+    for (child_name, child) in children.iter().take(HEATMAP_CHILDREN) {
+        print_node(&format!("{}/", child_name), child, total, depth + 1, indent);
+    }
+    if children.len() > HEATMAP_CHILDREN {
+        let rest: usize = children[HEATMAP_CHILDREN..].iter().map(|(_, child)| child.events).sum();
+        println!(
+            "{}  {}… {} more directories, {} events",
+            indent,
+            "  ".repeat(depth + 1),
+            children.len() - HEATMAP_CHILDREN,
+            rest
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Rect {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+}
+
+/// Split `rect` between `nodes` by their events, across its longer side, and recurse into each
+///
+/// `events` is what the whole of `rect` stands for; a parent's own events keep
+/// the part its children leave uncovered.
+fn layout_slices(svg: &mut String, nodes: &[(String, &DensityNode)], events: usize, rect: Rect, total: usize, depth: usize) {
+    let mut offset = 0.0;
+    for (name, node) in nodes {
+        let fraction = node.events as f64 / events.max(1) as f64;
+        let slice = if rect.w >= rect.h {
+            Rect {
+                x: rect.x + offset * rect.w,
+                w: fraction * rect.w,
+                ..rect
+            }
+        } else {
+            Rect {
+                y: rect.y + offset * rect.h,
+                h: fraction * rect.h,
+                ..rect
+            }
+        };
+        offset += fraction;
+        let _ = writeln!(
+            svg,
+            r##"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}" stroke="#fff"><title>{} ({} events)</title></rect>"##,
+            slice.x,
+            slice.y,
+            slice.w,
+            slice.h,
+            heat_color(node.events, total),
+            escape(name),
+            node.events
+        );
+        if slice.w > 60.0 && slice.h > 14.0 {
+            let _ = writeln!(
+                svg,
+                r#"<text x="{:.1}" y="{:.1}">{}</text>"#,
+                slice.x + 3.0,
+                slice.y + 12.0,
+                escape(name)
+            );
+        }
+        if depth + 1 < TREEMAP_DEPTH && slice.w > 4.0 && slice.h > 4.0 {
+            let children: Vec<(String, &DensityNode)> = node
+                .busiest()
+                .into_iter()
+                .map(|(child_name, child)| (format!("{}/{}", name, child_name), child))
+                .collect();
+            // Inset so each level's label stays visible above its children
+            let inner = Rect {
+                x: slice.x + 2.0,
+                y: slice.y + 16.0,
+                w: (slice.w - 4.0).max(0.0),
+                h: (slice.h - 18.0).max(0.0),
+            };
+            layout_slices(svg, &children, node.events, inner, total, depth + 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::EventKind;
+
+    #[test]
+    fn test_heatmap_counts_subtrees() {
+        let event = |path: &str| Event::new(EventKind::Any).add_path(PathBuf::from(path));
+        let heatmap = Heatmap::from_events(
+            &[PathBuf::from("/repo")],
+            &[
+                event("/repo/src/a.rs"),
+                event("/repo/src/ui/b.rs"),
+                event("/repo/src/ui/c.rs"),
+                event("/repo/README.md"),
+                event("/elsewhere/x"),
+            ],
+        );
+        assert_eq!(heatmap.total(), 4);
+        let root = &heatmap.roots[0].1;
+        let src = &root.children["src"];
+        assert_eq!((src.events, src.children["ui"].events), (3, 2));
+        assert_eq!(root.busiest()[0].0, "src");
+
+        let svg = heatmap.render_svg("Native <recursive>");
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        assert!(svg.contains("Native &lt;recursive&gt; (4 events)"));
+        assert!(svg.contains("/repo/src/ui (2 events)"));
+    }
+
+    #[test]
+    fn test_heat_scale() {
+        assert_eq!(heat_color(100, 100), "#bd0026");
+        assert_eq!(heat_color(0, 100), "#ffffcc");
+        assert_eq!((shade(0.6), shade(0.01)), ('█', '░'));
+    }
+}
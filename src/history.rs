@@ -0,0 +1,655 @@
+use crate::charts::{render_svg, sparkline, Chart, Series};
+use crate::harness::{report_walk_errors, walk_root};
+use crate::heap::ALLOCATOR_NAME;
+use crate::options::Options;
+use crate::trend::LinearTrend;
+use crate::report::{compare, print_deltas};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS runs (
+        id TEXT PRIMARY KEY,
+        started_at_ms INTEGER NOT NULL,
+        command TEXT NOT NULL,
+        args TEXT NOT NULL,
+        tree_fingerprint TEXT NOT NULL,
+        tree_files INTEGER NOT NULL,
+        environment TEXT NOT NULL,
+        os TEXT NOT NULL,
+        arch TEXT NOT NULL,
+        kernel TEXT NOT NULL,
+        hostname TEXT NOT NULL,
+        cpus INTEGER NOT NULL,
+        notify_version TEXT NOT NULL,
+        benchmark_version TEXT NOT NULL,
+        status TEXT NOT NULL,
+        allocator TEXT NOT NULL DEFAULT 'system'
+    );
+    CREATE TABLE IF NOT EXISTS measurements (
+        run_id TEXT NOT NULL REFERENCES runs(id),
+        watcher_mode TEXT NOT NULL,
+        metric TEXT NOT NULL,
+        value REAL NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS runs_by_tree ON runs(tree_fingerprint, started_at_ms);
+    CREATE INDEX IF NOT EXISTS measurements_by_run ON measurements(run_id);
+";
+
+/// Version of the store's schema this build writes, kept in `PRAGMA user_version`
+///
+/// Stores created before it was set are version 1.
+const STORE_VERSION: u32 = 3;
+
+/// Schema changes taking a store from version `n + 1` to `n + 2`, run in turn
+const MIGRATIONS: [&str; STORE_VERSION as usize - 1] = [
+    // Version 1 stores already have the version 2 tables
+    "",
+    "ALTER TABLE runs ADD COLUMN allocator TEXT NOT NULL DEFAULT 'system';",
+];
+
+/// One recorded value, e.g. `Native Recursive` / `setup_us` / `812.0`
+///
+/// Metric names carry their unit; a run may record a metric several times, once
+/// per iteration.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Measurement {
+    pub watcher_mode: String,
+    pub metric: String,
+    pub value: f64,
+}
+
+/// Where a run happened, so results from different machines aren't compared blindly
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Environment {
+    pub os: String,
+    pub arch: String,
+    pub kernel: String,
+    pub hostname: String,
+    pub cpus: usize,
+    pub notify_version: String,
+    pub benchmark_version: String,
+    /// Global allocator the build was compiled with, e.g. `system` or `jemalloc`
+    pub allocator: String,
+}
+
+impl Environment {
+    pub fn capture() -> Self {
+        let read = |path: &str| fs::read_to_string(path).map(|s| s.trim().to_string()).ok();
+        Self {
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            kernel: read("/proc/sys/kernel/osrelease").unwrap_or_else(|| "unknown".to_string()),
+            hostname: read("/proc/sys/kernel/hostname")
+                .or_else(|| std::env::var("HOSTNAME").ok())
+                .unwrap_or_else(|| "unknown".to_string()),
+            cpus: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            notify_version: env!("NOTIFY_VERSION").to_string(),
+            benchmark_version: env!("CARGO_PKG_VERSION").to_string(),
+            allocator: ALLOCATOR_NAME.to_string(),
+        }
+    }
+
+    /// Short key runs are grouped by, e.g. `linux-x86_64 6.8.0 on build01 (notify 6.1.1)`,
+    /// naming the allocator when it isn't the system one
+    pub fn key(&self) -> String {
+        let allocator = if self.allocator == "system" {
+            String::new()
+        } else {
+            format!(", {}", self.allocator)
+        };
+        format!(
+            "{}-{} {} on {} (notify {}{})",
+            self.os, self.arch, self.kernel, self.hostname, self.notify_version, allocator
+        )
+    }
+}
+
+/// Identity of the watched tree: a hash of every file's relative path and size
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeFingerprint {
+    pub hash: String,
+    pub files: usize,
+}
+
+impl TreeFingerprint {
+    /// Fingerprint `roots` in order; renaming the tree's top directory doesn't change it
+    ///
+    /// The tree is walked as the benchmark walks it, in name order with `--sort-enumeration`,
+    /// so a directory linked from two places is counted under the same path as the run used.
+    pub fn of(roots: &[PathBuf], options: &Options) -> Self {
+        // FNV-1a, so the value is stable across Rust releases
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut feed = |bytes: &[u8]| {
+            for byte in bytes {
+                hash ^= u64::from(*byte);
+                hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+            }
+        };
+        // The whole tree, not `--max-files` of it: a cut-off walk would fingerprint a different tree
+        let listings: Vec<_> = roots.iter().map(|root| walk_root(root, options).list_up_to(usize::MAX)).collect();
+        report_walk_errors(roots, &listings, "");
+        let mut files = 0;
+        for (i, (root, listing)) in roots.iter().zip(listings).enumerate() {
+            let mut entries: Vec<(String, u64)> = listing
+                .files
+                .into_iter()
+                .map(|path| {
+                    let size = fs::metadata(&path).map_or(0, |m| m.len());
+                    let relative = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().into_owned();
+                    (relative, size)
+                })
+                .collect();
+            entries.sort();
+            files += entries.len();
+            feed(&(i as u64).to_le_bytes());
+            for (relative, size) in entries {
+                feed(relative.as_bytes());
+                feed(&[0]);
+                feed(&size.to_le_bytes());
+            }
+        }
+        Self {
+            hash: format!("{:016x}", hash),
+            files,
+        }
+    }
+}
+
+/// Everything recorded about one invocation
+#[derive(Debug, Clone)]
+pub struct RunRecord {
+    pub id: String,
+    pub started_at_ms: i64,
+    /// The `<mode>` argument
+    pub command: String,
+    /// Flags after the mode, space-separated
+    pub args: String,
+    pub measurements: Vec<Measurement>,
+}
+
+impl RunRecord {
+    pub fn new(command: &str, args: &[String]) -> Self {
+        let started_at_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as i64);
+        Self {
+            id: format!("{}-{}", started_at_ms, std::process::id()),
+            started_at_ms,
+            command: command.to_string(),
+            args: args.join(" "),
+            measurements: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, watcher_mode: &str, metric: &str, value: f64) {
+        self.measurements.push(Measurement {
+            watcher_mode: watcher_mode.to_string(),
+            metric: metric.to_string(),
+            value,
+        });
+    }
+
+    /// Record `duration` in microseconds; `metric` should end in `_us`
+    pub fn add_duration(&mut self, watcher_mode: &str, metric: &str, duration: Duration) {
+        self.add(watcher_mode, metric, duration.as_secs_f64() * 1e6);
+    }
+}
+
+/// A run as read back from the store
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredRun {
+    pub id: String,
+    pub started_at_ms: i64,
+    pub command: String,
+    pub args: String,
+    pub tree_fingerprint: String,
+    pub tree_files: usize,
+    pub environment: String,
+    pub status: String,
+    pub measurements: usize,
+}
+
+/// SQLite database every run can be appended to with `--history`
+pub struct History {
+    conn: Connection,
+}
+
+impl History {
+    /// Open or create the store at `path`, migrating one written by an older build
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        let mut conn = Connection::open(path)?;
+        let version = store_version(&conn)?;
+        if version > STORE_VERSION {
+            return Err(rusqlite::Error::SqliteFailure(
+                rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_MISMATCH),
+                Some(format!(
+                    "history store is format version {}, newer than this build reads (up to {})",
+                    version, STORE_VERSION
+                )),
+            ));
+        }
+        let tx = conn.transaction()?;
+        for migration in &MIGRATIONS[version as usize - 1..] {
+            tx.execute_batch(migration)?;
+        }
+        tx.execute_batch(SCHEMA)?;
+        tx.pragma_update(None, "user_version", STORE_VERSION)?;
+        tx.commit()?;
+        Ok(Self { conn })
+    }
+
+    /// Store `run` and its measurements in one transaction
+    pub fn append(
+        &mut self,
+        run: &RunRecord,
+        tree: &TreeFingerprint,
+        environment: &Environment,
+        status: &str,
+    ) -> rusqlite::Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO runs (id, started_at_ms, command, args, tree_fingerprint, tree_files,
+                 environment, os, arch, kernel, hostname, cpus, notify_version, benchmark_version, status, allocator)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+            params![
+                run.id,
+                run.started_at_ms,
+                run.command,
+                run.args,
+                tree.hash,
+                tree.files as i64,
+                environment.key(),
+                environment.os,
+                environment.arch,
+                environment.kernel,
+                environment.hostname,
+                environment.cpus as i64,
+                environment.notify_version,
+                environment.benchmark_version,
+                status,
+                environment.allocator,
+            ],
+        )?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO measurements (run_id, watcher_mode, metric, value) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for m in &run.measurements {
+                insert.execute(params![run.id, m.watcher_mode, m.metric, m.value])?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// Most recent runs first, optionally only those on one tree
+    pub fn recent_runs(&self, tree_fingerprint: Option<&str>, limit: usize) -> rusqlite::Result<Vec<StoredRun>> {
+        let mut query = self.conn.prepare(
+            "SELECT r.id, r.started_at_ms, r.command, r.args, r.tree_fingerprint, r.tree_files,
+                    r.environment, r.status, COUNT(m.run_id)
+             FROM runs r LEFT JOIN measurements m ON m.run_id = r.id
+             WHERE ?1 IS NULL OR r.tree_fingerprint = ?1
+             GROUP BY r.id
+             ORDER BY r.started_at_ms DESC
+             LIMIT ?2",
+        )?;
+        let rows = query.query_map(params![tree_fingerprint, limit as i64], |row| {
+            Ok(StoredRun {
+                id: row.get(0)?,
+                started_at_ms: row.get(1)?,
+                command: row.get(2)?,
+                args: row.get(3)?,
+                tree_fingerprint: row.get(4)?,
+                tree_files: row.get::<_, i64>(5)? as usize,
+                environment: row.get(6)?,
+                status: row.get(7)?,
+                measurements: row.get::<_, i64>(8)? as usize,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Every measurement of one run, in recording order
+    pub fn measurements(&self, run_id: &str) -> rusqlite::Result<Vec<Measurement>> {
+        let mut query = self.conn.prepare(
+            "SELECT watcher_mode, metric, value FROM measurements WHERE run_id = ?1 ORDER BY rowid",
+        )?;
+        let rows = query.query_map(params![run_id], |row| {
+            Ok(Measurement {
+                watcher_mode: row.get(0)?,
+                metric: row.get(1)?,
+                value: row.get(2)?,
+            })
+        })?;
+        rows.collect()
+    }
+}
+
+/// Format version of the store behind `conn`; a new, empty store is already current
+fn store_version(conn: &Connection) -> rusqlite::Result<u32> {
+    let version: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version > 0 {
+        return Ok(version);
+    }
+    let has_runs: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'runs')",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(if has_runs { 1 } else { STORE_VERSION })
+}
+
+/// One run's mean value of a metric for one mode
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeriesPoint {
+    pub run_id: String,
+    pub started_at_ms: i64,
+    pub notify_version: String,
+    pub watcher_mode: String,
+    pub value: f64,
+}
+
+impl History {
+    /// `metric` per run and mode on one tree, oldest run first
+    pub fn series(&self, tree_fingerprint: &str, metric: &str) -> rusqlite::Result<Vec<SeriesPoint>> {
+        let mut query = self.conn.prepare(
+            "SELECT r.id, r.started_at_ms, r.notify_version, m.watcher_mode, AVG(m.value)
+             FROM runs r JOIN measurements m ON m.run_id = r.id
+             WHERE r.tree_fingerprint = ?1 AND m.metric = ?2
+             GROUP BY r.id, m.watcher_mode
+             ORDER BY r.started_at_ms, m.watcher_mode",
+        )?;
+        let rows = query.query_map(params![tree_fingerprint, metric], |row| {
+            Ok(SeriesPoint {
+                run_id: row.get(0)?,
+                started_at_ms: row.get(1)?,
+                notify_version: row.get(2)?,
+                watcher_mode: row.get(3)?,
+                value: row.get(4)?,
+            })
+        })?;
+        rows.collect()
+    }
+}
+
+/// Metrics `trend` charts, with their display names
+const TREND_METRICS: [(&str, &str); 2] = [("setup_us", "Setup time (µs)"), ("latency_p50_us", "Latency p50 (µs)")];
+
+/// Runs in `points` where the notify version differs from the run before, as (run index, version)
+fn version_changes(points: &[SeriesPoint], runs: &[String]) -> Vec<(usize, String)> {
+    let mut changes = Vec::new();
+    let mut previous: Option<&str> = None;
+    for (i, run) in runs.iter().enumerate() {
+        let Some(point) = points.iter().find(|p| &p.run_id == run) else {
+            continue;
+        };
+        if previous != Some(point.notify_version.as_str()) {
+            changes.push((i, point.notify_version.clone()));
+            previous = Some(&point.notify_version);
+        }
+    }
+    changes
+}
+
+/// Chart setup time and latency per mode across the runs recorded for `dir`'s tree
+///
+/// Each run is one step along the x axis; dashed markers (SVG) and the version
+/// line (terminal) show where the notify release changed, and a significant
+/// upward fit flags a likely regression.
+pub fn run_trend(dir: &Path, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    let path = options
+        .history
+        .as_ref()
+        .ok_or("trend needs --history <db> to read from")?;
+    let history = History::open(path)?;
+    let tree = TreeFingerprint::of(&[dir.to_path_buf()], options);
+    println!("\n=== Trends ({}) ===", path.display());
+    println!("Tree {}: {} files, fingerprint {}", dir.display(), tree.files, tree.hash);
+
+    let mut charts = Vec::new();
+    for (metric, title) in TREND_METRICS {
+        let points = history.series(&tree.hash, metric)?;
+        if points.is_empty() {
+            continue;
+        }
+        // Run order along the x axis, shared by every mode
+        let mut runs: Vec<String> = Vec::new();
+        for point in &points {
+            if !runs.contains(&point.run_id) {
+                runs.push(point.run_id.clone());
+            }
+        }
+        let run_index = |id: &str| runs.iter().position(|r| r == id).unwrap_or(0);
+        let changes = version_changes(&points, &runs);
+
+        println!("\n📈 {} over {} runs:", title, runs.len());
+        println!(
+            "  notify: {}",
+            changes
+                .iter()
+                .map(|(i, version)| format!("{} from run {}", version, i + 1))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        println!(
+            "  {:<20} {:>5} {:>10} {:>10} {:>9} {:>8}  Sparkline",
+            "Mode", "Runs", "First", "Last", "Change", "Rising"
+        );
+
+        let mut modes: Vec<&str> = points.iter().map(|p| p.watcher_mode.as_str()).collect();
+        modes.sort_unstable();
+        modes.dedup();
+        let mut series = Vec::new();
+        for mode in modes {
+            let mode_points: Vec<(f64, f64)> = points
+                .iter()
+                .filter(|p| p.watcher_mode == mode)
+                .map(|p| (run_index(&p.run_id) as f64, p.value))
+                .collect();
+            let values: Vec<f64> = mode_points.iter().map(|p| p.1).collect();
+            let (first, last) = (values[0], values[values.len() - 1]);
+            let rising = LinearTrend::fit(&mode_points).map_or("-", |trend| {
+                if trend.is_significant_growth() { "yes" } else { "no" }
+            });
+            println!(
+                "  {:<20} {:>5} {:>10.1} {:>10.1} {:>9} {:>8}  {}",
+                mode,
+                values.len(),
+                first,
+                last,
+                if first != 0.0 { format!("{:+.1}%", (last - first) / first * 100.0) } else { "-".to_string() },
+                rising,
+                sparkline(&values)
+            );
+            series.push(Series {
+                name: mode.to_string(),
+                points: mode_points,
+            });
+        }
+
+        charts.push(Chart {
+            title: title.to_string(),
+            series,
+            // The first run's version is the baseline, not a change
+            markers: changes
+                .iter()
+                .skip(1)
+                .map(|(i, version)| (*i as f64, format!("notify {}", version)))
+                .collect(),
+        });
+    }
+
+    if charts.is_empty() {
+        println!("No setup or latency measurements recorded for this tree");
+        return Ok(());
+    }
+    if let Some(svg_path) = &options.trend_svg {
+        fs::write(svg_path, render_svg(&charts))?;
+        println!("\nWrote {}", svg_path.display());
+    }
+
+    Ok(())
+}
+
+fn format_time(ms: i64) -> String {
+    let secs = ms / 1000;
+    let (days, day_secs) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+    // Civil date from days since the epoch (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        day_secs / 3600,
+        day_secs % 3600 / 60
+    )
+}
+
+/// List past runs on `dir`'s tree, or compare two runs with `--compare-runs`
+pub fn run_history(dir: &Path, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    let path = options
+        .history
+        .as_ref()
+        .ok_or("history needs --history <db> to read from")?;
+    let history = History::open(path)?;
+
+    if let Some((a, b)) = &options.compare_runs {
+        println!("\n=== Comparing runs {} and {} ===", a, b);
+        let deltas = compare(&history.measurements(a)?, &history.measurements(b)?, options.regression_threshold);
+        if deltas.is_empty() {
+            return Err(format!("no measurements recorded for {} or {}", a, b).into());
+        }
+        print_deltas(&deltas, a, b);
+        return Ok(());
+    }
+
+    let tree = TreeFingerprint::of(&[dir.to_path_buf()], options);
+    println!("\n=== Run History ({}) ===", path.display());
+    println!("Tree {}: {} files, fingerprint {}", dir.display(), tree.files, tree.hash);
+    let runs = history.recent_runs(Some(&tree.hash), options.history_limit)?;
+    if runs.is_empty() {
+        println!("No runs recorded for this tree");
+        return Ok(());
+    }
+    println!(
+        "  {:<22} {:<16} {:<18} {:>8} {:<8}  Environment",
+        "Run", "Started (UTC)", "Command", "Values", "Status"
+    );
+    for run in &runs {
+        println!(
+            "  {:<22} {:<16} {:<18} {:>8} {:<8}  {}",
+            run.id,
+            format_time(run.started_at_ms),
+            run.command,
+            run.measurements,
+            run.status,
+            run.environment
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_and_read_back() {
+        let db = std::env::temp_dir().join(format!("watcher-history-{}.db", std::process::id()));
+        let _ = fs::remove_file(&db);
+        let mut history = History::open(&db).unwrap();
+
+        let mut run = RunRecord::new("compare", &["--iterations".to_string(), "2".to_string()]);
+        run.add_duration("Native Recursive", "setup_us", Duration::from_micros(800));
+        run.add_duration("Native Recursive", "setup_us", Duration::from_micros(1200));
+        run.add("Manual Recursive", "events", 5.0);
+        let tree = TreeFingerprint {
+            hash: "abc".to_string(),
+            files: 10,
+        };
+        history.append(&run, &tree, &Environment::capture(), "ok").unwrap();
+
+        let runs = history.recent_runs(Some("abc"), 10).unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!((runs[0].command.as_str(), runs[0].measurements), ("compare", 3));
+        assert!(history.recent_runs(Some("other"), 10).unwrap().is_empty());
+
+        let measurements = history.measurements(&run.id).unwrap();
+        assert_eq!(measurements, run.measurements);
+        let deltas = compare(&measurements, &measurements, 5.0);
+        let setup = deltas.iter().find(|d| d.metric == "setup_us").unwrap();
+        assert!((setup.before.unwrap() - 1000.0).abs() < 1e-6);
+
+        let series = history.series("abc", "setup_us").unwrap();
+        assert_eq!(series.len(), 1);
+        assert!((series[0].value - 1000.0).abs() < 1e-6);
+        let runs = [run.id.clone()];
+        assert_eq!(version_changes(&series, &runs), [(0, env!("NOTIFY_VERSION").to_string())]);
+
+        fs::remove_file(&db).unwrap();
+    }
+
+    #[test]
+    fn test_open_migrates_older_stores() {
+        let db = std::env::temp_dir().join(format!("watcher-history-v1-{}.db", std::process::id()));
+        let _ = fs::remove_file(&db);
+        // A store from before versioning: the tables as they were then, no user_version
+        let v1_schema = SCHEMA.replace(",\n        allocator TEXT NOT NULL DEFAULT 'system'", "");
+        Connection::open(&db).unwrap().execute_batch(&v1_schema).unwrap();
+        assert_eq!(store_version(&Connection::open(&db).unwrap()).unwrap(), 1);
+
+        let mut history = History::open(&db).unwrap();
+        let run = RunRecord::new("native", &[]);
+        let tree = TreeFingerprint {
+            hash: "abc".to_string(),
+            files: 3,
+        };
+        history.append(&run, &tree, &Environment::capture(), "ok").unwrap();
+        let conn = Connection::open(&db).unwrap();
+        assert_eq!(store_version(&conn).unwrap(), STORE_VERSION);
+
+        conn.pragma_update(None, "user_version", STORE_VERSION + 1).unwrap();
+        let error = History::open(&db).err().unwrap().to_string();
+        assert!(error.contains("newer than this build reads"), "{}", error);
+
+        fs::remove_file(&db).unwrap();
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_root_name() {
+        let dirs = ["test_fingerprint_a", "test_fingerprint_b"].map(PathBuf::from);
+        for dir in &dirs {
+            fs::create_dir_all(dir.join("sub")).unwrap();
+            fs::write(dir.join("sub/a.js"), "a").unwrap();
+        }
+        let options = Options::default();
+        let a = TreeFingerprint::of(&dirs[..1], &options);
+        assert_eq!(a, TreeFingerprint::of(&dirs[1..], &options));
+        let sorted = Options {
+            sort_enumeration: true,
+            ..Options::default()
+        };
+        assert_eq!(a, TreeFingerprint::of(&dirs[..1], &sorted));
+        fs::write(dirs[1].join("sub/a.js"), "changed").unwrap();
+        assert_ne!(a.hash, TreeFingerprint::of(&dirs[1..], &options).hash);
+        assert_eq!(format_time(0), "1970-01-01 00:00");
+        assert_eq!(format_time(1_700_000_000_000), "2023-11-14 22:13");
+
+        for dir in &dirs {
+            fs::remove_dir_all(dir).unwrap();
+        }
+    }
+}
@@ -0,0 +1,310 @@
+use crate::budget::{write_targets, LruWatchSet};
+use crate::harness::{enumerate_dir, prepare_scratch_dir, settle_for, start_watcher, SettlingCollector};
+use crate::latency::{match_writes, LatencyReport, WriteRecord};
+use crate::options::Options;
+use crate::recursive_file_watcher::{
+    event_channel, extended_length, EventReceiver, WatchConfig, WatcherGuard, WatcherMode,
+};
+use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::VecDeque;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Recently modified files given their own watch by default
+pub const DEFAULT_HOT_FILES: usize = 64;
+
+/// Modifications made per watcher
+const HYBRID_WRITES: usize = 200;
+
+/// Pause between modifications, shorter than the default debounce so the debounced mode merges repeats
+const HYBRID_WRITE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Per-file watches on the most recently modified files, swapped as others get modified
+struct HotFiles {
+    watcher: RecommendedWatcher,
+    recent: LruWatchSet,
+    capacity: usize,
+    promotions: usize,
+}
+
+impl HotFiles {
+    /// Whether `path` already had its own watch; gives it one otherwise, evicting the
+    /// least recently modified file's
+    fn promote(&mut self, path: &Path) -> bool {
+        if self.recent.touch(path) {
+            return true;
+        }
+        if self.capacity == 0 || !path.is_file() {
+            return false;
+        }
+        if self.watcher.watch(&extended_length(path), RecursiveMode::NonRecursive).is_err() {
+            return false;
+        }
+        if let Some(evicted) = self.recent.insert(path.to_path_buf()) {
+            let _ = self.watcher.unwatch(&extended_length(&evicted));
+        }
+        self.promotions += 1;
+        false
+    }
+}
+
+/// Native recursive watch on the roots plus per-file watches on hot files
+///
+/// A change to a cold file arrives through the root watch and promotes the file;
+/// from then on its own watch reports it and the root watch's copy of the event
+/// is dropped, so a hot file is reported once instead of twice while the rest of
+/// the tree costs no more than one recursive watch.
+pub struct HybridWatcher {
+    root: RecommendedWatcher,
+    hot: Arc<Mutex<HotFiles>>,
+    receiver: EventReceiver,
+}
+
+impl HybridWatcher {
+    pub fn new_with_roots_and_config(roots: &[PathBuf], hot_files: usize, config: &WatchConfig) -> notify::Result<Self> {
+        let start = Instant::now();
+        let (tx, rx) = event_channel(config);
+        let tx = Arc::new(tx);
+
+        let manual_tx = tx.clone();
+        let hot = Arc::new(Mutex::new(HotFiles {
+            watcher: RecommendedWatcher::new(move |res| manual_tx.send(res), Config::default())?,
+            recent: LruWatchSet::new(hot_files),
+            capacity: hot_files,
+            promotions: 0,
+        }));
+
+        let root_hot = hot.clone();
+        let mut root = RecommendedWatcher::new(
+            move |res: notify::Result<notify::Event>| {
+                if let Ok(event) = &res {
+                    if event.kind.is_create() || event.kind.is_modify() {
+                        let mut hot = root_hot.lock().unwrap_or_else(PoisonError::into_inner);
+                        // Every path gets promoted, so no short-circuiting
+                        let reported: Vec<bool> = event.paths.iter().map(|path| hot.promote(path)).collect();
+                        if !reported.is_empty() && reported.iter().all(|&reported| reported) {
+                            return;
+                        }
+                    }
+                }
+                tx.send(res);
+            },
+            Config::default(),
+        )?;
+        for path in roots {
+            root.watch(&extended_length(path), RecursiveMode::Recursive)?;
+        }
+        let setup_time = start.elapsed();
+        println!(
+            "HybridWatcher: Native root watch with up to {} hot file watches, set up in {:?}",
+            hot_files, setup_time
+        );
+
+        Ok(Self {
+            root,
+            hot,
+            receiver: rx,
+        })
+    }
+
+    /// Shared count of files given their own watch so far
+    pub fn promotions(&self) -> PromotionCount {
+        PromotionCount(self.hot.clone())
+    }
+
+    /// Consume self and return a guard keeping both watchers alive, and the receiver
+    pub fn into_parts(self) -> (WatcherGuard, EventReceiver) {
+        (Box::new((self.root, self.hot)), self.receiver)
+    }
+}
+
+/// Promotions made by a `HybridWatcher`, readable after it's been split into parts
+pub struct PromotionCount(Arc<Mutex<HotFiles>>);
+
+impl PromotionCount {
+    pub fn get(&self) -> usize {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).promotions
+    }
+}
+
+/// For each write, whether its file was among the `hot_files` most recently written before it
+pub fn recently_written(targets: &[PathBuf], hot_files: usize) -> Vec<bool> {
+    let mut recent: VecDeque<&PathBuf> = VecDeque::with_capacity(hot_files + 1);
+    targets
+        .iter()
+        .map(|target| {
+            let hot = match recent.iter().position(|path| *path == target) {
+                Some(i) => {
+                    recent.remove(i);
+                    true
+                }
+                None => false,
+            };
+            recent.push_front(target);
+            recent.truncate(hot_files);
+            hot
+        })
+        .collect()
+}
+
+/// Noise and latency of one watcher under the hot-file workload
+#[derive(Debug)]
+pub struct HybridResult {
+    pub name: &'static str,
+    pub events: usize,
+    pub latency: LatencyReport,
+    /// Latency of writes to a file modified shortly before
+    pub hot: LatencyReport,
+    pub cold: LatencyReport,
+    /// Files the hybrid watcher gave their own watch
+    pub promotions: Option<usize>,
+}
+
+/// Make the hot-file workload on a copy of `dir` under one watcher and match events to writes
+fn measure(
+    dir: &Path,
+    name: &'static str,
+    settle: Duration,
+    options: &Options,
+    start: impl FnOnce(&Path) -> notify::Result<(WatcherGuard, EventReceiver, Option<PromotionCount>)>,
+) -> Result<HybridResult, Box<dyn std::error::Error>> {
+    println!("\n--- {} ---", name);
+    let scratch = std::path::absolute(prepare_scratch_dir(dir, "hybrid", options)?)?;
+    let result = (|| -> Result<_, Box<dyn std::error::Error>> {
+        let files = enumerate_dir(&scratch, options)?;
+        let (_watcher, rx, promotions) = start(&scratch)?;
+        let collector = SettlingCollector::spawn(rx, settle, options.max_collect, options.consumer_delay);
+        std::thread::sleep(options.stabilize);
+
+        let targets = write_targets(&files, HYBRID_WRITES, options.seed);
+        let mut writes = Vec::with_capacity(targets.len());
+        for target in &targets {
+            let started_at = Instant::now();
+            fs::OpenOptions::new().append(true).open(target)?.write_all(b"hybrid\n")?;
+            writes.push(WriteRecord {
+                path: target.clone(),
+                started_at,
+                written_at: Instant::now(),
+            });
+            std::thread::sleep(HYBRID_WRITE_INTERVAL);
+        }
+        collector.workload_done();
+        let settled = collector.finish().ok_or("event collector stopped")?;
+        Ok((targets, writes, settled, promotions))
+    })();
+    fs::remove_dir_all(&scratch)?;
+    let (targets, writes, settled, promotions) = result?;
+
+    let hot = recently_written(&targets, options.hot_files);
+    let subset = |wanted: bool| -> Vec<WriteRecord> {
+        writes.iter().zip(&hot).filter(|(_, &hot)| hot == wanted).map(|(write, _)| write.clone()).collect()
+    };
+    let result = HybridResult {
+        name,
+        events: settled.collected.events.len(),
+        latency: match_writes(&writes, &settled.collected),
+        hot: match_writes(&subset(true), &settled.collected),
+        cold: match_writes(&subset(false), &settled.collected),
+        promotions: promotions.map(|count| count.get()),
+    };
+    println!(
+        "   {} events for {} writes ({} hot), {} unmatched",
+        result.events,
+        writes.len(),
+        result.hot.samples.len() + result.hot.unmatched,
+        result.latency.unmatched
+    );
+    Ok(result)
+}
+
+/// Compare native, debounced and hybrid watching under writes clustered on a few hot files
+///
+/// Native mode reports everything fast but noisily, debounced mode merges the
+/// noise at the cost of latency; the hybrid watches the tree natively and gives
+/// the `--hot-files` most recently modified files their own watch, reporting each
+/// of their changes once. Writes are split into hot (the file was among those
+/// modified just before) and cold.
+pub fn run_hybrid_test(dir: &Path, options: &Options) -> Result<Vec<HybridResult>, Box<dyn std::error::Error>> {
+    println!("\n=== Hybrid Hot-File Watching ===");
+    println!("Source directory: {}", dir.display());
+    println!("Hot files: {}, debounce: {:?}", options.hot_files, options.debounce);
+
+    let mut results = Vec::new();
+    for mode in [WatcherMode::Native, WatcherMode::Debounced] {
+        results.push(measure(dir, mode.display_name(), settle_for(mode, options), options, |root| {
+            let (watcher, rx) = start_watcher(mode, root, options)?;
+            Ok((watcher, rx, None))
+        })?);
+    }
+    let settle = settle_for(WatcherMode::Native, options);
+    results.push(measure(dir, "Hybrid", settle, options, |root| {
+        let watcher = HybridWatcher::new_with_roots_and_config(
+            &[root.to_path_buf()],
+            options.hot_files,
+            &options.watch_config(),
+        )?;
+        let promotions = watcher.promotions();
+        let (watcher, rx) = watcher.into_parts();
+        Ok((watcher, rx, Some(promotions)))
+    })?);
+
+    let p = |report: &LatencyReport, pick: fn(&crate::latency::DurationSummary) -> Duration| {
+        report.total().map_or("-".to_string(), |summary| format!("{:.1?}", pick(&summary)))
+    };
+    println!("\n📊 Noise and latency with clustered writes ({}):", std::env::consts::OS);
+    println!(
+        "  {:<20} {:>8} {:>8} {:>10} {:>10} {:>10} {:>10} {:>8} {:>10}",
+        "Watcher", "Events", "Ev/Mod", "Hot p50", "Hot p99", "Cold p50", "Cold p99", "Missed", "Promoted"
+    );
+    for result in &results {
+        println!(
+            "  {:<20} {:>8} {:>8} {:>10} {:>10} {:>10} {:>10} {:>8} {:>10}",
+            result.name,
+            result.events,
+            result.latency.mean_events_per_write().map_or("-".to_string(), |mean| format!("{:.2}", mean)),
+            p(&result.hot, |summary| summary.p50),
+            p(&result.hot, |summary| summary.p99),
+            p(&result.cold, |summary| summary.p50),
+            p(&result.cold, |summary| summary.p99),
+            result.latency.unmatched,
+            result.promotions.map_or("-".to_string(), |count| count.to_string())
+        );
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recently_written() {
+        let targets: Vec<PathBuf> = ["a", "b", "a", "c", "d", "a", "d"].iter().map(PathBuf::from).collect();
+        assert_eq!(recently_written(&targets, 2), [false, false, true, false, false, false, true]);
+        assert_eq!(recently_written(&targets, 0), [false; 7]);
+    }
+
+    #[test]
+    fn test_hybrid_watcher_promotes_modified_files() {
+        let test_dir = std::path::absolute("test_temp_hybrid_dir").unwrap();
+        fs::create_dir_all(&test_dir).unwrap();
+        let file = test_dir.join("hot.txt");
+        fs::write(&file, "").unwrap();
+
+        let config = WatchConfig::default();
+        let watcher = HybridWatcher::new_with_roots_and_config(std::slice::from_ref(&test_dir), 4, &config).unwrap();
+        let promotions = watcher.promotions();
+        let (_watcher, rx) = watcher.into_parts();
+        fs::write(&file, "first").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while promotions.get() == 0 && Instant::now() < deadline {
+            let _ = rx.recv_timeout(Duration::from_millis(10));
+        }
+        assert_eq!(promotions.get(), 1);
+
+        fs::remove_dir_all(&test_dir).unwrap();
+    }
+}
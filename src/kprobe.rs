@@ -0,0 +1,270 @@
+use crate::latency::{DurationSummary, WriteRecord};
+use crate::normalize::{normalize, NormalizedKind};
+use crate::stats::CollectedEvents;
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// bpftrace program printing `<CLOCK_MONOTONIC ns> <inode>` whenever inotify queues an
+/// `IN_MODIFY` event; the inode is the modified file for both file and directory watches
+#[cfg_attr(not(all(feature = "ebpf", target_os = "linux")), allow(dead_code))]
+const PROBE_PROGRAM: &str = r#"kprobe:inotify_handle_inode_event /arg1 & 0x2/ { printf("%llu %llu\n", nsecs, ((struct inode *)arg2)->i_ino); }"#;
+
+/// One event inotify queued, as seen by the kernel probe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KernelEvent {
+    pub queued_at: Instant,
+    pub inode: u64,
+}
+
+/// Ties `CLOCK_MONOTONIC`, which bpftrace's `nsecs` reads, to `Instant`
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(not(all(feature = "ebpf", target_os = "linux")), allow(dead_code))]
+pub struct ClockAnchor {
+    instant: Instant,
+    monotonic_ns: u64,
+}
+
+#[cfg_attr(not(all(feature = "ebpf", target_os = "linux")), allow(dead_code))]
+impl ClockAnchor {
+    #[cfg(unix)]
+    pub fn now() -> Self {
+        // SAFETY: clock_gettime only writes into the timespec we hand it
+        let mut ts: libc::timespec = unsafe { std::mem::zeroed() };
+        unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) };
+        Self {
+            instant: Instant::now(),
+            monotonic_ns: ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64,
+        }
+    }
+
+    /// The `Instant` matching a `CLOCK_MONOTONIC` reading
+    pub fn instant_at(&self, monotonic_ns: u64) -> Instant {
+        if monotonic_ns >= self.monotonic_ns {
+            self.instant + Duration::from_nanos(monotonic_ns - self.monotonic_ns)
+        } else {
+            self.instant - Duration::from_nanos(self.monotonic_ns - monotonic_ns)
+        }
+    }
+}
+
+/// Parse one line of probe output
+#[cfg_attr(not(all(feature = "ebpf", target_os = "linux")), allow(dead_code))]
+fn parse_probe_line(line: &str, anchor: &ClockAnchor) -> Option<KernelEvent> {
+    let (ns, inode) = line.trim().split_once(' ')?;
+    Some(KernelEvent {
+        queued_at: anchor.instant_at(ns.parse().ok()?),
+        inode: inode.parse().ok()?,
+    })
+}
+
+/// A running bpftrace probe on inotify's event queueing
+#[cfg(all(feature = "ebpf", target_os = "linux"))]
+pub struct KernelQueueProbe {
+    child: std::process::Child,
+    reader: std::thread::JoinHandle<Vec<KernelEvent>>,
+}
+
+#[cfg(all(feature = "ebpf", target_os = "linux"))]
+impl KernelQueueProbe {
+    /// Start bpftrace and wait until the probe is attached; needs root and BTF
+    pub fn attach() -> io::Result<Self> {
+        use std::io::BufRead;
+        use std::process::{Command, Stdio};
+
+        let mut child = Command::new("bpftrace")
+            .args(["-e", PROBE_PROGRAM])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| io::Error::new(e.kind(), format!("could not run bpftrace: {}", e)))?;
+        let mut lines = io::BufReader::new(child.stdout.take().expect("piped stdout")).lines();
+        // bpftrace announces "Attaching 1 probe..." once the kprobe is live
+        match lines.next() {
+            Some(Ok(line)) if line.starts_with("Attaching") => {},
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(io::Error::other("bpftrace did not attach the probe"));
+            }
+        }
+        let anchor = ClockAnchor::now();
+        let reader = std::thread::spawn(move || {
+            lines
+                .map_while(Result::ok)
+                .filter_map(|line| parse_probe_line(&line, &anchor))
+                .collect()
+        });
+        Ok(Self { child, reader })
+    }
+
+    /// Detach the probe and return everything it saw
+    pub fn finish(mut self) -> Vec<KernelEvent> {
+        // SIGINT makes bpftrace flush its output before exiting
+        unsafe { libc::kill(self.child.id() as libc::pid_t, libc::SIGINT) };
+        let _ = self.child.wait();
+        self.reader.join().unwrap_or_default()
+    }
+}
+
+#[cfg(not(all(feature = "ebpf", target_os = "linux")))]
+pub struct KernelQueueProbe;
+
+#[cfg(not(all(feature = "ebpf", target_os = "linux")))]
+impl KernelQueueProbe {
+    pub fn attach() -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "kernel probes need a Linux build with `--features ebpf`",
+        ))
+    }
+
+    pub fn finish(self) -> Vec<KernelEvent> {
+        Vec::new()
+    }
+}
+
+/// The backend part of write-to-event latency, split where inotify queues the event
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct KernelSplit {
+    /// Write issued → event queued by inotify
+    pub kernel: Vec<Duration>,
+    /// Event queued → notify's callback running
+    pub delivery: Vec<Duration>,
+    /// Writes with no queued kernel event or no notify event after it
+    pub unmatched: usize,
+}
+
+impl KernelSplit {
+    /// Match each write to the first kernel event for its inode and the first notify
+    /// event for its path that follow the write, before the next write to that path
+    pub fn from_writes(
+        writes: &[WriteRecord],
+        collected: &CollectedEvents,
+        kernel_events: &[KernelEvent],
+        inodes: &HashMap<PathBuf, u64>,
+    ) -> Self {
+        let mut emitted: HashMap<PathBuf, Vec<Instant>> = HashMap::new();
+        for (event, &at) in collected.events.iter().zip(&collected.emitted_at) {
+            for change in normalize(event) {
+                if change.kind == NormalizedKind::Modified {
+                    emitted.entry(change.path).or_default().push(at);
+                }
+            }
+        }
+
+        let mut split = Self::default();
+        for (i, write) in writes.iter().enumerate() {
+            let path = std::path::absolute(&write.path).unwrap_or_else(|_| write.path.clone());
+            let next_start = writes[i + 1..]
+                .iter()
+                .find(|w| w.path == write.path)
+                .map(|w| w.started_at);
+            let in_window = |t: Instant| t >= write.started_at && next_start.is_none_or(|n| t < n);
+
+            let queued = inodes.get(&path).and_then(|&inode| {
+                kernel_events
+                    .iter()
+                    .filter(|e| e.inode == inode && in_window(e.queued_at))
+                    .map(|e| e.queued_at)
+                    .min()
+            });
+            let delivered = queued.and_then(|queued| {
+                emitted
+                    .get(&path)
+                    .and_then(|times| times.iter().copied().filter(|&t| t >= queued && in_window(t)).min())
+                    .map(|at| (queued, at))
+            });
+            match delivered {
+                Some((queued, at)) => {
+                    split.kernel.push(queued - write.started_at);
+                    split.delivery.push(at - queued);
+                },
+                None => split.unmatched += 1,
+            }
+        }
+        split
+    }
+
+    /// Print the two components next to the existing backend/queue figures
+    pub fn report(&self, indent: &str) {
+        let show = |label: &str, samples: &[Duration]| match DurationSummary::from_durations(samples.to_vec()) {
+            Some(s) => println!("{}{:<22} p50 {:.1?}, p99 {:.1?}, max {:.1?}", indent, label, s.p50, s.p99, s.max),
+            None => println!("{}{:<22} no samples", indent, label),
+        };
+        println!("{}Kernel split ({} writes, {} unmatched):", indent, self.kernel.len(), self.unmatched);
+        show("write → queued", &self.kernel);
+        show("queued → notify", &self.delivery);
+    }
+}
+
+/// Inode numbers of `paths`, keyed by absolute path
+#[cfg(unix)]
+pub fn inodes_of<'a>(paths: impl IntoIterator<Item = &'a PathBuf>) -> HashMap<PathBuf, u64> {
+    use std::os::unix::fs::MetadataExt;
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let inode = std::fs::metadata(path).ok()?.ino();
+            Some((std::path::absolute(path).ok()?, inode))
+        })
+        .collect()
+}
+
+#[cfg(not(unix))]
+pub fn inodes_of<'a>(_paths: impl IntoIterator<Item = &'a PathBuf>) -> HashMap<PathBuf, u64> {
+    HashMap::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{DataChange, ModifyKind};
+    use notify::{Event, EventKind};
+
+    #[test]
+    fn test_parse_probe_line() {
+        let anchor = ClockAnchor {
+            instant: Instant::now(),
+            monotonic_ns: 5_000_000,
+        };
+        let later = anchor.monotonic_ns + 1_500;
+        let event = parse_probe_line(&format!("{} 42\n", later), &anchor).unwrap();
+        assert_eq!(event.inode, 42);
+        assert_eq!(event.queued_at - anchor.instant, Duration::from_nanos(1_500));
+        assert!(anchor.instant_at(anchor.monotonic_ns - 10) < anchor.instant);
+        assert!(parse_probe_line("Attaching 1 probe...", &anchor).is_none());
+    }
+
+    #[test]
+    fn test_split_at_kernel_queue() {
+        let path = std::path::absolute("split-test-file").unwrap();
+        let start = Instant::now();
+        let at = |us: u64| start + Duration::from_micros(us);
+        let writes = vec![WriteRecord {
+            path: path.clone(),
+            started_at: at(0),
+            written_at: at(20),
+        }];
+        let mut collected = CollectedEvents::default();
+        collected
+            .events
+            .push(Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Any))).add_path(path.clone()));
+        collected.emitted_at.push(at(150));
+        collected.received_at.push(at(160));
+        let kernel_events = [
+            KernelEvent { queued_at: at(10), inode: 7 },
+            KernelEvent { queued_at: at(12), inode: 8 },
+        ];
+        let inodes = HashMap::from([(path, 7)]);
+
+        let split = KernelSplit::from_writes(&writes, &collected, &kernel_events, &inodes);
+        assert_eq!(split.kernel, [Duration::from_micros(10)]);
+        assert_eq!(split.delivery, [Duration::from_micros(140)]);
+        assert_eq!(split.unmatched, 0);
+
+        let missing = KernelSplit::from_writes(&writes, &collected, &[], &inodes);
+        assert_eq!(missing.unmatched, 1);
+    }
+}
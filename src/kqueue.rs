@@ -0,0 +1,196 @@
+use crate::harness::enumerate_files;
+use crate::limits::FdLimit;
+use crate::options::Options;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Per-file `EVFILT_VNODE` registrations, each holding its file open like notify's kqueue backend
+#[cfg(any(
+    target_os = "macos",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly"
+))]
+mod vnode {
+    use std::ffi::CString;
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, Instant};
+
+    #[cfg(target_os = "macos")]
+    const OPEN_FLAGS: libc::c_int = libc::O_EVTONLY;
+    #[cfg(not(target_os = "macos"))]
+    const OPEN_FLAGS: libc::c_int = libc::O_RDONLY;
+
+    pub struct VnodeWatches {
+        kq: libc::c_int,
+        fds: Vec<libc::c_int>,
+    }
+
+    impl VnodeWatches {
+        pub fn new() -> io::Result<Self> {
+            // SAFETY: kqueue takes no arguments and returns a new descriptor or -1
+            let kq = unsafe { libc::kqueue() };
+            if kq < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self { kq, fds: Vec::new() })
+        }
+
+        /// Open `path` and register it for writes, deletes, renames and attribute changes
+        pub fn add(&mut self, path: &Path) -> io::Result<()> {
+            let c_path = CString::new(path.as_os_str().as_bytes())?;
+            // SAFETY: `c_path` is NUL-terminated and outlives the call
+            let fd = unsafe { libc::open(c_path.as_ptr(), OPEN_FLAGS) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // SAFETY: an all-zero kevent is valid; the fields that matter are set below
+            let mut change: libc::kevent = unsafe { std::mem::zeroed() };
+            change.ident = fd as _;
+            change.filter = libc::EVFILT_VNODE;
+            change.flags = libc::EV_ADD | libc::EV_CLEAR;
+            change.fflags = libc::NOTE_WRITE | libc::NOTE_EXTEND | libc::NOTE_DELETE | libc::NOTE_RENAME | libc::NOTE_ATTRIB;
+            // SAFETY: one change in, no events out
+            let result = unsafe { libc::kevent(self.kq, &change, 1, std::ptr::null_mut(), 0, std::ptr::null()) };
+            if result < 0 {
+                let err = io::Error::last_os_error();
+                unsafe { libc::close(fd) };
+                return Err(err);
+            }
+            self.fds.push(fd);
+            Ok(())
+        }
+
+        pub fn len(&self) -> usize {
+            self.fds.len()
+        }
+    }
+
+    impl Drop for VnodeWatches {
+        fn drop(&mut self) {
+            for &fd in &self.fds {
+                unsafe { libc::close(fd) };
+            }
+            unsafe { libc::close(self.kq) };
+        }
+    }
+
+    /// Whether an open or registration failed because the process or system ran out of descriptors
+    fn is_fd_exhaustion(err: &io::Error) -> bool {
+        matches!(err.raw_os_error(), Some(libc::EMFILE) | Some(libc::ENFILE))
+    }
+
+    /// Register a kqueue watch for each file until descriptors run out
+    pub fn register_until_exhausted(files: &[PathBuf]) -> io::Result<(usize, Duration)> {
+        let mut watches = VnodeWatches::new()?;
+        let start = Instant::now();
+        for file in files {
+            match watches.add(file) {
+                Ok(()) => {},
+                Err(e) if is_fd_exhaustion(&e) => {
+                    println!("   Descriptors exhausted after {} watches ({})", watches.len(), e);
+                    break;
+                },
+                Err(e) => return Err(e),
+            }
+        }
+        Ok((watches.len(), start.elapsed()))
+    }
+}
+
+#[cfg(not(any(
+    target_os = "macos",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly"
+)))]
+mod vnode {
+    use std::io;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    pub fn register_until_exhausted(_files: &[PathBuf]) -> io::Result<(usize, Duration)> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "kqueue-budget needs macOS or a BSD"))
+    }
+}
+
+/// How far one descriptor budget stretched over a tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FdBudget {
+    pub files: usize,
+    pub watched: usize,
+    pub limit: FdLimit,
+    pub setup: Duration,
+}
+
+impl FdBudget {
+    pub fn coverage_percent(&self) -> f64 {
+        if self.files == 0 {
+            return 100.0;
+        }
+        self.watched as f64 / self.files as f64 * 100.0
+    }
+
+    pub fn is_partial(&self) -> bool {
+        self.watched < self.files
+    }
+}
+
+/// Count how many per-file kqueue watches fit under `RLIMIT_NOFILE`
+///
+/// kqueue holds a descriptor open for every watched file, so on macOS and the BSDs
+/// manual mode's coverage is capped by the open file limit long before setup time
+/// matters. `--raise-nofile` lifts the soft limit to the hard one first.
+pub fn run_kqueue_budget(roots: &[PathBuf], options: &Options) -> Result<FdBudget, Box<dyn std::error::Error>> {
+    println!("\n=== kqueue Descriptor Budget ===");
+    let mut limit = FdLimit::read().ok_or("could not read RLIMIT_NOFILE")?;
+    println!("RLIMIT_NOFILE: soft {}, hard {}", limit.soft, limit.hard);
+    if options.raise_nofile {
+        limit = limit.raise()?;
+        println!("Raised soft limit to {}", limit.soft);
+    }
+
+    let files: Vec<PathBuf> = enumerate_files(roots, options)?;
+    println!("Registering a kqueue watch for each of {} files", files.len());
+    let (watched, setup) = vnode::register_until_exhausted(&files)?;
+    let budget = FdBudget {
+        files: files.len(),
+        watched,
+        limit,
+        setup,
+    };
+
+    println!("\n📊 Descriptor budget:");
+    println!("  Soft limit:  {}", budget.limit.soft);
+    println!("  Watched:     {} of {} files ({:.1}% coverage)", budget.watched, budget.files, budget.coverage_percent());
+    println!("  Setup time:  {:.1?}", budget.setup);
+    if budget.is_partial() {
+        println!(
+            "  ⚠️  Coverage is bounded by descriptors, not time; {} more would be needed (try --raise-nofile)",
+            budget.files - budget.watched
+        );
+    }
+    Ok(budget)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_coverage() {
+        let budget = FdBudget {
+            files: 400,
+            watched: 100,
+            limit: FdLimit { soft: 256, hard: 1024 },
+            setup: Duration::from_millis(5),
+        };
+        assert!((budget.coverage_percent() - 25.0).abs() < 1e-9);
+        assert!(budget.is_partial());
+        assert!(!FdBudget { watched: 400, ..budget }.is_partial());
+    }
+}
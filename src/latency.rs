@@ -0,0 +1,642 @@
+use crate::format::{from_v1, unchanged, Migration, Versioned};
+use crate::normalize::normalize;
+use crate::stats::CollectedEvents;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// One write made by a modification phase
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteRecord {
+    pub path: PathBuf,
+    /// Taken immediately before the write was issued
+    pub started_at: Instant,
+    /// Taken immediately after the write returned
+    pub written_at: Instant,
+}
+
+/// Write-to-event latency of one modification, split at the event channel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatencySample {
+    pub path: PathBuf,
+    /// Write returning → notify's callback running (kernel and backend)
+    pub backend: Duration,
+    /// Callback running → consumer receiving the event (channel queuing)
+    pub queue: Duration,
+}
+
+impl LatencySample {
+    /// Write returning → consumer receiving the event
+    pub fn total(&self) -> Duration {
+        self.backend + self.queue
+    }
+}
+
+/// Order statistics over a set of durations
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DurationSummary {
+    pub min: Duration,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl DurationSummary {
+    /// Summarize `durations`, or `None` when there are none
+    pub fn from_durations(mut durations: Vec<Duration>) -> Option<Self> {
+        if durations.is_empty() {
+            return None;
+        }
+        durations.sort();
+        let percentile = |p: usize| durations[(durations.len() - 1) * p / 100];
+        Some(Self {
+            min: durations[0],
+            mean: durations.iter().sum::<Duration>() / durations.len() as u32,
+            p50: percentile(50),
+            p99: percentile(99),
+            max: durations[durations.len() - 1],
+        })
+    }
+}
+
+/// Latency samples for every write that was matched to an event
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LatencyReport {
+    pub samples: Vec<LatencySample>,
+    /// Writes no event was seen for
+    pub unmatched: usize,
+    /// Matched writes whose event was emitted before the write call returned
+    pub in_flight: usize,
+    /// Change events attributed to each write, matched or not
+    pub events_per_write: Vec<usize>,
+    /// Matched writes whose first event arrived before that of the write made just before them
+    pub out_of_order: usize,
+}
+
+impl LatencyReport {
+    pub fn backend(&self) -> Option<DurationSummary> {
+        DurationSummary::from_durations(self.samples.iter().map(|s| s.backend).collect())
+    }
+
+    pub fn queue(&self) -> Option<DurationSummary> {
+        DurationSummary::from_durations(self.samples.iter().map(|s| s.queue).collect())
+    }
+
+    pub fn total(&self) -> Option<DurationSummary> {
+        DurationSummary::from_durations(self.samples.iter().map(LatencySample::total).collect())
+    }
+
+    /// Mean events attributed to one write; `None` when there were no writes
+    pub fn mean_events_per_write(&self) -> Option<f64> {
+        if self.events_per_write.is_empty() {
+            return None;
+        }
+        Some(self.events_per_write.iter().sum::<usize>() as f64 / self.events_per_write.len() as f64)
+    }
+
+    /// Writes by how many events each produced, ascending by event count
+    pub fn events_per_write_counts(&self) -> Vec<(usize, usize)> {
+        let mut counts: Vec<(usize, usize)> = Vec::new();
+        for &events in &self.events_per_write {
+            match counts.iter_mut().find(|(n, _)| *n == events) {
+                Some((_, writes)) => *writes += 1,
+                None => counts.push((events, 1)),
+            }
+        }
+        counts.sort();
+        counts
+    }
+
+    /// Share of consecutive matched writes whose events arrived in write order; 1 with fewer than two
+    pub fn in_order_ratio(&self) -> f64 {
+        let pairs = self.samples.len().saturating_sub(1);
+        if pairs == 0 {
+            return 1.0;
+        }
+        (pairs - self.out_of_order) as f64 / pairs as f64
+    }
+
+    /// Histograms of backend, queue and total latency, in that order
+    pub fn histograms(&self, bounds: &[Duration]) -> [(&'static str, LatencyHistogram); 3] {
+        [
+            ("backend", LatencyHistogram::new(bounds, self.samples.iter().map(|s| s.backend))),
+            ("queue", LatencyHistogram::new(bounds, self.samples.iter().map(|s| s.queue))),
+            ("total", LatencyHistogram::new(bounds, self.samples.iter().map(LatencySample::total))),
+        ]
+    }
+
+    /// Print the histograms as text, or as one JSON object per line tagged with `mode`
+    pub fn print_histograms(
+        &self,
+        mode: &str,
+        bounds: &[Duration],
+        format: HistogramFormat,
+        indent: &str,
+    ) {
+        for (metric, histogram) in self.histograms(bounds) {
+            match format {
+                HistogramFormat::Text => {
+                    histogram.print_text(&format!("Latency histogram, {}", metric), indent)
+                }
+                HistogramFormat::Json => println!(
+                    "{}",
+                    HistogramRecord {
+                        format_version: HistogramRecord::FORMAT_VERSION,
+                        mode: Some(mode),
+                        metric,
+                        histogram,
+                    }
+                    .to_json()
+                ),
+            }
+        }
+    }
+
+    /// Print backend, queue and end-to-end summaries
+    pub fn report(&self, indent: &str) {
+        println!(
+            "{}Write-to-event latency: {} writes matched ({} emitted before the write returned), {} unmatched",
+            indent,
+            self.samples.len(),
+            self.in_flight,
+            self.unmatched
+        );
+        if let Some(mean) = self.mean_events_per_write() {
+            let counts: Vec<String> = self
+                .events_per_write_counts()
+                .iter()
+                .map(|(events, writes)| format!("{} write(s) → {}", writes, events))
+                .collect();
+            println!(
+                "{}Events per modification: mean {:.2} ({})",
+                indent,
+                mean,
+                counts.join(", ")
+            );
+        }
+        for (label, summary) in [
+            ("backend (write → callback)", self.backend()),
+            ("queue (callback → recv)", self.queue()),
+            ("total (write → recv)", self.total()),
+        ] {
+            if let Some(s) = summary {
+                println!(
+                    "{}  {:<28} min {:.1?}, mean {:.1?}, p50 {:.1?}, p99 {:.1?}, max {:.1?}",
+                    indent, label, s.min, s.mean, s.p50, s.p99, s.max
+                );
+            }
+        }
+    }
+}
+
+/// Histogram bucket upper bounds used unless `--histogram-buckets` is given
+pub const DEFAULT_HISTOGRAM_BUCKETS: [Duration; 14] = [
+    Duration::from_micros(10),
+    Duration::from_micros(25),
+    Duration::from_micros(50),
+    Duration::from_micros(100),
+    Duration::from_micros(250),
+    Duration::from_micros(500),
+    Duration::from_millis(1),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(25),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+];
+
+/// How `--histogram` prints latency histograms
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistogramFormat {
+    Text,
+    Json,
+}
+
+impl HistogramFormat {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "text" => Some(Self::Text),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+/// One histogram bucket: samples above the previous bound and at most `le_us`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Bucket {
+    pub le_us: u64,
+    pub count: usize,
+}
+
+/// Sample counts per latency bucket, keeping the whole distribution rather than a summary
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LatencyHistogram {
+    pub samples: usize,
+    pub buckets: Vec<Bucket>,
+    /// Samples above the last bound
+    pub above: usize,
+}
+
+/// One `--histogram json` line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HistogramRecord<'a> {
+    pub format_version: u32,
+    /// Watcher the samples came from; absent for setup-phase histograms printed per watcher
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<&'a str>,
+    pub metric: &'a str,
+    pub histogram: LatencyHistogram,
+}
+
+impl Versioned for HistogramRecord<'_> {
+    const FORMAT_VERSION: u32 = 3;
+    const MIGRATIONS: &'static [Migration] = &[from_v1, unchanged];
+}
+
+impl HistogramRecord<'_> {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+impl LatencyHistogram {
+    /// Bucket `samples` by the upper `bounds`, which must be ascending
+    pub fn new(bounds: &[Duration], samples: impl IntoIterator<Item = Duration>) -> Self {
+        let mut buckets: Vec<Bucket> = bounds
+            .iter()
+            .map(|bound| Bucket {
+                le_us: bound.as_micros() as u64,
+                count: 0,
+            })
+            .collect();
+        let mut histogram_samples = 0;
+        let mut above = 0;
+        for sample in samples {
+            histogram_samples += 1;
+            match bounds.iter().position(|bound| sample <= *bound) {
+                Some(i) => buckets[i].count += 1,
+                None => above += 1,
+            }
+        }
+        Self {
+            samples: histogram_samples,
+            buckets,
+            above,
+        }
+    }
+
+    /// Print one row per bucket between the first and last non-empty ones
+    pub fn print_text(&self, label: &str, indent: &str) {
+        println!("{}{} ({} samples):", indent, label, self.samples);
+        let width = self.buckets.iter().map(|b| b.count).chain([self.above]).max().unwrap_or(0);
+        let first = self.buckets.iter().position(|b| b.count > 0);
+        let last = self.buckets.iter().rposition(|b| b.count > 0);
+        let bar = |count: usize| "#".repeat((count * 40).div_ceil(width.max(1)));
+
+        if let (Some(first), Some(last)) = (first, last) {
+            for bucket in &self.buckets[first..=last] {
+                let bound = format!("≤ {:?}", Duration::from_micros(bucket.le_us));
+                println!("{}  {:>12} {:>6} {}", indent, bound, bucket.count, bar(bucket.count));
+            }
+        }
+        if self.above > 0 {
+            let bound = format!(
+                "> {:?}",
+                Duration::from_micros(self.buckets.last().map_or(0, |b| b.le_us))
+            );
+            println!("{}  {:>12} {:>6} {}", indent, bound, self.above, bar(self.above));
+        }
+    }
+}
+
+/// Pair each write with the first change event for its path
+///
+/// An event is attributed to a write if it was emitted after that write started
+/// and before the next write to the same path did. Events emitted while the
+/// write itself was still in progress count as zero backend latency. Both sides
+/// of a paired rename count as events for their path. Every attributed event is
+/// counted towards `events_per_write`, so a backend batching several writes into
+/// one event shows as a 1 followed by 0s.
+pub fn match_writes(writes: &[WriteRecord], collected: &CollectedEvents) -> LatencyReport {
+    // (emitted, received) per path, in arrival order
+    let mut arrivals: HashMap<PathBuf, Vec<(Instant, Instant)>> = HashMap::new();
+    for ((event, &emitted), &received) in collected
+        .events
+        .iter()
+        .zip(&collected.emitted_at)
+        .zip(&collected.received_at)
+    {
+        for change in normalize(event) {
+            // A rename onto a path (an atomic save) is a change to that path too
+            if let Some(to) = change.renamed_to {
+                arrivals.entry(to).or_default().push((emitted, received));
+            }
+            arrivals.entry(change.path).or_default().push((emitted, received));
+        }
+    }
+
+    let mut by_path: HashMap<PathBuf, Vec<&WriteRecord>> = HashMap::new();
+    for write in writes {
+        by_path.entry(absolute(&write.path)).or_default().push(write);
+    }
+
+    let mut report = LatencyReport::default();
+    // (write started, first event received) per matched write, to check arrival order
+    let mut firsts: Vec<(Instant, Instant)> = Vec::new();
+    for (path, mut path_writes) in by_path {
+        path_writes.sort_by_key(|w| w.started_at);
+        let events = arrivals.get(&path).map(Vec::as_slice).unwrap_or_default();
+        for (i, write) in path_writes.iter().enumerate() {
+            let written_at = write.written_at;
+            let next_start = path_writes.get(i + 1).map(|w| w.started_at);
+            let attributed = |(emitted, _): &&(Instant, Instant)| {
+                *emitted >= write.started_at && next_start.is_none_or(|t| *emitted < t)
+            };
+            report.events_per_write.push(events.iter().filter(attributed).count());
+            match events.iter().find(attributed) {
+                Some(&(emitted, received)) => {
+                    firsts.push((write.started_at, received));
+                    if emitted < written_at {
+                        report.in_flight += 1;
+                    }
+                    report.samples.push(LatencySample {
+                        path: path.clone(),
+                        backend: emitted.saturating_duration_since(written_at),
+                        queue: received.saturating_duration_since(emitted),
+                    });
+                }
+                None => report.unmatched += 1,
+            }
+        }
+    }
+    firsts.sort();
+    report.out_of_order = firsts.windows(2).filter(|pair| pair[1].1 < pair[0].1).count();
+    report
+}
+
+/// Writes per second over one burst, from the first write starting to the last returning
+pub fn achieved_rate(writes: &[WriteRecord]) -> f64 {
+    let (Some(first), Some(last)) = (writes.first(), writes.last()) else {
+        return 0.0;
+    };
+    writes.len() as f64 / (last.written_at - first.started_at).as_secs_f64().max(f64::EPSILON)
+}
+
+/// How a backend folded a stream of small writes into events
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coalescing {
+    pub writes: usize,
+    /// Events naming a written file, received once writing began
+    pub events: usize,
+    /// Longest stretch without such an event while writes were still being made
+    pub max_gap: Duration,
+    /// From the last write returning to the last event arriving
+    pub tail: Option<Duration>,
+    /// Files whose final write was never followed by an event
+    pub stale_files: usize,
+}
+
+impl Coalescing {
+    pub fn measure(writes: &[WriteRecord], collected: &CollectedEvents) -> Self {
+        let (Some(first), Some(last)) = (writes.first(), writes.last()) else {
+            return Self {
+                writes: 0,
+                events: 0,
+                max_gap: Duration::ZERO,
+                tail: None,
+                stale_files: 0,
+            };
+        };
+        let mut last_write: HashMap<PathBuf, Instant> = HashMap::new();
+        for write in writes {
+            last_write.insert(absolute(&write.path), write.started_at);
+        }
+
+        // (arrival, path) of every change to a written file, in arrival order; access
+        // events are left out as in `match_writes`
+        let mut arrivals: Vec<(Instant, PathBuf)> = Vec::new();
+        for (event, &received) in collected.events.iter().zip(&collected.received_at) {
+            if received < first.started_at {
+                continue;
+            }
+            if let Some(change) = normalize(event).into_iter().find(|c| last_write.contains_key(&c.path)) {
+                arrivals.push((received, change.path));
+            }
+        }
+        arrivals.sort_by_key(|(at, _)| *at);
+
+        let mut max_gap = Duration::ZERO;
+        let mut previous = first.started_at;
+        for &(at, _) in arrivals.iter().take_while(|(at, _)| *at <= last.written_at) {
+            max_gap = max_gap.max(at - previous);
+            previous = at;
+        }
+        max_gap = max_gap.max(last.written_at.saturating_duration_since(previous));
+
+        let stale_files = last_write
+            .iter()
+            .filter(|(path, &written)| {
+                !arrivals
+                    .iter()
+                    .any(|(at, p)| *at >= written && p == *path)
+            })
+            .count();
+
+        Self {
+            writes: writes.len(),
+            events: arrivals.len(),
+            max_gap,
+            tail: arrivals.last().map(|(at, _)| at.saturating_duration_since(last.written_at)),
+            stale_files,
+        }
+    }
+
+    pub fn events_per_write(&self) -> f64 {
+        self.events as f64 / self.writes.max(1) as f64
+    }
+}
+
+/// Absolute form of a write path, matching how notify reports event paths
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{AccessKind, DataChange, ModifyKind, RenameMode};
+    use notify::{Event, EventKind};
+
+    fn collected(items: &[(EventKind, &str, Instant, Instant)]) -> CollectedEvents {
+        let mut collected = CollectedEvents::default();
+        for (kind, path, emitted, received) in items {
+            collected.events.push(Event::new(*kind).add_path(PathBuf::from(path)));
+            collected.emitted_at.push(*emitted);
+            collected.received_at.push(*received);
+        }
+        collected
+    }
+
+    #[test]
+    fn test_achieved_rate() {
+        let start = Instant::now();
+        let writes: Vec<WriteRecord> = (0..=10)
+            .map(|i| WriteRecord {
+                path: PathBuf::from("f"),
+                started_at: start + Duration::from_millis(i * 10),
+                written_at: start + Duration::from_millis(i * 10 + 1),
+            })
+            .collect();
+        // 11 writes between 0ms and 101ms
+        assert!((achieved_rate(&writes) - 11.0 / 0.101).abs() < 1e-6);
+        assert_eq!(achieved_rate(&[]), 0.0);
+    }
+
+    #[test]
+    fn test_coalescing_gaps_tail_and_stale_files() {
+        let start = Instant::now();
+        let ms = |n| start + Duration::from_millis(n);
+        let modify = EventKind::Modify(ModifyKind::Data(DataChange::Any));
+        let write = |path: &str, at| WriteRecord {
+            path: absolute(Path::new(path)),
+            started_at: ms(at),
+            written_at: ms(at + 1),
+        };
+        let a = absolute(Path::new("a.log"));
+        let a = a.to_str().unwrap();
+
+        // Ten writes alternating between two files; only a.log is ever reported
+        let writes: Vec<WriteRecord> = (0..10)
+            .map(|i| write(if i % 2 == 0 { "a.log" } else { "b.log" }, i * 10))
+            .collect();
+        let collected = collected(&[
+            (modify, a, ms(5), ms(5)),
+            (EventKind::Access(AccessKind::Any), a, ms(20), ms(20)),
+            (modify, a, ms(60), ms(60)),
+            (modify, a, ms(130), ms(130)),
+        ]);
+
+        let coalescing = Coalescing::measure(&writes, &collected);
+        assert_eq!(coalescing.writes, 10);
+        assert_eq!(coalescing.events, 3);
+        assert!((coalescing.events_per_write() - 0.3).abs() < 1e-9);
+        // From the event at 60ms to the last write returning at 91ms
+        assert_eq!(coalescing.max_gap, Duration::from_millis(55));
+        assert_eq!(coalescing.tail, Some(Duration::from_millis(39)));
+        assert_eq!(coalescing.stale_files, 1);
+    }
+
+    #[test]
+    fn test_match_writes_splits_backend_and_queue() {
+        let start = Instant::now();
+        let ms = |n| start + Duration::from_millis(n);
+        let modify = EventKind::Modify(ModifyKind::Data(DataChange::Any));
+        let access = EventKind::Access(AccessKind::Any);
+
+        let writes = [
+            WriteRecord { path: PathBuf::from("/a"), started_at: ms(0), written_at: ms(1) },
+            WriteRecord { path: PathBuf::from("/a"), started_at: ms(99), written_at: ms(100) },
+            WriteRecord { path: PathBuf::from("/b"), started_at: ms(9), written_at: ms(10) },
+            WriteRecord { path: PathBuf::from("/c"), started_at: ms(19), written_at: ms(20) },
+        ];
+        let collected = collected(&[
+            (modify, "/c", ms(19), ms(21)),
+            (access, "/a", ms(1), ms(1)),
+            (modify, "/a", ms(4), ms(9)),
+            (modify, "/a", ms(5), ms(10)),
+            (modify, "/a", ms(102), ms(103)),
+        ]);
+
+        let report = match_writes(&writes, &collected);
+        assert_eq!(report.unmatched, 1);
+        assert_eq!(report.in_flight, 1);
+        let mut samples = report.samples.clone();
+        samples.sort_by_key(|s| (s.backend, s.queue));
+        assert_eq!(samples[0].path, PathBuf::from("/c"));
+        assert_eq!(samples[0].backend, Duration::ZERO);
+        assert_eq!(samples[1].backend, Duration::from_millis(2));
+        assert_eq!(samples[1].queue, Duration::from_millis(1));
+        assert_eq!(samples[2].backend, Duration::from_millis(3));
+        assert_eq!(samples[2].queue, Duration::from_millis(5));
+        assert_eq!(report.total().unwrap().max, Duration::from_millis(8));
+        // /a's first write got two modifies, its second one; /b got none
+        assert_eq!(report.events_per_write_counts(), [(0, 1), (1, 2), (2, 1)]);
+        assert_eq!(report.mean_events_per_write(), Some(1.0));
+        assert_eq!(report.out_of_order, 0);
+        assert_eq!(LatencyReport::default().mean_events_per_write(), None);
+    }
+
+    #[test]
+    fn test_match_writes_counts_out_of_order_arrivals() {
+        let start = Instant::now();
+        let ms = |n| start + Duration::from_millis(n);
+        let modify = EventKind::Modify(ModifyKind::Data(DataChange::Any));
+        let writes = [
+            WriteRecord { path: PathBuf::from("/x"), started_at: ms(0), written_at: ms(1) },
+            WriteRecord { path: PathBuf::from("/y"), started_at: ms(10), written_at: ms(11) },
+            WriteRecord { path: PathBuf::from("/z"), started_at: ms(20), written_at: ms(21) },
+        ];
+        // /x is reported after /y, which was written later
+        let collected = collected(&[
+            (modify, "/y", ms(12), ms(12)),
+            (modify, "/x", ms(13), ms(13)),
+            (modify, "/z", ms(22), ms(22)),
+        ]);
+
+        let report = match_writes(&writes, &collected);
+        assert_eq!(report.out_of_order, 1);
+        assert!((report.in_order_ratio() - 0.5).abs() < 1e-9);
+        assert_eq!(LatencyReport::default().in_order_ratio(), 1.0);
+    }
+
+    #[test]
+    fn test_match_writes_counts_rename_destination() {
+        let start = Instant::now();
+        let ms = |n| start + Duration::from_millis(n);
+        let writes = [WriteRecord { path: PathBuf::from("/a"), started_at: ms(0), written_at: ms(1) }];
+        let mut collected = CollectedEvents::default();
+        collected.events.push(
+            Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+                .add_path(PathBuf::from("/.a.tmp"))
+                .add_path(PathBuf::from("/a")),
+        );
+        collected.emitted_at.push(ms(3));
+        collected.received_at.push(ms(4));
+
+        let report = match_writes(&writes, &collected);
+        assert_eq!(report.unmatched, 0);
+        assert_eq!(report.samples[0].backend, Duration::from_millis(2));
+    }
+
+    #[test]
+    fn test_histogram_buckets() {
+        let bounds = [Duration::from_millis(1), Duration::from_millis(10)];
+        let samples = [0, 1, 2, 10, 11].map(Duration::from_millis);
+        let histogram = LatencyHistogram::new(&bounds, samples);
+
+        assert_eq!(histogram.samples, 5);
+        assert_eq!(histogram.buckets[0], Bucket { le_us: 1_000, count: 2 });
+        assert_eq!(histogram.buckets[1], Bucket { le_us: 10_000, count: 2 });
+        assert_eq!(histogram.above, 1);
+        assert_eq!(
+            serde_json::to_string(&histogram).unwrap(),
+            r#"{"samples":5,"buckets":[{"le_us":1000,"count":2},{"le_us":10000,"count":2}],"above":1}"#
+        );
+    }
+
+    #[test]
+    fn test_duration_summary() {
+        let summary =
+            DurationSummary::from_durations((1..=100).map(Duration::from_millis).collect()).unwrap();
+        assert_eq!(summary.min, Duration::from_millis(1));
+        assert_eq!(summary.p50, Duration::from_millis(50));
+        assert_eq!(summary.p99, Duration::from_millis(99));
+        assert_eq!(summary.max, Duration::from_millis(100));
+        assert!(DurationSummary::from_durations(Vec::new()).is_none());
+    }
+}
@@ -0,0 +1,205 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Watches left free for other processes when proceeding with a partial watch set
+const WATCH_HEADROOM: usize = 8192;
+
+/// Per-user inotify limits read from `/proc/sys/fs/inotify`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InotifyLimits {
+    pub max_user_watches: usize,
+    pub max_user_instances: usize,
+    pub max_queued_events: usize,
+}
+
+impl InotifyLimits {
+    /// Read the current limits, or `None` when not on Linux or `/proc` is unavailable
+    pub fn read() -> Option<Self> {
+        if !cfg!(target_os = "linux") {
+            return None;
+        }
+
+        let root = Path::new("/proc/sys/fs/inotify");
+        Some(Self {
+            max_user_watches: read_number(&root.join("max_user_watches"))?,
+            max_user_instances: read_number(&root.join("max_user_instances"))?,
+            max_queued_events: read_number(&root.join("max_queued_events"))?,
+        })
+    }
+
+    /// Suggested `max_user_watches` value that fits `needed` watches plus headroom
+    pub fn suggested_watches(needed: usize) -> usize {
+        (needed + WATCH_HEADROOM).next_power_of_two()
+    }
+}
+
+/// This process's open file limit (`RLIMIT_NOFILE`), which caps per-file kqueue watches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FdLimit {
+    pub soft: u64,
+    pub hard: u64,
+}
+
+impl FdLimit {
+    #[cfg(unix)]
+    pub fn read() -> Option<Self> {
+        // SAFETY: getrlimit only writes into the struct we hand it
+        let mut limit: libc::rlimit = unsafe { std::mem::zeroed() };
+        if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } != 0 {
+            return None;
+        }
+        Some(Self {
+            soft: limit.rlim_cur as u64,
+            hard: limit.rlim_max as u64,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn read() -> Option<Self> {
+        None
+    }
+
+    /// Raise the soft limit as far as the hard limit (and on macOS, `kern.maxfilesperproc`) allows
+    #[cfg(unix)]
+    pub fn raise(&self) -> io::Result<Self> {
+        let target = max_files_per_proc().map_or(self.hard, |max| max.min(self.hard));
+        let limit = libc::rlimit {
+            rlim_cur: target as libc::rlim_t,
+            rlim_max: self.hard as libc::rlim_t,
+        };
+        // SAFETY: setrlimit only reads the struct we hand it
+        if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &limit) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Self::read().ok_or_else(|| io::Error::other("could not read RLIMIT_NOFILE back"))
+    }
+
+    #[cfg(not(unix))]
+    pub fn raise(&self) -> io::Result<Self> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "RLIMIT_NOFILE is a Unix limit"))
+    }
+}
+
+/// macOS refuses soft limits above `kern.maxfilesperproc`, even when the hard limit is unlimited
+#[cfg(target_os = "macos")]
+fn max_files_per_proc() -> Option<u64> {
+    let mut value: libc::c_int = 0;
+    let mut size = std::mem::size_of::<libc::c_int>();
+    // SAFETY: the name is NUL-terminated and `value` is as large as `size` says
+    let result = unsafe {
+        libc::sysctlbyname(
+            c"kern.maxfilesperproc".as_ptr(),
+            &mut value as *mut libc::c_int as *mut libc::c_void,
+            &mut size,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    (result == 0).then_some(value as u64)
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn max_files_per_proc() -> Option<u64> {
+    None
+}
+
+fn read_number(path: &Path) -> Option<usize> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Check the manual-mode watch list against the inotify limits before registering anything
+///
+/// When the list exceeds `fs.inotify.max_user_watches` this prints the sysctl needed
+/// to fix it and either fails, or with `allow_partial` truncates the list so setup
+/// stays under the limit.
+pub fn preflight_manual_watches(
+    files: Vec<PathBuf>,
+    allow_partial: bool,
+) -> Result<Vec<PathBuf>, String> {
+    match InotifyLimits::read() {
+        Some(limits) => check_watch_budget(files, limits, allow_partial),
+        None => Ok(files),
+    }
+}
+
+fn check_watch_budget(
+    mut files: Vec<PathBuf>,
+    limits: InotifyLimits,
+    allow_partial: bool,
+) -> Result<Vec<PathBuf>, String> {
+    let needed = files.len();
+    println!(
+        "Preflight: {} watches needed, fs.inotify.max_user_watches = {}, max_user_instances = {}",
+        needed, limits.max_user_watches, limits.max_user_instances
+    );
+
+    if needed <= limits.max_user_watches {
+        return Ok(files);
+    }
+
+    eprintln!(
+        "⚠️  Manual mode needs {} inotify watches but fs.inotify.max_user_watches is {}",
+        needed, limits.max_user_watches
+    );
+    eprintln!("   Raise the limit with:");
+    eprintln!(
+        "     sudo sysctl fs.inotify.max_user_watches={}",
+        InotifyLimits::suggested_watches(needed)
+    );
+    eprintln!("   (add it to /etc/sysctl.d/ to keep it across reboots)");
+
+    let budget = limits.max_user_watches.saturating_sub(WATCH_HEADROOM);
+    if !allow_partial {
+        return Err(format!(
+            "inotify watch limit too low for manual mode (rerun with --allow-partial to watch the first {} files)",
+            budget
+        ));
+    }
+
+    eprintln!(
+        "   Proceeding with --allow-partial: watching {} of {} files ({:.1}% coverage)",
+        budget,
+        needed,
+        budget as f64 / needed as f64 * 100.0
+    );
+    files.truncate(budget);
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(count: usize) -> Vec<PathBuf> {
+        (0..count).map(|i| PathBuf::from(format!("f{}.js", i))).collect()
+    }
+
+    #[test]
+    fn test_check_watch_budget() {
+        let limits = InotifyLimits {
+            max_user_watches: 10_000,
+            max_user_instances: 128,
+            max_queued_events: 16_384,
+        };
+
+        assert_eq!(check_watch_budget(files(100), limits, false).unwrap().len(), 100);
+        assert!(check_watch_budget(files(20_000), limits, false).is_err());
+        assert_eq!(
+            check_watch_budget(files(20_000), limits, true).unwrap().len(),
+            10_000 - WATCH_HEADROOM
+        );
+    }
+
+    #[test]
+    fn test_read_fd_limit() {
+        let limit = FdLimit::read().unwrap();
+        assert!(limit.soft > 0 && limit.soft <= limit.hard);
+    }
+
+    #[test]
+    fn test_suggested_watches() {
+        assert_eq!(InotifyLimits::suggested_watches(73_810), 131_072);
+        assert!(InotifyLimits::suggested_watches(1) >= WATCH_HEADROOM);
+    }
+}
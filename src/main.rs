@@ -1,88 +1,340 @@
+mod adaptive;
+mod aggregate;
+mod batch;
+mod budget;
+mod cache;
+mod callback;
+mod capability;
+mod charts;
+mod churn;
+mod coalesce;
+mod coverage;
+mod curve;
+mod cycle;
+mod dot;
+mod estimate;
+mod faults;
+mod footprint;
+mod format;
+mod fsevents;
+mod harness;
+mod heap;
+mod heatmap;
+mod history;
+mod hybrid;
+mod kprobe;
+mod kqueue;
+mod latency;
+mod limits;
+mod matrix;
+mod merge;
+mod normalize;
+mod options;
+mod oracle;
+mod order;
+mod profile;
+mod rate_limit;
+mod rdcw;
 mod recursive_file_watcher;
-
+mod report;
+mod resources;
+mod roots;
+mod scenarios;
+mod schema;
+mod scorecard;
+mod select;
+mod shutdown;
+mod significance;
+mod soak;
+mod stats;
+mod stream;
+mod supervise;
+mod syscalls;
+mod tiers;
+mod trend;
+mod unwatch;
+mod verify;
+mod watch;
+
+use adaptive::{run_adaptive_test, ADAPTIVE_NAME};
+use aggregate::AggregateStats;
+use batch::run_batch_test;
+use budget::run_budget_test;
+use cache::run_cold_warm;
+use callback::run_callback_bench;
+use capability::run_capabilities;
+use churn::{
+    run_attach_latency_test, run_build_artifacts_test, run_create_heavy_test, run_delete_heavy_test,
+    run_npm_install_test, run_rename_storm_test, ChurnResult,
+};
+use coalesce::CoalesceStats;
+use coverage::{coverage_row, run_metadata_coverage, run_touch_coverage, run_write_coverage, Operation};
+use curve::run_setup_curve;
+use cycle::run_cycle_test;
+use dot::{mark_files, render_dot, DOT_MAX_FILES};
+use estimate::preflight_setup;
+use footprint::{format_bytes, run_filter_memory};
+use fsevents::run_fsevents_sweep;
+use harness::{
+    append_to_files, copy_dir_recursive, enumerate_dir, enumerate_roots, manual_watcher, ordered_watches,
+    recover_from_overflow, report_coverage, report_walk_errors, report_watch_times, select_watched, settle_for,
+    start_watcher_on_roots, tiered_files, QueueDepthSampler, SettlingCollector, FILTER_RATIO,
+};
+use heap::{AllocStats, AllocWindow, ALLOCATOR_NAME};
+use heatmap::Heatmap;
+use history::{run_history, run_trend, Environment, History, RunRecord, TreeFingerprint};
+use hybrid::run_hybrid_test;
+use kprobe::{inodes_of, KernelQueueProbe, KernelSplit};
+use kqueue::run_kqueue_budget;
+use latency::{match_writes, DurationSummary, LatencyReport};
+use matrix::run_matrix;
+use merge::run_merge;
+use normalize::{count_by_kind, describe_counts};
+use options::Options;
+use oracle::{Oracle, OracleScore};
+use order::run_order_comparison;
+use profile::{labelled_path, profile_path, SetupProfiler};
+use rdcw::run_buffer_sweep;
 use recursive_file_watcher::{
-    FilteredNativeRecursiveWatcher, ManualRecursiveWatcher, NativeRecursiveWatcher, WatcherMode,
-    collect_files_recursive,
+    DebouncedRecursiveWatcher, EventReceiver, FilterSet, NativeRecursiveWatcher, PollRecursiveWatcher, WatcherGuard,
+    WatcherMode,
 };
+use report::{run_diff, RunReport};
+use resources::{verify_released, ResourceSample, RELEASE_GRACE};
+use roots::{count_events as count_root_events, mark_fallback_roots, print_root_table, sum_watch_times, RootMetrics};
+use scenarios::{
+    run_async_test, run_coalesce_sweep, run_cross_device_test, run_deep_nesting_test, run_idle_test,
+    run_interference_test, run_large_file_test, run_long_path_test, run_mount_test, run_overflow_test,
+    run_slow_consumer_test, run_small_writes_test, run_stream_test, run_unc_test, run_unusual_names_test, MountKind,
+};
+use scorecard::{print_scorecards, Scorecard};
+use significance::{trim_outliers, MannWhitney};
+use soak::run_soak_test;
+use stats::{CollectedEvents, DirectoryCounts, PathCounts};
+use stream::run_stream_setup;
+use supervise::run_restart_test;
+use syscalls::run_syscall_counts;
+use tiers::{is_priority, TierReport};
+use unwatch::run_unwatch_test;
+use verify::{ContentCheck, ContentSnapshot};
+use watch::run_watch;
+use std::collections::HashSet;
 use std::env;
 use std::fs;
-use std::io;
 use std::path::{Path, PathBuf};
-use std::sync::mpsc;
+use std::sync::{mpsc, Arc};
 use std::time::{Duration, Instant};
 
-/// Get a subset of files for filtered watching (e.g., every 10th file)
-fn get_filtered_files(all_files: &[PathBuf], filter_ratio: usize) -> Vec<PathBuf> {
-    all_files
-        .iter()
-        .enumerate()
-        .filter_map(|(i, path)| {
-            if i % filter_ratio == 0 {
-                Some(path.clone())
-            } else {
-                None
-            }
-        })
-        .collect()
-}
-
-/// Benchmark different watcher modes
-fn benchmark_watcher(dir: &Path, mode: WatcherMode) -> Result<(), Box<dyn std::error::Error>> {
+/// Counts live heap bytes so `filter-memory` can read what each container holds
+#[cfg(feature = "heap-counters")]
+#[global_allocator]
+static ALLOCATOR: heap::CountingAllocator = heap::CountingAllocator;
+/// The chosen allocator on its own, so modes that don't read the counters don't pay for them
+#[cfg(not(feature = "heap-counters"))]
+#[global_allocator]
+static ALLOCATOR: heap::Inner = heap::Inner;
+
+/// Modes that run against a single tree and ignore `--root`
+const SINGLE_ROOT_MODES: [&str; 38] = [
+    "test-bind",
+    "test-overlay",
+    "test-container",
+    "test-cross-device",
+    "test-long-paths",
+    "test-deep-nesting",
+    "test-large-files",
+    "test-small-writes",
+    "test-unc",
+    "test-delete-heavy",
+    "test-create-heavy",
+    "test-attach-latency",
+    "test-rename-storm",
+    "test-npm-install",
+    "test-build-artifacts",
+    "test-restart",
+    "test-budget",
+    "test-hybrid",
+    "test-adaptive",
+    "test-batch",
+    "test-unusual-names",
+    "test-overflow",
+    "test-coalesce-sweep",
+    "fsevents-sweep",
+    "buffer-sweep",
+    "soak",
+    "test-idle",
+    "test-slow-consumer",
+    "test-async",
+    "test-stream",
+    "test-matrix",
+    "test-write-coverage",
+    "test-metadata-coverage",
+    "test-touch",
+    "filter-callback",
+    "watch",
+    "history",
+    "trend",
+];
+
+/// Benchmark different watcher modes, returning the watcher's setup time and the heap activity during it
+fn benchmark_watcher(
+    roots: &[PathBuf],
+    mode: WatcherMode,
+    options: &Options,
+) -> Result<(Duration, AllocStats), Box<dyn std::error::Error>> {
     println!("\n=== Benchmarking {} Watcher ===", mode.display_name());
-    println!("Directory: {}", dir.display());
+    for root in roots {
+        println!("Directory: {}", root.display());
+    }
 
     // First, count the files
     let start_count = Instant::now();
-    let all_files = collect_files_recursive(dir);
+    let listings = enumerate_roots(roots, options)?;
     let count_duration = start_count.elapsed();
+    report_walk_errors(roots, &listings, "");
+    let files_per_root: Vec<Vec<PathBuf>> = listings.into_iter().map(|listing| listing.files).collect();
+    let all_files: Vec<PathBuf> = files_per_root.concat();
     println!("File enumeration: {} files in {:?}", all_files.len(), count_duration);
 
-    // For filtered modes, select a subset of files (every 10th file)
-    let filter_ratio = 10;
-    let filtered_files = get_filtered_files(&all_files, filter_ratio);
+    // For filtered modes, select a subset of files (every 10th file of each root, plus priority files)
+    let filter_ratio = FILTER_RATIO;
+    let filtered_files: Vec<PathBuf> = files_per_root
+        .iter()
+        .flat_map(|files| tiered_files(files, &options.priority))
+        .collect();
+    if !options.priority.is_empty() {
+        let priority = all_files.iter().filter(|path| is_priority(path, &options.priority)).count();
+        println!("Priority files: {} always watched by the filtered modes ({})", priority, options.priority.join(", "));
+    }
+    let mut root_metrics: Vec<RootMetrics> = roots
+        .iter()
+        .map(|root| RootMetrics {
+            root: root.clone(),
+            ..RootMetrics::default()
+        })
+        .collect();
+    let individual = match mode {
+        WatcherMode::ManualFiltered => &filtered_files,
+        _ => &all_files,
+    };
+    preflight_setup(roots, mode, individual, &all_files, options, "")?;
 
     // Setup watcher based on mode
+    let baseline = ResourceSample::take();
+    let allocs = AllocWindow::start();
     let start_setup = Instant::now();
+    let profiler = SetupProfiler::start(options.profile.as_deref(), mode.display_name());
 
-    let (setup_time, rx, watched_count) = match mode {
+    // Files the manual modes hold a watch on, or the native filter lets through
+    let mut covered: Vec<PathBuf> = Vec::new();
+    // Keep the watcher itself alive for the event loop below
+    let (setup_time, watcher, rx, watched_count): (Duration, WatcherGuard, EventReceiver, usize) = match mode {
         WatcherMode::Manual => {
             println!("\nSetting up manual recursive watcher (individual file watches)...");
-            let watcher = ManualRecursiveWatcher::new(dir)?;
+            let watcher = manual_watcher(ordered_watches(all_files.clone(), options), options)?;
             let setup_time = watcher.setup_time();
             let watched = watcher.files_watched();
-            let (_watcher, rx) = watcher.into_parts();
-            (setup_time, rx, watched)
+            covered = watcher.watched_files().to_vec();
+            report_coverage(&watcher, "");
+            report_watch_times(&watcher, options, "");
+            sum_watch_times(&mut root_metrics, watcher.watched_files(), watcher.watch_times());
+            let (watcher, rx) = watcher.into_parts();
+            (setup_time, Box::new(watcher), rx, watched)
         },
         WatcherMode::Native => {
             println!("\nSetting up native recursive watcher...");
-            let watcher = NativeRecursiveWatcher::new(dir)?;
+            let watcher =
+                NativeRecursiveWatcher::new_with_roots_and_config(roots, &options.watch_config())?;
             let setup_time = watcher.setup_time();
-            let (_watcher, rx) = watcher.into_parts();
-            (setup_time, rx, all_files.len())
+            for ((m, time), files) in root_metrics
+                .iter_mut()
+                .zip(watcher.root_setup_times())
+                .zip(&files_per_root)
+            {
+                m.setup_time = *time;
+                m.files = files.len();
+            }
+            mark_fallback_roots(&mut root_metrics, watcher.fallback_roots(), "");
+            let (watcher, rx) = watcher.into_parts();
+            (setup_time, Box::new(watcher), rx, all_files.len())
         },
         WatcherMode::ManualFiltered => {
             println!("\nSetting up manual filtered watcher...");
             println!("Filtering: watching every {}th file ({} out of {} files)",
                      filter_ratio, filtered_files.len(), all_files.len());
-            let watcher = ManualRecursiveWatcher::new_with_files(filtered_files.clone())?;
+            let watcher = manual_watcher(ordered_watches(filtered_files, options), options)?;
             let setup_time = watcher.setup_time();
             let watched = watcher.files_watched();
-            let (_watcher, rx) = watcher.into_parts();
-            (setup_time, rx, watched)
+            covered = watcher.watched_files().to_vec();
+            report_coverage(&watcher, "");
+            report_watch_times(&watcher, options, "");
+            sum_watch_times(&mut root_metrics, watcher.watched_files(), watcher.watch_times());
+            let (watcher, rx) = watcher.into_parts();
+            (setup_time, Box::new(watcher), rx, watched)
         },
         WatcherMode::NativeFiltered => {
             println!("\nSetting up native filtered watcher...");
             println!("Filtering: watching directory but only notifying for {} out of {} files",
                      filtered_files.len(), all_files.len());
-            let watcher = NativeRecursiveWatcher::new_with_filter(dir, filtered_files.clone())?;
+            let watcher = NativeRecursiveWatcher::new_with_roots_filter_and_config(
+                roots,
+                &filtered_files,
+                &options.watch_config(),
+            )?;
             let setup_time = watcher.setup_time();
             let watched = watcher.files_filtered();
-            let (_watcher, rx) = watcher.into_parts();
-            (setup_time, rx, watched)
+            covered = filtered_files;
+            for ((m, time), files) in root_metrics
+                .iter_mut()
+                .zip(watcher.root_setup_times())
+                .zip(&files_per_root)
+            {
+                m.setup_time = *time;
+                m.files = tiered_files(files, &options.priority).len();
+            }
+            mark_fallback_roots(&mut root_metrics, watcher.fallback_roots(), "");
+            let (watcher, rx) = watcher.into_parts();
+            (setup_time, Box::new(watcher), rx, watched)
+        },
+        WatcherMode::Poll => {
+            println!("\nSetting up poll watcher (rescan every {:?})...", options.poll_interval);
+            let watcher =
+                PollRecursiveWatcher::new_with_roots_and_config(roots, &options.watch_config())?;
+            let setup_time = watcher.setup_time();
+            for ((m, time), files) in root_metrics
+                .iter_mut()
+                .zip(watcher.root_setup_times())
+                .zip(&files_per_root)
+            {
+                m.setup_time = *time;
+                m.files = files.len();
+            }
+            let (watcher, rx) = watcher.into_parts();
+            (setup_time, Box::new(watcher), rx, all_files.len())
+        },
+        WatcherMode::Debounced => {
+            println!("\nSetting up debounced watcher ({:?} quiet period)...", options.debounce);
+            let watcher =
+                DebouncedRecursiveWatcher::new_with_roots_and_config(roots, &options.watch_config())?;
+            let setup_time = watcher.setup_time();
+            for ((m, time), files) in root_metrics
+                .iter_mut()
+                .zip(watcher.root_setup_times())
+                .zip(&files_per_root)
+            {
+                m.setup_time = *time;
+                m.files = files.len();
+            }
+            let (debouncer, rx) = watcher.into_parts();
+            (setup_time, Box::new(debouncer), rx, all_files.len())
         },
     };
 
     let total_setup_time = start_setup.elapsed();
+    let allocs = allocs.finish();
+    if let Some(profiler) = profiler {
+        profiler.finish();
+    }
 
     println!("\n--- Setup Complete ---");
     println!("Watcher setup time: {:?}", setup_time);
@@ -92,6 +344,20 @@ fn benchmark_watcher(dir: &Path, mode: WatcherMode) -> Result<(), Box<dyn std::e
         println!("Average time per filtered file: {:?}",
                  setup_time / watched_count.max(1) as u32);
     }
+    if heap::COUNTING {
+        println!(
+            "Setup allocations ({} allocator): {} totalling {}, {} still held, peak +{}",
+            ALLOCATOR_NAME,
+            allocs.allocations,
+            format_bytes(allocs.bytes as usize),
+            format_bytes(allocs.retained_bytes),
+            format_bytes(allocs.peak_bytes)
+        );
+    }
+
+    if let Some(base) = &options.dot {
+        write_dot(base, roots, &all_files, mode, &covered, options);
+    }
 
     // Keep the watcher alive for a bit to test event handling
     println!("\nWatcher is active. Waiting for events (5 seconds)...");
@@ -100,20 +366,40 @@ fn benchmark_watcher(dir: &Path, mode: WatcherMode) -> Result<(), Box<dyn std::e
     // Try to receive events for 5 seconds
     let test_duration = Duration::from_secs(5);
     let test_start = Instant::now();
-    let mut event_count = 0;
+    let mut collected = CollectedEvents::default();
+    let mut directories = DirectoryCounts::default();
+    let mut paths = PathCounts::default();
+    let mut heatmap = Heatmap::new(roots);
+    let sampler = QueueDepthSampler::start(rx.depth());
 
     while test_start.elapsed() < test_duration {
         match rx.recv_timeout(Duration::from_millis(100)) {
-            Ok(Ok(event)) => {
-                event_count += 1;
+            Ok(item) => {
+                let received = collected.events.len();
+                collected.record(item);
+                let Some(event) = collected.events.get(received) else {
+                    continue;
+                };
+                let event_count = collected.events.len();
+                // Each of these allocates per event, so it's only fed when its report is asked for
+                if options.top_dirs > 0 {
+                    directories.record(event);
+                }
+                if options.top_paths > 0 {
+                    paths.record(event);
+                }
+                if options.wants_heatmap() {
+                    heatmap.record(event);
+                }
+                count_root_events(&mut root_metrics, std::slice::from_ref(event));
+                if !options.consumer_delay.is_zero() {
+                    std::thread::sleep(options.consumer_delay);
+                }
                 if event_count <= 5 {
                     println!("Event #{}: {:?} for {:?}",
                              event_count, event.kind, event.paths);
                 }
             }
-            Ok(Err(e)) => {
-                eprintln!("Watch error: {:?}", e);
-            }
             Err(mpsc::RecvTimeoutError::Timeout) => {
                 // No events, continue waiting
             }
@@ -124,102 +410,229 @@ fn benchmark_watcher(dir: &Path, mode: WatcherMode) -> Result<(), Box<dyn std::e
         }
     }
 
+    let event_count = collected.events.len();
     if event_count > 5 {
         println!("... and {} more events", event_count - 5);
     } else if event_count == 0 {
         println!("No events received (this is expected if no files were modified)");
     }
 
+    recover_from_overflow(&mut collected, roots, options);
+    collected.report_overflow("");
+    collected.kinds().report("");
+    directories.report(roots, options.top_dirs, "");
+    paths.report(roots, options.top_paths, "");
+    report_heatmap(&heatmap, mode, options, "");
+    sampler.finish().report("");
+    rx.overhead().report(options.channel.display_name(), "");
+    rx.backpressure().report("");
+    rx.injected().report("");
+    rx.rate_limited().report("");
+    if roots.len() > 1 {
+        println!("\nPer-root metrics:");
+        print_root_table(&root_metrics, "  ");
+    }
+
+    drop(watcher);
+    drop(rx);
+    verify_released(&baseline, RELEASE_GRACE)?;
+
     println!("\n=== Benchmark Complete ===\n");
 
-    Ok(())
+    Ok((setup_time, allocs))
 }
 
-/// Copy directory recursively to a temporary location
-fn copy_dir_recursive(src: &Path, dst: &Path) -> io::Result<()> {
-    // Create destination directory
-    fs::create_dir_all(dst)?;
-
-    // Read the source directory
-    for entry in fs::read_dir(src)? {
-        let entry = entry?;
-        let path = entry.path();
-        let file_name = entry.file_name();
-        let dest_path = dst.join(file_name);
-
-        if path.is_dir() {
-            // Recursively copy subdirectory
-            copy_dir_recursive(&path, &dest_path)?;
-        } else {
-            // Copy file
-            fs::copy(&path, &dest_path)?;
+/// Print `heatmap` with `--heatmap` and write its treemap with `--heatmap-svg`
+fn report_heatmap(heatmap: &Heatmap, mode: WatcherMode, options: &Options, indent: &str) {
+    if options.heatmap {
+        heatmap.print(indent);
+    }
+    if let Some(base) = &options.heatmap_svg {
+        let path = profile_path(base, mode.display_name());
+        match fs::write(&path, heatmap.render_svg(mode.display_name())) {
+            Ok(()) => println!("{}Heatmap written to {}", indent, path.display()),
+            Err(e) => eprintln!("{}Could not write heatmap to {}: {}", indent, path.display(), e),
         }
     }
+}
 
-    Ok(())
+/// Write the tree below `roots` as Graphviz, marking how `mode` covers each of `files`
+fn write_dot(base: &Path, roots: &[PathBuf], files: &[PathBuf], mode: WatcherMode, covered: &[PathBuf], options: &Options) {
+    if files.len() > DOT_MAX_FILES {
+        eprintln!(
+            "Not writing --dot: {} files is more than Graphviz lays out usefully (at most {})",
+            files.len(),
+            DOT_MAX_FILES
+        );
+        return;
+    }
+    let marked = mark_files(mode, files, covered, &options.exclude);
+    let path = labelled_path(base, mode.display_name(), "dot");
+    match fs::write(&path, render_dot(roots, &marked, mode.display_name())) {
+        Ok(()) => println!("Watched tree written to {}", path.display()),
+        Err(e) => eprintln!("Could not write watched tree to {}: {}", path.display(), e),
+    }
 }
 
-/// Run watch test with temporary directory
-fn run_watch_test(dir: &Path, mode: WatcherMode) -> Result<(), Box<dyn std::error::Error>> {
-    // Get the directory name for the temp path
-    let dir_name = dir.file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("test");
+/// Outcome of one `run_watch_test` run
+struct WatchTestResult {
+    mode: WatcherMode,
+    /// Watcher setup including the harness's own overhead
+    setup_time: Duration,
+    /// Time spent adding watches alone, comparable with `compare`'s setup times
+    watch_setup_time: Duration,
+    files_modified: usize,
+    collected: CollectedEvents,
+    coalesced: Option<CoalesceStats>,
+    /// Per-directory notifications, with `--aggregate-dirs`
+    aggregated: Option<AggregateStats>,
+    latency: LatencyReport,
+    /// Events checked against content hashes, with `--verify-content`
+    content: Option<ContentCheck>,
+    /// Events matched against the workload's expectations
+    oracle: OracleScore,
+    /// What the watcher still held after it was dropped, if anything
+    unreleased: Option<String>,
+    /// Roots polled because the native backend refused them
+    fallback_roots: Vec<PathBuf>,
+    /// Coverage and latency of priority files and the rest, with `--priority`
+    tiers: Vec<TierReport>,
+}
 
-    let tmp_dir = PathBuf::from("./tmp").join(dir_name);
+impl WatchTestResult {
+    /// Add this result's headline numbers to `run`
+    fn record(&self, run: &mut RunRecord) {
+        let mode = self.mode.display_name();
+        run.add_duration(mode, "setup_us", self.watch_setup_time);
+        run.add(mode, "events", self.collected.events.len() as f64);
+        run.add(mode, "unmatched", self.latency.unmatched as f64);
+        if let Some(mean) = self.latency.mean_events_per_write() {
+            run.add(mode, "events_per_modification", mean);
+        }
+        if let Some(total) = self.latency.total() {
+            run.add_duration(mode, "latency_p50_us", total.p50);
+            run.add_duration(mode, "latency_p99_us", total.p99);
+        }
+        run.add(mode, "precision", self.oracle.precision());
+        run.add(mode, "recall", self.oracle.recall());
+        run.add(mode, "spurious", self.oracle.spurious.len() as f64);
+        if self.collected.faults.total() > 0 {
+            run.add(mode, "injected_faults", self.collected.faults.total() as f64);
+        }
+        let limited = &self.collected.rate_limited;
+        if limited.delayed + limited.merged > 0 {
+            run.add(mode, "rate_delayed", limited.delayed as f64);
+            run.add(mode, "rate_merged", limited.merged as f64);
+        }
+        if let Some(aggregated) = &self.aggregated {
+            run.add(mode, "directory_notifications", aggregated.notifications as f64);
+            run.add(mode, "directory_compression", aggregated.compression_ratio());
+        }
+        if !self.fallback_roots.is_empty() {
+            run.add(mode, "poll_fallback_roots", self.fallback_roots.len() as f64);
+        }
+        for tier in &self.tiers {
+            if let Some(coverage) = tier.coverage() {
+                run.add(mode, &format!("{}_coverage", tier.tier.slug()), coverage);
+            }
+            if let Some(total) = tier.latency.total() {
+                run.add_duration(mode, &format!("{}_latency_p50_us", tier.tier.slug()), total.p50);
+            }
+        }
+        if let Some(content) = &self.content {
+            run.add(mode, "false_negatives", content.false_negatives.len() as f64);
+            run.add(mode, "false_positives", content.false_positives.len() as f64);
+        }
+    }
+}
+
+/// Fail the run if any watcher still held descriptors or watches after it was dropped
+fn check_released(results: &[WatchTestResult]) -> Result<(), Box<dyn std::error::Error>> {
+    let leaks: Vec<String> = results
+        .iter()
+        .filter_map(|result| Some(format!("{}: {}", result.mode.display_name(), result.unreleased.as_ref()?)))
+        .collect();
+    if leaks.is_empty() {
+        Ok(())
+    } else {
+        Err(leaks.join("; ").into())
+    }
+}
+
+/// Run watch test with temporary directory
+fn run_watch_test(
+    roots: &[PathBuf],
+    mode: WatcherMode,
+    options: &Options,
+) -> Result<WatchTestResult, Box<dyn std::error::Error>> {
+    // Get the directory name for the temp path, numbered when there are several roots
+    let tmp_dirs: Vec<PathBuf> = roots
+        .iter()
+        .enumerate()
+        .map(|(i, dir)| {
+            let dir_name = dir.file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("test");
+            if roots.len() > 1 {
+                PathBuf::from("./tmp").join(format!("{}-{}", dir_name, i))
+            } else {
+                PathBuf::from("./tmp").join(dir_name)
+            }
+        })
+        .collect();
 
     println!("\n=== Watch Test for {} ===", mode.display_name());
-    println!("Source directory: {}", dir.display());
-    println!("Temporary directory: {}", tmp_dir.display());
+    for (dir, tmp_dir) in roots.iter().zip(&tmp_dirs) {
+        println!("Source directory: {}", dir.display());
+        println!("Temporary directory: {}", tmp_dir.display());
+    }
 
     // Step 1: Copy files to temporary directory
     println!("\n1. Copying files to temporary directory...");
     let copy_start = Instant::now();
 
-    // Remove temp dir if it exists
-    if tmp_dir.exists() {
-        fs::remove_dir_all(&tmp_dir)?;
-    }
+    for (dir, tmp_dir) in roots.iter().zip(&tmp_dirs) {
+        // Remove temp dir if it exists
+        if tmp_dir.exists() {
+            fs::remove_dir_all(tmp_dir)?;
+        }
 
-    copy_dir_recursive(dir, &tmp_dir)?;
+        copy_dir_recursive(dir, tmp_dir, options)?;
+    }
     let copy_duration = copy_start.elapsed();
 
-    let file_count = collect_files_recursive(&tmp_dir).len();
+    let copied: Vec<Vec<PathBuf>> = tmp_dirs
+        .iter()
+        .map(|tmp_dir| enumerate_dir(tmp_dir, options))
+        .collect::<Result<_, _>>()?;
+    let file_count: usize = copied.iter().map(Vec::len).sum();
     println!("   Copied {} files in {:?}", file_count, copy_duration);
 
+    // Hashed before the watcher starts, so reading every file doesn't show up as events
+    let watched: Vec<PathBuf> = copied
+        .iter()
+        .flat_map(|files| select_watched(mode, files.clone(), &options.priority))
+        .filter_map(|path| std::path::absolute(path).ok())
+        .collect();
+    let watched_set: HashSet<&Path> = watched.iter().map(PathBuf::as_path).collect();
+    let before = options.verify_content.then(|| {
+        let snapshot = ContentSnapshot::take(&watched);
+        println!("   Hashed {} watched files", snapshot.len());
+        snapshot
+    });
+
     // Step 2: Set up watcher
     println!("\n2. Setting up {} watcher...", mode.display_name());
+    if let Err(e) = preflight_setup(&tmp_dirs, mode, &watched, &copied.concat(), options, "   ") {
+        for tmp_dir in &tmp_dirs {
+            let _ = fs::remove_dir_all(tmp_dir);
+        }
+        return Err(e.into());
+    }
+    let baseline = ResourceSample::take();
     let setup_start = Instant::now();
 
-    let (_watcher, rx) = match mode {
-        WatcherMode::Manual => {
-            let watcher = ManualRecursiveWatcher::new(&tmp_dir)?;
-            println!("   Setup time: {:?}", watcher.setup_time());
-            println!("   Files watched: {}", watcher.files_watched());
-            watcher.into_parts()
-        },
-        WatcherMode::Native => {
-            let watcher = NativeRecursiveWatcher::new(&tmp_dir)?;
-            println!("   Setup time: {:?}", watcher.setup_time());
-            watcher.into_parts()
-        },
-        WatcherMode::ManualFiltered => {
-            let all_files = collect_files_recursive(&tmp_dir);
-            let filtered_files = get_filtered_files(&all_files, 10);
-            let watcher = ManualRecursiveWatcher::new_with_files(filtered_files)?;
-            println!("   Setup time: {:?}", watcher.setup_time());
-            println!("   Files watched: {}", watcher.files_watched());
-            watcher.into_parts()
-        },
-        WatcherMode::NativeFiltered => {
-            let all_files = collect_files_recursive(&tmp_dir);
-            let filtered_files = get_filtered_files(&all_files, 10);
-            let watcher = NativeRecursiveWatcher::new_with_filter(&tmp_dir, filtered_files)?;
-            println!("   Setup time: {:?}", watcher.setup_time());
-            println!("   Files filtered: {}", watcher.files_filtered());
-            watcher.into_parts()
-        },
-    };
+    let (watcher, rx, mut root_metrics) = start_watcher_on_roots(mode, &tmp_dirs, options)?;
 
     let setup_duration = setup_start.elapsed();
     println!("   Total setup time: {:?}", setup_duration);
@@ -227,68 +640,153 @@ fn run_watch_test(dir: &Path, mode: WatcherMode) -> Result<(), Box<dyn std::erro
     // Step 3: Run tests (modify files and observe events)
     println!("\n3. Running file modification tests...");
 
-    // Get some files to modify
-    let test_files = collect_files_recursive(&tmp_dir);
-    let files_to_modify: Vec<_> = test_files.iter()
-        .take(5.min(test_files.len()))
+    // Get some files to modify from every root; `hot` ranks them by their source's mtime,
+    // since copying doesn't carry modification times over
+    let test_files: Vec<Vec<PathBuf>> = roots
+        .iter()
+        .zip(&tmp_dirs)
+        .zip(&copied)
+        .map(|((dir, tmp_dir), files)| {
+            let source_mtime = |path: &Path| {
+                let source = dir.join(path.strip_prefix(tmp_dir).ok()?);
+                fs::metadata(source).and_then(|metadata| metadata.modified()).ok()
+            };
+            options.modify_select.select(
+                files,
+                options.modify_count,
+                options.seed,
+                source_mtime,
+            )
+        })
+        .collect();
+    // Priority files are always modified too, up to `--modify-count` per root, so their tier has writes to measure
+    let picked: HashSet<&PathBuf> = test_files.iter().flatten().collect();
+    let priority_files: Vec<PathBuf> = copied
+        .iter()
+        .flat_map(|files| {
+            files
+                .iter()
+                .filter(|path| is_priority(path, &options.priority) && !picked.contains(path))
+                .take(options.modify_count)
+        })
+        .cloned()
         .collect();
+    let files_to_modify: Vec<_> = test_files.iter().flatten().chain(&priority_files).collect();
+
+    let mut collected = CollectedEvents::default();
+    let mut latency = LatencyReport::default();
+    let mut content = None;
+    let mut oracle = Oracle::default();
+    let mut score = OracleScore::default();
+    let mut tiers = Vec::new();
 
     if files_to_modify.is_empty() {
         println!("   No files to modify for testing");
     } else {
-        println!("   Modifying {} test files...", files_to_modify.len());
+        println!(
+            "   Modifying {} test files ({} per root, {})...",
+            files_to_modify.len(),
+            options.modify_count,
+            options.modify_select.display_name()
+        );
+        if !priority_files.is_empty() {
+            println!("   Including {} priority files", priority_files.len());
+        }
 
         // Start event collection thread
-        let (event_tx, event_rx) = mpsc::channel();
-        let test_duration = Duration::from_secs(3);
-
-        std::thread::spawn(move || {
-            let start = Instant::now();
-            let mut events = Vec::new();
-
-            while start.elapsed() < test_duration {
-                match rx.recv_timeout(Duration::from_millis(10)) {
-                    Ok(Ok(event)) => {
-                        events.push(event);
-                    }
-                    Ok(Err(e)) => {
-                        eprintln!("Watch error: {:?}", e);
-                    }
-                    Err(_) => {
-                        // Timeout or disconnected
-                    }
-                }
-            }
-
-            event_tx.send(events).unwrap();
-        });
+        let settle = settle_for(mode, options);
+        let collector = SettlingCollector::spawn(rx, settle, options.max_collect, options.consumer_delay);
 
         // Give watcher time to stabilize
-        std::thread::sleep(Duration::from_millis(100));
+        std::thread::sleep(options.stabilize);
+
+        let probe = if options.kernel_probe {
+            KernelQueueProbe::attach()
+                .map_err(|e| eprintln!("   Kernel probe unavailable: {}", e))
+                .ok()
+        } else {
+            None
+        };
 
         // Modify files
         let modify_start = Instant::now();
-        for (i, file_path) in files_to_modify.iter().enumerate() {
-            // Append to file
-            if let Ok(mut content) = fs::read_to_string(file_path) {
-                content.push_str(&format!("\n// Modified by test {}", i));
-                if let Err(e) = fs::write(file_path, content) {
-                    eprintln!("   Failed to modify {}: {}", file_path.display(), e);
-                }
-            }
-            // Small delay between modifications
-            std::thread::sleep(Duration::from_millis(10));
-        }
+        let writes = append_to_files(&files_to_modify, options.pace);
         let modify_duration = modify_start.elapsed();
+        // Only files this mode watches are owed an event; the filtered modes skip the rest by design,
+        // so neither the oracle nor the latency match counts them as missed
+        let watched_writes: Vec<_> = writes
+            .iter()
+            .filter(|write| std::path::absolute(&write.path).is_ok_and(|path| watched_set.contains(path.as_path())))
+            .cloned()
+            .collect();
+        oracle.expect_writes(&watched_writes);
+        for write in &writes {
+            oracle.touch(&write.path);
+        }
 
         println!("   Modified {} files in {:?}", files_to_modify.len(), modify_duration);
 
         // Wait for events
-        println!("   Collecting events for {:?}...", test_duration);
+        println!("   Collecting events until {:?} pass without one (at most {:?})...", settle, options.max_collect);
+        collector.workload_done();
 
         // Get collected events
-        if let Ok(events) = event_rx.recv_timeout(test_duration + Duration::from_secs(1)) {
+        if let Some(settled) = collector.finish() {
+            settled.report(settle, "   ");
+            collected = settled.collected;
+            recover_from_overflow(&mut collected, &tmp_dirs, options);
+            let events = &collected.events;
             println!("   Received {} events", events.len());
+            collected.kinds().report("   ");
+            if options.top_dirs > 0 {
+                DirectoryCounts::from_events(events).report(&tmp_dirs, options.top_dirs, "   ");
+            }
+            if options.top_paths > 0 {
+                PathCounts::from_events(events).report(&tmp_dirs, options.top_paths, "   ");
+            }
+            if options.wants_heatmap() {
+                report_heatmap(&Heatmap::from_events(&tmp_dirs, events), mode, options, "   ");
+            }
+            println!("   Normalized: {}", describe_counts(&count_by_kind(&collected.normalized())));
+            if let Some(window) = options.coalesce_window {
+                collected.coalesce(window).report("   ");
+            }
+            if let Some(window) = options.aggregate_window {
+                collected.aggregate(window).report("   ");
+            }
+            collected.report_overflow("   ");
+            collected.queue_depth.report("   ");
+            collected.channel.report(options.channel.display_name(), "   ");
+            collected.backpressure.report("   ");
+            collected.faults.report("   ");
+            collected.rate_limited.report("   ");
+            latency = match_writes(&watched_writes, &collected);
+            latency.report("   ");
+            if !options.priority.is_empty() {
+                // Over every write, so writes to files a filtered mode left unwatched show up as missed
+                tiers = TierReport::measure(&writes, &options.priority, &collected).to_vec();
+                for tier in &tiers {
+                    tier.report("   ");
+                }
+            }
+            score = oracle.score(&collected, options.expect_timeout);
+            score.report("   ");
+            if let Some(before) = &before {
+                let check = ContentCheck::compare(before, &ContentSnapshot::take(&watched), &collected);
+                check.report("   ");
+                content = Some(check);
+            }
+            if let Some(probe) = probe {
+                let inodes = inodes_of(files_to_modify.iter().copied());
+                KernelSplit::from_writes(&watched_writes, &collected, &probe.finish(), &inodes).report("   ");
+            }
+            if let Some(format) = options.histogram {
+                latency.print_histograms(mode.display_name(), &options.histogram_buckets, format, "   ");
+            }
+            if roots.len() > 1 {
+                count_root_events(&mut root_metrics, events);
+                print_root_table(&root_metrics, "   ");
+            }
 
             // Show first few events
             for (i, event) in events.iter().take(3).enumerate() {
@@ -301,45 +799,453 @@ fn run_watch_test(dir: &Path, mode: WatcherMode) -> Result<(), Box<dyn std::erro
         }
     }
 
+    // Checked before the tree is deleted, which would take any leaked watches with it
+    drop(watcher);
+    let unreleased = verify_released(&baseline, RELEASE_GRACE).err();
+    if let Some(unreleased) = &unreleased {
+        println!("   ⚠️  {}", unreleased);
+    }
+
     // Step 4: Cleanup
     println!("\n4. Cleaning up temporary directory...");
     let cleanup_start = Instant::now();
-    fs::remove_dir_all(&tmp_dir)?;
+    for tmp_dir in &tmp_dirs {
+        fs::remove_dir_all(tmp_dir)?;
+    }
     let cleanup_duration = cleanup_start.elapsed();
     println!("   Cleanup completed in {:?}", cleanup_duration);
 
     println!("\n=== Watch Test Complete ===\n");
 
-    Ok(())
+    Ok(WatchTestResult {
+        mode,
+        setup_time: setup_duration,
+        watch_setup_time: root_metrics.iter().map(|m| m.setup_time).sum(),
+        files_modified: files_to_modify.len(),
+        coalesced: options.coalesce_window.map(|window| collected.coalesce(window)),
+        aggregated: options.aggregate_window.map(|window| collected.aggregate(window)),
+        collected,
+        latency,
+        content,
+        oracle: score,
+        unreleased,
+        fallback_roots: root_metrics
+            .iter()
+            .filter(|m| m.fallback.is_some())
+            .map(|m| m.root.clone())
+            .collect(),
+        tiers,
+    })
+}
+
+/// Print one line per mode summarizing events and the watch error breakdown
+fn print_watch_test_summary(results: &[WatchTestResult]) {
+    println!("\n{}", "=".repeat(60));
+    println!("\n📊 Watch Test Summary:");
+    println!(
+        "  {:<20} {:>8} {:>8} {:>7} {:>9} {:>8} {:>7} {:>9} {:>9} {:>7} {:>9} {:>8} {:>9} {:>6} {:>9} {:>10} {:>10} {:>10}  {:<28} Kinds",
+        "Mode", "Modified", "Events", "Ev/Mod", "Prec/Rec", "Spurious", "FN/FP", "Overflow", "NotFound", "Denied", "MaxWatch", "Generic",
+        "Coalesced", "MaxQ", "Blk/Drop", "Backend", "Queue", "Send", "Normalized"
+    );
+    for result in results {
+        let errors = &result.collected.errors;
+        let p50 = |summary: Option<latency::DurationSummary>| {
+            summary.map_or("-".to_string(), |s| format!("{:.1?}", s.p50))
+        };
+        println!(
+            "  {:<20} {:>8} {:>8} {:>7} {:>9} {:>8} {:>7} {:>9} {:>9} {:>7} {:>9} {:>8} {:>9} {:>6} {:>9} {:>10} {:>10} {:>10}  {:<28} {}",
+            result.mode.display_name(),
+            result.files_modified,
+            result.collected.events.len(),
+            result.latency.mean_events_per_write().map_or("-".to_string(), |mean| format!("{:.2}", mean)),
+            result.oracle.describe(),
+            result.oracle.spurious.len(),
+            result.content.as_ref().map_or("-".to_string(), ContentCheck::describe),
+            if result.collected.overflowed() { "yes" } else { "no" },
+            errors.path_not_found,
+            errors.permission_denied,
+            errors.max_files_watched,
+            errors.generic,
+            result.coalesced.map_or("-".to_string(), |c| c.coalesced.to_string()),
+            result.collected.queue_depth.max,
+            format!("{}/{}", result.collected.backpressure.blocked, result.collected.backpressure.dropped),
+            p50(result.latency.backend()),
+            p50(result.latency.queue()),
+            result.collected.channel.mean_send().map_or("-".to_string(), |d| format!("{:.1?}", d)),
+            describe_counts(&count_by_kind(&result.collected.normalized())),
+            result.collected.kinds().compact()
+        );
+    }
+    for result in results {
+        if let Some(aggregated) = &result.aggregated {
+            println!(
+                "  {} aggregated {} per-file events into {} directory notifications ({:.1}x)",
+                result.mode.display_name(),
+                aggregated.normalized,
+                aggregated.notifications,
+                aggregated.compression_ratio()
+            );
+        }
+    }
+    for result in results.iter().filter(|result| !result.fallback_roots.is_empty()) {
+        let roots: Vec<String> = result.fallback_roots.iter().map(|root| root.display().to_string()).collect();
+        println!("  {} polled {} instead of watching natively", result.mode.display_name(), roots.join(", "));
+    }
+    for result in results.iter().filter(|result| !result.tiers.is_empty()) {
+        let tiers: Vec<String> = result
+            .tiers
+            .iter()
+            .map(|tier| {
+                let coverage = tier.coverage().map_or("-".to_string(), |coverage| format!("{:.1}%", coverage * 100.0));
+                let p50 = tier.latency.total().map_or("-".to_string(), |total| format!("{:.1?}", total.p50));
+                format!("{} {} (p50 {})", tier.tier.display_name(), coverage, p50)
+            })
+            .collect();
+        println!("  {} coverage by tier: {}", result.mode.display_name(), tiers.join(", "));
+    }
+}
+
+/// Print both sides' setup times and which is faster
+///
+/// With several iterations the ratio is of medians, and the verdict says whether a
+/// Mann-Whitney U test over the runs finds the difference significant. With
+/// `--trim-outliers` the comparison is repeated without runs beyond that many MADs.
+fn print_setup_comparison(a: (&str, &[Duration]), b: (&str, &[Duration]), options: &Options) {
+    print_setup_lines(a, b, "  ");
+
+    let iterated = a.1.len() > 1 || b.1.len() > 1;
+    if let (Some(k), true) = (options.trim_outliers, iterated) {
+        let (a_kept, b_kept) = (trim_outliers(a.1, k), trim_outliers(b.1, k));
+        println!(
+            "  With runs beyond {} MAD trimmed ({} and {} dropped):",
+            k,
+            a.1.len() - a_kept.len(),
+            b.1.len() - b_kept.len()
+        );
+        print_setup_lines((a.0, &a_kept), (b.0, &b_kept), "    ");
+    }
+}
+
+/// Setup time lines and the speedup verdict for one set of runs
+fn print_setup_lines(a: (&str, &[Duration]), b: (&str, &[Duration]), indent: &str) {
+    let (Some(a_summary), Some(b_summary)) = (
+        DurationSummary::from_durations(a.1.to_vec()),
+        DurationSummary::from_durations(b.1.to_vec()),
+    ) else {
+        println!("{}Not enough successful runs to compare", indent);
+        return;
+    };
+    let iterated = a.1.len() > 1 || b.1.len() > 1;
+    for (name, times, summary) in [(a.0, a.1, a_summary), (b.0, b.1, b_summary)] {
+        if iterated {
+            println!(
+                "{}{} setup time: {:?} median, {:?} mean, {:?}..{:?} over {} runs",
+                indent, name, summary.p50, summary.mean, summary.min, summary.max, times.len()
+            );
+        } else {
+            println!("{}{} setup time: {:?}", indent, name, summary.p50);
+        }
+    }
+
+    let verdict = if iterated {
+        let secs = |times: &[Duration]| times.iter().map(Duration::as_secs_f64).collect::<Vec<_>>();
+        MannWhitney::test(&secs(a.1), &secs(b.1)).map_or(String::new(), |test| format!(" ({})", test.describe()))
+    } else {
+        String::new()
+    };
+    let (a_time, b_time) = (a_summary.p50, b_summary.p50);
+    if b_time < a_time {
+        let speedup = a_time.as_nanos() as f64 / b_time.as_nanos() as f64;
+        println!("{}{} is {:.2}x faster{}", indent, b.0, speedup, verdict);
+    } else {
+        let speedup = b_time.as_nanos() as f64 / a_time.as_nanos() as f64;
+        println!("{}{} is {:.2}x faster{}", indent, a.0, speedup, verdict);
+    }
+}
+
+/// Print the `compare-all` table: setup cost against the fastest mode, then delivery
+fn print_compare_all_table(results: &[WatchTestResult]) {
+    let fastest = results.iter().map(|r| r.setup_time).min().unwrap_or_default();
+    println!("\n{}", "=".repeat(60));
+    println!("\n📊 All Modes Comparison:");
+    println!(
+        "  {:<20} {:>12} {:>9} {:>8} {:>7} {:>8} {:>9} {:>8} {:>7} {:>10} {:>10} {:>10}  Normalized",
+        "Mode", "Setup", "vs best", "Events", "Ev/Mod", "Missed", "Prec/Rec", "Spurious", "FN/FP", "Total p50", "Total p99", "Errors"
+    );
+    for result in results {
+        let total = result.latency.total();
+        let latency = |d: Option<Duration>| d.map_or("-".to_string(), |d| format!("{:.1?}", d));
+        println!(
+            "  {:<20} {:>12} {:>9} {:>8} {:>7} {:>8} {:>9} {:>8} {:>7} {:>10} {:>10} {:>10}  {}",
+            result.mode.display_name(),
+            format!("{:.1?}", result.setup_time),
+            format!("{:.2}x", result.setup_time.as_secs_f64() / fastest.as_secs_f64().max(f64::EPSILON)),
+            result.collected.events.len(),
+            result.latency.mean_events_per_write().map_or("-".to_string(), |mean| format!("{:.2}", mean)),
+            result.latency.unmatched,
+            result.oracle.describe(),
+            result.oracle.spurious.len(),
+            result.content.as_ref().map_or("-".to_string(), ContentCheck::describe),
+            latency(total.map(|s| s.p50)),
+            latency(total.map(|s| s.p99)),
+            result.collected.errors.total() + result.collected.overflow_errors,
+            describe_counts(&count_by_kind(&result.collected.normalized()))
+        );
+    }
+}
+
+/// Add each churn result's detection, pickup and watch-leak numbers to `run`
+fn record_churn(run: &mut RunRecord, results: &[ChurnResult]) {
+    for result in results {
+        let mode = result.mode.display_name();
+        run.add(mode, "recall", result.score.recall());
+        if let Some(latency) = result.pickup.as_ref().and_then(|pickup| pickup.latency) {
+            run.add_duration(mode, "pickup_p50_us", latency.p50);
+        }
+        if let Some(latency) = result.attach.as_ref().and_then(|attach| attach.latency) {
+            run.add_duration(mode, "attach_p50_us", latency.p50);
+            run.add_duration(mode, "attach_p99_us", latency.p99);
+        }
+        if let Some(renames) = result.renames {
+            run.add(mode, "renames_paired", renames.paired as f64);
+            run.add(mode, "renames_dropped", renames.dropped as f64);
+        }
+        if let Some(noise) = result.noise_events {
+            run.add(mode, "noise_events", noise as f64);
+            run.add(mode, "excluded_events", result.excluded as f64);
+        }
+        if let Some(leaked) = result.leaked_watches() {
+            run.add(mode, "leaked_watches", leaked as f64);
+        }
+    }
+}
+
+/// Append this invocation to the `--history` store, warning rather than failing the run
+fn record_run(path: &Path, run: &RunRecord, tree: &TreeFingerprint, environment: &Environment, status: &str) {
+    match History::open(path).and_then(|mut history| history.append(run, tree, environment, status)) {
+        Ok(()) => println!("Recorded run {} in {}", run.id, path.display()),
+        Err(e) => eprintln!("Failed to record run in {}: {}", path.display(), e),
+    }
+}
+
+/// Write this invocation's `--report` file, warning rather than failing the run
+fn write_report(path: &Path, report: &RunReport) {
+    match report.write(path) {
+        Ok(()) => println!("Wrote report {}", path.display()),
+        Err(e) => eprintln!("Failed to write report {}: {}", path.display(), e),
+    }
 }
 
 fn print_usage(program: &str) {
-    eprintln!("Usage: {} <directory> <mode>", program);
+    eprintln!("Usage: {} <directory> <mode> [options]", program);
+    eprintln!("       {} schema [snapshot|histogram|report|merged] - Print the JSON Schema of the structured output formats", program);
+    eprintln!("       {} diff <a.json> <b.json>       - Compare two --report files metric by metric, highlighting regressions", program);
+    eprintln!("       {} merge <file>... [--report <out>] - Combine --report files from several machines into one table per environment", program);
+    eprintln!("       {} capabilities <file>... [--capability-md <out>] - Summarise merged results per OS: failing scenarios and winning modes", program);
     eprintln!();
     eprintln!("Modes:");
     eprintln!("  manual           - Manually recursive: watch each file individually");
     eprintln!("  native           - Native recursive: use built-in recursive watching");
     eprintln!("  manual-filtered  - Manual with subset: watch only every 10th file");
     eprintln!("  native-filtered  - Native with filter: watch dir but filter events");
+    eprintln!("  poll             - Poll: rescan the tree every --poll-interval");
+    eprintln!("  debounced        - Native recursive events through notify's debouncer");
     eprintln!("  compare          - Compare manual vs native modes");
     eprintln!("  compare-filtered - Compare filtered manual vs filtered native");
+    eprintln!("  compare-all      - Run a watch test for every mode, including poll and debounced, in one table");
     eprintln!();
     eprintln!("Test Modes (with file modifications):");
     eprintln!("  test-manual      - Test manual watcher with file modifications");
     eprintln!("  test-native      - Test native watcher with file modifications");
     eprintln!("  test-filtered    - Test both filtered watchers");
-    eprintln!("  test-all         - Run all watch tests");
+    eprintln!("  test-all         - Run all watch tests, then grade each mode on a correctness scorecard");
+    eprintln!();
+    eprintln!("Mount Boundary Tests (require root / a container runtime):");
+    eprintln!("  test-bind        - Watch a bind mount, write via mount and backing dir");
+    eprintln!("  test-overlay     - Watch an overlayfs merged dir, write via merged and lower dir");
+    eprintln!("  test-container   - Watch a dir shared as a container volume, write from both sides");
+    eprintln!();
+    eprintln!("Scenario Tests:");
+    eprintln!("  test-cross-device - Move files into the tree from another filesystem");
+    eprintln!("  test-long-paths  - Watch a generated tree with paths over Windows' 260-character MAX_PATH");
+    eprintln!("  test-deep-nesting - Watch a single chain of directories hundreds of levels deep");
+    eprintln!("  test-large-files - Append to, patch and rewrite multi-hundred-MB files and time detection per mode");
+    eprintln!("  test-small-writes - Append thousands of tiny lines per second to a few files and report events per write");
+    eprintln!("  test-unusual-names - Watch files named with unicode, emoji, spaces and trailing dots, reporting mismatches");
+    eprintln!("  test-unc         - Watch the tree through a \\\\server\\share UNC path and compare delivery per mode (Windows)");
+    eprintln!("  test-delete-heavy - Delete three quarters of the tree while watching; reports detection and dangling watches");
+    eprintln!("  test-create-heavy - Create thousands of files in new directories; reports detection and directory pickup latency");
+    eprintln!("  test-rename-storm - Rename hundreds of files back to back and score whether rename pairs arrive matched");
+    eprintln!("  test-attach-latency - Time mkdir of a new subtree plus a file write inside it to that file's first event");
+    eprintln!("  test-npm-install - Unpack ~20k small files into node_modules and check filtered modes suppress the flood");
+    eprintln!("  test-build-artifacts - Rewrite artifacts under target/ and check --exclude hides every one of their events");
+    eprintln!("  test-restart     - Delete and recreate the root under a supervised watcher and measure the rebuild gap");
+    eprintln!("  test-budget      - Hold at most --watch-budget manual watches and measure how many modifications get caught");
+    eprintln!("  test-hybrid      - Watch the tree natively plus the --hot-files most recently modified files directly");
+    eprintln!("  test-adaptive    - Narrow or widen a native filter at runtime to keep delivered events under --target-rate");
+    eprintln!("  test-batch       - Compare consumer throughput with one channel send per event vs batched sends");
+    eprintln!("  test-overflow    - Flood the tree with a stalled consumer to overflow the kernel queue");
+    eprintln!("  test-coalesce-sweep - Compare compression and added latency across coalescing windows");
+    eprintln!("  fsevents-sweep   - Sweep the FSEvents stream latency and report events per write vs delivery latency (macOS)");
+    eprintln!("  buffer-sweep     - Sweep the ReadDirectoryChangesW buffer size against write rate, reporting overflows (Windows)");
+    eprintln!("  test-idle        - Measure CPU time and wakeups each watcher costs with no activity");
+    eprintln!("  test-slow-consumer - Write faster than a delayed consumer reads and report backlog and losses");
+    eprintln!("  test-async       - Compare delivery latency to a blocking thread vs a tokio task");
+    eprintln!("  test-stream      - Compare event throughput through into_stream() vs direct async recv");
+    eprintln!("  test-interference - Watch several roots at once and compare per-root latency to each alone");
+    eprintln!("  test-matrix      - Run modify, rename, atomic-save and burst against every mode as a pass/latency grid");
+    eprintln!("  test-write-coverage - Truncate, append, overwrite and rewrite files and show which event kinds each mode reports");
+    eprintln!("  test-metadata-coverage - Chmod, chown and set xattrs on files and show which modes report metadata events");
+    eprintln!("  test-touch       - Change only modification times, forward and back, and show which modes fire");
+    eprintln!("  cold-warm        - Time every mode's setup right after evicting caches and again warm");
+    eprintln!("  test-unwatch     - Time explicit unwatch calls per path (manual) and per root (native) against setup");
+    eprintln!("  test-cycle       - Set up and tear down each watcher --cycles times and check the cost stays flat");
+    eprintln!("  compare-orders   - Compare manual setup time across dfs, bfs, sorted and random registration orders");
+    eprintln!("  stream-setup     - Time manual setup end to end with the tree walked up front vs streamed into registration");
+    eprintln!("  setup-curve      - Register manual watches in batches and chart cumulative setup time vs watch count");
+    eprintln!("  syscalls         - Count inotify_add_watch/open/stat syscalls each mode issues during setup (Linux)");
+    eprintln!("  kqueue-budget    - Count per-file kqueue watches that fit under RLIMIT_NOFILE (macOS/BSD)");
+    eprintln!("  filter-callback  - Drive the filtered callback at 50k events/s and report its per-event time and allocations");
+    eprintln!("  filter-memory    - Measure the filter set's heap footprint at growing sizes against compact alternatives (needs --features heap-counters)");
+    eprintln!();
+    eprintln!("Long-Running Tests:");
+    eprintln!("  soak             - Keep watchers alive under a light workload, logging RSS, fds and latency");
+    eprintln!("  watch            - Watch the directory itself until Ctrl-C, printing periodic stats");
+    eprintln!();
+    eprintln!("Result History:");
+    eprintln!("  history          - List runs recorded for this tree in --history, or compare two with --compare-runs");
+    eprintln!("  trend            - Sparklines of setup time and latency per mode across recorded runs");
+    eprintln!();
+    eprintln!("Options:");
+    eprintln!("  --container-runtime <bin>  - Runtime for test-container (default: docker)");
+    eprintln!("  --container-image <image>  - Image for test-container (default: alpine)");
+    eprintln!("  --allow-partial            - Watch as many files as the inotify limit allows");
+    eprintln!("  --max-files <n>            - Abort (truncate with --truncate-files) when the roots hold more files; 0 for no cap (default: 2000000)");
+    eprintln!("  --truncate-files           - Use the first --max-files files instead of aborting when the roots hold more");
+    eprintln!("  --sort-enumeration         - List each directory in name order, so filtered subsets are reproducible");
+    eprintln!("  --max-setup <time>         - Warn (and ask, on a terminal) when setup is estimated to take longer (default: 60s)");
+    eprintln!("  --yes                      - Go ahead without asking when the setup estimate warns");
+    eprintln!("  --channel-capacity <n>     - Bound the event channel (test-overflow default: 64)");
+    eprintln!("  --rescan-on-overflow       - Re-enumerate after an overflow to time recovery");
+    eprintln!("  --coalesce <ms>            - Merge duplicate events per path within a window");
+    eprintln!("  --top-dirs <n>             - Noisiest directories listed with the event counts, 0 for none (default: 5)");
+    eprintln!("  --top-paths <n>            - Noisiest paths listed with their event kinds, 0 for none (default: 5)");
+    eprintln!("  --dot <file>               - Write the tree as Graphviz with watched/filtered/unwatched files marked, one file per watcher (up to 2000 files)");
+    eprintln!("  --heatmap                  - Print event density over the directory tree after each collection");
+    eprintln!("  --heatmap-svg <file>       - Write that density as an SVG treemap, one file per watcher (e.g. heat-native-recursive.svg)");
+    eprintln!("  --aggregate-dirs <ms>      - Also report per-file events folded into per-directory notifications within a window");
+    eprintln!("  --histogram <text|json>    - Print per-modification latency histograms in test modes");
+    eprintln!("  --histogram-buckets <list> - Histogram bucket bounds, e.g. 100us,1ms,10ms");
+    eprintln!("  --sweep-windows <ms,...>   - Windows for test-coalesce-sweep (default: 0,5,10,25,50,100,250,500)");
+    eprintln!("  --fsevents-latencies <ms,...> - Stream latencies for fsevents-sweep (default: 0,10,50,100,500)");
+    eprintln!("  --fsevents-flags <list|none> - file-events, no-defer, ignore-self, watch-root (default: file-events,no-defer)");
+    eprintln!("  --buffer-sizes <bytes,...> - Buffer sizes for buffer-sweep (default: 1024,4096,16384,65536)");
+    eprintln!("  --filter-sizes <n,...>     - Filter sizes for filter-memory (default: 1000,10000,100000,500000,1000000)");
+    eprintln!("  --soak-mode <modes|all>    - Comma-separated watcher modes for soak (default: native)");
+    eprintln!("  --soak-workload <kind>     - append or rename (default: append)");
+    eprintln!("  --max-rss-growth <KiB/h>   - Fail soak if RSS grows significantly faster than this");
+    eprintln!("  --soak-duration <time>     - How long soak runs, e.g. 30m or 4h (default: 1h)");
+    eprintln!("  --soak-interval <time>     - How often soak logs a sample (default: 60s)");
+    eprintln!("  --soak-write-interval <time> - Pause between soak writes (default: 1s)");
+    eprintln!("  --watch-mode <mode>        - Watcher mode for watch (default: native)");
+    eprintln!("  --stats-interval <time>    - How often watch prints a stats line (default: 10s)");
+    eprintln!("  --snapshot-file <path>     - Append each watch stats line to this file as JSON");
+    eprintln!("  --idle-duration <time>     - Idle period per mode for test-idle (default: 10s)");
+    eprintln!("  --foreign-dir <path>       - Other-filesystem dir for test-cross-device (default: /dev/shm)");
+    eprintln!("  --priority <names>         - Comma-separated file names or *suffixes the filtered modes always watch (e.g. package.json,*.toml)");
+    eprintln!("  --exclude <names>          - Comma-separated directory names whose events are discarded (e.g. target,node_modules)");
+    eprintln!("  --install-layout <layout>  - Package layout for test-npm-install: npm, pnpm (default: npm)");
+    eprintln!("  --nesting-depth <n>        - Directory levels for test-deep-nesting (default: 300)");
+    eprintln!("  --large-file-size <MiB>    - Size of each file in test-large-files (default: 256)");
+    eprintln!("  --log-files <n>            - Files test-small-writes appends to in turn (default: 4)");
+    eprintln!("  --log-rate <n>             - Appends per second in test-small-writes (default: 2000)");
+    eprintln!("  --unc-path <path>          - Share for test-unc to copy the tree into (default: the local admin share)");
+    eprintln!("  --consumer-delay <time>    - Simulated processing time per event (test-slow-consumer default: 5ms)");
+    eprintln!("  --on-full <block|drop>     - What a full bounded channel does to the callback (default: block)");
+    eprintln!("  --channel <std|crossbeam|tokio> - Event channel implementation (default: std)");
+    eprintln!("  --root <dir>               - Also watch <dir>; repeatable, reports per-root metrics");
+    eprintln!("  --poll-interval <time>     - Rescan interval of the poll watcher (default: 200ms)");
+    eprintln!("  --debounce <time>          - Quiet period of the debounced watcher (default: 50ms)");
+    eprintln!("  --cycles <n>               - Setup/teardown rounds per mode for test-cycle (default: 20)");
+    eprintln!("  --watch-retries <n>        - Retries of a watch call that failed transiently before the file is skipped (default: 3)");
+    eprintln!("  --retry-backoff <time>     - Wait before the first retry, doubled for each later one (default: 1ms)");
+    eprintln!("  --inject-faults <spec>     - Randomly drop, error or delay events before the consumer, seeded with --seed (e.g. drop=0.01,delay=0.05)");
+    eprintln!("  --fault-delay <time>       - Longest delay --inject-faults holds an event back (default: 20ms)");
+    eprintln!("  --rate-limit <n>           - Pass at most n events per second from the callback, delaying or merging the rest");
+    eprintln!("  --rate-burst <n>           - Events --rate-limit lets through at once after a quiet spell (default: 64)");
+    eprintln!("  --watch-budget <n>         - Watches test-budget may hold at once (default: 8192)");
+    eprintln!("  --hot-files <n>            - Recently modified files test-hybrid watches directly (default: 64)");
+    eprintln!("  --target-rate <n>          - Delivered events per second test-adaptive keeps under (default: 200)");
+    eprintln!("  --batch-events <n>         - Events a test-batch batch holds before it's flushed early (default: 256)");
+    eprintln!("  --batch-interval <time>    - How often test-batch flushes a partly filled batch (default: 5ms)");
+    eprintln!("  --iterations <n>           - Repeat compare setups and test the difference for significance (default: 1)");
+    eprintln!("  --trim-outliers <k>        - Also report iterated comparisons without runs beyond k MADs of the median");
+    eprintln!("  --history <db>             - Append this run's results to a SQLite history database");
+    eprintln!("  --history-limit <n>        - Runs history lists (default: 20)");
+    eprintln!("  --compare-runs <id>,<id>   - Compare two recorded runs metric by metric in history");
+    eprintln!("  --report <file>            - Write this run's results as JSON, to compare with diff");
+    eprintln!("  --regression-threshold <pct> - Change diff and --compare-runs call a regression or improvement (default: 5)");
+    eprintln!("  --trend-svg <path>         - Also write trend charts as SVG");
+    eprintln!("  --kernel-probe             - Split backend latency at inotify queueing via bpftrace (build with --features ebpf)");
+    eprintln!("  --drop-caches              - Evict via /proc/sys/vm/drop_caches in cold-warm (Linux, root); default is an fadvise pre-pass");
+    eprintln!("  --raise-nofile             - Raise the soft open file limit to the hard limit before kqueue-budget");
+    eprintln!("  --ignore-metadata          - Leave metadata-only events out of the coverage grids");
+    eprintln!("  --expect-timeout <dur>     - How long after an operation its expected event may arrive (default: 1s)");
+    eprintln!("  --modify-count <n>         - Files per root the watch test modifies (default: 5)");
+    eprintln!("  --modify-select <how>      - Which files to modify: first, random, spread, hot (default: first)");
+    eprintln!("  --stabilize <dur>          - Pause between starting a watcher and its first change (default: 100ms)");
+    eprintln!("  --pace <dur>               - Pause between consecutive modifications; 0 writes back to back (default: 10ms)");
+    eprintln!("  --no-poll-fallback         - Fail native setup on a refused root instead of polling it");
+    eprintln!("  --no-delay                 - Same as --stabilize 0 --pace 0");
+    eprintln!("  --settle <dur>             - End watch-test collection once no event arrives for this long (default: 500ms)");
+    eprintln!("  --max-collect <dur>        - Longest a watch test collects events for (default: 10s)");
+    eprintln!("  --verify-content           - Hash watched files around the watch test and report false negatives/positives");
+    eprintln!("  --watch-order <order>      - Manual watch registration order: dfs, bfs, sorted, random (default: dfs)");
+    eprintln!("  --seed <n>                 - Seed for --watch-order random and --modify-select random (default: 0)");
+    eprintln!("  --batch-size <n>           - Watches per timed batch in setup-curve (default: 1000)");
+    eprintln!("  --curve-svg <path>         - Also write the setup curve as SVG");
+    eprintln!("  --profile <path.svg>       - Sample each watcher's setup and write a flamegraph per mode");
     eprintln!();
     eprintln!("Examples:");
     eprintln!("  {} ./test-tree manual", program);
     eprintln!("  {} ./test-tree native", program);
     eprintln!("  {} ./test-tree test-manual", program);
     eprintln!("  {} ./test-tree test-all", program);
+    eprintln!("  {} ./test-tree native --root ./other-tree", program);
 }
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
+    // The modes that read result files rather than watch a directory
+    let file_mode: Option<Result<(), Box<dyn std::error::Error>>> = match args.get(1).map(String::as_str) {
+        Some("schema") => Some(schema::print_schema(args.get(2).map(String::as_str)).map_err(Into::into)),
+        Some("diff") => Some(match (args.get(2), args.get(3)) {
+            (Some(a), Some(b)) => Options::parse(&args[4..])
+                .map_err(Into::into)
+                .and_then(|options| run_diff(Path::new(a), Path::new(b), &options)),
+            _ => Err("diff needs two report files".into()),
+        }),
+        Some(command @ ("merge" | "capabilities")) => {
+            let files: Vec<PathBuf> = args[2..].iter().take_while(|arg| !arg.starts_with("--")).map(PathBuf::from).collect();
+            Some(Options::parse(&args[2 + files.len()..]).map_err(Into::into).and_then(|options| {
+                if command == "merge" {
+                    run_merge(&files, &options).map(|_| ())
+                } else {
+                    run_capabilities(&files, &options).map(|_| ())
+                }
+            }))
+        }
+        _ => None,
+    };
+    if let Some(result) = file_mode {
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     if args.len() < 3 {
         print_usage(&args[0]);
         std::process::exit(1);
@@ -348,6 +1254,16 @@ fn main() {
     let dir_path = Path::new(&args[1]);
     let mode_str = &args[2];
 
+    let options = match Options::parse(&args[3..]) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            eprintln!();
+            print_usage(&args[0]);
+            std::process::exit(1);
+        }
+    };
+
     if !dir_path.exists() {
         eprintln!("Error: Directory '{}' does not exist", dir_path.display());
         eprintln!();
@@ -364,57 +1280,83 @@ fn main() {
         std::process::exit(1);
     }
 
+    let mut roots = vec![dir_path.to_path_buf()];
+    for root in &options.roots {
+        if !root.is_dir() {
+            eprintln!("Error: --root '{}' is not a directory", root.display());
+            std::process::exit(1);
+        }
+        roots.push(root.clone());
+    }
+    if roots.len() > 1 && SINGLE_ROOT_MODES.contains(&mode_str.as_str()) {
+        println!("Note: {} only uses the first directory; extra --root values are ignored", mode_str);
+    }
+
     // Run benchmark based on mode
+    let mut run = RunRecord::new(mode_str, &args[3..]);
     let result = match mode_str.as_str() {
         "compare" => {
             // Run both modes and compare
             println!("Comparing manual vs native recursive watching");
             println!();
-            println!("Test directory: {}", dir_path.display());
+            for root in &roots {
+                println!("Test directory: {}", root.display());
+            }
 
-            let files = collect_files_recursive(dir_path);
+            let listings = match enumerate_roots(&roots, &options) {
+                Ok(listings) => listings,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                },
+            };
+            report_walk_errors(&roots, &listings, "");
+            let files: Vec<PathBuf> = listings.into_iter().flat_map(|listing| listing.files).collect();
             println!("Total files in directory: {}", files.len());
 
             println!("\n{}", "=".repeat(60));
 
             // Store results for comparison
-            let mut manual_time = Duration::default();
-            let mut native_time = Duration::default();
-
-            // Run manual mode
-            match ManualRecursiveWatcher::new(dir_path) {
-                Ok(watcher) => {
-                    manual_time = watcher.setup_time();
-                    println!("\nManual Recursive Watcher:");
-                    println!("  Setup time: {:?}", manual_time);
-                    println!("  Files watched: {}", watcher.files_watched());
-                },
-                Err(e) => eprintln!("Manual watcher failed: {}", e),
-            }
+            let mut manual_times = Vec::new();
+            let mut native_times = Vec::new();
 
-            println!("\n{}", "=".repeat(60));
+            for iteration in 1..=options.iterations {
+                if options.iterations > 1 {
+                    println!("\n--- Iteration {}/{} ---", iteration, options.iterations);
+                }
 
-            // Run native mode
-            match NativeRecursiveWatcher::new(dir_path) {
-                Ok(watcher) => {
-                    native_time = watcher.setup_time();
-                    println!("\nNative Recursive Watcher:");
-                    println!("  Setup time: {:?}", native_time);
-                },
-                Err(e) => eprintln!("Native watcher failed: {}", e),
+                // Run manual mode
+                match manual_watcher(ordered_watches(files.clone(), &options), &options) {
+                    Ok(watcher) => {
+                        manual_times.push(watcher.setup_time());
+                        println!("\nManual Recursive Watcher:");
+                        println!("  Setup time: {:?}", watcher.setup_time());
+                        println!("  Files watched: {}", watcher.files_watched());
+                        report_coverage(&watcher, "  ");
+                    },
+                    Err(e) => eprintln!("Manual watcher failed: {}", e),
+                }
+
+                println!("\n{}", "=".repeat(60));
+
+                // Run native mode
+                match NativeRecursiveWatcher::new_with_roots_and_config(&roots, &options.watch_config()) {
+                    Ok(watcher) => {
+                        native_times.push(watcher.setup_time());
+                        println!("\nNative Recursive Watcher:");
+                        println!("  Setup time: {:?}", watcher.setup_time());
+                    },
+                    Err(e) => eprintln!("Native watcher failed: {}", e),
+                }
             }
 
             println!("\n{}", "=".repeat(60));
             println!("\n📊 Comparison Results:");
-            println!("  Manual setup time: {:?}", manual_time);
-            println!("  Native setup time: {:?}", native_time);
-
-            if native_time < manual_time {
-                let speedup = manual_time.as_nanos() as f64 / native_time.as_nanos() as f64;
-                println!("  Native is {:.2}x faster", speedup);
-            } else {
-                let speedup = native_time.as_nanos() as f64 / manual_time.as_nanos() as f64;
-                println!("  Manual is {:.2}x faster", speedup);
+            print_setup_comparison(("Manual", &manual_times), ("Native", &native_times), &options);
+            for (mode, times) in [(WatcherMode::Manual, &manual_times), (WatcherMode::Native, &native_times)] {
+                for time in times {
+                    run.add_duration(mode.display_name(), "setup_us", *time);
+                }
             }
 
             Ok(())
@@ -423,104 +1365,350 @@ fn main() {
             // Compare filtered modes
             println!("Comparing filtered manual vs filtered native watching");
             println!();
-            println!("Test directory: {}", dir_path.display());
+            for root in &roots {
+                println!("Test directory: {}", root.display());
+            }
 
-            let all_files = collect_files_recursive(dir_path);
-            let filtered_files = get_filtered_files(&all_files, 10);
-            println!("Total files: {}, Filtered to: {} files", all_files.len(), filtered_files.len());
+            let listings = match enumerate_roots(&roots, &options) {
+                Ok(listings) => listings,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                },
+            };
+            report_walk_errors(&roots, &listings, "");
+            let files_per_root: Vec<Vec<PathBuf>> = listings.into_iter().map(|listing| listing.files).collect();
+            let total_files: usize = files_per_root.iter().map(Vec::len).sum();
+            let filtered_files: Vec<PathBuf> = files_per_root
+                .iter()
+                .flat_map(|files| tiered_files(files, &options.priority))
+                .collect();
+            println!("Total files: {}, Filtered to: {} files", total_files, filtered_files.len());
+            // Built once and shared by every native setup; only the watches are timed
+            let filter = Arc::new(FilterSet::from_files(&roots, &filtered_files));
 
             println!("\n{}", "=".repeat(60));
 
             // Store results for comparison
-            let mut manual_time = Duration::default();
-            let mut native_time = Duration::default();
-
-            // Run manual filtered mode
-            match ManualRecursiveWatcher::new_with_files(filtered_files.clone()) {
-                Ok(watcher) => {
-                    manual_time = watcher.setup_time();
-                    println!("\nManual Filtered Watcher:");
-                    println!("  Setup time: {:?}", manual_time);
-                    println!("  Files watched: {}", watcher.files_watched());
-                },
-                Err(e) => eprintln!("Manual filtered watcher failed: {}", e),
-            }
+            let mut manual_times = Vec::new();
+            let mut native_times = Vec::new();
 
-            println!("\n{}", "=".repeat(60));
+            for iteration in 1..=options.iterations {
+                if options.iterations > 1 {
+                    println!("\n--- Iteration {}/{} ---", iteration, options.iterations);
+                }
 
-            // Run native filtered mode
-            match NativeRecursiveWatcher::new_with_filter(dir_path, filtered_files.clone()) {
-                Ok(watcher) => {
-                    native_time = watcher.setup_time();
-                    println!("\nNative Filtered Watcher:");
-                    println!("  Setup time: {:?}", native_time);
-                    println!("  Files filtered: {}", watcher.files_filtered());
-                },
-                Err(e) => eprintln!("Native filtered watcher failed: {}", e),
+                // Run manual filtered mode
+                match manual_watcher(ordered_watches(filtered_files.clone(), &options), &options) {
+                    Ok(watcher) => {
+                        manual_times.push(watcher.setup_time());
+                        println!("\nManual Filtered Watcher:");
+                        println!("  Setup time: {:?}", watcher.setup_time());
+                        println!("  Files watched: {}", watcher.files_watched());
+                        report_coverage(&watcher, "  ");
+                    },
+                    Err(e) => eprintln!("Manual filtered watcher failed: {}", e),
+                }
+
+                println!("\n{}", "=".repeat(60));
+
+                // Run native filtered mode
+                match NativeRecursiveWatcher::new_with_filter_set(&roots, Arc::clone(&filter), &options.watch_config()) {
+                    Ok(watcher) => {
+                        native_times.push(watcher.setup_time());
+                        println!("\nNative Filtered Watcher:");
+                        println!("  Setup time: {:?}", watcher.setup_time());
+                        println!("  Files filtered: {}", watcher.files_filtered());
+                    },
+                    Err(e) => eprintln!("Native filtered watcher failed: {}", e),
+                }
             }
 
             println!("\n{}", "=".repeat(60));
             println!("\n📊 Filtered Comparison Results:");
-            println!("  Manual filtered setup time: {:?}", manual_time);
-            println!("  Native filtered setup time: {:?}", native_time);
-
-            if native_time < manual_time {
-                let speedup = manual_time.as_nanos() as f64 / native_time.as_nanos() as f64;
-                println!("  Native filtered is {:.2}x faster", speedup);
-            } else {
-                let speedup = native_time.as_nanos() as f64 / manual_time.as_nanos() as f64;
-                println!("  Manual filtered is {:.2}x faster", speedup);
+            print_setup_comparison(
+                ("Manual filtered", &manual_times),
+                ("Native filtered", &native_times),
+                &options,
+            );
+            for (mode, times) in [
+                (WatcherMode::ManualFiltered, &manual_times),
+                (WatcherMode::NativeFiltered, &native_times),
+            ] {
+                for time in times {
+                    run.add_duration(mode.display_name(), "setup_us", *time);
+                }
             }
 
             Ok(())
         },
         "test-manual" => {
             println!("Running watch test for manual mode");
-            run_watch_test(dir_path, WatcherMode::Manual)
+            run_watch_test(&roots, WatcherMode::Manual, &options).and_then(|result| {
+                result.record(&mut run);
+                check_released(&[result])
+            })
         },
         "test-native" => {
             println!("Running watch test for native mode");
-            run_watch_test(dir_path, WatcherMode::Native)
+            run_watch_test(&roots, WatcherMode::Native, &options).and_then(|result| {
+                result.record(&mut run);
+                check_released(&[result])
+            })
         },
         "test-filtered" => {
             println!("Running watch tests for filtered modes");
             println!("\n{}", "=".repeat(60));
+            let mut results = Vec::new();
 
-            if let Err(e) = run_watch_test(dir_path, WatcherMode::ManualFiltered) {
-                eprintln!("Manual filtered test failed: {}", e);
+            match run_watch_test(&roots, WatcherMode::ManualFiltered, &options) {
+                Ok(result) => {
+                    result.record(&mut run);
+                    results.push(result)
+                },
+                Err(e) => eprintln!("Manual filtered test failed: {}", e),
             }
 
             println!("\n{}", "=".repeat(60));
 
-            if let Err(e) = run_watch_test(dir_path, WatcherMode::NativeFiltered) {
-                eprintln!("Native filtered test failed: {}", e);
+            match run_watch_test(&roots, WatcherMode::NativeFiltered, &options) {
+                Ok(result) => {
+                    result.record(&mut run);
+                    results.push(result)
+                },
+                Err(e) => eprintln!("Native filtered test failed: {}", e),
             }
 
-            Ok(())
+            print_watch_test_summary(&results);
+            check_released(&results)
         },
         "test-all" => {
             println!("Running all watch tests");
 
-            let modes = [
-                WatcherMode::Manual,
-                WatcherMode::Native,
-                WatcherMode::ManualFiltered,
-                WatcherMode::NativeFiltered,
-            ];
+            let mut results = Vec::new();
 
-            for mode in &modes {
+            for mode in WatcherMode::ALL {
                 println!("\n{}", "=".repeat(60));
-                if let Err(e) = run_watch_test(dir_path, *mode) {
-                    eprintln!("{} test failed: {}", mode.display_name(), e);
+                match run_watch_test(&roots, mode, &options) {
+                    Ok(result) => {
+                        result.record(&mut run);
+                        results.push(result)
+                    },
+                    Err(e) => eprintln!("{} test failed: {}", mode.display_name(), e),
                 }
             }
 
-            Ok(())
+            print_watch_test_summary(&results);
+
+            // The write-coverage grid fills in the scenario column of the scorecard
+            println!("\nRunning the write-coverage grid for the scorecard...");
+            let mut scorecards = Vec::new();
+            for result in &results {
+                println!("\n--- {} ---", result.mode.display_name());
+                let cells = coverage_row(dir_path, result.mode, &Operation::WRITES, &options)
+                    .map_err(|e| eprintln!("   Coverage grid failed: {}", e))
+                    .ok();
+                let scorecard = Scorecard::new(result.mode, &result.oracle, &result.latency, cells.as_deref());
+                run.add(result.mode.display_name(), "score", scorecard.overall());
+                scorecards.push(scorecard);
+            }
+            print_scorecards(&scorecards);
+            check_released(&results)
+        },
+        "compare-all" => {
+            println!("Comparing every watcher mode against the same tree");
+
+            let mut results = Vec::new();
+
+            for mode in WatcherMode::EVERY {
+                println!("\n{}", "=".repeat(60));
+                match run_watch_test(&roots, mode, &options) {
+                    Ok(result) => {
+                        result.record(&mut run);
+                        results.push(result)
+                    },
+                    Err(e) => eprintln!("{} test failed: {}", mode.display_name(), e),
+                }
+            }
+
+            print_compare_all_table(&results);
+            check_released(&results)
         },
+        "test-bind" => run_mount_test(dir_path, MountKind::Bind, &options),
+        "test-overlay" => run_mount_test(dir_path, MountKind::Overlay, &options),
+        "test-container" => run_mount_test(dir_path, MountKind::Container, &options),
+        "test-cross-device" => run_cross_device_test(dir_path, &options),
+        "test-long-paths" => run_long_path_test(dir_path, &options),
+        "test-deep-nesting" => run_deep_nesting_test(dir_path, &options),
+        "test-large-files" => run_large_file_test(dir_path, &options),
+        "test-small-writes" => run_small_writes_test(dir_path, &options),
+        "test-unc" => run_unc_test(dir_path, &options),
+        "test-delete-heavy" => run_delete_heavy_test(dir_path, &options).map(|results| record_churn(&mut run, &results)),
+        "test-create-heavy" => run_create_heavy_test(dir_path, &options).map(|results| record_churn(&mut run, &results)),
+        "test-rename-storm" => run_rename_storm_test(dir_path, &options).map(|results| record_churn(&mut run, &results)),
+        "test-attach-latency" => run_attach_latency_test(dir_path, &options).map(|results| record_churn(&mut run, &results)),
+        "test-npm-install" => run_npm_install_test(dir_path, &options).map(|results| record_churn(&mut run, &results)),
+        "test-build-artifacts" => run_build_artifacts_test(dir_path, &options).map(|results| record_churn(&mut run, &results)),
+        "test-restart" => run_restart_test(dir_path, &options).map(|results| {
+            for result in results {
+                let mode = result.mode.display_name();
+                run.add(mode, "restarts", result.restarts.len() as f64);
+                if let Some(gap) = result.restarts.last().and_then(|restart| restart.gap()) {
+                    run.add_duration(mode, "restart_gap_us", gap);
+                }
+                run.add(mode, "missed_in_gap", result.in_gap.1 as f64);
+            }
+        }),
+        "test-budget" => run_budget_test(dir_path, &options).map(|results| {
+            for result in results {
+                let policy = result.policy.display_name();
+                run.add(policy, "budget_hit_rate", result.hit_rate());
+                run.add(policy, "budget_evictions", result.evictions as f64);
+            }
+        }),
+        "test-hybrid" => run_hybrid_test(dir_path, &options).map(|results| {
+            for result in results {
+                if let Some(mean) = result.latency.mean_events_per_write() {
+                    run.add(result.name, "events_per_modification", mean);
+                }
+                if let Some(hot) = result.hot.total() {
+                    run.add_duration(result.name, "hot_latency_p50_us", hot.p50);
+                }
+                if let Some(cold) = result.cold.total() {
+                    run.add_duration(result.name, "cold_latency_p50_us", cold.p50);
+                }
+            }
+        }),
+        "test-adaptive" => run_adaptive_test(dir_path, &options).map(|result| {
+            run.add(ADAPTIVE_NAME, "adaptive_coverage", result.coverage());
+            run.add(ADAPTIVE_NAME, "adaptive_rate", result.mean_rate());
+            run.add(ADAPTIVE_NAME, "adaptive_over_target", result.over_target() as f64);
+        }),
+        "test-batch" => run_batch_test(dir_path, &options).map(|results| {
+            for result in results {
+                let metric = format!("{}_events_per_sec", result.workload);
+                run.add(result.delivery.history_name(), &metric, result.events_per_sec());
+                if result.workload == "live" {
+                    run.add(result.delivery.history_name(), "live_errors", result.errors as f64);
+                }
+            }
+        }),
+        "test-unusual-names" => run_unusual_names_test(dir_path, &options),
+        "test-overflow" => run_overflow_test(dir_path, &options),
+        "test-coalesce-sweep" => run_coalesce_sweep(dir_path, &options),
+        "fsevents-sweep" => run_fsevents_sweep(dir_path, &options),
+        "buffer-sweep" => run_buffer_sweep(dir_path, &options),
+        "soak" => run_soak_test(dir_path, &options),
+        "watch" => run_watch(dir_path, &options),
+        "test-idle" => run_idle_test(dir_path, &options),
+        "test-interference" => run_interference_test(&roots, &options),
+        "test-slow-consumer" => run_slow_consumer_test(dir_path, &options),
+        "test-async" => run_async_test(dir_path, &options),
+        "test-stream" => run_stream_test(dir_path, &options),
+        "test-matrix" => run_matrix(dir_path, &options),
+        "test-write-coverage" => run_write_coverage(dir_path, &options),
+        "test-metadata-coverage" => run_metadata_coverage(dir_path, &options),
+        "test-touch" => run_touch_coverage(dir_path, &options),
+        "filter-callback" => run_callback_bench(dir_path, &options).map(|results| {
+            for cost in results {
+                let mode = cost.variant.display_name();
+                run.add(mode, "callback_ns", cost.time.mean.as_nanos() as f64);
+                run.add(mode, "callback_p99_ns", cost.time.p99.as_nanos() as f64);
+                if heap::COUNTING {
+                    run.add(mode, "callback_allocations_per_event", cost.allocations_per_event);
+                }
+            }
+        }),
+        "cold-warm" => run_cold_warm(&roots, &options).map(|results| {
+            for result in results {
+                let mode = result.mode.display_name();
+                run.add_duration(mode, result.eviction.metric(), result.cold);
+                run.add_duration(mode, "warm_setup_us", result.warm);
+            }
+        }),
+        "compare-orders" => run_order_comparison(&roots, &options).map(|results| {
+            for (order, times) in results {
+                for time in times {
+                    run.add_duration(&format!("Manual Recursive ({})", order.display_name()), "setup_us", time);
+                }
+            }
+        }),
+        "stream-setup" => run_stream_setup(&roots, &options).map(|results| {
+            for result in results {
+                run.add_duration(result.enumeration.display_name(), "end_to_end_setup_us", result.total);
+            }
+        }),
+        "filter-memory" => run_filter_memory(&roots, &options).map(|results| {
+            for footprint in results.footprints {
+                let mode = format!("{} ({})", footprint.container.display_name(), footprint.paths);
+                run.add(&mode, "filter_heap_bytes", footprint.heap_bytes as f64);
+                run.add(&mode, "filter_bytes_per_path", footprint.bytes_per_path());
+                run.add_duration(&mode, "filter_build_us", footprint.build);
+                run.add(&mode, "filter_lookup_ns", footprint.lookup.as_nanos() as f64);
+            }
+            for shared in results.sharing {
+                let mode = format!("{} ({})", shared.layout, shared.paths);
+                run.add(&mode, "filter_held_bytes", shared.heap_bytes as f64);
+                run.add_duration(&mode, "filter_clone_us", shared.clone);
+            }
+        }),
+        "test-unwatch" => run_unwatch_test(&roots, &options).map(|results| {
+            for result in results {
+                let mode = result.mode.display_name();
+                run.add_duration(mode, "unwatch_us", result.total());
+                run.add(mode, "unwatch_failures", result.failures as f64);
+                if let Some(summary) = DurationSummary::from_durations(result.unwatch_times) {
+                    run.add_duration(mode, "unwatch_p99_us", summary.p99);
+                }
+            }
+        }),
+        "test-cycle" => run_cycle_test(&roots, &options).map(|results| {
+            for result in results {
+                let mode = result.mode.display_name();
+                for cycle in &result.cycles {
+                    run.add_duration(mode, "cycle_setup_us", cycle.setup);
+                }
+                if let Some(trend) = result.trend() {
+                    run.add(mode, "cycle_slope_us", trend.slope);
+                }
+                if let Some(ratio) = result.warmup_ratio() {
+                    run.add(mode, "cycle_warmup_ratio", ratio);
+                }
+            }
+        }),
+        "setup-curve" => run_setup_curve(&roots, &options).map(|scaling| {
+            if let Some(scaling) = scaling {
+                run.add(WatcherMode::Manual.display_name(), "curve_exponent", scaling.exponent);
+            }
+        }),
+        "syscalls" => run_syscall_counts(&roots, &options).map(|results| {
+            for (mode, counts) in results {
+                let mode = mode.display_name();
+                run.add(mode, "syscalls_add_watch", counts.add_watch as f64);
+                run.add(mode, "syscalls_open", counts.open as f64);
+                run.add(mode, "syscalls_stat", counts.stat as f64);
+                run.add(mode, "syscalls_total", counts.total as f64);
+            }
+        }),
+        "kqueue-budget" => run_kqueue_budget(&roots, &options).map(|budget| {
+            let mode = WatcherMode::Manual.display_name();
+            run.add(mode, "kqueue_watches", budget.watched as f64);
+            run.add(mode, "kqueue_coverage_pct", budget.coverage_percent());
+        }),
+        "history" => run_history(dir_path, &options),
+        "trend" => run_trend(dir_path, &options),
         mode_str => {
             // Try to parse as a specific mode
             match WatcherMode::from_str(mode_str) {
-                Some(mode) => benchmark_watcher(dir_path, mode),
+                Some(mode) => benchmark_watcher(&roots, mode, &options).map(|(setup_time, allocs)| {
+                    let mode = mode.display_name();
+                    run.add_duration(mode, "setup_us", setup_time);
+                    if heap::COUNTING {
+                        run.add(mode, "setup_allocations", allocs.allocations as f64);
+                        run.add(mode, "setup_alloc_bytes", allocs.bytes as f64);
+                        run.add(mode, "setup_retained_bytes", allocs.retained_bytes as f64);
+                    }
+                }),
                 None => {
                     eprintln!("Unknown mode: {}", mode_str);
                     print_usage(&args[0]);
@@ -530,6 +1718,27 @@ fn main() {
         }
     };
 
+    let recording = options.history.is_some() || options.report.is_some();
+    if recording && !matches!(mode_str.as_str(), "history" | "trend") {
+        let recorded_roots = if SINGLE_ROOT_MODES.contains(&mode_str.as_str()) {
+            &roots[..1]
+        } else {
+            &roots[..]
+        };
+        let status = match &result {
+            Ok(()) => "ok".to_string(),
+            Err(e) => format!("error: {}", e),
+        };
+        let tree = TreeFingerprint::of(recorded_roots, &options);
+        let environment = Environment::capture();
+        if let Some(path) = &options.history {
+            record_run(path, &run, &tree, &environment, &status);
+        }
+        if let Some(path) = &options.report {
+            write_report(path, &RunReport::new(&run, &tree, &environment, &status));
+        }
+    }
+
     if let Err(e) = result {
         eprintln!("Error: {}", e);
         std::process::exit(1);
@@ -560,25 +1769,15 @@ mod tests {
         }
 
         // Test both watcher modes
-        assert!(benchmark_watcher(test_dir, WatcherMode::Manual).is_ok());
-        assert!(benchmark_watcher(test_dir, WatcherMode::Native).is_ok());
-        assert!(benchmark_watcher(test_dir, WatcherMode::ManualFiltered).is_ok());
-        assert!(benchmark_watcher(test_dir, WatcherMode::NativeFiltered).is_ok());
+        let roots = [test_dir.to_path_buf()];
+        assert!(benchmark_watcher(&roots, WatcherMode::Manual, &Options::default()).is_ok());
+        assert!(benchmark_watcher(&roots, WatcherMode::Native, &Options::default()).is_ok());
+        assert!(benchmark_watcher(&roots, WatcherMode::ManualFiltered, &Options::default()).is_ok());
+        assert!(benchmark_watcher(&roots, WatcherMode::NativeFiltered, &Options::default()).is_ok());
+        assert!(benchmark_watcher(&roots, WatcherMode::Poll, &Options::default()).is_ok());
+        assert!(benchmark_watcher(&roots, WatcherMode::Debounced, &Options::default()).is_ok());
 
         // Clean up
         fs::remove_dir_all(test_dir).unwrap();
     }
-
-    #[test]
-    fn test_get_filtered_files() {
-        let files: Vec<PathBuf> = (0..100)
-            .map(|i| PathBuf::from(format!("file{}.txt", i)))
-            .collect();
-
-        let filtered = get_filtered_files(&files, 10);
-        assert_eq!(filtered.len(), 10); // Should get every 10th file
-
-        let filtered = get_filtered_files(&files, 5);
-        assert_eq!(filtered.len(), 20); // Should get every 5th file
-    }
-}
\ No newline at end of file
+}
@@ -0,0 +1,258 @@
+use crate::harness::{append_to_files, prepare_scratch_dir, spawn_event_collector, start_watcher, watched_files};
+use crate::latency::{match_writes, WriteRecord};
+use crate::options::Options;
+use crate::recursive_file_watcher::WatcherMode;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Files each workload touches, apart from `Burst`
+const MATRIX_FILES: usize = 5;
+
+/// Files `Burst` appends to back to back
+const MATRIX_BURST_FILES: usize = 200;
+
+/// How long each cell collects events once its workload has run
+const MATRIX_COLLECT_DURATION: Duration = Duration::from_secs(2);
+
+/// File operation pattern one column of the matrix applies
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Workload {
+    /// Append to a file in place
+    Modify,
+    /// Rename a file to a new name in the same directory
+    Rename,
+    /// Write a temporary sibling and rename it over the file, as editors save
+    AtomicSave,
+    /// Append to many files with no pause between writes
+    Burst,
+}
+
+impl Workload {
+    pub const ALL: [Workload; 4] = [Self::Modify, Self::Rename, Self::AtomicSave, Self::Burst];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Modify => "modify",
+            Self::Rename => "rename",
+            Self::AtomicSave => "atomic-save",
+            Self::Burst => "burst",
+        }
+    }
+
+    /// How many of a mode's watched files this workload touches
+    fn file_count(&self) -> usize {
+        match self {
+            Self::Burst => MATRIX_BURST_FILES,
+            _ => MATRIX_FILES,
+        }
+    }
+
+    /// Apply the workload to `targets`, recording the path each operation should be reported on
+    ///
+    /// The paced workloads pause `pace` between operations; `Burst` never does.
+    fn apply(&self, targets: &[PathBuf], pace: Duration) -> Vec<WriteRecord> {
+        match self {
+            Self::Modify => append_to_files(targets, pace),
+            Self::Burst => append_to_files(targets, Duration::ZERO),
+            Self::Rename => paced(targets, pace, |target| {
+                fs::rename(target, target.with_extension("matrix-renamed"))
+            }),
+            Self::AtomicSave => paced(targets, pace, |target| {
+                let name = target.file_name().unwrap_or_default().to_string_lossy();
+                let tmp = target.with_file_name(format!(".{}.matrix-tmp", name));
+                let mut content = fs::read(target)?;
+                content.extend_from_slice(b"\n// Saved by matrix");
+                fs::write(&tmp, content)?;
+                fs::rename(&tmp, target)
+            }),
+        }
+    }
+}
+
+/// Run `op` on each target in turn, `pace` apart, recording the successful ones
+fn paced(targets: &[PathBuf], pace: Duration, op: impl Fn(&Path) -> std::io::Result<()>) -> Vec<WriteRecord> {
+    let mut writes = Vec::with_capacity(targets.len());
+    for target in targets {
+        let started_at = Instant::now();
+        match op(target) {
+            Ok(()) => writes.push(WriteRecord {
+                path: target.clone(),
+                started_at,
+                written_at: Instant::now(),
+            }),
+            Err(e) => eprintln!("   Failed on {}: {}", target.display(), e),
+        }
+        if !pace.is_zero() {
+            std::thread::sleep(pace);
+        }
+    }
+    writes
+}
+
+/// Outcome of one mode running one workload
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MatrixCell {
+    pub operations: usize,
+    /// Operations no event was seen for
+    pub missed: usize,
+    pub latency_p50: Option<Duration>,
+    pub overflowed: bool,
+    /// The watcher could not be set up at all
+    pub failed: bool,
+}
+
+impl MatrixCell {
+    /// Every operation was reported, without overflow
+    pub fn passed(&self) -> bool {
+        !self.failed && self.operations > 0 && self.missed == 0 && !self.overflowed
+    }
+
+    /// Grid text, e.g. `✓ 1.2ms` or `✗ 3/5`
+    fn describe(&self) -> String {
+        if self.failed {
+            "✗ error".to_string()
+        } else if self.passed() {
+            format!("✓ {}", self.latency_p50.map_or("-".to_string(), |d| format!("{:.1?}", d)))
+        } else if self.overflowed {
+            "✗ overflow".to_string()
+        } else {
+            format!("✗ {}/{}", self.missed, self.operations)
+        }
+    }
+}
+
+/// Run `workload` against a fresh copy of `dir` watched by `mode`
+fn run_cell(
+    dir: &Path,
+    mode: WatcherMode,
+    workload: Workload,
+    options: &Options,
+) -> Result<MatrixCell, Box<dyn std::error::Error>> {
+    let tmp_dir = std::path::absolute(prepare_scratch_dir(dir, "matrix", options)?)?;
+    let result = (|| -> Result<MatrixCell, Box<dyn std::error::Error>> {
+        let (_watcher, rx) = start_watcher(mode, &tmp_dir, options)?;
+        let targets: Vec<PathBuf> = watched_files(mode, &tmp_dir, options)?
+            .into_iter()
+            .take(workload.file_count())
+            .collect();
+
+        let collector = spawn_event_collector(rx, MATRIX_COLLECT_DURATION, options.consumer_delay);
+        // Give watcher time to stabilize
+        std::thread::sleep(options.stabilize);
+        let writes = workload.apply(&targets, options.pace);
+        let collected = collector.recv()?;
+
+        let latency = match_writes(&writes, &collected);
+        Ok(MatrixCell {
+            operations: writes.len(),
+            missed: latency.unmatched,
+            latency_p50: latency.total().map(|s| s.p50),
+            overflowed: collected.overflowed(),
+            failed: false,
+        })
+    })();
+    fs::remove_dir_all(&tmp_dir)?;
+    result
+}
+
+fn print_grid(rows: &[(WatcherMode, Vec<MatrixCell>)]) {
+    print!("  {:<20}", "Mode");
+    for workload in Workload::ALL {
+        print!(" {:>13}", workload.name());
+    }
+    println!(" {:>7}", "Passed");
+    for (mode, cells) in rows {
+        print!("  {:<20}", mode.display_name());
+        for cell in cells {
+            print!(" {:>13}", cell.describe());
+        }
+        println!(
+            " {:>7}",
+            format!("{}/{}", cells.iter().filter(|c| c.passed()).count(), cells.len())
+        );
+    }
+}
+
+/// Cross every watcher mode with every workload and print a pass/latency grid
+///
+/// Each cell gets its own scratch copy and watcher, so a rename or atomic save
+/// in one cell can't leave another mode watching a stale tree. A cell passes
+/// when every operation produced an event for its path; the latency shown is
+/// the median from the end of the operation to the consumer receiving it.
+pub fn run_matrix(dir: &Path, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    println!("\n=== Mode × Workload Matrix ===");
+    println!("Source directory: {}", dir.display());
+    println!(
+        "{} modes × {} workloads, collecting {:?} per cell",
+        WatcherMode::EVERY.len(),
+        Workload::ALL.len(),
+        MATRIX_COLLECT_DURATION
+    );
+
+    let mut rows = Vec::new();
+    for mode in WatcherMode::EVERY {
+        let mut cells = Vec::new();
+        for workload in Workload::ALL {
+            println!("\n--- {} / {} ---", mode.display_name(), workload.name());
+            let cell = run_cell(dir, mode, workload, options).unwrap_or_else(|e| {
+                eprintln!("   {} failed: {}", mode.display_name(), e);
+                MatrixCell {
+                    failed: true,
+                    ..MatrixCell::default()
+                }
+            });
+            cells.push(cell);
+        }
+        rows.push((mode, cells));
+    }
+
+    println!("\n{}", "=".repeat(60));
+    println!("\n📊 Matrix ({}):", std::env::consts::OS);
+    print_grid(&rows);
+
+    println!("\n=== Mode × Workload Matrix Complete ===\n");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cell_verdicts() {
+        let passed = MatrixCell {
+            operations: 5,
+            latency_p50: Some(Duration::from_micros(1500)),
+            ..MatrixCell::default()
+        };
+        assert!(passed.passed());
+        assert_eq!(passed.describe(), "✓ 1.5ms");
+
+        let missed = MatrixCell { missed: 2, ..passed };
+        assert!(!missed.passed());
+        assert_eq!(missed.describe(), "✗ 2/5");
+        assert!(!MatrixCell { overflowed: true, ..passed }.passed());
+        assert!(!MatrixCell::default().passed());
+    }
+
+    #[test]
+    fn test_atomic_save_replaces_file() {
+        let dir = std::path::absolute("test_matrix_dir").unwrap();
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("a.js");
+        fs::write(&file, "a").unwrap();
+
+        let writes = Workload::AtomicSave.apply(std::slice::from_ref(&file), Duration::ZERO);
+        assert_eq!(writes.len(), 1);
+        assert_eq!(writes[0].path, file);
+        assert!(fs::read_to_string(&file).unwrap().ends_with("Saved by matrix"));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        Workload::Rename.apply(std::slice::from_ref(&file), Duration::ZERO);
+        assert!(!file.exists() && file.with_extension("matrix-renamed").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
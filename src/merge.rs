@@ -0,0 +1,289 @@
+use crate::format::{self, environment_allocator, from_v1, Migration, Versioned};
+use crate::history::{Environment, Measurement};
+use crate::options::Options;
+use crate::report::{better_direction, mean, samples_by_metric, RunReport};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One run inside a merged report; its environment is the group it sits in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MergedRun {
+    pub id: String,
+    pub started_at_ms: i64,
+    pub command: String,
+    pub args: String,
+    pub status: String,
+    pub tree_fingerprint: String,
+    pub tree_files: usize,
+    pub measurements: Vec<Measurement>,
+}
+
+/// Every merged run from one machine and platform
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnvironmentRuns {
+    pub environment: Environment,
+    pub runs: Vec<MergedRun>,
+}
+
+impl EnvironmentRuns {
+    /// Mean of each (mode, metric) across this environment's runs
+    pub fn means(&self) -> BTreeMap<(String, String), f64> {
+        let measurements: Vec<Measurement> = self.runs.iter().flat_map(|run| run.measurements.clone()).collect();
+        samples_by_metric(&measurements)
+            .into_iter()
+            .map(|(key, values)| (key, mean(&values)))
+            .collect()
+    }
+}
+
+/// `--report` files from several machines combined, grouped by their captured environment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MergedReport {
+    pub format_version: u32,
+    pub environments: Vec<EnvironmentRuns>,
+}
+
+impl Versioned for MergedReport {
+    const FORMAT_VERSION: u32 = 3;
+    const MIGRATIONS: &'static [Migration] = &[from_v1, environment_allocator];
+}
+
+impl MergedReport {
+    /// Group `reports` by environment in order of first appearance; a run seen twice is kept once
+    pub fn merge(reports: impl IntoIterator<Item = RunReport>) -> Self {
+        let mut environments: Vec<EnvironmentRuns> = Vec::new();
+        let mut seen = HashSet::new();
+        for report in reports {
+            if !seen.insert(report.id.clone()) {
+                continue;
+            }
+            let run = MergedRun {
+                id: report.id,
+                started_at_ms: report.started_at_ms,
+                command: report.command,
+                args: report.args,
+                status: report.status,
+                tree_fingerprint: report.tree_fingerprint,
+                tree_files: report.tree_files,
+                measurements: report.measurements,
+            };
+            match environments.iter_mut().find(|group| group.environment == report.environment) {
+                Some(group) => group.runs.push(run),
+                None => environments.push(EnvironmentRuns {
+                    environment: report.environment,
+                    runs: vec![run],
+                }),
+            }
+        }
+        Self {
+            format_version: Self::FORMAT_VERSION,
+            environments,
+        }
+    }
+
+    /// The merged runs as the individual reports they came from, so merges can be merged again
+    pub fn into_reports(self) -> impl Iterator<Item = RunReport> {
+        self.environments.into_iter().flat_map(|group| {
+            let environment = group.environment;
+            group.runs.into_iter().map(move |run| RunReport {
+                format_version: RunReport::FORMAT_VERSION,
+                id: run.id,
+                started_at_ms: run.started_at_ms,
+                command: run.command,
+                args: run.args,
+                status: run.status,
+                tree_fingerprint: run.tree_fingerprint,
+                tree_files: run.tree_files,
+                environment: environment.clone(),
+                measurements: run.measurements,
+            })
+        })
+    }
+
+    /// Read a merged report of any supported format version
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let json = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        format::load(&json)
+            .map(|(merged, _)| merged)
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, json + "\n").map_err(|e| e.to_string())
+    }
+
+    /// Column heading per environment: `os-arch`, followed where that alone is ambiguous by
+    /// whichever of host, kernel, CPU count, notify and benchmark version, and allocator tell
+    /// the environments on that platform apart
+    pub fn labels(&self) -> Vec<String> {
+        let platform = |env: &Environment| format!("{}-{}", env.os, env.arch);
+        let fields: [fn(&Environment) -> String; 6] = [
+            |env| format!("@{}", env.hostname),
+            |env| format!(" {}", env.kernel),
+            |env| format!(" {}cpu", env.cpus),
+            |env| format!(" notify-{}", env.notify_version),
+            |env| format!(" v{}", env.benchmark_version),
+            |env| format!(" {}", env.allocator),
+        ];
+        self.environments
+            .iter()
+            .map(|group| {
+                let peers: Vec<&Environment> = self
+                    .environments
+                    .iter()
+                    .map(|other| &other.environment)
+                    .filter(|other| platform(other) == platform(&group.environment))
+                    .collect();
+                let mut label = platform(&group.environment);
+                for field in fields {
+                    let value = field(&group.environment);
+                    if peers.iter().any(|peer| field(peer) != value) {
+                        label.push_str(&value);
+                    }
+                }
+                label
+            })
+            .collect()
+    }
+
+    /// One row per (mode, metric) with each environment's mean, the best one starred
+    pub fn print_comparison(&self) {
+        let labels = self.labels();
+        let means: Vec<BTreeMap<(String, String), f64>> = self.environments.iter().map(EnvironmentRuns::means).collect();
+        let keys: BTreeSet<&(String, String)> = means.iter().flat_map(|m| m.keys()).collect();
+
+        print!("  {:<20} {:<24}", "Mode", "Metric");
+        for label in &labels {
+            print!(" {:>18}", label);
+        }
+        println!();
+        for key in keys {
+            let values: Vec<Option<f64>> = means.iter().map(|m| m.get(key).copied()).collect();
+            let best = best_index(&key.1, &values);
+            print!("  {:<20} {:<24}", key.0, key.1);
+            for (i, value) in values.iter().enumerate() {
+                let cell = value.map_or("-".to_string(), |v| format!("{:.1}{}", v, if best == Some(i) { "*" } else { " " }));
+                print!(" {:>18}", cell);
+            }
+            println!();
+        }
+        println!("  * best across environments, where the metric has a better direction");
+    }
+}
+
+/// Environment with the best value of `metric`, when it has a direction and at least two values
+fn best_index(metric: &str, values: &[Option<f64>]) -> Option<usize> {
+    let better = better_direction(metric)?;
+    let present: Vec<(usize, f64)> = values.iter().enumerate().filter_map(|(i, v)| Some((i, (*v)?))).collect();
+    if present.len() < 2 {
+        return None;
+    }
+    let pick = present.into_iter().reduce(|best, next| if better.prefers(next.1, best.1) { next } else { best });
+    pick.map(|(i, _)| i)
+}
+
+/// Runs in `path`, whether it holds one run's `--report` or an earlier merge
+pub fn load_reports(path: &Path) -> Result<Vec<RunReport>, String> {
+    let json = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let value: serde_json::Value = serde_json::from_str(&json).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if value.get("environments").is_some() {
+        Ok(MergedReport::load(path)?.into_reports().collect())
+    } else {
+        Ok(vec![RunReport::load(path)?])
+    }
+}
+
+/// Combine result files from several machines, print them side by side and
+/// write the merged report with `--report`
+pub fn run_merge(paths: &[PathBuf], options: &Options) -> Result<MergedReport, Box<dyn std::error::Error>> {
+    if paths.len() < 2 {
+        return Err("merge needs at least two report files".into());
+    }
+    let mut reports = Vec::new();
+    for path in paths {
+        reports.extend(load_reports(path)?);
+    }
+    let merged = MergedReport::merge(reports);
+
+    println!("\n=== Merged {} files ===", paths.len());
+    for (label, group) in merged.labels().iter().zip(&merged.environments) {
+        println!("  {:<24} {} runs, {}", label, group.runs.len(), group.environment.key());
+    }
+    let trees: BTreeSet<&str> = merged
+        .environments
+        .iter()
+        .flat_map(|group| group.runs.iter().map(|run| run.tree_fingerprint.as_str()))
+        .collect();
+    if trees.len() > 1 {
+        println!("Note: the runs watched {} different trees, so differences may reflect the tree", trees.len());
+    }
+    println!();
+    merged.print_comparison();
+
+    if let Some(path) = &options.report {
+        merged.write(path)?;
+        println!("\nWrote merged report {}", path.display());
+    }
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::{RunRecord, TreeFingerprint};
+
+    fn report(os: &str, host: &str, setup_us: f64) -> RunReport {
+        let mut run = RunRecord::new("native", &[]);
+        run.id = format!("{}-{}-{}", os, host, setup_us);
+        run.add("Native Recursive", "setup_us", setup_us);
+        let environment = Environment {
+            os: os.to_string(),
+            arch: "x86_64".to_string(),
+            hostname: host.to_string(),
+            ..Environment::capture()
+        };
+        let tree = TreeFingerprint {
+            hash: "abc".to_string(),
+            files: 3,
+        };
+        RunReport::new(&run, &tree, &environment, "ok")
+    }
+
+    #[test]
+    fn test_merge_groups_by_environment() {
+        let first = report("linux", "a", 100.0);
+        let merged = MergedReport::merge([
+            first.clone(),
+            report("macos", "b", 40.0),
+            report("linux", "a", 300.0),
+            first,
+            report("linux", "c", 50.0),
+        ]);
+        let runs: Vec<usize> = merged.environments.iter().map(|group| group.runs.len()).collect();
+        assert_eq!(runs, [2, 1, 1]);
+        assert_eq!(merged.labels(), ["linux-x86_64@a", "macos-x86_64", "linux-x86_64@c"]);
+        // The same host before and after a kernel upgrade and an allocator change
+        let mut upgraded = report("linux", "a", 90.0);
+        upgraded.id = "upgraded".to_string();
+        upgraded.environment.kernel = "6.9.0".to_string();
+        upgraded.environment.allocator = "jemalloc".to_string();
+        let mut before = report("linux", "a", 100.0);
+        before.environment.kernel = "6.1.0".to_string();
+        before.environment.allocator = "system".to_string();
+        let upgrade = MergedReport::merge([before, upgraded]);
+        assert_eq!(upgrade.labels(), ["linux-x86_64 6.1.0 system", "linux-x86_64 6.9.0 jemalloc"]);
+        let key = ("Native Recursive".to_string(), "setup_us".to_string());
+        assert_eq!(merged.environments[0].means()[&key], 200.0);
+
+        let values: Vec<Option<f64>> = merged.environments.iter().map(|group| group.means().get(&key).copied()).collect();
+        assert_eq!(best_index("setup_us", &values), Some(1));
+        assert_eq!(best_index("events", &values), None);
+
+        // Merging a merge again is lossless
+        let again = MergedReport::merge(merged.clone().into_reports());
+        assert_eq!(again, merged);
+    }
+}
@@ -0,0 +1,165 @@
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Canonical change kinds shared by every backend
+///
+/// inotify, FSEvents, kqueue and ReadDirectoryChangesW disagree on subkinds (and
+/// on whether access events exist at all), so comparisons between platforms are
+/// made on this smaller set instead of raw `EventKind`s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum NormalizedKind {
+    Created,
+    Modified,
+    Removed,
+    Renamed,
+}
+
+impl fmt::Display for NormalizedKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Created => "created",
+            Self::Modified => "modified",
+            Self::Removed => "removed",
+            Self::Renamed => "renamed",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A single canonical change to one path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedEvent {
+    pub kind: NormalizedKind,
+    pub path: PathBuf,
+    /// Destination of a rename when the backend reported both sides together
+    pub renamed_to: Option<PathBuf>,
+}
+
+/// Map a raw notify event to canonical events, one per affected path
+///
+/// Access events carry no change and `Other` is backend-specific, so both map
+/// to nothing.
+pub fn normalize(event: &Event) -> Vec<NormalizedEvent> {
+    let kind = match event.kind {
+        EventKind::Create(_) => NormalizedKind::Created,
+        EventKind::Remove(_) => NormalizedKind::Removed,
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+            return vec![NormalizedEvent {
+                kind: NormalizedKind::Renamed,
+                path: event.paths[0].clone(),
+                renamed_to: Some(event.paths[1].clone()),
+            }];
+        }
+        EventKind::Modify(ModifyKind::Name(_)) => NormalizedKind::Renamed,
+        EventKind::Modify(_) | EventKind::Any => NormalizedKind::Modified,
+        EventKind::Access(_) | EventKind::Other => return Vec::new(),
+    };
+
+    event
+        .paths
+        .iter()
+        .map(|path| NormalizedEvent {
+            kind,
+            path: path.clone(),
+            renamed_to: None,
+        })
+        .collect()
+}
+
+/// Whether `event` only changed metadata (permissions, ownership, timestamps, xattrs)
+///
+/// These normalize to `Modified` like content changes; this tells them apart.
+pub fn is_metadata(event: &Event) -> bool {
+    matches!(event.kind, EventKind::Modify(ModifyKind::Metadata(_)))
+}
+
+/// Normalize a batch of raw events, preserving order
+pub fn normalize_all<'a>(events: impl IntoIterator<Item = &'a Event>) -> Vec<NormalizedEvent> {
+    events.into_iter().flat_map(normalize).collect()
+}
+
+/// Count normalized events per canonical kind
+pub fn count_by_kind(events: &[NormalizedEvent]) -> BTreeMap<NormalizedKind, usize> {
+    let mut counts = BTreeMap::new();
+    for event in events {
+        *counts.entry(event.kind).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// One-line summary like `created 1, modified 5`
+pub fn describe_counts(counts: &BTreeMap<NormalizedKind, usize>) -> String {
+    if counts.is_empty() {
+        return "-".to_string();
+    }
+    counts
+        .iter()
+        .map(|(kind, count)| format!("{} {}", kind, count))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{AccessKind, AccessMode, CreateKind, DataChange, MetadataKind};
+
+    fn event(kind: EventKind, paths: &[&str]) -> Event {
+        paths
+            .iter()
+            .fold(Event::new(kind), |e, p| e.add_path(PathBuf::from(p)))
+    }
+
+    #[test]
+    fn test_normalize_kinds() {
+        let cases = [
+            (EventKind::Create(CreateKind::File), Some(NormalizedKind::Created)),
+            (EventKind::Modify(ModifyKind::Data(DataChange::Any)), Some(NormalizedKind::Modified)),
+            (
+                EventKind::Modify(ModifyKind::Metadata(MetadataKind::Any)),
+                Some(NormalizedKind::Modified),
+            ),
+            (EventKind::Modify(ModifyKind::Name(RenameMode::From)), Some(NormalizedKind::Renamed)),
+            (EventKind::Remove(notify::event::RemoveKind::File), Some(NormalizedKind::Removed)),
+            (EventKind::Any, Some(NormalizedKind::Modified)),
+            (EventKind::Access(AccessKind::Close(AccessMode::Write)), None),
+            (EventKind::Other, None),
+        ];
+
+        for (kind, expected) in cases {
+            let normalized = normalize(&event(kind, &["/a"]));
+            assert_eq!(normalized.first().map(|e| e.kind), expected, "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn test_normalize_rename_both_sides() {
+        let normalized = normalize(&event(
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+            &["/from", "/to"],
+        ));
+        assert_eq!(
+            normalized,
+            vec![NormalizedEvent {
+                kind: NormalizedKind::Renamed,
+                path: PathBuf::from("/from"),
+                renamed_to: Some(PathBuf::from("/to")),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_count_and_describe() {
+        let events = [
+            event(EventKind::Create(CreateKind::File), &["/a"]),
+            event(EventKind::Modify(ModifyKind::Any), &["/a", "/b"]),
+            event(EventKind::Access(AccessKind::Any), &["/a"]),
+        ];
+        let counts = count_by_kind(&normalize_all(&events));
+        assert_eq!(describe_counts(&counts), "created 1, modified 2");
+        assert_eq!(describe_counts(&BTreeMap::new()), "-");
+    }
+}
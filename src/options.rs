@@ -0,0 +1,900 @@
+use crate::adaptive::DEFAULT_TARGET_RATE;
+use crate::batch::{DEFAULT_BATCH_EVENTS, DEFAULT_BATCH_INTERVAL};
+use crate::budget::DEFAULT_WATCH_BUDGET;
+use crate::churn::InstallLayout;
+use crate::estimate::DEFAULT_MAX_SETUP;
+use crate::faults::{FaultPlan, DEFAULT_FAULT_DELAY};
+use crate::rate_limit::{RateLimit, DEFAULT_RATE_BURST};
+use crate::report::DEFAULT_REGRESSION_THRESHOLD;
+use crate::fsevents::FsEventsFlag;
+use crate::harness::DEFAULT_MAX_FILES;
+use crate::hybrid::DEFAULT_HOT_FILES;
+use crate::latency::{HistogramFormat, DEFAULT_HISTOGRAM_BUCKETS};
+use crate::recursive_file_watcher::{
+    ChannelKind, FullPolicy, WatchConfig, WatcherMode, DEFAULT_DEBOUNCE, DEFAULT_POLL_INTERVAL,
+    DEFAULT_RETRY_BACKOFF, DEFAULT_WATCH_RETRIES,
+};
+use crate::order::WatchOrder;
+use crate::select::ModifySelect;
+use crate::soak::SoakWorkload;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Optional flags accepted after `<directory> <mode>`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    /// Container runtime binary used by `test-container`
+    pub container_runtime: String,
+    /// Image the `test-container` workload runs in
+    pub container_image: String,
+    /// Directory on another filesystem used by `test-cross-device`
+    pub foreign_dir: PathBuf,
+    /// Truncate manual watch lists that exceed the OS limit instead of failing
+    pub allow_partial: bool,
+    /// Most files enumerated across all roots before a run aborts, or truncates with `truncate_files`; `None` for no cap
+    pub max_files: Option<usize>,
+    /// Keep the first `max_files` files instead of aborting when the roots hold more
+    pub truncate_files: bool,
+    /// Visit directory entries in name order, so file lists and filtered subsets match across runs and platforms
+    pub sort_enumeration: bool,
+    /// Estimated setup time beyond which a run warns, and asks when interactive
+    pub max_setup: Duration,
+    /// Go ahead without asking when the setup estimate raises a warning
+    pub assume_yes: bool,
+    /// Capacity of the event channel; unbounded when `None`
+    pub channel_capacity: Option<usize>,
+    /// Re-enumerate the tree after an overflow signal to measure recovery cost
+    pub rescan_on_overflow: bool,
+    /// Merge duplicate events for the same path within this window when reporting
+    pub coalesce_window: Option<Duration>,
+    /// Noisiest directories listed in event reports; none when zero
+    pub top_dirs: usize,
+    /// Noisiest paths listed with their kinds in event reports; none when zero
+    pub top_paths: usize,
+    /// Graphviz file the watched tree and its coverage go to, one per watcher
+    pub dot: Option<PathBuf>,
+    /// Print event density over the directory tree after each collection
+    pub heatmap: bool,
+    /// Write that density as an SVG treemap, one file per watcher
+    pub heatmap_svg: Option<PathBuf>,
+    /// Fold per-file events into per-directory notifications within this window when reporting
+    pub aggregate_window: Option<Duration>,
+    /// Windows evaluated by `test-coalesce-sweep`; the built-in ladder when empty
+    pub sweep_windows: Vec<Duration>,
+    /// Print per-modification latency histograms in this format
+    pub histogram: Option<HistogramFormat>,
+    /// Upper bounds of the latency histogram buckets, ascending
+    pub histogram_buckets: Vec<Duration>,
+    /// Watchers kept alive by `soak`, one after another
+    pub soak_modes: Vec<WatcherMode>,
+    /// What `soak`'s background writer does to each file
+    pub soak_workload: SoakWorkload,
+    /// How long `soak` runs
+    pub soak_duration: Duration,
+    /// How often `soak` logs a sample
+    pub soak_interval: Duration,
+    /// Pause between background writes during `soak`
+    pub soak_write_interval: Duration,
+    /// Fail `soak` when RSS grows significantly faster than this many KiB per hour
+    pub max_rss_growth: Option<u64>,
+    /// How long `test-idle` leaves each watcher without filesystem activity
+    pub idle_duration: Duration,
+    /// Extra directories watched alongside `<directory>`, one per `--root`
+    pub roots: Vec<PathBuf>,
+    /// Channel implementation for the event pipeline
+    pub channel: ChannelKind,
+    /// What a full bounded channel does to the watcher callback
+    pub on_full: FullPolicy,
+    /// Simulated processing time per received event
+    pub consumer_delay: Duration,
+    /// Watcher mode used by `watch`
+    pub watch_mode: WatcherMode,
+    /// How often `watch` prints a stats line
+    pub stats_interval: Duration,
+    /// File `watch` appends one JSON snapshot per stats line to
+    pub snapshot_file: Option<PathBuf>,
+    /// Rescan interval of the poll watcher
+    pub poll_interval: Duration,
+    /// Quiet period of the debounced watcher
+    pub debounce: Duration,
+    /// Times `compare` and `compare-filtered` set up each watcher
+    pub iterations: usize,
+    /// Setup/teardown rounds per mode in `test-cycle`
+    pub cycles: usize,
+    /// Also report iterated comparisons without runs beyond this many MADs of the median
+    pub trim_outliers: Option<f64>,
+    /// SQLite database each run is appended to, and `history` reads from
+    pub history: Option<PathBuf>,
+    /// JSON file this run's results are written to, for `diff`
+    pub report: Option<PathBuf>,
+    /// Change in percent past which `diff` and `--compare-runs` call a metric regressed or improved
+    pub regression_threshold: f64,
+    /// Markdown file `capabilities` writes its matrix to
+    pub capability_md: Option<PathBuf>,
+    /// Runs `history` lists
+    pub history_limit: usize,
+    /// Two run IDs `history` compares metric by metric
+    pub compare_runs: Option<(String, String)>,
+    /// File `trend` writes its charts to as SVG
+    pub trend_svg: Option<PathBuf>,
+    /// Base path for setup-phase flamegraphs, one file per watcher mode
+    pub profile: Option<PathBuf>,
+    /// Split backend latency at inotify's event queueing with a kernel probe (needs `--features ebpf`)
+    pub kernel_probe: bool,
+    /// Watches registered between timings by `setup-curve`
+    pub batch_size: usize,
+    /// File `setup-curve` writes its chart to as SVG
+    pub curve_svg: Option<PathBuf>,
+    /// Order manual mode registers its watches in
+    pub watch_order: WatchOrder,
+    /// Seed for `--watch-order random`
+    pub seed: u64,
+    /// Evict caches through `/proc/sys/vm/drop_caches` before cold setups (Linux, root)
+    pub drop_caches: bool,
+    /// Lift the soft `RLIMIT_NOFILE` to the hard limit before `kqueue-budget`
+    pub raise_nofile: bool,
+    /// Leave metadata-only events out of the coverage grids
+    pub ignore_metadata: bool,
+    /// How long after an operation starts its expected event may arrive
+    pub expect_timeout: Duration,
+    /// Files per root the watch test modifies
+    pub modify_count: usize,
+    /// How the watch test picks the files it modifies
+    pub modify_select: ModifySelect,
+    /// Pause between starting a watcher and its first change
+    pub stabilize: Duration,
+    /// Pause between consecutive modifications; zero writes them back to back
+    pub pace: Duration,
+    /// Quiet period after the last change that ends the watch test's event collection
+    pub settle: Duration,
+    /// Longest the watch test collects events for, settled or not
+    pub max_collect: Duration,
+    /// Hash watched files around the modification phase and score events against real changes
+    pub verify_content: bool,
+    /// FSEvents latencies swept by `fsevents-sweep`; the built-in ladder when empty
+    pub fsevents_latencies: Vec<Duration>,
+    /// `FSEventStreamCreate` flags used by `fsevents-sweep`
+    pub fsevents_flags: Vec<FsEventsFlag>,
+    /// `ReadDirectoryChangesW` buffer sizes in bytes swept by `buffer-sweep`; the built-in ladder when empty
+    pub buffer_sizes: Vec<usize>,
+    /// Filter-set sizes `filter-memory` measures; the built-in ladder when empty
+    pub filter_sizes: Vec<usize>,
+    /// Files `test-small-writes` appends to in turn
+    pub log_files: usize,
+    /// Appends per second across all files in `test-small-writes`
+    pub log_rate: u64,
+    /// Size in MiB of each file `test-large-files` changes
+    pub large_file_mib: u64,
+    /// Directory names whose events are discarded before they reach the consumer
+    pub exclude: Vec<String>,
+    /// File names (or `*suffix` patterns) the filtered modes always watch, on top of their sample
+    pub priority: Vec<String>,
+    /// Poll roots the native backend refuses instead of failing setup
+    pub poll_fallback: bool,
+    /// Retries of a transiently failing watch call in manual setup before the file is skipped
+    pub watch_retries: u32,
+    /// Wait before the first of those retries, doubled for each later one
+    pub retry_backoff: Duration,
+    /// Per-event fault rates injected between the callback and the consumer, seeded with `--seed`
+    pub inject_faults: Option<FaultPlan>,
+    /// Longest time an injected delay holds an event back
+    pub fault_delay: Duration,
+    /// Events per second the callback passes on; unlimited when `None`
+    pub rate_limit: Option<f64>,
+    /// Events the rate limiter lets through at once
+    pub rate_burst: u32,
+    /// Watches `test-budget` may hold at once
+    pub watch_budget: usize,
+    /// Recently modified files `test-hybrid` gives their own watch
+    pub hot_files: usize,
+    /// Delivered events per second `test-adaptive` keeps under
+    pub target_rate: f64,
+    /// Events a `test-batch` batch holds before it's flushed early
+    pub batch_events: usize,
+    /// How often `test-batch` flushes a partly filled batch
+    pub batch_interval: Duration,
+    /// Package layout `test-npm-install` unpacks
+    pub install_layout: InstallLayout,
+    /// Directory levels `test-deep-nesting` generates
+    pub nesting_depth: usize,
+    /// Share `test-unc` copies its tree into, e.g. `\\server\share\dir`; the local admin share when unset
+    pub unc_path: Option<PathBuf>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            container_runtime: "docker".to_string(),
+            container_image: "alpine".to_string(),
+            foreign_dir: PathBuf::from("/dev/shm"),
+            allow_partial: false,
+            max_files: Some(DEFAULT_MAX_FILES),
+            truncate_files: false,
+            sort_enumeration: false,
+            max_setup: DEFAULT_MAX_SETUP,
+            assume_yes: false,
+            channel_capacity: None,
+            rescan_on_overflow: false,
+            coalesce_window: None,
+            aggregate_window: None,
+            top_dirs: 5,
+            top_paths: 5,
+            dot: None,
+            heatmap: false,
+            heatmap_svg: None,
+            sweep_windows: Vec::new(),
+            histogram: None,
+            histogram_buckets: DEFAULT_HISTOGRAM_BUCKETS.to_vec(),
+            soak_modes: vec![WatcherMode::Native],
+            soak_workload: SoakWorkload::Append,
+            soak_duration: Duration::from_secs(60 * 60),
+            soak_interval: Duration::from_secs(60),
+            soak_write_interval: Duration::from_secs(1),
+            max_rss_growth: None,
+            idle_duration: Duration::from_secs(10),
+            roots: Vec::new(),
+            channel: ChannelKind::Std,
+            on_full: FullPolicy::Block,
+            consumer_delay: Duration::ZERO,
+            watch_mode: WatcherMode::Native,
+            stats_interval: Duration::from_secs(10),
+            snapshot_file: None,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            debounce: DEFAULT_DEBOUNCE,
+            iterations: 1,
+            cycles: 20,
+            trim_outliers: None,
+            history: None,
+            report: None,
+            regression_threshold: DEFAULT_REGRESSION_THRESHOLD,
+            capability_md: None,
+            history_limit: 20,
+            compare_runs: None,
+            trend_svg: None,
+            profile: None,
+            kernel_probe: false,
+            batch_size: 1000,
+            curve_svg: None,
+            watch_order: WatchOrder::default(),
+            seed: 0,
+            drop_caches: false,
+            raise_nofile: false,
+            ignore_metadata: false,
+            expect_timeout: Duration::from_secs(1),
+            modify_count: 5,
+            modify_select: ModifySelect::default(),
+            stabilize: Duration::from_millis(100),
+            pace: Duration::from_millis(10),
+            settle: Duration::from_millis(500),
+            max_collect: Duration::from_secs(10),
+            verify_content: false,
+            fsevents_latencies: Vec::new(),
+            fsevents_flags: vec![FsEventsFlag::FileEvents, FsEventsFlag::NoDefer],
+            buffer_sizes: Vec::new(),
+            filter_sizes: Vec::new(),
+            unc_path: None,
+            exclude: Vec::new(),
+            priority: Vec::new(),
+            poll_fallback: true,
+            watch_retries: DEFAULT_WATCH_RETRIES,
+            retry_backoff: DEFAULT_RETRY_BACKOFF,
+            inject_faults: None,
+            fault_delay: DEFAULT_FAULT_DELAY,
+            rate_limit: None,
+            rate_burst: DEFAULT_RATE_BURST,
+            watch_budget: DEFAULT_WATCH_BUDGET,
+            hot_files: DEFAULT_HOT_FILES,
+            target_rate: DEFAULT_TARGET_RATE,
+            batch_events: DEFAULT_BATCH_EVENTS,
+            batch_interval: DEFAULT_BATCH_INTERVAL,
+            install_layout: InstallLayout::default(),
+            nesting_depth: 300,
+            large_file_mib: 256,
+            log_files: 4,
+            log_rate: 2000,
+        }
+    }
+}
+
+impl Options {
+    /// Parse flags of the form `--name value`
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Self::default();
+        let mut iter = args.iter();
+
+        while let Some(flag) = iter.next() {
+            let mut value = || {
+                iter.next()
+                    .cloned()
+                    .ok_or_else(|| format!("Missing value for {}", flag))
+            };
+
+            match flag.as_str() {
+                "--container-runtime" => options.container_runtime = value()?,
+                "--container-image" => options.container_image = value()?,
+                "--foreign-dir" => options.foreign_dir = PathBuf::from(value()?),
+                "--unc-path" => options.unc_path = Some(PathBuf::from(value()?)),
+                "--exclude" => {
+                    options.exclude = value()?
+                        .split(',')
+                        .map(|name| name.trim().to_string())
+                        .filter(|name| !name.is_empty())
+                        .collect()
+                }
+                "--priority" => {
+                    options.priority = value()?
+                        .split(',')
+                        .map(|name| name.trim().to_string())
+                        .filter(|name| !name.is_empty())
+                        .collect()
+                }
+                "--install-layout" => {
+                    let layout = value()?;
+                    options.install_layout = InstallLayout::from_str(&layout)
+                        .ok_or_else(|| format!("Invalid value for {}: {}", flag, layout))?
+                }
+                "--nesting-depth" => options.nesting_depth = parse_number(flag, &value()?)?,
+                "--large-file-size" => {
+                    let raw = value()?;
+                    options.large_file_mib = parse_number(flag, &raw)?;
+                    // Sized in bytes when the files are written, which must not overflow
+                    if options.large_file_mib.checked_mul(1024 * 1024).is_none() {
+                        return Err(format!("Invalid value for {}: {}", flag, raw));
+                    }
+                }
+                "--log-files" => options.log_files = parse_number(flag, &value()?)?,
+                "--log-rate" => {
+                    let raw = value()?;
+                    options.log_rate = parse_number(flag, &raw)?;
+                    // Anything faster than one append per nanosecond has no interval left to pace by
+                    if !(1..=1_000_000_000).contains(&options.log_rate) {
+                        return Err(format!("Invalid value for {}: {}", flag, raw));
+                    }
+                }
+                "--allow-partial" => options.allow_partial = true,
+                "--yes" => options.assume_yes = true,
+                "--max-setup" => options.max_setup = parse_duration(flag, &value()?)?,
+                "--max-files" => options.max_files = Some(parse_number(flag, &value()?)?).filter(|&max| max > 0),
+                "--truncate-files" => options.truncate_files = true,
+                "--sort-enumeration" => options.sort_enumeration = true,
+                "--rescan-on-overflow" => options.rescan_on_overflow = true,
+                "--kernel-probe" => options.kernel_probe = true,
+                "--drop-caches" => options.drop_caches = true,
+                "--raise-nofile" => options.raise_nofile = true,
+                "--ignore-metadata" => options.ignore_metadata = true,
+                "--verify-content" => options.verify_content = true,
+                "--heatmap" => options.heatmap = true,
+                "--no-poll-fallback" => options.poll_fallback = false,
+                "--no-delay" => {
+                    options.stabilize = Duration::ZERO;
+                    options.pace = Duration::ZERO;
+                }
+                "--coalesce" => {
+                    options.coalesce_window = Some(Duration::from_millis(parse_number(flag, &value()?)?))
+                }
+                "--top-dirs" => options.top_dirs = parse_number(flag, &value()?)?,
+                "--top-paths" => options.top_paths = parse_number(flag, &value()?)?,
+                "--aggregate-dirs" => {
+                    options.aggregate_window = Some(Duration::from_millis(parse_number(flag, &value()?)?))
+                }
+                "--sweep-windows" => {
+                    options.sweep_windows = value()?
+                        .split(',')
+                        .map(|ms| parse_number(flag, ms.trim()).map(Duration::from_millis))
+                        .collect::<Result<_, _>>()?
+                }
+                "--fsevents-latencies" => {
+                    options.fsevents_latencies = value()?
+                        .split(',')
+                        .map(|ms| parse_number(flag, ms.trim()).map(Duration::from_millis))
+                        .collect::<Result<_, _>>()?
+                }
+                "--fsevents-flags" => {
+                    let flags = value()?;
+                    options.fsevents_flags = FsEventsFlag::parse_list(&flags)
+                        .ok_or_else(|| format!("Invalid value for {}: {}", flag, flags))?
+                }
+                "--buffer-sizes" => {
+                    options.buffer_sizes = value()?
+                        .split(',')
+                        .map(|bytes| parse_number(flag, bytes.trim()))
+                        .collect::<Result<_, _>>()?
+                }
+                "--filter-sizes" => {
+                    options.filter_sizes = value()?
+                        .split(',')
+                        .map(|count| parse_number(flag, count.trim()))
+                        .collect::<Result<_, _>>()?
+                }
+                "--histogram" => {
+                    let format = value()?;
+                    options.histogram = Some(
+                        HistogramFormat::from_str(&format)
+                            .ok_or_else(|| format!("Invalid value for {}: {}", flag, format))?,
+                    )
+                }
+                "--histogram-buckets" => {
+                    let buckets = value()?
+                        .split(',')
+                        .map(|bound| parse_duration(flag, bound.trim()))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if buckets.is_empty() || !buckets.windows(2).all(|w| w[0] < w[1]) {
+                        return Err(format!("{} must be ascending", flag));
+                    }
+                    options.histogram_buckets = buckets;
+                }
+                "--soak-mode" => {
+                    let modes = value()?;
+                    options.soak_modes = if modes == "all" {
+                        WatcherMode::ALL.to_vec()
+                    } else {
+                        modes
+                            .split(',')
+                            .map(|mode| {
+                                WatcherMode::from_str(mode.trim())
+                                    .ok_or_else(|| format!("Invalid value for {}: {}", flag, mode))
+                            })
+                            .collect::<Result<_, _>>()?
+                    }
+                }
+                "--soak-workload" => {
+                    let workload = value()?;
+                    options.soak_workload = SoakWorkload::from_str(&workload)
+                        .ok_or_else(|| format!("Invalid value for {}: {}", flag, workload))?
+                }
+                "--max-rss-growth" => options.max_rss_growth = Some(parse_number(flag, &value()?)?),
+                "--soak-duration" => options.soak_duration = parse_duration(flag, &value()?)?,
+                "--soak-interval" => options.soak_interval = parse_duration(flag, &value()?)?,
+                "--soak-write-interval" => {
+                    options.soak_write_interval = parse_duration(flag, &value()?)?
+                }
+                "--idle-duration" => options.idle_duration = parse_duration(flag, &value()?)?,
+                "--root" => options.roots.push(PathBuf::from(value()?)),
+                "--channel" => {
+                    let channel = value()?;
+                    options.channel = ChannelKind::from_str(&channel)
+                        .ok_or_else(|| format!("Invalid value for {}: {}", flag, channel))?
+                }
+                "--watch-order" => {
+                    let order = value()?;
+                    options.watch_order = WatchOrder::from_str(&order)
+                        .ok_or_else(|| format!("Invalid value for {}: {}", flag, order))?
+                }
+                "--modify-count" => options.modify_count = parse_number(flag, &value()?)?,
+                "--modify-select" => {
+                    let select = value()?;
+                    options.modify_select = ModifySelect::from_str(&select)
+                        .ok_or_else(|| format!("Invalid value for {}: {}", flag, select))?
+                }
+                "--seed" => options.seed = parse_number(flag, &value()?)?,
+                "--watch-mode" => {
+                    let mode = value()?;
+                    options.watch_mode = WatcherMode::from_str(&mode)
+                        .ok_or_else(|| format!("Invalid value for {}: {}", flag, mode))?
+                }
+                "--stats-interval" => options.stats_interval = parse_duration(flag, &value()?)?,
+                "--snapshot-file" => options.snapshot_file = Some(PathBuf::from(value()?)),
+                "--poll-interval" => options.poll_interval = parse_duration(flag, &value()?)?,
+                "--debounce" => options.debounce = parse_duration(flag, &value()?)?,
+                "--cycles" => options.cycles = parse_number(flag, &value()?)?,
+                "--watch-retries" => options.watch_retries = parse_number(flag, &value()?)?,
+                "--retry-backoff" => options.retry_backoff = parse_duration(flag, &value()?)?,
+                "--inject-faults" => {
+                    let spec = value()?;
+                    options.inject_faults = Some(
+                        FaultPlan::from_str(&spec).ok_or_else(|| format!("Invalid value for {}: {}", flag, spec))?,
+                    )
+                }
+                "--fault-delay" => options.fault_delay = parse_duration(flag, &value()?)?,
+                "--rate-limit" => {
+                    let value = value()?;
+                    let rate: f64 = parse_number(flag, &value)?;
+                    if !(rate > 0.0 && rate.is_finite()) {
+                        return Err(format!("Invalid value for {}: {}", flag, value));
+                    }
+                    options.rate_limit = Some(rate);
+                }
+                "--rate-burst" => {
+                    let burst = value()?;
+                    options.rate_burst = parse_number(flag, &burst)?;
+                    if options.rate_burst == 0 {
+                        return Err(format!("Invalid value for {}: {}", flag, burst));
+                    }
+                }
+                "--watch-budget" => options.watch_budget = parse_number(flag, &value()?)?,
+                "--hot-files" => options.hot_files = parse_number(flag, &value()?)?,
+                "--target-rate" => {
+                    let raw = value()?;
+                    options.target_rate = parse_number(flag, &raw)?;
+                    if !(options.target_rate > 0.0 && options.target_rate.is_finite()) {
+                        return Err(format!("Invalid value for {}: {}", flag, raw));
+                    }
+                }
+                "--batch-events" => {
+                    let batch_events = value()?;
+                    options.batch_events = parse_number(flag, &batch_events)?;
+                    if options.batch_events == 0 {
+                        return Err(format!("Invalid value for {}: {}", flag, batch_events));
+                    }
+                }
+                "--batch-interval" => options.batch_interval = parse_duration(flag, &value()?)?,
+                "--iterations" => {
+                    let iterations = value()?;
+                    options.iterations = parse_number(flag, &iterations)?;
+                    if options.iterations == 0 {
+                        return Err(format!("Invalid value for {}: {}", flag, iterations));
+                    }
+                }
+                "--batch-size" => {
+                    let batch_size = value()?;
+                    options.batch_size = parse_number(flag, &batch_size)?;
+                    if options.batch_size == 0 {
+                        return Err(format!("Invalid value for {}: {}", flag, batch_size));
+                    }
+                }
+                "--curve-svg" => options.curve_svg = Some(PathBuf::from(value()?)),
+                "--dot" => options.dot = Some(PathBuf::from(value()?)),
+                "--heatmap-svg" => options.heatmap_svg = Some(PathBuf::from(value()?)),
+                "--trim-outliers" => {
+                    let raw = value()?;
+                    let k: f64 = parse_number(flag, &raw)?;
+                    if k.is_nan() || k <= 0.0 {
+                        return Err(format!("Invalid value for {}: {}", flag, raw));
+                    }
+                    options.trim_outliers = Some(k);
+                }
+                "--history" => options.history = Some(PathBuf::from(value()?)),
+                "--report" => options.report = Some(PathBuf::from(value()?)),
+                "--capability-md" => options.capability_md = Some(PathBuf::from(value()?)),
+                "--regression-threshold" => {
+                    let value = value()?;
+                    let threshold: f64 = parse_number(flag, &value)?;
+                    if !(threshold >= 0.0 && threshold.is_finite()) {
+                        return Err(format!("Invalid value for {}: {}", flag, value));
+                    }
+                    options.regression_threshold = threshold;
+                }
+                "--profile" => options.profile = Some(PathBuf::from(value()?)),
+                "--trend-svg" => options.trend_svg = Some(PathBuf::from(value()?)),
+                "--history-limit" => options.history_limit = parse_number(flag, &value()?)?,
+                "--compare-runs" => {
+                    let runs = value()?;
+                    let (a, b) = runs
+                        .split_once(',')
+                        .filter(|(a, b)| !a.trim().is_empty() && !b.trim().is_empty())
+                        .ok_or_else(|| format!("Invalid value for {}: {}", flag, runs))?;
+                    options.compare_runs = Some((a.trim().to_string(), b.trim().to_string()));
+                }
+                "--consumer-delay" => options.consumer_delay = parse_duration(flag, &value()?)?,
+                "--expect-timeout" => options.expect_timeout = parse_duration(flag, &value()?)?,
+                "--stabilize" => options.stabilize = parse_duration(flag, &value()?)?,
+                "--pace" => options.pace = parse_duration(flag, &value()?)?,
+                "--settle" => options.settle = parse_duration(flag, &value()?)?,
+                "--max-collect" => options.max_collect = parse_duration(flag, &value()?)?,
+                "--on-full" => {
+                    let policy = value()?;
+                    options.on_full = FullPolicy::from_str(&policy)
+                        .ok_or_else(|| format!("Invalid value for {}: {}", flag, policy))?
+                }
+                "--channel-capacity" => options.channel_capacity = Some(parse_number(flag, &value()?)?),
+                _ => return Err(format!("Unknown option: {}", flag)),
+            }
+        }
+
+        if options.channel == ChannelKind::Tokio && options.channel_capacity == Some(0) {
+            return Err("Invalid value for --channel-capacity: 0 (a tokio channel needs room for at least one event)".to_string());
+        }
+
+        Ok(options)
+    }
+
+    /// Whether any output needs events binned into a heatmap
+    pub fn wants_heatmap(&self) -> bool {
+        self.heatmap || self.heatmap_svg.is_some()
+    }
+
+    /// Watcher options derived from these flags
+    pub fn watch_config(&self) -> WatchConfig {
+        WatchConfig {
+            channel_capacity: self.channel_capacity,
+            channel: self.channel,
+            on_full: self.on_full,
+            poll_interval: self.poll_interval,
+            debounce: self.debounce,
+            exclude: self.exclude.clone(),
+            poll_fallback: self.poll_fallback,
+            watch_retries: self.watch_retries,
+            retry_backoff: self.retry_backoff,
+            faults: self.inject_faults.map(|plan| FaultPlan {
+                max_delay: self.fault_delay,
+                seed: self.seed,
+                ..plan
+            }),
+            rate_limit: self.rate_limit.map(|rate| RateLimit {
+                rate,
+                burst: self.rate_burst,
+            }),
+        }
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value for {}: {}", flag, value))
+}
+
+/// Parse a duration like `250us`, `5ms`, `1s` or `2h`; a bare number is milliseconds
+fn parse_duration(flag: &str, value: &str) -> Result<Duration, String> {
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = parse_number(flag, number)?;
+    let invalid = || format!("Invalid value for {}: {}", flag, value);
+    match unit {
+        "ns" => Ok(Duration::from_nanos(number)),
+        "us" | "µs" => Ok(Duration::from_micros(number)),
+        "" | "ms" => Ok(Duration::from_millis(number)),
+        "s" => Ok(Duration::from_secs(number)),
+        "m" => number.checked_mul(60).map(Duration::from_secs).ok_or_else(invalid),
+        "h" => number.checked_mul(60 * 60).map(Duration::from_secs).ok_or_else(invalid),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_defaults() {
+        assert_eq!(Options::parse(&[]), Ok(Options::default()));
+    }
+
+    #[test]
+    fn test_parse_flags() {
+        let options = Options::parse(&args(&[
+            "--container-runtime", "podman",
+            "--container-image", "busybox",
+            "--foreign-dir", "/mnt/other",
+            "--allow-partial",
+            "--max-setup", "5m",
+            "--max-files", "300000",
+            "--truncate-files",
+            "--sort-enumeration",
+            "--yes",
+            "--channel-capacity", "64",
+            "--rescan-on-overflow",
+            "--coalesce", "50",
+            "--aggregate-dirs", "100",
+            "--top-dirs", "3",
+            "--top-paths", "10",
+            "--sweep-windows", "0, 10,100",
+            "--histogram", "json",
+            "--histogram-buckets", "250us,5ms,1s",
+            "--soak-mode", "manual-filtered,native",
+            "--soak-workload", "rename",
+            "--max-rss-growth", "512",
+            "--idle-duration", "30s",
+            "--soak-duration", "2h",
+            "--soak-interval", "5m",
+            "--soak-write-interval", "200",
+            "--root", "../a",
+            "--root", "/b",
+            "--channel", "crossbeam",
+            "--on-full", "drop",
+            "--consumer-delay", "15",
+            "--expect-timeout", "3s",
+            "--modify-count", "50",
+            "--modify-select", "spread",
+            "--stabilize", "1s",
+            "--pace", "0",
+            "--settle", "250",
+            "--max-collect", "20s",
+            "--watch-mode", "manual",
+            "--stats-interval", "30s",
+            "--snapshot-file", "day.jsonl",
+            "--poll-interval", "1s",
+            "--debounce", "25",
+            "--iterations", "7",
+            "--cycles", "50",
+            "--watch-retries", "5",
+            "--retry-backoff", "250us",
+            "--inject-faults", "drop=0.05,error=0.01",
+            "--fault-delay", "5ms",
+            "--rate-limit", "500",
+            "--rate-burst", "20",
+            "--watch-budget", "512",
+            "--hot-files", "16",
+            "--target-rate", "50",
+            "--batch-events", "32",
+            "--batch-interval", "2ms",
+            "--trim-outliers", "2.5",
+            "--history", "results.db",
+            "--report", "after.json",
+            "--capability-md", "CAPABILITIES.md",
+            "--regression-threshold", "2.5",
+            "--history-limit", "5",
+            "--compare-runs", "1-2, 3-4",
+            "--trend-svg", "trend.svg",
+            "--profile", "setup.svg",
+            "--kernel-probe",
+            "--batch-size", "500",
+            "--curve-svg", "curve.svg",
+            "--dot", "tree.dot",
+            "--heatmap",
+            "--heatmap-svg", "heat.svg",
+            "--watch-order", "bfs",
+            "--seed", "42",
+            "--drop-caches",
+            "--raise-nofile",
+            "--ignore-metadata",
+            "--verify-content",
+            "--fsevents-latencies", "0,20, 200",
+            "--fsevents-flags", "file-events,ignore-self",
+            "--buffer-sizes", "4096,65536",
+            "--filter-sizes", "1000, 50000",
+            "--unc-path", r"\\server\share\bench",
+            "--exclude", "target, node_modules",
+            "--priority", "package.json, *.toml",
+            "--install-layout", "pnpm",
+            "--nesting-depth", "800",
+            "--large-file-size", "512",
+            "--log-files", "8",
+            "--log-rate", "5000",
+        ]))
+        .unwrap();
+        assert_eq!(options.container_runtime, "podman");
+        assert_eq!(options.container_image, "busybox");
+        assert_eq!(options.foreign_dir, PathBuf::from("/mnt/other"));
+        assert!(options.allow_partial);
+        assert_eq!(options.max_files, Some(300_000));
+        assert!(options.truncate_files);
+        assert!(options.sort_enumeration);
+        assert_eq!(Options::parse(&args(&["--max-files", "0"])).unwrap().max_files, None);
+        assert_eq!(options.max_setup, Duration::from_secs(5 * 60));
+        assert!(options.assume_yes);
+        assert_eq!(options.watch_config().channel_capacity, Some(64));
+        assert!(options.rescan_on_overflow);
+        assert_eq!(options.coalesce_window, Some(Duration::from_millis(50)));
+        assert_eq!(options.aggregate_window, Some(Duration::from_millis(100)));
+        assert_eq!(options.top_dirs, 3);
+        assert_eq!(options.top_paths, 10);
+        assert_eq!(
+            options.sweep_windows,
+            [0, 10, 100].map(Duration::from_millis).to_vec()
+        );
+
+        assert!(Options::parse(&args(&["--container-image"])).is_err());
+        assert!(Options::parse(&args(&["--bogus", "1"])).is_err());
+        assert!(Options::parse(&args(&["--channel-capacity", "lots"])).is_err());
+        assert!(Options::parse(&args(&["--channel", "tokio", "--channel-capacity", "0"])).is_err());
+        assert!(Options::parse(&args(&["--channel-capacity", "0"])).is_ok());
+        assert_eq!(options.histogram, Some(HistogramFormat::Json));
+        assert_eq!(
+            options.histogram_buckets,
+            [
+                Duration::from_micros(250),
+                Duration::from_millis(5),
+                Duration::from_secs(1)
+            ]
+        );
+        assert_eq!(options.soak_modes, [WatcherMode::ManualFiltered, WatcherMode::Native]);
+        assert_eq!(options.soak_workload, SoakWorkload::Rename);
+        assert_eq!(options.max_rss_growth, Some(512));
+        assert_eq!(options.idle_duration, Duration::from_secs(30));
+        assert_eq!(
+            Options::parse(&args(&["--soak-mode", "all"])).unwrap().soak_modes,
+            WatcherMode::ALL
+        );
+        assert_eq!(options.soak_duration, Duration::from_secs(2 * 60 * 60));
+        assert_eq!(options.soak_interval, Duration::from_secs(5 * 60));
+        assert_eq!(options.soak_write_interval, Duration::from_millis(200));
+        assert_eq!(options.roots, [PathBuf::from("../a"), PathBuf::from("/b")]);
+        assert_eq!(options.watch_config().channel, ChannelKind::Crossbeam);
+        assert!(Options::parse(&args(&["--channel", "flume"])).is_err());
+        assert_eq!(options.watch_config().on_full, FullPolicy::Drop);
+        assert_eq!(options.consumer_delay, Duration::from_millis(15));
+        assert_eq!(options.expect_timeout, Duration::from_secs(3));
+        assert_eq!(options.modify_count, 50);
+        assert_eq!(options.modify_select, ModifySelect::Spread);
+        assert!(Options::parse(&args(&["--modify-select", "last"])).is_err());
+        assert_eq!(options.stabilize, Duration::from_secs(1));
+        assert_eq!(options.pace, Duration::ZERO);
+        let no_delay = Options::parse(&args(&["--no-delay"])).unwrap();
+        assert_eq!((no_delay.stabilize, no_delay.pace), (Duration::ZERO, Duration::ZERO));
+        assert!(options.watch_config().poll_fallback);
+        assert!(!Options::parse(&args(&["--no-poll-fallback"])).unwrap().watch_config().poll_fallback);
+        assert_eq!(options.settle, Duration::from_millis(250));
+        assert_eq!(options.max_collect, Duration::from_secs(20));
+        assert_eq!(options.watch_mode, WatcherMode::Manual);
+        assert_eq!(options.stats_interval, Duration::from_secs(30));
+        assert_eq!(options.snapshot_file, Some(PathBuf::from("day.jsonl")));
+        assert_eq!(options.watch_config().poll_interval, Duration::from_secs(1));
+        assert_eq!(options.watch_config().watch_retries, 5);
+        assert_eq!(options.watch_config().retry_backoff, Duration::from_micros(250));
+        let faults = options.watch_config().faults.unwrap();
+        assert_eq!((faults.drop, faults.error, faults.delay), (0.05, 0.01, 0.0));
+        assert_eq!((faults.max_delay, faults.seed), (Duration::from_millis(5), 42));
+        assert!(Options::parse(&args(&["--inject-faults", "drop=2"])).is_err());
+        assert_eq!(options.watch_budget, 512);
+        assert_eq!(options.hot_files, 16);
+        assert_eq!(options.target_rate, 50.0);
+        assert_eq!(options.batch_events, 32);
+        assert_eq!(options.batch_interval, Duration::from_millis(2));
+        assert!(Options::parse(&args(&["--batch-events", "0"])).is_err());
+        assert_eq!(Options::default().watch_config().faults, None);
+        assert_eq!(options.watch_config().rate_limit, Some(RateLimit { rate: 500.0, burst: 20 }));
+        assert!(Options::parse(&args(&["--rate-limit", "0"])).is_err());
+        assert!(Options::parse(&args(&["--rate-burst", "0"])).is_err());
+        assert_eq!(Options::default().watch_config().rate_limit, None);
+        assert_eq!(options.watch_config().debounce, Duration::from_millis(25));
+        assert_eq!(options.iterations, 7);
+        assert!(Options::parse(&args(&["--iterations", "0"])).is_err());
+        assert_eq!(options.cycles, 50);
+        assert_eq!(options.trim_outliers, Some(2.5));
+        assert!(Options::parse(&args(&["--trim-outliers", "-1"])).is_err());
+        assert_eq!(
+            Options::parse(&args(&["--trim-outliers", "-0"])),
+            Err("Invalid value for --trim-outliers: -0".to_string())
+        );
+        assert_eq!(options.history, Some(PathBuf::from("results.db")));
+        assert_eq!(options.report, Some(PathBuf::from("after.json")));
+        assert_eq!(options.capability_md, Some(PathBuf::from("CAPABILITIES.md")));
+        assert_eq!(options.regression_threshold, 2.5);
+        assert!(Options::parse(&args(&["--regression-threshold", "-1"])).is_err());
+        assert_eq!(options.history_limit, 5);
+        assert_eq!(options.compare_runs, Some(("1-2".to_string(), "3-4".to_string())));
+        assert!(Options::parse(&args(&["--compare-runs", "1-2"])).is_err());
+        assert_eq!(options.trend_svg, Some(PathBuf::from("trend.svg")));
+        assert_eq!(options.profile, Some(PathBuf::from("setup.svg")));
+        assert!(options.kernel_probe);
+        assert_eq!(options.batch_size, 500);
+        assert_eq!(options.curve_svg, Some(PathBuf::from("curve.svg")));
+        assert_eq!(options.dot, Some(PathBuf::from("tree.dot")));
+        assert!(options.heatmap);
+        assert_eq!(options.heatmap_svg, Some(PathBuf::from("heat.svg")));
+        assert!(Options::parse(&args(&["--batch-size", "0"])).is_err());
+        assert_eq!(options.watch_order, WatchOrder::Bfs);
+        assert_eq!(options.seed, 42);
+        assert!(options.drop_caches);
+        assert!(options.raise_nofile);
+        assert!(options.ignore_metadata);
+        assert!(options.verify_content);
+        assert_eq!(
+            options.fsevents_latencies,
+            [0, 20, 200].map(Duration::from_millis)
+        );
+        assert_eq!(options.fsevents_flags, [FsEventsFlag::FileEvents, FsEventsFlag::IgnoreSelf]);
+        assert!(Options::parse(&args(&["--fsevents-flags", "no-defer,sticky"])).is_err());
+        assert_eq!(options.buffer_sizes, [4096, 65536]);
+        assert_eq!(options.filter_sizes, [1000, 50000]);
+        assert_eq!(options.unc_path, Some(PathBuf::from(r"\\server\share\bench")));
+        assert_eq!(options.exclude, ["target", "node_modules"]);
+        assert_eq!(options.watch_config().exclude, ["target", "node_modules"]);
+        assert_eq!(options.priority, ["package.json", "*.toml"]);
+        assert_eq!(options.install_layout, InstallLayout::Pnpm);
+        assert!(Options::parse(&args(&["--install-layout", "yarn"])).is_err());
+        assert_eq!(options.nesting_depth, 800);
+        assert_eq!(options.large_file_mib, 512);
+        assert_eq!(options.log_files, 8);
+        assert_eq!(options.log_rate, 5000);
+        assert!(Options::parse(&args(&["--watch-order", "zigzag"])).is_err());
+
+        assert!(Options::parse(&args(&["--sweep-windows", "10,,20"])).is_err());
+        assert!(Options::parse(&args(&["--histogram", "xml"])).is_err());
+        assert!(Options::parse(&args(&["--histogram-buckets", "5ms,1ms"])).is_err());
+        assert!(Options::parse(&args(&["--histogram-buckets", "5min"])).is_err());
+        assert!(Options::parse(&args(&["--soak-mode", "fast"])).is_err());
+        assert!(Options::parse(&args(&["--soak-duration", "18446744073709551615h"])).is_err());
+        assert!(Options::parse(&args(&["--large-file-size", "18446744073709551615"])).is_err());
+        assert!(Options::parse(&args(&["--log-rate", "0"])).is_err());
+        assert!(Options::parse(&args(&["--log-rate", "5000000000"])).is_err());
+        assert!(Options::parse(&args(&["--target-rate", "0"])).is_err());
+        assert!(Options::parse(&args(&["--target-rate", "inf"])).is_err());
+        assert!(Options::parse(&args(&["--target-rate", "NaN"])).is_err());
+    }
+}
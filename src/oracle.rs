@@ -0,0 +1,230 @@
+use crate::latency::WriteRecord;
+use crate::normalize::{normalize, NormalizedKind};
+use crate::stats::CollectedEvents;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// One change a workload made and expects its watcher to report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expectation {
+    pub path: PathBuf,
+    pub kind: NormalizedKind,
+    /// Taken immediately before the operation was issued
+    pub at: Instant,
+}
+
+/// Expectations registered by a workload as it runs, scored against what arrived
+#[derive(Debug, Clone, Default)]
+pub struct Oracle {
+    expectations: Vec<Expectation>,
+    /// Every path the workload changed, expected to be reported or not
+    touched: HashSet<PathBuf>,
+}
+
+impl Oracle {
+    /// Expect a `kind` event for `path` from an operation started at `at`
+    pub fn expect(&mut self, path: impl Into<PathBuf>, kind: NormalizedKind, at: Instant) {
+        let path = std::path::absolute(path.into()).unwrap_or_default();
+        self.touched.insert(path.clone());
+        self.expectations.push(Expectation { path, kind, at });
+    }
+
+    /// Note a change to `path` that no event is owed for, so events naming it aren't spurious
+    pub fn touch(&mut self, path: impl Into<PathBuf>) {
+        self.touched.insert(std::path::absolute(path.into()).unwrap_or_default());
+    }
+
+    /// Expect a `Modified` event for each write
+    pub fn expect_writes(&mut self, writes: &[WriteRecord]) {
+        for write in writes {
+            self.expect(&write.path, NormalizedKind::Modified, write.started_at);
+        }
+    }
+
+    /// Match received events against the expectations
+    ///
+    /// An expectation is met by a received event of the same path and kind within
+    /// `timeout` of the operation starting. A received event is correct when it
+    /// falls in some expectation's window, so a backend reporting one write twice
+    /// keeps full precision; only events nothing asked for lower it.
+    pub fn score(&self, collected: &CollectedEvents, timeout: Duration) -> OracleScore {
+        let received: Vec<(PathBuf, NormalizedKind, Instant)> = collected
+            .events
+            .iter()
+            .zip(&collected.received_at)
+            .flat_map(|(event, &at)| normalize(event).into_iter().map(move |change| (change.path, change.kind, at)))
+            .collect();
+        let answers = |expectation: &Expectation, (path, kind, at): &(PathBuf, NormalizedKind, Instant)| {
+            path == &expectation.path
+                && *kind == expectation.kind
+                && *at >= expectation.at
+                && *at - expectation.at <= timeout
+        };
+
+        let unmet: Vec<Expectation> = self
+            .expectations
+            .iter()
+            .filter(|expectation| !received.iter().any(|event| answers(expectation, event)))
+            .cloned()
+            .collect();
+        let unexpected = received
+            .iter()
+            .filter(|event| !self.expectations.iter().any(|expectation| answers(expectation, event)))
+            .count();
+        let spurious = received
+            .iter()
+            .filter(|(path, _, _)| !self.touched.contains(path))
+            .map(|(path, _, _)| path.clone())
+            .collect();
+        let touched_dirs = self
+            .touched
+            .iter()
+            .flat_map(|path| path.ancestors().skip(1))
+            .map(Path::to_path_buf)
+            .collect();
+        OracleScore {
+            expected: self.expectations.len(),
+            received: received.len(),
+            unmet,
+            unexpected,
+            spurious,
+            touched_dirs,
+        }
+    }
+}
+
+/// Precision and recall of one mode's events against what its workload expected
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OracleScore {
+    pub expected: usize,
+    /// Normalized changes received
+    pub received: usize,
+    /// Expectations no received event met in time
+    pub unmet: Vec<Expectation>,
+    /// Received changes no expectation asked for
+    pub unexpected: usize,
+    /// Paths of received changes to files the workload never touched, one per change
+    pub spurious: Vec<PathBuf>,
+    /// Directories above a touched file, whose mtime updates make up much of `spurious`
+    pub touched_dirs: HashSet<PathBuf>,
+}
+
+impl OracleScore {
+    /// Share of received changes that were expected; 1 when nothing arrived
+    pub fn precision(&self) -> f64 {
+        if self.received == 0 {
+            return 1.0;
+        }
+        (self.received - self.unexpected) as f64 / self.received as f64
+    }
+
+    /// Share of expectations that were met; 1 when nothing was expected
+    pub fn recall(&self) -> f64 {
+        if self.expected == 0 {
+            return 1.0;
+        }
+        (self.expected - self.unmet.len()) as f64 / self.expected as f64
+    }
+
+    /// Spurious changes reported for a directory above a touched file
+    pub fn spurious_on_parents(&self) -> usize {
+        self.spurious.iter().filter(|path| self.touched_dirs.contains(*path)).count()
+    }
+
+    /// Table text, e.g. `100/80` for full precision and 80% recall
+    pub fn describe(&self) -> String {
+        format!("{:.0}/{:.0}", self.precision() * 100.0, self.recall() * 100.0)
+    }
+
+    pub fn report(&self, indent: &str) {
+        println!(
+            "{}Oracle: {} expected, {} met, {} unexpected of {} received (precision {:.1}%, recall {:.1}%)",
+            indent,
+            self.expected,
+            self.expected - self.unmet.len(),
+            self.unexpected,
+            self.received,
+            self.precision() * 100.0,
+            self.recall() * 100.0
+        );
+        for expectation in self.unmet.iter().take(3) {
+            println!("{}  unmet: {} {}", indent, expectation.kind, expectation.path.display());
+        }
+        if self.unmet.len() > 3 {
+            println!("{}  ... and {} more unmet", indent, self.unmet.len() - 3);
+        }
+        if !self.spurious.is_empty() {
+            let paths: HashSet<&PathBuf> = self.spurious.iter().collect();
+            println!(
+                "{}Spurious: {} changes on {} untouched paths ({} on parent directories)",
+                indent,
+                self.spurious.len(),
+                paths.len(),
+                self.spurious_on_parents()
+            );
+            let mut paths: Vec<&PathBuf> = paths.into_iter().collect();
+            paths.sort();
+            for path in paths.iter().take(3) {
+                println!("{}  spurious: {}", indent, path.display());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, DataChange, ModifyKind};
+    use notify::{Event, EventKind};
+
+    #[test]
+    fn test_score_precision_and_recall() {
+        let start = Instant::now();
+        let ms = |n| start + Duration::from_millis(n);
+        let path = |name: &str| std::path::absolute(name).unwrap();
+        let modify = EventKind::Modify(ModifyKind::Data(DataChange::Any));
+
+        let mut oracle = Oracle::default();
+        oracle.expect("a.js", NormalizedKind::Modified, ms(0));
+        oracle.expect("b.js", NormalizedKind::Modified, ms(0));
+        oracle.expect("c.js", NormalizedKind::Created, ms(0));
+        oracle.expect("d.js", NormalizedKind::Modified, ms(0));
+
+        let mut collected = CollectedEvents::default();
+        for (kind, name, at) in [
+            // Reported twice: both count as correct
+            (modify, "a.js", 5),
+            (modify, "a.js", 6),
+            // Right path, wrong kind
+            (modify, "c.js", 5),
+            (EventKind::Create(CreateKind::File), "c.js", 7),
+            // Too late for its window
+            (modify, "d.js", 1500),
+            (modify, "other.js", 5),
+        ] {
+            collected.events.push(Event::new(kind).add_path(path(name)));
+            collected.received_at.push(ms(at));
+        }
+
+        let score = oracle.score(&collected, Duration::from_secs(1));
+        assert_eq!((score.expected, score.received, score.unexpected), (4, 6, 3));
+        let unmet: Vec<PathBuf> = score.unmet.iter().map(|e| e.path.clone()).collect();
+        assert_eq!(unmet, [path("b.js"), path("d.js")]);
+        assert!((score.precision() - 0.5).abs() < 1e-9);
+        assert!((score.recall() - 0.5).abs() < 1e-9);
+        assert_eq!(score.describe(), "50/50");
+        assert_eq!(score.spurious, [path("other.js")]);
+        assert_eq!(score.spurious_on_parents(), 0);
+
+        // The directory holding a.js is touched by its write, but never itself written
+        let dir = path("a.js").parent().unwrap().to_path_buf();
+        collected.events.push(Event::new(modify).add_path(dir.clone()));
+        collected.received_at.push(ms(5));
+        oracle.touch("other.js");
+        let score = oracle.score(&collected, Duration::from_secs(1));
+        assert_eq!(score.spurious, [dir]);
+        assert_eq!(score.spurious_on_parents(), 1);
+        assert_eq!(OracleScore::default().precision(), 1.0);
+    }
+}
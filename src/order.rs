@@ -0,0 +1,163 @@
+use crate::harness::{enumerate_files, manual_watcher};
+use crate::latency::DurationSummary;
+use crate::options::Options;
+use crate::significance::MannWhitney;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Order in which manual mode registers its per-file watches
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WatchOrder {
+    /// As enumerated, finishing each directory's subtree before its siblings
+    #[default]
+    Dfs,
+    /// Shallowest files first, level by level
+    Bfs,
+    /// Lexicographic by path
+    Sorted,
+    /// Shuffled with `--seed`, scattering consecutive watches across directories
+    Random,
+}
+
+impl WatchOrder {
+    pub const ALL: [Self; 4] = [Self::Dfs, Self::Bfs, Self::Sorted, Self::Random];
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "dfs" => Some(Self::Dfs),
+            "bfs" => Some(Self::Bfs),
+            "sorted" => Some(Self::Sorted),
+            "random" => Some(Self::Random),
+            _ => None,
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Dfs => "dfs",
+            Self::Bfs => "bfs",
+            Self::Sorted => "sorted",
+            Self::Random => "random",
+        }
+    }
+
+    /// Rearrange files enumerated by `walk_files` (which is DFS order)
+    pub fn arrange(&self, mut files: Vec<PathBuf>, seed: u64) -> Vec<PathBuf> {
+        match self {
+            Self::Dfs => {},
+            // A stable sort by depth keeps each level in the order its parents were visited
+            Self::Bfs => files.sort_by_key(|path| path.components().count()),
+            Self::Sorted => files.sort(),
+            Self::Random => shuffle(&mut files, seed),
+        }
+        files
+    }
+}
+
+/// Amount SplitMix64 advances its state by for each output
+pub const SPLITMIX_GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// SplitMix64 output for an already-advanced `state`
+pub fn splitmix64(state: u64) -> u64 {
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Fisher-Yates shuffle driven by SplitMix64, so `--seed` reproduces the order
+pub fn shuffle<T>(items: &mut [T], seed: u64) {
+    let mut state = seed;
+    let mut next = move || {
+        state = state.wrapping_add(SPLITMIX_GAMMA);
+        splitmix64(state)
+    };
+    for i in (1..items.len()).rev() {
+        let j = (next() % (i as u64 + 1)) as usize;
+        items.swap(i, j);
+    }
+}
+
+/// Setup times for each order, one per iteration
+pub type OrderTimes = Vec<(WatchOrder, Vec<Duration>)>;
+
+/// Set up manual mode once per order per iteration and compare setup times against DFS
+///
+/// Orders are interleaved within each iteration so page-cache warmup doesn't favour
+/// whichever order happens to run last.
+pub fn run_order_comparison(
+    roots: &[PathBuf],
+    options: &Options,
+) -> Result<OrderTimes, Box<dyn std::error::Error>> {
+    println!("\n=== Watch Registration Order ===");
+    let files: Vec<PathBuf> = enumerate_files(roots, options)?;
+    println!("Registering {} manual watches in each order, {} iteration(s)", files.len(), options.iterations);
+
+    let mut results: OrderTimes = WatchOrder::ALL.iter().map(|&o| (o, Vec::new())).collect();
+    for iteration in 1..=options.iterations {
+        if options.iterations > 1 {
+            println!("\n--- Iteration {}/{} ---", iteration, options.iterations);
+        }
+        for (order, times) in results.iter_mut() {
+            println!("\n{} order:", order.display_name());
+            let watcher = manual_watcher(order.arrange(files.clone(), options.seed), options)?;
+            times.push(watcher.setup_time());
+        }
+    }
+
+    println!("\n📊 Setup time by registration order:");
+    println!("  {:<8} {:>12} {:>12} {:>12} {:>9}  vs dfs", "Order", "Median", "Min", "Max", "Ratio");
+    let baseline = results[0].1.clone();
+    let secs = |times: &[Duration]| times.iter().map(Duration::as_secs_f64).collect::<Vec<_>>();
+    let baseline_median = DurationSummary::from_durations(baseline.clone()).map_or(0.0, |s| s.p50.as_secs_f64());
+    for (order, times) in &results {
+        let Some(summary) = DurationSummary::from_durations(times.clone()) else {
+            continue;
+        };
+        let verdict = if *order == WatchOrder::Dfs || times.len() < 2 {
+            String::new()
+        } else {
+            MannWhitney::test(&secs(times), &secs(&baseline)).map_or(String::new(), |test| test.describe())
+        };
+        let line = format!(
+            "  {:<8} {:>12} {:>12} {:>12} {:>8.2}x  {}",
+            order.display_name(),
+            format!("{:.1?}", summary.p50),
+            format!("{:.1?}", summary.min),
+            format!("{:.1?}", summary.max),
+            summary.p50.as_secs_f64() / baseline_median.max(f64::EPSILON),
+            verdict
+        );
+        println!("{}", line.trim_end());
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arrange_orders() {
+        let dfs: Vec<PathBuf> = ["t/b/x/1", "t/b/2", "t/a/3", "t/4"].iter().map(PathBuf::from).collect();
+        assert_eq!(WatchOrder::Dfs.arrange(dfs.clone(), 0), dfs);
+        assert_eq!(
+            WatchOrder::Bfs.arrange(dfs.clone(), 0),
+            ["t/4", "t/b/2", "t/a/3", "t/b/x/1"].map(PathBuf::from)
+        );
+        assert_eq!(
+            WatchOrder::Sorted.arrange(dfs.clone(), 0),
+            ["t/4", "t/a/3", "t/b/2", "t/b/x/1"].map(PathBuf::from)
+        );
+
+        let many: Vec<PathBuf> = (0..50).map(|i| PathBuf::from(i.to_string())).collect();
+        let shuffled = WatchOrder::Random.arrange(many.clone(), 7);
+        assert_ne!(shuffled, many);
+        assert_eq!(shuffled, WatchOrder::Random.arrange(many.clone(), 7));
+        let mut sorted = shuffled;
+        sorted.sort();
+        let mut expected = many;
+        expected.sort();
+        assert_eq!(sorted, expected);
+    }
+}
@@ -0,0 +1,108 @@
+use std::path::{Path, PathBuf};
+
+/// Sampling rate while profiling setup, in Hz
+#[cfg(unix)]
+const PROFILE_FREQUENCY: i32 = 1000;
+
+/// Where the flamegraph for `label` goes, given the `--profile` path
+///
+/// Runs that set up several watchers get one file per watcher, e.g.
+/// `setup-native-recursive.svg` for `--profile setup.svg`.
+pub fn profile_path(base: &Path, label: &str) -> PathBuf {
+    labelled_path(base, label, "svg")
+}
+
+/// `base` with `label` appended to its stem, keeping its extension or adding `extension`
+pub fn labelled_path(base: &Path, label: &str, extension: &str) -> PathBuf {
+    let slug: String = label
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let stem = base.file_stem().and_then(|s| s.to_str()).unwrap_or("profile");
+    let extension = base.extension().and_then(|s| s.to_str()).unwrap_or(extension);
+    base.with_file_name(format!("{}-{}.{}", stem, slug, extension))
+}
+
+/// Samples this process's stacks from `start` until `finish`, then writes a flamegraph
+#[cfg(unix)]
+pub struct SetupProfiler {
+    guard: pprof::ProfilerGuard<'static>,
+    output: PathBuf,
+}
+
+#[cfg(unix)]
+impl SetupProfiler {
+    /// Start sampling if `--profile` was given; failures are reported and profiling skipped
+    pub fn start(base: Option<&Path>, label: &str) -> Option<Self> {
+        let output = profile_path(base?, label);
+        match pprof::ProfilerGuardBuilder::default()
+            .frequency(PROFILE_FREQUENCY)
+            .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+            .build()
+        {
+            Ok(guard) => Some(Self { guard, output }),
+            Err(e) => {
+                eprintln!("   Could not start profiler: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Stop sampling and write the flamegraph
+    ///
+    /// Setup that finishes between two samples leaves nothing to draw, so no file is written.
+    pub fn finish(self) {
+        let result = self.guard.report().build().and_then(|report| {
+            let samples: isize = report.data.values().sum();
+            if samples == 0 {
+                return Ok(0);
+            }
+            let file = std::fs::File::create(&self.output).map_err(pprof::Error::IoError)?;
+            report.flamegraph(file)?;
+            Ok(samples)
+        });
+        match result {
+            Ok(0) => println!("   Setup profile: no samples, setup was shorter than the sampling interval"),
+            Ok(samples) => println!("   Setup profile: {} samples → {}", samples, self.output.display()),
+            Err(e) => eprintln!("   Failed to write {}: {}", self.output.display(), e),
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub struct SetupProfiler;
+
+#[cfg(not(unix))]
+impl SetupProfiler {
+    pub fn start(base: Option<&Path>, _label: &str) -> Option<Self> {
+        if base.is_some() {
+            eprintln!("   --profile needs pprof, which is only available on unix");
+        }
+        None
+    }
+
+    pub fn finish(self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_path() {
+        assert_eq!(
+            profile_path(Path::new("out/setup.svg"), "Native Recursive"),
+            PathBuf::from("out/setup-native-recursive.svg")
+        );
+        assert_eq!(
+            profile_path(Path::new("flame"), "Manual Filtered"),
+            PathBuf::from("flame-manual-filtered.svg")
+        );
+        assert_eq!(
+            labelled_path(Path::new("tree.gv"), "Native Filtered", "dot"),
+            PathBuf::from("tree-native-filtered.gv")
+        );
+        assert_eq!(labelled_path(Path::new("tree"), "Poll", "dot"), PathBuf::from("tree-poll.dot"));
+    }
+}
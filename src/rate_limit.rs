@@ -0,0 +1,146 @@
+use notify::{Event, EventKind};
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Events a rate-limited callback may pass at once after a quiet spell by default
+pub const DEFAULT_RATE_BURST: u32 = 64;
+
+/// Token bucket settings for the callback: `rate` events per second, `burst` at once
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub rate: f64,
+    pub burst: u32,
+}
+
+/// What the limiter does with one event
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Admission {
+    /// A token was free
+    Now,
+    /// Held on notify's thread until its token comes due
+    After(Duration),
+    /// Discarded: the bucket is empty and the previous event was for the same paths
+    /// and kind, so the consumer learns nothing new from it
+    Merged,
+}
+
+struct BucketState {
+    /// Free tokens; negative while events are waiting on tokens already handed out
+    tokens: f64,
+    refilled: Instant,
+    /// Kind and paths of the last admitted event; the paths are copied into the
+    /// buffers already held, so admitting an event only allocates for longer paths
+    last: Option<(EventKind, Vec<PathBuf>)>,
+}
+
+/// Token bucket shared by every call of a watcher's callback
+pub struct TokenBucket {
+    limit: RateLimit,
+    state: Mutex<BucketState>,
+}
+
+impl TokenBucket {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            state: Mutex::new(BucketState {
+                tokens: limit.burst as f64,
+                refilled: Instant::now(),
+                last: None,
+            }),
+        }
+    }
+
+    /// Take a token for `event` arriving at `now`
+    ///
+    /// A late event reserves the next token and is told how long to wait for it,
+    /// so concurrent callers queue up in order instead of racing for refills.
+    pub fn admit(&self, event: &Event, now: Instant) -> Admission {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let elapsed = now.saturating_duration_since(state.refilled);
+        state.tokens = (state.tokens + elapsed.as_secs_f64() * self.limit.rate).min(self.limit.burst as f64);
+        state.refilled = state.refilled.max(now);
+
+        let repeat = state
+            .last
+            .as_ref()
+            .is_some_and(|(kind, paths)| *kind == event.kind && *paths == event.paths);
+        if state.tokens < 1.0 && repeat {
+            return Admission::Merged;
+        }
+        state.tokens -= 1.0;
+        match &mut state.last {
+            Some((kind, paths)) => {
+                *kind = event.kind;
+                paths.clone_from(&event.paths);
+            },
+            None => state.last = Some((event.kind, event.paths.clone())),
+        }
+        if state.tokens >= 0.0 {
+            Admission::Now
+        } else {
+            Admission::After(Duration::from_secs_f64(-state.tokens / self.limit.rate))
+        }
+    }
+}
+
+/// Events the rate limiter held back or merged
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimited {
+    pub delayed: u64,
+    pub merged: u64,
+    /// Time delayed events spent waiting for tokens, together
+    pub delay_time: Duration,
+}
+
+impl RateLimited {
+    /// Limiting done after `earlier` was taken
+    pub fn since(&self, earlier: &RateLimited) -> Self {
+        Self {
+            delayed: self.delayed.saturating_sub(earlier.delayed),
+            merged: self.merged.saturating_sub(earlier.merged),
+            delay_time: self.delay_time.saturating_sub(earlier.delay_time),
+        }
+    }
+
+    /// Print what the limiter did, if it did anything
+    pub fn report(&self, indent: &str) {
+        if self.delayed == 0 && self.merged == 0 {
+            return;
+        }
+        println!(
+            "{}Rate limited: {} delayed ({:.1?} waiting for tokens), {} merged",
+            indent, self.delayed, self.delay_time, self.merged
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let event = |path: &str| Event::new(EventKind::Any).add_path(PathBuf::from(path));
+        let bucket = TokenBucket::new(RateLimit { rate: 10.0, burst: 2 });
+        let start = Instant::now();
+        assert_eq!(bucket.admit(&event("a"), start), Admission::Now);
+        assert_eq!(bucket.admit(&event("b"), start), Admission::Now);
+        // Out of tokens: a repeat merges, a new path waits a token's worth
+        assert_eq!(bucket.admit(&event("b"), start), Admission::Merged);
+        let Admission::After(wait) = bucket.admit(&event("c"), start) else {
+            panic!("expected a delay");
+        };
+        assert!(wait.abs_diff(Duration::from_millis(100)) < Duration::from_millis(1));
+        let Admission::After(wait) = bucket.admit(&event("d"), start) else {
+            panic!("expected a delay");
+        };
+        assert!(wait.abs_diff(Duration::from_millis(200)) < Duration::from_millis(1));
+        // A second refills the burst, and no more
+        let later = start + Duration::from_secs(1);
+        assert_eq!(bucket.admit(&event("d"), later), Admission::Now);
+        assert_eq!(bucket.admit(&event("e"), later), Admission::Now);
+        assert!(matches!(bucket.admit(&event("f"), later), Admission::After(_)));
+    }
+}
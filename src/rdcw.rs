@@ -0,0 +1,275 @@
+use crate::harness::{prepare_scratch_dir, spawn_event_collector, watched_files, write_rounds};
+use crate::latency::{achieved_rate, match_writes};
+use crate::options::Options;
+use crate::recursive_file_watcher::{EventReceiver, WatchConfig, WatcherMode};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Buffer sizes swept by `buffer-sweep` unless `--buffer-sizes` is given; 16 KiB is notify's
+const DEFAULT_BUFFER_SIZES: [usize; 4] = [1024, 4096, 16384, 65536];
+
+/// Pause between appends at each step of the rate ladder, slowest first
+const WRITE_INTERVALS: [Duration; 3] = [Duration::from_millis(1), Duration::from_micros(100), Duration::ZERO];
+
+/// Files appended to per burst, and appends per file
+const BURST_FILES: usize = 200;
+const BURST_ROUNDS: usize = 5;
+
+#[cfg(windows)]
+mod backend {
+    use crate::recursive_file_watcher::{event_channel, EventReceiver, EventSink, WatchConfig};
+    use notify::event::{CreateKind, DataChange, ModifyKind, RemoveKind, RenameMode};
+    use notify::{Event, EventKind};
+    use std::io;
+    use std::os::windows::ffi::{OsStrExt, OsStringExt};
+    use std::path::{Path, PathBuf};
+    use std::thread::JoinHandle;
+    use windows_sys::Win32::Foundation::{CloseHandle, ERROR_NOTIFY_ENUM_DIR, HANDLE, INVALID_HANDLE_VALUE, WAIT_OBJECT_0};
+    use windows_sys::Win32::Storage::FileSystem::{
+        CreateFileW, ReadDirectoryChangesW, FILE_ACTION_ADDED, FILE_ACTION_MODIFIED, FILE_ACTION_REMOVED,
+        FILE_ACTION_RENAMED_NEW_NAME, FILE_ACTION_RENAMED_OLD_NAME, FILE_FLAG_BACKUP_SEMANTICS, FILE_FLAG_OVERLAPPED,
+        FILE_LIST_DIRECTORY, FILE_NOTIFY_CHANGE_ATTRIBUTES, FILE_NOTIFY_CHANGE_DIR_NAME, FILE_NOTIFY_CHANGE_FILE_NAME,
+        FILE_NOTIFY_CHANGE_LAST_WRITE, FILE_NOTIFY_CHANGE_SIZE, FILE_NOTIFY_INFORMATION, FILE_SHARE_DELETE,
+        FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING,
+    };
+    use windows_sys::Win32::System::Threading::{CreateEventW, ResetEvent, SetEvent, WaitForMultipleObjects, INFINITE};
+    use windows_sys::Win32::System::IO::{CancelIo, GetOverlappedResult, OVERLAPPED};
+
+    /// Same change classes notify's Windows backend asks for
+    const NOTIFY_FILTER: u32 = FILE_NOTIFY_CHANGE_FILE_NAME
+        | FILE_NOTIFY_CHANGE_DIR_NAME
+        | FILE_NOTIFY_CHANGE_ATTRIBUTES
+        | FILE_NOTIFY_CHANGE_SIZE
+        | FILE_NOTIFY_CHANGE_LAST_WRITE;
+
+    /// Handles shared with the reader thread
+    struct Handles {
+        dir: HANDLE,
+        stop: HANDLE,
+    }
+
+    // SAFETY: kernel handles may be used from any thread
+    unsafe impl Send for Handles {}
+
+    /// A recursive `ReadDirectoryChangesW` watch with a caller-chosen buffer size
+    pub struct BufferedWatcher {
+        stop: HANDLE,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl BufferedWatcher {
+        pub fn new(root: &Path, buffer_size: usize, watch: &WatchConfig) -> notify::Result<(Self, EventReceiver)> {
+            let wide: Vec<u16> = root.as_os_str().encode_wide().chain(Some(0)).collect();
+            // SAFETY: `wide` is NUL-terminated and outlives the call
+            let dir = unsafe {
+                CreateFileW(
+                    wide.as_ptr(),
+                    FILE_LIST_DIRECTORY,
+                    FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+                    std::ptr::null(),
+                    OPEN_EXISTING,
+                    FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OVERLAPPED,
+                    0,
+                )
+            };
+            if dir == INVALID_HANDLE_VALUE {
+                return Err(notify::Error::io(io::Error::last_os_error()).add_path(root.to_path_buf()));
+            }
+            // SAFETY: manual-reset, initially unsignalled, unnamed
+            let stop = unsafe { CreateEventW(std::ptr::null(), 1, 0, std::ptr::null()) };
+            let (sink, rx) = event_channel(watch);
+            let handles = Handles { dir, stop };
+            let root = root.to_path_buf();
+            let thread = std::thread::Builder::new()
+                .name("buffer-sweep reader".to_string())
+                .spawn(move || read_changes(handles, root, buffer_size, sink))?;
+            Ok((
+                Self {
+                    stop,
+                    thread: Some(thread),
+                },
+                rx,
+            ))
+        }
+    }
+
+    impl Drop for BufferedWatcher {
+        fn drop(&mut self) {
+            unsafe { SetEvent(self.stop) };
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+            unsafe { CloseHandle(self.stop) };
+        }
+    }
+
+    /// Reissue `ReadDirectoryChangesW` until stopped, reporting a zero-byte completion as overflow
+    fn read_changes(handles: Handles, root: PathBuf, buffer_size: usize, sink: EventSink) {
+        // The buffer must be DWORD-aligned
+        let mut buffer = vec![0u32; buffer_size.div_ceil(4)];
+        // SAFETY: manual-reset, initially unsignalled, unnamed
+        let io_event = unsafe { CreateEventW(std::ptr::null(), 1, 0, std::ptr::null()) };
+        loop {
+            // SAFETY: an all-zero OVERLAPPED is the documented initial state
+            let mut overlapped: OVERLAPPED = unsafe { std::mem::zeroed() };
+            overlapped.hEvent = io_event;
+            // SAFETY: buffer and overlapped stay alive until the read completes or is cancelled below
+            let issued = unsafe {
+                ReadDirectoryChangesW(
+                    handles.dir,
+                    buffer.as_mut_ptr().cast(),
+                    (buffer.len() * 4) as u32,
+                    1,
+                    NOTIFY_FILTER,
+                    std::ptr::null_mut(),
+                    &mut overlapped,
+                    None,
+                )
+            };
+            if issued == 0 {
+                sink.send(Err(notify::Error::io(io::Error::last_os_error())));
+                break;
+            }
+
+            let waits = [io_event, handles.stop];
+            let woken = unsafe { WaitForMultipleObjects(2, waits.as_ptr(), 0, INFINITE) };
+            let mut bytes = 0u32;
+            if woken != WAIT_OBJECT_0 {
+                unsafe {
+                    CancelIo(handles.dir);
+                    GetOverlappedResult(handles.dir, &overlapped, &mut bytes, 1);
+                }
+                break;
+            }
+            let ok = unsafe { GetOverlappedResult(handles.dir, &overlapped, &mut bytes, 0) };
+            let overflowed = if ok == 0 {
+                let err = io::Error::last_os_error();
+                if err.raw_os_error() != Some(ERROR_NOTIFY_ENUM_DIR as i32) {
+                    sink.send(Err(notify::Error::io(err)));
+                    break;
+                }
+                true
+            } else {
+                bytes == 0
+            };
+            if overflowed {
+                let overflow = io::Error::from_raw_os_error(ERROR_NOTIFY_ENUM_DIR as i32);
+                sink.send(Err(notify::Error::io(overflow).add_path(root.clone())));
+            } else {
+                // SAFETY: the kernel filled `bytes` bytes of the buffer with FILE_NOTIFY_INFORMATION records
+                unsafe { deliver(buffer.as_ptr().cast(), &root, &sink) };
+            }
+            unsafe { ResetEvent(io_event) };
+        }
+        unsafe {
+            CloseHandle(io_event);
+            CloseHandle(handles.dir);
+        }
+    }
+
+    unsafe fn deliver(mut record: *const u8, root: &Path, sink: &EventSink) {
+        loop {
+            let info = &*(record as *const FILE_NOTIFY_INFORMATION);
+            let name = std::slice::from_raw_parts(info.FileName.as_ptr(), info.FileNameLength as usize / 2);
+            let path = root.join(std::ffi::OsString::from_wide(name));
+            let kind = match info.Action {
+                FILE_ACTION_ADDED => EventKind::Create(CreateKind::Any),
+                FILE_ACTION_REMOVED => EventKind::Remove(RemoveKind::Any),
+                FILE_ACTION_MODIFIED => EventKind::Modify(ModifyKind::Data(DataChange::Any)),
+                FILE_ACTION_RENAMED_OLD_NAME => EventKind::Modify(ModifyKind::Name(RenameMode::From)),
+                FILE_ACTION_RENAMED_NEW_NAME => EventKind::Modify(ModifyKind::Name(RenameMode::To)),
+                _ => EventKind::Any,
+            };
+            sink.send(Ok(Event::new(kind).add_path(path)));
+            if info.NextEntryOffset == 0 {
+                break;
+            }
+            record = record.add(info.NextEntryOffset as usize);
+        }
+    }
+}
+
+/// Start a buffered watch on `root`; only Windows has `ReadDirectoryChangesW`
+#[cfg(windows)]
+fn start_buffered(root: &Path, buffer_size: usize, watch: &WatchConfig) -> notify::Result<(Box<dyn Send>, EventReceiver)> {
+    let (watcher, rx) = backend::BufferedWatcher::new(root, buffer_size, watch)?;
+    Ok((Box::new(watcher), rx))
+}
+
+#[cfg(not(windows))]
+fn start_buffered(_root: &Path, _buffer_size: usize, _watch: &WatchConfig) -> notify::Result<(Box<dyn Send>, EventReceiver)> {
+    Err(notify::Error::generic("buffer-sweep needs Windows' ReadDirectoryChangesW"))
+}
+
+/// Sweep `ReadDirectoryChangesW` buffer sizes against rising write rates
+///
+/// When a burst fills the buffer before the reader reissues the call, Windows drops
+/// the whole batch and reports a zero-byte completion; the table shows at which
+/// rate each size starts overflowing and how many writes go unseen.
+pub fn run_buffer_sweep(dir: &Path, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    if !cfg!(windows) {
+        return Err("buffer-sweep needs Windows' ReadDirectoryChangesW".into());
+    }
+    println!("\n=== ReadDirectoryChangesW Buffer Sweep ===");
+    println!("Source directory: {}", dir.display());
+    let sizes = if options.buffer_sizes.is_empty() {
+        DEFAULT_BUFFER_SIZES.to_vec()
+    } else {
+        options.buffer_sizes.clone()
+    };
+
+    println!("\n1. Copying files to temporary directory...");
+    let tmp_dir = std::path::absolute(prepare_scratch_dir(dir, "buffer-sweep", options)?)?;
+    let targets: Vec<PathBuf> = watched_files(WatcherMode::Native, &tmp_dir, options)?.into_iter().take(BURST_FILES).collect();
+
+    println!("\n2. Running bursts at each buffer size...");
+    let mut rows = Vec::new();
+    for &size in &sizes {
+        for interval in WRITE_INTERVALS {
+            let (watcher, rx) = start_buffered(&tmp_dir, size, &options.watch_config())?;
+            let collect_duration = Duration::from_secs(3);
+            let event_rx = spawn_event_collector(rx, collect_duration, Duration::ZERO);
+            std::thread::sleep(options.stabilize);
+
+            let writes = write_rounds(&targets, BURST_ROUNDS, interval);
+            let collected = event_rx
+                .recv_timeout(collect_duration + Duration::from_secs(1))
+                .unwrap_or_default();
+            drop(watcher);
+
+            let missed = match_writes(&writes, &collected).unmatched;
+            let rate = achieved_rate(&writes);
+            println!(
+                "   {} B @ {:.0} writes/s: {} events, {} overflows, {} missed",
+                size,
+                rate,
+                collected.events.len(),
+                collected.overflow_errors,
+                missed
+            );
+            rows.push((size, rate, writes.len(), collected, missed));
+        }
+    }
+
+    println!("\n📊 Buffer size sweep:");
+    println!(
+        "  {:>8} {:>12} {:>8} {:>8} {:>10} {:>8}",
+        "Buffer", "Writes/s", "Writes", "Events", "Overflows", "Missed"
+    );
+    for (size, rate, writes, collected, missed) in &rows {
+        println!(
+            "  {:>8} {:>12.0} {:>8} {:>8} {:>10} {:>8}",
+            size,
+            rate,
+            writes,
+            collected.events.len(),
+            collected.overflow_errors,
+            missed
+        );
+    }
+
+    println!("\n3. Cleaning up...");
+    fs::remove_dir_all(&tmp_dir)?;
+    Ok(())
+}
@@ -1,98 +1,1242 @@
-use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
-use std::collections::HashSet;
+use crate::faults::{Fault, FaultInjector, FaultPlan, InjectedFaults, INJECTED_ERROR};
+use crate::rate_limit::{Admission, RateLimit, RateLimited, TokenBucket};
+use crate::stats::{is_rescan, Backpressure, ChannelOverhead};
+use futures::Stream;
+use notify::{Config, ErrorKind, Event, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use notify_debouncer_full::{new_debouncer, DebounceEventResult, Debouncer, FileIdMap};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::mpsc;
-use std::time::Instant;
-
-/// Recursively collect all files in a directory
-/// Returns a vector of PathBuf for all files found
-pub fn collect_files_recursive(dir: &Path) -> Vec<PathBuf> {
-    let mut files = Vec::new();
-    collect_files_recursive_impl(dir, &mut files);
-    files
-}
-
-/// Helper function to recursively collect files
-fn collect_files_recursive_impl(dir: &Path, files: &mut Vec<PathBuf>) {
-    if let Ok(entries) = fs::read_dir(dir) {
-        for entry in entries.filter_map(Result::ok) {
-            let path = entry.path();
-            if path.is_dir() {
-                // Recurse into subdirectory
-                collect_files_recursive_impl(&path, files);
-            } else if path.is_file() {
-                // Add file to the collection
-                files.push(path);
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::error::TryRecvError;
+
+/// Lazily walk the files below `dir`
+///
+/// Each call to `next` reads only as far as the next file, so a consumer can act on
+/// files, e.g. register watches, while the rest of the tree is still being listed.
+///
+/// The walk keeps its own stack of pending directory listings instead of recursing,
+/// so pathologically deep trees can't overflow the thread's stack. Each listing is
+/// read in full before descending, which keeps one descriptor open at a time while
+/// still visiting a subdirectory's files where that subdirectory appears.
+pub fn walk_files(dir: &Path) -> FileWalk {
+    FileWalk::new(dir, false)
+}
+
+/// Like `walk_files`, visiting each directory's entries in name order
+///
+/// The order `read_dir` returns depends on the filesystem, so only a sorted walk
+/// lists the same tree identically on every run and platform.
+pub fn walk_files_sorted(dir: &Path) -> FileWalk {
+    FileWalk::new(dir, true)
+}
+
+/// Identity of a directory however it's reached: device and inode number
+#[cfg(unix)]
+type DirId = (u64, u64);
+
+#[cfg(unix)]
+fn dir_id(_dir: &Path, metadata: &fs::Metadata) -> Option<DirId> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+/// Identity of a directory however it's reached: its canonical path
+#[cfg(not(unix))]
+type DirId = PathBuf;
+
+#[cfg(not(unix))]
+fn dir_id(dir: &Path, _metadata: &fs::Metadata) -> Option<DirId> {
+    fs::canonicalize(dir).ok()
+}
+
+/// Iterator over the files of a tree, see `walk_files`
+///
+/// A directory that is also one of its own ancestors, reached through a symlink,
+/// is recorded as a loop and not entered, so cyclic links can't trap the walk. One
+/// reached a second time some other way, e.g. a link to a sibling, is recorded as a
+/// duplicate and not entered either, so no file is listed twice.
+pub struct FileWalk {
+    /// Each directory being walked and its remaining entries, innermost last
+    stack: Vec<(Option<DirId>, std::vec::IntoIter<PathBuf>)>,
+    /// Every directory entered so far
+    visited: HashSet<DirId>,
+    /// What couldn't be read so far; those parts of the tree are skipped
+    errors: Vec<WalkError>,
+    /// Links back into a directory already being walked
+    loops: Vec<PathBuf>,
+    /// Links to a directory already walked elsewhere
+    duplicates: Vec<PathBuf>,
+    /// Whether each listing is sorted by name before it's visited
+    sorted: bool,
+}
+
+impl FileWalk {
+    fn new(dir: &Path, sorted: bool) -> Self {
+        let mut walk = Self {
+            stack: Vec::new(),
+            visited: HashSet::new(),
+            errors: Vec::new(),
+            loops: Vec::new(),
+            duplicates: Vec::new(),
+            sorted,
+        };
+        let id = fs::metadata(dir).ok().and_then(|metadata| dir_id(dir, &metadata));
+        walk.visited.extend(id.iter().cloned());
+        walk.descend(dir, id);
+        walk
+    }
+
+    /// Up to `limit` of the remaining files, together with everything the walk had to
+    /// skip on the way; the walk stops as soon as the limit is reached
+    pub fn list_up_to(mut self, limit: usize) -> FileListing {
+        let files = self.by_ref().take(limit).collect();
+        FileListing {
+            files,
+            errors: self.errors,
+            loops: self.loops,
+            duplicates: self.duplicates,
+        }
+    }
+
+    /// Read `dir`'s entries in full and make them the next to visit
+    fn descend(&mut self, dir: &Path, id: Option<DirId>) {
+        let mut entries = Vec::new();
+        match fs::read_dir(dir) {
+            Ok(listing) => {
+                for entry in listing {
+                    match entry {
+                        Ok(entry) => entries.push(entry.path()),
+                        Err(e) => self.errors.push(WalkError::new(dir, WalkErrorSite::Entry, &e)),
+                    }
+                }
+            },
+            Err(e) => self.errors.push(WalkError::new(dir, WalkErrorSite::Directory, &e)),
+        }
+        if self.sorted {
+            entries.sort();
+        }
+        self.stack.push((id, entries.into_iter()));
+    }
+}
+
+impl Iterator for FileWalk {
+    type Item = PathBuf;
+
+    fn next(&mut self) -> Option<PathBuf> {
+        while let Some((_, entries)) = self.stack.last_mut() {
+            let Some(path) = entries.next() else {
+                self.stack.pop();
+                continue;
+            };
+            // Follows symlinks, like `is_dir` and `is_file`, in a single stat
+            let Ok(metadata) = fs::metadata(&path) else {
+                continue;
+            };
+            if metadata.is_dir() {
+                let id = dir_id(&path, &metadata);
+                if id.is_some() && self.stack.iter().any(|(ancestor, _)| *ancestor == id) {
+                    self.loops.push(path);
+                } else if id.as_ref().is_some_and(|id| !self.visited.insert(id.to_owned())) {
+                    self.duplicates.push(path);
+                } else {
+                    // Descend into subdirectory
+                    self.descend(&path, id);
+                }
+            } else if metadata.is_file() {
+                return Some(path);
             }
         }
+        None
+    }
+}
+
+/// Where in a directory listing the walk failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalkErrorSite {
+    /// The directory couldn't be opened, so nothing below it was seen
+    Directory,
+    /// One entry of an opened directory couldn't be read
+    Entry,
+}
+
+/// Part of a tree the walk couldn't read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalkError {
+    /// The directory being listed
+    pub path: PathBuf,
+    pub site: WalkErrorSite,
+    pub kind: std::io::ErrorKind,
+    pub message: String,
+}
+
+impl WalkError {
+    fn new(path: &Path, site: WalkErrorSite, error: &std::io::Error) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            site,
+            kind: error.kind(),
+            message: error.to_string(),
+        }
+    }
+}
+
+/// The result of walking a tree: its files and the parts that couldn't be read
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileListing {
+    pub files: Vec<PathBuf>,
+    pub errors: Vec<WalkError>,
+    /// Symlinked directories skipped because they lead back to one of their ancestors
+    pub loops: Vec<PathBuf>,
+    /// Directories skipped because the walk had already listed them by another path
+    pub duplicates: Vec<PathBuf>,
+}
+
+impl FileListing {
+    /// Directories whose contents were missed entirely
+    pub fn unreadable_dirs(&self) -> usize {
+        self.errors.iter().filter(|e| e.site == WalkErrorSite::Directory).count()
+    }
+
+    /// Skips, of either kind, caused by missing permissions
+    pub fn permission_errors(&self) -> usize {
+        self.errors.iter().filter(|e| e.kind == std::io::ErrorKind::PermissionDenied).count()
+    }
+}
+
+/// How often the poll watcher rescans when no interval is given
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How long the debounced watcher waits for a path to go quiet by default
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// Options shared by all watcher types
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchConfig {
+    /// Bound the event channel to this many queued events; unbounded when `None`
+    ///
+    /// By default a full bounded channel blocks notify's event thread, which in turn
+    /// lets the kernel queue fill up — the way a slow consumer behaves in a real tool.
+    pub channel_capacity: Option<usize>,
+    /// Channel implementation carrying events from notify's thread to the consumer
+    pub channel: ChannelKind,
+    /// What the callback does when a bounded channel is full
+    pub on_full: FullPolicy,
+    /// Rescan interval of the poll watcher
+    pub poll_interval: Duration,
+    /// Quiet period the debounced watcher waits for before reporting a path
+    pub debounce: Duration,
+    /// Directory names whose events the callback discards, like a dev tool's ignore list
+    pub exclude: Vec<String>,
+    /// Poll any root the native backend refuses to watch instead of failing setup
+    pub poll_fallback: bool,
+    /// Times manual setup retries a file whose watch failed transiently before skipping it
+    pub watch_retries: u32,
+    /// Wait before the first retry; doubled for each later one up to `MAX_RETRY_BACKOFF`
+    pub retry_backoff: Duration,
+    /// Faults the callback injects into events before queueing them
+    pub faults: Option<FaultPlan>,
+    /// Token bucket the callback passes events through before queueing them
+    pub rate_limit: Option<RateLimit>,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            channel_capacity: None,
+            channel: ChannelKind::default(),
+            on_full: FullPolicy::default(),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            debounce: DEFAULT_DEBOUNCE,
+            exclude: Vec::new(),
+            poll_fallback: true,
+            watch_retries: DEFAULT_WATCH_RETRIES,
+            retry_backoff: DEFAULT_RETRY_BACKOFF,
+            faults: None,
+            rate_limit: None,
+        }
+    }
+}
+
+/// How the watcher callback handles a full bounded channel
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FullPolicy {
+    /// Wait for room, stalling notify's event thread
+    #[default]
+    Block,
+    /// Discard the item and carry on
+    Drop,
+}
+
+impl FullPolicy {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "block" => Some(Self::Block),
+            "drop" => Some(Self::Drop),
+            _ => None,
+        }
+    }
+}
+
+/// Channel implementation used for a watcher's event pipeline
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChannelKind {
+    /// `std::sync::mpsc`
+    #[default]
+    Std,
+    /// `crossbeam_channel`, which holds up better under contention
+    Crossbeam,
+    /// `tokio::sync::mpsc`, for consumers running on an async runtime
+    Tokio,
+}
+
+impl ChannelKind {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "std" => Some(Self::Std),
+            "crossbeam" => Some(Self::Crossbeam),
+            "tokio" => Some(Self::Tokio),
+            _ => None,
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Std => "std",
+            Self::Crossbeam => "crossbeam",
+            Self::Tokio => "tokio",
+        }
+    }
+}
+
+/// One item from a watcher's event channel
+#[derive(Debug)]
+pub struct WatchEvent {
+    pub result: notify::Result<Event>,
+    /// When notify's callback handed the item over, before it was queued
+    pub emitted_at: Instant,
+}
+
+impl From<notify::Result<Event>> for WatchEvent {
+    fn from(result: notify::Result<Event>) -> Self {
+        Self {
+            result,
+            emitted_at: Instant::now(),
+        }
+    }
+}
+
+/// Number of items handed to a watcher's event channel but not yet received
+///
+/// `mpsc` can't report its length, so both ends keep this counter up to date. A
+/// send blocked on a full bounded channel already counts, so the depth can read
+/// slightly above the capacity.
+#[derive(Debug, Clone, Default)]
+pub struct QueueDepth(Arc<AtomicUsize>);
+
+impl QueueDepth {
+    pub fn get(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Time spent inside channel operations, shared by both ends
+#[derive(Debug, Default)]
+struct ChannelCounters {
+    sends: AtomicU64,
+    send_nanos: AtomicU64,
+    recvs: AtomicU64,
+    recv_nanos: AtomicU64,
+    /// Sends that found a bounded channel full and waited for room
+    blocked: AtomicU64,
+    blocked_nanos: AtomicU64,
+    /// Sends that found a bounded channel full and discarded the item
+    dropped: AtomicU64,
+    /// Events discarded because every path they name is excluded
+    excluded: AtomicU64,
+    injected_drops: AtomicU64,
+    injected_errors: AtomicU64,
+    injected_delays: AtomicU64,
+    injected_delay_nanos: AtomicU64,
+    /// Events the rate limiter held until a token came due
+    rate_delays: AtomicU64,
+    rate_delay_nanos: AtomicU64,
+    /// Events the rate limiter discarded as repeats of the one before
+    rate_merges: AtomicU64,
+}
+
+impl ChannelCounters {
+    fn add(count: &AtomicU64, nanos: &AtomicU64, elapsed: Duration) {
+        count.fetch_add(1, Ordering::Relaxed);
+        nanos.fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    fn overhead(&self) -> ChannelOverhead {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        ChannelOverhead {
+            sends: load(&self.sends),
+            send_time: Duration::from_nanos(load(&self.send_nanos)),
+            recvs: load(&self.recvs),
+            recv_time: Duration::from_nanos(load(&self.recv_nanos)),
+        }
+    }
+
+    fn injected(&self) -> InjectedFaults {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        InjectedFaults {
+            dropped: load(&self.injected_drops),
+            errors: load(&self.injected_errors),
+            delayed: load(&self.injected_delays),
+            delay_time: Duration::from_nanos(load(&self.injected_delay_nanos)),
+        }
+    }
+
+    fn rate_limited(&self) -> RateLimited {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        RateLimited {
+            delayed: load(&self.rate_delays),
+            merged: load(&self.rate_merges),
+            delay_time: Duration::from_nanos(load(&self.rate_delay_nanos)),
+        }
+    }
+
+    fn backpressure(&self) -> Backpressure {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        Backpressure {
+            sends: load(&self.sends),
+            blocked: load(&self.blocked),
+            blocked_time: Duration::from_nanos(load(&self.blocked_nanos)),
+            dropped: load(&self.dropped),
+        }
+    }
+}
+
+/// Receiving half of a watcher's event channel
+pub struct EventReceiver {
+    rx: ChannelReceiver,
+    depth: QueueDepth,
+    counters: Arc<ChannelCounters>,
+}
+
+enum ChannelReceiver {
+    Std(mpsc::Receiver<WatchEvent>),
+    Crossbeam(crossbeam_channel::Receiver<WatchEvent>),
+    /// Synchronous receives lock the receiver and poll it, since blocking on a
+    /// runtime would panic when the caller is already running inside one
+    Tokio(Mutex<TokioReceiver>),
+}
+
+/// How long a synchronous receive on a tokio channel sleeps between polls
+const TOKIO_POLL_INTERVAL: Duration = Duration::from_micros(100);
+
+impl ChannelReceiver {
+    fn try_recv(&self) -> Option<WatchEvent> {
+        match self {
+            Self::Std(rx) => rx.try_recv().ok(),
+            Self::Crossbeam(rx) => rx.try_recv().ok(),
+            Self::Tokio(rx) => rx.lock().unwrap_or_else(PoisonError::into_inner).try_recv().ok(),
+        }
+    }
+
+    fn recv_timeout(&self, timeout: Duration) -> Result<WatchEvent, mpsc::RecvTimeoutError> {
+        match self {
+            Self::Std(rx) => rx.recv_timeout(timeout),
+            Self::Crossbeam(rx) => rx.recv_timeout(timeout).map_err(|e| match e {
+                crossbeam_channel::RecvTimeoutError::Timeout => mpsc::RecvTimeoutError::Timeout,
+                crossbeam_channel::RecvTimeoutError::Disconnected => {
+                    mpsc::RecvTimeoutError::Disconnected
+                }
+            }),
+            Self::Tokio(rx) => {
+                let deadline = Instant::now() + timeout;
+                let mut rx = rx.lock().unwrap_or_else(PoisonError::into_inner);
+                loop {
+                    match rx.try_recv() {
+                        Ok(item) => return Ok(item),
+                        Err(TryRecvError::Disconnected) => return Err(mpsc::RecvTimeoutError::Disconnected),
+                        Err(TryRecvError::Empty) => {}
+                    }
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        return Err(mpsc::RecvTimeoutError::Timeout);
+                    }
+                    std::thread::sleep(TOKIO_POLL_INTERVAL.min(left));
+                }
+            }
+        }
+    }
+
+    fn recv(&self) -> Result<WatchEvent, mpsc::RecvError> {
+        match self {
+            Self::Std(rx) => rx.recv(),
+            Self::Crossbeam(rx) => rx.recv().map_err(|_| mpsc::RecvError),
+            Self::Tokio(_) => loop {
+                match self.recv_timeout(TOKIO_POLL_INTERVAL) {
+                    Ok(item) => return Ok(item),
+                    Err(mpsc::RecvTimeoutError::Timeout) => {}
+                    Err(mpsc::RecvTimeoutError::Disconnected) => return Err(mpsc::RecvError),
+                }
+            },
+        }
+    }
+}
+
+impl EventReceiver {
+    /// Block until an item arrives, or fail once the watcher is gone
+    pub fn recv(&self) -> Result<WatchEvent, mpsc::RecvError> {
+        let item = match self.try_recv_timed() {
+            Some(item) => item,
+            None => self.rx.recv()?,
+        };
+        self.depth.0.fetch_sub(1, Ordering::Relaxed);
+        Ok(item)
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<WatchEvent, mpsc::RecvTimeoutError> {
+        let item = match self.try_recv_timed() {
+            Some(item) => item,
+            None => self.rx.recv_timeout(timeout)?,
+        };
+        self.depth.0.fetch_sub(1, Ordering::Relaxed);
+        Ok(item)
+    }
+
+    /// Take an already-queued item, timing the receive
+    ///
+    /// Only receives that didn't have to wait are timed, so the recv overhead is
+    /// the channel's own cost rather than time spent waiting for events.
+    fn try_recv_timed(&self) -> Option<WatchEvent> {
+        let start = Instant::now();
+        let item = self.rx.try_recv()?;
+        ChannelCounters::add(&self.counters.recvs, &self.counters.recv_nanos, start.elapsed());
+        Some(item)
+    }
+
+    /// Handle for observing how many items are queued, e.g. from a sampler thread
+    pub fn depth(&self) -> QueueDepth {
+        self.depth.clone()
+    }
+
+    /// Time spent in channel operations since the watcher was created
+    pub fn overhead(&self) -> ChannelOverhead {
+        self.counters.overhead()
+    }
+
+    /// How often the callback found a bounded channel full since the watcher was created
+    pub fn backpressure(&self) -> Backpressure {
+        self.counters.backpressure()
+    }
+
+    /// Events the exclude list kept from the consumer since the watcher was created
+    pub fn excluded(&self) -> u64 {
+        self.counters.excluded.load(Ordering::Relaxed)
+    }
+
+    /// Faults injected into this channel's events since the watcher was created
+    pub fn injected(&self) -> InjectedFaults {
+        self.counters.injected()
+    }
+
+    /// Events the rate limiter delayed or merged since the watcher was created
+    pub fn rate_limited(&self) -> RateLimited {
+        self.counters.rate_limited()
+    }
+
+    /// Switch to async receiving; `None` unless this is a `ChannelKind::Tokio` channel
+    pub fn into_async(self) -> Option<AsyncEventReceiver> {
+        match self.rx {
+            ChannelReceiver::Tokio(rx) => Some(AsyncEventReceiver {
+                rx: rx.into_inner().unwrap_or_else(PoisonError::into_inner),
+                depth: self.depth,
+                counters: self.counters,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Async receiving half of a `ChannelKind::Tokio` event channel
+pub struct AsyncEventReceiver {
+    rx: TokioReceiver,
+    depth: QueueDepth,
+    counters: Arc<ChannelCounters>,
+}
+
+impl AsyncEventReceiver {
+    /// Wait for the next item, or `None` once the watcher is gone
+    pub async fn recv(&mut self) -> Option<WatchEvent> {
+        let start = Instant::now();
+        let item = match self.rx.try_recv() {
+            Ok(item) => {
+                ChannelCounters::add(&self.counters.recvs, &self.counters.recv_nanos, start.elapsed());
+                item
+            }
+            Err(_) => self.rx.recv().await?,
+        };
+        self.depth.0.fetch_sub(1, Ordering::Relaxed);
+        Some(item)
+    }
+
+    pub fn depth(&self) -> QueueDepth {
+        self.depth.clone()
+    }
+
+    pub fn overhead(&self) -> ChannelOverhead {
+        self.counters.overhead()
+    }
+}
+
+/// Stream of a watcher's events that keeps the watcher alive until it's dropped
+pub fn event_stream<W>(
+    watcher: W,
+    rx: AsyncEventReceiver,
+) -> impl Stream<Item = notify::Result<Event>> {
+    futures::stream::unfold((watcher, rx), |(watcher, mut rx)| async move {
+        let item = rx.recv().await?;
+        Some((item.result, (watcher, rx)))
+    })
+}
+
+enum TokioReceiver {
+    Unbounded(tokio::sync::mpsc::UnboundedReceiver<WatchEvent>),
+    Bounded(tokio::sync::mpsc::Receiver<WatchEvent>),
+}
+
+impl TokioReceiver {
+    fn try_recv(&mut self) -> Result<WatchEvent, TryRecvError> {
+        match self {
+            Self::Unbounded(rx) => rx.try_recv(),
+            Self::Bounded(rx) => rx.try_recv(),
+        }
+    }
+
+    async fn recv(&mut self) -> Option<WatchEvent> {
+        match self {
+            Self::Unbounded(rx) => rx.recv().await,
+            Self::Bounded(rx) => rx.recv().await,
+        }
+    }
+}
+
+/// Sending half of a watcher's event channel
+pub(crate) struct EventSink {
+    tx: SinkSender,
+    depth: QueueDepth,
+    counters: Arc<ChannelCounters>,
+    on_full: FullPolicy,
+    exclude: Vec<String>,
+    faults: Option<FaultInjector>,
+    rate_limit: Option<TokenBucket>,
+}
+
+enum SinkSender {
+    Unbounded(mpsc::Sender<WatchEvent>),
+    Bounded(mpsc::SyncSender<WatchEvent>),
+    Crossbeam(crossbeam_channel::Sender<WatchEvent>),
+    TokioUnbounded(tokio::sync::mpsc::UnboundedSender<WatchEvent>),
+    TokioBounded(tokio::sync::mpsc::Sender<WatchEvent>),
+}
+
+impl EventSink {
+    /// Stamp `res` with the current time and queue it, unless an injected fault or the
+    /// rate limiter says otherwise
+    pub(crate) fn send(&self, res: notify::Result<Event>) {
+        self.send_normalized(res.map(without_extended_prefixes));
+    }
+
+    /// `send` for a result whose paths already had their extended-length prefixes stripped
+    pub(crate) fn send_normalized(&self, res: notify::Result<Event>) {
+        let mut item = WatchEvent::from(res);
+        if let (Some(faults), Ok(_)) = (&self.faults, &item.result) {
+            match faults.draw() {
+                Some(Fault::Drop) => {
+                    self.counters.injected_drops.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+                Some(Fault::Error) => {
+                    self.counters.injected_errors.fetch_add(1, Ordering::Relaxed);
+                    item.result = Err(notify::Error::generic(INJECTED_ERROR));
+                }
+                // Held on notify's thread after stamping, so the delay shows up as queue time
+                Some(Fault::Delay(delay)) => {
+                    ChannelCounters::add(&self.counters.injected_delays, &self.counters.injected_delay_nanos, delay);
+                    std::thread::sleep(delay);
+                }
+                None => {}
+            }
+        }
+        if let (Some(bucket), Ok(event)) = (&self.rate_limit, &item.result) {
+            match bucket.admit(event, Instant::now()) {
+                Admission::Now => {}
+                // Like a blocking send, waiting here stalls notify's thread rather than the consumer
+                Admission::After(wait) => {
+                    ChannelCounters::add(&self.counters.rate_delays, &self.counters.rate_delay_nanos, wait);
+                    std::thread::sleep(wait);
+                }
+                Admission::Merged => {
+                    self.counters.rate_merges.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+            }
+        }
+        self.forward(item);
+    }
+
+    /// Queue an item another channel already stamped, keeping its emit time
+    pub(crate) fn forward(&self, item: WatchEvent) {
+        if let Ok(event) = &item.result {
+            let excluded = |path: &PathBuf| is_excluded(path, &self.exclude);
+            if !self.exclude.is_empty() && !event.paths.is_empty() && event.paths.iter().all(excluded) {
+                self.counters.excluded.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+        }
+        // Count before sending so the receiver never sees the counter go negative
+        self.depth.0.fetch_add(1, Ordering::Relaxed);
+        let start = Instant::now();
+        let sent = match &self.tx {
+            SinkSender::Unbounded(tx) => tx.send(item).is_ok(),
+            SinkSender::Bounded(tx) => match tx.try_send(item) {
+                Ok(()) => true,
+                Err(mpsc::TrySendError::Full(item)) => self.on_full(|| tx.send(item).is_ok()),
+                Err(mpsc::TrySendError::Disconnected(_)) => false,
+            },
+            SinkSender::Crossbeam(tx) => match tx.try_send(item) {
+                Ok(()) => true,
+                Err(crossbeam_channel::TrySendError::Full(item)) => {
+                    self.on_full(|| tx.send(item).is_ok())
+                }
+                Err(crossbeam_channel::TrySendError::Disconnected(_)) => false,
+            },
+            SinkSender::TokioUnbounded(tx) => tx.send(item).is_ok(),
+            // notify's callback thread isn't on a runtime, so blocking_send is fine here
+            SinkSender::TokioBounded(tx) => match tx.try_send(item) {
+                Ok(()) => true,
+                Err(tokio::sync::mpsc::error::TrySendError::Full(item)) => {
+                    self.on_full(|| tx.blocking_send(item).is_ok())
+                }
+                Err(tokio::sync::mpsc::error::TrySendError::Closed(_)) => false,
+            },
+        };
+        ChannelCounters::add(&self.counters.sends, &self.counters.send_nanos, start.elapsed());
+        // Send errors just mean the receiver was dropped
+        if !sent {
+            self.depth.0.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Apply the full-channel policy, with `send` performing the blocking send
+    fn on_full(&self, send: impl FnOnce() -> bool) -> bool {
+        match self.on_full {
+            FullPolicy::Block => {
+                let start = Instant::now();
+                let sent = send();
+                ChannelCounters::add(&self.counters.blocked, &self.counters.blocked_nanos, start.elapsed());
+                sent
+            }
+            FullPolicy::Drop => {
+                self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+                false
+            }
+        }
+    }
+}
+
+/// Create the event channel described by `config`
+pub(crate) fn event_channel(config: &WatchConfig) -> (EventSink, EventReceiver) {
+    let (tx, rx) = match (config.channel, config.channel_capacity) {
+        (ChannelKind::Std, Some(capacity)) => {
+            let (tx, rx) = mpsc::sync_channel(capacity);
+            (SinkSender::Bounded(tx), ChannelReceiver::Std(rx))
+        }
+        (ChannelKind::Std, None) => {
+            let (tx, rx) = mpsc::channel();
+            (SinkSender::Unbounded(tx), ChannelReceiver::Std(rx))
+        }
+        (ChannelKind::Crossbeam, capacity) => {
+            let (tx, rx) = match capacity {
+                Some(capacity) => crossbeam_channel::bounded(capacity),
+                None => crossbeam_channel::unbounded(),
+            };
+            (SinkSender::Crossbeam(tx), ChannelReceiver::Crossbeam(rx))
+        }
+        (ChannelKind::Tokio, capacity) => {
+            let (tx, rx) = match capacity {
+                Some(capacity) => {
+                    let (tx, rx) = tokio::sync::mpsc::channel(capacity);
+                    (SinkSender::TokioBounded(tx), TokioReceiver::Bounded(rx))
+                }
+                None => {
+                    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+                    (SinkSender::TokioUnbounded(tx), TokioReceiver::Unbounded(rx))
+                }
+            };
+            (tx, ChannelReceiver::Tokio(Mutex::new(rx)))
+        }
+    };
+    let depth = QueueDepth::default();
+    let counters = Arc::new(ChannelCounters::default());
+    (
+        EventSink {
+            tx,
+            depth: depth.clone(),
+            counters: counters.clone(),
+            on_full: config.on_full,
+            exclude: config.exclude.clone(),
+            faults: config.faults.map(FaultInjector::new),
+            rate_limit: config.rate_limit.map(TokenBucket::new),
+        },
+        EventReceiver { rx, depth, counters },
+    )
+}
+
+/// Whether any component of `path` is one of the `exclude` directory names
+pub fn is_excluded(path: &Path, exclude: &[String]) -> bool {
+    path.components().any(|component| exclude.iter().any(|name| component.as_os_str() == name.as_str()))
+}
+
+/// Make `path` absolute the same way notify does before it reports event paths
+fn absolute_path(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Windows' `MAX_PATH`, counted in UTF-16 units including the terminating NUL
+///
+/// std adds the extended-length prefix itself, but notify hands watched paths to
+/// `CreateFileW` unchanged, so watches on longer paths fail unless we add it.
+#[cfg_attr(not(windows), allow(dead_code))]
+const MAX_PATH: usize = 260;
+
+/// `\\?\C:\...` or `\\?\UNC\server\share\...` for an absolute Windows path too long for `MAX_PATH`
+#[cfg_attr(not(windows), allow(dead_code))]
+fn add_extended_prefix(path: &str) -> Option<String> {
+    if path.encode_utf16().count() < MAX_PATH || path.starts_with(r"\\?\") {
+        return None;
+    }
+    // The kernel no longer normalizes separators once the prefix is there
+    let path = path.replace('/', "\\");
+    Some(match path.strip_prefix(r"\\") {
+        Some(unc) => format!(r"\\?\UNC\{}", unc),
+        None => format!(r"\\?\{}", path),
+    })
+}
+
+/// Undo `add_extended_prefix`, so event paths compare equal to enumerated ones
+#[cfg_attr(not(windows), allow(dead_code))]
+fn strip_extended_prefix(path: &str) -> Option<String> {
+    if let Some(unc) = path.strip_prefix(r"\\?\UNC\") {
+        return Some(format!(r"\\{}", unc));
+    }
+    path.strip_prefix(r"\\?\").map(str::to_string)
+}
+
+/// The path to hand notify's `watch`, with the extended-length prefix where Windows needs one
+pub fn extended_length(path: &Path) -> Cow<'_, Path> {
+    #[cfg(windows)]
+    if let Some(prefixed) = absolute_path(path).to_str().and_then(add_extended_prefix) {
+        return Cow::Owned(PathBuf::from(prefixed));
+    }
+    Cow::Borrowed(path)
+}
+
+/// `path` without an extended-length prefix, as notify reports paths below a prefixed watch
+pub fn without_extended_prefix(path: &Path) -> Cow<'_, Path> {
+    #[cfg(windows)]
+    if let Some(stripped) = path.to_str().and_then(strip_extended_prefix) {
+        return Cow::Owned(PathBuf::from(stripped));
+    }
+    Cow::Borrowed(path)
+}
+
+/// `\\server\share` lowercased at the start of a UNC path, since Windows compares both case-insensitively
+#[cfg_attr(not(windows), allow(dead_code))]
+fn fold_unc_share(path: &str) -> Option<String> {
+    let rest = path.strip_prefix(r"\\").filter(|rest| !rest.starts_with(r"?\"))?;
+    // The share ends at the separator after the server's
+    let share_end = rest.match_indices('\\').nth(1).map_or(rest.len(), |(i, _)| i);
+    let share = &rest[..share_end];
+    if !share.chars().any(char::is_uppercase) {
+        return None;
+    }
+    Some(format!(r"\\{}{}", share.to_lowercase(), &rest[share_end..]))
+}
+
+/// Key for filter-set lookups, so a file matches however notify spelled its path
+///
+/// Drops any extended-length prefix and, for UNC paths, folds the case of the
+/// server and share names, which event paths don't always preserve.
+pub fn filter_key(path: &Path) -> Cow<'_, Path> {
+    let stripped = without_extended_prefix(path);
+    #[cfg(windows)]
+    if let Some(folded) = stripped.to_str().and_then(fold_unc_share) {
+        return Cow::Owned(PathBuf::from(folded));
+    }
+    stripped
+}
+
+/// Filter-set entry for a watched file: absolute, since notify reports absolute
+/// paths, and keyed the way event paths are looked up
+pub fn filter_entry(path: &Path) -> PathBuf {
+    filter_key(&absolute_path(path)).into_owned()
+}
+
+/// Files a filtered watcher passes events for, stored relative to their watch root
+/// with each directory's path stored once
+///
+/// A million absolute paths mostly repeat a few thousand directories under one
+/// deep root, so keeping only file names under their root-relative directory takes
+/// far less memory than one `PathBuf` per file, and the entries no longer depend on
+/// where the tree sits. Built once and shared with the event callback through an `Arc`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterSet {
+    /// Watch roots, keyed like event paths
+    roots: Vec<PathBuf>,
+    /// Per root, plus one last for files outside every root: directory below the root → file names
+    trees: Vec<HashMap<Box<Path>, HashSet<Box<OsStr>>>>,
+    len: usize,
+}
+
+impl FilterSet {
+    pub fn new(roots: &[PathBuf]) -> Self {
+        Self {
+            roots: roots.iter().map(|root| filter_entry(root)).collect(),
+            trees: vec![HashMap::new(); roots.len() + 1],
+            len: 0,
+        }
+    }
+
+    /// Set of the files among `files` that exist, borrowed or owned, keyed like event paths
+    pub fn from_files<I>(roots: &[PathBuf], files: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<Path>,
+    {
+        let mut set = Self::new(roots);
+        for path in files {
+            let path = path.as_ref();
+            if path.is_file() {
+                set.insert(&filter_entry(path));
+            }
+        }
+        set
+    }
+
+    /// The first root `path` lies under and the rest of the path; files outside every root keep their full path
+    fn split<'a>(&self, path: &'a Path) -> (usize, &'a Path) {
+        self.roots
+            .iter()
+            .enumerate()
+            .find_map(|(i, root)| path.strip_prefix(root).ok().map(|rest| (i, rest)))
+            .unwrap_or((self.roots.len(), path))
+    }
+
+    /// Add a file's absolute, keyed path; false if it was already present or has no file name
+    pub fn insert(&mut self, path: &Path) -> bool {
+        let (tree, path) = self.split(path);
+        let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+            return false;
+        };
+        let dirs = &mut self.trees[tree];
+        let names = match dirs.get_mut(dir) {
+            Some(names) => names,
+            None => dirs.entry(Box::from(dir)).or_default(),
+        };
+        let added = names.insert(Box::from(name));
+        self.len += added as usize;
+        added
+    }
+
+    /// Whether an event's keyed path is one of the set's files
+    pub fn contains(&self, path: &Path) -> bool {
+        let (tree, path) = self.split(path);
+        match (path.parent(), path.file_name()) {
+            (Some(dir), Some(name)) => self.trees[tree].get(dir).is_some_and(|names| names.contains(name)),
+            _ => false,
+        }
+    }
+
+    /// Files in the set
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the set's roots are `roots`, compared the way it keys them
+    pub fn has_roots(&self, roots: &[PathBuf]) -> bool {
+        self.roots.len() == roots.len() && self.roots.iter().zip(roots).all(|(own, root)| *own == filter_entry(root))
+    }
+
+    /// Move the set onto another copy of its roots, e.g. from the source tree to
+    /// its `./tmp` copy; entries are stored below their root, so only the roots change
+    ///
+    /// Files outside every root keep their absolute paths.
+    pub fn rebase(&mut self, roots: &[PathBuf]) -> Result<(), String> {
+        if roots.len() != self.roots.len() {
+            return Err(format!("cannot rebase a filter over {} roots onto {}", self.roots.len(), roots.len()));
+        }
+        self.roots = roots.iter().map(|root| filter_entry(root)).collect();
+        Ok(())
+    }
+}
+
+impl<P: AsRef<Path>> Extend<P> for FilterSet {
+    fn extend<I: IntoIterator<Item = P>>(&mut self, paths: I) {
+        for path in paths {
+            self.insert(path.as_ref());
+        }
+    }
+}
+
+/// Strip extended-length prefixes from every path in `event`
+fn without_extended_prefixes(mut event: Event) -> Event {
+    for path in &mut event.paths {
+        if let Cow::Owned(stripped) = without_extended_prefix(path) {
+            *path = stripped;
+        }
+    }
+    event
+}
+
+/// Callback passing on events about `filter`'s files, plus rescans and errors
+///
+/// Paths are normalized once, in place, and the event is then matched and moved
+/// into the channel without copying any of them.
+pub(crate) fn filter_handler(filter: Arc<FilterSet>, tx: EventSink) -> impl Fn(notify::Result<Event>) + Send + Sync {
+    move |res| {
+        let res = res.map(without_extended_prefixes);
+        let should_send = match &res {
+            // Check if any of the paths in the event are in our filter set
+            Ok(event) if !is_rescan(event) => event.paths.iter().any(|path| filter.contains(&filter_key(path))),
+            // Rescan notices and errors aren't about one path; always pass them on
+            _ => true,
+        };
+        if should_send {
+            tx.send_normalized(res);
+        }
+    }
+}
+
+/// Files a streamed walk may run ahead of registration by
+const STREAM_BUFFER: usize = 1024;
+
+/// Retries of a transiently failing watch call before the file is skipped
+pub const DEFAULT_WATCH_RETRIES: u32 = 3;
+
+/// Wait before the first retry of a failed watch call
+pub const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(1);
+
+/// Longest wait between two retries, however many are configured
+const MAX_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Whether a failed watch call is worth retrying, and skipping if it keeps failing
+///
+/// Only interrupted calls and a kernel briefly out of memory or queue space can
+/// succeed on a second try; the watch limit itself is left to the caller.
+fn is_transient(error: &notify::Error) -> bool {
+    match &error.kind {
+        ErrorKind::Io(e) => matches!(
+            e.kind(),
+            std::io::ErrorKind::Interrupted
+                | std::io::ErrorKind::WouldBlock
+                | std::io::ErrorKind::OutOfMemory
+                | std::io::ErrorKind::StorageFull
+        ),
+        _ => false,
+    }
+}
+
+/// Whether a failed watch call means the file can't be watched at all, so it's skipped without a retry
+///
+/// A file deleted between enumeration and registration fails like this, as does
+/// one the process isn't allowed to read.
+fn is_unwatchable(error: &notify::Error) -> bool {
+    match &error.kind {
+        ErrorKind::PathNotFound => true,
+        ErrorKind::Io(e) => matches!(
+            e.kind(),
+            std::io::ErrorKind::NotFound | std::io::ErrorKind::PermissionDenied
+        ),
+        _ => false,
     }
 }
 
 /// Manual recursive file watcher that watches each file individually
 pub struct ManualRecursiveWatcher {
     watcher: RecommendedWatcher,
-    receiver: mpsc::Receiver<notify::Result<Event>>,
+    receiver: EventReceiver,
     files_watched: usize,
+    files_requested: usize,
     setup_time: std::time::Duration,
+    /// Time each successful watch call took, retries included, in the order of `watched`
+    watch_times: Vec<Duration>,
+    /// Files with a watch still registered, in the order they were added
+    watched: Vec<PathBuf>,
+    /// Watch calls repeated after a transient failure
+    retries: usize,
+    /// Files given up on, because they can't be watched or every retry failed
+    skipped: Vec<PathBuf>,
+    /// Time slept between retries, left out of `setup_time` and `watch_times`
+    backoff_time: Duration,
+    /// Whether setup stopped at the OS watch limit
+    limit_reached: bool,
 }
 
 impl ManualRecursiveWatcher {
-    /// Create a new manual recursive watcher for the specified directory
-    pub fn new(dir: &Path) -> notify::Result<Self> {
-        // Collect all files recursively
-        let files = collect_files_recursive(dir);
-        Self::new_with_files(files)
+    /// Create a new manual recursive watcher for specific files with custom options
+    pub fn new_with_config<I>(files_to_watch: I, config: &WatchConfig) -> notify::Result<Self>
+    where
+        I: IntoIterator<Item = PathBuf>,
+    {
+        // Collect the files from the iterator
+        let files: Vec<PathBuf> = files_to_watch.into_iter().collect();
+
+        println!(
+            "ManualRecursiveWatcher: Watching {} specific files",
+            files.len()
+        );
+        Self::watch_each(files, config)
+    }
+
+    /// Create a manual watcher that registers each file as soon as `files` yields it
+    ///
+    /// `files`, typically a lazy walk such as `walk_files`, runs on its own thread and
+    /// feeds registration through a channel, so enumeration and registration overlap
+    /// instead of running one after the other. As with the other constructors the
+    /// setup time covers registration only; waiting on the walk is left out.
+    pub fn new_streaming<I>(files: I, config: &WatchConfig) -> notify::Result<Self>
+    where
+        I: IntoIterator<Item = PathBuf>,
+        I::IntoIter: Send + 'static,
+    {
+        println!("ManualRecursiveWatcher: Watching files as they are enumerated");
+        let (tx, rx) = std::sync::mpsc::sync_channel(STREAM_BUFFER);
+        let files = files.into_iter();
+        // Stops at the first send after the watcher is done and drops the receiver
+        std::thread::spawn(move || {
+            for file in files {
+                if tx.send(file).is_err() {
+                    break;
+                }
+            }
+        });
+        Self::watch_each(rx, config)
     }
 
-    /// Create a new manual recursive watcher for specific files
-    pub fn new_with_files<I>(files_to_watch: I) -> notify::Result<Self>
+    /// Add a non-recursive watch for each of `files` in turn
+    fn watch_each<I>(files: I, config: &WatchConfig) -> notify::Result<Self>
     where
         I: IntoIterator<Item = PathBuf>,
     {
         // Create a channel for receiving events
-        let (tx, rx) = mpsc::channel();
+        let (tx, rx) = event_channel(config);
 
         // Create the watcher with a custom config
         let mut watcher = RecommendedWatcher::new(
             move |res: notify::Result<Event>| {
-                let _ = tx.send(res);  // Ignore send errors when receiver is dropped
+                tx.send(res);
             },
             Config::default(),
         )?;
 
-        // Collect the files from the iterator
-        let files: Vec<PathBuf> = files_to_watch.into_iter().collect();
-        let files_count = files.len();
-
-        println!(
-            "ManualRecursiveWatcher: Watching {} specific files",
-            files_count
-        );
-
         // Add watch for each file individually (non-recursive mode)
         let start_watch = Instant::now();
-        for file_path in &files {
-            watcher.watch(file_path, RecursiveMode::NonRecursive)?;
+        let mut files = files.into_iter();
+        let (expected, _) = files.size_hint();
+        // Time spent waiting for `files` to yield, e.g. on a streamed walk
+        let mut waited = Duration::ZERO;
+        let mut files_count = 0;
+        let mut watched = Vec::with_capacity(expected);
+        let mut watch_times = Vec::with_capacity(expected);
+        let mut retries = 0;
+        let mut skipped = Vec::new();
+        let mut backoff_time = Duration::ZERO;
+        let mut limit_reached = false;
+        loop {
+            let start_next = Instant::now();
+            let Some(file_path) = files.next() else {
+                break;
+            };
+            let start_one = Instant::now();
+            waited += start_one - start_next;
+            files_count += 1;
+            let mut backoff = config.retry_backoff;
+            let mut slept = Duration::ZERO;
+            let mut attempt = 0;
+            let result = loop {
+                match watcher.watch(&extended_length(&file_path), RecursiveMode::NonRecursive) {
+                    Err(e) if is_transient(&e) && attempt < config.watch_retries => {
+                        attempt += 1;
+                        let start_sleep = Instant::now();
+                        std::thread::sleep(backoff);
+                        slept += start_sleep.elapsed();
+                        backoff = (backoff * 2).min(MAX_RETRY_BACKOFF);
+                    }
+                    result => break result,
+                }
+            };
+            retries += attempt as usize;
+            backoff_time += slept;
+            match result {
+                Ok(()) => {
+                    watch_times.push(start_one.elapsed().saturating_sub(slept));
+                    watched.push(file_path);
+                }
+                Err(e) if matches!(e.kind, ErrorKind::MaxFilesWatch) => {
+                    // Keep what we have instead of aborting; coverage is reported instead
+                    limit_reached = true;
+                    break;
+                }
+                Err(e) if is_transient(&e) || is_unwatchable(&e) => skipped.push(file_path),
+                Err(e) => return Err(e),
+            }
+        }
+        if limit_reached {
+            // The rest still counts as requested, so coverage shows what was missed;
+            // only what's known without draining it, as a lazy walk would run to the end
+            files_count += files.size_hint().0;
+            eprintln!(
+                "ManualRecursiveWatcher: Watch limit reached after {} of {} files, continuing with partial coverage",
+                watched.len(), files_count
+            );
         }
-        let watch_duration = start_watch.elapsed();
+        // Backoff and enumeration are waiting, not registering, so they're kept out of the setup time
+        let watch_duration = start_watch.elapsed().saturating_sub(backoff_time + waited);
+        let watched_count = watched.len();
 
         println!(
             "ManualRecursiveWatcher: Added watches for {} files in {:?}",
-            files_count, watch_duration
+            watched_count, watch_duration
         );
-        if files_count > 0 {
+        if watched_count > 0 {
             println!(
                 "ManualRecursiveWatcher: Average time per watch: {:?}",
-                watch_duration / files_count as u32
+                watch_duration / watched_count as u32
+            );
+        }
+        if retries > 0 || !skipped.is_empty() {
+            println!(
+                "ManualRecursiveWatcher: {} watch calls retried ({:?} backing off), {} files skipped",
+                retries,
+                backoff_time,
+                skipped.len()
             );
         }
 
         Ok(Self {
             watcher,
             receiver: rx,
-            files_watched: files_count,
+            files_watched: watched_count,
+            files_requested: files_count,
             setup_time: watch_duration,
+            watch_times,
+            watched,
+            retries,
+            skipped,
+            backoff_time,
+            limit_reached,
         })
     }
 
@@ -101,55 +1245,201 @@ impl ManualRecursiveWatcher {
         self.files_watched
     }
 
+    /// Get the number of files a watch was requested for
+    pub fn files_requested(&self) -> usize {
+        self.files_requested
+    }
+
+    /// Percentage of requested files that actually got a watch
+    pub fn coverage_percent(&self) -> f64 {
+        if self.files_requested == 0 {
+            100.0
+        } else {
+            self.files_watched as f64 / self.files_requested as f64 * 100.0
+        }
+    }
+
+    /// Whether some requested files ended up without a watch
+    pub fn is_partial(&self) -> bool {
+        self.files_watched < self.files_requested
+    }
+
+    /// Whether setup stopped early because the OS watch limit was reached
+    pub fn limit_reached(&self) -> bool {
+        self.limit_reached
+    }
+
+    /// Watch calls repeated after a transient failure
+    pub fn retries(&self) -> usize {
+        self.retries
+    }
+
+    /// Files skipped because they can't be watched or their watch kept failing transiently
+    pub fn skipped(&self) -> &[PathBuf] {
+        &self.skipped
+    }
+
+    /// Time slept between retries, which `setup_time` leaves out
+    pub fn backoff_time(&self) -> Duration {
+        self.backoff_time
+    }
+
+    /// Files that got a watch, in registration order; `watch_times` lines up with these
+    pub fn watched_files(&self) -> &[PathBuf] {
+        &self.watched
+    }
+
     /// Get the setup time for adding all watches
     pub fn setup_time(&self) -> std::time::Duration {
         self.setup_time
     }
 
-    /// Get the event receiver
-    pub fn receiver(&self) -> &mpsc::Receiver<notify::Result<Event>> {
-        &self.receiver
+    /// Time each successful watch took, in the order of `watched_files`
+    pub fn watch_times(&self) -> &[Duration] {
+        &self.watch_times
+    }
+
+    /// Remove every watch with its own `unwatch` call, returning how long each took
+    ///
+    /// A failed call doesn't stop the rest; its error takes its place in the result.
+    pub fn unwatch_all(&mut self) -> Vec<notify::Result<Duration>> {
+        std::mem::take(&mut self.watched)
+            .iter()
+            .map(|file_path| {
+                let start_one = Instant::now();
+                self.watcher.unwatch(&extended_length(file_path))?;
+                Ok(start_one.elapsed())
+            })
+            .collect()
     }
 
     /// Consume self and return the watcher and receiver
-    pub fn into_parts(self) -> (RecommendedWatcher, mpsc::Receiver<notify::Result<Event>>) {
+    pub fn into_parts(self) -> (RecommendedWatcher, EventReceiver) {
         (self.watcher, self.receiver)
     }
 }
 
+/// Add a recursive watch on each root, returning how long each one took
+fn watch_roots(watcher: &mut impl Watcher, roots: &[PathBuf]) -> notify::Result<Vec<Duration>> {
+    roots
+        .iter()
+        .map(|root| {
+            let start_watch = Instant::now();
+            watcher.watch(&extended_length(root), RecursiveMode::Recursive)?;
+            Ok(start_watch.elapsed())
+        })
+        .collect()
+}
+
+/// Whether a refused native watch is worth polling instead: limits and unsupported
+/// filesystems are, a missing path isn't
+fn falls_back_to_polling(error: &notify::Error) -> bool {
+    !matches!(error.kind, ErrorKind::PathNotFound | ErrorKind::WatchNotFound | ErrorKind::InvalidConfig(_))
+}
+
+/// Native watcher plus the poll watcher covering any roots it refused
+///
+/// Only held so both keep watching until dropped.
+pub struct NativeWatchers {
+    _native: RecommendedWatcher,
+    _fallback: Option<PollWatcher>,
+}
+
+/// Roots the native backend refused, each with its error, and the poll watcher covering them
+struct PollFallback {
+    watcher: Option<PollWatcher>,
+    roots: Vec<(PathBuf, String)>,
+}
+
+/// Add a native recursive watch on each root, returning how long each one took
+///
+/// With `config.poll_fallback`, a root the backend refuses (watch limit,
+/// unsupported filesystem) is polled by a watcher calling `handler` instead, and
+/// its time is the poll watcher's initial scan.
+fn watch_roots_with_fallback<F>(
+    watcher: &mut RecommendedWatcher,
+    roots: &[PathBuf],
+    config: &WatchConfig,
+    handler: F,
+) -> notify::Result<(Vec<Duration>, PollFallback)>
+where
+    F: Fn(notify::Result<Event>) + Send + 'static,
+{
+    let mut fallback = PollFallback { watcher: None, roots: Vec::new() };
+    let mut handler = Some(handler);
+    let mut times = Vec::with_capacity(roots.len());
+    for root in roots {
+        let start_watch = Instant::now();
+        match watcher.watch(&extended_length(root), RecursiveMode::Recursive) {
+            Ok(()) => {}
+            Err(e) if config.poll_fallback && falls_back_to_polling(&e) => {
+                // Drop whatever part of the subtree the backend did manage to watch
+                let _ = watcher.unwatch(&extended_length(root));
+                eprintln!("NativeRecursiveWatcher: {} can't be watched natively ({}); polling it instead", root.display(), e);
+                let poll = match (&mut fallback.watcher, handler.take()) {
+                    (Some(poll), _) => poll,
+                    (None, Some(handler)) => fallback.watcher.insert(PollWatcher::new(
+                        handler,
+                        Config::default()
+                            .with_poll_interval(config.poll_interval)
+                            .with_compare_contents(true),
+                    )?),
+                    (None, None) => unreachable!("the handler is only taken to create the poll watcher"),
+                };
+                let start_watch = Instant::now();
+                poll.watch(&absolute_path(root), RecursiveMode::Recursive)?;
+                fallback.roots.push((root.clone(), e.to_string()));
+                times.push(start_watch.elapsed());
+                continue;
+            }
+            Err(e) => return Err(e),
+        }
+        times.push(start_watch.elapsed());
+    }
+    Ok((times, fallback))
+}
+
 /// Native recursive watcher that uses the OS's native recursive watching
 pub struct NativeRecursiveWatcher {
     watcher: RecommendedWatcher,
-    receiver: mpsc::Receiver<notify::Result<Event>>,
+    receiver: EventReceiver,
     setup_time: std::time::Duration,
+    root_setup_times: Vec<Duration>,
+    /// Roots with a recursive watch still registered
+    roots: Vec<PathBuf>,
+    fallback: PollFallback,
 }
 
 /// Native recursive watcher with filtering
 pub struct FilteredNativeRecursiveWatcher {
     watcher: RecommendedWatcher,
-    receiver: mpsc::Receiver<notify::Result<Event>>,
-    filter_files: HashSet<PathBuf>,
+    receiver: EventReceiver,
+    filter_files: Arc<FilterSet>,
     setup_time: std::time::Duration,
+    root_setup_times: Vec<Duration>,
+    fallback: PollFallback,
 }
 
 impl NativeRecursiveWatcher {
-    /// Create a new native recursive watcher for the specified directory
-    pub fn new(dir: &Path) -> notify::Result<Self> {
-        // Create a channel for receiving events
-        let (tx, rx) = mpsc::channel();
+    /// Create a new native recursive watcher over several roots with custom options
+    pub fn new_with_roots_and_config(roots: &[PathBuf], config: &WatchConfig) -> notify::Result<Self> {
+        // Create a channel for receiving events, shared with a poll fallback if one is needed
+        let (tx, rx) = event_channel(config);
+        let tx = Arc::new(tx);
 
         // Create the watcher
+        let native_tx = tx.clone();
         let mut watcher = RecommendedWatcher::new(
             move |res: notify::Result<Event>| {
-                let _ = tx.send(res);  // Ignore send errors when receiver is dropped
+                native_tx.send(res);
             },
             Config::default(),
         )?;
 
-        // Watch the directory recursively using native recursive mode
-        let start_watch = Instant::now();
-        watcher.watch(dir, RecursiveMode::Recursive)?;
-        let watch_duration = start_watch.elapsed();
+        // Watch each root recursively using native recursive mode
+        let (root_setup_times, fallback) =
+            watch_roots_with_fallback(&mut watcher, roots, config, move |res| tx.send(res))?;
+        let watch_duration = root_setup_times.iter().sum();
 
         println!(
             "NativeRecursiveWatcher: Setup native recursive watch in {:?}",
@@ -160,54 +1450,60 @@ impl NativeRecursiveWatcher {
             watcher,
             receiver: rx,
             setup_time: watch_duration,
+            root_setup_times,
+            roots: roots.to_vec(),
+            fallback,
         })
     }
 
-    /// Create a new native recursive watcher with file filtering
-    pub fn new_with_filter<I>(
-        dir: &Path,
+    /// Create a new native recursive watcher over several roots with file filtering
+    ///
+    /// `files_to_watch` may borrow, e.g. `&[PathBuf]` or an iterator of `&Path`; the
+    /// list is only read while the filter set is built.
+    pub fn new_with_roots_filter_and_config<I>(
+        roots: &[PathBuf],
         files_to_watch: I,
+        config: &WatchConfig,
     ) -> notify::Result<FilteredNativeRecursiveWatcher>
     where
-        I: IntoIterator<Item = PathBuf>,
+        I: IntoIterator,
+        I::Item: AsRef<Path>,
     {
-        // Collect files into a HashSet for fast lookup
-        let filter_files: HashSet<PathBuf> = files_to_watch
-            .into_iter()
-            .filter(|p| p.exists() && p.is_file())
-            .collect();
+        let filter_files = Arc::new(FilterSet::from_files(roots, files_to_watch));
+        Self::new_with_filter_set(roots, filter_files, config)
+    }
 
+    /// Create a filtered native watcher over several roots from an already built filter
+    /// set, so repeated setups share one set instead of rebuilding it
+    ///
+    /// A set built over another copy of the roots is rebased onto `roots` first.
+    pub fn new_with_filter_set(
+        roots: &[PathBuf],
+        mut filter_files: Arc<FilterSet>,
+        config: &WatchConfig,
+    ) -> notify::Result<FilteredNativeRecursiveWatcher> {
+        if !filter_files.has_roots(roots) {
+            Arc::make_mut(&mut filter_files).rebase(roots).map_err(|e| notify::Error::generic(&e))?;
+        }
         let files_count = filter_files.len();
 
         // Create a channel for receiving events
-        let (tx, rx) = mpsc::channel();
+        let (tx, rx) = event_channel(config);
 
-        // Clone the filter_files for the closure
-        let filter_files_clone = filter_files.clone();
+        // Filter events to only include files in our filter set; shared with a poll fallback
+        let handler = Arc::new(filter_handler(Arc::clone(&filter_files), tx));
 
         // Create the watcher with filtering
+        let native_handler = handler.clone();
         let mut watcher = RecommendedWatcher::new(
-            move |res: notify::Result<Event>| {
-                // Filter events to only include files in our filter set
-                if let Ok(event) = &res {
-                    // Check if any of the paths in the event are in our filter set
-                    let should_send = event
-                        .paths
-                        .iter()
-                        .any(|path| filter_files_clone.contains(path));
-
-                    if should_send {
-                        let _ = tx.send(res);  // Ignore send errors when receiver is dropped
-                    }
-                }
-            },
+            move |res: notify::Result<Event>| native_handler(res),
             Config::default(),
         )?;
 
-        // Watch the directory recursively using native recursive mode
-        let start_watch = Instant::now();
-        watcher.watch(dir, RecursiveMode::Recursive)?;
-        let watch_duration = start_watch.elapsed();
+        // Watch each root recursively using native recursive mode
+        let (root_setup_times, fallback) =
+            watch_roots_with_fallback(&mut watcher, roots, config, move |res| handler(res))?;
+        let watch_duration = root_setup_times.iter().sum();
 
         println!(
             "FilteredNativeRecursiveWatcher: Setup native recursive watch with {} file filters in {:?}",
@@ -219,6 +1515,8 @@ impl NativeRecursiveWatcher {
             receiver: rx,
             filter_files,
             setup_time: watch_duration,
+            root_setup_times,
+            fallback,
         })
     }
 
@@ -227,14 +1525,41 @@ impl NativeRecursiveWatcher {
         self.setup_time
     }
 
-    /// Get the event receiver
-    pub fn receiver(&self) -> &mpsc::Receiver<notify::Result<Event>> {
-        &self.receiver
+    /// Setup time of each root's recursive watch, in the order the roots were given
+    pub fn root_setup_times(&self) -> &[Duration] {
+        &self.root_setup_times
     }
 
-    /// Consume self and return the watcher and receiver
-    pub fn into_parts(self) -> (RecommendedWatcher, mpsc::Receiver<notify::Result<Event>>) {
-        (self.watcher, self.receiver)
+    /// Remove each root's recursive watch with a single `unwatch` call, returning how long each took
+    ///
+    /// As in `ManualRecursiveWatcher::unwatch_all`, a failed call is returned in place and the rest go on.
+    pub fn unwatch_roots(&mut self) -> Vec<notify::Result<Duration>> {
+        let polled: Vec<PathBuf> = self.fallback.roots.iter().map(|(root, _)| root.clone()).collect();
+        std::mem::take(&mut self.roots)
+            .iter()
+            .map(|root| {
+                let start_unwatch = Instant::now();
+                match &mut self.fallback.watcher {
+                    Some(poll) if polled.contains(root) => poll.unwatch(&absolute_path(root))?,
+                    _ => self.watcher.unwatch(&extended_length(root))?,
+                }
+                Ok(start_unwatch.elapsed())
+            })
+            .collect()
+    }
+
+    /// Roots polled because the native backend refused them, each with its error
+    pub fn fallback_roots(&self) -> &[(PathBuf, String)] {
+        &self.fallback.roots
+    }
+
+    /// Consume self and return the watchers and receiver
+    pub fn into_parts(self) -> (NativeWatchers, EventReceiver) {
+        let watchers = NativeWatchers {
+            _native: self.watcher,
+            _fallback: self.fallback.watcher,
+        };
+        (watchers, self.receiver)
     }
 }
 
@@ -249,17 +1574,161 @@ impl FilteredNativeRecursiveWatcher {
         self.setup_time
     }
 
-    /// Get the event receiver
-    pub fn receiver(&self) -> &mpsc::Receiver<notify::Result<Event>> {
-        &self.receiver
+    /// Setup time of each root's recursive watch, in the order the roots were given
+    pub fn root_setup_times(&self) -> &[Duration] {
+        &self.root_setup_times
+    }
+
+    /// Roots polled because the native backend refused them, each with its error
+    pub fn fallback_roots(&self) -> &[(PathBuf, String)] {
+        &self.fallback.roots
+    }
+
+    /// Consume self and return the watchers and receiver
+    pub fn into_parts(self) -> (NativeWatchers, EventReceiver) {
+        let watchers = NativeWatchers {
+            _native: self.watcher,
+            _fallback: self.fallback.watcher,
+        };
+        (watchers, self.receiver)
+    }
+}
+
+/// Handle that keeps a watcher of any mode running; events stop once it's dropped
+pub type WatcherGuard = Box<dyn Send>;
+
+/// Recursive watcher that rescans the tree on an interval instead of using OS events
+pub struct PollRecursiveWatcher {
+    watcher: PollWatcher,
+    receiver: EventReceiver,
+    setup_time: std::time::Duration,
+    root_setup_times: Vec<Duration>,
+}
+
+impl PollRecursiveWatcher {
+    /// Create a poll watcher over several roots, rescanning every `config.poll_interval`
+    ///
+    /// notify only compares whole-second mtimes, so contents are hashed too or edits
+    /// within the same second would go unseen; setup includes that initial scan.
+    pub fn new_with_roots_and_config(roots: &[PathBuf], config: &WatchConfig) -> notify::Result<Self> {
+        let (tx, rx) = event_channel(config);
+
+        let mut watcher = PollWatcher::new(
+            move |res: notify::Result<Event>| {
+                tx.send(res);
+            },
+            Config::default()
+                .with_poll_interval(config.poll_interval)
+                .with_compare_contents(true),
+        )?;
+
+        // Unlike the OS backends, the poll watcher reports paths the way they were given
+        let roots: Vec<PathBuf> = roots.iter().map(|root| absolute_path(root)).collect();
+        let root_setup_times = watch_roots(&mut watcher, &roots)?;
+        let watch_duration = root_setup_times.iter().sum();
+
+        println!(
+            "PollRecursiveWatcher: Setup poll watch every {:?} in {:?}",
+            config.poll_interval, watch_duration
+        );
+
+        Ok(Self {
+            watcher,
+            receiver: rx,
+            setup_time: watch_duration,
+            root_setup_times,
+        })
+    }
+
+    /// Get the setup time, including the initial scan
+    pub fn setup_time(&self) -> std::time::Duration {
+        self.setup_time
+    }
+
+    /// Setup time of each root's watch, in the order the roots were given
+    pub fn root_setup_times(&self) -> &[Duration] {
+        &self.root_setup_times
     }
 
     /// Consume self and return the watcher and receiver
-    pub fn into_parts(self) -> (RecommendedWatcher, mpsc::Receiver<notify::Result<Event>>) {
+    pub fn into_parts(self) -> (PollWatcher, EventReceiver) {
         (self.watcher, self.receiver)
     }
 }
 
+/// Native recursive watcher whose events pass through notify's full debouncer
+///
+/// Events for a path are held until it has been quiet for `config.debounce`, so
+/// their latency includes that wait; the debouncer also stitches renames together.
+pub struct DebouncedRecursiveWatcher {
+    debouncer: Debouncer<RecommendedWatcher, FileIdMap>,
+    receiver: EventReceiver,
+    setup_time: std::time::Duration,
+    root_setup_times: Vec<Duration>,
+}
+
+impl DebouncedRecursiveWatcher {
+    /// Create a debounced native watcher over several roots with custom options
+    pub fn new_with_roots_and_config(roots: &[PathBuf], config: &WatchConfig) -> notify::Result<Self> {
+        let (tx, rx) = event_channel(config);
+
+        let mut debouncer = new_debouncer(config.debounce, None, move |res: DebounceEventResult| {
+            match res {
+                Ok(events) => {
+                    for event in events {
+                        tx.send(Ok(event.event));
+                    }
+                }
+                Err(errors) => {
+                    for error in errors {
+                        tx.send(Err(error));
+                    }
+                }
+            }
+        })?;
+
+        // The file id cache lets the debouncer match the two halves of a rename
+        let root_setup_times = roots
+            .iter()
+            .map(|root| {
+                let start_watch = Instant::now();
+                let root = extended_length(root);
+                debouncer.watcher().watch(&root, RecursiveMode::Recursive)?;
+                debouncer.cache().add_root(root.as_ref(), RecursiveMode::Recursive);
+                Ok(start_watch.elapsed())
+            })
+            .collect::<notify::Result<Vec<_>>>()?;
+        let watch_duration = root_setup_times.iter().sum();
+
+        println!(
+            "DebouncedRecursiveWatcher: Setup debounced watch ({:?} quiet period) in {:?}",
+            config.debounce, watch_duration
+        );
+
+        Ok(Self {
+            debouncer,
+            receiver: rx,
+            setup_time: watch_duration,
+            root_setup_times,
+        })
+    }
+
+    /// Get the setup time, including filling the file id cache
+    pub fn setup_time(&self) -> std::time::Duration {
+        self.setup_time
+    }
+
+    /// Setup time of each root's watch, in the order the roots were given
+    pub fn root_setup_times(&self) -> &[Duration] {
+        &self.root_setup_times
+    }
+
+    /// Consume self and return the debouncer and receiver
+    pub fn into_parts(self) -> (Debouncer<RecommendedWatcher, FileIdMap>, EventReceiver) {
+        (self.debouncer, self.receiver)
+    }
+}
+
 /// Watcher mode enum for selecting which type of watcher to use
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum WatcherMode {
@@ -271,9 +1740,31 @@ pub enum WatcherMode {
     ManualFiltered,
     /// Native with filtered files: watch directory but filter events
     NativeFiltered,
+    /// Poll: rescan the tree on an interval
+    Poll,
+    /// Debounced: native recursive events merged by notify's debouncer
+    Debounced,
 }
 
 impl WatcherMode {
+    /// The event-driven modes the scenario tests compare, in the order they are reported
+    pub const ALL: [WatcherMode; 4] = [
+        Self::Manual,
+        Self::Native,
+        Self::ManualFiltered,
+        Self::NativeFiltered,
+    ];
+
+    /// `ALL` plus the poll and debounced watchers, for `compare-all`
+    pub const EVERY: [WatcherMode; 6] = [
+        Self::Manual,
+        Self::Native,
+        Self::ManualFiltered,
+        Self::NativeFiltered,
+        Self::Poll,
+        Self::Debounced,
+    ];
+
     /// Parse from string
     pub fn from_str(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
@@ -281,17 +1772,27 @@ impl WatcherMode {
             "native" => Some(Self::Native),
             "manual-filtered" => Some(Self::ManualFiltered),
             "native-filtered" => Some(Self::NativeFiltered),
+            "poll" => Some(Self::Poll),
+            "debounced" => Some(Self::Debounced),
             _ => None,
         }
     }
 
+    /// Whether files created after setup are watched; the manual and filtered
+    /// modes only ever cover the files enumerated up front
+    pub fn watches_new_paths(&self) -> bool {
+        matches!(self, Self::Native | Self::Poll | Self::Debounced)
+    }
+
     /// Get display name
-    pub fn display_name(&self) -> &str {
+    pub fn display_name(&self) -> &'static str {
         match self {
             Self::Manual => "Manual Recursive",
             Self::Native => "Native Recursive",
             Self::ManualFiltered => "Manual Filtered",
             Self::NativeFiltered => "Native Filtered",
+            Self::Poll => "Poll",
+            Self::Debounced => "Debounced",
         }
     }
 }
@@ -302,7 +1803,7 @@ mod tests {
     use std::fs::{self, File};
 
     #[test]
-    fn test_collect_files_recursive() {
+    fn test_walk_files() {
         // Create a temporary test directory
         let test_dir = Path::new("test_temp_watcher_dir");
         fs::create_dir_all(test_dir).unwrap();
@@ -317,19 +1818,446 @@ mod tests {
         File::create(sub_dir.join("file3.txt")).unwrap();
 
         // Test file collection
-        let files = collect_files_recursive(test_dir);
+        let files: Vec<PathBuf> = walk_files(test_dir).collect();
         assert_eq!(files.len(), 3);
 
         // Clean up
         fs::remove_dir_all(test_dir).unwrap();
     }
 
+    #[test]
+    fn test_walk_files_far_past_recursion_depth() {
+        // Deep enough that a recursive walk on a small thread stack would overflow
+        let test_dir = Path::new("test_deep_collect_dir");
+        let mut deepest = test_dir.to_path_buf();
+        for _ in 0..1000 {
+            deepest.push("d");
+        }
+        fs::create_dir_all(&deepest).unwrap();
+        File::create(deepest.join("leaf.txt")).unwrap();
+
+        let files = std::thread::Builder::new()
+            .stack_size(64 * 1024)
+            .spawn(move || walk_files(Path::new("test_deep_collect_dir")).collect::<Vec<_>>())
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(files, [deepest.join("leaf.txt")]);
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_manual_watcher_full_coverage() {
+        let test_dir = Path::new("test_temp_coverage_dir");
+        fs::create_dir_all(test_dir).unwrap();
+        File::create(test_dir.join("file1.txt")).unwrap();
+        File::create(test_dir.join("file2.txt")).unwrap();
+
+        let mut watcher = ManualRecursiveWatcher::new_with_config(walk_files(test_dir), &WatchConfig::default()).unwrap();
+        assert_eq!(watcher.files_requested(), 2);
+        assert_eq!(watcher.files_watched(), 2);
+        assert!(!watcher.is_partial());
+        assert_eq!(watcher.coverage_percent(), 100.0);
+        assert_eq!((watcher.retries(), watcher.skipped().len()), (0, 0));
+        assert_eq!(watcher.unwatch_all().into_iter().filter(Result::is_ok).count(), 2);
+        assert!(watcher.unwatch_all().is_empty());
+
+        let roots = [test_dir.to_path_buf()];
+        let mut native = NativeRecursiveWatcher::new_with_roots_and_config(&roots, &WatchConfig::default()).unwrap();
+        assert_eq!(native.unwatch_roots().into_iter().filter(Result::is_ok).count(), 1);
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_event_channel_kinds() {
+        for channel in [ChannelKind::Std, ChannelKind::Crossbeam, ChannelKind::Tokio] {
+            for channel_capacity in [None, Some(4)] {
+                let config = WatchConfig {
+                    channel_capacity,
+                    channel,
+                    ..WatchConfig::default()
+                };
+                let (tx, rx) = event_channel(&config);
+                tx.send(Ok(Event::new(notify::EventKind::Any)));
+                tx.send(Err(notify::Error::generic("boom")));
+                assert_eq!(rx.depth().get(), 2);
+
+                assert!(rx.recv().unwrap().result.is_ok());
+                assert!(rx.recv_timeout(Duration::from_millis(10)).unwrap().result.is_err());
+                assert_eq!(rx.depth().get(), 0);
+
+                let overhead = rx.overhead();
+                assert_eq!((overhead.sends, overhead.recvs), (2, 2));
+                assert!(matches!(
+                    rx.recv_timeout(Duration::from_millis(1)),
+                    Err(mpsc::RecvTimeoutError::Timeout)
+                ));
+                drop(tx);
+                assert!(rx.recv().is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_tokio_channel_into_async() {
+        let (_, rx) = event_channel(&WatchConfig::default());
+        assert!(rx.into_async().is_none());
+
+        let config = WatchConfig {
+            channel: ChannelKind::Tokio,
+            ..WatchConfig::default()
+        };
+        let (tx, rx) = event_channel(&config);
+        let mut rx = rx.into_async().unwrap();
+        tx.send(Ok(Event::new(notify::EventKind::Any)));
+        drop(tx);
+
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        runtime.block_on(async {
+            assert!(rx.recv().await.unwrap().result.is_ok());
+            assert!(rx.recv().await.is_none());
+        });
+        assert_eq!(rx.depth().get(), 0);
+    }
+
+    #[test]
+    fn test_tokio_recv_timeout_inside_runtime() {
+        let config = WatchConfig {
+            channel: ChannelKind::Tokio,
+            ..WatchConfig::default()
+        };
+        let (tx, rx) = event_channel(&config);
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        runtime.block_on(async {
+            let timeout = Duration::from_millis(1);
+            assert_eq!(rx.recv_timeout(timeout).err(), Some(mpsc::RecvTimeoutError::Timeout));
+            tx.send(Ok(Event::new(notify::EventKind::Any)));
+            assert!(rx.recv_timeout(timeout).is_ok());
+            drop(tx);
+            assert_eq!(rx.recv_timeout(timeout).err(), Some(mpsc::RecvTimeoutError::Disconnected));
+        });
+    }
+
+    #[test]
+    fn test_into_stream() {
+        use futures::StreamExt;
+
+        let test_dir = Path::new("test_temp_stream_dir");
+        fs::create_dir_all(test_dir).unwrap();
+        let file = test_dir.join("file1.txt");
+        File::create(&file).unwrap();
+
+        let config = WatchConfig {
+            channel: ChannelKind::Tokio,
+            ..WatchConfig::default()
+        };
+        let roots = [test_dir.to_path_buf()];
+        let (_, rx) = NativeRecursiveWatcher::new_with_roots_and_config(&roots, &WatchConfig::default())
+            .unwrap()
+            .into_parts();
+        assert!(rx.into_async().is_none());
+        let (watchers, rx) = NativeRecursiveWatcher::new_with_roots_and_config(&roots, &config)
+            .unwrap()
+            .into_parts();
+        let stream = event_stream(watchers, rx.into_async().unwrap());
+        fs::write(&file, "changed").unwrap();
+
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+        let first = runtime.block_on(async {
+            let mut events = Box::pin(stream.filter_map(|res| async { res.ok() }));
+            tokio::time::timeout(Duration::from_secs(5), events.next()).await
+        });
+        assert!(first.unwrap().unwrap().paths.iter().any(|p| p.ends_with("file1.txt")));
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_full_channel_policies() {
+        for channel in [ChannelKind::Std, ChannelKind::Crossbeam, ChannelKind::Tokio] {
+            let config = WatchConfig {
+                channel_capacity: Some(1),
+                channel,
+                on_full: FullPolicy::Drop,
+                ..WatchConfig::default()
+            };
+            let (tx, rx) = event_channel(&config);
+            for _ in 0..3 {
+                tx.send(Ok(Event::new(notify::EventKind::Any)));
+            }
+            let backpressure = rx.backpressure();
+            assert_eq!((backpressure.sends, backpressure.dropped, backpressure.blocked), (3, 2, 0));
+            assert_eq!(rx.depth().get(), 1);
+
+            let config = WatchConfig {
+                on_full: FullPolicy::Block,
+                ..config
+            };
+            let (tx, rx) = event_channel(&config);
+            let sender = std::thread::spawn(move || {
+                for _ in 0..2 {
+                    tx.send(Ok(Event::new(notify::EventKind::Any)));
+                }
+            });
+            std::thread::sleep(Duration::from_millis(20));
+            assert!(rx.recv().is_ok());
+            sender.join().unwrap();
+            assert!(rx.recv().is_ok());
+            let backpressure = rx.backpressure();
+            assert_eq!((backpressure.blocked, backpressure.dropped), (1, 0));
+            assert!(backpressure.blocked_time >= Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_manual_watcher_skips_missing_files() {
+        let test_dir = Path::new("test_temp_skip_dir");
+        fs::create_dir_all(test_dir).unwrap();
+        File::create(test_dir.join("kept.txt")).unwrap();
+
+        let config = WatchConfig {
+            watch_retries: 2,
+            ..WatchConfig::default()
+        };
+        let files = vec![test_dir.join("gone.txt"), test_dir.join("kept.txt")];
+        let watcher = ManualRecursiveWatcher::new_with_config(files, &config).unwrap();
+        assert_eq!(watcher.watched_files(), [test_dir.join("kept.txt")]);
+        assert_eq!(watcher.skipped(), [test_dir.join("gone.txt")]);
+        assert_eq!(watcher.retries(), 0);
+        assert_eq!(watcher.backoff_time(), Duration::ZERO);
+        assert!(watcher.is_partial());
+        assert!(!watcher.limit_reached());
+        assert!(!is_transient(&notify::Error::new(ErrorKind::MaxFilesWatch)));
+        assert!(is_transient(&notify::Error::io(std::io::ErrorKind::Interrupted.into())));
+        let denied = notify::Error::io(std::io::ErrorKind::PermissionDenied.into());
+        assert!(!is_transient(&denied) && is_unwatchable(&denied));
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_falls_back_to_polling() {
+        assert!(falls_back_to_polling(&notify::Error::new(ErrorKind::MaxFilesWatch)));
+        assert!(falls_back_to_polling(&notify::Error::io(std::io::Error::from_raw_os_error(38))));
+        assert!(!falls_back_to_polling(&notify::Error::path_not_found()));
+        assert!(!falls_back_to_polling(&notify::Error::new(ErrorKind::WatchNotFound)));
+    }
+
+    #[test]
+    fn test_exclude_list() {
+        let config = WatchConfig {
+            exclude: vec!["target".to_string()],
+            ..WatchConfig::default()
+        };
+        let (tx, rx) = event_channel(&config);
+        let event = |paths: &[&str]| {
+            let event = Event::new(notify::EventKind::Any);
+            Ok(paths.iter().fold(event, |event, path| event.add_path(PathBuf::from(path))))
+        };
+        tx.send(event(&["/repo/target/debug/app"]));
+        tx.send(event(&["/repo/target/a.d", "/repo/src/main.rs"]));
+        tx.send(event(&["/repo/src/target.rs"]));
+        tx.send(event(&[]));
+        assert_eq!(rx.excluded(), 1);
+        assert_eq!(rx.depth().get(), 3);
+        assert!(is_excluded(Path::new("target/debug"), &config.exclude));
+        assert!(!is_excluded(Path::new("targets/debug"), &config.exclude));
+    }
+
+    #[test]
+    fn test_extended_length_prefix() {
+        let long = format!(r"C:\{}\file.js", "d".repeat(MAX_PATH));
+        assert_eq!(add_extended_prefix(&long), Some(format!(r"\\?\{}", long)));
+        assert_eq!(add_extended_prefix(r"C:\short\file.js"), None);
+        let share = format!(r"\\server\share\{}", "d".repeat(MAX_PATH));
+        let prefixed = add_extended_prefix(&share).unwrap();
+        assert!(prefixed.starts_with(r"\\?\UNC\server\share\"));
+        assert_eq!(add_extended_prefix(&prefixed), None);
+
+        assert_eq!(strip_extended_prefix(&prefixed), Some(share));
+        assert_eq!(strip_extended_prefix(&format!(r"\\?\{}", long)), Some(long));
+        assert_eq!(strip_extended_prefix(r"C:\short"), None);
+
+        assert_eq!(
+            fold_unc_share(r"\\FileServer\Builds$\Src\a.js"),
+            Some(r"\\fileserver\builds$\Src\a.js".to_string())
+        );
+        assert_eq!(fold_unc_share(r"\\SERVER"), Some(r"\\server".to_string()));
+        assert_eq!(fold_unc_share(r"\\server\share\A.js"), None);
+        assert_eq!(fold_unc_share(r"\\?\UNC\Server\share"), None);
+        assert_eq!(fold_unc_share(r"C:\Src"), None);
+    }
+
+    #[test]
+    fn test_filter_set() {
+        let mut set = FilterSet::new(&[PathBuf::from("/t")]);
+        set.extend(["/t/a/1.js", "/t/a/2.js", "/t/b/1.js", "/t/0.js", "/u/1.js"]);
+        assert_eq!(set.len(), 5);
+        // Stored below the root; the file outside it keeps its full path
+        let dirs: HashSet<&Path> = set.trees[0].keys().map(|dir| dir.as_ref()).collect();
+        assert_eq!(dirs, HashSet::from([Path::new("a"), Path::new("b"), Path::new("")]));
+        assert!(set.trees[1].contains_key(Path::new("/u")));
+        assert!(!set.insert(Path::new("/t/a/1.js")));
+        assert!(!set.insert(Path::new("/")));
+        assert_eq!(set.len(), 5);
+
+        assert!(set.contains(Path::new("/t/b/1.js")));
+        assert!(set.contains(Path::new("/t/0.js")));
+        assert!(set.contains(Path::new("/u/1.js")));
+        assert!(!set.contains(Path::new("/t/b/2.js")));
+        assert!(!set.contains(Path::new("/t/a")));
+        assert!(!set.contains(Path::new("/t")));
+        assert!(!set.contains(Path::new("/t/c/1.js")));
+        assert!(!set.contains(Path::new("/v/t/a/1.js")));
+    }
+
+    #[test]
+    fn test_filter_set_rebase() {
+        let mut set = FilterSet::new(&[PathBuf::from("/src/a"), PathBuf::from("/src/b")]);
+        set.extend(["/src/a/x/1.js", "/src/b/2.js", "/u/3.js"]);
+        let copy = [PathBuf::from("/tmp/a"), PathBuf::from("/tmp/b")];
+        assert!(!set.has_roots(&copy));
+
+        set.rebase(&copy).unwrap();
+        assert!(set.has_roots(&copy));
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(Path::new("/tmp/a/x/1.js")));
+        assert!(set.contains(Path::new("/tmp/b/2.js")));
+        assert!(!set.contains(Path::new("/src/a/x/1.js")));
+        assert!(!set.contains(Path::new("/tmp/b/x/1.js")));
+        // Outside every root before and after
+        assert!(set.contains(Path::new("/u/3.js")));
+        assert!(set.rebase(&copy[..1]).is_err());
+    }
+
+    #[test]
+    fn test_list_files_reports_unreadable_dirs() {
+        let listing = walk_files(Path::new("/nonexistent/watcher-walk")).list_up_to(usize::MAX);
+        assert!(listing.files.is_empty());
+        assert_eq!(listing.unreadable_dirs(), 1);
+        assert_eq!(listing.errors[0].kind, std::io::ErrorKind::NotFound);
+        assert_eq!(listing.permission_errors(), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_list_files_reports_permission_errors() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = std::env::temp_dir().join(format!("watcher-walk-denied-{}", std::process::id()));
+        fs::create_dir_all(dir.join("locked")).unwrap();
+        fs::write(dir.join("1.js"), "").unwrap();
+        fs::write(dir.join("locked/2.js"), "").unwrap();
+        fs::set_permissions(dir.join("locked"), fs::Permissions::from_mode(0o000)).unwrap();
+
+        let mut listing = walk_files(&dir).list_up_to(usize::MAX);
+        // Root reads through any mode, so the lock only shows for other users
+        if fs::read_dir(dir.join("locked")).is_err() {
+            assert_eq!(listing.files, [dir.join("1.js")]);
+            assert_eq!(listing.errors.len(), 1);
+            assert_eq!((listing.errors[0].path.clone(), listing.errors[0].site), (dir.join("locked"), WalkErrorSite::Directory));
+            assert_eq!((listing.unreadable_dirs(), listing.permission_errors()), (1, 1));
+        } else {
+            assert_eq!(listing.files.len(), 2);
+            assert!(listing.errors.is_empty());
+        }
+        fs::set_permissions(dir.join("locked"), fs::Permissions::from_mode(0o755)).unwrap();
+
+        // An entry that couldn't be read counts as a permission error but leaves its directory readable
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        let before = (listing.unreadable_dirs(), listing.permission_errors());
+        listing.errors.push(WalkError::new(&dir, WalkErrorSite::Entry, &denied));
+        assert_eq!((listing.unreadable_dirs(), listing.permission_errors()), (before.0, before.1 + 1));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_walk_skips_symlink_loops() {
+        let dir = std::env::temp_dir().join(format!("watcher-walk-loop-{}", std::process::id()));
+        fs::create_dir_all(dir.join("a/b")).unwrap();
+        fs::write(dir.join("a/b/1.js"), "").unwrap();
+        std::os::unix::fs::symlink("../..", dir.join("a/b/up")).unwrap();
+        // A link to a sibling isn't a loop, but leads to a directory the walk lists anyway
+        std::os::unix::fs::symlink("a/b", dir.join("c")).unwrap();
+
+        let listing = walk_files_sorted(&dir).list_up_to(usize::MAX);
+        // `a/b` is walked first, so its files are listed once and `c` is only recorded
+        assert_eq!(listing.files, [dir.join("a/b/1.js")]);
+        assert_eq!(listing.duplicates, [dir.join("c")]);
+        assert_eq!(listing.loops, [dir.join("a/b/up")]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sorted_walk_lists_entries_in_name_order() {
+        let dir = std::env::temp_dir().join(format!("watcher-walk-sorted-{}", std::process::id()));
+        fs::create_dir_all(dir.join("m")).unwrap();
+        for file in ["z.js", "a.js", "m/2.js", "m/1.js", "b.js"] {
+            fs::write(dir.join(file), "").unwrap();
+        }
+        let files: Vec<PathBuf> = walk_files_sorted(&dir).collect();
+        let expected: Vec<PathBuf> = ["a.js", "b.js", "m/1.js", "m/2.js", "z.js"].iter().map(|f| dir.join(f)).collect();
+        assert_eq!(files, expected);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_filter_set_from_borrowed_files() {
+        let dir = std::env::temp_dir().join(format!("watcher-filter-set-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("a")).unwrap();
+        let files = vec![dir.join("a/1.js"), dir.join("2.js"), dir.join("missing.js"), dir.join("a")];
+        for file in &files[..2] {
+            std::fs::write(file, "").unwrap();
+        }
+
+        // Only existing files are kept, and the list is left to the caller
+        let roots = [dir.clone()];
+        let set = FilterSet::from_files(&roots, &files);
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&filter_entry(&files[0])));
+        let paths: Vec<&Path> = files.iter().map(PathBuf::as_path).collect();
+        assert_eq!(FilterSet::from_files(&roots, paths), set);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn test_watcher_mode_parsing() {
         assert_eq!(WatcherMode::from_str("manual"), Some(WatcherMode::Manual));
         assert_eq!(WatcherMode::from_str("MANUAL"), Some(WatcherMode::Manual));
         assert_eq!(WatcherMode::from_str("native"), Some(WatcherMode::Native));
         assert_eq!(WatcherMode::from_str("NATIVE"), Some(WatcherMode::Native));
+        assert_eq!(WatcherMode::from_str("poll"), Some(WatcherMode::Poll));
+        assert_eq!(WatcherMode::from_str("debounced"), Some(WatcherMode::Debounced));
         assert_eq!(WatcherMode::from_str("invalid"), None);
     }
+
+    #[test]
+    fn test_poll_and_debounced_deliver_events() {
+        let test_dir = absolute_path(Path::new("test_poll_debounced_dir"));
+        fs::create_dir_all(&test_dir).unwrap();
+        let file = test_dir.join("a.txt");
+        File::create(&file).unwrap();
+
+        let config = WatchConfig {
+            poll_interval: Duration::from_millis(20),
+            debounce: Duration::from_millis(20),
+            ..WatchConfig::default()
+        };
+        let roots = [test_dir.clone()];
+        let poll = PollRecursiveWatcher::new_with_roots_and_config(&roots, &config).unwrap();
+        let debounced = DebouncedRecursiveWatcher::new_with_roots_and_config(&roots, &config).unwrap();
+        assert_eq!(poll.root_setup_times().len(), 1);
+        let (_poll, poll_rx) = poll.into_parts();
+        let (_debouncer, debounced_rx) = debounced.into_parts();
+
+        fs::write(&file, "changed contents").unwrap();
+        for rx in [poll_rx, debounced_rx] {
+            let event = rx.recv_timeout(Duration::from_secs(5)).unwrap().result.unwrap();
+            assert!(event.paths.contains(&file), "{:?}", event);
+        }
+
+        fs::remove_dir_all(&test_dir).unwrap();
+    }
 }
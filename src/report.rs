@@ -0,0 +1,343 @@
+use crate::format::{self, environment_allocator, from_v1, Migration, Versioned};
+use crate::history::{Environment, Measurement, RunRecord, TreeFingerprint};
+use crate::options::Options;
+use crate::significance::MannWhitney;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Change in percent below which `diff` doesn't call a metric better or worse
+pub const DEFAULT_REGRESSION_THRESHOLD: f64 = 5.0;
+
+/// One invocation's results as a standalone JSON file, written with `--report`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunReport {
+    pub format_version: u32,
+    pub id: String,
+    pub started_at_ms: i64,
+    pub command: String,
+    pub args: String,
+    pub status: String,
+    pub tree_fingerprint: String,
+    pub tree_files: usize,
+    pub environment: Environment,
+    pub measurements: Vec<Measurement>,
+}
+
+impl Versioned for RunReport {
+    const FORMAT_VERSION: u32 = 3;
+    const MIGRATIONS: &'static [Migration] = &[from_v1, environment_allocator];
+}
+
+impl RunReport {
+    pub fn new(run: &RunRecord, tree: &TreeFingerprint, environment: &Environment, status: &str) -> Self {
+        Self {
+            format_version: Self::FORMAT_VERSION,
+            id: run.id.clone(),
+            started_at_ms: run.started_at_ms,
+            command: run.command.clone(),
+            args: run.args.clone(),
+            status: status.to_string(),
+            tree_fingerprint: tree.hash.clone(),
+            tree_files: tree.files,
+            environment: environment.clone(),
+            measurements: run.measurements.clone(),
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, json + "\n").map_err(|e| e.to_string())
+    }
+
+    /// Read a report of any supported format version
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let json = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        format::load(&json)
+            .map(|(report, _)| report)
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+/// Which way a metric should move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Better {
+    Lower,
+    Higher,
+}
+
+impl Better {
+    /// Whether `candidate` beats `current`
+    pub fn prefers(&self, candidate: f64, current: f64) -> bool {
+        match self {
+            Self::Lower => candidate < current,
+            Self::Higher => candidate > current,
+        }
+    }
+}
+
+/// Which way `metric` should move, judged by its name; `None` for counts that are neither
+pub fn better_direction(metric: &str) -> Option<Better> {
+    const HIGHER: [&str; 8] = [
+        "coverage",
+        "hit_rate",
+        "per_sec",
+        "precision",
+        "recall",
+        "score",
+        "paired",
+        "compression",
+    ];
+    const LOWER: [&str; 14] = [
+        "false_",
+        "leaked",
+        "missed",
+        "dropped",
+        "spurious",
+        "unmatched",
+        "syscalls_",
+        "noise",
+        "over_target",
+        "evictions",
+        "warmup_ratio",
+        "per_modification",
+        "_bytes",
+        "allocations",
+    ];
+    if metric.ends_with("_us") || metric.ends_with("_ns") || LOWER.iter().any(|part| metric.contains(part)) {
+        Some(Better::Lower)
+    } else if HIGHER.iter().any(|part| metric.contains(part)) {
+        Some(Better::Higher)
+    } else {
+        None
+    }
+}
+
+/// How one metric moved between two runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Regression,
+    Improvement,
+    /// Moved past the threshold, but not significantly across the recorded iterations
+    Noise,
+    Unchanged,
+}
+
+impl Verdict {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Regression => "⚠️  regression",
+            Self::Improvement => "✓ improved",
+            Self::Noise => "noise",
+            Self::Unchanged => "",
+        }
+    }
+}
+
+/// One (mode, metric) compared between two runs, by the mean of its recorded values
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricDelta {
+    pub watcher_mode: String,
+    pub metric: String,
+    pub before: Option<f64>,
+    pub after: Option<f64>,
+    pub verdict: Verdict,
+}
+
+impl MetricDelta {
+    /// Relative change in percent, when both sides have a value and `before` isn't zero
+    pub fn change_percent(&self) -> Option<f64> {
+        match (self.before, self.after) {
+            (Some(x), Some(y)) if x != 0.0 => Some((y - x) / x * 100.0),
+            _ => None,
+        }
+    }
+}
+
+pub fn samples_by_metric(measurements: &[Measurement]) -> BTreeMap<(String, String), Vec<f64>> {
+    let mut samples: BTreeMap<(String, String), Vec<f64>> = BTreeMap::new();
+    for m in measurements {
+        samples.entry((m.watcher_mode.clone(), m.metric.clone())).or_default().push(m.value);
+    }
+    samples
+}
+
+pub fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Every (mode, metric) either side recorded, with a verdict for those that moved
+///
+/// A metric counts as changed once its mean moves by `threshold` percent in a
+/// known direction; when both runs recorded it several times the difference must
+/// also be significant, or it is reported as noise.
+pub fn compare(before: &[Measurement], after: &[Measurement], threshold: f64) -> Vec<MetricDelta> {
+    let before = samples_by_metric(before);
+    let after = samples_by_metric(after);
+    let keys: std::collections::BTreeSet<_> = before.keys().chain(after.keys()).collect();
+    keys.into_iter()
+        .map(|key| {
+            let (a, b) = (before.get(key), after.get(key));
+            let mut delta = MetricDelta {
+                watcher_mode: key.0.clone(),
+                metric: key.1.clone(),
+                before: a.map(|values| mean(values)),
+                after: b.map(|values| mean(values)),
+                verdict: Verdict::Unchanged,
+            };
+            if let (Some(change), Some(better)) = (delta.change_percent(), better_direction(&key.1)) {
+                if change.abs() >= threshold {
+                    let worse = (change > 0.0) == (better == Better::Lower);
+                    let repeated = a.zip(b).filter(|(a, b)| a.len() > 1 && b.len() > 1);
+                    delta.verdict = match repeated.and_then(|(a, b)| MannWhitney::test(a, b)) {
+                        Some(test) if !test.is_significant() => Verdict::Noise,
+                        _ if worse => Verdict::Regression,
+                        _ => Verdict::Improvement,
+                    };
+                }
+            }
+            delta
+        })
+        .collect()
+}
+
+/// Print `deltas` as a table with the two runs labelled `a` and `b`, then a verdict count
+pub fn print_deltas(deltas: &[MetricDelta], a: &str, b: &str) {
+    println!(
+        "  {:<20} {:<24} {:>14} {:>14} {:>9}  Verdict",
+        "Mode", "Metric", a, b, "Change"
+    );
+    let cell = |v: Option<f64>| v.map_or("-".to_string(), |v| format!("{:.1}", v));
+    for delta in deltas {
+        println!(
+            "  {:<20} {:<24} {:>14} {:>14} {:>9}  {}",
+            delta.watcher_mode,
+            delta.metric,
+            cell(delta.before),
+            cell(delta.after),
+            delta.change_percent().map_or("-".to_string(), |c| format!("{:+.1}%", c)),
+            delta.verdict.display_name()
+        );
+    }
+    let count = |verdict| deltas.iter().filter(|d| d.verdict == verdict).count();
+    println!(
+        "\n{} regressions, {} improvements, {} within noise across {} metrics",
+        count(Verdict::Regression),
+        count(Verdict::Improvement),
+        count(Verdict::Noise),
+        deltas.len()
+    );
+}
+
+/// Compare two `--report` files metric by metric, highlighting regressions
+pub fn run_diff(a: &Path, b: &Path, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    let before = RunReport::load(a)?;
+    let after = RunReport::load(b)?;
+    println!("\n=== Diff {} → {} ===", a.display(), b.display());
+    for (path, report) in [(a, &before), (b, &after)] {
+        println!(
+            "  {}: {} {} ({}), {}",
+            path.display(),
+            report.command,
+            report.args,
+            report.status,
+            report.environment.key()
+        );
+    }
+    if before.tree_fingerprint != after.tree_fingerprint {
+        println!(
+            "Note: the runs watched different trees ({} files vs {}), so deltas may reflect the tree",
+            before.tree_files, after.tree_files
+        );
+    }
+    if before.environment.key() != after.environment.key() {
+        println!("Note: the runs come from different environments");
+    }
+    println!();
+
+    let deltas = compare(&before.measurements, &after.measurements, options.regression_threshold);
+    if deltas.is_empty() {
+        return Err("neither report holds any measurements".into());
+    }
+    let label = |path: &Path| path.file_stem().map_or_else(|| path.display().to_string(), |s| s.to_string_lossy().into_owned());
+    print_deltas(&deltas, &label(a), &label(b));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measurement(mode: &str, metric: &str, value: f64) -> Measurement {
+        Measurement {
+            watcher_mode: mode.to_string(),
+            metric: metric.to_string(),
+            value,
+        }
+    }
+
+    #[test]
+    fn test_better_direction() {
+        assert_eq!(better_direction("setup_us"), Some(Better::Lower));
+        assert_eq!(better_direction("false_negatives"), Some(Better::Lower));
+        assert_eq!(better_direction("filter_bytes_per_path"), Some(Better::Lower));
+        assert_eq!(better_direction("filter_lookup_ns"), Some(Better::Lower));
+        assert_eq!(better_direction("replay_events_per_sec"), Some(Better::Higher));
+        assert_eq!(better_direction("recall"), Some(Better::Higher));
+        assert_eq!(better_direction("events"), None);
+    }
+
+    #[test]
+    fn test_compare() {
+        let before = [
+            measurement("Native", "setup_us", 1000.0),
+            measurement("Native", "recall", 1.0),
+            measurement("Native", "events", 10.0),
+            measurement("Manual", "latency_p50_us", 400.0),
+        ];
+        let after = [
+            measurement("Native", "setup_us", 1500.0),
+            measurement("Native", "recall", 1.02),
+            measurement("Native", "events", 30.0),
+            measurement("Manual", "score", 90.0),
+        ];
+        let deltas = compare(&before, &after, DEFAULT_REGRESSION_THRESHOLD);
+        let verdicts: Vec<(&str, Verdict)> = deltas.iter().map(|d| (d.metric.as_str(), d.verdict)).collect();
+        assert_eq!(
+            verdicts,
+            [
+                ("latency_p50_us", Verdict::Unchanged),
+                ("score", Verdict::Unchanged),
+                ("events", Verdict::Unchanged),
+                ("recall", Verdict::Unchanged),
+                ("setup_us", Verdict::Regression),
+            ]
+        );
+        assert_eq!(deltas[4].change_percent(), Some(50.0));
+        assert_eq!(deltas[0].after, None);
+
+        // Iterations that overlap make a threshold-sized move noise
+        let setup = |values: [f64; 5]| values.map(|v| measurement("Native", "setup_us", v));
+        let noisy_before = setup([900.0, 1100.0, 1000.0, 950.0, 1050.0]);
+        let noisy_after = setup([1200.0, 950.0, 1150.0, 1000.0, 1100.0]);
+        assert_eq!(compare(&noisy_before, &noisy_after, 5.0)[0].verdict, Verdict::Noise);
+        let faster = setup([500.0, 520.0, 510.0, 530.0, 505.0]);
+        assert_eq!(compare(&noisy_before, &faster, 5.0)[0].verdict, Verdict::Improvement);
+    }
+
+    #[test]
+    fn test_report_round_trip() {
+        let path = std::env::temp_dir().join(format!("watcher-report-{}.json", std::process::id()));
+        let mut run = RunRecord::new("native", &[]);
+        run.add("Native Recursive", "setup_us", 812.0);
+        let tree = TreeFingerprint {
+            hash: "abc".to_string(),
+            files: 3,
+        };
+        let report = RunReport::new(&run, &tree, &Environment::capture(), "ok");
+        report.write(&path).unwrap();
+        assert_eq!(RunReport::load(&path).unwrap(), report);
+        fs::remove_file(&path).unwrap();
+    }
+}
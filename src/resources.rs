@@ -0,0 +1,172 @@
+use std::fs;
+use std::time::{Duration, Instant};
+
+/// Point-in-time resource usage of this process
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceSample {
+    /// Resident set size; only available where `/proc` is
+    pub rss_bytes: Option<u64>,
+    /// Open file descriptors; only available where `/proc` is
+    pub open_fds: Option<usize>,
+    /// Open inotify instances; Linux only
+    pub inotify_instances: Option<usize>,
+    /// Watches registered on this process's inotify instances; Linux only
+    pub inotify_watches: Option<usize>,
+    /// User plus system CPU time consumed so far
+    pub cpu_time: Duration,
+    /// Times a thread blocked and was later woken up
+    pub voluntary_switches: u64,
+    /// Times a thread was preempted
+    pub involuntary_switches: u64,
+}
+
+impl ResourceSample {
+    /// Sample the current process
+    pub fn take() -> Self {
+        let mut sample = Self {
+            rss_bytes: read_rss_bytes(),
+            open_fds: count_open_fds(),
+            inotify_instances: count_inotify_instances(),
+            inotify_watches: count_inotify_watches(),
+            ..Self::default()
+        };
+        sample.fill_rusage();
+        sample
+    }
+
+    #[cfg(unix)]
+    fn fill_rusage(&mut self) {
+        // SAFETY: getrusage only writes into the zeroed struct we hand it
+        let usage = unsafe {
+            let mut usage: libc::rusage = std::mem::zeroed();
+            if libc::getrusage(libc::RUSAGE_SELF, &mut usage) != 0 {
+                return;
+            }
+            usage
+        };
+        let timeval = |tv: libc::timeval| {
+            Duration::from_secs(tv.tv_sec as u64) + Duration::from_micros(tv.tv_usec as u64)
+        };
+        self.cpu_time = timeval(usage.ru_utime) + timeval(usage.ru_stime);
+        self.voluntary_switches = usage.ru_nvcsw as u64;
+        self.involuntary_switches = usage.ru_nivcsw as u64;
+    }
+
+    #[cfg(not(unix))]
+    fn fill_rusage(&mut self) {}
+
+    /// Descriptors, inotify instances and watches held beyond `baseline`, e.g. `2 fds`
+    pub fn held_beyond(&self, baseline: &ResourceSample) -> Vec<String> {
+        let counts = [
+            (self.open_fds, baseline.open_fds, "fds"),
+            (self.inotify_instances, baseline.inotify_instances, "inotify instances"),
+            (self.inotify_watches, baseline.inotify_watches, "inotify watches"),
+        ];
+        counts
+            .into_iter()
+            .filter_map(|(now, then, what)| match (now, then) {
+                (Some(now), Some(then)) if now > then => Some(format!("{} {}", now - then, what)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Resident set size in MiB, for display
+    pub fn rss_mib(&self) -> Option<f64> {
+        self.rss_bytes.map(|bytes| bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+fn read_rss_bytes() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+fn count_open_fds() -> Option<usize> {
+    Some(fs::read_dir("/proc/self/fd").ok()?.count())
+}
+
+/// Count the descriptors in `/proc/self/fd` that link to an inotify instance
+fn count_inotify_instances() -> Option<usize> {
+    let fds = fs::read_dir("/proc/self/fd").ok()?.flatten();
+    let is_inotify = |target: std::path::PathBuf| target.as_os_str() == "anon_inode:inotify";
+    Some(fds.filter(|entry| fs::read_link(entry.path()).is_ok_and(is_inotify)).count())
+}
+
+/// Sum the `inotify wd:` lines `/proc/self/fdinfo` lists for each inotify descriptor
+fn count_inotify_watches() -> Option<usize> {
+    let mut watches = 0;
+    for entry in fs::read_dir("/proc/self/fdinfo").ok()?.flatten() {
+        // Descriptors closed since the listing simply fail to read
+        if let Ok(info) = fs::read_to_string(entry.path()) {
+            watches += info.lines().filter(|line| line.starts_with("inotify wd:")).count();
+        }
+    }
+    Some(watches)
+}
+
+/// How long `verify_released` waits for a dropped watcher's event thread to close its descriptors
+pub const RELEASE_GRACE: Duration = Duration::from_secs(1);
+
+/// Check that a dropped watcher gave back everything held beyond `baseline`
+///
+/// notify closes its descriptors on its own event thread once the watcher is
+/// dropped, so this polls for up to `grace` before naming whatever is still held.
+/// Run it before deleting the watched tree: removing a watched directory makes
+/// the kernel drop its watches, which would hide a leak.
+pub fn verify_released(baseline: &ResourceSample, grace: Duration) -> Result<(), String> {
+    let start = Instant::now();
+    loop {
+        let held = ResourceSample::take().held_beyond(baseline);
+        if held.is_empty() {
+            return Ok(());
+        }
+        if start.elapsed() >= grace {
+            return Err(format!("watcher still holds {} after drop", held.join(", ")));
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Format an optional value for a table cell, `-` when unavailable
+pub fn or_dash<T: std::fmt::Display>(value: Option<T>) -> String {
+    value.map_or("-".to_string(), |v| v.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_sample() {
+        let sample = ResourceSample::take();
+        if cfg!(target_os = "linux") {
+            assert!(sample.rss_bytes.unwrap() > 0);
+            assert!(sample.open_fds.unwrap() >= 3);
+        }
+        // Spin a little so some CPU time is on the clock
+        let start = std::time::Instant::now();
+        while start.elapsed() < Duration::from_millis(20) {}
+        assert!(ResourceSample::take().cpu_time >= sample.cpu_time);
+    }
+
+    #[test]
+    fn test_held_beyond() {
+        let baseline = ResourceSample {
+            open_fds: Some(10),
+            inotify_instances: Some(0),
+            inotify_watches: None,
+            ..ResourceSample::default()
+        };
+        let after = ResourceSample {
+            open_fds: Some(13),
+            inotify_instances: Some(0),
+            inotify_watches: Some(4),
+            ..ResourceSample::default()
+        };
+        assert_eq!(after.held_beyond(&baseline), ["3 fds"]);
+        assert!(baseline.held_beyond(&after).is_empty());
+    }
+}
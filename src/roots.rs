@@ -0,0 +1,143 @@
+use notify::Event;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Setup and event metrics for one watch root
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RootMetrics {
+    pub root: PathBuf,
+    /// Files watched (manual) or reported on (native) below this root
+    pub files: usize,
+    pub setup_time: Duration,
+    pub events: usize,
+    /// Why the native backend refused this root, when it's polled instead
+    pub fallback: Option<String>,
+}
+
+/// Index of the root `path` lies under, preferring the deepest match for nested roots
+pub fn root_index(path: &Path, roots: &[PathBuf]) -> Option<usize> {
+    roots
+        .iter()
+        .enumerate()
+        .filter(|(_, root)| path.starts_with(root))
+        .max_by_key(|(_, root)| root.components().count())
+        .map(|(i, _)| i)
+}
+
+/// Absolute forms of `roots`, matching how notify reports event paths
+pub fn absolute_roots(roots: &[PathBuf]) -> Vec<PathBuf> {
+    roots
+        .iter()
+        .map(|root| std::path::absolute(root).unwrap_or_else(|_| root.clone()))
+        .collect()
+}
+
+/// Count `events` per root; an event touching several roots counts for each
+pub fn count_events(metrics: &mut [RootMetrics], events: &[Event]) {
+    let roots = absolute_roots(&metrics.iter().map(|m| m.root.clone()).collect::<Vec<_>>());
+    for event in events {
+        let mut touched: Vec<usize> = event
+            .paths
+            .iter()
+            .filter_map(|path| root_index(path, &roots))
+            .collect();
+        touched.sort_unstable();
+        touched.dedup();
+        for i in touched {
+            metrics[i].events += 1;
+        }
+    }
+}
+
+/// Sum per-file watch times into per-root setup times
+pub fn sum_watch_times(metrics: &mut [RootMetrics], files: &[PathBuf], watch_times: &[Duration]) {
+    let roots: Vec<PathBuf> = metrics.iter().map(|m| m.root.clone()).collect();
+    for (file, time) in files.iter().zip(watch_times) {
+        if let Some(i) = root_index(file, &roots) {
+            metrics[i].files += 1;
+            metrics[i].setup_time += *time;
+        }
+    }
+}
+
+/// Mark the roots a native watcher fell back to polling for, printing each one
+pub fn mark_fallback_roots(metrics: &mut [RootMetrics], fallback: &[(PathBuf, String)], indent: &str) {
+    for (root, reason) in fallback {
+        println!("{}Poll fallback: {} ({})", indent, root.display(), reason);
+        if let Some(m) = metrics.iter_mut().find(|m| &m.root == root) {
+            m.fallback = Some(reason.clone());
+        }
+    }
+}
+
+/// Print one row per root plus an aggregate row
+pub fn print_root_table(metrics: &[RootMetrics], indent: &str) {
+    println!(
+        "{}{:<40} {:>8} {:>12} {:>8}",
+        indent, "Root", "Files", "Setup", "Events"
+    );
+    for m in metrics {
+        println!(
+            "{}{:<40} {:>8} {:>12} {:>8}{}",
+            indent,
+            m.root.display(),
+            m.files,
+            format!("{:.1?}", m.setup_time),
+            m.events,
+            if m.fallback.is_some() { "  (polled)" } else { "" }
+        );
+    }
+    println!(
+        "{}{:<40} {:>8} {:>12} {:>8}",
+        indent,
+        "(all roots)",
+        metrics.iter().map(|m| m.files).sum::<usize>(),
+        format!("{:.1?}", metrics.iter().map(|m| m.setup_time).sum::<Duration>()),
+        metrics.iter().map(|m| m.events).sum::<usize>()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::EventKind;
+
+    #[test]
+    fn test_root_index_prefers_deepest() {
+        let roots = [PathBuf::from("/repo"), PathBuf::from("/repo/generated"), PathBuf::from("/other")];
+        assert_eq!(root_index(Path::new("/repo/src/a.js"), &roots), Some(0));
+        assert_eq!(root_index(Path::new("/repo/generated/b.js"), &roots), Some(1));
+        assert_eq!(root_index(Path::new("/other/c.js"), &roots), Some(2));
+        assert_eq!(root_index(Path::new("/elsewhere/d.js"), &roots), None);
+        // Component-wise, so a sibling with a common prefix doesn't match
+        assert_eq!(root_index(Path::new("/repository/e.js"), &roots), None);
+    }
+
+    #[test]
+    fn test_count_events_and_watch_times() {
+        let mut metrics: Vec<RootMetrics> = ["/a", "/b"]
+            .iter()
+            .map(|root| RootMetrics {
+                root: PathBuf::from(root),
+                ..RootMetrics::default()
+            })
+            .collect();
+        let events = [
+            Event::new(EventKind::Any).add_path(PathBuf::from("/a/1")),
+            Event::new(EventKind::Any).add_path(PathBuf::from("/a/2")).add_path(PathBuf::from("/b/2")),
+        ];
+        count_events(&mut metrics, &events);
+        assert_eq!((metrics[0].events, metrics[1].events), (2, 1));
+
+        let files = [PathBuf::from("/a/1"), PathBuf::from("/b/1"), PathBuf::from("/b/2")];
+        let times = [1, 2, 3].map(Duration::from_millis);
+        sum_watch_times(&mut metrics, &files, &times);
+        assert_eq!(metrics[0].setup_time, Duration::from_millis(1));
+        assert_eq!(metrics[1].setup_time, Duration::from_millis(5));
+        assert_eq!(metrics[1].files, 2);
+
+        mark_fallback_roots(&mut metrics, &[(PathBuf::from("/b"), "no space left".to_string())], "");
+        assert_eq!(metrics[0].fallback, None);
+        assert_eq!(metrics[1].fallback.as_deref(), Some("no space left"));
+    }
+}
@@ -0,0 +1,2229 @@
+use crate::harness::{
+    append_to_files, collect_events, copy_dir_recursive, enumerate_dir, get_filtered_files, prepare_scratch_dir,
+    collect_events_async, recover_from_overflow, scratch_name, spawn_event_collector,
+    start_async_watcher, start_event_stream, start_watcher, watched_files, write_rounds,
+    QueueDepthSampler,
+    FILTER_RATIO,
+};
+use crate::latency::{achieved_rate, match_writes, Coalescing, DurationSummary, LatencyReport, WriteRecord};
+use crate::limits::InotifyLimits;
+use crate::options::Options;
+use crate::recursive_file_watcher::{ChannelKind, EventReceiver, WatcherMode};
+use crate::resources::ResourceSample;
+use crate::stats::{is_rescan, CollectedEvents};
+use futures::StreamExt;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+
+/// Number of files each write phase touches
+const FILES_PER_PHASE: usize = 5;
+
+/// How long events are collected after each write phase
+const PHASE_COLLECT_DURATION: Duration = Duration::from_secs(2);
+
+/// Channel capacity for the overflow scenario unless `--channel-capacity` is given
+const OVERFLOW_CHANNEL_CAPACITY: usize = 64;
+
+/// Threads modifying files at the same time in the overflow scenario
+const OVERFLOW_WRITER_THREADS: usize = 4;
+
+/// Coalescing windows (ms) swept unless `--sweep-windows` is given
+const SWEEP_WINDOWS_MS: [u64; 8] = [0, 5, 10, 25, 50, 100, 250, 500];
+
+/// Rounds of appends to each file in the coalescing sweep workload
+const SWEEP_WRITE_ROUNDS: usize = 20;
+
+/// Pause between appends in the coalescing sweep workload
+const SWEEP_WRITE_INTERVAL: Duration = Duration::from_millis(2);
+
+/// Independent copies of `<directory>` the interference test watches when no `--root` is given
+const INTERFERENCE_COPIES: usize = 2;
+
+/// Rounds of appends to each root's files in the interference workload
+const INTERFERENCE_WRITE_ROUNDS: usize = 10;
+
+/// Pause between appends in the interference workload
+const INTERFERENCE_WRITE_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Per-event processing time in the slow-consumer scenario unless `--consumer-delay` is given
+const SLOW_CONSUMER_DELAY: Duration = Duration::from_millis(5);
+
+/// Files appended to in the slow-consumer workload
+const SLOW_CONSUMER_FILES: usize = 200;
+
+/// Pause between appends in the slow-consumer workload
+const SLOW_CONSUMER_WRITE_INTERVAL: Duration = Duration::from_millis(1);
+
+/// The slow consumer stops once nothing has arrived for this long after the writes
+const SLOW_CONSUMER_IDLE: Duration = Duration::from_secs(1);
+
+/// Upper bound on how long the slow consumer keeps draining
+const SLOW_CONSUMER_DRAIN_LIMIT: Duration = Duration::from_secs(60);
+
+/// Rounds of appends to each file in the async delivery workload
+const ASYNC_WRITE_ROUNDS: usize = 10;
+
+/// Pause between appends in the async delivery workload
+const ASYNC_WRITE_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Worker threads of the runtime the async consumer runs on
+const ASYNC_WORKER_THREADS: usize = 2;
+
+/// Files appended to in the streaming throughput burst
+const STREAM_BURST_FILES: usize = 500;
+
+/// Rounds of back-to-back appends in the streaming throughput burst
+const STREAM_BURST_ROUNDS: usize = 3;
+
+/// The streaming consumers stop once nothing has arrived for this long
+const STREAM_IDLE: Duration = Duration::from_millis(500);
+
+/// How the watched tree is exposed across a mount boundary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MountKind {
+    /// `mount --bind` of a backing copy; the mount point is watched
+    Bind,
+    /// overlayfs with the backing copy as lowerdir; the merged dir is watched
+    Overlay,
+    /// The backing copy is watched and shared into a container as a volume
+    Container,
+}
+
+impl MountKind {
+    /// Get display name
+    pub fn display_name(&self) -> &str {
+        match self {
+            Self::Bind => "Bind Mount",
+            Self::Overlay => "Overlayfs",
+            Self::Container => "Container Volume",
+        }
+    }
+
+    fn slug(&self) -> &str {
+        match self {
+            Self::Bind => "bind",
+            Self::Overlay => "overlay",
+            Self::Container => "container",
+        }
+    }
+}
+
+/// Where a write phase performs its modifications
+enum Writer {
+    /// Append to files below this host directory
+    Host(PathBuf),
+    /// Append from inside a container that has the backing dir mounted at `/work`
+    Container { backing: PathBuf },
+}
+
+/// A labelled set of writes performed while the watcher is active
+struct Phase {
+    label: &'static str,
+    writer: Writer,
+}
+
+/// Unmounts the target when dropped so failed runs don't leave mounts behind
+struct MountGuard {
+    target: PathBuf,
+}
+
+impl Drop for MountGuard {
+    fn drop(&mut self) {
+        if let Err(e) = run_command(Command::new("umount").arg(&self.target)) {
+            eprintln!("   Failed to unmount {}: {}", self.target.display(), e);
+        }
+    }
+}
+
+/// Run a command, turning a non-zero exit status into an error
+fn run_command(command: &mut Command) -> io::Result<()> {
+    let output = command.output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "{:?} failed: {}",
+            command,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+/// Shell script appending a marker to every file passed as a positional argument
+fn container_script() -> &'static str {
+    r#"for f in "$@"; do echo "// Modified from container" >> "/work/$f"; done"#
+}
+
+impl Writer {
+    fn write(&self, relative: &[PathBuf], options: &Options) -> io::Result<()> {
+        match self {
+            Self::Host(root) => {
+                let files: Vec<PathBuf> = relative.iter().map(|p| root.join(p)).collect();
+                append_to_files(&files, options.pace);
+                Ok(())
+            },
+            Self::Container { backing } => {
+                let volume = format!("{}:/work", fs::canonicalize(backing)?.display());
+                run_command(
+                    Command::new(&options.container_runtime)
+                        .args(["run", "--rm", "-v", &volume, &options.container_image])
+                        .args(["sh", "-c", container_script(), "sh"])
+                        .args(relative),
+                )
+            },
+        }
+    }
+}
+
+/// Run the modification test across a bind mount, overlayfs or container volume
+///
+/// Each watcher mode watches the same path while two phases of writes are made:
+/// one through the watched path and one from the other side of the boundary.
+/// The per-phase event counts show which events survive the mount.
+pub fn run_mount_test(
+    dir: &Path,
+    kind: MountKind,
+    options: &Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("\n=== {} Watch Test ===", kind.display_name());
+    println!("Source directory: {}", dir.display());
+
+    println!("\n1. Copying files to backing directory...");
+    let base = PathBuf::from("./tmp").join(format!("{}-{}", scratch_name(dir), kind.slug()));
+    if base.exists() {
+        fs::remove_dir_all(&base)?;
+    }
+    let backing = base.join("backing");
+    let mount_point = base.join("mnt");
+    copy_dir_recursive(dir, &backing, options)?;
+    println!("   Scratch directory: {}", base.display());
+
+    println!("\n2. Preparing {}...", kind.display_name());
+    let (guard, watch_path, phases) = match kind {
+        MountKind::Bind => {
+            fs::create_dir_all(&mount_point)?;
+            run_command(Command::new("mount").arg("--bind").arg(&backing).arg(&mount_point))?;
+            let phases = vec![
+                Phase { label: "via mount", writer: Writer::Host(mount_point.clone()) },
+                Phase { label: "via backing dir", writer: Writer::Host(backing.clone()) },
+            ];
+            (Some(MountGuard { target: mount_point.clone() }), mount_point.clone(), phases)
+        },
+        MountKind::Overlay => {
+            let upper = base.join("upper");
+            let work = base.join("work");
+            for path in [&upper, &work, &mount_point] {
+                fs::create_dir_all(path)?;
+            }
+            let mount_options = format!(
+                "lowerdir={},upperdir={},workdir={}",
+                fs::canonicalize(&backing)?.display(),
+                fs::canonicalize(&upper)?.display(),
+                fs::canonicalize(&work)?.display(),
+            );
+            run_command(
+                Command::new("mount")
+                    .args(["-t", "overlay", "overlay", "-o", &mount_options])
+                    .arg(&mount_point),
+            )?;
+            let phases = vec![
+                Phase { label: "via merged dir", writer: Writer::Host(mount_point.clone()) },
+                Phase { label: "via lowerdir", writer: Writer::Host(backing.clone()) },
+            ];
+            (Some(MountGuard { target: mount_point.clone() }), mount_point.clone(), phases)
+        },
+        MountKind::Container => {
+            let phases = vec![
+                Phase { label: "from host", writer: Writer::Host(backing.clone()) },
+                Phase {
+                    label: "from container",
+                    writer: Writer::Container { backing: backing.clone() },
+                },
+            ];
+            (None, backing.clone(), phases)
+        },
+    };
+    println!("   Watching: {}", watch_path.display());
+
+    // Pick targets from the filtered subset so every mode is watching them;
+    // each phase gets its own files to keep overlay copy-up from interfering
+    let watched = enumerate_dir(&watch_path, options)?;
+    let targets: Vec<PathBuf> = get_filtered_files(&watched, FILTER_RATIO)
+        .iter()
+        .filter_map(|p| p.strip_prefix(&watch_path).ok().map(Path::to_path_buf))
+        .collect();
+
+    println!("\n3. Running write phases for each watcher mode...");
+    let mut results = Vec::new();
+
+    for mode in WatcherMode::ALL {
+        println!("\n   --- {} ---", mode.display_name());
+        let (watcher, rx) = start_watcher(mode, &watch_path, options)?;
+        let mut counts = Vec::new();
+
+        for (i, phase) in phases.iter().enumerate() {
+            let files: Vec<PathBuf> = targets
+                .iter()
+                .skip(i * FILES_PER_PHASE)
+                .take(FILES_PER_PHASE)
+                .cloned()
+                .collect();
+
+            // Give watcher time to stabilize
+            std::thread::sleep(options.stabilize);
+
+            if let Err(e) = phase.writer.write(&files, options) {
+                eprintln!("   Phase '{}' failed: {}", phase.label, e);
+            }
+
+            // Events queue up in the channel, so draining after the writes loses nothing
+            let collected = collect_events(&rx, PHASE_COLLECT_DURATION);
+            println!(
+                "   {}: modified {} files, received {} events",
+                phase.label,
+                files.len(),
+                collected.events.len()
+            );
+            collected.report_overflow("   ");
+            counts.push((files.len(), collected.events.len()));
+        }
+
+        drop(watcher);
+        results.push((mode, counts));
+    }
+
+    println!("\n📊 Events surviving the {} boundary:", kind.display_name());
+    print!("  {:<20}", "Mode");
+    for phase in &phases {
+        print!(" {:>18}", phase.label);
+    }
+    println!();
+    for (mode, counts) in &results {
+        print!("  {:<20}", mode.display_name());
+        for (modified, received) in counts {
+            print!(" {:>18}", format!("{} ev / {} files", received, modified));
+        }
+        println!();
+    }
+
+    println!("\n4. Cleaning up...");
+    drop(guard);
+    fs::remove_dir_all(&base)?;
+
+    println!("\n=== {} Watch Test Complete ===\n", kind.display_name());
+
+    Ok(())
+}
+
+/// How a move into the watched tree was carried out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MoveOutcome {
+    /// `rename(2)` succeeded, so both paths were on the same filesystem
+    Renamed,
+    /// `rename(2)` failed with EXDEV and the file was copied then unlinked
+    CopiedAndUnlinked,
+}
+
+/// Move a file the way `mv` does: rename, falling back to copy+unlink across devices
+fn move_file(from: &Path, to: &Path) -> io::Result<MoveOutcome> {
+    match fs::rename(from, to) {
+        Ok(()) => Ok(MoveOutcome::Renamed),
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            fs::copy(from, to)?;
+            fs::remove_file(from)?;
+            Ok(MoveOutcome::CopiedAndUnlinked)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Check whether two paths live on different filesystems
+#[cfg(unix)]
+fn on_different_devices(a: &Path, b: &Path) -> io::Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+    Ok(fs::metadata(a)?.dev() != fs::metadata(b)?.dev())
+}
+
+#[cfg(not(unix))]
+fn on_different_devices(_a: &Path, _b: &Path) -> io::Result<bool> {
+    Ok(true)
+}
+
+/// Render a sequence of items as `A → B → C`
+fn describe_sequence<T>(items: &[T], describe: impl Fn(&T) -> String) -> String {
+    if items.is_empty() {
+        return "(no events)".to_string();
+    }
+    items
+        .iter()
+        .map(describe)
+        .collect::<Vec<_>>()
+        .join(" → ")
+}
+
+/// Move files into the watched tree from another filesystem and record what each mode reports
+///
+/// Two arrivals are tested per mode: a file landing on a new path, and a file
+/// replacing one that is already watched. Across devices `rename(2)` fails with
+/// EXDEV, so the move degrades to copy+unlink and the event sequence changes.
+pub fn run_cross_device_test(
+    dir: &Path,
+    options: &Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("\n=== Cross-Device Rename Test ===");
+    println!("Source directory: {}", dir.display());
+    println!("Foreign directory: {}", options.foreign_dir.display());
+
+    println!("\n1. Copying files to temporary directory...");
+    let tmp_dir = prepare_scratch_dir(dir, "cross-device", options)?;
+    let watched = enumerate_dir(&tmp_dir, options)?;
+    let targets = get_filtered_files(&watched, FILTER_RATIO);
+    if targets.is_empty() {
+        fs::remove_dir_all(&tmp_dir)?;
+        return Err("no files to replace in the cross-device tree".into());
+    }
+    let staging = options.foreign_dir.join(format!("watcher-benchmark-{}", std::process::id()));
+    fs::create_dir_all(&staging)?;
+
+    if !on_different_devices(&staging, &tmp_dir)? {
+        println!(
+            "   Warning: {} is on the same filesystem as {}; moves will be plain renames",
+            staging.display(),
+            tmp_dir.display()
+        );
+    }
+
+    println!("\n2. Moving files into the watched tree for each watcher mode...");
+    let mut results = Vec::new();
+
+    for (i, mode) in WatcherMode::ALL.into_iter().enumerate() {
+        println!("\n   --- {} ---", mode.display_name());
+        let (watcher, rx) = start_watcher(mode, &tmp_dir, options)?;
+        let mut sequences = Vec::new();
+
+        let arrivals = [
+            ("new path", tmp_dir.join(format!("arrived-{}.js", i))),
+            ("replace existing", targets[i % targets.len()].clone()),
+        ];
+
+        for (label, destination) in arrivals {
+            let source = staging.join(format!("incoming-{}.js", i));
+            fs::write(&source, format!("// Moved in for {}\n", mode.display_name()))?;
+
+            // Give watcher time to stabilize
+            std::thread::sleep(options.stabilize);
+
+            let outcome = move_file(&source, &destination)?;
+            let collected = collect_events(&rx, PHASE_COLLECT_DURATION);
+            let sequence = describe_sequence(&collected.events, |e| format!("{:?}", e.kind));
+            let normalized = describe_sequence(&collected.normalized(), |e| e.kind.to_string());
+            println!("   {} ({:?}): {}", label, outcome, sequence);
+            sequences.push((label, sequence, normalized));
+        }
+
+        drop(watcher);
+        results.push((mode, sequences));
+    }
+
+    println!("\n📊 Event sequences for files arriving from another filesystem:");
+    for (mode, sequences) in &results {
+        println!("  {}:", mode.display_name());
+        for (label, sequence, normalized) in sequences {
+            println!("    {:<18} {}", label, sequence);
+            println!("    {:<18} normalized: {}", "", normalized);
+        }
+    }
+
+    println!("\n3. Cleaning up...");
+    fs::remove_dir_all(&staging)?;
+    fs::remove_dir_all(&tmp_dir)?;
+
+    println!("\n=== Cross-Device Rename Test Complete ===\n");
+
+    Ok(())
+}
+
+/// Absolute path length the long-path tree goes past, beyond Windows' `MAX_PATH` of 260
+const LONG_PATH_LENGTH: usize = 300;
+
+/// Files created at the bottom of the long-path tree
+const LONG_PATH_FILES: usize = 20;
+
+/// Nest directories below `root` until their absolute path is longer than `min_len`,
+/// then create `files` files at the bottom and return them
+fn build_deep_tree(root: &Path, min_len: usize, files: usize) -> io::Result<Vec<PathBuf>> {
+    let mut dir = std::path::absolute(root)?;
+    let mut level = 0;
+    while dir.as_os_str().len() <= min_len {
+        dir.push(format!("level-{:02}-{}", level, "x".repeat(32)));
+        level += 1;
+    }
+    fs::create_dir_all(&dir)?;
+    (0..files)
+        .map(|i| {
+            let file = dir.join(format!("deep-{}.js", i));
+            fs::write(&file, format!("// Deep file {}\n", i))?;
+            Ok(file)
+        })
+        .collect()
+}
+
+/// Watch a generated tree whose paths exceed `MAX_PATH` and check every write is seen
+///
+/// On Windows this exercises the extended-length prefix the watchers add; the
+/// filtered modes also show whether prefixed event paths still match the filter set.
+pub fn run_long_path_test(
+    dir: &Path,
+    options: &Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("\n=== Long Path Test ===");
+
+    println!("\n1. Generating a tree with paths over {} characters...", LONG_PATH_LENGTH);
+    let tmp_dir = PathBuf::from("./tmp").join(format!("{}-long-paths", scratch_name(dir)));
+    if tmp_dir.exists() {
+        fs::remove_dir_all(&tmp_dir)?;
+    }
+    let deep_files = build_deep_tree(&tmp_dir, LONG_PATH_LENGTH, LONG_PATH_FILES)?;
+    let longest = deep_files.iter().map(|f| f.as_os_str().len()).max().unwrap_or(0);
+    println!("   {} files, paths up to {} characters", deep_files.len(), longest);
+
+    println!("\n2. Appending to the deep files for each watcher mode...");
+    let mut results = Vec::new();
+
+    for mode in WatcherMode::ALL {
+        println!("\n   --- {} ---", mode.display_name());
+        let (watcher, rx) = start_watcher(mode, &tmp_dir, options)?;
+        let targets = watched_files(mode, &tmp_dir, options)?;
+
+        let event_rx = spawn_event_collector(rx, PHASE_COLLECT_DURATION, Duration::ZERO);
+        // Give watcher time to stabilize
+        std::thread::sleep(options.stabilize);
+
+        let writes = append_to_files(&targets, options.pace);
+        let collected = event_rx
+            .recv_timeout(PHASE_COLLECT_DURATION + Duration::from_secs(1))
+            .unwrap_or_default();
+        drop(watcher);
+
+        let missed = match_writes(&writes, &collected).unmatched;
+        println!("   {} writes, {} events, {} missed", writes.len(), collected.events.len(), missed);
+        results.push((mode, writes.len(), missed));
+    }
+
+    println!("\n📊 Events for paths over {} characters:", LONG_PATH_LENGTH);
+    println!("  {:<20} {:>8} {:>8} {:>8}", "Mode", "Writes", "Matched", "Missed");
+    for (mode, writes, missed) in &results {
+        let status = if *missed == 0 { "✅" } else { "❌" };
+        println!(
+            "  {:<20} {:>8} {:>8} {:>8} {}",
+            mode.display_name(),
+            writes,
+            writes - missed,
+            missed,
+            status
+        );
+    }
+
+    println!("\n3. Cleaning up...");
+    fs::remove_dir_all(&tmp_dir)?;
+
+    println!("\n=== Long Path Test Complete ===\n");
+
+    Ok(())
+}
+
+/// Create a chain of `depth` nested directories below `root` with a file at every level,
+/// returned shallowest first
+fn build_nested_tree(root: &Path, depth: usize) -> io::Result<Vec<PathBuf>> {
+    let mut dir = root.to_path_buf();
+    let mut files = Vec::with_capacity(depth);
+    for level in 1..=depth {
+        dir.push("d");
+        fs::create_dir_all(&dir)?;
+        let file = dir.join(format!("level-{}.js", level));
+        fs::write(&file, format!("// Depth {}\n", level))?;
+        files.push(file);
+    }
+    Ok(files)
+}
+
+/// Number of path components below `root`, i.e. a file's nesting depth
+fn depth_below(path: &Path, root: &Path) -> usize {
+    path.strip_prefix(root).map_or(0, |rel| rel.components().count().saturating_sub(1))
+}
+
+/// Watch a single chain of directories hundreds of levels deep
+///
+/// Times the iterative enumeration, then each mode's setup and delivery for the
+/// deepest files, where recursive backends walk the whole chain and path lengths
+/// approach the platform's limits.
+pub fn run_deep_nesting_test(
+    dir: &Path,
+    options: &Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("\n=== Deep Nesting Test ===");
+
+    println!("\n1. Generating a tree {} levels deep...", options.nesting_depth);
+    let tmp_dir = std::path::absolute(PathBuf::from("./tmp").join(format!("{}-deep-nesting", scratch_name(dir))))?;
+    if tmp_dir.exists() {
+        fs::remove_dir_all(&tmp_dir)?;
+    }
+    let nested = build_nested_tree(&tmp_dir, options.nesting_depth)?;
+    let longest = nested.last().map_or(0, |f| f.as_os_str().len());
+
+    let start = Instant::now();
+    let enumerated = enumerate_dir(&tmp_dir, options)?;
+    let enumeration = start.elapsed();
+    println!(
+        "   Enumerated {} of {} files in {:.1?}; deepest path is {} characters",
+        enumerated.len(),
+        nested.len(),
+        enumeration,
+        longest
+    );
+
+    println!("\n2. Appending to the deepest files for each watcher mode...");
+    let mut results = Vec::new();
+
+    for mode in WatcherMode::EVERY {
+        println!("\n   --- {} ---", mode.display_name());
+        let start = Instant::now();
+        let (watcher, rx) = match start_watcher(mode, &tmp_dir, options) {
+            Ok(started) => started,
+            Err(e) => {
+                println!("   Setup failed: {}", e);
+                results.push((mode, Err(e.to_string())));
+                continue;
+            },
+        };
+        let setup = start.elapsed();
+        let mut targets = watched_files(mode, &tmp_dir, options)?;
+        targets.sort_by_key(|f| std::cmp::Reverse(depth_below(f, &tmp_dir)));
+        targets.truncate(FILES_PER_PHASE);
+
+        let collect_duration = PHASE_COLLECT_DURATION + options.poll_interval;
+        let event_rx = spawn_event_collector(rx, collect_duration, Duration::ZERO);
+        // Give watcher time to stabilize
+        std::thread::sleep(options.stabilize);
+
+        let writes = append_to_files(&targets, options.pace);
+        let collected = event_rx
+            .recv_timeout(collect_duration + Duration::from_secs(1))
+            .unwrap_or_default();
+        drop(watcher);
+
+        let report = match_writes(&writes, &collected);
+        let deepest = targets.first().map_or(0, |f| depth_below(f, &tmp_dir));
+        println!(
+            "   Setup {:.1?}; {} writes down to depth {}, {} missed",
+            setup,
+            writes.len(),
+            deepest,
+            report.unmatched
+        );
+        results.push((mode, Ok((setup, writes.len(), report))));
+    }
+
+    println!("\n📊 Delivery at depth {}:", options.nesting_depth);
+    println!(
+        "  {:<20} {:>12} {:>8} {:>8} {:>12}",
+        "Mode", "Setup", "Writes", "Missed", "Latency p50"
+    );
+    for (mode, result) in &results {
+        match result {
+            Ok((setup, writes, report)) => println!(
+                "  {:<20} {:>12} {:>8} {:>8} {:>12}",
+                mode.display_name(),
+                format!("{:.1?}", setup),
+                writes,
+                report.unmatched,
+                report.total().map_or("-".to_string(), |s| format!("{:.1?}", s.p50))
+            ),
+            Err(e) => println!("  {:<20} setup failed: {}", mode.display_name(), e),
+        }
+    }
+
+    println!("\n3. Cleaning up...");
+    fs::remove_dir_all(&tmp_dir)?;
+
+    println!("\n=== Deep Nesting Test Complete ===\n");
+
+    Ok(())
+}
+
+/// Modes the few-file scenarios compare; the filtered modes would leave most of the files unwatched
+const UNFILTERED_MODES: [WatcherMode; 4] = [
+    WatcherMode::Manual,
+    WatcherMode::Native,
+    WatcherMode::Poll,
+    WatcherMode::Debounced,
+];
+
+/// Bytes written by an append or in-place change, and the chunk size files are filled with
+const LARGE_FILE_PATCH: usize = 4096;
+const LARGE_FILE_CHUNK: usize = 1024 * 1024;
+
+/// How one large file is changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LargeWrite {
+    /// A few KiB added at the end
+    Append,
+    /// A few KiB overwritten in the middle, keeping the size
+    InPlace,
+    /// The whole file truncated and written again
+    Rewrite,
+}
+
+impl LargeWrite {
+    const ALL: [Self; 3] = [Self::Append, Self::InPlace, Self::Rewrite];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Append => "append",
+            Self::InPlace => "in-place",
+            Self::Rewrite => "rewrite",
+        }
+    }
+
+    /// Make this change to `path`, a file of `size` bytes
+    fn apply(&self, path: &Path, size: u64) -> io::Result<WriteRecord> {
+        use std::io::{Seek, SeekFrom, Write};
+        let started_at = Instant::now();
+        match self {
+            Self::Append => fs::OpenOptions::new()
+                .append(true)
+                .open(path)?
+                .write_all(&[b'+'; LARGE_FILE_PATCH])?,
+            Self::InPlace => {
+                let mut file = fs::OpenOptions::new().write(true).open(path)?;
+                file.seek(SeekFrom::Start(size / 2))?;
+                file.write_all(&[b'~'; LARGE_FILE_PATCH])?;
+            },
+            Self::Rewrite => fill_file(path, size, b'#')?,
+        }
+        Ok(WriteRecord {
+            path: std::path::absolute(path)?,
+            started_at,
+            written_at: Instant::now(),
+        })
+    }
+}
+
+/// Write `size` bytes of `byte` to `path` in large chunks, replacing its contents
+fn fill_file(path: &Path, size: u64, byte: u8) -> io::Result<()> {
+    use std::io::Write;
+    let chunk = vec![byte; LARGE_FILE_CHUNK];
+    let mut file = io::BufWriter::new(fs::File::create(path)?);
+    let mut left = size;
+    while left > 0 {
+        let n = left.min(LARGE_FILE_CHUNK as u64) as usize;
+        file.write_all(&chunk[..n])?;
+        left -= n as u64;
+    }
+    file.flush()
+}
+
+/// When the first event naming `path` arrived at or after `since`
+fn first_event_after(collected: &CollectedEvents, path: &Path, since: Instant) -> Option<Instant> {
+    collected
+        .events
+        .iter()
+        .zip(&collected.received_at)
+        .filter(|(event, &at)| at >= since && event.paths.iter().any(|p| p == path))
+        .map(|(_, &at)| at)
+        .min()
+}
+
+/// Append to, patch and rewrite multi-hundred-MB files and time detection per mode
+///
+/// Detection is measured from the start of each change, since a rewrite of a large
+/// file is usually reported while it is still being written. The poll watcher
+/// hashes file contents on every scan, so its CPU time grows with the file size.
+pub fn run_large_file_test(
+    dir: &Path,
+    options: &Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("\n=== Large File Modification Test ===");
+
+    let size = options.large_file_mib * 1024 * 1024;
+    println!("\n1. Creating {} files of {} MiB...", LargeWrite::ALL.len(), options.large_file_mib);
+    let tmp_dir = std::path::absolute(PathBuf::from("./tmp").join(format!("{}-large-files", scratch_name(dir))))?;
+    if tmp_dir.exists() {
+        fs::remove_dir_all(&tmp_dir)?;
+    }
+    fs::create_dir_all(&tmp_dir)?;
+    let files: Vec<(LargeWrite, PathBuf)> = LargeWrite::ALL
+        .iter()
+        .map(|&change| (change, tmp_dir.join(format!("large-{}.bin", change.name()))))
+        .collect();
+
+    println!("\n2. Changing each file for each watcher mode...");
+    let phase = PHASE_COLLECT_DURATION + options.poll_interval * 2;
+    let mut results = Vec::new();
+
+    for mode in UNFILTERED_MODES {
+        println!("\n   --- {} ---", mode.display_name());
+        // Fresh contents each round, so the poll watcher's hashes start from scratch too
+        for (_, file) in &files {
+            fill_file(file, size, b'.')?;
+        }
+        let (watcher, rx) = start_watcher(mode, &tmp_dir, options)?;
+        let event_rx = spawn_event_collector(rx, phase * files.len() as u32 + phase, Duration::ZERO);
+        // Give watcher time to stabilize
+        std::thread::sleep(options.stabilize + options.poll_interval);
+
+        let before = ResourceSample::take();
+        let mut writes = Vec::new();
+        for (change, file) in &files {
+            let write = change.apply(file, size)?;
+            println!("   {} took {:.1?}", change.name(), write.written_at - write.started_at);
+            writes.push((*change, write));
+            std::thread::sleep(phase);
+        }
+        let cpu = ResourceSample::take().cpu_time.saturating_sub(before.cpu_time);
+        let collected = event_rx
+            .recv_timeout(phase * 2 + Duration::from_secs(1))
+            .unwrap_or_default();
+        drop(watcher);
+
+        let detected: Vec<(LargeWrite, Option<Duration>)> = writes
+            .iter()
+            .map(|(change, write)| {
+                let at = first_event_after(&collected, &write.path, write.started_at);
+                (*change, at.map(|at| at - write.started_at))
+            })
+            .collect();
+        results.push((mode, detected, cpu));
+    }
+
+    println!("\n📊 Detection latency from the start of each change ({} MiB files):", options.large_file_mib);
+    print!("  {:<20}", "Mode");
+    for change in LargeWrite::ALL {
+        print!(" {:>12}", change.name());
+    }
+    println!(" {:>10}", "CPU");
+    for (mode, detected, cpu) in &results {
+        print!("  {:<20}", mode.display_name());
+        for (_, latency) in detected {
+            print!(" {:>12}", latency.map_or("missed".to_string(), |l| format!("{:.1?}", l)));
+        }
+        println!(" {:>10}", format!("{:.1?}", cpu));
+    }
+
+    println!("\n3. Cleaning up...");
+    fs::remove_dir_all(&tmp_dir)?;
+
+    println!("\n=== Large File Modification Test Complete ===\n");
+
+    Ok(())
+}
+
+/// How long `test-small-writes` keeps appending for each mode
+const LOG_BURST: Duration = Duration::from_secs(2);
+
+/// Append one short line at a time to `files` in turn, holding each open like a logger
+///
+/// Writes are paced against a deadline so sleep overshoot doesn't accumulate; when
+/// the machine can't keep up the achieved rate shows it.
+fn append_log_lines(files: &[PathBuf], rate: u64, duration: Duration) -> io::Result<Vec<WriteRecord>> {
+    use std::io::Write;
+    let mut handles = files
+        .iter()
+        .map(|path| fs::OpenOptions::new().append(true).open(path))
+        .collect::<io::Result<Vec<_>>>()?;
+    let interval = Duration::from_secs_f64(1.0 / rate as f64);
+    let mut writes = Vec::new();
+    let start = Instant::now();
+    for i in 0.. {
+        let deadline = start + interval * i;
+        if deadline - start >= duration {
+            break;
+        }
+        if let Some(wait) = deadline.checked_duration_since(Instant::now()) {
+            std::thread::sleep(wait);
+        }
+        let which = i as usize % files.len();
+        let started_at = Instant::now();
+        writeln!(handles[which], "[{}] request handled", i)?;
+        writes.push(WriteRecord {
+            path: files[which].clone(),
+            started_at,
+            written_at: Instant::now(),
+        });
+    }
+    Ok(writes)
+}
+
+/// Append thousands of tiny lines per second to a few files, as logs inside a watched tree do
+///
+/// Reports how many events each backend emits per write and how long it goes quiet
+/// while writes continue, which is where coalescing and debouncing show up; stale
+/// files are ones whose last line was never followed by an event.
+pub fn run_small_writes_test(
+    dir: &Path,
+    options: &Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("\n=== Many Small Writes Test ===");
+
+    println!("\n1. Creating {} log files...", options.log_files);
+    let tmp_dir = std::path::absolute(PathBuf::from("./tmp").join(format!("{}-small-writes", scratch_name(dir))))?;
+    if tmp_dir.exists() {
+        fs::remove_dir_all(&tmp_dir)?;
+    }
+    fs::create_dir_all(&tmp_dir)?;
+    let files: Vec<PathBuf> = (0..options.log_files.max(1))
+        .map(|i| tmp_dir.join(format!("app-{}.log", i)))
+        .collect();
+    for file in &files {
+        fs::write(file, "")?;
+    }
+
+    println!(
+        "\n2. Appending at {} writes/s for {:?} per watcher mode...",
+        options.log_rate, LOG_BURST
+    );
+    let collect_duration = LOG_BURST + PHASE_COLLECT_DURATION + options.poll_interval;
+    let mut results = Vec::new();
+
+    for mode in UNFILTERED_MODES {
+        println!("\n   --- {} ---", mode.display_name());
+        let (watcher, rx) = start_watcher(mode, &tmp_dir, options)?;
+        let event_rx = spawn_event_collector(rx, collect_duration, Duration::ZERO);
+        // Give watcher time to stabilize
+        std::thread::sleep(options.stabilize);
+
+        let writes = append_log_lines(&files, options.log_rate, LOG_BURST)?;
+        let collected = event_rx
+            .recv_timeout(collect_duration + Duration::from_secs(1))
+            .unwrap_or_default();
+        drop(watcher);
+
+        let coalescing = Coalescing::measure(&writes, &collected);
+        println!(
+            "   {} writes ({:.0}/s), {} events",
+            coalescing.writes,
+            achieved_rate(&writes),
+            coalescing.events
+        );
+        results.push((mode, achieved_rate(&writes), coalescing));
+    }
+
+    println!("\n📊 Events per write ({} files):", files.len());
+    println!(
+        "  {:<20} {:>8} {:>10} {:>8} {:>10} {:>12} {:>10} {:>6}",
+        "Mode", "Writes", "Rate/s", "Events", "Ev/write", "Max gap", "Tail", "Stale"
+    );
+    for (mode, rate, coalescing) in &results {
+        println!(
+            "  {:<20} {:>8} {:>10.0} {:>8} {:>10.3} {:>12} {:>10} {:>6}",
+            mode.display_name(),
+            coalescing.writes,
+            rate,
+            coalescing.events,
+            coalescing.events_per_write(),
+            format!("{:.1?}", coalescing.max_gap),
+            coalescing.tail.map_or("-".to_string(), |t| format!("{:.1?}", t)),
+            coalescing.stale_files
+        );
+    }
+
+    println!("\n3. Cleaning up...");
+    fs::remove_dir_all(&tmp_dir)?;
+
+    println!("\n=== Many Small Writes Test Complete ===\n");
+
+    Ok(())
+}
+
+/// File names `test-unusual-names` creates; each one trips up some platform or path comparison
+const UNUSUAL_NAMES: [&str; 10] = [
+    "ünïcödé.js",
+    "日本語.js",
+    "🚀 launch.js",
+    "with spaces.js",
+    " leading space.js",
+    "trailing dot.js.",
+    "dots...js",
+    // "café" with a combining accent (NFD), which macOS may report precomposed
+    "cafe\u{301}.js",
+    "hash#and&amp.js",
+    "semi;colon'quote.js",
+];
+
+/// Unusual names this platform can create; Win32 silently strips trailing dots
+fn unusual_names() -> Vec<&'static str> {
+    UNUSUAL_NAMES
+        .into_iter()
+        .filter(|name| !(cfg!(windows) && name.ends_with('.')))
+        .collect()
+}
+
+/// Create one file per unusual name in `root` and a subdirectory holding the same set
+fn build_unusual_tree(root: &Path) -> io::Result<Vec<PathBuf>> {
+    let nested = root.join("sub dir ✨");
+    fs::create_dir_all(&nested)?;
+    let mut files = Vec::new();
+    for dir in [root, nested.as_path()] {
+        for name in unusual_names() {
+            let file = dir.join(name);
+            fs::write(&file, format!("// {}\n", name))?;
+            files.push(file);
+        }
+    }
+    Ok(files)
+}
+
+/// Watch files with unicode, emoji, spaces and trailing dots in their names
+///
+/// Enumeration is checked against the files created, and every mode's events
+/// against the writes, so a name that the filter set or the latency matcher
+/// spells differently from the backend shows up as a per-mode mismatch.
+pub fn run_unusual_names_test(
+    dir: &Path,
+    options: &Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("\n=== Unusual File Names Test ===");
+
+    println!("\n1. Generating files with unusual names...");
+    let tmp_dir = std::path::absolute(PathBuf::from("./tmp").join(format!("{}-unusual-names", scratch_name(dir))))?;
+    if tmp_dir.exists() {
+        fs::remove_dir_all(&tmp_dir)?;
+    }
+    let created = build_unusual_tree(&tmp_dir)?;
+    let enumerated: HashSet<PathBuf> = enumerate_dir(&tmp_dir, options)?.into_iter().collect();
+    let unlisted: Vec<&PathBuf> = created.iter().filter(|f| !enumerated.contains(*f)).collect();
+    println!("   Created {} files, enumerated {}", created.len(), enumerated.len());
+    for file in &unlisted {
+        println!("   ❌ Not enumerated: {:?}", file.file_name().unwrap_or_default());
+    }
+
+    println!("\n2. Appending to every file for each watcher mode...");
+    let mut results = Vec::new();
+
+    for mode in WatcherMode::EVERY {
+        println!("\n   --- {} ---", mode.display_name());
+        let (watcher, rx) = start_watcher(mode, &tmp_dir, options)?;
+        let targets = watched_files(mode, &tmp_dir, options)?;
+
+        let collect_duration = PHASE_COLLECT_DURATION + options.poll_interval;
+        let event_rx = spawn_event_collector(rx, collect_duration, Duration::ZERO);
+        // Give watcher time to stabilize
+        std::thread::sleep(options.stabilize);
+
+        let writes = append_to_files(&targets, options.pace);
+        let collected = event_rx
+            .recv_timeout(collect_duration + Duration::from_secs(1))
+            .unwrap_or_default();
+        drop(watcher);
+
+        // A write is unmatched when no event arrived for exactly its path
+        let seen: HashSet<PathBuf> = collected.events.iter().flat_map(|e| e.paths.iter().cloned()).collect();
+        let missed: Vec<String> = writes
+            .iter()
+            .filter(|w| !seen.contains(&w.path))
+            .map(|w| format!("{:?}", w.path.strip_prefix(&tmp_dir).unwrap_or(&w.path)))
+            .collect();
+        println!("   {} writes, {} events, {} unmatched", writes.len(), collected.events.len(), missed.len());
+        results.push((mode, writes.len(), missed));
+    }
+
+    println!("\n📊 Path matching for unusual names:");
+    println!("  {:<20} {:>8} {:>10}", "Mode", "Writes", "Unmatched");
+    for (mode, writes, missed) in &results {
+        println!("  {:<20} {:>8} {:>10}", mode.display_name(), writes, missed.len());
+        for name in missed {
+            println!("  {:<20}   {}", "", name);
+        }
+    }
+    if !unlisted.is_empty() {
+        println!("  {} created files were missing from enumeration", unlisted.len());
+    }
+
+    println!("\n3. Cleaning up...");
+    fs::remove_dir_all(&tmp_dir)?;
+
+    println!("\n=== Unusual File Names Test Complete ===\n");
+
+    Ok(())
+}
+
+/// `\\localhost\C$\...` for a local absolute path: the administrative share that
+/// reaches the same directory through the SMB redirector
+fn admin_share(path: &str) -> Option<String> {
+    let mut chars = path.chars();
+    let drive = chars.next().filter(char::is_ascii_alphabetic)?;
+    let rest = chars.as_str().strip_prefix(':')?;
+    if !rest.starts_with(['\\', '/']) {
+        return None;
+    }
+    Some(format!(r"\\localhost\{}${}", drive.to_ascii_uppercase(), rest.replace('/', "\\")))
+}
+
+/// Watch a tree through a `\\server\share` UNC path and report what each mode delivers
+///
+/// Without `--unc-path` the scratch copy is reached through its `\\localhost\C$`
+/// administrative share. Over SMB, `ReadDirectoryChangesW` only sees what the
+/// server forwards as change notifications and fails outright with buffers over
+/// 64 KiB; the poll watcher sees everything but pays a round trip per stat.
+pub fn run_unc_test(
+    dir: &Path,
+    options: &Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !cfg!(windows) {
+        return Err("test-unc needs Windows; UNC paths are a Windows concept".into());
+    }
+    println!("\n=== UNC Path Test ===");
+    println!("Source directory: {}", dir.display());
+
+    println!("\n1. Copying files to the share...");
+    let (unc_root, cleanup) = match &options.unc_path {
+        Some(share) => {
+            let target = share.join(format!("{}-unc", scratch_name(dir)));
+            if target.exists() {
+                fs::remove_dir_all(&target)?;
+            }
+            copy_dir_recursive(dir, &target, options)?;
+            (target.clone(), target)
+        },
+        None => {
+            let local = std::path::absolute(prepare_scratch_dir(dir, "unc", options)?)?;
+            let share = local
+                .to_str()
+                .and_then(admin_share)
+                .ok_or_else(|| format!("no administrative share for {}", local.display()))?;
+            (PathBuf::from(share), local)
+        },
+    };
+    println!("   Watching {}", unc_root.display());
+
+    println!("\n2. Appending through the share for each watcher mode...");
+    let mut results = Vec::new();
+
+    for mode in WatcherMode::EVERY {
+        println!("\n   --- {} ---", mode.display_name());
+        let (watcher, rx) = match start_watcher(mode, &unc_root, options) {
+            Ok(started) => started,
+            Err(e) => {
+                println!("   Could not watch the share: {}", e);
+                results.push((mode, Err(e.to_string())));
+                continue;
+            },
+        };
+        let targets: Vec<PathBuf> = watched_files(mode, &unc_root, options)?
+            .into_iter()
+            .take(FILES_PER_PHASE)
+            .collect();
+
+        let collect_duration = PHASE_COLLECT_DURATION + options.poll_interval;
+        let event_rx = spawn_event_collector(rx, collect_duration, Duration::ZERO);
+        // Give watcher time to stabilize
+        std::thread::sleep(options.stabilize);
+
+        let writes = append_to_files(&targets, options.pace);
+        let collected = event_rx
+            .recv_timeout(collect_duration + Duration::from_secs(1))
+            .unwrap_or_default();
+        drop(watcher);
+
+        let sample = collected.events.iter().flat_map(|e| e.paths.first()).next().cloned();
+        let missed = match_writes(&writes, &collected).unmatched;
+        println!("   {} writes, {} events, {} missed", writes.len(), collected.events.len(), missed);
+        if let Some(path) = &sample {
+            println!("   Event paths look like {}", path.display());
+        }
+        results.push((mode, Ok((writes.len(), collected.events.len(), missed))));
+    }
+
+    println!("\n📊 Delivery through {}:", unc_root.display());
+    println!("  {:<20} {:>8} {:>8} {:>8}", "Mode", "Writes", "Events", "Missed");
+    for (mode, result) in &results {
+        match result {
+            Ok((writes, events, missed)) => println!(
+                "  {:<20} {:>8} {:>8} {:>8}",
+                mode.display_name(),
+                writes,
+                events,
+                missed
+            ),
+            Err(e) => println!("  {:<20} setup failed: {}", mode.display_name(), e),
+        }
+    }
+
+    println!("\n3. Cleaning up...");
+    fs::remove_dir_all(&cleanup)?;
+
+    println!("\n=== UNC Path Test Complete ===\n");
+
+    Ok(())
+}
+
+/// Modify every file as fast as possible from several threads at once
+fn modify_concurrently(files: &[PathBuf], threads: usize) {
+    let chunk_size = files.len().div_ceil(threads.max(1)).max(1);
+    std::thread::scope(|scope| {
+        for chunk in files.chunks(chunk_size) {
+            scope.spawn(move || append_to_files(chunk, Duration::ZERO));
+        }
+    });
+}
+
+/// Try to overflow the kernel event queue and report what each mode makes of it
+///
+/// Every file in the tree is modified simultaneously while the consumer is stalled
+/// behind a small bounded channel. Once notify's thread blocks on the full channel
+/// the kernel queue fills up; afterwards the backlog is drained and checked for
+/// rescan signals and for watched files that never produced an event.
+pub fn run_overflow_test(
+    dir: &Path,
+    options: &Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("\n=== Queue Overflow Stress Test ===");
+    println!("Source directory: {}", dir.display());
+
+    let mut stress_options = options.clone();
+    let capacity = *stress_options
+        .channel_capacity
+        .get_or_insert(OVERFLOW_CHANNEL_CAPACITY);
+    println!("Event channel capacity: {}", capacity);
+    if let Some(limits) = InotifyLimits::read() {
+        println!("Kernel queue size (fs.inotify.max_queued_events): {}", limits.max_queued_events);
+    }
+
+    println!("\n1. Copying files to temporary directory...");
+    let tmp_dir = std::path::absolute(prepare_scratch_dir(dir, "overflow", options)?)?;
+    let all_files = enumerate_dir(&tmp_dir, options)?;
+    println!("   {} files to modify", all_files.len());
+
+    println!("\n2. Flooding the tree for each watcher mode...");
+    let mut results = Vec::new();
+
+    for mode in WatcherMode::ALL {
+        println!("\n   --- {} ---", mode.display_name());
+        let (watcher, rx) = start_watcher(mode, &tmp_dir, &stress_options)?;
+        let expected: HashSet<PathBuf> = watched_files(mode, &tmp_dir, options)?.into_iter().collect();
+
+        // Give watcher time to stabilize
+        std::thread::sleep(options.stabilize);
+
+        // The consumer stays stalled until the writers are done
+        let sampler = QueueDepthSampler::start(rx.depth());
+        let backpressure_before = rx.backpressure();
+        let write_start = std::time::Instant::now();
+        modify_concurrently(&all_files, OVERFLOW_WRITER_THREADS);
+        println!(
+            "   Modified {} files with {} threads in {:?}",
+            all_files.len(),
+            OVERFLOW_WRITER_THREADS,
+            write_start.elapsed()
+        );
+        std::thread::sleep(Duration::from_millis(200));
+
+        let mut collected = collect_events(&rx, PHASE_COLLECT_DURATION);
+        // Cover the flood as well as the drain
+        collected.queue_depth = sampler.finish();
+        collected.backpressure = rx.backpressure().since(&backpressure_before);
+        recover_from_overflow(&mut collected, std::slice::from_ref(&tmp_dir), options);
+        // Access-only notifications don't count as seeing the change
+        let normalized = collected.normalized();
+        let seen: HashSet<&Path> = normalized.iter().map(|e| e.path.as_path()).collect();
+        let missed = expected.iter().filter(|p| !seen.contains(p.as_path())).count();
+
+        println!(
+            "   Received {} events, {} of {} watched files missed",
+            collected.events.len(),
+            missed,
+            expected.len()
+        );
+        collected.report_overflow("   ");
+        collected.queue_depth.report("   ");
+        collected.backpressure.report("   ");
+
+        drop(watcher);
+        results.push((mode, missed, expected.len(), collected));
+    }
+
+    println!("\n📊 Queue overflow results:");
+    println!(
+        "  {:<20} {:>10} {:>9} {:>10} {:>14} {:>12} {:>10} {:>10} {:>9} {:>9}",
+        "Mode", "Events", "Overflow", "Watched", "Lost files", "Recovery", "Max queue", "Mean queue",
+        "Blocked", "Dropped"
+    );
+    for (mode, missed, watched, collected) in &results {
+        let lost = format!(
+            "{} ({:.1}%)",
+            missed,
+            *missed as f64 / (*watched).max(1) as f64 * 100.0
+        );
+        let recovery = collected
+            .recovery_time
+            .map(|d| format!("{:?}", d))
+            .unwrap_or_else(|| "-".to_string());
+        println!(
+            "  {:<20} {:>10} {:>9} {:>10} {:>14} {:>12} {:>10} {:>10.1} {:>9} {:>9}",
+            mode.display_name(),
+            collected.events.len(),
+            if collected.overflowed() { "yes" } else { "no" },
+            watched,
+            lost,
+            recovery,
+            collected.queue_depth.max,
+            collected.queue_depth.mean,
+            collected.backpressure.blocked,
+            collected.backpressure.dropped
+        );
+    }
+
+    println!("\n3. Cleaning up...");
+    fs::remove_dir_all(&tmp_dir)?;
+
+    println!("\n=== Queue Overflow Stress Test Complete ===\n");
+
+    Ok(())
+}
+
+/// Windows to sweep: the `--sweep-windows` list, or the built-in ladder
+fn sweep_windows(options: &Options) -> Vec<Duration> {
+    if options.sweep_windows.is_empty() {
+        SWEEP_WINDOWS_MS.map(Duration::from_millis).to_vec()
+    } else {
+        options.sweep_windows.clone()
+    }
+}
+
+/// Replay one burst workload per mode through the coalescer at several window sizes
+///
+/// Each mode's events are recorded once, then coalesced offline at every window,
+/// so the ratios within a mode are directly comparable.
+pub fn run_coalesce_sweep(
+    dir: &Path,
+    options: &Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("\n=== Coalescing Window Sweep ===");
+    println!("Source directory: {}", dir.display());
+
+    let windows = sweep_windows(options);
+    println!("Windows: {}", describe_sequence(&windows, |w| format!("{:?}", w)));
+
+    println!("\n1. Copying files to temporary directory...");
+    let tmp_dir = std::path::absolute(prepare_scratch_dir(dir, "coalesce-sweep", options)?)?;
+
+    println!("\n2. Recording a burst workload for each watcher mode...");
+    let mut results = Vec::new();
+
+    for mode in WatcherMode::ALL {
+        println!("\n   --- {} ---", mode.display_name());
+        let (watcher, rx) = start_watcher(mode, &tmp_dir, options)?;
+        let targets: Vec<PathBuf> = watched_files(mode, &tmp_dir, options)?
+            .into_iter()
+            .take(FILES_PER_PHASE)
+            .collect();
+
+        // Collect while writing so arrival times reflect delivery, not a later drain
+        let collect_duration = PHASE_COLLECT_DURATION + options.stabilize;
+        let event_rx = spawn_event_collector(rx, collect_duration, Duration::ZERO);
+
+        // Give watcher time to stabilize
+        std::thread::sleep(options.stabilize);
+
+        for _ in 0..SWEEP_WRITE_ROUNDS {
+            append_to_files(&targets, SWEEP_WRITE_INTERVAL);
+        }
+        println!(
+            "   Appended to {} files {} times each",
+            targets.len(),
+            SWEEP_WRITE_ROUNDS
+        );
+
+        let collected = event_rx
+            .recv_timeout(collect_duration + Duration::from_secs(1))
+            .unwrap_or_default();
+        println!("   Received {} events", collected.events.len());
+        collected.report_overflow("   ");
+
+        drop(watcher);
+        let stats: Vec<_> = windows.iter().map(|&window| collected.coalesce(window)).collect();
+        results.push((mode, stats));
+    }
+
+    println!("\n📊 Coalescing sweep results:");
+    println!(
+        "  {:<20} {:>10} {:>8} {:>11} {:>10} {:>8} {:>14} {:>14}",
+        "Mode", "Window", "Raw", "Normalized", "Delivered", "Ratio", "Mean +latency", "Max +latency"
+    );
+    for (mode, stats) in &results {
+        for stat in stats {
+            println!(
+                "  {:<20} {:>10} {:>8} {:>11} {:>10} {:>7.2}x {:>14} {:>14}",
+                mode.display_name(),
+                format!("{:?}", stat.window),
+                stat.raw,
+                stat.normalized,
+                stat.coalesced,
+                stat.compression_ratio(),
+                format!("{:.1?}", stat.mean_added_latency),
+                format!("{:.1?}", stat.max_added_latency)
+            );
+        }
+    }
+
+    println!("\n3. Cleaning up...");
+    fs::remove_dir_all(&tmp_dir)?;
+
+    println!("\n=== Coalescing Window Sweep Complete ===\n");
+
+    Ok(())
+}
+
+/// CPU time and context switches consumed while nothing happened on disk
+#[derive(Debug, Clone, Copy)]
+struct IdleCost {
+    duration: Duration,
+    cpu_time: Duration,
+    wakeups: u64,
+    preemptions: u64,
+}
+
+impl IdleCost {
+    fn between(before: ResourceSample, after: ResourceSample, duration: Duration) -> Self {
+        Self {
+            duration,
+            cpu_time: after.cpu_time.saturating_sub(before.cpu_time),
+            wakeups: after.voluntary_switches.saturating_sub(before.voluntary_switches),
+            preemptions: after.involuntary_switches.saturating_sub(before.involuntary_switches),
+        }
+    }
+
+    fn cpu_percent(&self) -> f64 {
+        self.cpu_time.as_secs_f64() / self.duration.as_secs_f64().max(f64::EPSILON) * 100.0
+    }
+
+    fn wakeups_per_sec(&self) -> f64 {
+        self.wakeups as f64 / self.duration.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// Sleep through `duration` and measure what the whole process consumed meanwhile
+fn measure_idle(duration: Duration) -> IdleCost {
+    let before = ResourceSample::take();
+    let start = std::time::Instant::now();
+    std::thread::sleep(duration);
+    IdleCost::between(before, ResourceSample::take(), start.elapsed())
+}
+
+/// Run each watcher with no filesystem activity and report the CPU time and wakeups it costs
+///
+/// The consumer blocks in `recv` with no timeout, so anything above the
+/// no-watcher baseline is the backend's own idle work.
+pub fn run_idle_test(dir: &Path, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    println!("\n=== Idle Overhead Test ===");
+    println!("Source directory: {}", dir.display());
+    println!("Idle period per mode: {:?}", options.idle_duration);
+
+    println!("\n1. Copying files to temporary directory...");
+    let tmp_dir = std::path::absolute(prepare_scratch_dir(dir, "idle", options)?)?;
+
+    println!("\n2. Measuring baseline with no watcher...");
+    let baseline = measure_idle(options.idle_duration);
+    let mut results = Vec::new();
+
+    println!("\n3. Measuring each watcher mode...");
+    for mode in WatcherMode::ALL {
+        println!("\n   --- {} ---", mode.display_name());
+        let (watcher, rx) = start_watcher(mode, &tmp_dir, options)?;
+        let consumer = std::thread::spawn(move || {
+            let mut received = 0;
+            while rx.recv().is_ok() {
+                received += 1;
+            }
+            received
+        });
+
+        // Let setup work settle before the clock starts
+        std::thread::sleep(Duration::from_millis(200));
+        let cost = measure_idle(options.idle_duration);
+
+        drop(watcher);
+        let received = consumer.join().unwrap_or(0);
+        if received > 0 {
+            println!("   Note: {} events arrived during the idle period", received);
+        }
+        results.push((mode.display_name(), cost));
+    }
+
+    println!("\n📊 Idle overhead results:");
+    println!(
+        "  {:<22} {:>12} {:>8} {:>10} {:>12} {:>12}",
+        "Mode", "CPU time", "CPU %", "Wakeups", "Wakeups/s", "Preemptions"
+    );
+    for (name, cost) in [("No watcher (baseline)", baseline)].into_iter().chain(results) {
+        println!(
+            "  {:<22} {:>12} {:>8.3} {:>10} {:>12.1} {:>12}",
+            name,
+            format!("{:.1?}", cost.cpu_time),
+            cost.cpu_percent(),
+            cost.wakeups,
+            cost.wakeups_per_sec(),
+            cost.preemptions
+        );
+    }
+
+    println!("\n4. Cleaning up...");
+    fs::remove_dir_all(&tmp_dir)?;
+
+    println!("\n=== Idle Overhead Test Complete ===\n");
+
+    Ok(())
+}
+
+/// Latency of one root watched on its own versus alongside the other roots
+struct Interference {
+    mode: WatcherMode,
+    root: PathBuf,
+    isolated: LatencyReport,
+    concurrent: LatencyReport,
+}
+
+impl Interference {
+    /// Concurrent p50 over isolated p50; above 1 means the other roots slowed this one down
+    fn slowdown(isolated: Option<DurationSummary>, concurrent: Option<DurationSummary>) -> Option<f64> {
+        let isolated = isolated?.p50.as_secs_f64();
+        let concurrent = concurrent?.p50.as_secs_f64();
+        (isolated > 0.0).then(|| concurrent / isolated)
+    }
+}
+
+/// Watch each root with its own `mode` watcher, write to all of them at once and
+/// match each root's writes against that root's events
+fn watch_and_write(
+    mode: WatcherMode,
+    roots: &[PathBuf],
+    options: &Options,
+) -> Result<Vec<LatencyReport>, Box<dyn std::error::Error>> {
+    let collect_duration = PHASE_COLLECT_DURATION + options.stabilize;
+    let mut watchers = Vec::new();
+    let mut collectors = Vec::new();
+    let mut targets = Vec::new();
+    for root in roots {
+        let (watcher, rx) = start_watcher(mode, root, options)?;
+        watchers.push(watcher);
+        collectors.push(spawn_event_collector(rx, collect_duration, Duration::ZERO));
+        targets.push(
+            watched_files(mode, root, options)?
+                .into_iter()
+                .take(FILES_PER_PHASE)
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    // Give watchers time to stabilize
+    std::thread::sleep(options.stabilize);
+
+    let writes: Vec<_> = std::thread::scope(|scope| {
+        let writers: Vec<_> = targets
+            .iter()
+            .map(|files| {
+                scope.spawn(move || {
+                    write_rounds(files, INTERFERENCE_WRITE_ROUNDS, INTERFERENCE_WRITE_INTERVAL)
+                })
+            })
+            .collect();
+        writers
+            .into_iter()
+            .map(|writer| writer.join().unwrap_or_default())
+            .collect()
+    });
+
+    let reports = collectors
+        .into_iter()
+        .zip(&writes)
+        .map(|(collector, writes)| {
+            let collected = collector
+                .recv_timeout(collect_duration + Duration::from_secs(1))
+                .unwrap_or_default();
+            collected.report_overflow("   ");
+            match_writes(writes, &collected)
+        })
+        .collect();
+    drop(watchers);
+
+    Ok(reports)
+}
+
+/// Measure whether watching several roots at once degrades latency for each of them
+///
+/// Every root gets its own watcher, the way an editor with several workspace
+/// folders would set them up. Each root is first measured alone, then all roots
+/// are watched and written to simultaneously and the latencies are compared.
+/// Without `--root`, `<directory>` is copied twice to get independent roots.
+pub fn run_interference_test(
+    roots: &[PathBuf],
+    options: &Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("\n=== Multi-Root Interference Test ===");
+    for root in roots {
+        println!("Source directory: {}", root.display());
+    }
+
+    println!("\n1. Copying files to temporary directories...");
+    let sources: Vec<&PathBuf> = if roots.len() > 1 {
+        roots.iter().collect()
+    } else {
+        std::iter::repeat_n(&roots[0], INTERFERENCE_COPIES).collect()
+    };
+    let mut tmp_dirs = Vec::new();
+    for (i, source) in sources.iter().enumerate() {
+        let tmp_dir = std::path::absolute(prepare_scratch_dir(source, &format!("interference-{}", i), options)?)?;
+        println!("   {} → {}", source.display(), tmp_dir.display());
+        tmp_dirs.push(tmp_dir);
+    }
+
+    println!("\n2. Measuring each watcher mode...");
+    let mut results = Vec::new();
+    for mode in WatcherMode::ALL {
+        println!("\n   --- {} (isolated) ---", mode.display_name());
+        let mut isolated = Vec::new();
+        for tmp_dir in &tmp_dirs {
+            isolated.extend(watch_and_write(mode, std::slice::from_ref(tmp_dir), options)?);
+        }
+
+        println!("\n   --- {} (concurrent) ---", mode.display_name());
+        let concurrent = watch_and_write(mode, &tmp_dirs, options)?;
+
+        for ((root, isolated), concurrent) in tmp_dirs.iter().zip(isolated).zip(concurrent) {
+            results.push(Interference {
+                mode,
+                root: root.clone(),
+                isolated,
+                concurrent,
+            });
+        }
+    }
+
+    println!("\n📊 Interference results (total latency):");
+    println!(
+        "  {:<20} {:<28} {:>12} {:>12} {:>12} {:>12} {:>9} {:>10}",
+        "Mode", "Root", "Alone p50", "Alone p99", "Shared p50", "Shared p99", "Slowdown", "Unmatched"
+    );
+    let cell = |d: Option<Duration>| d.map_or("-".to_string(), |d| format!("{:.1?}", d));
+    for result in &results {
+        let isolated = result.isolated.total();
+        let concurrent = result.concurrent.total();
+        println!(
+            "  {:<20} {:<28} {:>12} {:>12} {:>12} {:>12} {:>9} {:>10}",
+            result.mode.display_name(),
+            result.root.file_name().unwrap_or_default().to_string_lossy(),
+            cell(isolated.map(|s| s.p50)),
+            cell(isolated.map(|s| s.p99)),
+            cell(concurrent.map(|s| s.p50)),
+            cell(concurrent.map(|s| s.p99)),
+            Interference::slowdown(isolated, concurrent)
+                .map_or("-".to_string(), |ratio| format!("{:.2}x", ratio)),
+            format!("{}/{}", result.isolated.unmatched, result.concurrent.unmatched)
+        );
+    }
+
+    println!("\n3. Cleaning up...");
+    for tmp_dir in &tmp_dirs {
+        fs::remove_dir_all(tmp_dir)?;
+    }
+
+    println!("\n=== Multi-Root Interference Test Complete ===\n");
+
+    Ok(())
+}
+
+/// Receive from `rx` with `delay` per item until `writing` is cleared and the
+/// channel has been quiet for `SLOW_CONSUMER_IDLE`, or the drain limit is hit
+fn drain_slowly(rx: &EventReceiver, delay: Duration, writing: &AtomicBool) -> (CollectedEvents, bool) {
+    let start = Instant::now();
+    let mut collected = CollectedEvents::default();
+    let overhead_before = rx.overhead();
+    let backpressure_before = rx.backpressure();
+
+    let finished = loop {
+        if start.elapsed() >= SLOW_CONSUMER_DRAIN_LIMIT {
+            break false;
+        }
+        match rx.recv_timeout(SLOW_CONSUMER_IDLE) {
+            Ok(item) => {
+                collected.record(item);
+                std::thread::sleep(delay);
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) if writing.load(Ordering::Relaxed) => {}
+            Err(_) => break true,
+        }
+    };
+
+    collected.channel = rx.overhead().since(&overhead_before);
+    collected.backpressure = rx.backpressure().since(&backpressure_before);
+    (collected, finished)
+}
+
+/// What one mode made of a consumer slower than the event rate
+struct SlowConsumerResult {
+    mode: WatcherMode,
+    written: usize,
+    lost: usize,
+    /// Backlog added per second while the writer was running
+    growth_per_sec: f64,
+    drain_time: Duration,
+    drained: bool,
+    collected: CollectedEvents,
+}
+
+/// Write faster than a deliberately slow consumer reads and see what each mode does
+///
+/// The consumer sleeps `--consumer-delay` after every event. The channel backlog is
+/// sampled throughout; once the writer stops the consumer keeps draining, and files
+/// that never produced an event count as lost. Combine with `--channel-capacity`
+/// to push the backlog into the kernel queue instead of an unbounded channel.
+pub fn run_slow_consumer_test(
+    dir: &Path,
+    options: &Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let delay = if options.consumer_delay.is_zero() {
+        SLOW_CONSUMER_DELAY
+    } else {
+        options.consumer_delay
+    };
+    println!("\n=== Slow Consumer Test ===");
+    println!("Source directory: {}", dir.display());
+    println!(
+        "Consumer delay: {:?} per event, one append every {:?}",
+        delay, SLOW_CONSUMER_WRITE_INTERVAL
+    );
+    match options.channel_capacity {
+        Some(capacity) => println!("Event channel capacity: {} ({:?} when full)", capacity, options.on_full),
+        None => println!("Event channel capacity: unbounded"),
+    }
+
+    println!("\n1. Copying files to temporary directory...");
+    let tmp_dir = std::path::absolute(prepare_scratch_dir(dir, "slow-consumer", options)?)?;
+
+    println!("\n2. Running each watcher mode against the slow consumer...");
+    let mut results = Vec::new();
+
+    for mode in WatcherMode::ALL {
+        println!("\n   --- {} ---", mode.display_name());
+        let (watcher, rx) = start_watcher(mode, &tmp_dir, options)?;
+        let targets: Vec<PathBuf> = watched_files(mode, &tmp_dir, options)?
+            .into_iter()
+            .take(SLOW_CONSUMER_FILES)
+            .collect();
+
+        // Give watcher time to stabilize
+        std::thread::sleep(options.stabilize);
+
+        let writing = Arc::new(AtomicBool::new(true));
+        let depth = rx.depth();
+        let sampler = QueueDepthSampler::start(depth.clone());
+        let consumer = {
+            let writing = writing.clone();
+            std::thread::spawn(move || {
+                let start = Instant::now();
+                let (collected, drained) = drain_slowly(&rx, delay, &writing);
+                (collected, drained, start.elapsed())
+            })
+        };
+
+        let write_start = Instant::now();
+        let before = depth.get();
+        let written = append_to_files(&targets, SLOW_CONSUMER_WRITE_INTERVAL).len();
+        let write_time = write_start.elapsed();
+        let growth_per_sec = (depth.get() as f64 - before as f64) / write_time.as_secs_f64();
+        writing.store(false, Ordering::Relaxed);
+        println!("   Appended to {} files in {:?}", written, write_time);
+
+        let (mut collected, drained, drain_time) = consumer
+            .join()
+            .map_err(|_| "slow consumer thread panicked")?;
+        collected.queue_depth = sampler.finish();
+        recover_from_overflow(&mut collected, std::slice::from_ref(&tmp_dir), options);
+        drop(watcher);
+
+        let normalized = collected.normalized();
+        let seen: HashSet<&Path> = normalized.iter().map(|e| e.path.as_path()).collect();
+        let lost = targets.iter().filter(|p| !seen.contains(p.as_path())).count();
+
+        println!(
+            "   Received {} events over {:?}, {} of {} modified files lost",
+            collected.events.len(),
+            drain_time,
+            lost,
+            written
+        );
+        if !drained {
+            println!("   Gave up draining after {:?}", SLOW_CONSUMER_DRAIN_LIMIT);
+        }
+        collected.report_overflow("   ");
+        collected.queue_depth.report("   ");
+        collected.backpressure.report("   ");
+
+        results.push(SlowConsumerResult {
+            mode,
+            written,
+            lost,
+            growth_per_sec,
+            drain_time,
+            drained,
+            collected,
+        });
+    }
+
+    println!("\n📊 Slow consumer results:");
+    println!(
+        "  {:<20} {:>8} {:>12} {:>10} {:>10} {:>12} {:>9} {:>9} {:>9} {:>12}",
+        "Mode", "Events", "Lost files", "Max queue", "Mean queue", "Growth/s", "Overflow", "Blocked",
+        "Dropped", "Drain time"
+    );
+    for result in &results {
+        let collected = &result.collected;
+        println!(
+            "  {:<20} {:>8} {:>12} {:>10} {:>10.1} {:>12.1} {:>9} {:>9} {:>9} {:>12}",
+            result.mode.display_name(),
+            collected.events.len(),
+            format!("{}/{}", result.lost, result.written),
+            collected.queue_depth.max,
+            collected.queue_depth.mean,
+            result.growth_per_sec,
+            if collected.overflowed() { "yes" } else { "no" },
+            collected.backpressure.blocked,
+            collected.backpressure.dropped,
+            format!(
+                "{:.1?}{}",
+                result.drain_time,
+                if result.drained { "" } else { "+" }
+            )
+        );
+    }
+
+    println!("\n3. Cleaning up...");
+    fs::remove_dir_all(&tmp_dir)?;
+
+    println!("\n=== Slow Consumer Test Complete ===\n");
+
+    Ok(())
+}
+
+/// Watch `tmp_dir` with a synchronous consumer and match the workload's writes
+fn sync_delivery(
+    mode: WatcherMode,
+    tmp_dir: &Path,
+    options: &Options,
+) -> Result<LatencyReport, Box<dyn std::error::Error>> {
+    let collect_duration = PHASE_COLLECT_DURATION + options.stabilize;
+    let (watcher, rx) = start_watcher(mode, tmp_dir, options)?;
+    let targets: Vec<PathBuf> = watched_files(mode, tmp_dir, options)?.into_iter().take(FILES_PER_PHASE).collect();
+    let event_rx = spawn_event_collector(rx, collect_duration, Duration::ZERO);
+
+    // Give watcher time to stabilize
+    std::thread::sleep(options.stabilize);
+    let writes = write_rounds(&targets, ASYNC_WRITE_ROUNDS, ASYNC_WRITE_INTERVAL);
+
+    let collected = event_rx
+        .recv_timeout(collect_duration + Duration::from_secs(1))
+        .unwrap_or_default();
+    collected.report_overflow("   ");
+    drop(watcher);
+    Ok(match_writes(&writes, &collected))
+}
+
+/// Watch `tmp_dir` with a consumer task on `runtime` and match the workload's writes
+fn async_delivery(
+    mode: WatcherMode,
+    tmp_dir: &Path,
+    options: &Options,
+    runtime: &tokio::runtime::Runtime,
+) -> Result<LatencyReport, Box<dyn std::error::Error>> {
+    let collect_duration = PHASE_COLLECT_DURATION + options.stabilize;
+    let (watcher, mut rx) = start_async_watcher(mode, tmp_dir, options)?;
+    let targets: Vec<PathBuf> = watched_files(mode, tmp_dir, options)?.into_iter().take(FILES_PER_PHASE).collect();
+    let consumer = runtime.spawn(async move { collect_events_async(&mut rx, collect_duration).await });
+
+    // Give watcher time to stabilize
+    std::thread::sleep(options.stabilize);
+    let writes = write_rounds(&targets, ASYNC_WRITE_ROUNDS, ASYNC_WRITE_INTERVAL);
+
+    let collected = runtime.block_on(consumer)?;
+    collected.report_overflow("   ");
+    drop(watcher);
+    Ok(match_writes(&writes, &collected))
+}
+
+/// Compare delivery latency to a blocking consumer thread and to a tokio task
+///
+/// The sync side uses `--channel` (std unless crossbeam is asked for); the async
+/// side always delivers through `tokio::sync::mpsc` to a task on a multi-threaded
+/// runtime, the way an async server would embed the watcher.
+pub fn run_async_test(dir: &Path, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    let sync_options = Options {
+        channel: match options.channel {
+            ChannelKind::Tokio => ChannelKind::Std,
+            channel => channel,
+        },
+        ..options.clone()
+    };
+    println!("\n=== Async vs Sync Delivery Test ===");
+    println!("Source directory: {}", dir.display());
+    println!(
+        "Sync channel: {}, async channel: tokio ({} worker threads)",
+        sync_options.channel.display_name(),
+        ASYNC_WORKER_THREADS
+    );
+
+    println!("\n1. Copying files to temporary directory...");
+    let tmp_dir = std::path::absolute(prepare_scratch_dir(dir, "async", options)?)?;
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(ASYNC_WORKER_THREADS)
+        .enable_time()
+        .build()?;
+
+    println!("\n2. Measuring each watcher mode...");
+    let mut results = Vec::new();
+    for mode in WatcherMode::ALL {
+        println!("\n   --- {} (sync) ---", mode.display_name());
+        let sync = sync_delivery(mode, &tmp_dir, &sync_options)?;
+        println!("\n   --- {} (async) ---", mode.display_name());
+        let asynchronous = async_delivery(mode, &tmp_dir, options, &runtime)?;
+        results.push((mode, sync, asynchronous));
+    }
+
+    println!("\n📊 Delivery latency (callback → consumer):");
+    println!(
+        "  {:<20} {:>10} {:>10} {:>10} {:>10} {:>11} {:>11} {:>10}",
+        "Mode", "Sync p50", "Sync p99", "Async p50", "Async p99", "Sync total", "Async total", "Unmatched"
+    );
+    let cell = |d: Option<Duration>| d.map_or("-".to_string(), |d| format!("{:.1?}", d));
+    for (mode, sync, asynchronous) in &results {
+        println!(
+            "  {:<20} {:>10} {:>10} {:>10} {:>10} {:>11} {:>11} {:>10}",
+            mode.display_name(),
+            cell(sync.queue().map(|s| s.p50)),
+            cell(sync.queue().map(|s| s.p99)),
+            cell(asynchronous.queue().map(|s| s.p50)),
+            cell(asynchronous.queue().map(|s| s.p99)),
+            cell(sync.total().map(|s| s.p50)),
+            cell(asynchronous.total().map(|s| s.p50)),
+            format!("{}/{}", sync.unmatched, asynchronous.unmatched)
+        );
+    }
+
+    println!("\n3. Cleaning up...");
+    drop(runtime);
+    fs::remove_dir_all(&tmp_dir)?;
+
+    println!("\n=== Async vs Sync Delivery Test Complete ===\n");
+
+    Ok(())
+}
+
+/// Events a consumer got through, and over what span of arrival times
+#[derive(Debug, Default, Clone, Copy)]
+struct Throughput {
+    events: usize,
+    first: Option<Instant>,
+    last: Option<Instant>,
+}
+
+impl Throughput {
+    fn record(&mut self) {
+        let now = Instant::now();
+        self.events += 1;
+        self.first.get_or_insert(now);
+        self.last = Some(now);
+    }
+
+    /// Events per second between the first and last arrival
+    fn per_sec(&self) -> Option<f64> {
+        let span = self.last?.duration_since(self.first?).as_secs_f64();
+        (self.events > 1 && span > 0.0).then(|| (self.events - 1) as f64 / span)
+    }
+
+    /// How long to wait for the next event: long for the first, short once flowing
+    fn wait(&self) -> Duration {
+        if self.events == 0 {
+            PHASE_COLLECT_DURATION
+        } else {
+            STREAM_IDLE
+        }
+    }
+}
+
+/// Run `consume` on `runtime` while a burst of appends hits `targets`, `stabilize` after it starts
+fn stream_burst<F>(
+    runtime: &tokio::runtime::Runtime,
+    targets: &[PathBuf],
+    stabilize: Duration,
+    consume: F,
+) -> Result<Throughput, Box<dyn std::error::Error>>
+where
+    F: std::future::Future<Output = Throughput> + Send + 'static,
+{
+    let consumer = runtime.spawn(consume);
+
+    // Give watcher time to stabilize
+    std::thread::sleep(stabilize);
+    write_rounds(targets, STREAM_BURST_ROUNDS, Duration::ZERO);
+
+    Ok(runtime.block_on(consumer)?)
+}
+
+/// Compare event throughput through `into_stream()` combinators with direct `recv()`
+///
+/// Both consumers run as tokio tasks on the same runtime and see the same burst of
+/// back-to-back appends, so the difference is the stream adapter's cost.
+pub fn run_stream_test(dir: &Path, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    println!("\n=== Streaming Throughput Test ===");
+    println!("Source directory: {}", dir.display());
+
+    println!("\n1. Copying files to temporary directory...");
+    let tmp_dir = std::path::absolute(prepare_scratch_dir(dir, "stream", options)?)?;
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(ASYNC_WORKER_THREADS)
+        .enable_time()
+        .build()?;
+
+    println!("\n2. Measuring each watcher mode...");
+    let mut results = Vec::new();
+    for mode in WatcherMode::ALL {
+        let targets: Vec<PathBuf> = watched_files(mode, &tmp_dir, options)?
+            .into_iter()
+            .take(STREAM_BURST_FILES)
+            .collect();
+
+        println!("\n   --- {} (recv) ---", mode.display_name());
+        let (watcher, mut rx) = start_async_watcher(mode, &tmp_dir, options)?;
+        let direct = stream_burst(&runtime, &targets, options.stabilize, async move {
+            let mut throughput = Throughput::default();
+            while let Ok(Some(item)) = tokio::time::timeout(throughput.wait(), rx.recv()).await {
+                if matches!(&item.result, Ok(event) if !is_rescan(event)) {
+                    throughput.record();
+                }
+            }
+            throughput
+        })?;
+        drop(watcher);
+
+        println!("\n   --- {} (stream) ---", mode.display_name());
+        let stream = start_event_stream(mode, &tmp_dir, options)?;
+        let streamed = stream_burst(&runtime, &targets, options.stabilize, async move {
+            let mut events = Box::pin(
+                stream
+                    .filter_map(|res| async { res.ok() })
+                    .filter(|event| futures::future::ready(!is_rescan(event))),
+            );
+            let mut throughput = Throughput::default();
+            while let Ok(Some(_)) = tokio::time::timeout(throughput.wait(), events.next()).await {
+                throughput.record();
+            }
+            throughput
+        })?;
+
+        println!(
+            "   Appended to {} files {} times; {} events via recv, {} via stream",
+            targets.len(),
+            STREAM_BURST_ROUNDS,
+            direct.events,
+            streamed.events
+        );
+        results.push((mode, direct, streamed));
+    }
+
+    println!("\n📊 Streaming throughput:");
+    println!(
+        "  {:<20} {:>12} {:>14} {:>12} {:>14} {:>10}",
+        "Mode", "recv events", "recv events/s", "Stream evts", "Stream evts/s", "Ratio"
+    );
+    let rate = |t: &Throughput| t.per_sec().map_or("-".to_string(), |r| format!("{:.0}", r));
+    for (mode, direct, streamed) in &results {
+        let ratio = match (streamed.per_sec(), direct.per_sec()) {
+            (Some(stream), Some(direct)) => format!("{:.2}x", stream / direct),
+            _ => "-".to_string(),
+        };
+        println!(
+            "  {:<20} {:>12} {:>14} {:>12} {:>14} {:>10}",
+            mode.display_name(),
+            direct.events,
+            rate(direct),
+            streamed.events,
+            rate(streamed),
+            ratio
+        );
+    }
+
+    println!("\n3. Cleaning up...");
+    drop(runtime);
+    fs::remove_dir_all(&tmp_dir)?;
+
+    println!("\n=== Streaming Throughput Test Complete ===\n");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recursive_file_watcher::walk_files;
+
+    #[test]
+    fn test_throughput_rate() {
+        let mut throughput = Throughput::default();
+        assert_eq!(throughput.per_sec(), None);
+        assert_eq!(throughput.wait(), PHASE_COLLECT_DURATION);
+
+        let start = Instant::now();
+        throughput.events = 11;
+        throughput.first = Some(start);
+        throughput.last = Some(start + Duration::from_millis(100));
+        assert!((throughput.per_sec().unwrap() - 100.0).abs() < 1e-6);
+        assert_eq!(throughput.wait(), STREAM_IDLE);
+    }
+
+    #[test]
+    fn test_interference_slowdown() {
+        let summary = |ms| {
+            DurationSummary::from_durations(vec![Duration::from_millis(ms)])
+        };
+        assert_eq!(Interference::slowdown(summary(2), summary(3)), Some(1.5));
+        assert_eq!(Interference::slowdown(summary(0), summary(3)), None);
+        assert_eq!(Interference::slowdown(None, summary(3)), None);
+    }
+
+    #[test]
+    fn test_container_script_is_positional() {
+        let script = container_script();
+        assert!(script.contains("\"$@\""));
+        assert!(script.contains("/work/$f"));
+    }
+
+    #[test]
+    fn test_build_deep_tree() {
+        let test_dir = Path::new("test_deep_tree_dir");
+        let files = build_deep_tree(test_dir, LONG_PATH_LENGTH, 3).unwrap();
+        assert_eq!(files.len(), 3);
+        assert!(files.iter().all(|f| f.is_file() && f.as_os_str().len() > LONG_PATH_LENGTH));
+        assert_eq!(walk_files(test_dir).count(), 3);
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_large_writes_keep_or_replace_size() {
+        let test_dir = Path::new("test_large_write_dir");
+        fs::create_dir_all(test_dir).unwrap();
+        let file = test_dir.join("big.bin");
+        let size = 3 * LARGE_FILE_CHUNK as u64 / 2;
+        let len = || fs::metadata(&file).unwrap().len();
+
+        fill_file(&file, size, b'.').unwrap();
+        assert_eq!(len(), size);
+        LargeWrite::InPlace.apply(&file, size).unwrap();
+        assert_eq!(len(), size);
+        LargeWrite::Append.apply(&file, size).unwrap();
+        assert_eq!(len(), size + LARGE_FILE_PATCH as u64);
+        let write = LargeWrite::Rewrite.apply(&file, size).unwrap();
+        assert_eq!(len(), size);
+        assert!(write.written_at >= write.started_at);
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_append_log_lines_round_robin() {
+        let test_dir = Path::new("test_log_lines_dir");
+        fs::create_dir_all(test_dir).unwrap();
+        let files: Vec<PathBuf> = (0..3).map(|i| test_dir.join(format!("{}.log", i))).collect();
+        for file in &files {
+            fs::write(file, "").unwrap();
+        }
+
+        let writes = append_log_lines(&files, 1000, Duration::from_millis(30)).unwrap();
+        let lines = |file: &PathBuf| fs::read_to_string(file).unwrap().lines().count();
+        assert_eq!(writes.len(), 30);
+        assert_eq!(lines(&files[0]), 10);
+        assert_eq!(lines(&files[2]), 10);
+        assert_eq!(writes[4].path, files[1]);
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_nested_tree_depths() {
+        let test_dir = Path::new("test_nested_tree_dir");
+        let files = build_nested_tree(test_dir, 40).unwrap();
+        assert_eq!(files.len(), 40);
+        assert_eq!(depth_below(&files[0], test_dir), 1);
+        assert_eq!(depth_below(&files[39], test_dir), 40);
+        assert_eq!(walk_files(test_dir).count(), 40);
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_unusual_names_enumerate() {
+        let test_dir = std::path::absolute("test_unusual_names_dir").unwrap();
+        let created = build_unusual_tree(&test_dir).unwrap();
+        assert_eq!(created.len(), unusual_names().len() * 2);
+        let mut enumerated: Vec<PathBuf> = walk_files(&test_dir).collect();
+        let mut expected = created;
+        enumerated.sort();
+        expected.sort();
+        assert_eq!(enumerated, expected);
+
+        fs::remove_dir_all(&test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_admin_share() {
+        assert_eq!(admin_share(r"c:\work\tmp"), Some(r"\\localhost\C$\work\tmp".to_string()));
+        assert_eq!(admin_share("D:/src"), Some(r"\\localhost\D$\src".to_string()));
+        assert_eq!(admin_share(r"\\server\share"), None);
+        assert_eq!(admin_share("/tmp/work"), None);
+    }
+
+    #[test]
+    fn test_move_file_same_device_renames() {
+        let test_dir = Path::new("test_move_file_dir");
+        fs::create_dir_all(test_dir).unwrap();
+        fs::write(test_dir.join("a.txt"), "a").unwrap();
+
+        let outcome = move_file(&test_dir.join("a.txt"), &test_dir.join("b.txt")).unwrap();
+        assert_eq!(outcome, MoveOutcome::Renamed);
+        assert!(!test_dir.join("a.txt").exists());
+        assert_eq!(fs::read_to_string(test_dir.join("b.txt")).unwrap(), "a");
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_modify_concurrently_touches_every_file() {
+        let test_dir = Path::new("test_modify_concurrently_dir");
+        fs::create_dir_all(test_dir).unwrap();
+        let files: Vec<PathBuf> = (0..7)
+            .map(|i| test_dir.join(format!("file{}.txt", i)))
+            .collect();
+        for file in &files {
+            fs::write(file, "").unwrap();
+        }
+
+        modify_concurrently(&files, 3);
+        for file in &files {
+            assert!(fs::read_to_string(file).unwrap().contains("Modified by test"));
+        }
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_command_reports_failure() {
+        assert!(run_command(&mut Command::new("true")).is_ok());
+        assert!(run_command(&mut Command::new("false")).is_err());
+    }
+}
@@ -0,0 +1,497 @@
+use crate::format::Versioned;
+use crate::history::{Environment, Measurement};
+use crate::latency::{Bucket, HistogramRecord, LatencyHistogram};
+use crate::merge::{EnvironmentRuns, MergedReport, MergedRun};
+use crate::report::RunReport;
+use crate::watch::WatchSnapshot;
+use serde_json::{json, Map, Value};
+
+const SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// A serde model written as JSON, described for downstream validation and code generation
+pub trait JsonSchema {
+    /// Key under `$defs`, and the title of a standalone schema
+    const NAME: &'static str;
+
+    /// Schema of one value; nested models are referred to as `#/$defs/<NAME>`
+    fn schema() -> Value;
+
+    /// Nested models' definitions, innermost first
+    fn definitions() -> Vec<(&'static str, Value)> {
+        Vec::new()
+    }
+}
+
+/// Structured outputs `schema` knows about: (name, what writes it, standalone schema)
+pub fn formats() -> Vec<(&'static str, &'static str, Value)> {
+    vec![
+        ("snapshot", "one line of the `watch --snapshot-file` log", standalone::<WatchSnapshot>()),
+        ("histogram", "one line of `--histogram json` output", standalone::<HistogramRecord>()),
+        ("report", "a `--report` file, as read by `diff`", standalone::<RunReport>()),
+        ("merged", "the `merge --report` file combining several machines", standalone::<MergedReport>()),
+    ]
+}
+
+/// `T`'s schema as a document of its own, with everything it refers to under `$defs`
+pub fn standalone<T: JsonSchema>() -> Value {
+    let mut schema = T::schema();
+    let object = schema.as_object_mut().expect("model schemas are objects");
+    object.insert("$schema".to_string(), json!(SCHEMA_DIALECT));
+    object.insert("title".to_string(), json!(T::NAME));
+    let definitions = T::definitions();
+    if !definitions.is_empty() {
+        let defs: Map<String, Value> = definitions.into_iter().map(|(name, def)| (name.to_string(), def)).collect();
+        object.insert("$defs".to_string(), Value::Object(defs));
+    }
+    schema
+}
+
+/// Print the schema of the format named `name`, or of every format when `None`
+pub fn print_schema(name: Option<&str>) -> Result<(), String> {
+    let formats = formats();
+    let schema = match name {
+        Some(name) => formats
+            .into_iter()
+            .find(|(format, _, _)| *format == name)
+            .map(|(_, _, schema)| schema)
+            .ok_or_else(|| format!("Unknown format: {} (expected snapshot, histogram, report or merged)", name))?,
+        None => {
+            let defs: Map<String, Value> = formats
+                .into_iter()
+                .map(|(format, description, mut schema)| {
+                    // Each format resolves its own `#/$defs/...` references
+                    schema["$id"] = json!(format!("{}.json", format));
+                    schema["description"] = json!(description);
+                    (format.to_string(), schema)
+                })
+                .collect();
+            json!({
+                "$schema": SCHEMA_DIALECT,
+                "title": "watcher-benchmark structured output",
+                "$defs": defs,
+            })
+        }
+    };
+    println!("{}", serde_json::to_string_pretty(&schema).map_err(|e| e.to_string())?);
+    Ok(())
+}
+
+fn object(properties: Value, required: &[&str]) -> Value {
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+/// The version stamp a current result of `T`'s format carries
+fn format_version<T: Versioned>() -> Value {
+    json!({ "type": "integer", "const": T::FORMAT_VERSION })
+}
+
+fn nullable(kind: &str) -> Value {
+    json!({ "type": [kind, "null"] })
+}
+
+fn reference(name: &str) -> Value {
+    json!({ "$ref": format!("#/$defs/{}", name) })
+}
+
+impl JsonSchema for WatchSnapshot {
+    const NAME: &'static str = "WatchSnapshot";
+
+    fn schema() -> Value {
+        object(
+            json!({
+                "format_version": format_version::<Self>(),
+                "timestamp_ms": { "type": "integer", "minimum": 0, "description": "Interval end, milliseconds since the Unix epoch" },
+                "elapsed_secs": { "type": "number", "minimum": 0 },
+                "interval_secs": { "type": "number", "minimum": 0 },
+                "events": { "type": "integer", "minimum": 0 },
+                "events_per_sec": { "type": "number", "minimum": 0 },
+                "queue_p50_us": nullable("integer"),
+                "queue_p99_us": nullable("integer"),
+                "kinds": {
+                    "type": "object",
+                    "additionalProperties": { "type": "integer", "minimum": 0 },
+                    "description": "Normalized change counts, e.g. modified",
+                },
+                "rescans": { "type": "integer", "minimum": 0 },
+                "errors": { "type": "integer", "minimum": 0 },
+                "overflowed": { "type": "boolean" },
+                "rss_bytes": nullable("integer"),
+            }),
+            &[
+                "format_version",
+                "timestamp_ms",
+                "elapsed_secs",
+                "interval_secs",
+                "events",
+                "events_per_sec",
+                "queue_p50_us",
+                "queue_p99_us",
+                "kinds",
+                "rescans",
+                "errors",
+                "overflowed",
+                "rss_bytes",
+            ],
+        )
+    }
+}
+
+impl JsonSchema for Bucket {
+    const NAME: &'static str = "Bucket";
+
+    fn schema() -> Value {
+        object(
+            json!({
+                "le_us": { "type": "integer", "minimum": 0, "description": "Upper bound in microseconds" },
+                "count": { "type": "integer", "minimum": 0 },
+            }),
+            &["le_us", "count"],
+        )
+    }
+}
+
+impl JsonSchema for LatencyHistogram {
+    const NAME: &'static str = "LatencyHistogram";
+
+    fn schema() -> Value {
+        object(
+            json!({
+                "samples": { "type": "integer", "minimum": 0 },
+                "buckets": { "type": "array", "items": reference(Bucket::NAME) },
+                "above": { "type": "integer", "minimum": 0, "description": "Samples above the last bound" },
+            }),
+            &["samples", "buckets", "above"],
+        )
+    }
+
+    fn definitions() -> Vec<(&'static str, Value)> {
+        vec![(Bucket::NAME, Bucket::schema())]
+    }
+}
+
+impl JsonSchema for HistogramRecord<'_> {
+    const NAME: &'static str = "HistogramRecord";
+
+    fn schema() -> Value {
+        object(
+            json!({
+                "format_version": format_version::<Self>(),
+                "mode": { "type": "string", "description": "Watcher the samples came from" },
+                "metric": { "type": "string" },
+                "histogram": reference(LatencyHistogram::NAME),
+            }),
+            &["format_version", "metric", "histogram"],
+        )
+    }
+
+    fn definitions() -> Vec<(&'static str, Value)> {
+        let mut definitions = LatencyHistogram::definitions();
+        definitions.push((LatencyHistogram::NAME, LatencyHistogram::schema()));
+        definitions
+    }
+}
+
+impl JsonSchema for Measurement {
+    const NAME: &'static str = "Measurement";
+
+    fn schema() -> Value {
+        object(
+            json!({
+                "watcher_mode": { "type": "string" },
+                "metric": { "type": "string", "description": "Name carrying the unit, e.g. setup_us" },
+                "value": { "type": "number" },
+            }),
+            &["watcher_mode", "metric", "value"],
+        )
+    }
+}
+
+impl JsonSchema for Environment {
+    const NAME: &'static str = "Environment";
+
+    fn schema() -> Value {
+        object(
+            json!({
+                "os": { "type": "string" },
+                "arch": { "type": "string" },
+                "kernel": { "type": "string" },
+                "hostname": { "type": "string" },
+                "cpus": { "type": "integer", "minimum": 1 },
+                "notify_version": { "type": "string" },
+                "benchmark_version": { "type": "string" },
+                "allocator": { "type": "string" },
+            }),
+            &["os", "arch", "kernel", "hostname", "cpus", "notify_version", "benchmark_version", "allocator"],
+        )
+    }
+}
+
+impl JsonSchema for RunReport {
+    const NAME: &'static str = "RunReport";
+
+    fn schema() -> Value {
+        object(
+            json!({
+                "format_version": format_version::<Self>(),
+                "id": { "type": "string" },
+                "started_at_ms": { "type": "integer", "description": "Milliseconds since the Unix epoch" },
+                "command": { "type": "string" },
+                "args": { "type": "string" },
+                "status": { "type": "string" },
+                "tree_fingerprint": { "type": "string" },
+                "tree_files": { "type": "integer", "minimum": 0 },
+                "environment": reference(Environment::NAME),
+                "measurements": { "type": "array", "items": reference(Measurement::NAME) },
+            }),
+            &[
+                "format_version",
+                "id",
+                "started_at_ms",
+                "command",
+                "args",
+                "status",
+                "tree_fingerprint",
+                "tree_files",
+                "environment",
+                "measurements",
+            ],
+        )
+    }
+
+    fn definitions() -> Vec<(&'static str, Value)> {
+        vec![
+            (Environment::NAME, Environment::schema()),
+            (Measurement::NAME, Measurement::schema()),
+        ]
+    }
+}
+
+impl JsonSchema for MergedRun {
+    const NAME: &'static str = "MergedRun";
+
+    fn schema() -> Value {
+        object(
+            json!({
+                "id": { "type": "string" },
+                "started_at_ms": { "type": "integer" },
+                "command": { "type": "string" },
+                "args": { "type": "string" },
+                "status": { "type": "string" },
+                "tree_fingerprint": { "type": "string" },
+                "tree_files": { "type": "integer", "minimum": 0 },
+                "measurements": { "type": "array", "items": reference(Measurement::NAME) },
+            }),
+            &["id", "started_at_ms", "command", "args", "status", "tree_fingerprint", "tree_files", "measurements"],
+        )
+    }
+}
+
+impl JsonSchema for EnvironmentRuns {
+    const NAME: &'static str = "EnvironmentRuns";
+
+    fn schema() -> Value {
+        object(
+            json!({
+                "environment": reference(Environment::NAME),
+                "runs": { "type": "array", "items": reference(MergedRun::NAME) },
+            }),
+            &["environment", "runs"],
+        )
+    }
+}
+
+impl JsonSchema for MergedReport {
+    const NAME: &'static str = "MergedReport";
+
+    fn schema() -> Value {
+        object(
+            json!({
+                "format_version": format_version::<Self>(),
+                "environments": { "type": "array", "items": reference(EnvironmentRuns::NAME) },
+            }),
+            &["format_version", "environments"],
+        )
+    }
+
+    fn definitions() -> Vec<(&'static str, Value)> {
+        vec![
+            (Environment::NAME, Environment::schema()),
+            (Measurement::NAME, Measurement::schema()),
+            (MergedRun::NAME, MergedRun::schema()),
+            (EnvironmentRuns::NAME, EnvironmentRuns::schema()),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    /// Just enough of JSON Schema to check the models against the keywords emitted above
+    fn validate(value: &Value, schema: &Value, root: &Value) -> Result<(), String> {
+        if let Some(target) = schema["$ref"].as_str() {
+            let name = target.trim_start_matches("#/$defs/");
+            return validate(value, &root["$defs"][name], root);
+        }
+        if let Some(types) = schema.get("type") {
+            let types: Vec<&str> = match types {
+                Value::String(kind) => vec![kind.as_str()],
+                kinds => kinds.as_array().unwrap().iter().map(|k| k.as_str().unwrap()).collect(),
+            };
+            let matches = |kind: &&str| match *kind {
+                "integer" => value.is_u64() || value.is_i64(),
+                "number" => value.is_number(),
+                "string" => value.is_string(),
+                "boolean" => value.is_boolean(),
+                "array" => value.is_array(),
+                "object" => value.is_object(),
+                "null" => value.is_null(),
+                _ => false,
+            };
+            if !types.iter().any(matches) {
+                return Err(format!("{} is not {:?}", value, types));
+            }
+        }
+        if let Some(expected) = schema.get("const") {
+            if value != expected {
+                return Err(format!("{} is not {}", value, expected));
+            }
+        }
+        if let (Some(minimum), Some(number)) = (schema["minimum"].as_f64(), value.as_f64()) {
+            if number < minimum {
+                return Err(format!("{} is below {}", value, minimum));
+            }
+        }
+        if let Some(object) = value.as_object() {
+            for required in schema["required"].as_array().into_iter().flatten() {
+                if !object.contains_key(required.as_str().unwrap()) {
+                    return Err(format!("missing {}", required));
+                }
+            }
+            for (key, field) in object {
+                match schema["properties"].get(key) {
+                    Some(property) => validate(field, property, root)?,
+                    None if schema["additionalProperties"] == json!(false) => {
+                        return Err(format!("unexpected property {}", key))
+                    }
+                    None => {
+                        if let Some(extra) = schema.get("additionalProperties") {
+                            validate(field, extra, root)?;
+                        }
+                    }
+                }
+            }
+        }
+        if let (Some(items), Some(values)) = (schema.get("items"), value.as_array()) {
+            for item in values {
+                validate(item, items, root)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check<T: JsonSchema + serde::Serialize>(model: &T) -> Result<(), String> {
+        let schema = standalone::<T>();
+        validate(&serde_json::to_value(model).unwrap(), &schema, &schema)
+    }
+
+    #[test]
+    fn test_schemas_match_models() {
+        let snapshot = WatchSnapshot {
+            format_version: WatchSnapshot::FORMAT_VERSION,
+            timestamp_ms: 1_700_000_000_000,
+            elapsed_secs: 10.0,
+            interval_secs: 10.0,
+            events: 4,
+            events_per_sec: 0.4,
+            queue_p50_us: Some(12),
+            queue_p99_us: None,
+            kinds: BTreeMap::from([("modified".to_string(), 4)]),
+            rescans: 0,
+            errors: 0,
+            overflowed: false,
+            rss_bytes: None,
+        };
+        assert_eq!(check(&snapshot), Ok(()));
+
+        let histogram = LatencyHistogram::new(&[Duration::from_millis(1)], [Duration::from_micros(10)]);
+        for mode in [Some("Native Recursive"), None] {
+            let record = HistogramRecord {
+                format_version: HistogramRecord::FORMAT_VERSION,
+                mode,
+                metric: "total",
+                histogram: histogram.clone(),
+            };
+            assert_eq!(check(&record), Ok(()));
+        }
+
+        let mut run = crate::history::RunRecord::new("native", &[]);
+        run.add("Native Recursive", "setup_us", 812.0);
+        let tree = crate::history::TreeFingerprint {
+            hash: "abc".to_string(),
+            files: 3,
+        };
+        let report = RunReport::new(&run, &tree, &Environment::capture(), "ok");
+        assert_eq!(check(&report), Ok(()));
+        assert_eq!(check(&MergedReport::merge([report])), Ok(()));
+
+        let schema = standalone::<HistogramRecord>();
+        assert!(validate(&json!({ "metric": "total" }), &schema, &schema).is_err());
+        assert!(schema["$defs"]["Bucket"].is_object());
+    }
+
+    #[test]
+    fn test_written_reports_round_trip_through_schema() {
+        let dir = std::env::temp_dir().join(format!("schema-round-trip-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut run = crate::history::RunRecord::new("test-native", &["--modify-count".to_string(), "5".to_string()]);
+        run.add("Native Recursive", "setup_us", 812.0);
+        run.add("Native Recursive", "latency_p99_us", 40.5);
+        let tree = crate::history::TreeFingerprint {
+            hash: "abc".to_string(),
+            files: 3,
+        };
+        let report = RunReport::new(&run, &tree, &Environment::capture(), "ok");
+        let merged = MergedReport::merge([report.clone()]);
+
+        let report_path = dir.join("report.json");
+        report.write(&report_path).unwrap();
+        let merged_path = dir.join("merged.json");
+        merged.write(&merged_path).unwrap();
+        let read = |path: &std::path::Path| -> Value {
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+        };
+        let (written_report, written_merged) = (read(&report_path), read(&merged_path));
+
+        let schema = standalone::<RunReport>();
+        assert_eq!(validate(&written_report, &schema, &schema), Ok(()));
+        // What passes the schema is what the reader accepts back
+        assert_eq!(RunReport::load(&report_path).unwrap(), report);
+        let mut drifted = written_report.clone();
+        drifted["environment"]["gpu"] = json!("none");
+        assert!(validate(&drifted, &schema, &schema).is_err());
+        drifted = written_report;
+        drifted["format_version"] = json!(RunReport::FORMAT_VERSION + 1);
+        assert!(validate(&drifted, &schema, &schema).is_err());
+
+        let schema = standalone::<MergedReport>();
+        assert_eq!(validate(&written_merged, &schema, &schema), Ok(()));
+        assert_eq!(MergedReport::load(&merged_path).unwrap(), merged);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_format_names() {
+        let names: Vec<&str> = formats().iter().map(|(name, _, _)| *name).collect();
+        assert_eq!(names, ["snapshot", "histogram", "report", "merged"]);
+        assert!(print_schema(Some("summary")).is_err());
+    }
+}
@@ -0,0 +1,140 @@
+use crate::coverage::CoverageCell;
+use crate::latency::LatencyReport;
+use crate::oracle::OracleScore;
+use crate::recursive_file_watcher::WatcherMode;
+
+/// Letter grade for a percentage, A at 95 and above down to F below 50
+pub fn grade(percent: f64) -> char {
+    match percent {
+        p if p >= 95.0 => 'A',
+        p if p >= 85.0 => 'B',
+        p if p >= 70.0 => 'C',
+        p if p >= 50.0 => 'D',
+        _ => 'F',
+    }
+}
+
+/// One mode's verification results rolled up into percentages, 100 being flawless
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scorecard {
+    pub mode: WatcherMode,
+    /// Expected changes reported in time (oracle recall)
+    pub delivery: f64,
+    /// One event per modification scores 100; two score 50
+    pub duplication: f64,
+    /// Consecutive modifications whose events arrived in the order they were made
+    pub ordering: f64,
+    /// Received changes that named a path the workload touched
+    pub noise: f64,
+    /// Coverage grid cells where every target was reported, when the grid ran
+    pub coverage: Option<f64>,
+}
+
+impl Scorecard {
+    pub fn new(
+        mode: WatcherMode,
+        oracle: &OracleScore,
+        latency: &LatencyReport,
+        coverage: Option<&[CoverageCell]>,
+    ) -> Self {
+        let duplication = latency
+            .mean_events_per_write()
+            .map_or(1.0, |mean| if mean <= 1.0 { 1.0 } else { 1.0 / mean });
+        let noise = if oracle.received == 0 {
+            1.0
+        } else {
+            1.0 - oracle.spurious.len() as f64 / oracle.received as f64
+        };
+        let coverage = coverage.and_then(|cells| {
+            let judged: Vec<bool> = cells.iter().filter_map(CoverageCell::complete).collect();
+            (!judged.is_empty())
+                .then(|| judged.iter().filter(|&&complete| complete).count() as f64 / judged.len() as f64)
+        });
+        Self {
+            mode,
+            delivery: oracle.recall() * 100.0,
+            duplication: duplication * 100.0,
+            ordering: latency.in_order_ratio() * 100.0,
+            noise: noise * 100.0,
+            coverage: coverage.map(|ratio| ratio * 100.0),
+        }
+    }
+
+    /// Mean of every check that ran
+    pub fn overall(&self) -> f64 {
+        let checks: Vec<f64> = [self.delivery, self.duplication, self.ordering, self.noise]
+            .into_iter()
+            .chain(self.coverage)
+            .collect();
+        checks.iter().sum::<f64>() / checks.len() as f64
+    }
+}
+
+/// Print one row per mode with each check as a percentage and letter grade
+pub fn print_scorecards(scorecards: &[Scorecard]) {
+    let cell = |percent: f64| format!("{:.0}% {}", percent, grade(percent));
+    println!("\n📋 Correctness Scorecard:");
+    println!(
+        "  {:<20} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9}",
+        "Mode", "Delivery", "Dupes", "Order", "Noise", "Coverage", "Overall"
+    );
+    for scorecard in scorecards {
+        println!(
+            "  {:<20} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9}",
+            scorecard.mode.display_name(),
+            cell(scorecard.delivery),
+            cell(scorecard.duplication),
+            cell(scorecard.ordering),
+            cell(scorecard.noise),
+            scorecard.coverage.map_or("-".to_string(), cell),
+            cell(scorecard.overall())
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_scorecard_rolls_up_checks() {
+        let oracle = OracleScore {
+            expected: 4,
+            received: 10,
+            unmet: Vec::new(),
+            unexpected: 2,
+            spurious: vec![PathBuf::from("/tmp"), PathBuf::from("/tmp")],
+            ..OracleScore::default()
+        };
+        let latency = LatencyReport {
+            events_per_write: vec![2, 2, 3, 3],
+            ..LatencyReport::default()
+        };
+        let reported = CoverageCell { targets: 3, reported: 3, ..CoverageCell::default() };
+        let missed = CoverageCell { targets: 3, reported: 1, ..CoverageCell::default() };
+        let unsupported = CoverageCell { targets: 3, unsupported: true, ..CoverageCell::default() };
+
+        let scorecard = Scorecard::new(WatcherMode::Native, &oracle, &latency, Some(&[reported, missed, unsupported]));
+        assert_eq!(scorecard.delivery, 100.0);
+        assert_eq!(scorecard.duplication, 40.0);
+        assert_eq!(scorecard.ordering, 100.0);
+        assert_eq!(scorecard.noise, 80.0);
+        assert_eq!(scorecard.coverage, Some(50.0));
+        assert_eq!(scorecard.overall(), 74.0);
+        assert_eq!(grade(scorecard.overall()), 'C');
+
+        let without_grid = Scorecard::new(WatcherMode::Native, &oracle, &latency, None);
+        assert_eq!(without_grid.overall(), 80.0);
+    }
+
+    #[test]
+    fn test_grade_boundaries() {
+        assert_eq!(grade(100.0), 'A');
+        assert_eq!(grade(95.0), 'A');
+        assert_eq!(grade(94.9), 'B');
+        assert_eq!(grade(70.0), 'C');
+        assert_eq!(grade(50.0), 'D');
+        assert_eq!(grade(0.0), 'F');
+    }
+}
@@ -0,0 +1,101 @@
+use crate::order::shuffle;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Which of a tree's files the watch test modifies
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ModifySelect {
+    /// The first files enumerated, which cluster in one corner of the tree
+    #[default]
+    First,
+    /// A sample shuffled with `--seed`
+    Random,
+    /// Evenly spaced through the enumeration, reaching every part of the tree
+    Spread,
+    /// The most recently modified files of the source tree, as an editor session would touch
+    Hot,
+}
+
+impl ModifySelect {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "first" => Some(Self::First),
+            "random" => Some(Self::Random),
+            "spread" => Some(Self::Spread),
+            "hot" => Some(Self::Hot),
+            _ => None,
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::First => "first",
+            Self::Random => "random",
+            Self::Spread => "spread",
+            Self::Hot => "hot",
+        }
+    }
+
+    /// Pick up to `count` of `files`, given in enumeration order
+    ///
+    /// `mtime` gives the time `Hot` ranks a file by; files without one rank last.
+    pub fn select(
+        &self,
+        files: &[PathBuf],
+        count: usize,
+        seed: u64,
+        mtime: impl Fn(&Path) -> Option<SystemTime>,
+    ) -> Vec<PathBuf> {
+        let count = count.min(files.len());
+        match self {
+            Self::First => files[..count].to_vec(),
+            Self::Random => {
+                let mut files = files.to_vec();
+                shuffle(&mut files, seed);
+                files.truncate(count);
+                files
+            },
+            Self::Spread => (0..count).map(|i| files[i * files.len() / count].clone()).collect(),
+            Self::Hot => {
+                let mut files: Vec<(Option<SystemTime>, &PathBuf)> =
+                    files.iter().map(|path| (mtime(path), path)).collect();
+                // Newest first; a stable sort keeps ties in enumeration order
+                files.sort_by_key(|(mtime, _)| std::cmp::Reverse(*mtime));
+                files.into_iter().take(count).map(|(_, path)| path.clone()).collect()
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_select_strategies() {
+        let files: Vec<PathBuf> = (0..10).map(|i| PathBuf::from(format!("f{}", i))).collect();
+        let names = |selected: Vec<PathBuf>| -> Vec<String> {
+            selected.iter().map(|p| p.display().to_string()).collect()
+        };
+        let no_mtime = |_: &Path| None;
+
+        assert_eq!(names(ModifySelect::First.select(&files, 3, 0, no_mtime)), ["f0", "f1", "f2"]);
+        assert_eq!(names(ModifySelect::Spread.select(&files, 3, 0, no_mtime)), ["f0", "f3", "f6"]);
+        assert_eq!(ModifySelect::First.select(&files, 20, 0, no_mtime).len(), 10);
+
+        let random = ModifySelect::Random.select(&files, 4, 7, no_mtime);
+        assert_eq!(random.len(), 4);
+        assert_eq!(random, ModifySelect::Random.select(&files, 4, 7, no_mtime));
+
+        // f4 and f8 are the newest; files without an mtime come last
+        let epoch = SystemTime::UNIX_EPOCH;
+        let mtime = |path: &Path| match path.to_str() {
+            Some("f4") => Some(epoch + Duration::from_secs(30)),
+            Some("f8") => Some(epoch + Duration::from_secs(20)),
+            Some("f9") => None,
+            _ => Some(epoch),
+        };
+        assert_eq!(names(ModifySelect::Hot.select(&files, 3, 0, mtime)), ["f4", "f8", "f0"]);
+    }
+}
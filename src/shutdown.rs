@@ -0,0 +1,43 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set by the signal handler once SIGINT or SIGTERM arrives
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Whether a graceful shutdown was requested
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::Relaxed)
+}
+
+#[cfg(unix)]
+extern "C" fn handle_signal(signal: libc::c_int) {
+    REQUESTED.store(true, Ordering::Relaxed);
+    // A second Ctrl-C gets the default behavior and kills the process right away
+    // SAFETY: signal() is async-signal-safe
+    unsafe {
+        libc::signal(signal, libc::SIG_DFL);
+    }
+}
+
+/// Turn SIGINT/SIGTERM into a shutdown request for long-running modes
+///
+/// Loops are expected to poll `requested()` and wind down: stop workloads, drop
+/// watchers, report what they have and remove their scratch directories.
+#[cfg(unix)]
+pub fn install() -> io::Result<()> {
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        // SAFETY: the handler only touches an atomic and calls signal()
+        let previous = unsafe {
+            libc::signal(signal, handle_signal as extern "C" fn(libc::c_int) as libc::sighandler_t)
+        };
+        if previous == libc::SIG_ERR {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn install() -> io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,212 @@
+use std::time::Duration;
+
+/// p-value below which a difference between two samples is treated as real
+const SIGNIFICANCE_LEVEL: f64 = 0.05;
+
+/// Largest `n1 * n2` for which the exact U distribution is enumerated
+const EXACT_LIMIT: usize = 400;
+
+/// Two-sided Mann-Whitney U test between two independent samples
+///
+/// Rank-based, so it makes no normality assumption and isn't thrown by one slow
+/// run the way a t-test on setup times would be.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MannWhitney {
+    /// U statistic of the first sample
+    pub u: f64,
+    pub p_value: f64,
+    /// Whether `p_value` comes from the exact distribution rather than the normal approximation
+    pub exact: bool,
+}
+
+impl MannWhitney {
+    /// Test `a` against `b`, or `None` when either is empty
+    ///
+    /// Small samples without ties use the exact distribution of U; otherwise the
+    /// tie-corrected normal approximation.
+    pub fn test(a: &[f64], b: &[f64]) -> Option<Self> {
+        let (n1, n2) = (a.len(), b.len());
+        if n1 == 0 || n2 == 0 {
+            return None;
+        }
+
+        let mut pooled: Vec<(f64, usize)> = a
+            .iter()
+            .map(|&v| (v, 0))
+            .chain(b.iter().map(|&v| (v, 1)))
+            .collect();
+        pooled.sort_by(|x, y| x.0.total_cmp(&y.0));
+
+        // Average ranks over runs of equal values
+        let mut rank_sum_a = 0.0;
+        let mut tie_term = 0.0;
+        let mut i = 0;
+        while i < pooled.len() {
+            let mut j = i;
+            while j + 1 < pooled.len() && pooled[j + 1].0 == pooled[i].0 {
+                j += 1;
+            }
+            let rank = (i + j) as f64 / 2.0 + 1.0;
+            let run = (j - i + 1) as f64;
+            tie_term += run.powi(3) - run;
+            rank_sum_a += rank * pooled[i..=j].iter().filter(|p| p.1 == 0).count() as f64;
+            i = j + 1;
+        }
+
+        let u = rank_sum_a - (n1 * (n1 + 1)) as f64 / 2.0;
+        let mean = (n1 * n2) as f64 / 2.0;
+
+        if tie_term == 0.0 && n1 * n2 <= EXACT_LIMIT {
+            let counts = u_distribution(n1, n2);
+            let total: f64 = counts.iter().sum();
+            // Two-sided: twice the smaller tail, capped at 1
+            let k = u.round() as usize;
+            let lower: f64 = counts[..=k].iter().sum();
+            let upper: f64 = counts[k..].iter().sum();
+            let p_value = (2.0 * lower.min(upper) / total).min(1.0);
+            return Some(Self { u, p_value, exact: true });
+        }
+
+        let n = (n1 + n2) as f64;
+        let variance = (n1 * n2) as f64 / 12.0 * ((n + 1.0) - tie_term / (n * (n - 1.0)));
+        let p_value = if variance <= 0.0 {
+            1.0
+        } else {
+            // Continuity correction towards the mean
+            let z = ((u - mean).abs() - 0.5).max(0.0) / variance.sqrt();
+            (2.0 * (1.0 - standard_normal_cdf(z))).min(1.0)
+        };
+        Some(Self { u, p_value, exact: false })
+    }
+
+    pub fn is_significant(&self) -> bool {
+        self.p_value < SIGNIFICANCE_LEVEL
+    }
+
+    /// Short annotation like `significant, Mann-Whitney p = 0.008`
+    pub fn describe(&self) -> String {
+        format!(
+            "{}, Mann-Whitney p = {:.3}",
+            if self.is_significant() { "significant" } else { "not significant" },
+            self.p_value
+        )
+    }
+}
+
+/// Scales the median absolute deviation to match a normal distribution's standard deviation
+const MAD_SCALE: f64 = 1.4826;
+
+/// Drop runs more than `k` scaled MADs from the median, keeping the rest in order
+///
+/// When most runs are identical the MAD is zero and nothing is dropped, rather
+/// than every differing run.
+pub fn trim_outliers(times: &[Duration], k: f64) -> Vec<Duration> {
+    let median = |mut values: Vec<f64>| {
+        values.sort_by(f64::total_cmp);
+        let mid = values.len() / 2;
+        if values.len().is_multiple_of(2) {
+            (values[mid - 1] + values[mid]) / 2.0
+        } else {
+            values[mid]
+        }
+    };
+    if times.len() < 3 {
+        return times.to_vec();
+    }
+    let secs: Vec<f64> = times.iter().map(Duration::as_secs_f64).collect();
+    let center = median(secs.clone());
+    let mad = median(secs.iter().map(|v| (v - center).abs()).collect()) * MAD_SCALE;
+    if mad == 0.0 {
+        return times.to_vec();
+    }
+    times
+        .iter()
+        .zip(&secs)
+        .filter(|(_, v)| (*v - center).abs() <= k * mad)
+        .map(|(t, _)| *t)
+        .collect()
+}
+
+/// Number of orderings giving each U value for samples of `n1` and `n2`
+fn u_distribution(n1: usize, n2: usize) -> Vec<f64> {
+    // table[i][j][u]: orderings of i first-sample and j second-sample values with statistic u
+    let max_u = n1 * n2;
+    let mut table = vec![vec![vec![0.0; max_u + 1]; n2 + 1]; n1 + 1];
+    for row in table.iter_mut() {
+        row[0][0] = 1.0;
+    }
+    for counts in table[0].iter_mut() {
+        counts[0] = 1.0;
+    }
+    for i in 1..=n1 {
+        for j in 1..=n2 {
+            for u in 0..=max_u {
+                // The largest value belongs to the first sample (beating all j of the
+                // second) or to the second sample (beating none of the first)
+                let from_first = if u >= j { table[i - 1][j][u - j] } else { 0.0 };
+                table[i][j][u] = from_first + table[i][j - 1][u];
+            }
+        }
+    }
+    table[n1][n2].clone()
+}
+
+/// Φ(z), via the Abramowitz-Stegun erf approximation (|error| < 1.5e-7)
+fn standard_normal_cdf(z: f64) -> f64 {
+    let x = z.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.327_591_1 * x);
+    let poly = t
+        * (0.254_829_592
+            + t * (-0.284_496_736 + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    let erf = 1.0 - poly * (-x * x).exp();
+    if z >= 0.0 {
+        0.5 * (1.0 + erf)
+    } else {
+        0.5 * (1.0 - erf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_separated_samples() {
+        // Complete separation of 5 vs 5: 2 of 252 orderings are as extreme
+        let test = MannWhitney::test(&[1.0, 2.0, 3.0, 4.0, 5.0], &[6.0, 7.0, 8.0, 9.0, 10.0]).unwrap();
+        assert!(test.exact);
+        assert_eq!(test.u, 0.0);
+        assert!((test.p_value - 2.0 / 252.0).abs() < 1e-12);
+        assert!(test.is_significant());
+
+        let single = MannWhitney::test(&[1.0], &[2.0]).unwrap();
+        assert_eq!(single.p_value, 1.0);
+        assert!(MannWhitney::test(&[], &[1.0]).is_none());
+    }
+
+    #[test]
+    fn test_interleaved_and_tied_samples() {
+        let noise = MannWhitney::test(&[1.0, 4.0, 5.0, 8.0], &[2.0, 3.0, 6.0, 7.0]).unwrap();
+        assert!(!noise.is_significant());
+
+        // Ties fall back to the normal approximation
+        let a: Vec<f64> = (0..20).map(|i| (i / 2) as f64).collect();
+        let b: Vec<f64> = a.iter().map(|v| v + 15.0).collect();
+        let tied = MannWhitney::test(&a, &b).unwrap();
+        assert!(!tied.exact);
+        assert!(tied.p_value < 0.001, "{}", tied.p_value);
+        assert!((standard_normal_cdf(1.96) - 0.975).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_trim_outliers() {
+        // One page-cache miss among otherwise steady runs
+        let times = [10, 11, 9, 10, 12, 80].map(Duration::from_millis);
+        let kept = trim_outliers(&times, 3.0);
+        assert_eq!(kept, [10, 11, 9, 10, 12].map(Duration::from_millis));
+
+        let steady = [5, 5, 5, 6].map(Duration::from_millis);
+        assert_eq!(trim_outliers(&steady, 3.0), steady);
+        assert_eq!(trim_outliers(&times[..2], 3.0), &times[..2]);
+    }
+}
@@ -0,0 +1,346 @@
+use crate::harness::{append_to_files, collect_events, prepare_scratch_dir, start_watcher, watched_files};
+use crate::latency::{match_writes, WriteRecord};
+use crate::options::Options;
+use crate::recursive_file_watcher::WatcherMode;
+use crate::resources::{or_dash, ResourceSample};
+use crate::shutdown;
+use crate::trend::LinearTrend;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// State of a soak run at the end of one logging interval
+#[derive(Debug, Clone)]
+pub struct SoakSample {
+    pub elapsed: Duration,
+    pub resources: ResourceSample,
+    pub events: usize,
+    pub writes: usize,
+    /// Writes in this interval no event was seen for
+    pub unmatched: usize,
+    pub latency_p50: Option<Duration>,
+    pub latency_p99: Option<Duration>,
+    pub errors: usize,
+    pub overflowed: bool,
+}
+
+/// What the soak's background writer does to each file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoakWorkload {
+    /// Append a line
+    Append,
+    /// Rename the file aside and back, to exercise watch bookkeeping across renames
+    Rename,
+}
+
+impl SoakWorkload {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "append" => Some(Self::Append),
+            "rename" => Some(Self::Rename),
+            _ => None,
+        }
+    }
+
+    /// Apply the workload to `target`, recording the first operation for latency matching
+    fn apply(self, target: &Path) -> Option<WriteRecord> {
+        match self {
+            Self::Append => append_to_files(std::slice::from_ref(&target), Duration::ZERO).pop(),
+            Self::Rename => {
+                let aside = target.with_extension("soak-renamed");
+                let started_at = Instant::now();
+                fs::rename(target, &aside).ok()?;
+                let written_at = Instant::now();
+                if let Err(e) = fs::rename(&aside, target) {
+                    eprintln!("   Failed to rename {} back: {}", aside.display(), e);
+                }
+                Some(WriteRecord {
+                    path: target.to_path_buf(),
+                    started_at,
+                    written_at,
+                })
+            }
+        }
+    }
+}
+
+/// Apply `workload` to `targets` in turn, one file every `interval`, until `stop` is set
+fn spawn_background_writer(
+    targets: Vec<PathBuf>,
+    workload: SoakWorkload,
+    interval: Duration,
+    stop: Arc<AtomicBool>,
+    writes: Arc<Mutex<Vec<WriteRecord>>>,
+) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        for target in targets.iter().cycle() {
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            writes.lock().unwrap().extend(workload.apply(target));
+            std::thread::sleep(interval);
+        }
+    })
+}
+
+fn print_sample_header() {
+    println!(
+        "  {:>10} {:>9} {:>6} {:>8} {:>7} {:>9} {:>10} {:>10} {:>7} {:>9}",
+        "Elapsed", "RSS MiB", "FDs", "Events", "Writes", "Unmatched", "p50", "p99", "Errors", "Overflow"
+    );
+}
+
+fn print_sample(sample: &SoakSample) {
+    let latency = |d: Option<Duration>| d.map_or("-".to_string(), |d| format!("{:.1?}", d));
+    println!(
+        "  {:>10} {:>9} {:>6} {:>8} {:>7} {:>9} {:>10} {:>10} {:>7} {:>9}",
+        format!("{:.0?}", sample.elapsed),
+        or_dash(sample.resources.rss_mib().map(|mib| format!("{:.1}", mib))),
+        or_dash(sample.resources.open_fds),
+        sample.events,
+        sample.writes,
+        sample.unmatched,
+        latency(sample.latency_p50),
+        latency(sample.latency_p99),
+        sample.errors,
+        if sample.overflowed { "yes" } else { "no" }
+    );
+}
+
+/// RSS trend of one mode's soak run
+struct RssGrowth {
+    mode: WatcherMode,
+    trend: Option<LinearTrend>,
+}
+
+impl RssGrowth {
+    /// Fit RSS (KiB) against elapsed hours, so the slope reads as KiB/h
+    fn fit(mode: WatcherMode, samples: &[SoakSample]) -> Self {
+        let points: Vec<(f64, f64)> = samples
+            .iter()
+            .filter_map(|s| {
+                let rss = s.resources.rss_bytes? as f64 / 1024.0;
+                Some((s.elapsed.as_secs_f64() / 3600.0, rss))
+            })
+            .collect();
+        Self {
+            mode,
+            trend: LinearTrend::fit(&points),
+        }
+    }
+
+    /// Whether growth is both significant and steeper than `limit` KiB/h
+    fn exceeds(&self, limit: Option<u64>) -> bool {
+        match (self.trend, limit) {
+            (Some(trend), Some(limit)) => {
+                trend.is_significant_growth() && trend.slope > limit as f64
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Keep each `--soak-mode` watcher alive under a low-rate workload, logging resources and latency
+///
+/// The short test modes can't show leaks or drift; each mode runs for as long as
+/// `--soak-duration` asks, one operation every `--soak-write-interval`. The run
+/// fails if a mode's RSS grows significantly faster than `--max-rss-growth`.
+pub fn run_soak_test(dir: &Path, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    println!("\n=== Soak Test ===");
+    println!("Source directory: {}", dir.display());
+    println!(
+        "Duration per mode: {:?}, logging every {:?}, one {:?} every {:?}",
+        options.soak_duration, options.soak_interval, options.soak_workload, options.soak_write_interval
+    );
+    match shutdown::install() {
+        Ok(()) => println!("Press Ctrl-C to stop early and report partial results"),
+        Err(e) => eprintln!("Could not install signal handler: {}", e),
+    }
+
+    let mut growth = Vec::new();
+    for &mode in &options.soak_modes {
+        if shutdown::requested() {
+            break;
+        }
+        let samples = soak_one_mode(dir, mode, options)?;
+        growth.push(RssGrowth::fit(mode, &samples));
+    }
+
+    if shutdown::requested() {
+        println!(
+            "\n⚠️  Interrupted: partial results for {} of {} modes",
+            growth.len(),
+            options.soak_modes.len()
+        );
+    }
+
+    println!("\n📊 RSS growth per mode:");
+    println!(
+        "  {:<20} {:>8} {:>14} {:>10} {:>12}",
+        "Mode", "Samples", "Slope KiB/h", "t", "Significant"
+    );
+    for result in &growth {
+        match result.trend {
+            Some(trend) => println!(
+                "  {:<20} {:>8} {:>14.1} {:>10.2} {:>12}",
+                result.mode.display_name(),
+                trend.points,
+                trend.slope,
+                trend.t_statistic(),
+                if trend.is_significant_growth() { "yes" } else { "no" }
+            ),
+            None => println!(
+                "  {:<20} {:>8} {:>14} {:>10} {:>12}",
+                result.mode.display_name(),
+                "-",
+                "-",
+                "-",
+                "too few samples"
+            ),
+        }
+    }
+
+    let leaking: Vec<&str> = growth
+        .iter()
+        .filter(|g| g.exceeds(options.max_rss_growth))
+        .map(|g| g.mode.display_name())
+        .collect();
+
+    println!("\n=== Soak Test Complete ===\n");
+
+    if shutdown::requested() {
+        Err("soak interrupted by signal".into())
+    } else if leaking.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "RSS grew faster than {} KiB/h in: {}",
+            options.max_rss_growth.unwrap_or_default(),
+            leaking.join(", ")
+        )
+        .into())
+    }
+}
+
+/// Soak a single watcher mode in its own scratch copy and return the logged samples
+fn soak_one_mode(
+    dir: &Path,
+    mode: WatcherMode,
+    options: &Options,
+) -> Result<Vec<SoakSample>, Box<dyn std::error::Error>> {
+    println!("\n{}", "=".repeat(60));
+    println!("\n--- {} ---", mode.display_name());
+
+    println!("\n1. Copying files to temporary directory...");
+    let tmp_dir = std::path::absolute(prepare_scratch_dir(dir, "soak", options)?)?;
+
+    let targets = watched_files(mode, &tmp_dir, options)?;
+    if targets.is_empty() {
+        fs::remove_dir_all(&tmp_dir)?;
+        return Err("no files to modify in the soak tree".into());
+    }
+
+    println!("\n2. Setting up {} watcher...", mode.display_name());
+    let (watcher, rx) = start_watcher(mode, &tmp_dir, options)?;
+
+    println!("\n3. Soaking...");
+    let stop = Arc::new(AtomicBool::new(false));
+    let writes = Arc::new(Mutex::new(Vec::new()));
+    let writer = spawn_background_writer(
+        targets,
+        options.soak_workload,
+        options.soak_write_interval,
+        stop.clone(),
+        writes.clone(),
+    );
+
+    let start = Instant::now();
+    let baseline = ResourceSample::take();
+    let mut samples = Vec::new();
+    print_sample_header();
+
+    while start.elapsed() < options.soak_duration && !shutdown::requested() {
+        let remaining = options.soak_duration.saturating_sub(start.elapsed());
+        let collected = collect_events(&rx, options.soak_interval.min(remaining));
+        let interval_writes = std::mem::take(&mut *writes.lock().unwrap());
+        let latency = match_writes(&interval_writes, &collected);
+        let total = latency.total();
+
+        let sample = SoakSample {
+            elapsed: start.elapsed(),
+            resources: ResourceSample::take(),
+            events: collected.events.len(),
+            writes: interval_writes.len(),
+            unmatched: latency.unmatched,
+            latency_p50: total.map(|s| s.p50),
+            latency_p99: total.map(|s| s.p99),
+            errors: collected.errors.total(),
+            overflowed: collected.overflowed(),
+        };
+        print_sample(&sample);
+        samples.push(sample);
+    }
+
+    stop.store(true, Ordering::Relaxed);
+    let _ = writer.join();
+    drop(watcher);
+
+    if let Some(last) = samples.last() {
+        println!("\n   Summary ({:.1?}):", last.elapsed);
+        if let (Some(before), Some(after)) = (baseline.rss_mib(), last.resources.rss_mib()) {
+            println!("   RSS: {:.1} MiB → {:.1} MiB ({:+.1} MiB)", before, after, after - before);
+        }
+        if let (Some(before), Some(after)) = (baseline.open_fds, last.resources.open_fds) {
+            println!("   Open fds: {} → {}", before, after);
+        }
+        println!(
+            "   Events: {}, writes: {}, unmatched writes: {}, errors: {}, intervals with overflow: {}",
+            samples.iter().map(|s| s.events).sum::<usize>(),
+            samples.iter().map(|s| s.writes).sum::<usize>(),
+            samples.iter().map(|s| s.unmatched).sum::<usize>(),
+            samples.iter().map(|s| s.errors).sum::<usize>(),
+            samples.iter().filter(|s| s.overflowed).count()
+        );
+    }
+
+    println!("\n4. Cleaning up...");
+    fs::remove_dir_all(&tmp_dir)?;
+
+    Ok(samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(minutes: u64, rss_kib: u64) -> SoakSample {
+        SoakSample {
+            elapsed: Duration::from_secs(minutes * 60),
+            resources: ResourceSample {
+                rss_bytes: Some(rss_kib * 1024),
+                ..ResourceSample::default()
+            },
+            events: 0,
+            writes: 0,
+            unmatched: 0,
+            latency_p50: None,
+            latency_p99: None,
+            errors: 0,
+            overflowed: false,
+        }
+    }
+
+    #[test]
+    fn test_rss_growth_limit() {
+        // 10 KiB per minute = 600 KiB/h, with a little jitter
+        let samples: Vec<_> = (1..=10).map(|m| sample(m, 4096 + m * 10 + m % 2)).collect();
+        let growth = RssGrowth::fit(WatcherMode::Manual, &samples);
+        let slope = growth.trend.unwrap().slope;
+        assert!((slope - 600.0).abs() < 30.0, "{}", slope);
+        assert!(growth.exceeds(Some(100)));
+        assert!(!growth.exceeds(Some(1000)));
+        assert!(!growth.exceeds(None));
+    }
+}
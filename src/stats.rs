@@ -0,0 +1,684 @@
+use crate::aggregate::{aggregate, changed_dir, AggregateStats};
+use crate::coalesce::{coalesce, CoalesceStats};
+use crate::faults::InjectedFaults;
+use crate::rate_limit::RateLimited;
+use crate::normalize::{is_metadata, normalize_all, NormalizedEvent};
+use crate::recursive_file_watcher::WatchEvent;
+use notify::{ErrorKind, Event, EventKind};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Whether `event` is a rescan/overflow notice rather than a change to a path
+///
+/// Backends flag these with `Flag::Rescan`; some emit a bare `EventKind::Other`
+/// with no paths instead.
+pub fn is_rescan(event: &Event) -> bool {
+    event.need_rescan() || (matches!(event.kind, EventKind::Other) && event.paths.is_empty())
+}
+
+/// Whether a watch error means the backend dropped events
+pub fn is_overflow_error(error: &notify::Error) -> bool {
+    match &error.kind {
+        ErrorKind::Io(e) => e.raw_os_error().is_some_and(is_overflow_os_error),
+        ErrorKind::Generic(message) => message.to_lowercase().contains("overflow"),
+        _ => false,
+    }
+}
+
+#[cfg(unix)]
+fn is_overflow_os_error(code: i32) -> bool {
+    code == libc::ENOBUFS
+}
+
+#[cfg(windows)]
+fn is_overflow_os_error(code: i32) -> bool {
+    // ERROR_NOTIFY_ENUM_DIR: the change buffer overflowed
+    code == 1022
+}
+
+#[cfg(not(any(unix, windows)))]
+fn is_overflow_os_error(_code: i32) -> bool {
+    false
+}
+
+/// Top-level name of an event kind, ignoring subkinds
+pub fn kind_name(kind: &EventKind) -> &'static str {
+    match kind {
+        EventKind::Any => "Any",
+        EventKind::Access(_) => "Access",
+        EventKind::Create(_) => "Create",
+        EventKind::Modify(_) => "Modify",
+        EventKind::Remove(_) => "Remove",
+        EventKind::Other => "Other",
+    }
+}
+
+/// Event counts per kind, keyed by the full kind including subkinds
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct KindBreakdown {
+    counts: BTreeMap<(&'static str, String), usize>,
+}
+
+impl KindBreakdown {
+    /// Tally the kinds of `events`
+    pub fn from_events<'a>(events: impl IntoIterator<Item = &'a Event>) -> Self {
+        let mut breakdown = Self::default();
+        for event in events {
+            breakdown.record(&event.kind);
+        }
+        breakdown
+    }
+
+    /// Count one event of `kind`
+    pub fn record(&mut self, kind: &EventKind) {
+        *self.counts.entry((kind_name(kind), format!("{:?}", kind))).or_insert(0) += 1;
+    }
+
+    /// Counts per top-level kind, e.g. `Modify`
+    pub fn by_kind(&self) -> BTreeMap<&'static str, usize> {
+        let mut totals = BTreeMap::new();
+        for ((kind, _), count) in &self.counts {
+            *totals.entry(*kind).or_insert(0) += count;
+        }
+        totals
+    }
+
+    /// One-line summary like `Access 5, Modify 10`
+    pub fn compact(&self) -> String {
+        if self.counts.is_empty() {
+            return "-".to_string();
+        }
+        self.by_kind()
+            .iter()
+            .map(|(kind, count)| format!("{} {}", kind, count))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Print totals per kind with their subkinds indented below
+    pub fn report(&self, indent: &str) {
+        if self.counts.is_empty() {
+            return;
+        }
+        println!("{}Event kinds:", indent);
+        for (kind, total) in self.by_kind() {
+            println!("{}  {:<28} {:>6}", indent, kind, total);
+            for ((_, subkind), count) in self.counts.iter().filter(|((k, _), _)| *k == kind) {
+                println!("{}    {:<26} {:>6}", indent, subkind, count);
+            }
+        }
+    }
+}
+
+/// Event counts per directory, to show which subtrees make the most noise
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DirectoryCounts {
+    counts: HashMap<PathBuf, usize>,
+}
+
+impl DirectoryCounts {
+    /// Tally the directories `events` touch
+    pub fn from_events<'a>(events: impl IntoIterator<Item = &'a Event>) -> Self {
+        let mut counts = Self::default();
+        for event in events {
+            counts.record(event);
+        }
+        counts
+    }
+
+    /// Count `event` once against each directory its paths are in
+    pub fn record(&mut self, event: &Event) {
+        let dirs: HashSet<&Path> = event.paths.iter().map(|path| changed_dir(path)).collect();
+        for dir in dirs {
+            *self.counts.entry(dir.to_path_buf()).or_insert(0) += 1;
+        }
+    }
+
+    /// The `n` directories with the most events, busiest first, ties by path
+    pub fn top(&self, n: usize) -> Vec<(&Path, usize)> {
+        let mut top: Vec<(&Path, usize)> = self.counts.iter().map(|(dir, &count)| (dir.as_path(), count)).collect();
+        top.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        top.truncate(n);
+        top
+    }
+
+    /// Print the `n` noisiest directories, relative to whichever of `roots` they're under
+    pub fn report(&self, roots: &[PathBuf], n: usize, indent: &str) {
+        let top = self.top(n);
+        if top.is_empty() {
+            return;
+        }
+        let roots = absolute_roots(roots);
+        let total: usize = self.counts.values().sum();
+        println!("{}Noisiest directories ({} of {}):", indent, top.len(), self.counts.len());
+        for (dir, count) in top {
+            println!(
+                "{}  {:<48} {:>8} {:>6.1}%",
+                indent,
+                relative_to_roots(dir, &roots),
+                count,
+                count as f64 / total as f64 * 100.0
+            );
+        }
+    }
+}
+
+/// Event counts and kinds per path, to pick out editor temp files, logs and other noise
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PathCounts {
+    kinds: HashMap<PathBuf, KindBreakdown>,
+}
+
+impl PathCounts {
+    /// Tally the paths `events` name
+    pub fn from_events<'a>(events: impl IntoIterator<Item = &'a Event>) -> Self {
+        let mut counts = Self::default();
+        for event in events {
+            counts.record(event);
+        }
+        counts
+    }
+
+    /// Count `event` against each path it names
+    pub fn record(&mut self, event: &Event) {
+        for path in &event.paths {
+            self.kinds.entry(path.clone()).or_default().record(&event.kind);
+        }
+    }
+
+    /// The `n` paths with the most events and their kinds, busiest first, ties by path
+    pub fn top(&self, n: usize) -> Vec<(&Path, usize, &KindBreakdown)> {
+        let mut top: Vec<(&Path, usize, &KindBreakdown)> = self
+            .kinds
+            .iter()
+            .map(|(path, kinds)| (path.as_path(), kinds.by_kind().values().sum(), kinds))
+            .collect();
+        top.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        top.truncate(n);
+        top
+    }
+
+    /// Print the `n` noisiest paths with their kinds, relative to whichever of `roots` they're under
+    pub fn report(&self, roots: &[PathBuf], n: usize, indent: &str) {
+        let top = self.top(n);
+        if top.is_empty() {
+            return;
+        }
+        let roots = absolute_roots(roots);
+        println!("{}Noisiest paths ({} of {}):", indent, top.len(), self.kinds.len());
+        for (path, count, kinds) in top {
+            println!(
+                "{}  {:<48} {:>8}  {}",
+                indent,
+                relative_to_roots(path, &roots),
+                count,
+                kinds.compact()
+            );
+        }
+    }
+}
+
+/// `roots` made absolute, the form event paths come in whatever form the roots were given in
+fn absolute_roots(roots: &[PathBuf]) -> Vec<PathBuf> {
+    roots.iter().filter_map(|root| std::path::absolute(root).ok()).collect()
+}
+
+/// `path` as `./relative` below the first of `roots` containing it, in full otherwise
+fn relative_to_roots(path: &Path, roots: &[PathBuf]) -> String {
+    roots
+        .iter()
+        .find_map(|root| path.strip_prefix(root).ok())
+        .map_or(path.display().to_string(), |relative| format!("./{}", relative.display()))
+}
+
+/// Watch errors broken down by cause
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorStats {
+    pub path_not_found: usize,
+    pub permission_denied: usize,
+    pub max_files_watched: usize,
+    pub generic: usize,
+}
+
+impl ErrorStats {
+    /// Count `error` under its category
+    pub fn record(&mut self, error: &notify::Error) {
+        match &error.kind {
+            ErrorKind::PathNotFound => self.path_not_found += 1,
+            ErrorKind::MaxFilesWatch => self.max_files_watched += 1,
+            ErrorKind::Io(e) => match e.kind() {
+                std::io::ErrorKind::NotFound => self.path_not_found += 1,
+                std::io::ErrorKind::PermissionDenied => self.permission_denied += 1,
+                _ => self.generic += 1,
+            },
+            _ => self.generic += 1,
+        }
+    }
+
+    /// Total number of errors recorded
+    pub fn total(&self) -> usize {
+        self.path_not_found + self.permission_denied + self.max_files_watched + self.generic
+    }
+
+    /// Print the breakdown, if any errors were seen
+    pub fn report(&self, indent: &str) {
+        if self.total() > 0 {
+            println!(
+                "{}Watch errors: {} (path not found {}, permission denied {}, max files watched {}, generic {})",
+                indent,
+                self.total(),
+                self.path_not_found,
+                self.permission_denied,
+                self.max_files_watched,
+                self.generic
+            );
+        }
+    }
+}
+
+/// Periodic samples of how many events sat unreceived in the channel
+///
+/// A deep queue means the consumer is the bottleneck; a shallow one while events
+/// are still missing points at the backend.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct QueueDepthStats {
+    pub samples: usize,
+    pub max: usize,
+    pub mean: f64,
+}
+
+impl QueueDepthStats {
+    pub fn from_samples(samples: &[usize]) -> Self {
+        Self {
+            samples: samples.len(),
+            max: samples.iter().copied().max().unwrap_or(0),
+            mean: samples.iter().sum::<usize>() as f64 / samples.len().max(1) as f64,
+        }
+    }
+
+    /// Print max and mean depth, if anything was sampled
+    pub fn report(&self, indent: &str) {
+        if self.samples > 0 {
+            println!(
+                "{}Channel queue depth: max {}, mean {:.1} ({} samples)",
+                indent, self.max, self.mean, self.samples
+            );
+        }
+    }
+}
+
+/// Time spent inside event channel operations
+///
+/// Sends include any time notify's thread spent blocked on a full bounded channel.
+/// Receives only cover items that were already queued, so waiting isn't counted.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelOverhead {
+    pub sends: u64,
+    pub send_time: Duration,
+    pub recvs: u64,
+    pub recv_time: Duration,
+}
+
+impl ChannelOverhead {
+    /// Overhead accrued after `earlier` was taken
+    pub fn since(&self, earlier: &ChannelOverhead) -> Self {
+        Self {
+            sends: self.sends.saturating_sub(earlier.sends),
+            send_time: self.send_time.saturating_sub(earlier.send_time),
+            recvs: self.recvs.saturating_sub(earlier.recvs),
+            recv_time: self.recv_time.saturating_sub(earlier.recv_time),
+        }
+    }
+
+    pub fn mean_send(&self) -> Option<Duration> {
+        mean_of(self.send_time, self.sends)
+    }
+
+    pub fn mean_recv(&self) -> Option<Duration> {
+        mean_of(self.recv_time, self.recvs)
+    }
+
+    /// Print send and receive totals and means, if anything was sent
+    pub fn report(&self, channel: &str, indent: &str) {
+        if self.sends == 0 {
+            return;
+        }
+        let mean = |d: Option<Duration>| d.map_or("-".to_string(), |d| format!("{:.1?}", d));
+        println!(
+            "{}Channel overhead ({}): {} sends, {:.1?} total, {} mean; {} queued recvs, {:.1?} total, {} mean",
+            indent,
+            channel,
+            self.sends,
+            self.send_time,
+            mean(self.mean_send()),
+            self.recvs,
+            self.recv_time,
+            mean(self.mean_recv())
+        );
+    }
+}
+
+/// `total` spread over `n` operations, computed in floating point so counts past
+/// `u32::MAX` don't wrap
+fn mean_of(total: Duration, n: u64) -> Option<Duration> {
+    (n > 0).then(|| Duration::from_secs_f64(total.as_secs_f64() / n as f64))
+}
+
+/// How often the watcher callback found a bounded channel full
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Backpressure {
+    pub sends: u64,
+    /// Sends that had to wait for the consumer to make room
+    pub blocked: u64,
+    /// Total time those sends spent waiting
+    pub blocked_time: Duration,
+    /// Items discarded because the channel was full
+    pub dropped: u64,
+}
+
+impl Backpressure {
+    /// Backpressure accrued after `earlier` was taken
+    pub fn since(&self, earlier: &Backpressure) -> Self {
+        Self {
+            sends: self.sends.saturating_sub(earlier.sends),
+            blocked: self.blocked.saturating_sub(earlier.blocked),
+            blocked_time: self.blocked_time.saturating_sub(earlier.blocked_time),
+            dropped: self.dropped.saturating_sub(earlier.dropped),
+        }
+    }
+
+    /// Print how many sends blocked or dropped, if any did
+    pub fn report(&self, indent: &str) {
+        if self.blocked == 0 && self.dropped == 0 {
+            return;
+        }
+        let percent = |n: u64| n as f64 / self.sends.max(1) as f64 * 100.0;
+        println!(
+            "{}Backpressure: {} of {} sends blocked ({:.1}%, {:.1?} waiting), {} dropped ({:.1}%)",
+            indent,
+            self.blocked,
+            self.sends,
+            percent(self.blocked),
+            self.blocked_time,
+            self.dropped,
+            percent(self.dropped)
+        );
+    }
+}
+
+/// Keep the items of `items` whose flag in `keep` is set; items past the end of
+/// `keep` have no event to judge them by and are kept
+fn retain_flagged<T>(items: &mut Vec<T>, keep: &[bool]) {
+    let mut flags = keep.iter();
+    items.retain(|_| flags.next().copied().unwrap_or(true));
+}
+
+/// Everything an event-collection loop received, with overflow signals counted separately
+#[derive(Debug, Default)]
+pub struct CollectedEvents {
+    /// Regular change events
+    pub events: Vec<Event>,
+    /// When notify's callback emitted each entry in `events`
+    pub emitted_at: Vec<Instant>,
+    /// When each entry in `events` was received from the channel
+    pub received_at: Vec<Instant>,
+    /// Rescan/overflow notices delivered as events
+    pub rescans: usize,
+    /// Errors indicating the backend dropped events
+    pub overflow_errors: usize,
+    /// All other watch errors, by cause
+    pub errors: ErrorStats,
+    /// Time spent re-enumerating the tree after an overflow, when requested
+    pub recovery_time: Option<Duration>,
+    /// Channel backlog sampled while collecting
+    pub queue_depth: QueueDepthStats,
+    /// Time spent in channel operations while collecting
+    pub channel: ChannelOverhead,
+    /// Full-channel blocking and drops while collecting
+    pub backpressure: Backpressure,
+    /// Events the exclude list kept from the consumer while collecting
+    pub excluded: u64,
+    /// Faults injected into the watcher's events while collecting
+    pub faults: InjectedFaults,
+    /// Events the rate limiter delayed or merged while collecting
+    pub rate_limited: RateLimited,
+}
+
+impl CollectedEvents {
+    /// Sort one item from the watcher channel into the right bucket
+    pub fn record(&mut self, item: WatchEvent) {
+        match item.result {
+            Ok(event) if is_rescan(&event) => self.rescans += 1,
+            Ok(event) => {
+                self.events.push(event);
+                self.emitted_at.push(item.emitted_at);
+                self.received_at.push(Instant::now());
+            }
+            Err(e) if is_overflow_error(&e) => self.overflow_errors += 1,
+            Err(e) => self.errors.record(&e),
+        }
+    }
+
+    /// Drop metadata-only events, for consumers that only care about content
+    pub fn drop_metadata(&mut self) {
+        let keep: Vec<bool> = self.events.iter().map(|event| !is_metadata(event)).collect();
+        retain_flagged(&mut self.events, &keep);
+        retain_flagged(&mut self.emitted_at, &keep);
+        retain_flagged(&mut self.received_at, &keep);
+    }
+
+    /// Breakdown of the regular events by kind
+    pub fn kinds(&self) -> KindBreakdown {
+        KindBreakdown::from_events(&self.events)
+    }
+
+    /// The regular events mapped to canonical kinds
+    pub fn normalized(&self) -> Vec<NormalizedEvent> {
+        normalize_all(&self.events)
+    }
+
+    /// Raw vs coalesced counts when duplicates within `window` are merged
+    pub fn coalesce(&self, window: Duration) -> CoalesceStats {
+        coalesce(&self.events, &self.received_at, window).1
+    }
+
+    /// Per-file events vs per-directory notifications when changes within `window` are folded together
+    pub fn aggregate(&self, window: Duration) -> AggregateStats {
+        aggregate(&self.events, &self.received_at, window).1
+    }
+
+    /// Whether the backend signalled that events were lost
+    pub fn overflowed(&self) -> bool {
+        self.rescans > 0 || self.overflow_errors > 0
+    }
+
+    /// Run `rescan` if an overflow was signalled and record how long recovery took
+    pub fn recover_with<T>(&mut self, rescan: impl FnOnce() -> T) -> Option<T> {
+        if !self.overflowed() {
+            return None;
+        }
+        let start = Instant::now();
+        let result = rescan();
+        self.recovery_time = Some(start.elapsed());
+        Some(result)
+    }
+
+    /// Print the overflow and error accounting, if there is anything to report
+    pub fn report_overflow(&self, indent: &str) {
+        if self.overflowed() {
+            println!(
+                "{}Overflow signals: {} rescan events, {} overflow errors",
+                indent, self.rescans, self.overflow_errors
+            );
+        }
+        if let Some(recovery) = self.recovery_time {
+            println!("{}Recovery re-enumeration: {:?}", indent, recovery);
+        }
+        self.errors.report(indent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{Flag, MetadataKind, ModifyKind};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_record_separates_rescans_and_errors() {
+        let mut collected = CollectedEvents::default();
+        collected.record(Ok(Event::new(EventKind::Modify(ModifyKind::Any))
+            .add_path(PathBuf::from("/a"))).into());
+        collected.record(Ok(Event::new(EventKind::Other).set_flag(Flag::Rescan)).into());
+        collected.record(Ok(Event::new(EventKind::Other)).into());
+        collected.record(Err(notify::Error::generic("event queue overflow")).into());
+        collected.record(Err(notify::Error::path_not_found()).into());
+
+        assert_eq!(collected.events.len(), 1);
+        assert_eq!(collected.rescans, 2);
+        assert_eq!(collected.overflow_errors, 1);
+        assert_eq!(collected.errors.path_not_found, 1);
+        assert!(collected.overflowed());
+    }
+
+    #[test]
+    fn test_drop_metadata() {
+        let mut collected = CollectedEvents::default();
+        collected.drop_metadata();
+        assert!(collected.events.is_empty());
+
+        collected.record(Ok(Event::new(EventKind::Modify(ModifyKind::Metadata(MetadataKind::Any)))).into());
+        collected.record(Ok(Event::new(EventKind::Modify(ModifyKind::Any))).into());
+        // A timestamp with no event behind it survives rather than panicking
+        collected.received_at.push(Instant::now());
+        collected.drop_metadata();
+        assert_eq!(collected.events.len(), 1);
+        assert_eq!((collected.emitted_at.len(), collected.received_at.len()), (1, 2));
+    }
+
+    #[test]
+    fn test_error_stats_categories() {
+        let mut stats = ErrorStats::default();
+        stats.record(&notify::Error::path_not_found());
+        stats.record(&notify::Error::io(std::io::Error::from(std::io::ErrorKind::NotFound)));
+        stats.record(&notify::Error::io(std::io::Error::from(
+            std::io::ErrorKind::PermissionDenied,
+        )));
+        stats.record(&notify::Error::new(ErrorKind::MaxFilesWatch));
+        stats.record(&notify::Error::generic("boom"));
+        stats.record(&notify::Error::watch_not_found());
+
+        assert_eq!(
+            stats,
+            ErrorStats {
+                path_not_found: 2,
+                permission_denied: 1,
+                max_files_watched: 1,
+                generic: 2,
+            }
+        );
+        assert_eq!(stats.total(), 6);
+    }
+
+    #[test]
+    fn test_kind_breakdown() {
+        use notify::event::{AccessKind, AccessMode, CreateKind, DataChange};
+
+        let events = [
+            Event::new(EventKind::Create(CreateKind::File)),
+            Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Any))),
+            Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Any))),
+            Event::new(EventKind::Modify(ModifyKind::Any)),
+            Event::new(EventKind::Access(AccessKind::Close(AccessMode::Write))),
+        ];
+        let breakdown = KindBreakdown::from_events(&events);
+
+        assert_eq!(breakdown.by_kind().get("Modify"), Some(&3));
+        assert_eq!(breakdown.by_kind().get("Create"), Some(&1));
+        assert_eq!(
+            breakdown.counts.get(&("Modify", "Modify(Data(Any))".to_string())),
+            Some(&2)
+        );
+        assert_eq!(breakdown.compact(), "Access 1, Create 1, Modify 3");
+        assert_eq!(KindBreakdown::default().compact(), "-");
+        assert_eq!(kind_name(&events[4].kind), "Access");
+    }
+
+    #[test]
+    fn test_directory_counts() {
+        let event = |paths: &[&str]| {
+            paths.iter().fold(Event::new(EventKind::Any), |event, path| event.add_path(PathBuf::from(path)))
+        };
+        let counts = DirectoryCounts::from_events(&[
+            event(&["/r/src/a.rs"]),
+            event(&["/r/src/b.rs"]),
+            // A rename within one directory counts once
+            event(&["/r/src/c.rs", "/r/src/d.rs"]),
+            event(&["/r/docs/x.md"]),
+            event(&["/r/a.txt"]),
+        ]);
+        assert_eq!(
+            counts.top(2),
+            [(Path::new("/r/src"), 3), (Path::new("/r"), 1)]
+        );
+        assert_eq!(counts.top(10).len(), 3);
+        assert!(DirectoryCounts::default().top(5).is_empty());
+    }
+
+    #[test]
+    fn test_path_counts() {
+        use notify::event::{AccessKind, DataChange};
+
+        let modify = Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Any)));
+        let access = Event::new(EventKind::Access(AccessKind::Any));
+        let counts = PathCounts::from_events(&[
+            modify.clone().add_path(PathBuf::from("/r/.a.swp")),
+            access.clone().add_path(PathBuf::from("/r/.a.swp")),
+            modify.clone().add_path(PathBuf::from("/r/.a.swp")),
+            modify.clone().add_path(PathBuf::from("/r/log.txt")),
+            access.add_path(PathBuf::from("/r/b.rs")),
+        ]);
+        let top: Vec<_> = counts.top(2).into_iter().map(|(path, count, kinds)| (path, count, kinds.compact())).collect();
+        assert_eq!(
+            top,
+            [
+                (Path::new("/r/.a.swp"), 3, "Access 1, Modify 2".to_string()),
+                (Path::new("/r/b.rs"), 1, "Access 1".to_string()),
+            ]
+        );
+        assert_eq!(relative_to_roots(Path::new("/r/b.rs"), &[PathBuf::from("/r")]), "./b.rs");
+        assert_eq!(relative_to_roots(Path::new("/s/b.rs"), &[PathBuf::from("/r")]), "/s/b.rs");
+    }
+
+    #[test]
+    fn test_queue_depth_stats() {
+        let stats = QueueDepthStats::from_samples(&[0, 4, 2, 0]);
+        assert_eq!(stats.samples, 4);
+        assert_eq!(stats.max, 4);
+        assert_eq!(stats.mean, 1.5);
+        assert_eq!(QueueDepthStats::from_samples(&[]), QueueDepthStats::default());
+    }
+
+    #[test]
+    fn test_channel_overhead_means() {
+        let overhead = ChannelOverhead {
+            sends: 1 << 33,
+            send_time: Duration::from_secs(1 << 33),
+            ..ChannelOverhead::default()
+        };
+        assert_eq!(overhead.mean_send(), Some(Duration::from_secs(1)));
+        assert_eq!(overhead.mean_recv(), None);
+    }
+
+    #[test]
+    fn test_recover_only_after_overflow() {
+        let mut collected = CollectedEvents::default();
+        assert_eq!(collected.recover_with(|| 1), None);
+        assert!(collected.recovery_time.is_none());
+
+        collected.rescans = 1;
+        assert_eq!(collected.recover_with(|| 1), Some(1));
+        assert!(collected.recovery_time.is_some());
+    }
+}
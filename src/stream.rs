@@ -0,0 +1,133 @@
+use crate::harness::{enumerate_roots, report_walk_errors, walk_root};
+use crate::latency::DurationSummary;
+use crate::options::Options;
+use crate::recursive_file_watcher::{is_excluded, ManualRecursiveWatcher};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// How manual mode gets from a tree to its watches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Enumeration {
+    /// Walk the whole tree into a list, then register every file in it
+    Sequential,
+    /// Register each file as the walk reaches it
+    Streaming,
+}
+
+impl Enumeration {
+    pub const ALL: [Self; 2] = [Self::Sequential, Self::Streaming];
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Sequential => "sequential",
+            Self::Streaming => "streaming",
+        }
+    }
+}
+
+/// One end-to-end manual setup: tree walk plus watch registration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamedSetup {
+    pub enumeration: Enumeration,
+    pub total: Duration,
+    pub watched: usize,
+}
+
+/// Walk `roots` and watch the first `limit` files they hold the given way, timing both together
+fn setup(enumeration: Enumeration, roots: &[PathBuf], limit: usize, options: &Options) -> notify::Result<StreamedSetup> {
+    let config = options.watch_config();
+    let (roots, walk_options) = (roots.to_vec(), options.clone());
+    let exclude = options.exclude.clone();
+    let start = Instant::now();
+    // Owned, so the streaming variant can hand it to its walker thread
+    let walk = roots
+        .into_iter()
+        .flat_map(move |root| walk_root(&root, &walk_options))
+        .take(limit)
+        .filter(move |file| !is_excluded(file, &exclude));
+    let watcher = match enumeration {
+        Enumeration::Sequential => ManualRecursiveWatcher::new_with_config(walk.collect::<Vec<PathBuf>>(), &config)?,
+        Enumeration::Streaming => ManualRecursiveWatcher::new_streaming(walk, &config)?,
+    };
+    let total = start.elapsed();
+    Ok(StreamedSetup {
+        enumeration,
+        total,
+        watched: watcher.files_watched(),
+    })
+}
+
+/// Time manual mode's whole setup with the walk finished up front and with it
+/// streamed into registration, alternating the two each iteration
+///
+/// `--watch-order` needs the full list up front, so neither run applies it; both
+/// register files in walk order.
+pub fn run_stream_setup(roots: &[PathBuf], options: &Options) -> Result<Vec<StreamedSetup>, Box<dyn std::error::Error>> {
+    println!("\n=== Sequential vs Streaming Enumeration ===");
+    // Held to `--max-files`, which both variants then stop at; this also warms the
+    // dentry cache, so neither pays for the first walk
+    let listings = enumerate_roots(roots, options)?;
+    let files: usize = listings.iter().map(|listing| listing.files.len()).sum();
+    println!("{} files, {} iterations", files, options.iterations);
+    report_walk_errors(roots, &listings, "");
+
+    let mut results = Vec::new();
+    for iteration in 0..options.iterations {
+        let order = if iteration % 2 == 0 { Enumeration::ALL } else { [Enumeration::Streaming, Enumeration::Sequential] };
+        for enumeration in order {
+            results.push(setup(enumeration, roots, files, options)?);
+        }
+    }
+
+    println!("\n📊 Enumeration plus registration:");
+    println!("  {:<12} {:>10} {:>12} {:>12}", "Variant", "Watched", "Median", "Mean");
+    let mut medians = Vec::new();
+    for enumeration in Enumeration::ALL {
+        let runs: Vec<&StreamedSetup> = results.iter().filter(|r| r.enumeration == enumeration).collect();
+        let Some(summary) = DurationSummary::from_durations(runs.iter().map(|r| r.total).collect()) else {
+            continue;
+        };
+        println!(
+            "  {:<12} {:>10} {:>12} {:>12}",
+            enumeration.display_name(),
+            runs[0].watched,
+            format!("{:.1?}", summary.p50),
+            format!("{:.1?}", summary.mean)
+        );
+        medians.push(summary.p50);
+    }
+    if let [sequential, streaming] = medians[..] {
+        let (sequential, streaming) = (sequential.as_secs_f64(), streaming.as_secs_f64());
+        if streaming <= sequential {
+            println!("\n  Streaming setup is {:.2}x faster than sequential", sequential / streaming.max(f64::EPSILON));
+        } else {
+            println!("\n  Streaming setup is {:.2}x slower than sequential", streaming / sequential.max(f64::EPSILON));
+        }
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recursive_file_watcher::walk_files;
+    use std::fs;
+
+    #[test]
+    fn test_streaming_watches_the_same_files() {
+        let dir = std::env::temp_dir().join(format!("watcher-stream-{}", std::process::id()));
+        fs::create_dir_all(dir.join("a/b")).unwrap();
+        for file in ["1.js", "a/2.js", "a/b/3.js"] {
+            fs::write(dir.join(file), "").unwrap();
+        }
+        assert_eq!(walk_files(&dir).count(), 3);
+
+        let roots = [dir.clone()];
+        let sequential = setup(Enumeration::Sequential, &roots, usize::MAX, &Options::default()).unwrap();
+        let streaming = setup(Enumeration::Streaming, &roots, usize::MAX, &Options::default()).unwrap();
+        assert_eq!((sequential.watched, streaming.watched), (3, 3));
+        let capped = setup(Enumeration::Streaming, &roots, 2, &Options::default()).unwrap();
+        assert_eq!(capped.watched, 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
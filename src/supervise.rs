@@ -0,0 +1,327 @@
+use crate::harness::{
+    copy_dir_recursive, prepare_scratch_dir, settle_for, start_watcher_on_roots, watched_files, SettlingCollector,
+};
+use crate::options::Options;
+use crate::recursive_file_watcher::{event_channel, EventReceiver, WatcherGuard, WatcherMode};
+use crate::stats::{is_overflow_error, CollectedEvents};
+use notify::{ErrorKind, Event};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How often the supervisor checks its roots are still the directories it watched
+const ROOT_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Wait before retrying a rebuild that failed or found a root missing
+const REBUILD_RETRY: Duration = Duration::from_millis(20);
+
+/// Writes `test-restart` spreads over the time around a rebuild
+const RESTART_WRITES: usize = 50;
+
+/// Spacing of those writes; fixed so the gap shows up whatever `--pace` says
+const RESTART_WRITE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// One rebuild of a supervised watcher
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Restart {
+    pub cause: String,
+    pub detected_at: Instant,
+    /// When the rebuilt watcher was ready, or `None` if it never was
+    pub resumed_at: Option<Instant>,
+}
+
+impl Restart {
+    /// How long no watcher was running
+    pub fn gap(&self) -> Option<Duration> {
+        Some(self.resumed_at?.duration_since(self.detected_at))
+    }
+}
+
+/// Identity of a root directory, so one deleted and recreated under the same name is noticed
+///
+/// Inode numbers are often reused straight away, so the creation time is part of it.
+fn root_identity(root: &Path) -> Option<(u64, u128)> {
+    let metadata = fs::metadata(root).ok()?;
+    let created = metadata.created().ok()?.duration_since(std::time::UNIX_EPOCH).ok()?.as_nanos();
+    #[cfg(unix)]
+    let inode = std::os::unix::fs::MetadataExt::ino(&metadata);
+    #[cfg(not(unix))]
+    let inode = 0;
+    Some((inode, created))
+}
+
+/// Why the watcher can't carry on after delivering `result`, if it can't
+fn fatal_cause(result: &notify::Result<Event>, roots: &[PathBuf]) -> Option<String> {
+    match result {
+        Ok(event) if event.kind.is_remove() => {
+            let root = event.paths.iter().find(|path| roots.contains(path))?;
+            Some(format!("root {} removed", root.display()))
+        }
+        Ok(_) => None,
+        Err(e) if is_overflow_error(e) => None,
+        Err(e) => match e.kind {
+            ErrorKind::Io(_) | ErrorKind::Generic(_) | ErrorKind::PathNotFound | ErrorKind::WatchNotFound => {
+                Some(format!("backend error: {}", e))
+            }
+            _ => None,
+        },
+    }
+}
+
+/// Whether any root is missing or has been replaced since `identities` were taken
+fn roots_changed(roots: &[PathBuf], identities: &[Option<(u64, u128)>]) -> Option<String> {
+    let (root, _) = roots.iter().zip(identities).find(|(root, identity)| root_identity(root) != **identity)?;
+    Some(format!("root {} removed or replaced", root.display()))
+}
+
+/// A watcher of one mode, rebuilt whenever it fails, behind a channel that outlives it
+///
+/// Events are forwarded with their original emit time. A removed root is waited
+/// for, then watched afresh; each rebuild is recorded with the gap during which
+/// nothing was watching.
+pub struct Supervisor {
+    stop: Arc<AtomicBool>,
+    restarts: Arc<Mutex<Vec<Restart>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Supervisor {
+    pub fn start(mode: WatcherMode, roots: &[PathBuf], options: &Options) -> notify::Result<(Self, EventReceiver)> {
+        let roots: Vec<PathBuf> = roots.iter().map(std::path::absolute).collect::<Result<_, _>>()?;
+        let (sink, rx) = event_channel(&options.watch_config());
+        let mut identities: Vec<_> = roots.iter().map(|root| root_identity(root)).collect();
+        let (watcher, inner, _) = start_watcher_on_roots(mode, &roots, options)?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let restarts = Arc::new(Mutex::new(Vec::new()));
+        let thread = {
+            let stop = stop.clone();
+            let restarts = restarts.clone();
+            let options = options.clone();
+            std::thread::spawn(move || {
+                let mut current: (WatcherGuard, EventReceiver) = (watcher, inner);
+                let mut last_check = Instant::now();
+                while !stop.load(Ordering::Relaxed) {
+                    let mut cause = match current.1.recv_timeout(ROOT_CHECK_INTERVAL) {
+                        Ok(item) => {
+                            let cause = fatal_cause(&item.result, &roots);
+                            sink.forward(item);
+                            cause
+                        }
+                        Err(RecvTimeoutError::Timeout) => None,
+                        Err(RecvTimeoutError::Disconnected) => Some("watcher disconnected".to_string()),
+                    };
+                    if cause.is_none() && last_check.elapsed() >= ROOT_CHECK_INTERVAL {
+                        last_check = Instant::now();
+                        cause = roots_changed(&roots, &identities);
+                    }
+                    let Some(cause) = cause else {
+                        continue;
+                    };
+
+                    let detected_at = Instant::now();
+                    println!("   Supervisor: {}; rebuilding the watcher", cause);
+                    drop(current);
+                    restarts.lock().unwrap_or_else(PoisonError::into_inner).push(Restart {
+                        cause,
+                        detected_at,
+                        resumed_at: None,
+                    });
+                    loop {
+                        if stop.load(Ordering::Relaxed) {
+                            return;
+                        }
+                        if roots.iter().all(|root| root.is_dir()) {
+                            identities = roots.iter().map(|root| root_identity(root)).collect();
+                            match start_watcher_on_roots(mode, &roots, &options) {
+                                Ok((watcher, inner, _)) => {
+                                    current = (watcher, inner);
+                                    break;
+                                }
+                                Err(e) => eprintln!("   Supervisor: rebuild failed: {}", e),
+                            }
+                        }
+                        std::thread::sleep(REBUILD_RETRY);
+                    }
+                    let resumed_at = Instant::now();
+                    if let Some(restart) = restarts.lock().unwrap_or_else(PoisonError::into_inner).last_mut() {
+                        restart.resumed_at = Some(resumed_at);
+                    }
+                    println!("   Supervisor: watching again after {:.1?}", resumed_at - detected_at);
+                }
+            })
+        };
+
+        Ok((
+            Self {
+                stop,
+                restarts,
+                thread: Some(thread),
+            },
+            rx,
+        ))
+    }
+
+    /// Every rebuild so far, oldest first
+    pub fn restarts(&self) -> Vec<Restart> {
+        self.restarts.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+}
+
+impl Drop for Supervisor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// One mode's recovery from its root being deleted and recreated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestartResult {
+    pub mode: WatcherMode,
+    pub restarts: Vec<Restart>,
+    /// Writes made before the rebuilt watcher was ready, and how many of them went unreported
+    pub in_gap: (usize, usize),
+    /// Writes made once it was, and how many of them were reported
+    pub after: (usize, usize),
+}
+
+/// Split `writes` at `resumed_at` into `(writes, unreported)` before it and `(writes, reported)` after it
+fn split_writes(
+    writes: &[(PathBuf, Instant)],
+    resumed_at: Option<Instant>,
+    collected: &CollectedEvents,
+) -> ((usize, usize), (usize, usize)) {
+    // A write counts as reported by an event emitted before the next write to the same file
+    let reported = |(path, at): &(PathBuf, Instant)| {
+        let next = writes.iter().find(|(other, later)| other == path && later > at).map(|(_, later)| *later);
+        collected.events.iter().zip(&collected.emitted_at).any(|(event, emitted)| {
+            emitted >= at && next.is_none_or(|next| *emitted < next) && event.paths.contains(path)
+        })
+    };
+    let (before, after): (Vec<_>, Vec<_>) =
+        writes.iter().partition(|(_, at)| resumed_at.is_none_or(|resumed| *at < resumed));
+    (
+        (before.len(), before.iter().filter(|write| !reported(write)).count()),
+        (after.len(), after.iter().filter(|write| reported(write)).count()),
+    )
+}
+
+/// Delete and recreate each mode's root under a supervised watcher and measure the recovery
+///
+/// Writes to the recreated tree start straight away, one every
+/// `RESTART_WRITE_INTERVAL`, so the ones landing before the rebuilt watcher is
+/// ready show what the gap costs and the rest show whether watching really resumed.
+pub fn run_restart_test(dir: &Path, options: &Options) -> Result<Vec<RestartResult>, Box<dyn std::error::Error>> {
+    println!("\n=== Supervised Restart ===");
+    println!("Source directory: {}", dir.display());
+
+    let mut results = Vec::new();
+    for mode in WatcherMode::EVERY {
+        println!("\n--- {} ---", mode.display_name());
+        let scratch = std::path::absolute(prepare_scratch_dir(dir, "restart", options)?)?;
+        let (supervisor, rx) = Supervisor::start(mode, std::slice::from_ref(&scratch), options)?;
+        let settle = settle_for(mode, options);
+        let collector = SettlingCollector::spawn(rx, settle, options.max_collect, options.consumer_delay);
+        std::thread::sleep(options.stabilize);
+
+        fs::remove_dir_all(&scratch)?;
+        copy_dir_recursive(dir, &scratch, options)?;
+        let targets = watched_files(mode, &scratch, options)?;
+        let mut writes = Vec::new();
+        for i in 0..RESTART_WRITES {
+            let Some(target) = targets.get(i % targets.len().max(1)) else {
+                break;
+            };
+            let at = Instant::now();
+            fs::OpenOptions::new().append(true).open(target)?.write_all(b"restart\n")?;
+            writes.push((std::path::absolute(target)?, at));
+            std::thread::sleep(RESTART_WRITE_INTERVAL);
+        }
+        collector.workload_done();
+        let settled = collector.finish().ok_or("event collector stopped")?;
+        let restarts = supervisor.restarts();
+        drop(supervisor);
+        fs::remove_dir_all(&scratch)?;
+
+        let resumed_at = restarts.last().and_then(|restart| restart.resumed_at);
+        let (in_gap, after) = split_writes(&writes, resumed_at, &settled.collected);
+        let result = RestartResult { mode, restarts, in_gap, after };
+        println!(
+            "   {} restart(s); {} of {} writes before resuming missed, {} of {} after reported",
+            result.restarts.len(),
+            result.in_gap.1,
+            result.in_gap.0,
+            result.after.1,
+            result.after.0
+        );
+        results.push(result);
+    }
+
+    println!("\n📊 Recovery after the root was deleted and recreated ({}):", std::env::consts::OS);
+    println!(
+        "  {:<20} {:>8} {:>10} {:>14} {:>14}  Cause",
+        "Mode", "Restarts", "Gap", "Missed in gap", "After resume"
+    );
+    for result in &results {
+        let restart = result.restarts.last();
+        println!(
+            "  {:<20} {:>8} {:>10} {:>14} {:>14}  {}",
+            result.mode.display_name(),
+            result.restarts.len(),
+            restart.and_then(Restart::gap).map_or("-".to_string(), |gap| format!("{:.1?}", gap)),
+            format!("{}/{}", result.in_gap.1, result.in_gap.0),
+            format!("{}/{}", result.after.1, result.after.0),
+            restart.map_or("not detected", |restart| restart.cause.as_str())
+        );
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, RemoveKind};
+    use notify::EventKind;
+
+    #[test]
+    fn test_fatal_cause() {
+        let roots = [PathBuf::from("/watched")];
+        let removed = |path: &str| Ok(Event::new(EventKind::Remove(RemoveKind::Folder)).add_path(PathBuf::from(path)));
+        assert!(fatal_cause(&removed("/watched"), &roots).unwrap().contains("removed"));
+        assert_eq!(fatal_cause(&removed("/watched/sub"), &roots), None);
+        let created = Ok(Event::new(EventKind::Create(CreateKind::File)).add_path(PathBuf::from("/watched")));
+        assert_eq!(fatal_cause(&created, &roots), None);
+        assert!(fatal_cause(&Err(notify::Error::generic("backend died")), &roots).is_some());
+        assert_eq!(fatal_cause(&Err(notify::Error::generic("queue overflow")), &roots), None);
+    }
+
+    #[test]
+    fn test_split_writes() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut writes: Vec<_> = (0..4).map(|i| (PathBuf::from(format!("/w/{}", i)), at(i * 10))).collect();
+        let collected = CollectedEvents {
+            events: vec![
+                Event::new(EventKind::Any).add_path(PathBuf::from("/w/0")),
+                Event::new(EventKind::Any).add_path(PathBuf::from("/w/3")),
+            ],
+            emitted_at: vec![at(1), at(31)],
+            ..CollectedEvents::default()
+        };
+        assert_eq!(split_writes(&writes, Some(at(15)), &collected), ((2, 1), (2, 1)));
+        assert_eq!(split_writes(&writes, None, &collected), ((4, 2), (0, 0)));
+
+        // The event after a second write to /w/0 doesn't report the first one
+        writes[0].1 = at(0);
+        writes.push((PathBuf::from("/w/0"), at(0) + Duration::from_micros(500)));
+        assert_eq!(split_writes(&writes, Some(at(15)), &collected), ((3, 2), (2, 1)));
+    }
+}
@@ -0,0 +1,258 @@
+use crate::harness::{enumerate_files, start_watcher_on_roots};
+use crate::options::Options;
+use crate::recursive_file_watcher::WatcherMode;
+use std::io;
+use std::path::PathBuf;
+
+/// Syscalls issued while setting up one watcher, grouped by what explains setup cost
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SyscallCounts {
+    pub add_watch: u64,
+    /// `open` and `openat`, mostly directory reads during the walk
+    pub open: u64,
+    /// The `stat` family, including `statx` and `fstat`
+    pub stat: u64,
+    /// Every syscall, including the ones above
+    pub total: u64,
+}
+
+/// Which column of `SyscallCounts` a syscall number belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(not(all(target_os = "linux", target_env = "gnu")), allow(dead_code))]
+enum Category {
+    AddWatch,
+    Open,
+    Stat,
+}
+
+impl SyscallCounts {
+    #[cfg_attr(not(all(target_os = "linux", target_env = "gnu")), allow(dead_code))]
+    fn count(&mut self, category: Option<Category>) {
+        self.total += 1;
+        match category {
+            Some(Category::AddWatch) => self.add_watch += 1,
+            Some(Category::Open) => self.open += 1,
+            Some(Category::Stat) => self.stat += 1,
+            None => {},
+        }
+    }
+
+    /// Syscalls of any kind per watched file
+    pub fn per_file(&self, files: usize) -> f64 {
+        self.total as f64 / files.max(1) as f64
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn classify(nr: i64) -> Option<Category> {
+    #[cfg(target_arch = "x86_64")]
+    if [libc::SYS_open, libc::SYS_creat].contains(&nr) {
+        return Some(Category::Open);
+    }
+    #[cfg(target_arch = "x86_64")]
+    if [libc::SYS_stat, libc::SYS_lstat].contains(&nr) {
+        return Some(Category::Stat);
+    }
+    match nr {
+        libc::SYS_inotify_add_watch => Some(Category::AddWatch),
+        libc::SYS_openat | libc::SYS_openat2 => Some(Category::Open),
+        libc::SYS_newfstatat | libc::SYS_fstat | libc::SYS_statx => Some(Category::Stat),
+        _ => None,
+    }
+}
+
+/// Run `setup` in a forked child traced with ptrace and count the syscalls it makes
+///
+/// The child stops itself before `setup` and `_exit`s straight after, so process
+/// startup and watcher teardown are not counted. Threads the setup spawns (notify's
+/// event loop, which issues `inotify_add_watch` for recursive modes) are followed.
+/// Forking copies only the calling thread, so call this before starting any others.
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+pub fn count_setup_syscalls(setup: impl FnOnce()) -> io::Result<SyscallCounts> {
+    use std::collections::HashSet;
+
+    // SAFETY: the child only sets up tracing and runs `setup` before `_exit`; it
+    // never returns into the parent's code
+    let child = unsafe { libc::fork() };
+    if child < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if child == 0 {
+        unsafe {
+            // Setup output would be interleaved with the parent's report
+            let null = libc::open(c"/dev/null".as_ptr(), libc::O_WRONLY);
+            if null >= 0 {
+                libc::dup2(null, libc::STDOUT_FILENO);
+            }
+            if libc::ptrace(libc::PTRACE_TRACEME, 0, 0, 0) != 0 {
+                libc::_exit(2);
+            }
+            libc::raise(libc::SIGSTOP);
+        }
+        setup();
+        unsafe { libc::_exit(0) };
+    }
+
+    let mut status = 0;
+    // SAFETY: plain waitpid/ptrace calls on our own child and its threads
+    unsafe {
+        if libc::waitpid(child, &mut status, 0) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if !libc::WIFSTOPPED(status) {
+            return Err(io::Error::other("traced child exited before setup"));
+        }
+        let trace_options = libc::PTRACE_O_TRACESYSGOOD | libc::PTRACE_O_TRACECLONE | libc::PTRACE_O_EXITKILL;
+        if libc::ptrace(libc::PTRACE_SETOPTIONS, child, 0, trace_options) != 0 {
+            let err = io::Error::last_os_error();
+            libc::kill(child, libc::SIGKILL);
+            libc::waitpid(child, &mut status, 0);
+            return Err(err);
+        }
+        libc::ptrace(libc::PTRACE_SYSCALL, child, 0, 0);
+    }
+
+    let mut counts = SyscallCounts::default();
+    let mut threads: HashSet<libc::pid_t> = HashSet::from([child]);
+    while !threads.is_empty() {
+        let tid = unsafe { libc::waitpid(-1, &mut status, libc::__WALL) };
+        if tid < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if libc::WIFEXITED(status) || libc::WIFSIGNALED(status) {
+            threads.remove(&tid);
+            if tid == child && libc::WIFSIGNALED(status) {
+                return Err(io::Error::other(format!(
+                    "traced setup died with signal {}",
+                    libc::WTERMSIG(status)
+                )));
+            }
+            continue;
+        }
+        threads.insert(tid);
+
+        let signal = libc::WSTOPSIG(status);
+        let mut deliver = 0;
+        if signal == libc::SIGTRAP | 0x80 {
+            let mut info: libc::ptrace_syscall_info = unsafe { std::mem::zeroed() };
+            let read = unsafe {
+                libc::ptrace(
+                    libc::PTRACE_GET_SYSCALL_INFO,
+                    tid,
+                    std::mem::size_of::<libc::ptrace_syscall_info>(),
+                    &mut info as *mut libc::ptrace_syscall_info,
+                )
+            };
+            if read > 0 && info.op == libc::PTRACE_SYSCALL_INFO_ENTRY {
+                // SAFETY: `op` says the entry variant of the union was filled in
+                let nr = unsafe { info.u.entry.nr } as i64;
+                counts.count(classify(nr));
+            }
+        } else if signal != libc::SIGTRAP && signal != libc::SIGSTOP {
+            // A real signal for the tracee, not a clone event or a new thread's initial stop
+            deliver = signal;
+        }
+        unsafe { libc::ptrace(libc::PTRACE_SYSCALL, tid, 0, deliver) };
+    }
+    Ok(counts)
+}
+
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+pub fn count_setup_syscalls(_setup: impl FnOnce()) -> io::Result<SyscallCounts> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "syscall counting needs ptrace on Linux",
+    ))
+}
+
+/// Count the syscalls each watcher mode issues during setup over `roots`
+///
+/// Wall time depends on caches and the scheduler; syscall counts don't, and show
+/// how setup work grows with the tree (one `inotify_add_watch` per file for manual
+/// mode against one per directory for native).
+pub fn run_syscall_counts(
+    roots: &[PathBuf],
+    options: &Options,
+) -> Result<Vec<(WatcherMode, SyscallCounts)>, Box<dyn std::error::Error>> {
+    println!("\n=== Setup Syscall Counts ===");
+    let files = enumerate_files(roots, options)?.len();
+    let dirs: usize = roots.iter().map(|root| count_dirs(root)).sum();
+    println!("Tree: {} files in {} directories", files, dirs);
+
+    let mut results = Vec::new();
+    for mode in WatcherMode::EVERY {
+        println!("   Tracing {} setup...", mode.display_name());
+        let counts = count_setup_syscalls(|| {
+            match start_watcher_on_roots(mode, roots, options) {
+                // Dropping would count teardown; the child exits right after
+                Ok(watcher) => std::mem::forget(watcher),
+                Err(e) => eprintln!("   {} setup failed: {}", mode.display_name(), e),
+            }
+        })?;
+        results.push((mode, counts));
+    }
+
+    println!("\n📊 Setup syscalls:");
+    println!(
+        "  {:<20} {:>12} {:>10} {:>10} {:>10} {:>10}",
+        "Mode", "add_watch", "open", "stat", "total", "per file"
+    );
+    for (mode, counts) in &results {
+        println!(
+            "  {:<20} {:>12} {:>10} {:>10} {:>10} {:>10.1}",
+            mode.display_name(),
+            counts.add_watch,
+            counts.open,
+            counts.stat,
+            counts.total,
+            counts.per_file(files)
+        );
+    }
+    Ok(results)
+}
+
+fn count_dirs(root: &std::path::Path) -> usize {
+    std::fs::read_dir(root)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+                .map(|entry| count_dirs(&entry.path()))
+                .sum::<usize>()
+        })
+        .unwrap_or(0)
+        + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_by_category() {
+        let mut counts = SyscallCounts::default();
+        counts.count(Some(Category::AddWatch));
+        counts.count(Some(Category::Stat));
+        counts.count(None);
+        assert_eq!(
+            counts,
+            SyscallCounts {
+                add_watch: 1,
+                open: 0,
+                stat: 1,
+                total: 3,
+            }
+        );
+        assert_eq!(counts.per_file(2), 1.5);
+        assert_eq!(counts.per_file(0), 3.0);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify(libc::SYS_inotify_add_watch), Some(Category::AddWatch));
+        assert_eq!(classify(libc::SYS_openat), Some(Category::Open));
+        assert_eq!(classify(libc::SYS_statx), Some(Category::Stat));
+        assert_eq!(classify(libc::SYS_write), None);
+    }
+}
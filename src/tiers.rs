@@ -0,0 +1,133 @@
+use crate::latency::{match_writes, LatencyReport, WriteRecord};
+use crate::stats::CollectedEvents;
+use std::path::Path;
+
+/// Whether `path` is on the `--priority` list: its file name matches a name, or a `*` pattern's suffix
+pub fn is_priority(path: &Path, priority: &[String]) -> bool {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    priority.iter().any(|pattern| match pattern.strip_prefix('*') {
+        Some(suffix) => name.ends_with(suffix),
+        None => name == pattern,
+    })
+}
+
+/// Which part of a two-tier filter a file falls in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tier {
+    /// Always watched, like a project's config files
+    Priority,
+    /// Everything else, of which filtered modes watch a sample
+    Rest,
+}
+
+impl Tier {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Priority => "Priority",
+            Self::Rest => "Rest",
+        }
+    }
+
+    /// Prefix for this tier's history metrics
+    pub fn slug(&self) -> &'static str {
+        match self {
+            Self::Priority => "priority",
+            Self::Rest => "rest",
+        }
+    }
+}
+
+/// Coverage and latency of the writes to one tier's files
+#[derive(Debug, Clone, PartialEq)]
+pub struct TierReport {
+    pub tier: Tier,
+    pub latency: LatencyReport,
+}
+
+impl TierReport {
+    /// Split `writes` by tier and match each tier's writes against `collected`
+    pub fn measure(writes: &[WriteRecord], priority: &[String], collected: &CollectedEvents) -> [TierReport; 2] {
+        [Tier::Priority, Tier::Rest].map(|tier| {
+            let tier_writes: Vec<WriteRecord> = writes
+                .iter()
+                .filter(|write| is_priority(&write.path, priority) == (tier == Tier::Priority))
+                .cloned()
+                .collect();
+            TierReport {
+                tier,
+                latency: match_writes(&tier_writes, collected),
+            }
+        })
+    }
+
+    pub fn writes(&self) -> usize {
+        self.latency.samples.len() + self.latency.unmatched
+    }
+
+    /// Fraction of this tier's writes that got an event, or `None` without writes
+    pub fn coverage(&self) -> Option<f64> {
+        let writes = self.writes();
+        (writes > 0).then(|| self.latency.samples.len() as f64 / writes as f64)
+    }
+
+    pub fn report(&self, indent: &str) {
+        let Some(coverage) = self.coverage() else {
+            println!("{}{} tier: no writes", indent, self.tier.display_name());
+            return;
+        };
+        let latency = self.latency.total().map_or("-".to_string(), |total| {
+            format!("p50 {:.1?}, p99 {:.1?}", total.p50, total.p99)
+        });
+        println!(
+            "{}{} tier: {} of {} writes reported ({:.1}%), latency {}",
+            indent,
+            self.tier.display_name(),
+            self.latency.samples.len(),
+            self.writes(),
+            coverage * 100.0,
+            latency
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::{Event, EventKind};
+    use std::path::PathBuf;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_is_priority() {
+        let priority = ["package.json".to_string(), "*.toml".to_string()];
+        assert!(is_priority(Path::new("/repo/package.json"), &priority));
+        assert!(is_priority(Path::new("/repo/crates/a/Cargo.toml"), &priority));
+        assert!(!is_priority(Path::new("/repo/src/package.json.bak"), &priority));
+        assert!(!is_priority(Path::new("/repo/src/main.rs"), &priority));
+        assert!(!is_priority(Path::new("/repo/package.json"), &[]));
+    }
+
+    #[test]
+    fn test_tier_reports() {
+        let start = Instant::now();
+        let write = |path: &str| WriteRecord {
+            path: PathBuf::from(path),
+            started_at: start,
+            written_at: start,
+        };
+        let writes = [write("/r/Cargo.toml"), write("/r/a.rs"), write("/r/b.rs")];
+        let mut collected = CollectedEvents::default();
+        for path in ["/r/Cargo.toml", "/r/a.rs"] {
+            collected.events.push(Event::new(EventKind::Any).add_path(PathBuf::from(path)));
+            collected.emitted_at.push(start + Duration::from_millis(1));
+            collected.received_at.push(start + Duration::from_millis(2));
+        }
+
+        let [priority, rest] = TierReport::measure(&writes, &["*.toml".to_string()], &collected);
+        assert_eq!((priority.tier, priority.writes(), priority.coverage()), (Tier::Priority, 1, Some(1.0)));
+        assert_eq!((rest.tier, rest.writes(), rest.coverage()), (Tier::Rest, 2, Some(0.5)));
+        assert_eq!(TierReport::measure(&[], &[], &collected)[0].coverage(), None);
+    }
+}
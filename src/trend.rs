@@ -0,0 +1,88 @@
+/// Least-squares line through a series of `(x, y)` points
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinearTrend {
+    pub points: usize,
+    /// Change in `y` per unit of `x`
+    pub slope: f64,
+    pub intercept: f64,
+    /// Standard error of `slope`
+    pub slope_std_error: f64,
+}
+
+/// |t| above which a slope is treated as real rather than noise (≈95% two-sided)
+const SIGNIFICANT_T: f64 = 2.0;
+
+impl LinearTrend {
+    /// Fit a line, or `None` with fewer than three points or no spread in `x`
+    pub fn fit(points: &[(f64, f64)]) -> Option<Self> {
+        let n = points.len();
+        if n < 3 {
+            return None;
+        }
+        let count = n as f64;
+        let mean_x = points.iter().map(|p| p.0).sum::<f64>() / count;
+        let mean_y = points.iter().map(|p| p.1).sum::<f64>() / count;
+        let sxx: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+        if sxx == 0.0 {
+            return None;
+        }
+        let sxy: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+        let slope = sxy / sxx;
+        let intercept = mean_y - slope * mean_x;
+        let residuals: f64 = points
+            .iter()
+            .map(|p| (p.1 - (intercept + slope * p.0)).powi(2))
+            .sum();
+        let slope_std_error = (residuals / (count - 2.0) / sxx).sqrt();
+
+        Some(Self {
+            points: n,
+            slope,
+            intercept,
+            slope_std_error,
+        })
+    }
+
+    /// The slope's t statistic; infinite for a perfect non-flat fit
+    pub fn t_statistic(&self) -> f64 {
+        if self.slope_std_error == 0.0 {
+            if self.slope == 0.0 {
+                0.0
+            } else {
+                f64::INFINITY.copysign(self.slope)
+            }
+        } else {
+            self.slope / self.slope_std_error
+        }
+    }
+
+    /// Whether the slope is positive and unlikely to be noise
+    pub fn is_significant_growth(&self) -> bool {
+        self.t_statistic() > SIGNIFICANT_T
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_exact_line() {
+        let points: Vec<_> = (0..10).map(|x| (x as f64, 3.0 + 2.0 * x as f64)).collect();
+        let trend = LinearTrend::fit(&points).unwrap();
+        assert!((trend.slope - 2.0).abs() < 1e-9);
+        assert!((trend.intercept - 3.0).abs() < 1e-9);
+        assert!(trend.is_significant_growth());
+        assert!(LinearTrend::fit(&points[..2]).is_none());
+    }
+
+    #[test]
+    fn test_noise_is_not_growth() {
+        let points = [(0.0, 10.0), (1.0, 12.0), (2.0, 9.0), (3.0, 11.0), (4.0, 10.0), (5.0, 10.5)];
+        let trend = LinearTrend::fit(&points).unwrap();
+        assert!(!trend.is_significant_growth());
+
+        let flat = [(0.0, 5.0), (1.0, 5.0), (2.0, 5.0)];
+        assert_eq!(LinearTrend::fit(&flat).unwrap().t_statistic(), 0.0);
+    }
+}
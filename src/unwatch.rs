@@ -0,0 +1,132 @@
+use crate::harness::{enumerate_files, manual_watcher, ordered_watches};
+use crate::latency::DurationSummary;
+use crate::options::Options;
+use crate::recursive_file_watcher::{NativeRecursiveWatcher, WatcherMode};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Setup and teardown cost of one watcher, unwatched explicitly instead of dropped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnwatchResult {
+    pub mode: WatcherMode,
+    pub setup_time: Duration,
+    /// One entry per successful `unwatch` call: a file each in manual mode, a root each in native mode
+    pub unwatch_times: Vec<Duration>,
+    /// `unwatch` calls that returned an error
+    pub failures: usize,
+}
+
+impl UnwatchResult {
+    /// Split the outcome of each `unwatch` call into times and a failure count, printing the first error
+    fn from_attempts(mode: WatcherMode, setup_time: Duration, attempts: Vec<notify::Result<Duration>>) -> Self {
+        let mut unwatch_times = Vec::with_capacity(attempts.len());
+        let mut failures = 0;
+        for attempt in attempts {
+            match attempt {
+                Ok(elapsed) => unwatch_times.push(elapsed),
+                Err(e) => {
+                    if failures == 0 {
+                        eprintln!("   Unwatch failed: {}", e);
+                    }
+                    failures += 1;
+                },
+            }
+        }
+        if failures > 1 {
+            eprintln!("   ... and {} more unwatch failures", failures - 1);
+        }
+        UnwatchResult {
+            mode,
+            setup_time,
+            unwatch_times,
+            failures,
+        }
+    }
+
+    /// Time spent in every `unwatch` call together
+    pub fn total(&self) -> Duration {
+        self.unwatch_times.iter().sum()
+    }
+
+    /// Total unwatch time as a fraction of setup time
+    pub fn ratio(&self) -> f64 {
+        self.total().as_secs_f64() / self.setup_time.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// Set up manual and native mode, then remove their watches with explicit `unwatch` calls
+///
+/// Tools that stop watching a project when its window loses focus pay this cost
+/// on every switch, and it's invisible in setup numbers. Manual mode unwatches
+/// file by file, so its per-path distribution is reported; native mode drops
+/// each root's whole recursive watch in one call.
+pub fn run_unwatch_test(roots: &[PathBuf], options: &Options) -> Result<Vec<UnwatchResult>, Box<dyn std::error::Error>> {
+    println!("\n=== Unwatch Latency ===");
+    let files: Vec<PathBuf> = enumerate_files(roots, options)?;
+    println!("{} files under {} root(s)", files.len(), roots.len());
+
+    println!("\n--- {} ---", WatcherMode::Manual.display_name());
+    let mut watcher = manual_watcher(ordered_watches(files, options), options)?;
+    let manual = UnwatchResult::from_attempts(WatcherMode::Manual, watcher.setup_time(), watcher.unwatch_all());
+
+    println!("\n--- {} ---", WatcherMode::Native.display_name());
+    let mut watcher = NativeRecursiveWatcher::new_with_roots_and_config(roots, &options.watch_config())?;
+    let native = UnwatchResult::from_attempts(WatcherMode::Native, watcher.setup_time(), watcher.unwatch_roots());
+
+    let results = vec![manual, native];
+    println!("\n📊 Unwatch vs setup ({}):", std::env::consts::OS);
+    println!(
+        "  {:<20} {:>8} {:>8} {:>12} {:>12} {:>10} {:>10} {:>10} {:>10} {:>8}",
+        "Mode", "Calls", "Failed", "Setup", "Unwatch", "Mean", "p50", "p99", "Max", "Ratio"
+    );
+    for result in &results {
+        let Some(summary) = DurationSummary::from_durations(result.unwatch_times.clone()) else {
+            println!("  {:<20} {:>8} {:>8}", result.mode.display_name(), 0, result.failures);
+            continue;
+        };
+        println!(
+            "  {:<20} {:>8} {:>8} {:>12} {:>12} {:>10} {:>10} {:>10} {:>10} {:>7.2}x",
+            result.mode.display_name(),
+            result.unwatch_times.len(),
+            result.failures,
+            format!("{:.1?}", result.setup_time),
+            format!("{:.1?}", result.total()),
+            format!("{:.1?}", summary.mean),
+            format!("{:.1?}", summary.p50),
+            format!("{:.1?}", summary.p99),
+            format!("{:.1?}", summary.max),
+            result.ratio()
+        );
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unwatch_totals() {
+        let result = UnwatchResult {
+            mode: WatcherMode::Manual,
+            setup_time: Duration::from_millis(40),
+            unwatch_times: vec![Duration::from_millis(4), Duration::from_millis(6)],
+            failures: 0,
+        };
+        assert_eq!(result.total(), Duration::from_millis(10));
+        assert_eq!(result.ratio(), 0.25);
+    }
+
+    #[test]
+    fn test_unwatch_counts_failures() {
+        let attempts = vec![
+            Ok(Duration::from_millis(4)),
+            Err(notify::Error::path_not_found()),
+            Ok(Duration::from_millis(6)),
+            Err(notify::Error::watch_not_found()),
+        ];
+        let result = UnwatchResult::from_attempts(WatcherMode::Manual, Duration::from_millis(40), attempts);
+        assert_eq!(result.unwatch_times, vec![Duration::from_millis(4), Duration::from_millis(6)]);
+        assert_eq!(result.failures, 2);
+    }
+}
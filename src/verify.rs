@@ -0,0 +1,150 @@
+use crate::stats::CollectedEvents;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+
+/// Content hash of every readable file in a set, taken before or after a modification phase
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentSnapshot {
+    hashes: HashMap<PathBuf, u64>,
+}
+
+impl ContentSnapshot {
+    /// Hash `files`, skipping any that can't be read
+    pub fn take<P: AsRef<Path>>(files: &[P]) -> Self {
+        let hashes = files
+            .iter()
+            .filter_map(|file| {
+                let file = file.as_ref();
+                let content = fs::read(file).ok()?;
+                let mut hasher = DefaultHasher::new();
+                hasher.write(&content);
+                Some((file.to_path_buf(), hasher.finish()))
+            })
+            .collect();
+        Self { hashes }
+    }
+
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    /// Paths whose content differs in `later`, including ones only present in one of the two
+    pub fn changed_in(&self, later: &Self) -> HashSet<PathBuf> {
+        let mut changed: HashSet<PathBuf> = self
+            .hashes
+            .iter()
+            .filter(|(path, hash)| later.hashes.get(*path) != Some(hash))
+            .map(|(path, _)| path.clone())
+            .collect();
+        changed.extend(later.hashes.keys().filter(|path| !self.hashes.contains_key(*path)).cloned());
+        changed
+    }
+
+    fn contains(&self, path: &Path) -> bool {
+        self.hashes.contains_key(path)
+    }
+}
+
+/// Events checked against what actually changed on disk, rather than counted
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentCheck {
+    /// Files whose content changed
+    pub changed: usize,
+    /// Changed files no event named
+    pub false_negatives: Vec<PathBuf>,
+    /// Files an event named whose content is unchanged
+    pub false_positives: Vec<PathBuf>,
+}
+
+impl ContentCheck {
+    /// Compare the files events named against the ones whose hash changed
+    ///
+    /// Only paths in either snapshot count, so events for directories or for files
+    /// outside the watched set don't show up as false positives.
+    pub fn compare(before: &ContentSnapshot, after: &ContentSnapshot, collected: &CollectedEvents) -> Self {
+        let changed = before.changed_in(after);
+        let mut reported = HashSet::new();
+        for change in collected.normalized() {
+            reported.extend(change.renamed_to);
+            reported.insert(change.path);
+        }
+        reported.retain(|path| before.contains(path) || after.contains(path));
+
+        let mut false_negatives: Vec<PathBuf> = changed.difference(&reported).cloned().collect();
+        let mut false_positives: Vec<PathBuf> = reported.difference(&changed).cloned().collect();
+        false_negatives.sort();
+        false_positives.sort();
+        Self {
+            changed: changed.len(),
+            false_negatives,
+            false_positives,
+        }
+    }
+
+    /// Table text, false negatives over false positives
+    pub fn describe(&self) -> String {
+        format!("{}/{}", self.false_negatives.len(), self.false_positives.len())
+    }
+
+    pub fn report(&self, indent: &str) {
+        println!(
+            "{}Content check: {} files changed, {} false negatives, {} false positives",
+            indent,
+            self.changed,
+            self.false_negatives.len(),
+            self.false_positives.len()
+        );
+        for (label, paths) in [("missed", &self.false_negatives), ("spurious", &self.false_positives)] {
+            for path in paths.iter().take(3) {
+                println!("{}  {}: {}", indent, label, path.display());
+            }
+            if paths.len() > 3 {
+                println!("{}  ... and {} more {}", indent, paths.len() - 3, label);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{AccessKind, DataChange, ModifyKind};
+    use notify::{Event, EventKind};
+
+    #[test]
+    fn test_content_check_false_negatives_and_positives() {
+        let dir = std::path::absolute("test_verify_dir").unwrap();
+        fs::create_dir_all(&dir).unwrap();
+        let files: Vec<PathBuf> = ["a.js", "b.js", "c.js"].iter().map(|name| dir.join(name)).collect();
+        for file in &files {
+            fs::write(file, "same").unwrap();
+        }
+        let before = ContentSnapshot::take(&files);
+        fs::write(&files[0], "changed").unwrap();
+        fs::write(&files[1], "changed").unwrap();
+        let after = ContentSnapshot::take(&files);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let modify = EventKind::Modify(ModifyKind::Data(DataChange::Any));
+        let mut collected = CollectedEvents::default();
+        for (kind, path) in [
+            (modify, &files[0]),
+            // The access carries no change; the modify of unchanged c.js is the false positive
+            (EventKind::Access(AccessKind::Any), &files[2]),
+            (modify, &files[2]),
+            (modify, &dir),
+        ] {
+            collected.events.push(Event::new(kind).add_path(path.clone()));
+        }
+
+        assert_eq!(before.len(), 3);
+        let check = ContentCheck::compare(&before, &after, &collected);
+        assert_eq!(check.changed, 2);
+        assert_eq!(check.false_negatives, [files[1].clone()]);
+        assert_eq!(check.false_positives, [files[2].clone()]);
+        assert_eq!(check.describe(), "1/1");
+    }
+}
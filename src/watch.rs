@@ -0,0 +1,285 @@
+use crate::format::{self, from_v1, unchanged, Migration, Versioned};
+use crate::harness::{collect_events, start_watcher};
+use crate::latency::DurationSummary;
+use crate::normalize::{count_by_kind, describe_counts};
+use crate::options::Options;
+use crate::resources::{or_dash, ResourceSample};
+use crate::shutdown;
+use crate::stats::CollectedEvents;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Stats for one interval of `watch`, as written to the snapshot file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchSnapshot {
+    pub format_version: u32,
+    /// Wall-clock time the interval ended, in milliseconds since the Unix epoch
+    pub timestamp_ms: u128,
+    pub elapsed_secs: f64,
+    pub interval_secs: f64,
+    pub events: usize,
+    pub events_per_sec: f64,
+    /// Callback-to-consumer latency; backend latency is unknown for edits made elsewhere
+    pub queue_p50_us: Option<u128>,
+    pub queue_p99_us: Option<u128>,
+    /// Normalized change counts, e.g. `modified`
+    pub kinds: BTreeMap<String, usize>,
+    pub rescans: usize,
+    pub errors: usize,
+    pub overflowed: bool,
+    pub rss_bytes: Option<u64>,
+}
+
+impl Versioned for WatchSnapshot {
+    const FORMAT_VERSION: u32 = 3;
+    const MIGRATIONS: &'static [Migration] = &[from_v1, unchanged];
+}
+
+impl WatchSnapshot {
+    fn from_interval(collected: &CollectedEvents, elapsed: Duration, interval: Duration) -> Self {
+        let queue = DurationSummary::from_durations(
+            collected
+                .emitted_at
+                .iter()
+                .zip(&collected.received_at)
+                .map(|(emitted, received)| received.saturating_duration_since(*emitted))
+                .collect(),
+        );
+        let kinds = count_by_kind(&collected.normalized())
+            .into_iter()
+            .map(|(kind, count)| (kind.to_string(), count))
+            .collect();
+        Self {
+            format_version: Self::FORMAT_VERSION,
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis()),
+            elapsed_secs: elapsed.as_secs_f64(),
+            interval_secs: interval.as_secs_f64(),
+            events: collected.events.len(),
+            events_per_sec: collected.events.len() as f64 / interval.as_secs_f64().max(f64::EPSILON),
+            queue_p50_us: queue.map(|s| s.p50.as_micros()),
+            queue_p99_us: queue.map(|s| s.p99.as_micros()),
+            kinds,
+            rescans: collected.rescans,
+            errors: collected.errors.total() + collected.overflow_errors,
+            overflowed: collected.overflowed(),
+            rss_bytes: ResourceSample::take().rss_bytes,
+        }
+    }
+
+    fn print(&self, collected: &CollectedEvents) {
+        let us = |v: Option<u128>| or_dash(v.map(|us| format!("{:.1?}", Duration::from_micros(us as u64))));
+        println!(
+            "[{:>8}] {:>6} events {:>8.1}/s  queue p50 {:>8} p99 {:>8}  errors {:>3}{}  {}",
+            format!("{:.0?}", Duration::from_secs_f64(self.elapsed_secs)),
+            self.events,
+            self.events_per_sec,
+            us(self.queue_p50_us),
+            us(self.queue_p99_us),
+            self.errors,
+            if self.overflowed { "  OVERFLOW" } else { "" },
+            describe_counts(&count_by_kind(&collected.normalized()))
+        );
+    }
+}
+
+/// Whether the last line of `path` has no newline yet
+fn ends_mid_line(path: &Path) -> io::Result<bool> {
+    let mut file = File::open(path)?;
+    if file.metadata()?.len() == 0 {
+        return Ok(false);
+    }
+    let mut last = [0];
+    file.seek(SeekFrom::End(-1))?;
+    file.read_exact(&mut last)?;
+    Ok(last != *b"\n")
+}
+
+fn append_snapshot(file: &mut File, snapshot: &WatchSnapshot) -> io::Result<()> {
+    let line = serde_json::to_string(snapshot).map_err(io::Error::other)?;
+    writeln!(file, "{}", line)?;
+    file.flush()
+}
+
+/// Every snapshot in a `--snapshot-file` log, migrated to the current format,
+/// with the version each line was written in, and how many lines were skipped
+///
+/// A line that doesn't parse, such as one cut short when a run was killed
+/// mid-write, is skipped with a warning; a line from a newer build fails the load.
+pub fn load_snapshots(path: &Path) -> Result<(Vec<(WatchSnapshot, u32)>, usize), String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut snapshots = Vec::new();
+    let mut skipped = 0;
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let newer = serde_json::from_str(&line)
+            .ok()
+            .and_then(|value| format::version_of(&value).ok())
+            .filter(|&version| format::is_newer::<WatchSnapshot>(version));
+        if let Some(version) = newer {
+            return Err(format!(
+                "{} line {}: format version {} is newer than this build reads (up to {})",
+                path.display(),
+                i + 1,
+                version,
+                WatchSnapshot::FORMAT_VERSION
+            ));
+        }
+        match format::load(&line) {
+            Ok(snapshot) => snapshots.push(snapshot),
+            Err(e) => {
+                eprintln!("⚠️  Skipping {} line {}: {}", path.display(), i + 1, e);
+                skipped += 1;
+            }
+        }
+    }
+    Ok((snapshots, skipped))
+}
+
+/// Watch `dir` until interrupted, printing a stats line every `--stats-interval`
+///
+/// Unlike the test modes this watches the directory itself, not a scratch copy,
+/// so it can observe real editing activity. With `--snapshot-file` every line is
+/// also appended as JSON; the final partial interval is written on Ctrl-C.
+pub fn run_watch(dir: &Path, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    println!("\n=== Watching {} ===", dir.display());
+    println!(
+        "Mode: {}, stats every {:?}",
+        options.watch_mode.display_name(),
+        options.stats_interval
+    );
+
+    let mut snapshot_file = match &options.snapshot_file {
+        Some(path) => {
+            // Refuse to append onto a log a newer build wrote, or one that isn't a snapshot log
+            if path.exists() {
+                let (earlier, skipped) = load_snapshots(path)?;
+                if earlier.is_empty() && skipped > 0 {
+                    return Err(format!("{} holds no snapshots; not appending to it", path.display()).into());
+                }
+                let older = earlier
+                    .iter()
+                    .filter(|(_, version)| *version < WatchSnapshot::FORMAT_VERSION)
+                    .count();
+                if !earlier.is_empty() {
+                    println!(
+                        "{} already holds {} snapshots ({} from older format versions)",
+                        path.display(),
+                        earlier.len(),
+                        older
+                    );
+                }
+            }
+            println!("Appending snapshots to {}", path.display());
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            // Start on a line of its own after a run that was killed mid-line
+            if ends_mid_line(path)? {
+                writeln!(file)?;
+            }
+            Some(file)
+        }
+        None => None,
+    };
+    if let Err(e) = shutdown::install() {
+        eprintln!("Could not install signal handler: {}", e);
+    }
+
+    let (watcher, rx) = start_watcher(options.watch_mode, dir, options)?;
+    println!("Press Ctrl-C to stop\n");
+
+    let start = Instant::now();
+    let mut total_events = 0;
+    let mut intervals = 0;
+    while !shutdown::requested() {
+        let interval_start = Instant::now();
+        let collected = collect_events(&rx, options.stats_interval);
+        let snapshot = WatchSnapshot::from_interval(&collected, start.elapsed(), interval_start.elapsed());
+        snapshot.print(&collected);
+        if let Some(file) = snapshot_file.as_mut() {
+            if let Err(e) = append_snapshot(file, &snapshot) {
+                eprintln!("Failed to write snapshot: {}", e);
+            }
+        }
+        total_events += snapshot.events;
+        intervals += 1;
+    }
+
+    drop(watcher);
+    println!(
+        "\nStopped after {:.1?}: {} events over {} intervals",
+        start.elapsed(),
+        total_events,
+        intervals
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, ModifyKind};
+    use notify::{Event, EventKind};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_snapshot_from_interval() {
+        let mut collected = CollectedEvents::default();
+        let path = PathBuf::from("/w/a.js");
+        for kind in [
+            EventKind::Create(CreateKind::File),
+            EventKind::Modify(ModifyKind::Any),
+            EventKind::Modify(ModifyKind::Any),
+        ] {
+            collected.record(Ok(Event::new(kind).add_path(path.clone())).into());
+        }
+
+        let snapshot = WatchSnapshot::from_interval(&collected, Duration::from_secs(4), Duration::from_secs(2));
+        assert_eq!(snapshot.events, 3);
+        assert!((snapshot.events_per_sec - 1.5).abs() < 1e-9);
+        assert_eq!(snapshot.kinds.get("created"), Some(&1));
+        assert_eq!(snapshot.kinds.get("modified"), Some(&2));
+        assert!(snapshot.queue_p50_us.is_some());
+
+        let json: serde_json::Value = serde_json::to_value(&snapshot).unwrap();
+        assert_eq!(json["events"], 3);
+        assert_eq!(json["kinds"]["modified"], 2);
+        assert_eq!(json["format_version"], WatchSnapshot::FORMAT_VERSION);
+    }
+
+    #[test]
+    fn test_load_snapshots() {
+        let path = std::env::temp_dir().join(format!("watcher-snapshots-{}.jsonl", std::process::id()));
+        let mut collected = CollectedEvents::default();
+        collected.record(Ok(Event::new(EventKind::Any).add_path(PathBuf::from("/w/a.js"))).into());
+        let current = WatchSnapshot::from_interval(&collected, Duration::from_secs(1), Duration::from_secs(1));
+        let mut legacy = serde_json::to_value(&current).unwrap();
+        legacy.as_object_mut().unwrap().remove("format_version");
+
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "{}", legacy).unwrap();
+        append_snapshot(&mut file, &current).unwrap();
+        // A run killed mid-write leaves a truncated line behind
+        let truncated = serde_json::to_string(&current).unwrap();
+        writeln!(file, "{}", &truncated[..truncated.len() / 2]).unwrap();
+        writeln!(file, "{{\"format_version\":2}}").unwrap();
+        let (loaded, skipped) = load_snapshots(&path).unwrap();
+        assert_eq!(loaded, [(current.clone(), 1), (current, WatchSnapshot::FORMAT_VERSION)]);
+        assert_eq!(skipped, 2);
+
+        writeln!(file, "{{\"format_version\":{}}}", WatchSnapshot::FORMAT_VERSION + 1).unwrap();
+        assert!(load_snapshots(&path).unwrap_err().contains("line 5"));
+        assert!(!ends_mid_line(&path).unwrap());
+        write!(file, "{{\"format_").unwrap();
+        assert!(ends_mid_line(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+}